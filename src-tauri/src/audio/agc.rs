@@ -0,0 +1,229 @@
+//! Automatic gain control, for levels that change with every headset
+//! and every meeting.
+//!
+//! `Agc` brings the input's RMS towards `TARGET_DBFS`. A level detector
+//! follows the input's power; the gain moves towards the one that
+//! would put that level on target, fast when it has to come down
+//! (`ATTACK`, a loud voice starts) and slowly when it may go up
+//! (`RELEASE`), never above `MAX_AGC_GAIN_DB`. A callback whose input
+//! is below the VAD's speech threshold holds the gain: pauses and room
+//! noise aren't pumped up into hiss the VAD would take for speech.
+//!
+//! It runs in the capture callbacks on the mixed float samples, after
+//! `Settings.gain` and the filters and before the conversion to 16-bit
+//! and the resampling, and keeps its state from one callback to the next.
+//! `Settings.agc` turns it on.
+
+use std::time::Duration;
+
+/// The RMS the output is brought to.
+pub const TARGET_DBFS: f32 = -20.0;
+/// The most the AGC amplifies.
+pub const MAX_AGC_GAIN_DB: f32 = 30.0;
+/// The most it attenuates.
+pub const MIN_AGC_GAIN_DB: f32 = -20.0;
+
+/// How long the level detector averages over.
+const LEVEL_WINDOW: Duration = Duration::from_millis(50);
+/// Time constant of a gain reduction.
+const ATTACK: Duration = Duration::from_millis(20);
+/// Time constant of a gain increase.
+const RELEASE: Duration = Duration::from_secs(1);
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// One-pole smoothing coefficient for `time` at `sample_rate`.
+fn coefficient(time: Duration, sample_rate: u32) -> f32 {
+    1.0 - (-1.0 / (time.as_secs_f32() * sample_rate as f32)).exp()
+}
+
+#[derive(Debug, Clone)]
+pub struct Agc {
+    /// Smoothed power of the input.
+    power: f32,
+    gain: f32,
+    level_coef: f32,
+    attack_coef: f32,
+    release_coef: f32,
+}
+
+impl Agc {
+    /// An AGC for input at `sample_rate`, starting at `gain` (what the
+    /// previous recording ended at, so it needn't ramp up again).
+    pub fn new(sample_rate: u32, gain: f32) -> Self {
+        Self {
+            power: 0.0,
+            gain: gain.clamp(
+                db_to_amplitude(MIN_AGC_GAIN_DB),
+                db_to_amplitude(MAX_AGC_GAIN_DB),
+            ),
+            level_coef: coefficient(LEVEL_WINDOW, sample_rate),
+            attack_coef: coefficient(ATTACK, sample_rate),
+            release_coef: coefficient(RELEASE, sample_rate),
+        }
+    }
+
+    /// The current gain, as an amplitude factor.
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Apply the gain to `samples` (full scale at ±1.0), in place. Under
+    /// `gate` RMS, the VAD's threshold, the gain holds.
+    pub fn process(&mut self, samples: &mut [f32], gate: f32) {
+        // NaN and infinities would stick in the detector for good
+        for sample in samples.iter_mut() {
+            if !sample.is_finite() {
+                *sample = 0.0;
+            }
+        }
+        // Judged on the whole callback: the detector's decay after a
+        // loud stretch would otherwise raise the gain into the pause
+        let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32;
+        let gated = power.sqrt() < gate;
+
+        let target = db_to_amplitude(TARGET_DBFS);
+        let (min_gain, max_gain) = (
+            db_to_amplitude(MIN_AGC_GAIN_DB),
+            db_to_amplitude(MAX_AGC_GAIN_DB),
+        );
+        for sample in samples {
+            let input = *sample;
+            self.power += self.level_coef * (input * input - self.power);
+            let level = self.power.sqrt();
+            if !gated && level > 0.0 {
+                let wanted = (target / level).clamp(min_gain, max_gain);
+                let coef = if wanted < self.gain {
+                    self.attack_coef
+                } else {
+                    self.release_coef
+                };
+                self.gain += coef * (wanted - self.gain);
+            }
+            *sample = input * self.gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::vad::{DEFAULT_SPEECH_THRESHOLD, SILENT_BUFFER_RMS};
+
+    const RATE: u32 = 16_000;
+
+    /// `secs` of a 440 Hz sine of peak `amplitude`.
+    fn sine(amplitude: f32, secs: f32) -> Vec<f32> {
+        (0..(secs * RATE as f32) as usize)
+            .map(|i| amplitude * (i as f32 * 440.0 * std::f32::consts::TAU / RATE as f32).sin())
+            .collect()
+    }
+
+    fn rms_dbfs(samples: &[f32]) -> f32 {
+        let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+        10.0 * power.log10()
+    }
+
+    /// Feed `signal` in 10 ms callbacks, with the VAD's threshold as
+    /// low as it goes; the output.
+    fn run(agc: &mut Agc, signal: Vec<f32>) -> Vec<f32> {
+        run_gated(agc, signal, SILENT_BUFFER_RMS)
+    }
+
+    fn run_gated(agc: &mut Agc, mut signal: Vec<f32>, gate: f32) -> Vec<f32> {
+        for chunk in signal.chunks_mut(RATE as usize / 100) {
+            agc.process(chunk, gate);
+        }
+        signal
+    }
+
+    /// The last half second of `output`.
+    fn settled(output: &[f32]) -> &[f32] {
+        &output[output.len() - RATE as usize / 2..]
+    }
+
+    #[test]
+    fn quiet_then_loud_converges_on_the_target() {
+        let mut agc = Agc::new(RATE, 1.0);
+
+        // -43 dBFS: brought up by ~23 dB
+        let quiet = run(&mut agc, sine(0.01, 5.0));
+        assert!((rms_dbfs(&sine(0.01, 1.0)) + 43.0).abs() < 0.1);
+        assert!(
+            (rms_dbfs(settled(&quiet)) - TARGET_DBFS).abs() < 1.0,
+            "{}",
+            rms_dbfs(settled(&quiet))
+        );
+
+        // -9 dBFS right after: brought down within a fraction of a second
+        let loud = run(&mut agc, sine(0.5, 1.0));
+        assert!(
+            (rms_dbfs(settled(&loud)) - TARGET_DBFS).abs() < 1.0,
+            "{}",
+            rms_dbfs(settled(&loud))
+        );
+        // 0.1 / (0.5 / √2)
+        assert!((agc.gain() - 0.283).abs() < 0.02);
+    }
+
+    #[test]
+    fn silence_holds_the_gain() {
+        let mut agc = Agc::new(RATE, 1.0);
+        run(&mut agc, sine(0.5, 1.0));
+        let gain = agc.gain();
+
+        // Room noise well under the floor, for longer than the release
+        let noise = run(&mut agc, sine(0.002, 3.0));
+        assert!((agc.gain() - gain).abs() < 1e-3);
+        assert!(rms_dbfs(&noise) < -60.0);
+        run(&mut agc, vec![0.0; RATE as usize]);
+        assert!((agc.gain() - gain).abs() < 1e-3);
+    }
+
+    #[test]
+    fn noise_under_the_vad_threshold_holds_the_gain() {
+        let mut agc = Agc::new(RATE, 1.0);
+        // 0.007 RMS: over the silent-buffer floor, under the threshold
+        let noise = run_gated(&mut agc, sine(0.01, 5.0), DEFAULT_SPEECH_THRESHOLD);
+        assert_eq!(agc.gain(), 1.0);
+        assert!(rms_dbfs(&noise) < -40.0);
+
+        // Speech over it is still brought to the target
+        let speech = run_gated(&mut agc, sine(0.05, 5.0), DEFAULT_SPEECH_THRESHOLD);
+        assert!((rms_dbfs(settled(&speech)) - TARGET_DBFS).abs() < 1.0);
+    }
+
+    #[test]
+    fn gain_stays_within_its_limits() {
+        // Just above the floor: the target is out of reach
+        let mut agc = Agc::new(RATE, 1.0);
+        run(&mut agc, sine(0.0044, 10.0));
+        assert!(agc.gain() <= db_to_amplitude(MAX_AGC_GAIN_DB));
+        assert!(agc.gain() > db_to_amplitude(MAX_AGC_GAIN_DB - 1.0));
+
+        // Full scale, constantly
+        let mut agc = Agc::new(RATE, 1.0);
+        run(&mut agc, vec![1.0; RATE as usize]);
+        assert!((agc.gain() - 0.1).abs() < 0.01);
+
+        // A starting gain out of range, from an older setting
+        assert_eq!(
+            Agc::new(RATE, 1000.0).gain(),
+            db_to_amplitude(MAX_AGC_GAIN_DB)
+        );
+    }
+
+    #[test]
+    fn nan_doesnt_stick() {
+        let mut agc = Agc::new(RATE, 1.0);
+        let mut samples = vec![f32::NAN, 0.1, f32::INFINITY];
+        agc.process(&mut samples, SILENT_BUFFER_RMS);
+        assert_eq!(samples[0], 0.0);
+        assert!(samples[1].is_finite());
+        assert!(agc.gain().is_finite());
+        let out = run(&mut agc, sine(0.1, 1.0));
+        assert!(out.iter().all(|s| s.is_finite()));
+    }
+}
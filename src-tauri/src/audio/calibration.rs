@@ -0,0 +1,174 @@
+//! Noise floor calibration: a VAD threshold fitted to the microphone.
+//!
+//! `DEFAULT_SPEECH_THRESHOLD` suits a quiet laptop mic; a hissy one
+//! counts its noise as speech, and a very clean one misses soft speech.
+//! `calibrate_noise_floor` records `CALIBRATION_SECS` of the room with
+//! the user silent, and `calibrate` measures it in `FRAME_MS` frames:
+//! the floor is their `FLOOR_PERCENTILE`, and the threshold
+//! `NOISE_MARGIN` above it. A frame well over the rest is someone
+//! speaking (or a door), and a floor near speech level leaves no room
+//! for a threshold: both are refused rather than saved.
+
+use serde::Serialize;
+use thiserror::Error;
+
+use super::vad::rms;
+
+/// Seconds recorded for a calibration.
+pub const CALIBRATION_SECS: u64 = 3;
+
+/// The threshold over the floor: about +9.5 dB.
+pub const NOISE_MARGIN: f32 = 3.0;
+
+/// Bounds of a calibrated threshold, in RMS (0 to 1): about -46 dBFS,
+/// so a digitally silent mic still needs some signal, and -20 dBFS,
+/// past which normal speech doesn't register.
+pub const MIN_SPEECH_THRESHOLD: f32 = 0.005;
+pub const MAX_SPEECH_THRESHOLD: f32 = 0.1;
+
+/// Frames measured, as the VAD sees chunks.
+const FRAME_MS: u32 = 100;
+
+/// Frames under the floor: the noise's usual peaks, not a stray click.
+const FLOOR_PERCENTILE: f32 = 0.9;
+
+/// A frame this many times over the median (+12 dB) is not the room.
+const SPEECH_RATIO: f32 = 4.0;
+
+/// `calibrate_noise_floor` result: what was measured and set.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoiseCalibration {
+    /// The room's level, in RMS (0 to 1).
+    pub floor: f32,
+    /// The VAD threshold set, in RMS.
+    pub threshold: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CalibrationError {
+    #[error("Too little audio to calibrate")]
+    TooShort,
+    #[error(
+        "Something was heard during calibration (peaks at {peak_db:.0} dBFS); \
+         stay silent and try again"
+    )]
+    SpeechDetected { peak_db: f32 },
+    #[error(
+        "The room is too loud to calibrate (noise at {floor_db:.0} dBFS); \
+         the default threshold was kept"
+    )]
+    TooNoisy { floor_db: f32 },
+}
+
+fn dbfs(rms: f32) -> f32 {
+    20.0 * rms.max(1e-6).log10()
+}
+
+/// The value `fraction` of the way through `sorted`.
+fn percentile(sorted: &[f32], fraction: f32) -> f32 {
+    let index = ((sorted.len() - 1) as f32 * fraction).round() as usize;
+    sorted[index]
+}
+
+/// Measure `samples` of a silent room, at `sample_rate` Hz.
+pub fn calibrate(samples: &[i16], sample_rate: u32) -> Result<NoiseCalibration, CalibrationError> {
+    let frame = (sample_rate * FRAME_MS / 1000).max(1) as usize;
+    let mut levels: Vec<f32> = samples.chunks_exact(frame).map(rms).collect();
+    // A second at least, for a distribution
+    if levels.len() < (1000 / FRAME_MS) as usize {
+        return Err(CalibrationError::TooShort);
+    }
+    levels.sort_by(f32::total_cmp);
+
+    let median = percentile(&levels, 0.5);
+    let peak = levels[levels.len() - 1];
+    if peak > (median * SPEECH_RATIO).max(MIN_SPEECH_THRESHOLD) {
+        return Err(CalibrationError::SpeechDetected {
+            peak_db: dbfs(peak),
+        });
+    }
+    let floor = percentile(&levels, FLOOR_PERCENTILE);
+    let threshold = (floor * NOISE_MARGIN).max(MIN_SPEECH_THRESHOLD);
+    if threshold > MAX_SPEECH_THRESHOLD {
+        return Err(CalibrationError::TooNoisy {
+            floor_db: dbfs(floor),
+        });
+    }
+    Ok(NoiseCalibration { floor, threshold })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    /// `secs` of noise at `level` (RMS), deterministic.
+    fn noise(level: f32, secs: f32) -> Vec<i16> {
+        let mut seed = 0x2545_f491_u32;
+        (0..(secs * RATE as f32) as usize)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                // Uniform in ±1: an RMS of 1/√3
+                let uniform = seed as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                (uniform * level * 3f32.sqrt() * i16::MAX as f32) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn threshold_is_a_margin_over_the_floor() {
+        let calibration = calibrate(&noise(0.004, 3.0), RATE).unwrap();
+        assert!((calibration.floor - 0.004).abs() < 0.0005);
+        assert_eq!(calibration.threshold, calibration.floor * NOISE_MARGIN);
+    }
+
+    #[test]
+    fn a_silent_mic_gets_the_minimum() {
+        let calibration = calibrate(&vec![0; 3 * RATE as usize], RATE).unwrap();
+        assert_eq!(calibration.floor, 0.0);
+        assert_eq!(calibration.threshold, MIN_SPEECH_THRESHOLD);
+    }
+
+    #[test]
+    fn speech_is_refused() {
+        let mut samples = noise(0.002, 3.0);
+        // Half a second of talking in the middle
+        let speech = noise(0.05, 0.5);
+        samples[RATE as usize..][..speech.len()].copy_from_slice(&speech);
+        match calibrate(&samples, RATE) {
+            Err(CalibrationError::SpeechDetected { peak_db }) => {
+                assert!((peak_db - dbfs(0.05)).abs() < 1.0)
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn a_loud_room_is_refused() {
+        assert!(matches!(
+            calibrate(&noise(0.05, 3.0), RATE),
+            Err(CalibrationError::TooNoisy { .. })
+        ));
+    }
+
+    #[test]
+    fn too_short_is_refused() {
+        assert_eq!(
+            calibrate(&noise(0.004, 0.5), RATE),
+            Err(CalibrationError::TooShort)
+        );
+    }
+
+    #[test]
+    fn errors_say_what_to_do() {
+        let error = CalibrationError::SpeechDetected { peak_db: -26.2 };
+        assert_eq!(
+            error.to_string(),
+            "Something was heard during calibration (peaks at -26 dBFS); stay silent and try again"
+        );
+    }
+}
@@ -1,10 +1,28 @@
 #![allow(dead_code)]
 
+use super::agc::Agc;
+use super::channels::ChannelMode;
+use super::clipping::{ClipDetector, Clipping};
+use super::devices::{self, DeviceFallback};
+use super::filters::{AudioFilters, Biquad, DcBlocker, HIGH_PASS_HZ};
+use super::gain;
+use super::level_monitor::{LevelMeter, MonitorLevel};
+use super::pauses::{PauseLog, PauseMark};
+use super::pre_roll::{self, PreRoll};
+use super::rate::{RateCorrection, RateEstimator};
+use super::silent_input::{SilentInput, SilentInputDetector, SILENT_INPUT_SECS};
+use super::source::{self, CaptureSource, Input};
+use super::spill::{self, SpillFailure, SpillWriter};
+use super::vad::DEFAULT_SPEECH_THRESHOLD;
+use crate::mic_log::MicUsageLog;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{SampleFormat, Stream};
+use cpal::{FromSample, Sample, SampleFormat, SizedSample, Stream};
 use parking_lot::Mutex;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::mpsc;
 
@@ -18,14 +36,28 @@ pub enum AudioCaptureError {
     NotAvailable,
     #[error("No input device found")]
     NoInputDevice,
-    #[error("Unsupported sample format")]
-    UnsupportedFormat,
+    #[error("Unsupported sample format: {0}")]
+    UnsupportedFormat(String),
+    #[error("System audio can't be recorded: {0}")]
+    NotSupported(String),
 }
 
-/// Audio buffer for storing captured samples
+/// Audio buffer for storing captured samples. With a spill threshold
+/// the older ones go to a file on disk (see `audio::spill`).
 pub struct AudioBuffer {
     samples: Vec<i16>,
     sample_rate: u32,
+    /// The directory, and how many samples `samples` may hold before
+    /// they move there.
+    spill_to: Option<(PathBuf, usize)>,
+    /// Takes the capture's samples from before those in `samples`. Set
+    /// up ahead, outside the audio callbacks; a new one per capture.
+    writer: Option<SpillWriter>,
+    /// Set while a stream's rate may still be corrected: the samples
+    /// stay in RAM, where `resample` reaches them.
+    spill_held: bool,
+    /// Why spilling or reading back failed, until `take_spill_failure`.
+    spill_failure: Option<String>,
 }
 
 impl AudioBuffer {
@@ -33,23 +65,128 @@ impl AudioBuffer {
         Self {
             samples: Vec::with_capacity(sample_rate as usize * 30), // 30 seconds buffer
             sample_rate,
+            spill_to: None,
+            writer: None,
+            spill_held: false,
+            spill_failure: None,
+        }
+    }
+
+    /// Spill to a file in `dir` past `threshold` samples; `None` keeps
+    /// everything in RAM. What is already spilled stays there.
+    pub fn set_spill(&mut self, spill: Option<(PathBuf, usize)>) {
+        self.spill_to = spill;
+        self.ready_writer();
+    }
+
+    /// Start a writer for the next spill, if spilling and there is none.
+    fn ready_writer(&mut self) {
+        let Some((dir, _)) = &self.spill_to else {
+            return;
+        };
+        if self.writer.is_some() {
+            return;
+        }
+        match SpillWriter::spawn(dir.clone()) {
+            Ok(writer) => self.writer = Some(writer),
+            Err(e) => {
+                let message = format!(
+                    "Couldn't start writing the recording to disk: {}. It stays in memory.",
+                    e
+                );
+                tracing::warn!("{}", message);
+                self.spill_failure = Some(message);
+            }
         }
     }
 
     pub fn push(&mut self, samples: &[i16]) {
         self.samples.extend_from_slice(samples);
+        let over = match &self.spill_to {
+            Some((_, threshold)) => self.samples.len() >= *threshold,
+            None => false,
+        };
+        if over && !self.spill_held {
+            self.spill();
+        }
     }
 
+    /// Keep the samples in RAM (`true`) until released, even past the
+    /// threshold.
+    pub fn hold_spill(&mut self, held: bool) {
+        self.spill_held = held;
+    }
+
+    /// Resample what is in RAM by `ratio`, after a rate correction.
+    /// Spilled samples are left as they are: `hold_spill` keeps them in
+    /// RAM until the rate is known.
+    pub fn resample(&mut self, ratio: f64) {
+        self.samples = resample(&self.samples, ratio);
+    }
+
+    /// Hand `samples` to the writer. Runs on the audio thread, once per
+    /// threshold's worth; never waits. Kept when the writer is busy
+    /// (tried again with the next push) or failed (the rest of the
+    /// capture stays in RAM).
+    fn spill(&mut self) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+        if writer.failed() {
+            return;
+        }
+        let block = std::mem::take(&mut self.samples);
+        if let Err(block) = writer.send(block) {
+            self.samples = block;
+        }
+    }
+
+    /// Drop every sample, a spill file included.
     pub fn clear(&mut self) {
         self.samples.clear();
+        self.writer = None;
+        self.ready_writer();
     }
 
+    /// The samples in RAM: the latest ones, once some were spilled.
     pub fn get_samples(&self) -> &[i16] {
         &self.samples
     }
 
+    /// All of the capture, the spilled samples read back first. If they
+    /// can't be, only the ones in RAM, and `take_spill_failure` says so.
+    /// Waits for the writer to catch up, so not for the audio thread.
     pub fn take_samples(&mut self) -> Vec<i16> {
-        std::mem::take(&mut self.samples)
+        let latest = std::mem::take(&mut self.samples);
+        let Some(writer) = self.writer.take() else {
+            return latest;
+        };
+        self.ready_writer();
+        if let Some(message) = writer.take_failure() {
+            self.spill_failure = Some(message);
+        }
+        let (mut samples, failure) = writer.finish();
+        if let Some(message) = failure {
+            tracing::error!("{}", message);
+            self.spill_failure = Some(message);
+        }
+        samples.extend_from_slice(&latest);
+        samples
+    }
+
+    /// Why spilling failed, once.
+    pub fn take_spill_failure(&mut self) -> Option<String> {
+        let failure = self.writer.as_ref().and_then(SpillWriter::take_failure);
+        failure.or_else(|| self.spill_failure.take())
+    }
+
+    /// Samples captured, spilled ones included.
+    pub fn len(&self) -> usize {
+        self.writer.as_ref().map_or(0, SpillWriter::len) + self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     pub fn sample_rate(&self) -> u32 {
@@ -57,7 +194,97 @@ impl AudioBuffer {
     }
 
     pub fn duration_secs(&self) -> f32 {
-        self.samples.len() as f32 / self.sample_rate as f32
+        self.len() as f32 / self.sample_rate as f32
+    }
+}
+
+/// How long no callback may run before `stop` takes the samples.
+const STOP_QUIET: Duration = Duration::from_millis(20);
+/// `stop` gives up waiting for the callbacks after this.
+const STOP_TIMEOUT: Duration = Duration::from_millis(250);
+const STOP_POLL: Duration = Duration::from_millis(2);
+
+/// Counts the stream callbacks, so `stop` can wait for the ones still
+/// running. Some backends keep calling (or finish a call) after the
+/// stream is dropped; a callback that checked `is_capturing` just
+/// before `stop` would otherwise push its samples after they were
+/// taken, and they would open the next recording.
+///
+/// The count goes up on entering and on leaving a callback: odd means
+/// one is running.
+#[derive(Clone, Default)]
+pub struct CallbackActivity(Arc<AtomicU64>);
+
+impl CallbackActivity {
+    /// Mark a callback as running until the guard drops.
+    pub fn enter(&self) -> ActiveCallback<'_> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        ActiveCallback(&self.0)
+    }
+
+    /// Block until no callback has run for `quiet`, or `timeout` passed.
+    /// `false` on timeout.
+    pub fn wait_quiet(&self, quiet: Duration, timeout: Duration) -> bool {
+        let start = Instant::now();
+        let mut last = self.0.load(Ordering::SeqCst);
+        let mut stable_since = start;
+        loop {
+            let now = Instant::now();
+            let current = self.0.load(Ordering::SeqCst);
+            if current != last {
+                last = current;
+                stable_since = now;
+            } else if current.is_multiple_of(2) && now - stable_since >= quiet {
+                return true;
+            }
+            if now - start >= timeout {
+                return false;
+            }
+            std::thread::sleep(STOP_POLL);
+        }
+    }
+
+    /// Block until the callback running now, if any, has returned, or
+    /// `timeout` passed. `false` on timeout. For a stream that stays
+    /// open, where `wait_quiet` would never see quiet: callbacks that
+    /// start later see `is_capturing` already false.
+    pub fn wait_running(&self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        let running = self.0.load(Ordering::SeqCst);
+        if running.is_multiple_of(2) {
+            return true;
+        }
+        while self.0.load(Ordering::SeqCst) == running {
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            std::thread::sleep(STOP_POLL);
+        }
+        true
+    }
+}
+
+pub struct ActiveCallback<'a>(&'a AtomicU64);
+
+impl Drop for ActiveCallback<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// What `stop` waits for (see `CallbackActivity`).
+#[derive(Debug, Clone, Copy)]
+struct StopSettle {
+    quiet: Duration,
+    timeout: Duration,
+}
+
+impl Default for StopSettle {
+    fn default() -> Self {
+        Self {
+            quiet: STOP_QUIET,
+            timeout: STOP_TIMEOUT,
+        }
     }
 }
 
@@ -68,24 +295,599 @@ pub struct AudioChunk {
     pub sample_rate: u32,
 }
 
+/// A stream error, as sent to `create_error_channel`'s receiver.
+#[derive(Debug, Clone)]
+pub struct StreamFailure {
+    pub message: String,
+    /// The device went away (unplugged, a headset out of battery): the
+    /// stream delivers nothing more. Other errors can be transient.
+    pub device_lost: bool,
+}
+
+/// Told about a corrected input rate, with the updated per-device
+/// overrides to persist.
+pub type RateReporter = Arc<dyn Fn(RateCorrection, HashMap<String, u32>) + Send + Sync>;
+
+/// Told about stream errors (device unplugged, driver failure), from
+/// the audio thread.
+pub type StreamErrorReporter = Arc<dyn Fn(String) + Send + Sync>;
+
+/// Told when the preferred device is missing and the default is used.
+pub type DeviceFallbackReporter = Arc<dyn Fn(DeviceFallback) + Send + Sync>;
+
+/// Told (from the audio thread) that the input clips.
+pub type ClippingReporter = Arc<dyn Fn(Clipping) + Send + Sync>;
+
+/// Told (from the audio thread) that the input has been digital silence
+/// for a while. `muted` is left `None`: asking is too slow for there.
+pub type SilentInputReporter = Arc<dyn Fn(SilentInput) + Send + Sync>;
+
+/// Told (from the audio thread) the input's level, while the level
+/// monitor is on.
+pub type MonitorReporter = Arc<dyn Fn(MonitorLevel) + Send + Sync>;
+
+/// Told that a capture couldn't spill to disk, or be read back.
+pub type SpillReporter = Arc<dyn Fn(SpillFailure) + Send + Sync>;
+
+/// Per-stream rate check: owns the resample ratio and corrects it when
+/// the device turns out to deliver another rate than it reports. Holds
+/// the buffer's spilling until then, so the correction reaches every
+/// sample that went through the wrong ratio.
+struct RateCheck {
+    estimator: RateEstimator,
+    device: String,
+    hardware_rate: u32,
+    target_rate: u32,
+    ratio: f64,
+    buffer: Arc<Mutex<AudioBuffer>>,
+    /// Whether this stream still holds the buffer's spilling.
+    spill_held: bool,
+    overrides: Arc<Mutex<HashMap<String, u32>>>,
+    reporter: Option<RateReporter>,
+}
+
+impl RateCheck {
+    /// The resample ratio for a callback of `frames` frames.
+    fn ratio(&mut self, frames: usize) -> f64 {
+        if let Some(correction) = self.estimator.observe(frames, Instant::now()) {
+            tracing::warn!(
+                "Input device \"{}\" delivers {} Hz, not {} Hz; correcting",
+                self.device,
+                correction.measured,
+                correction.reported
+            );
+            let ratio = self.target_rate as f64 / correction.measured as f64;
+            // What's buffered so far went through the wrong ratio
+            self.buffer.lock().resample(ratio / self.ratio);
+            self.ratio = ratio;
+            let overrides = {
+                let mut overrides = self.overrides.lock();
+                if correction.measured == self.hardware_rate {
+                    overrides.remove(&self.device);
+                } else {
+                    overrides.insert(self.device.clone(), correction.measured);
+                }
+                overrides.clone()
+            };
+            if let Some(reporter) = &self.reporter {
+                reporter(correction, overrides);
+            }
+        }
+        if self.spill_held && self.estimator.is_done() {
+            self.buffer.lock().hold_spill(false);
+            self.spill_held = false;
+        }
+        self.ratio
+    }
+}
+
+/// Where a stream's callbacks put what they get: mono, at the target
+/// rate, into the buffer and the chunk channel.
+struct StreamSink {
+    channels: usize,
+    buffer: Arc<Mutex<AudioBuffer>>,
+    is_capturing: Arc<AtomicBool>,
+    /// Read on every callback: a paused recording's samples are dropped.
+    paused: Arc<AtomicBool>,
+    callbacks: CallbackActivity,
+    /// Read on every callback: a stream held open for the pre-roll
+    /// outlives the channel it was opened with.
+    chunk_sender: Arc<Mutex<Option<mpsc::UnboundedSender<AudioChunk>>>>,
+    target_rate: u32,
+    rate: RateCheck,
+    /// `f32` bits of the gain in dB, read on every callback so a change
+    /// applies mid-recording.
+    gain_db: Arc<AtomicU32>,
+    clips: ClipDetector,
+    clipping_reporter: Option<ClippingReporter>,
+    /// The device's name, for `SilentInput`.
+    device: String,
+    silence: SilentInputDetector,
+    silent_input_reporter: Option<SilentInputReporter>,
+    /// Always on; starts over with each recording.
+    dc_blocker: DcBlocker,
+    /// Whether the last callback was a recording's.
+    was_capturing: bool,
+    /// Which filters are on, read on every callback.
+    filters: Arc<Mutex<AudioFilters>>,
+    /// Which channels are recorded, read on every callback.
+    channel_mode: Arc<Mutex<ChannelMode>>,
+    /// Set while the high-pass is on; a new one (no stale state) each
+    /// time it is turned on.
+    high_pass: Option<Biquad>,
+    source_rate: u32,
+    agc_enabled: Arc<AtomicBool>,
+    agc: Agc,
+    /// `f32` bits of the AGC's gain, for the next stream to start from.
+    agc_gain: Arc<AtomicU32>,
+    agc_gate: Arc<AtomicU32>,
+    /// Where the samples go between recordings.
+    pre_roll: Arc<Mutex<PreRoll>>,
+    spill_reporter: Option<SpillReporter>,
+    /// Read on every callback: the level monitor comes and goes while
+    /// the stream stays open.
+    monitor: Arc<Mutex<Option<MonitorReporter>>>,
+    meter: LevelMeter,
+}
+
+impl StreamSink {
+    fn push<T>(&mut self, data: &[T])
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        // Only a recording's callbacks are waited for by `stop`
+        let active = self.callbacks.enter();
+        let capturing = self.is_capturing.load(Ordering::SeqCst);
+        let _active = capturing.then_some(active);
+        // Every callback counts, dropped or not: the rate is measured as
+        // frames against wall time, and a pause doesn't stop the device
+        let resample_ratio = self.rate.ratio(data.len() / self.channels);
+        let monitor = self.monitor.lock().clone();
+        if monitor.is_none() {
+            self.meter.reset();
+            if !capturing && self.pre_roll.lock().capacity() == 0 {
+                return;
+            }
+        }
+        if capturing && self.paused.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let gain_db = f32::from_bits(self.gain_db.load(Ordering::Relaxed));
+        let channel_mode = *self.channel_mode.lock();
+        let factor = gain::factor(gain_db);
+        let mut mixed = mix(data, self.channels, channel_mode, factor);
+        if capturing && !self.was_capturing {
+            self.dc_blocker.reset();
+        }
+        self.was_capturing = capturing;
+        self.dc_blocker.process(&mut mixed);
+        if !capturing {
+            // Counted per recording
+            self.silence.reset();
+        } else if self.silence.observe(&mixed, factor, self.source_rate) {
+            tracing::warn!(
+                "No signal from \"{}\" for {} s; muted?",
+                self.device,
+                SILENT_INPUT_SECS
+            );
+            if let Some(report) = &self.silent_input_reporter {
+                report(SilentInput {
+                    device: self.device.clone(),
+                    muted: None,
+                });
+            }
+        }
+        let filters = *self.filters.lock();
+        match (&mut self.high_pass, filters.high_pass) {
+            (Some(filter), true) => filter.process(&mut mixed),
+            (None, true) => {
+                let mut filter = Biquad::high_pass(HIGH_PASS_HZ, self.source_rate);
+                filter.process(&mut mixed);
+                self.high_pass = Some(filter);
+            }
+            (_, false) => self.high_pass = None,
+        }
+        if self.agc_enabled.load(Ordering::Relaxed) {
+            let gate = f32::from_bits(self.agc_gate.load(Ordering::Relaxed));
+            self.agc.process(&mut mixed, gate);
+            self.agc_gain
+                .store(self.agc.gain().to_bits(), Ordering::Relaxed);
+        }
+        let mono_samples = quantize(&mixed);
+        if let Some(report) = &monitor {
+            if let Some(rms) = self.meter.observe(&mono_samples, Instant::now()) {
+                report(MonitorLevel::new(rms));
+            }
+        }
+        if capturing {
+            if let Some(percent) = self.clips.observe(&mono_samples, Instant::now()) {
+                if let Some(report) = &self.clipping_reporter {
+                    report(Clipping { percent, gain_db });
+                }
+            }
+        }
+
+        // Simple resampling (linear interpolation)
+        let resampled = resample(&mono_samples, resample_ratio);
+
+        if !capturing {
+            let mut pre_roll = self.pre_roll.lock();
+            // Unless `start` took the pre-roll meanwhile: then these
+            // follow it in the recording
+            if !self.is_capturing.load(Ordering::SeqCst) {
+                pre_roll.push(&resampled);
+                return;
+            }
+        }
+
+        let spill_failure = {
+            let mut buffer = self.buffer.lock();
+            buffer.push(&resampled);
+            buffer.take_spill_failure()
+        };
+        if let (Some(message), Some(report)) = (spill_failure, &self.spill_reporter) {
+            report(SpillFailure { message });
+        }
+
+        // Send chunk for real-time processing
+        if let Some(sender) = &*self.chunk_sender.lock() {
+            let _ = sender.send(AudioChunk {
+                samples: resampled,
+                sample_rate: self.target_rate,
+            });
+        }
+    }
+}
+
+/// An input stream of `T` samples from `device`, feeding `sink`.
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut sink: StreamSink,
+    err_fn: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<Stream, AudioCaptureError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| sink.push(data),
+            err_fn,
+            None,
+        )
+        .map_err(|e| AudioCaptureError::StreamError(e.to_string()))
+}
+
+/// Interleaved `data` of `channels` channels as float mono, times the
+/// amplitude `factor`. Samples convert as cpal does (unsigned formats
+/// are offset binary, 128 is silence for `u8`); a frame's channels are
+/// averaged, or the one `mode` picks is taken. A channel the device
+/// doesn't have (the mode was set for another one) falls back to the
+/// average.
+fn mix<T>(data: &[T], channels: usize, mode: ChannelMode, factor: f32) -> Vec<f32>
+where
+    T: Sample,
+    f32: FromSample<T>,
+{
+    let picked = mode.index().map(usize::from).filter(|&i| i < channels);
+    data.chunks(channels)
+        .map(|frame| match picked.and_then(|i| frame.get(i)) {
+            Some(&sample) => sample.to_sample::<f32>() * factor,
+            None => {
+                let sum: f32 = frame.iter().map(|&sample| sample.to_sample::<f32>()).sum();
+                sum / frame.len() as f32 * factor
+            }
+        })
+        .collect()
+}
+
+/// `samples` as 16-bit, saturating at the bounds. Clipping is counted
+/// on the result, by the `ClipDetector`.
+fn quantize(samples: &[f32]) -> Vec<i16> {
+    samples
+        .iter()
+        .map(|&sample| gain::apply(sample, 1.0).0)
+        .collect()
+}
+
+/// An open input stream; dropping it stops it.
+enum InputStream {
+    Cpal(Stream),
+    /// The default sink's monitor (see `source::Input::Monitor`).
+    #[cfg(target_os = "linux")]
+    Monitor(super::monitor::MonitorStream),
+}
+
 /// Audio capture handler using cpal
 pub struct AudioCapture {
     buffer: Arc<Mutex<AudioBuffer>>,
     is_capturing: Arc<AtomicBool>,
-    stream: Mutex<Option<Stream>>,
-    chunk_sender: Mutex<Option<mpsc::UnboundedSender<AudioChunk>>>,
+    /// Set while the recording is paused: the stream stays open, and
+    /// its samples go nowhere.
+    paused: Arc<AtomicBool>,
+    pauses: Mutex<PauseLog>,
+    callbacks: CallbackActivity,
+    stop_settle: StopSettle,
+    stream: Mutex<Option<InputStream>>,
+    /// Name of the device `stream` records from.
+    stream_device: Mutex<Option<String>>,
+    /// What `stream` records.
+    stream_source: Mutex<CaptureSource>,
+    chunk_sender: Arc<Mutex<Option<mpsc::UnboundedSender<AudioChunk>>>>,
+    /// Read at each error, like `chunk_sender`.
+    error_sender: Arc<Mutex<Option<mpsc::UnboundedSender<StreamFailure>>>>,
+    /// The open stream's device went away: a stream held open for the
+    /// pre-roll is reopened at `start` instead of reused.
+    stream_failed: Arc<AtomicBool>,
     target_sample_rate: u32,
+    /// Records every stream open/close. See `crate::mic_log`.
+    usage_log: MicUsageLog,
+    /// Measured input rates of devices that misreport theirs, by name.
+    rate_overrides: Arc<Mutex<HashMap<String, u32>>>,
+    rate_reporter: Mutex<Option<RateReporter>>,
+    error_reporter: Mutex<Option<StreamErrorReporter>>,
+    /// Id of the device to record from (`Settings.preferred_device`);
+    /// `None` for the system default.
+    preferred_device: Mutex<Option<String>>,
+    /// Name of the application system audio is recorded from
+    /// (`Settings.capture_application`); `None` for all of it.
+    capture_application: Mutex<Option<String>>,
+    fallback_reporter: Mutex<Option<DeviceFallbackReporter>>,
+    /// `f32` bits of `Settings.gain`, shared with the running stream.
+    gain_db: Arc<AtomicU32>,
+    clipping_reporter: Mutex<Option<ClippingReporter>>,
+    silent_input_reporter: Mutex<Option<SilentInputReporter>>,
+    /// `Settings.audio_filters`, shared with the running stream.
+    filters: Arc<Mutex<AudioFilters>>,
+    /// `Settings.channel_mode`, shared with the running stream.
+    channel_mode: Arc<Mutex<ChannelMode>>,
+    /// `Settings.agc`, shared with the running stream.
+    agc_enabled: Arc<AtomicBool>,
+    /// `f32` bits of the AGC gain the last stream ended at.
+    agc_gain: Arc<AtomicU32>,
+    /// `f32` bits of the VAD's speech threshold, under which the AGC
+    /// holds its gain; shared with the running stream.
+    agc_gate: Arc<AtomicU32>,
+    /// The audio from before `start`; its capacity is
+    /// `Settings.pre_roll_ms`, and while it isn't 0 the stream stays
+    /// open between recordings.
+    pre_roll: Arc<Mutex<PreRoll>>,
+    spill_reporter: Mutex<Option<SpillReporter>>,
+    /// Set while the level monitor is on (see `audio::level_monitor`),
+    /// shared with the running stream. Like the pre-roll, it holds the
+    /// microphone's stream open between recordings.
+    monitor: Arc<Mutex<Option<MonitorReporter>>>,
 }
 
+/// Usage log trigger for the time the stream is open for the pre-roll.
+const PRE_ROLL_TRIGGER: &str = "pre-roll";
+
+/// Usage log trigger for the time the stream is open for the level
+/// monitor alone.
+const MONITOR_TRIGGER: &str = "level-monitor";
+
 impl AudioCapture {
     pub fn new() -> Self {
         Self {
             buffer: Arc::new(Mutex::new(AudioBuffer::new(16000))), // 16kHz for Whisper
             is_capturing: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+            pauses: Mutex::new(PauseLog::default()),
+            callbacks: CallbackActivity::default(),
+            stop_settle: StopSettle::default(),
             stream: Mutex::new(None),
-            chunk_sender: Mutex::new(None),
+            stream_device: Mutex::new(None),
+            stream_source: Mutex::new(CaptureSource::default()),
+            chunk_sender: Arc::new(Mutex::new(None)),
+            error_sender: Arc::new(Mutex::new(None)),
+            stream_failed: Arc::new(AtomicBool::new(false)),
             target_sample_rate: 16000, // Whisper expects 16kHz
+            usage_log: MicUsageLog::default(),
+            rate_overrides: Arc::new(Mutex::new(HashMap::new())),
+            rate_reporter: Mutex::new(None),
+            error_reporter: Mutex::new(None),
+            preferred_device: Mutex::new(None),
+            capture_application: Mutex::new(None),
+            fallback_reporter: Mutex::new(None),
+            gain_db: Arc::new(AtomicU32::new(0f32.to_bits())),
+            clipping_reporter: Mutex::new(None),
+            silent_input_reporter: Mutex::new(None),
+            filters: Arc::new(Mutex::new(AudioFilters::default())),
+            channel_mode: Arc::new(Mutex::new(ChannelMode::default())),
+            agc_enabled: Arc::new(AtomicBool::new(false)),
+            agc_gain: Arc::new(AtomicU32::new(1f32.to_bits())),
+            agc_gate: Arc::new(AtomicU32::new(DEFAULT_SPEECH_THRESHOLD.to_bits())),
+            pre_roll: Arc::new(Mutex::new(PreRoll::default())),
+            spill_reporter: Mutex::new(None),
+            monitor: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The application the next system audio `start` records alone, by
+    /// `CaptureApplication::name`; `None` for all of the system audio.
+    pub fn set_capture_application(&self, name: Option<String>) {
+        *self.capture_application.lock() = name;
+    }
+
+    /// The device the next `start` opens, by `AudioDevice::id`. A
+    /// stream held open between recordings (pre-roll, level monitor)
+    /// moves to it.
+    pub fn set_preferred_device(&self, id: Option<String>) {
+        *self.preferred_device.lock() = id;
+        let mut stream = self.stream.lock();
+        if self.is_capturing() || stream.is_none() {
+            return;
         }
+        stream.take();
+        self.pre_roll.lock().take();
+        self.usage_log.closed(0);
+        match self.open_stream(CaptureSource::Microphone) {
+            Ok(reopened) => {
+                *stream = Some(reopened);
+                self.usage_log.opened(self.held_trigger());
+            }
+            Err(e) => tracing::warn!("Input stream held open between recordings closed: {}", e),
+        }
+    }
+
+    /// Keep `ms` of audio from before each recording, holding the input
+    /// stream open between recordings (see `audio::pre_roll`); 0 closes
+    /// it. During a recording the stream is kept or closed at `stop`.
+    pub fn set_pre_roll(&self, ms: u32) -> Result<(), AudioCaptureError> {
+        self.pre_roll
+            .lock()
+            .set_capacity(pre_roll::capacity_for(ms, self.target_sample_rate));
+        let mut stream = self.stream.lock();
+        if self.is_capturing() {
+            return Ok(());
+        }
+        match (ms > 0, stream.is_some()) {
+            (true, false) => {
+                *stream = Some(self.open_stream(CaptureSource::Microphone)?);
+                self.usage_log.opened(PRE_ROLL_TRIGGER);
+                tracing::info!("Input stream held open for {} ms of pre-roll", ms);
+            }
+            // The level monitor still needs it
+            (false, true) if !self.is_monitoring() => {
+                stream.take();
+                self.pre_roll.lock().take();
+                self.usage_log.closed(0);
+                tracing::info!("Pre-roll off, input stream closed");
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn pre_roll_ms(&self) -> u32 {
+        let capacity = self.pre_roll.lock().capacity() as u64;
+        (capacity * 1000 / u64::from(self.target_sample_rate)) as u32
+    }
+
+    /// Report the input's level every `MONITOR_INTERVAL` until
+    /// `stop_monitor`, without recording. The stream already open (the
+    /// pre-roll's, a recording's) is used, else the microphone's is
+    /// opened; a recording started meanwhile takes it over, and the
+    /// levels carry on from it.
+    pub fn start_monitor(
+        &self,
+        reporter: impl Fn(MonitorLevel) + Send + Sync + 'static,
+    ) -> Result<(), AudioCaptureError> {
+        let mut stream = self.stream.lock();
+        if stream.is_none() && !self.is_capturing() {
+            *stream = Some(self.open_stream(CaptureSource::Microphone)?);
+            self.usage_log.opened(MONITOR_TRIGGER);
+            tracing::info!("Input stream opened for the level monitor");
+        }
+        *self.monitor.lock() = Some(Arc::new(reporter));
+        Ok(())
+    }
+
+    /// Stop reporting the level, closing the stream if only the monitor
+    /// held it open.
+    pub fn stop_monitor(&self) {
+        if self.monitor.lock().take().is_none() {
+            return;
+        }
+        let mut stream = self.stream.lock();
+        if self.is_capturing() || self.pre_roll.lock().capacity() > 0 {
+            return;
+        }
+        if stream.take().is_some() {
+            self.usage_log.closed(0);
+            tracing::info!("Level monitor off, input stream closed");
+        }
+    }
+
+    pub fn is_monitoring(&self) -> bool {
+        self.monitor.lock().is_some()
+    }
+
+    /// Usage log trigger for a stream held open between recordings.
+    fn held_trigger(&self) -> &'static str {
+        if self.pre_roll.lock().capacity() > 0 {
+            PRE_ROLL_TRIGGER
+        } else {
+            MONITOR_TRIGGER
+        }
+    }
+
+    pub fn set_fallback_reporter(&self, reporter: impl Fn(DeviceFallback) + Send + Sync + 'static) {
+        *self.fallback_reporter.lock() = Some(Arc::new(reporter));
+    }
+
+    /// Input gain in dB (`Settings.gain`). Takes effect on the next
+    /// callback, a running recording included.
+    pub fn set_gain(&self, db: f32) {
+        self.gain_db.store(db.to_bits(), Ordering::Relaxed);
+    }
+
+    /// The input filters (`Settings.audio_filters`, see
+    /// `audio::filters`). Take effect on the next callback.
+    pub fn set_filters(&self, filters: AudioFilters) {
+        *self.filters.lock() = filters;
+    }
+
+    /// The channels recorded (`Settings.channel_mode`, see
+    /// `audio::channels`). Takes effect on the next callback.
+    pub fn set_channel_mode(&self, mode: ChannelMode) {
+        *self.channel_mode.lock() = mode;
+    }
+
+    /// Automatic gain control (`Settings.agc`, see `audio::agc`). Takes
+    /// effect on the next callback.
+    pub fn set_agc(&self, enabled: bool) {
+        self.agc_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// The VAD's speech threshold (RMS) now, for the AGC to hold its
+    /// gain under.
+    pub fn set_agc_gate(&self, threshold: f32) {
+        self.agc_gate.store(threshold.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_clipping_reporter(&self, reporter: impl Fn(Clipping) + Send + Sync + 'static) {
+        *self.clipping_reporter.lock() = Some(Arc::new(reporter));
+    }
+
+    pub fn set_silent_input_reporter(
+        &self,
+        reporter: impl Fn(SilentInput) + Send + Sync + 'static,
+    ) {
+        *self.silent_input_reporter.lock() = Some(Arc::new(reporter));
+    }
+
+    /// Past `mb` of audio in memory, move a capture to a temp file in
+    /// `dir` (`Settings.capture_spill_mb`, see `audio::spill`); 0 keeps
+    /// it all in RAM. Takes effect on the next callback.
+    pub fn set_spill(&self, dir: PathBuf, mb: u32) {
+        let spill = (mb > 0).then(|| (dir, spill::threshold_samples(mb)));
+        self.buffer.lock().set_spill(spill);
+    }
+
+    pub fn set_spill_reporter(&self, reporter: impl Fn(SpillFailure) + Send + Sync + 'static) {
+        *self.spill_reporter.lock() = Some(Arc::new(reporter));
+    }
+
+    /// Rates measured in earlier sessions (`Settings.sample_rate_overrides`).
+    pub fn set_rate_overrides(&self, overrides: HashMap<String, u32>) {
+        *self.rate_overrides.lock() = overrides;
+    }
+
+    pub fn set_error_reporter(&self, reporter: impl Fn(String) + Send + Sync + 'static) {
+        *self.error_reporter.lock() = Some(Arc::new(reporter));
+    }
+
+    /// Called (from the audio thread) when a capture's rate is corrected.
+    pub fn set_rate_reporter(
+        &self,
+        reporter: impl Fn(RateCorrection, HashMap<String, u32>) + Send + Sync + 'static,
+    ) {
+        *self.rate_reporter.lock() = Some(Arc::new(reporter));
     }
 
     /// Create a channel to receive audio chunks
@@ -95,154 +897,428 @@ impl AudioCapture {
         rx
     }
 
-    /// Start capturing audio from the default input device
-    pub fn start(&self) -> Result<(), AudioCaptureError> {
+    /// Create a channel to receive the stream's errors. They still go to
+    /// the error reporter as well.
+    pub fn create_error_channel(&self) -> mpsc::UnboundedReceiver<StreamFailure> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        *self.error_sender.lock() = Some(tx);
+        rx
+    }
+
+    /// Start capturing audio from `source`: for the microphone, the
+    /// preferred input device, or the default one when none is set or
+    /// it is missing. `trigger` (what asked for the microphone) goes to
+    /// the usage log. With a pre-roll the microphone's stream is already
+    /// open, and the recording starts with it.
+    pub fn start(&self, trigger: &str, source: CaptureSource) -> Result<(), AudioCaptureError> {
         if self.is_capturing.load(Ordering::SeqCst) {
             return Ok(()); // Already capturing
         }
 
-        let host = cpal::default_host();
+        let mut stream = self.stream.lock();
+        if stream.is_some() {
+            // Held open between recordings: that session ends here
+            self.usage_log.closed(0);
+            if self.stream_failed.load(Ordering::SeqCst) {
+                tracing::warn!("The input held open for the pre-roll is gone; reopening");
+                stream.take();
+            } else if source != CaptureSource::Microphone {
+                // The pre-roll is the microphone's: not this recording's
+                stream.take();
+                self.pre_roll.lock().take();
+            }
+        }
+        if stream.is_none() {
+            *stream = Some(self.open_stream(source)?);
+        }
+        self.begin_capture();
+        self.usage_log.opened(trigger);
+
+        tracing::info!("Audio capture started");
+        Ok(())
+    }
 
-        let device = host
-            .default_input_device()
-            .ok_or(AudioCaptureError::NoInputDevice)?;
+    /// The preferred input device, or the default one when none is set
+    /// or it is missing (reported to the fallback reporter).
+    fn microphone(&self) -> Result<cpal::Device, AudioCaptureError> {
+        let preferred = self.preferred_device.lock().clone();
+        let found = preferred.as_deref().and_then(devices::find_input_device);
+        let missing = preferred.filter(|_| found.is_none());
+        let device = match found {
+            Some(device) => device,
+            None => cpal::default_host()
+                .default_input_device()
+                .ok_or(AudioCaptureError::NoInputDevice)?,
+        };
 
         let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
-        tracing::info!("Using input device: {}", device_name);
-
-        let config = device
-            .default_input_config()
-            .map_err(|e| AudioCaptureError::DeviceError(e.to_string()))?;
+        if let Some(preferred) = missing {
+            tracing::warn!(
+                "Input device \"{}\" not found; using the default",
+                preferred
+            );
+            if let Some(report) = &*self.fallback_reporter.lock() {
+                report(DeviceFallback {
+                    preferred,
+                    used: device_name.clone(),
+                });
+            }
+        }
+        Ok(device)
+    }
 
-        let source_sample_rate = config.sample_rate().0;
-        let channels = config.channels() as usize;
+    /// Open and start the input stream. Its samples are dropped until
+    /// `begin_capture`, or kept for the pre-roll.
+    fn open_stream(&self, source: CaptureSource) -> Result<InputStream, AudioCaptureError> {
+        let input = match source {
+            CaptureSource::Microphone => {
+                let device = self.microphone()?;
+                let config = device
+                    .default_input_config()
+                    .map_err(|e| AudioCaptureError::DeviceError(e.to_string()))?;
+                Input::Device { device, config }
+            }
+            CaptureSource::SystemAudio => {
+                let application = self.capture_application.lock().clone();
+                source::system_audio_input(application.as_deref())?
+            }
+        };
+        let (device_name, hardware_rate, channels, format) = match &input {
+            Input::Device { device, config } => (
+                device.name().unwrap_or_else(|_| "Unknown".to_string()),
+                config.sample_rate().0,
+                config.channels() as usize,
+                config.sample_format().to_string(),
+            ),
+            #[cfg(target_os = "linux")]
+            Input::Monitor(pcm) => (
+                pcm.name().to_string(),
+                pcm.rate(),
+                pcm.channels() as usize,
+                SampleFormat::I16.to_string(),
+            ),
+        };
+        tracing::info!("Recording {} from: {}", source.as_str(), device_name);
         tracing::info!(
-            "Input config: {} Hz, {} channels, format: {:?}",
-            source_sample_rate,
+            "Input config: {} Hz, {} channels, format: {}",
+            hardware_rate,
             channels,
-            config.sample_format()
+            format
         );
+        let source_sample_rate = match self.rate_overrides.lock().get(&device_name) {
+            Some(&measured) => {
+                tracing::info!("Using the rate measured earlier: {} Hz", measured);
+                measured
+            }
+            None => hardware_rate,
+        };
+
+        // Resampling state, corrected on the fly if the device turns out
+        // to deliver another rate.
+        self.buffer.lock().hold_spill(true);
+        let rate = RateCheck {
+            estimator: RateEstimator::new(source_sample_rate),
+            device: device_name.clone(),
+            hardware_rate,
+            target_rate: self.target_sample_rate,
+            ratio: self.target_sample_rate as f64 / source_sample_rate as f64,
+            buffer: Arc::clone(&self.buffer),
+            spill_held: true,
+            overrides: Arc::clone(&self.rate_overrides),
+            reporter: self.rate_reporter.lock().clone(),
+        };
+        let sink = StreamSink {
+            channels,
+            buffer: Arc::clone(&self.buffer),
+            is_capturing: Arc::clone(&self.is_capturing),
+            paused: Arc::clone(&self.paused),
+            callbacks: self.callbacks.clone(),
+            chunk_sender: Arc::clone(&self.chunk_sender),
+            target_rate: self.target_sample_rate,
+            rate,
+            gain_db: Arc::clone(&self.gain_db),
+            clips: ClipDetector::default(),
+            clipping_reporter: self.clipping_reporter.lock().clone(),
+            device: device_name.clone(),
+            silence: SilentInputDetector::default(),
+            silent_input_reporter: self.silent_input_reporter.lock().clone(),
+            dc_blocker: DcBlocker::new(source_sample_rate),
+            was_capturing: false,
+            filters: Arc::clone(&self.filters),
+            channel_mode: Arc::clone(&self.channel_mode),
+            high_pass: None,
+            source_rate: source_sample_rate,
+            agc_enabled: Arc::clone(&self.agc_enabled),
+            agc: Agc::new(
+                source_sample_rate,
+                f32::from_bits(self.agc_gain.load(Ordering::Relaxed)),
+            ),
+            agc_gain: Arc::clone(&self.agc_gain),
+            agc_gate: Arc::clone(&self.agc_gate),
+            pre_roll: Arc::clone(&self.pre_roll),
+            spill_reporter: self.spill_reporter.lock().clone(),
+            monitor: Arc::clone(&self.monitor),
+            meter: LevelMeter::default(),
+        };
 
-        let buffer = Arc::clone(&self.buffer);
-        let is_capturing = Arc::clone(&self.is_capturing);
-        let chunk_sender = self.chunk_sender.lock().clone();
-        let target_rate = self.target_sample_rate;
-
-        // Resampling state
-        let resample_ratio = target_rate as f64 / source_sample_rate as f64;
-
-        let err_fn = |err| tracing::error!("Audio stream error: {}", err);
-
-        let stream = match config.sample_format() {
-            SampleFormat::F32 => {
-                let stream = device
-                    .build_input_stream(
-                        &config.into(),
-                        move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                            if !is_capturing.load(Ordering::SeqCst) {
-                                return;
-                            }
-
-                            // Convert f32 to i16 and handle channels
-                            let mono_samples: Vec<i16> = data
-                                .chunks(channels)
-                                .map(|frame| {
-                                    // Average channels for mono
-                                    let sum: f32 = frame.iter().sum();
-                                    let avg = sum / channels as f32;
-                                    (avg * i16::MAX as f32) as i16
-                                })
-                                .collect();
-
-                            // Simple resampling (linear interpolation)
-                            let resampled = resample(&mono_samples, resample_ratio);
-
-                            // Store in buffer
-                            buffer.lock().push(&resampled);
-
-                            // Send chunk for real-time processing
-                            if let Some(ref sender) = chunk_sender {
-                                let _ = sender.send(AudioChunk {
-                                    samples: resampled,
-                                    sample_rate: target_rate,
-                                });
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| AudioCaptureError::StreamError(e.to_string()))?;
-                stream
+        let error_reporter = self.error_reporter.lock().clone();
+        let error_sender = Arc::clone(&self.error_sender);
+        let stream_failed = Arc::clone(&self.stream_failed);
+        let err_fn = move |err: cpal::StreamError| {
+            tracing::error!("Audio stream error: {}", err);
+            let device_lost = matches!(err, cpal::StreamError::DeviceNotAvailable);
+            if device_lost {
+                stream_failed.store(true, Ordering::SeqCst);
             }
-            SampleFormat::I16 => {
-                let stream = device
-                    .build_input_stream(
-                        &config.into(),
-                        move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                            if !is_capturing.load(Ordering::SeqCst) {
-                                return;
-                            }
-
-                            // Handle channels
-                            let mono_samples: Vec<i16> = data
-                                .chunks(channels)
-                                .map(|frame| {
-                                    let sum: i32 = frame.iter().map(|&s| s as i32).sum();
-                                    (sum / channels as i32) as i16
-                                })
-                                .collect();
-
-                            // Simple resampling
-                            let resampled = resample(&mono_samples, resample_ratio);
-
-                            buffer.lock().push(&resampled);
-
-                            if let Some(ref sender) = chunk_sender {
-                                let _ = sender.send(AudioChunk {
-                                    samples: resampled,
-                                    sample_rate: target_rate,
-                                });
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                    .map_err(|e| AudioCaptureError::StreamError(e.to_string()))?;
-                stream
+            if let Some(report) = &error_reporter {
+                report(err.to_string());
+            }
+            if let Some(sender) = &*error_sender.lock() {
+                let _ = sender.send(StreamFailure {
+                    message: err.to_string(),
+                    device_lost,
+                });
             }
-            _ => return Err(AudioCaptureError::UnsupportedFormat),
         };
 
+        let (device, config) = match input {
+            Input::Device { device, config } => (device, config),
+            #[cfg(target_os = "linux")]
+            Input::Monitor(pcm) => {
+                let mut sink = sink;
+                let stream = pcm.start(move |data| sink.push(data), err_fn)?;
+                self.stream_failed.store(false, Ordering::SeqCst);
+                *self.stream_device.lock() = Some(device_name);
+                *self.stream_source.lock() = source;
+                return Ok(InputStream::Monitor(stream));
+            }
+        };
+        let sample_format = config.sample_format();
+        let config: cpal::StreamConfig = config.into();
+        let stream = match sample_format {
+            SampleFormat::I8 => build_stream::<i8>(&device, &config, sink, err_fn),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, sink, err_fn),
+            SampleFormat::I32 => build_stream::<i32>(&device, &config, sink, err_fn),
+            SampleFormat::I64 => build_stream::<i64>(&device, &config, sink, err_fn),
+            SampleFormat::U8 => build_stream::<u8>(&device, &config, sink, err_fn),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, sink, err_fn),
+            SampleFormat::U32 => build_stream::<u32>(&device, &config, sink, err_fn),
+            SampleFormat::U64 => build_stream::<u64>(&device, &config, sink, err_fn),
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, sink, err_fn),
+            SampleFormat::F64 => build_stream::<f64>(&device, &config, sink, err_fn),
+            other => Err(AudioCaptureError::UnsupportedFormat(other.to_string())),
+        }?;
+
         stream
             .play()
             .map_err(|e| AudioCaptureError::StreamError(e.to_string()))?;
+        self.stream_failed.store(false, Ordering::SeqCst);
+        *self.stream_device.lock() = Some(device_name);
+        *self.stream_source.lock() = source;
+        Ok(InputStream::Cpal(stream))
+    }
+
+    /// Reopen the stream of a running capture whose device went away:
+    /// on the preferred device if it is back, else the default (system
+    /// audio is reopened as such). The
+    /// recording carries on in the same buffer, resampled from the new
+    /// stream's rate.
+    pub fn restart(&self) -> Result<(), AudioCaptureError> {
+        let mut stream = self.stream.lock();
+        // Dropping the dead one first: some backends hold the device
+        stream.take();
+        let source = *self.stream_source.lock();
+        *stream = Some(self.open_stream(source)?);
+        tracing::info!("Input stream reopened");
+        Ok(())
+    }
+
+    /// Move a running capture to the system default input if that
+    /// changed (headphones plugged in mid-recording), unless a device
+    /// is preferred or it records system audio: the new device's name
+    /// if it moved. See `restart`.
+    pub fn follow_default_device(&self) -> Result<Option<String>, AudioCaptureError> {
+        if !self.is_capturing()
+            || self.preferred_device.lock().is_some()
+            || *self.stream_source.lock() != CaptureSource::Microphone
+        {
+            return Ok(None);
+        }
+        let Some(default) = cpal::default_host()
+            .default_input_device()
+            .and_then(|device| device.name().ok())
+        else {
+            return Ok(None);
+        };
+        if self.stream_device.lock().as_deref() == Some(default.as_str()) {
+            return Ok(None);
+        }
+        tracing::info!("The system default input is now \"{}\"", default);
+        self.restart()?;
+        Ok(self.stream_device.lock().clone())
+    }
 
+    /// Start accepting samples, the pre-roll first. Whatever is in the
+    /// buffer is dropped: it can only be left over from an earlier
+    /// capture.
+    fn begin_capture(&self) {
+        // Held until capture is on, so a callback either lands in the
+        // pre-roll taken here or after it
+        let mut pre_roll = self.pre_roll.lock();
+        let mut buffer = self.buffer.lock();
+        if !buffer.is_empty() {
+            tracing::warn!(
+                "Dropping {} samples left over from the previous capture",
+                buffer.len()
+            );
+            buffer.clear();
+        }
+        let before = pre_roll.take();
+        if !before.is_empty() {
+            tracing::debug!("Recording starts with {} samples of pre-roll", before.len());
+            buffer.push(&before);
+        }
+        self.paused.store(false, Ordering::SeqCst);
+        *self.pauses.lock() = PauseLog::default();
         self.is_capturing.store(true, Ordering::SeqCst);
-        *self.stream.lock() = Some(stream);
+    }
 
-        tracing::info!("Audio capture started");
-        Ok(())
+    /// Stop adding to the running capture's buffer until `resume`; the
+    /// stream stays open. The pause is kept for `take_pauses`. `false`
+    /// if not capturing or paused already.
+    pub fn pause(&self) -> bool {
+        if !self.is_capturing() {
+            return false;
+        }
+        // Flagged first: a callback running now may still land, before
+        // the position is taken
+        self.paused.store(true, Ordering::SeqCst);
+        let at_ms = (self.buffer_duration() * 1000.0) as u64;
+        let paused = self.pauses.lock().pause(at_ms, Instant::now());
+        if paused {
+            tracing::info!("Audio capture paused at {} ms", at_ms);
+        }
+        paused
+    }
+
+    /// Add to the buffer again, after what was captured before `pause`.
+    pub fn resume(&self) -> bool {
+        let resumed = self.pauses.lock().resume(Instant::now());
+        self.paused.store(false, Ordering::SeqCst);
+        if resumed {
+            tracing::info!("Audio capture resumed");
+        }
+        resumed
+    }
+
+    /// The last capture's pauses (see `audio::pauses`), once stopped.
+    pub fn take_pauses(&self) -> Vec<PauseMark> {
+        self.pauses.lock().finish(Instant::now())
     }
 
-    /// Stop capturing audio and return all captured samples
+    /// Stop capturing audio and return all captured samples. Waits
+    /// (at most `STOP_TIMEOUT`) for the callbacks still running, so none
+    /// of their samples land after these are taken. With a pre-roll or
+    /// the level monitor the microphone's stream stays open and goes
+    /// back to them; after system audio, the microphone's is reopened.
     pub fn stop(&self) -> Result<Vec<i16>, AudioCaptureError> {
-        self.is_capturing.store(false, Ordering::SeqCst);
+        let was_capturing = self.is_capturing.swap(false, Ordering::SeqCst);
+        // A pause under way ends here
+        self.pauses.lock().resume(Instant::now());
+        self.paused.store(false, Ordering::SeqCst);
+
+        let held = self.pre_roll.lock().capacity() > 0 || self.is_monitoring();
+        let microphone = *self.stream_source.lock() == CaptureSource::Microphone;
+        let keep_open = held && microphone && self.stream.lock().is_some();
+        if keep_open && !was_capturing {
+            // Only the pre-roll or the monitor is running
+            return Ok(self.take_samples());
+        }
+        if !keep_open {
+            // Drop the stream to stop it
+            if let Some(stream) = self.stream.lock().take() {
+                drop(stream);
+            }
+        }
 
-        // Drop the stream to stop it
-        if let Some(stream) = self.stream.lock().take() {
-            drop(stream);
+        let waiting = Instant::now();
+        let settle = self.stop_settle;
+        let settled = if keep_open {
+            self.callbacks.wait_running(settle.timeout)
+        } else {
+            self.callbacks.wait_quiet(settle.quiet, settle.timeout)
+        };
+        if settled {
+            tracing::debug!("Audio callbacks quiet after {:.0?}", waiting.elapsed());
+        } else {
+            tracing::warn!(
+                "Audio callbacks still running {:.0?} after stop; taking the samples anyway",
+                settle.timeout
+            );
         }
 
-        let samples = self.buffer.lock().take_samples();
+        let samples = self.take_samples();
+        self.usage_log
+            .closed((samples.len() * std::mem::size_of::<i16>()) as u64);
+        if keep_open {
+            self.usage_log.opened(self.held_trigger());
+        }
         tracing::info!(
             "Audio capture stopped, {} samples ({:.2}s)",
             samples.len(),
             samples.len() as f32 / self.target_sample_rate as f32
         );
+        if held && !microphone {
+            if let Err(e) = self.hold_microphone() {
+                tracing::warn!("Failed to reopen the input between recordings: {}", e);
+            }
+        }
 
         Ok(samples)
     }
 
+    /// Open the microphone's stream for the pre-roll or the level
+    /// monitor, after a recording that had another.
+    fn hold_microphone(&self) -> Result<(), AudioCaptureError> {
+        let mut stream = self.stream.lock();
+        if stream.is_none() && !self.is_capturing() {
+            *stream = Some(self.open_stream(CaptureSource::Microphone)?);
+            self.usage_log.opened(self.held_trigger());
+        }
+        Ok(())
+    }
+
+    /// Take what the running capture has so far, but for the last `keep`
+    /// samples, which stay for what follows: a continuous recording's
+    /// cut (see `audio::utterance`). The capture goes on.
+    pub fn cut(&self, keep: usize) -> Vec<i16> {
+        let (samples, failure) = {
+            // Held throughout, so no callback lands before the kept ones
+            let mut buffer = self.buffer.lock();
+            let mut samples = buffer.take_samples();
+            let kept = samples.split_off(samples.len().saturating_sub(keep));
+            buffer.push(&kept);
+            (samples, buffer.take_spill_failure())
+        };
+        if let (Some(message), Some(report)) = (failure, &*self.spill_reporter.lock()) {
+            report(SpillFailure { message });
+        }
+        samples
+    }
+
+    /// The buffer's samples, reporting a spill file that couldn't be
+    /// read back.
+    fn take_samples(&self) -> Vec<i16> {
+        let (samples, failure) = {
+            let mut buffer = self.buffer.lock();
+            (buffer.take_samples(), buffer.take_spill_failure())
+        };
+        if let (Some(message), Some(report)) = (failure, &*self.spill_reporter.lock()) {
+            report(SpillFailure { message });
+        }
+        samples
+    }
+
     /// Check if currently capturing
     pub fn is_capturing(&self) -> bool {
         self.is_capturing.load(Ordering::SeqCst)
@@ -253,7 +1329,7 @@ impl AudioCapture {
         self.buffer.lock().duration_secs()
     }
 
-    /// Clear the buffer
+    /// Clear the buffer, removing its spill file
     pub fn clear_buffer(&self) {
         self.buffer.lock().clear();
     }
@@ -262,6 +1338,10 @@ impl AudioCapture {
     pub fn sample_rate(&self) -> u32 {
         self.target_sample_rate
     }
+
+    pub fn usage_log(&self) -> &MicUsageLog {
+        &self.usage_log
+    }
 }
 
 impl Default for AudioCapture {
@@ -271,7 +1351,7 @@ impl Default for AudioCapture {
 }
 
 /// Simple linear interpolation resampling
-fn resample(samples: &[i16], ratio: f64) -> Vec<i16> {
+pub(super) fn resample(samples: &[i16], ratio: f64) -> Vec<i16> {
     if (ratio - 1.0).abs() < 0.001 {
         return samples.to_vec();
     }
@@ -307,3 +1387,400 @@ fn resample(samples: &[i16], ratio: f64) -> Vec<i16> {
 // We handle this by wrapping in Mutex
 unsafe impl Send for AudioCapture {}
 unsafe impl Sync for AudioCapture {}
+
+#[cfg(test)]
+mod tests {
+    use super::super::clipping::clipped_ratio;
+    use super::*;
+    use std::thread;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// Wait (at most a second) for the spill writer to get there.
+    fn eventually(mut done: impl FnMut() -> bool) {
+        let started = Instant::now();
+        while !done() {
+            assert!(started.elapsed() < Duration::from_secs(1), "timed out");
+            thread::sleep(ms(1));
+        }
+    }
+
+    /// A capture with a second of audio in it.
+    fn capturing() -> AudioCapture {
+        let capture = AudioCapture::new();
+        capture.begin_capture();
+        capture.buffer.lock().push(&[1; 16_000]);
+        capture
+    }
+
+    /// Fake audio source: a callback that got past the `is_capturing`
+    /// check and is still busy for `busy` when `stop` runs, as on the
+    /// backends that call once more after the stream is dropped.
+    fn late_callback(capture: &AudioCapture, busy: Duration) -> thread::JoinHandle<()> {
+        let buffer = Arc::clone(&capture.buffer);
+        let is_capturing = Arc::clone(&capture.is_capturing);
+        let callbacks = capture.callbacks.clone();
+        let (entered, in_callback) = std::sync::mpsc::channel();
+        let source = thread::spawn(move || {
+            let _active = callbacks.enter();
+            if !is_capturing.load(Ordering::SeqCst) {
+                return;
+            }
+            entered.send(()).unwrap();
+            thread::sleep(busy);
+            buffer.lock().push(&[7; 160]);
+        });
+        in_callback.recv().unwrap();
+        source
+    }
+
+    #[test]
+    fn stop_without_waiting_leaks_into_the_next_capture() {
+        // What `stop` did before: take the samples straight away
+        let mut capture = capturing();
+        capture.stop_settle = StopSettle {
+            quiet: Duration::ZERO,
+            timeout: Duration::ZERO,
+        };
+        let source = late_callback(&capture, ms(30));
+        assert_eq!(capture.stop().unwrap().len(), 16_000);
+        source.join().unwrap();
+        // The last word of this dictation, waiting for the next one...
+        assert_eq!(capture.buffer.lock().get_samples().len(), 160);
+        // ...which now drops it
+        capture.begin_capture();
+        assert!(capture.buffer.lock().get_samples().is_empty());
+    }
+
+    #[test]
+    fn stop_waits_for_the_running_callback() {
+        let capture = capturing();
+        let source = late_callback(&capture, ms(30));
+        let samples = capture.stop().unwrap();
+        source.join().unwrap();
+        assert_eq!(samples.len(), 16_160);
+        assert_eq!(samples.last(), Some(&7));
+        assert!(capture.buffer.lock().get_samples().is_empty());
+    }
+
+    #[test]
+    fn stop_is_bounded() {
+        let capture = capturing();
+        let source = late_callback(&capture, ms(1000));
+        let started = Instant::now();
+        assert_eq!(capture.stop().unwrap().len(), 16_000);
+        assert!(started.elapsed() < ms(800));
+        source.join().unwrap();
+    }
+
+    #[test]
+    fn recording_starts_with_the_pre_roll() {
+        let capture = AudioCapture::new();
+        {
+            let mut pre_roll = capture.pre_roll.lock();
+            pre_roll.set_capacity(4);
+            pre_roll.push(&[1, 2, 3, 4, 5, 6]);
+        }
+        capture.begin_capture();
+        capture.buffer.lock().push(&[7, 8]);
+        assert_eq!(capture.stop().unwrap(), [3, 4, 5, 6, 7, 8]);
+        assert!(capture.pre_roll.lock().take().is_empty());
+        assert_eq!(capture.pre_roll_ms(), 0);
+    }
+
+    #[test]
+    fn pauses_are_logged_per_capture() {
+        let capture = AudioCapture::new();
+        assert!(!capture.pause());
+        capture.begin_capture();
+        capture.buffer.lock().push(&[0; 8000]);
+        assert!(capture.pause());
+        assert!(!capture.pause());
+        assert!(capture.resume());
+        assert!(!capture.resume());
+        assert!(capture.pause());
+        // Stopped while paused
+        assert_eq!(capture.stop().unwrap().len(), 8000);
+        assert!(!capture.paused.load(Ordering::SeqCst));
+        let at: Vec<u64> = capture.take_pauses().iter().map(|p| p.at_ms).collect();
+        assert_eq!(at, [500, 500]);
+
+        capture.begin_capture();
+        capture.pause();
+        capture.begin_capture();
+        assert!(!capture.paused.load(Ordering::SeqCst));
+        assert!(capture.take_pauses().is_empty());
+    }
+
+    #[test]
+    fn cuts_keep_the_capture_going() {
+        let capture = AudioCapture::new();
+        capture.begin_capture();
+        capture.buffer.lock().push(&[1, 2, 3, 4, 5]);
+        assert_eq!(capture.cut(2), [1, 2, 3]);
+        capture.buffer.lock().push(&[6]);
+        assert_eq!(capture.cut(0), [4, 5, 6]);
+        assert!(capture.cut(10).is_empty());
+        assert!(capture.is_capturing());
+        capture.buffer.lock().push(&[7]);
+        assert_eq!(capture.stop().unwrap(), [7]);
+    }
+
+    #[test]
+    fn the_monitor_rides_on_a_recording() {
+        // Capturing: no stream of its own to open
+        let capture = capturing();
+        capture.start_monitor(|_| {}).unwrap();
+        assert!(capture.is_monitoring());
+        assert_eq!(capture.held_trigger(), MONITOR_TRIGGER);
+        capture.stop_monitor();
+        assert!(!capture.is_monitoring());
+        // The recording is untouched
+        assert!(capture.is_capturing());
+        assert_eq!(capture.stop().unwrap().len(), 16_000);
+        capture.stop_monitor();
+    }
+
+    #[test]
+    fn long_captures_spill_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = || std::fs::read_dir(dir.path()).unwrap().count();
+        let mut buffer = AudioBuffer::new(16_000);
+        buffer.set_spill(Some((dir.path().to_path_buf(), 4)));
+        buffer.push(&[1, 2, 3]);
+        assert_eq!(files(), 0);
+        buffer.push(&[4, 5]);
+        buffer.push(&[6, 7, 8, 9]);
+        buffer.push(&[10]);
+        assert_eq!(buffer.get_samples(), [10]);
+        assert_eq!(buffer.len(), 10);
+        eventually(|| files() == 1);
+
+        assert_eq!(buffer.take_samples(), (1..=10).collect::<Vec<i16>>());
+        assert!(buffer.take_spill_failure().is_none());
+        assert_eq!(files(), 0);
+    }
+
+    #[test]
+    fn a_failed_spill_stays_in_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let not_a_dir = dir.path().join("cache");
+        std::fs::write(&not_a_dir, "").unwrap();
+        let mut buffer = AudioBuffer::new(16_000);
+        buffer.set_spill(Some((not_a_dir, 2)));
+        buffer.push(&[1, 2, 3]);
+        eventually(|| buffer.take_spill_failure().is_some());
+        // Reported once, and not tried again this capture
+        buffer.push(&[4, 5]);
+        assert_eq!(buffer.get_samples(), [4, 5]);
+        assert!(buffer.take_spill_failure().is_none());
+        assert_eq!(buffer.take_samples(), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn a_rate_correction_reaches_samples_past_the_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = || std::fs::read_dir(dir.path()).unwrap().count();
+        let mut buffer = AudioBuffer::new(16_000);
+        buffer.set_spill(Some((dir.path().to_path_buf(), 4)));
+        // A new stream, its rate not measured yet
+        buffer.hold_spill(true);
+        buffer.push(&[0; 6]);
+        assert_eq!(buffer.get_samples().len(), 6);
+        // It delivers a third of the rate it reports
+        buffer.resample(3.0);
+        buffer.hold_spill(false);
+        buffer.push(&[7]);
+        assert!(buffer.get_samples().is_empty());
+        eventually(|| files() == 1);
+
+        let mut expected = vec![0; 18];
+        expected.push(7);
+        assert_eq!(buffer.take_samples(), expected);
+    }
+
+    #[test]
+    fn stop_and_clear_remove_the_spill_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = || std::fs::read_dir(dir.path()).unwrap().count();
+        let capture = AudioCapture::new();
+        capture
+            .buffer
+            .lock()
+            .set_spill(Some((dir.path().to_path_buf(), 2)));
+
+        capture.begin_capture();
+        capture.buffer.lock().push(&[1, 2, 3]);
+        assert_eq!(capture.buffer_duration(), 3.0 / 16_000.0);
+        assert_eq!(capture.stop().unwrap(), [1, 2, 3]);
+        assert_eq!(files(), 0);
+
+        capture.begin_capture();
+        capture.buffer.lock().push(&[4, 5]);
+        eventually(|| files() == 1);
+        capture.clear_buffer();
+        eventually(|| files() == 0);
+        // Left over when the next capture begins
+        capture.buffer.lock().push(&[6, 7]);
+        eventually(|| files() == 1);
+        capture.begin_capture();
+        eventually(|| files() == 0);
+        assert!(capture.stop().unwrap().is_empty());
+    }
+
+    #[test]
+    fn wait_running_waits_for_the_running_callback_only() {
+        let activity = CallbackActivity::default();
+        assert!(activity.wait_running(Duration::ZERO));
+
+        // Callbacks every 5 ms go on after the recording
+        let done = Arc::new(AtomicBool::new(false));
+        let source = {
+            let activity = activity.clone();
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    let active = activity.enter();
+                    thread::sleep(ms(2));
+                    drop(active);
+                    thread::sleep(ms(3));
+                }
+            })
+        };
+        let started = Instant::now();
+        assert!(activity.wait_running(ms(500)));
+        assert!(started.elapsed() < ms(100));
+        done.store(true, Ordering::SeqCst);
+        source.join().unwrap();
+
+        let _running = activity.enter();
+        assert!(!activity.wait_running(ms(20)));
+    }
+
+    /// `data` through the conversions.
+    fn to_mono<T>(data: &[T], channels: usize, factor: f32) -> Vec<i16>
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        quantize(&mix(data, channels, ChannelMode::Mix, factor))
+    }
+
+    /// `to_mono` at unity gain.
+    fn mono<T>(data: &[T], channels: usize) -> Vec<i16>
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        to_mono(data, channels, 1.0)
+    }
+
+    #[test]
+    fn integer_formats_convert_at_their_edges() {
+        assert_eq!(mono(&[0u8, 128, 255], 1), [i16::MIN, 0, 32_512]);
+        assert_eq!(mono(&[0u16, 32_768, u16::MAX], 1), [i16::MIN, 0, i16::MAX]);
+        assert_eq!(mono(&[i8::MIN, 0, i8::MAX], 1), [i16::MIN, 0, 32_512]);
+        assert_eq!(mono(&[i16::MIN, -1, i16::MAX], 1), [i16::MIN, -1, i16::MAX]);
+        // Below 16 bits rounds toward zero
+        assert_eq!(
+            mono(&[i32::MIN, -1, 0, 65_535, 65_536, i32::MAX], 1),
+            [i16::MIN, 0, 0, 0, 1, i16::MAX]
+        );
+        assert_eq!(mono(&[0u32, 1 << 31, u32::MAX], 1), [i16::MIN, 0, i16::MAX]);
+        assert_eq!(mono(&[i64::MIN, i64::MAX], 1), [i16::MIN, i16::MAX]);
+        assert_eq!(mono(&[0u64, u64::MAX], 1), [i16::MIN, i16::MAX]);
+    }
+
+    #[test]
+    fn float_formats_saturate() {
+        let full = mono(&[-1.0f32, -0.5, 0.0, 0.5, 1.0], 1);
+        assert_eq!(full, [i16::MIN, -16_384, 0, 16_384, i16::MAX]);
+        assert_eq!(clipped_ratio(&full), 0.4);
+        let over = mono(&[-2.0f64, -1.0, 0.0, 0.5, 1.0, 1.5], 1);
+        assert_eq!(over, [i16::MIN, i16::MIN, 0, 16_384, i16::MAX, i16::MAX]);
+        assert_eq!(clipped_ratio(&over), 4.0 / 6.0);
+        let nan = mono(&[f32::NAN], 1);
+        assert_eq!(nan, [0]);
+        assert_eq!(clipped_ratio(&nan), 0.0);
+    }
+
+    #[test]
+    fn channels_are_averaged() {
+        // Full scale on both channels doesn't overflow
+        assert_eq!(
+            mono(&[i16::MAX, i16::MAX, i16::MIN, i16::MIN], 2),
+            [i16::MAX, i16::MIN]
+        );
+        assert_eq!(mono(&[1.0f32, -1.0, 0.5, 0.5], 2), [0, 16_384]);
+        // Silence in unsigned stereo
+        assert_eq!(mono(&[128u8, 128], 2), [0]);
+        // A frame cut short at the end of the callback
+        assert_eq!(mono(&[100i16, 200, 300, 400, 500], 2), [150, 350, 500]);
+    }
+
+    #[test]
+    fn one_channel_can_be_taken() {
+        let frames = [0.5f32, 0.0, 0.0, 0.0, -0.25, 0.0, 0.0, 1.0];
+        let take = |mode| quantize(&mix(&frames, 4, mode, 1.0));
+        // The voice on the first input, at a quarter in the mix
+        assert_eq!(take(ChannelMode::Mix), [4_096, 6_144]);
+        assert_eq!(take(ChannelMode::Left), [16_384, -8_192]);
+        assert_eq!(take(ChannelMode::Right), [0, 0]);
+        assert_eq!(take(ChannelMode::Channel(3)), [0, i16::MAX]);
+        // Set for a device with more channels than this one
+        assert_eq!(take(ChannelMode::Channel(4)), take(ChannelMode::Mix));
+        assert_eq!(
+            quantize(&mix(&[100i16, 200, 300], 1, ChannelMode::Right, 1.0)),
+            [100, 200, 300]
+        );
+        // A frame cut short, without the channel
+        assert_eq!(
+            quantize(&mix(&[1.0f32, 0.5, 0.25], 2, ChannelMode::Right, 1.0)),
+            [16_384, 8_192]
+        );
+    }
+
+    #[test]
+    fn gain_applies_to_the_mix_and_clips() {
+        let boosted = to_mono(&[1_000i16, -20_000, 20_000], 1, 2.0);
+        assert_eq!(boosted, [2_000, i16::MIN, i16::MAX]);
+        assert_eq!(clipped_ratio(&boosted), 2.0 / 3.0);
+        // Averaged first: a loud channel with a quiet one doesn't clip
+        let mixed = to_mono(&[0.25f32, 0.25, 0.9, 0.0, 1.0, 1.0], 2, 2.0);
+        assert_eq!(mixed, [16_384, 29_491, i16::MAX]);
+        assert_eq!(clipped_ratio(&mixed), 1.0 / 3.0);
+        let cut = to_mono(&[64u8, 192], 1, gain::factor(-20.0));
+        assert_eq!(cut, [-1_638, 1_638]);
+        assert_eq!(clipped_ratio(&cut), 0.0);
+    }
+
+    #[test]
+    fn quiet_needs_no_callback_for_the_whole_period() {
+        let activity = CallbackActivity::default();
+        let started = Instant::now();
+        assert!(activity.wait_quiet(ms(20), ms(500)));
+        assert!(started.elapsed() >= ms(20));
+
+        // Callbacks every 5 ms never leave 20 ms of quiet
+        let done = Arc::new(AtomicBool::new(false));
+        let source = {
+            let activity = activity.clone();
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    drop(activity.enter());
+                    thread::sleep(ms(5));
+                }
+            })
+        };
+        assert!(!activity.wait_quiet(ms(20), ms(100)));
+        done.store(true, Ordering::SeqCst);
+        source.join().unwrap();
+
+        // Nor does one that is still running
+        let _running = activity.enter();
+        assert!(!activity.wait_quiet(ms(20), ms(50)));
+    }
+}
@@ -0,0 +1,88 @@
+//! Which of a multi-channel input's channels are recorded.
+//!
+//! The capture callbacks average a frame's channels into mono, which
+//! suits a stereo microphone. On an interface whose voice is on one
+//! input and the others are empty, the average buries it under the
+//! silence (a quarter of the level with four channels). With
+//! `Settings.channel_mode` the callbacks take that one channel instead.
+
+use serde::{Deserialize, Serialize};
+
+/// `set_channel_mode` payload. Frontend mirror: `ChannelMode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChannelMode {
+    /// The average of every channel.
+    #[default]
+    Mix,
+    Left,
+    Right,
+    /// The channel at this index, 0 for the first.
+    Channel(u16),
+}
+
+impl ChannelMode {
+    /// The one channel taken, if not the mix.
+    pub fn index(self) -> Option<u16> {
+        match self {
+            ChannelMode::Mix => None,
+            ChannelMode::Left => Some(0),
+            ChannelMode::Right => Some(1),
+            ChannelMode::Channel(index) => Some(index),
+        }
+    }
+
+    /// Whether a device with `channels` channels has the one taken.
+    pub fn check(self, channels: u16) -> Result<(), String> {
+        match self.index() {
+            Some(index) if index >= channels => Err(format!(
+                "The input has {} channel{}, there is no channel {}",
+                channels,
+                if channels == 1 { "" } else { "s" },
+                index + 1
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_pick_a_channel() {
+        assert_eq!(ChannelMode::Mix.index(), None);
+        assert_eq!(ChannelMode::Left.index(), Some(0));
+        assert_eq!(ChannelMode::Right.index(), Some(1));
+        assert_eq!(ChannelMode::Channel(7).index(), Some(7));
+    }
+
+    #[test]
+    fn checked_against_the_channel_count() {
+        assert!(ChannelMode::Mix.check(1).is_ok());
+        assert!(ChannelMode::Left.check(1).is_ok());
+        assert_eq!(
+            ChannelMode::Right.check(1).unwrap_err(),
+            "The input has 1 channel, there is no channel 2"
+        );
+        assert!(ChannelMode::Channel(7).check(8).is_ok());
+        assert_eq!(
+            ChannelMode::Channel(8).check(8).unwrap_err(),
+            "The input has 8 channels, there is no channel 9"
+        );
+    }
+
+    #[test]
+    fn settings_payload() {
+        assert_eq!(
+            serde_json::to_value(ChannelMode::Left).unwrap(),
+            serde_json::json!("left")
+        );
+        assert_eq!(
+            serde_json::from_value::<ChannelMode>(serde_json::json!({ "channel": 3 })).unwrap(),
+            ChannelMode::Channel(3)
+        );
+        assert_eq!(ChannelMode::default(), ChannelMode::Mix);
+    }
+}
@@ -0,0 +1,199 @@
+//! Clipping: input too hot to transcribe.
+//!
+//! A microphone (or a gain) driving the converter to its limits flattens
+//! the peaks, and Whisper makes garbage of the distortion. The capture
+//! callbacks hand their 16-bit samples to a `ClipDetector`, which counts
+//! those at or near full scale over the last `CLIP_WINDOW` and says when
+//! they pass `CLIP_THRESHOLD_PERCENT`, for the `audio:clipping` event.
+//! `clipped_ratio` is the same count over a whole recording, for
+//! `transcript:final`.
+//!
+//! Pure: the caller passes the callback times in, so it is tested with
+//! synthetic timings.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 99% of full scale: the converter's last tenth of a dB. Clipped peaks
+/// sit at the bound, or just under it once filtered or resampled.
+pub const NEAR_FULL_SCALE: u16 = 32_440;
+
+/// How far back the share of clipped samples is taken.
+pub const CLIP_WINDOW: Duration = Duration::from_millis(500);
+
+/// A clean recording has none, or a stray peak; past this share of a
+/// window the distortion is audible.
+pub const CLIP_THRESHOLD_PERCENT: f32 = 0.1;
+
+/// Clipping is reported at most this often while it goes on.
+pub const CLIP_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `audio:clipping` payload. Frontend mirror: `Clipping`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Clipping {
+    /// Share of the last `CLIP_WINDOW`'s samples at or near full scale.
+    pub percent: f32,
+    /// The gain they were recorded at.
+    pub gain_db: f32,
+}
+
+/// Whether `sample` is at or near full scale.
+pub fn is_clipped(sample: i16) -> bool {
+    sample.unsigned_abs() >= NEAR_FULL_SCALE
+}
+
+/// Share (0 to 1) of `samples` at or near full scale; 0 for none.
+pub fn clipped_ratio(samples: &[i16]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let clipped = samples.iter().filter(|&&s| is_clipped(s)).count();
+    clipped as f32 / samples.len() as f32
+}
+
+/// Watches a stream's samples and says when too many clip.
+#[derive(Debug, Clone, Default)]
+pub struct ClipDetector {
+    /// The callbacks within `CLIP_WINDOW`: when, samples, clipped.
+    window: VecDeque<(Instant, usize, usize)>,
+    samples: usize,
+    clipped: usize,
+    reported: Option<Instant>,
+}
+
+impl ClipDetector {
+    /// Count a callback's `samples`, received at `now`. Returns the
+    /// window's percentage when it is time to report: over the threshold
+    /// with new clips, at most every `CLIP_REPORT_INTERVAL`.
+    pub fn observe(&mut self, samples: &[i16], now: Instant) -> Option<f32> {
+        let clipped = samples.iter().filter(|&&s| is_clipped(s)).count();
+        while let Some(&(at, len, old)) = self.window.front() {
+            if now.duration_since(at) < CLIP_WINDOW {
+                break;
+            }
+            self.window.pop_front();
+            self.samples -= len;
+            self.clipped -= old;
+        }
+        self.window.push_back((now, samples.len(), clipped));
+        self.samples += samples.len();
+        self.clipped += clipped;
+
+        if clipped == 0 {
+            return None;
+        }
+        let percent = self.clipped as f32 * 100.0 / self.samples as f32;
+        let due = self
+            .reported
+            .is_none_or(|last| now.duration_since(last) >= CLIP_REPORT_INTERVAL);
+        if percent < CLIP_THRESHOLD_PERCENT || !due {
+            return None;
+        }
+        self.reported = Some(now);
+        Some(percent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 ms at 16 kHz, a sine of `amplitude` flattened at full scale.
+    fn callback(amplitude: f32) -> Vec<i16> {
+        (0..160)
+            .map(|i| {
+                let phase = i as f32 / 160.0 * std::f32::consts::TAU * 4.0;
+                (phase.sin() * amplitude * 32768.0).clamp(-32768.0, 32767.0) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn near_full_scale_counts() {
+        assert!(is_clipped(i16::MAX));
+        assert!(is_clipped(i16::MIN));
+        assert!(is_clipped(-32_440));
+        assert!(!is_clipped(32_000));
+        assert!(!is_clipped(0));
+    }
+
+    #[test]
+    fn ratio_of_a_buffer() {
+        assert_eq!(clipped_ratio(&[]), 0.0);
+        assert_eq!(clipped_ratio(&callback(0.5)), 0.0);
+        assert_eq!(clipped_ratio(&[i16::MAX, 0, i16::MIN, 100]), 0.5);
+        // Twice too hot: most of each cycle is flat
+        assert!(clipped_ratio(&callback(2.0)) > 0.5);
+    }
+
+    #[test]
+    fn clean_input_is_never_reported() {
+        let start = Instant::now();
+        let mut detector = ClipDetector::default();
+        for i in 0..200 {
+            let now = start + Duration::from_millis(i * 10);
+            assert_eq!(detector.observe(&callback(0.9), now), None);
+        }
+    }
+
+    #[test]
+    fn hot_input_is_reported_at_most_every_interval() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut detector = ClipDetector::default();
+        let percent = detector.observe(&callback(2.0), at(0)).unwrap();
+        assert!(percent > 50.0);
+        assert_eq!(detector.observe(&callback(2.0), at(500)), None);
+        assert!(detector.observe(&callback(2.0), at(1000)).is_some());
+        // Only new clips are reported
+        assert_eq!(detector.observe(&callback(0.5), at(2500)), None);
+        assert!(detector.observe(&callback(2.0), at(2510)).is_some());
+    }
+
+    #[test]
+    fn a_stray_peak_is_under_the_threshold() {
+        let start = Instant::now();
+        let mut detector = ClipDetector::default();
+        for i in 0..49 {
+            let now = start + Duration::from_millis(i * 10);
+            assert_eq!(detector.observe(&callback(0.5), now), None);
+        }
+        // One sample in the window's 8000
+        let mut peak = callback(0.5);
+        peak[0] = i16::MAX;
+        assert_eq!(
+            detector.observe(&peak, start + Duration::from_millis(490)),
+            None
+        );
+    }
+
+    #[test]
+    fn old_callbacks_leave_the_window() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut detector = ClipDetector::default();
+        assert!(detector.observe(&callback(2.0), at(0)).is_some());
+        for ms in (10..1500).step_by(10) {
+            detector.observe(&callback(0.5), at(ms));
+        }
+        // The hot callback is long gone: one clipped sample in the window
+        let mut peak = callback(0.5);
+        peak[0] = i16::MAX;
+        assert_eq!(detector.observe(&peak, at(1500)), None);
+        assert_eq!(detector.window.len(), 50);
+    }
+
+    #[test]
+    fn payload() {
+        assert_eq!(
+            serde_json::to_value(Clipping {
+                percent: 2.5,
+                gain_db: 6.0
+            })
+            .unwrap(),
+            serde_json::json!({ "percent": 2.5, "gainDb": 6.0 })
+        );
+    }
+}
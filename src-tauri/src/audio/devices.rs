@@ -0,0 +1,326 @@
+//! The input devices there are to record from.
+//!
+//! cpal is asked host by host (ALSA and JACK on Linux, WASAPI and ASIO
+//! on Windows, CoreAudio on macOS). A host or a device that fails to
+//! answer doesn't fail the listing: what it said goes into `warnings`
+//! and the rest is listed.
+//!
+//! Names aren't unique (two identical USB microphones), so a device is
+//! picked by its `id`: host and name, numbered from the second device
+//! of a name on (`ALSA/USB Mic#2`). `Settings.preferred_device` keeps
+//! one; `find_input_device` opens it again.
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// An input device. Frontend mirror: `AudioDevice`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioDevice {
+    /// What `set_audio_device` takes.
+    pub id: String,
+    pub name: String,
+    /// The cpal host it is reached through: "ALSA", "WASAPI", …
+    pub host: String,
+    /// The system default input, which recording uses.
+    pub is_default: bool,
+    /// Of its default input config; `None` when it wouldn't tell.
+    pub default_sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+/// `get_audio_devices` result.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioDevices {
+    /// The default first, then as the hosts listed them.
+    pub devices: Vec<AudioDevice>,
+    /// Hosts and devices that couldn't be listed, and why.
+    pub warnings: Vec<String>,
+}
+
+/// `audio:device-fallback` payload: the preferred device wasn't there,
+/// so the default was recorded from. Frontend mirror: `DeviceFallback`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceFallback {
+    /// The id of the device asked for.
+    pub preferred: String,
+    /// Name of the default device used instead.
+    pub used: String,
+}
+
+/// A host's answer, before it is listed.
+struct HostProbe {
+    host: String,
+    /// The host recording goes through.
+    is_default: bool,
+    devices: Result<Vec<DeviceProbe>, String>,
+}
+
+struct DeviceProbe {
+    name: Result<String, String>,
+    /// Sample rate and channels of the default input config.
+    config: Result<(u32, u16), String>,
+}
+
+/// Every input device of every host there is.
+pub fn list_input_devices() -> AudioDevices {
+    let default_host = cpal::default_host();
+    let default_name = default_host
+        .default_input_device()
+        .and_then(|device| device.name().ok());
+    let mut probes = Vec::new();
+    for id in cpal::available_hosts() {
+        let host = id.name().to_string();
+        let devices = cpal::host_from_id(id)
+            .map_err(|e| e.to_string())
+            .and_then(|host| host.input_devices().map_err(|e| e.to_string()))
+            .map(|devices| devices.map(|device| probe(&device)).collect());
+        probes.push(HostProbe {
+            host,
+            is_default: id == default_host.id(),
+            devices,
+        });
+    }
+    assemble(probes, default_name.as_deref())
+}
+
+/// The input device `id` (an `AudioDevice::id`), if it is there.
+pub fn find_input_device(id: &str) -> Option<cpal::Device> {
+    for host_id in cpal::available_hosts() {
+        let host_name = host_id.name();
+        if !id.starts_with(&format!("{host_name}/")) {
+            continue;
+        }
+        let devices = match cpal::host_from_id(host_id).map(|host| host.input_devices()) {
+            Ok(Ok(devices)) => devices,
+            _ => continue,
+        };
+        let mut seen = HashMap::new();
+        for device in devices {
+            let Ok(name) = device.name() else { continue };
+            if device_id(host_name, &name, &mut seen) == id {
+                return Some(device);
+            }
+        }
+    }
+    None
+}
+
+/// Channels of the device recording opens: `preferred` (an
+/// `AudioDevice::id`) if it is there, else the default. `None` when
+/// there is none or it wouldn't tell.
+pub fn input_channels(preferred: Option<&str>) -> Option<u16> {
+    let device = match preferred.and_then(find_input_device) {
+        Some(device) => device,
+        None => cpal::default_host().default_input_device()?,
+    };
+    device
+        .default_input_config()
+        .ok()
+        .map(|config| config.channels())
+}
+
+/// The id of the next device named `name` on `host`, `seen` counting
+/// the names met so far on the host.
+fn device_id(host: &str, name: &str, seen: &mut HashMap<String, usize>) -> String {
+    let n = seen.entry(name.to_string()).or_insert(0);
+    *n += 1;
+    match *n {
+        1 => format!("{host}/{name}"),
+        n => format!("{host}/{name}#{n}"),
+    }
+}
+
+fn probe(device: &cpal::Device) -> DeviceProbe {
+    DeviceProbe {
+        name: device.name().map_err(|e| e.to_string()),
+        config: device
+            .default_input_config()
+            .map(|config| (config.sample_rate().0, config.channels()))
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// The listing, from what the hosts said; `default_name` is the default
+/// host's default input.
+fn assemble(probes: Vec<HostProbe>, default_name: Option<&str>) -> AudioDevices {
+    let mut listed = AudioDevices::default();
+    let mut default_found = false;
+    for probe in probes {
+        let devices = match probe.devices {
+            Ok(devices) => devices,
+            Err(e) => {
+                listed
+                    .warnings
+                    .push(format!("{}: couldn't list devices: {e}", probe.host));
+                continue;
+            }
+        };
+        let mut seen = HashMap::new();
+        for device in devices {
+            let name = match device.name {
+                Ok(name) => name,
+                Err(e) => {
+                    listed
+                        .warnings
+                        .push(format!("{}: a device has no name: {e}", probe.host));
+                    continue;
+                }
+            };
+            let config = match device.config {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    listed
+                        .warnings
+                        .push(format!("{}: {name}: no default config: {e}", probe.host));
+                    None
+                }
+            };
+            // The first of its name, when there are several
+            let is_default = !default_found && probe.is_default && Some(&*name) == default_name;
+            default_found |= is_default;
+            listed.devices.push(AudioDevice {
+                id: device_id(&probe.host, &name, &mut seen),
+                is_default,
+                host: probe.host.clone(),
+                name,
+                default_sample_rate: config.map(|(rate, _)| rate),
+                channels: config.map(|(_, channels)| channels),
+            });
+        }
+    }
+    // Stable: the rest keep the hosts' order
+    listed.devices.sort_by_key(|device| !device.is_default);
+    listed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(name: &str, config: Result<(u32, u16), &str>) -> DeviceProbe {
+        DeviceProbe {
+            name: Ok(name.to_string()),
+            config: config.map_err(str::to_string),
+        }
+    }
+
+    #[test]
+    fn failures_are_warnings() {
+        let probes = vec![
+            HostProbe {
+                host: "ALSA".into(),
+                is_default: true,
+                devices: Ok(vec![
+                    device("Webcam", Ok((48_000, 2))),
+                    device("USB Interface", Err("device busy")),
+                    DeviceProbe {
+                        name: Err("gone".into()),
+                        config: Ok((44_100, 1)),
+                    },
+                ]),
+            },
+            HostProbe {
+                host: "JACK".into(),
+                is_default: false,
+                devices: Err("server not running".into()),
+            },
+        ];
+        let listed = assemble(probes, Some("USB Interface"));
+        assert_eq!(
+            listed.devices,
+            [
+                AudioDevice {
+                    id: "ALSA/USB Interface".into(),
+                    name: "USB Interface".into(),
+                    host: "ALSA".into(),
+                    is_default: true,
+                    default_sample_rate: None,
+                    channels: None,
+                },
+                AudioDevice {
+                    id: "ALSA/Webcam".into(),
+                    name: "Webcam".into(),
+                    host: "ALSA".into(),
+                    is_default: false,
+                    default_sample_rate: Some(48_000),
+                    channels: Some(2),
+                },
+            ]
+        );
+        assert_eq!(
+            listed.warnings,
+            [
+                "ALSA: USB Interface: no default config: device busy",
+                "ALSA: a device has no name: gone",
+                "JACK: couldn't list devices: server not running",
+            ]
+        );
+    }
+
+    #[test]
+    fn default_is_the_default_hosts() {
+        let probes = vec![
+            HostProbe {
+                host: "ASIO".into(),
+                is_default: false,
+                devices: Ok(vec![device("Mic", Ok((48_000, 1)))]),
+            },
+            HostProbe {
+                host: "WASAPI".into(),
+                is_default: true,
+                devices: Ok(vec![device("Mic", Ok((48_000, 1)))]),
+            },
+        ];
+        let listed = assemble(probes, Some("Mic"));
+        let defaults: Vec<&str> = listed
+            .devices
+            .iter()
+            .filter(|device| device.is_default)
+            .map(|device| device.host.as_str())
+            .collect();
+        assert_eq!(defaults, ["WASAPI"]);
+        assert_eq!(listed.devices[0].host, "WASAPI");
+        assert!(listed.warnings.is_empty());
+    }
+
+    #[test]
+    fn repeated_names_get_numbered_ids() {
+        let probes = vec![
+            HostProbe {
+                host: "ALSA".into(),
+                is_default: true,
+                devices: Ok(vec![
+                    device("USB Mic", Ok((48_000, 1))),
+                    device("Webcam", Ok((16_000, 1))),
+                    device("USB Mic", Ok((48_000, 1))),
+                    device("USB Mic", Ok((44_100, 2))),
+                ]),
+            },
+            HostProbe {
+                host: "JACK".into(),
+                is_default: false,
+                devices: Ok(vec![device("USB Mic", Ok((48_000, 1)))]),
+            },
+        ];
+        let listed = assemble(probes, Some("USB Mic"));
+        let ids: Vec<(&str, bool)> = listed
+            .devices
+            .iter()
+            .map(|device| (device.id.as_str(), device.is_default))
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                ("ALSA/USB Mic", true),
+                ("ALSA/Webcam", false),
+                ("ALSA/USB Mic#2", false),
+                ("ALSA/USB Mic#3", false),
+                ("JACK/USB Mic", false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! End of speech, for voice-activated recordings.
+//!
+//! A `VoiceActivated` recording stops itself: the chunk task hands each
+//! VAD result to an `EndpointDetector`, which says when the speech is
+//! over (the VAD's hangover, `Settings.silence_timeout_ms`, ran out) and
+//! the recording goes through `stop_listen`'s flow. Silence before the
+//! first word doesn't count, but after `MAX_SPEECH_WAIT_MS` of it the
+//! recording stops anyway, rather than keep the microphone open for
+//! nobody.
+//!
+//! Pure: fed VAD results and chunk lengths.
+
+use serde::Serialize;
+
+/// How long a voice-activated recording waits for someone to speak.
+pub const MAX_SPEECH_WAIT_MS: u64 = 30_000;
+
+/// Why a voice-activated recording stopped. `listen:voice-end` payload
+/// (`reason`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VoiceEnd {
+    /// Speech, then the silence timeout.
+    SpeechEnded,
+    /// Nothing said within `MAX_SPEECH_WAIT_MS`.
+    NoSpeech,
+}
+
+/// Watches a recording's VAD results for the end of speech.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointDetector {
+    /// Speech was heard.
+    heard: bool,
+    /// Samples before the first speech.
+    waited: u64,
+    ended: bool,
+}
+
+impl EndpointDetector {
+    /// Count a chunk of `samples` at `sample_rate` Hz, which the VAD
+    /// found `is_speech`. Says once when the recording should stop.
+    pub fn observe(
+        &mut self,
+        is_speech: bool,
+        samples: usize,
+        sample_rate: u32,
+    ) -> Option<VoiceEnd> {
+        if self.ended {
+            return None;
+        }
+        let end = if is_speech {
+            self.heard = true;
+            None
+        } else if self.heard {
+            Some(VoiceEnd::SpeechEnded)
+        } else {
+            self.waited += samples as u64;
+            (self.waited * 1000 >= MAX_SPEECH_WAIT_MS * u64::from(sample_rate))
+                .then_some(VoiceEnd::NoSpeech)
+        };
+        self.ended = end.is_some();
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    #[test]
+    fn leading_silence_is_ignored() {
+        let mut detector = EndpointDetector::default();
+        // 10 s of nothing, in 10 ms chunks
+        for _ in 0..1000 {
+            assert_eq!(detector.observe(false, 160, RATE), None);
+        }
+        assert_eq!(detector.observe(true, 160, RATE), None);
+    }
+
+    #[test]
+    fn ends_when_the_speech_does() {
+        let mut detector = EndpointDetector::default();
+        detector.observe(false, 1600, RATE);
+        // Speech, hangover included
+        for _ in 0..30 {
+            assert_eq!(detector.observe(true, 1600, RATE), None);
+        }
+        assert_eq!(
+            detector.observe(false, 1600, RATE),
+            Some(VoiceEnd::SpeechEnded)
+        );
+        // Said once
+        assert_eq!(detector.observe(false, 1600, RATE), None);
+        assert_eq!(detector.observe(true, 1600, RATE), None);
+    }
+
+    #[test]
+    fn gives_up_waiting() {
+        let mut detector = EndpointDetector::default();
+        let chunks = MAX_SPEECH_WAIT_MS / 100;
+        for _ in 1..chunks {
+            assert_eq!(detector.observe(false, 1600, RATE), None);
+        }
+        assert_eq!(
+            detector.observe(false, 1600, RATE),
+            Some(VoiceEnd::NoSpeech)
+        );
+        assert_eq!(detector.observe(false, 1600, RATE), None);
+    }
+
+    #[test]
+    fn payload() {
+        assert_eq!(
+            serde_json::to_value(VoiceEnd::NoSpeech).unwrap(),
+            serde_json::json!("no-speech")
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Audio files: read for `transcribe_file`, written for
+//! `save_last_recording`.
+//!
+//! Reads a WAV file (8/16/24/32-bit integer or 32-bit float PCM) and
+//! turns it into what the engine expects: 16 kHz mono `i16`, channels
+//! averaged and resampled with the same linear interpolation as live
+//! capture. The duration is checked from the header before anything is
+//! decoded, so an hours-long file is refused without reading it.
+//! Recordings are written back as they were captured: 16 kHz mono
+//! 16-bit PCM.
+
+use serde::Serialize;
+use std::path::Path;
+use thiserror::Error;
+
+use super::capture::resample;
+
+/// Longest file accepted, in seconds.
+pub const MAX_FILE_SECS: u64 = 2 * 60 * 60;
+const TARGET_SAMPLE_RATE: u32 = 16000;
+
+#[derive(Error, Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum AudioFileError {
+    #[error("File not found: {path}")]
+    NotFound { path: String },
+    #[error("Unsupported audio file: {message}")]
+    Unsupported { message: String },
+    #[error("Audio file is {duration_secs} s long; the limit is {max_secs} s")]
+    TooLong { duration_secs: u64, max_secs: u64 },
+    #[error("Failed to read audio file: {message}")]
+    Read { message: String },
+    #[error("Failed to write audio file: {message}")]
+    Write { message: String },
+}
+
+impl From<hound::Error> for AudioFileError {
+    fn from(e: hound::Error) -> Self {
+        match e {
+            hound::Error::IoError(e) => Self::Read {
+                message: e.to_string(),
+            },
+            other => Self::Unsupported {
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
+/// Decode `path` to 16 kHz mono samples.
+pub fn read_wav(path: &Path) -> Result<Vec<i16>, AudioFileError> {
+    if !path.is_file() {
+        return Err(AudioFileError::NotFound {
+            path: path.display().to_string(),
+        });
+    }
+    let reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    if spec.channels == 0 || spec.sample_rate == 0 {
+        return Err(AudioFileError::Unsupported {
+            message: "no channels or sample rate in the header".to_string(),
+        });
+    }
+    let duration_secs = u64::from(reader.duration()) / u64::from(spec.sample_rate);
+    if duration_secs > MAX_FILE_SECS {
+        return Err(AudioFileError::TooLong {
+            duration_secs,
+            max_secs: MAX_FILE_SECS,
+        });
+    }
+
+    let interleaved = decode(reader)?;
+    let mono = downmix(&interleaved, usize::from(spec.channels));
+    tracing::debug!(
+        "Read {} ({} Hz, {} channels, {} s)",
+        path.display(),
+        spec.sample_rate,
+        spec.channels,
+        duration_secs
+    );
+    Ok(resample(
+        &mono,
+        f64::from(TARGET_SAMPLE_RATE) / f64::from(spec.sample_rate),
+    ))
+}
+
+/// Write 16 kHz mono `samples` to `path` as a 16-bit WAV, creating
+/// its directory.
+pub fn save_wav(path: &Path, samples: &[i16]) -> Result<(), AudioFileError> {
+    let write_error = |e: &dyn std::fmt::Display| AudioFileError::Write {
+        message: format!("{}: {e}", path.display()),
+    };
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| write_error(&e))?;
+    }
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: TARGET_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).map_err(|e| write_error(&e))?;
+    for &sample in samples {
+        writer.write_sample(sample).map_err(|e| write_error(&e))?;
+    }
+    writer.finalize().map_err(|e| write_error(&e))
+}
+
+/// All samples as `i16`, still interleaved.
+fn decode<R: std::io::Read>(reader: hound::WavReader<R>) -> Result<Vec<i16>, AudioFileError> {
+    let spec = reader.spec();
+    match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, 32) => reader
+            .into_samples::<f32>()
+            .map(|s| s.map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16))
+            .collect::<Result<_, _>>()
+            .map_err(Into::into),
+        (hound::SampleFormat::Int, bits @ 1..=32) => reader
+            .into_samples::<i32>()
+            .map(|s| s.map(|s| scale_to_i16(s, bits)))
+            .collect::<Result<_, _>>()
+            .map_err(Into::into),
+        (format, bits) => Err(AudioFileError::Unsupported {
+            message: format!("{bits}-bit {format:?} samples"),
+        }),
+    }
+}
+
+/// Rescale a `bits`-wide integer sample to 16 bits.
+fn scale_to_i16(sample: i32, bits: u16) -> i16 {
+    if bits >= 16 {
+        (sample >> (bits - 16)) as i16
+    } else {
+        (sample << (16 - bits)) as i16
+    }
+}
+
+/// Average interleaved channels into one.
+fn downmix(interleaved: &[i16], channels: usize) -> Vec<i16> {
+    if channels == 1 {
+        return interleaved.to_vec();
+    }
+    interleaved
+        .chunks(channels)
+        .map(|frame| (frame.iter().map(|&s| i32::from(s)).sum::<i32>() / frame.len() as i32) as i16)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_wav(
+        dir: &tempfile::TempDir,
+        spec: hound::WavSpec,
+        write: impl FnOnce(&mut hound::WavWriter<std::io::BufWriter<std::fs::File>>),
+    ) -> std::path::PathBuf {
+        let path = dir.path().join("test.wav");
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        write(&mut writer);
+        writer.finalize().unwrap();
+        path
+    }
+
+    fn spec(
+        channels: u16,
+        sample_rate: u32,
+        bits: u16,
+        format: hound::SampleFormat,
+    ) -> hound::WavSpec {
+        hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: bits,
+            sample_format: format,
+        }
+    }
+
+    #[test]
+    fn mono_16k_is_read_as_is() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_wav(&dir, spec(1, 16000, 16, hound::SampleFormat::Int), |w| {
+            for s in [0i16, 100, -100, i16::MAX] {
+                w.write_sample(s).unwrap();
+            }
+        });
+        assert_eq!(read_wav(&path).unwrap(), vec![0, 100, -100, i16::MAX]);
+    }
+
+    #[test]
+    fn stereo_float_48k_is_downmixed_and_resampled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_wav(&dir, spec(2, 48000, 32, hound::SampleFormat::Float), |w| {
+            // One second of a constant signal, left and right different
+            for _ in 0..48000 {
+                w.write_sample(0.5f32).unwrap();
+                w.write_sample(0.0f32).unwrap();
+            }
+        });
+        let samples = read_wav(&path).unwrap();
+        assert_eq!(samples.len(), 16000);
+        let expected = (0.5 * i16::MAX as f32) as i16 / 2;
+        assert!(samples.iter().all(|&s| (s - expected).abs() <= 1));
+    }
+
+    #[test]
+    fn saved_recordings_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recordings").join("last.wav");
+        let samples = [0i16, 1, -1, i16::MIN, i16::MAX, 1234];
+        save_wav(&path, &samples).unwrap();
+        let written = hound::WavReader::open(&path).unwrap().spec();
+        assert_eq!(written, spec(1, 16000, 16, hound::SampleFormat::Int));
+        assert_eq!(read_wav(&path).unwrap(), samples);
+
+        // Over a file that's there: replaced
+        save_wav(&path, &samples[..2]).unwrap();
+        assert_eq!(read_wav(&path).unwrap(), [0, 1]);
+    }
+
+    #[test]
+    fn saving_where_it_cant_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, "x").unwrap();
+        let err = save_wav(&file.join("last.wav"), &[0]).unwrap_err();
+        assert!(matches!(err, AudioFileError::Write { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn integer_widths_are_rescaled() {
+        assert_eq!(scale_to_i16(i32::from(i16::MAX), 16), i16::MAX);
+        assert_eq!(scale_to_i16(0x7f_ffff, 24), i16::MAX);
+        assert_eq!(scale_to_i16(-0x80_0000, 24), i16::MIN);
+        assert_eq!(scale_to_i16(127, 8), 127 << 8);
+        assert_eq!(scale_to_i16(i32::MIN, 32), i16::MIN);
+    }
+
+    #[test]
+    fn missing_and_invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.wav");
+        assert!(matches!(
+            read_wav(&missing),
+            Err(AudioFileError::NotFound { .. })
+        ));
+
+        let not_wav = dir.path().join("notes.wav");
+        std::fs::write(&not_wav, b"definitely not RIFF data").unwrap();
+        assert!(matches!(
+            read_wav(&not_wav),
+            Err(AudioFileError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn too_long_is_refused_from_the_header() {
+        let dir = tempfile::tempdir().unwrap();
+        // 1 Hz makes a long duration out of few samples
+        let path = write_wav(&dir, spec(1, 1, 16, hound::SampleFormat::Int), |w| {
+            for _ in 0..=MAX_FILE_SECS {
+                w.write_sample(0i16).unwrap();
+            }
+        });
+        assert_eq!(
+            read_wav(&path),
+            Err(AudioFileError::TooLong {
+                duration_secs: MAX_FILE_SECS + 1,
+                max_secs: MAX_FILE_SECS
+            })
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Filters on the captured input, before it is resampled.
+//!
+//! `Settings.audio_filters` says which are on. For now there is one: a
+//! high-pass at `HIGH_PASS_HZ` for desk thumps, HVAC hum and other
+//! rumble well under the voice. It is a second-order Butterworth biquad
+//! (the RBJ cookbook's) whose coefficients come from the device's
+//! sample rate, with its state kept from one callback to the next.
+//!
+//! Before them, always on, a `DcBlocker` takes out the DC offset some
+//! microphones add: a constant bias counts in the RMS, so the meter
+//! never drops to zero and the VAD hears speech in silence.
+
+use serde::{Deserialize, Serialize};
+use std::f64::consts::{FRAC_1_SQRT_2, TAU};
+
+/// Cutoff of the high-pass: under the lowest voices' fundamentals.
+pub const HIGH_PASS_HZ: f64 = 100.0;
+
+/// Cutoff of the DC blocker: well under anything audible, so the
+/// voice is untouched.
+pub const DC_BLOCK_HZ: f64 = 10.0;
+
+/// The filters that are on. `set_audio_filters` payload. Frontend
+/// mirror: `AudioFilters`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioFilters {
+    #[serde(default)]
+    pub high_pass: bool,
+}
+
+/// A biquad in transposed direct form II.
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    s1: f64,
+    s2: f64,
+}
+
+impl Biquad {
+    /// A Butterworth high-pass at `cutoff` Hz for input at `sample_rate`.
+    pub fn high_pass(cutoff: f64, sample_rate: u32) -> Self {
+        let w0 = TAU * cutoff / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * FRAC_1_SQRT_2);
+        let a0 = 1.0 + alpha;
+        Self {
+            b0: (1.0 + cos) / 2.0 / a0,
+            b1: -(1.0 + cos) / a0,
+            b2: (1.0 + cos) / 2.0 / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha) / a0,
+            s1: 0.0,
+            s2: 0.0,
+        }
+    }
+
+    /// Filter `samples` in place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            let x = *sample as f64;
+            let y = self.b0 * x + self.s1;
+            self.s1 = self.b1 * x - self.a1 * y + self.s2;
+            self.s2 = self.b2 * x - self.a2 * y;
+            *sample = y as f32;
+        }
+    }
+}
+
+/// A one-pole high-pass at `DC_BLOCK_HZ`: `y[n] = x[n] - x[n-1] +
+/// pole·y[n-1]`.
+#[derive(Debug, Clone)]
+pub struct DcBlocker {
+    pole: f64,
+    /// `None` until the first sample, which primes it: the offset is
+    /// taken out from the start instead of decaying from a step.
+    last_in: Option<f64>,
+    last_out: f64,
+}
+
+impl DcBlocker {
+    /// A DC blocker for input at `sample_rate`.
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            pole: (-TAU * DC_BLOCK_HZ / sample_rate as f64).exp(),
+            last_in: None,
+            last_out: 0.0,
+        }
+    }
+
+    /// Filter `samples` in place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            let x = *sample as f64;
+            let y = x - self.last_in.unwrap_or(x) + self.pole * self.last_out;
+            self.last_in = Some(x);
+            self.last_out = y;
+            *sample = y as f32;
+        }
+    }
+
+    /// Start over, primed again by the next sample.
+    pub fn reset(&mut self) {
+        self.last_in = None;
+        self.last_out = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `secs` of a `freq` Hz sine at `rate`, peak 0.5.
+    fn tone(freq: f64, rate: u32, secs: f64) -> Vec<f32> {
+        (0..(secs * rate as f64) as usize)
+            .map(|i| (0.5 * (TAU * freq * i as f64 / rate as f64).sin()) as f32)
+            .collect()
+    }
+
+    /// Gain in dB of the high-pass on a `freq` Hz tone, past the
+    /// filter's settling.
+    fn response_db(freq: f64, rate: u32) -> f64 {
+        let input = tone(freq, rate, 1.0);
+        let mut output = input.clone();
+        let mut filter = Biquad::high_pass(HIGH_PASS_HZ, rate);
+        // In 10 ms callbacks, as the device delivers them
+        for chunk in output.chunks_mut(rate as usize / 100) {
+            filter.process(chunk);
+        }
+        let power = |s: &[f32]| s.iter().map(|&x| (x as f64).powi(2)).sum::<f64>();
+        let settled = rate as usize / 2;
+        10.0 * (power(&output[settled..]) / power(&input[settled..])).log10()
+    }
+
+    #[test]
+    fn rumble_is_attenuated() {
+        for rate in [16_000, 44_100, 48_000] {
+            // Butterworth: -10·log10(1 + (100/50)^4) ≈ -12.3 dB
+            let db = response_db(50.0, rate);
+            assert!((db + 12.3).abs() < 0.3, "{rate} Hz: {db}");
+            assert!(response_db(20.0, rate) < -27.0);
+        }
+    }
+
+    #[test]
+    fn voice_passes() {
+        for rate in [16_000, 44_100, 48_000] {
+            let db = response_db(1_000.0, rate);
+            assert!(db.abs() < 0.05, "{rate} Hz: {db}");
+            // -3 dB at the cutoff
+            assert!((response_db(HIGH_PASS_HZ, rate) + 3.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn dc_is_removed() {
+        let mut samples = vec![0.25f32; 16_000];
+        Biquad::high_pass(HIGH_PASS_HZ, 16_000).process(&mut samples);
+        assert!(samples[8_000..].iter().all(|s| s.abs() < 1e-4));
+    }
+
+    /// `input` through a DC blocker, in 10 ms callbacks.
+    fn dc_blocked(input: &[f32], rate: u32) -> Vec<f32> {
+        let mut output = input.to_vec();
+        let mut blocker = DcBlocker::new(rate);
+        for chunk in output.chunks_mut(rate as usize / 100) {
+            blocker.process(chunk);
+        }
+        output
+    }
+
+    #[test]
+    fn dc_offset_is_blocked_and_the_voice_kept() {
+        for rate in [16_000, 44_100, 48_000] {
+            let voice = tone(200.0, rate, 1.0);
+            // A mic biased a third of the way to full scale
+            let biased: Vec<f32> = voice.iter().map(|s| s + 0.3).collect();
+            let output = dc_blocked(&biased, rate);
+
+            let settled = &output[rate as usize / 2..];
+            let mean = settled.iter().map(|&s| s as f64).sum::<f64>() / settled.len() as f64;
+            assert!(mean.abs() < 1e-3, "{rate} Hz: mean {mean}");
+            let power = |s: &[f32]| s.iter().map(|&x| (x as f64).powi(2)).sum::<f64>();
+            let db = 10.0 * (power(settled) / power(&voice[rate as usize / 2..])).log10();
+            assert!(db.abs() < 0.05, "{rate} Hz: {db}");
+        }
+    }
+
+    #[test]
+    fn primed_by_the_first_sample() {
+        // No step to settle from: the offset is gone at once
+        let output = dc_blocked(&[0.3; 1_600], 16_000);
+        assert!(output.iter().all(|s| s.abs() < 1e-6));
+
+        let mut blocker = DcBlocker::new(16_000);
+        let mut samples = vec![0.3f32; 160];
+        blocker.process(&mut samples);
+        blocker.reset();
+        // A new offset, after a reset, is taken out at once too
+        let mut samples = vec![-0.2f32; 160];
+        blocker.process(&mut samples);
+        assert!(samples.iter().all(|s| s.abs() < 1e-6));
+    }
+
+    #[test]
+    fn settings_payload() {
+        assert_eq!(
+            serde_json::to_value(AudioFilters { high_pass: true }).unwrap(),
+            serde_json::json!({ "highPass": true })
+        );
+        // Filters added later are off in older settings
+        let filters: AudioFilters = serde_json::from_str("{}").unwrap();
+        assert_eq!(filters, AudioFilters::default());
+    }
+}
@@ -0,0 +1,66 @@
+//! Input gain, for microphones too quiet (or too hot) to transcribe.
+//!
+//! `Settings.gain` is in dB. The capture callbacks apply it to the
+//! float samples, before they become 16-bit, so a boost doesn't scale
+//! up rounding noise, and whatever reads the chunks (the VAD, the
+//! level meter, streaming partials) gets what Whisper will get.
+//! Samples pushed past full scale saturate, and `audio::clipping`
+//! reports them.
+
+pub const MIN_GAIN_DB: f32 = -20.0;
+pub const MAX_GAIN_DB: f32 = 30.0;
+
+/// The amplitude factor of `db`.
+pub fn factor(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// `sample` (full scale at ±1.0) times `factor`, as 16-bit; `true` if
+/// it had to be clipped to fit.
+pub fn apply(sample: f32, factor: f32) -> (i16, bool) {
+    let scaled = sample * factor * 32768.0;
+    // `as` saturates, and takes NaN to 0
+    let clipped = scaled > i16::MAX as f32 || scaled < i16::MIN as f32;
+    (scaled as i16, clipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decibels_scale_amplitude() {
+        assert_eq!(factor(0.0), 1.0);
+        assert!((factor(20.0) - 10.0).abs() < 1e-4);
+        assert!((factor(-20.0) - 0.1).abs() < 1e-6);
+        assert!((factor(6.0) - 1.995).abs() < 1e-3);
+        assert!((factor(MAX_GAIN_DB) - 31.62).abs() < 1e-2);
+    }
+
+    #[test]
+    fn unity_gain_is_lossless() {
+        for sample in [i16::MIN, -1, 0, 1, 12_345, i16::MAX] {
+            assert_eq!(apply(sample as f32 / 32768.0, 1.0), (sample, false));
+        }
+    }
+
+    #[test]
+    fn boost_saturates_at_the_bounds() {
+        let boost = factor(20.0);
+        assert_eq!(apply(0.01, boost).0, 3276);
+        assert_eq!(apply(-0.01, boost).0, -3276);
+        assert_eq!(apply(0.5, boost), (i16::MAX, true));
+        assert_eq!(apply(-0.5, boost), (i16::MIN, true));
+        // Full scale itself is one past i16::MAX
+        assert_eq!(apply(1.0, 1.0), (i16::MAX, true));
+        assert_eq!(apply(-1.0, 1.0), (i16::MIN, false));
+        assert_eq!(apply(f32::NAN, boost), (0, false));
+    }
+
+    #[test]
+    fn cut_never_clips() {
+        let cut = factor(MIN_GAIN_DB);
+        assert_eq!(apply(1.0, cut), (3276, false));
+        assert_eq!(apply(-1.0, cut), (-3276, false));
+    }
+}
@@ -0,0 +1,132 @@
+//! Level monitor: the input's level outside of a recording.
+//!
+//! `vad:level` is only sent while listening, so the settings window's
+//! microphone test starts a monitor instead. It rides on whatever
+//! stream is open (the pre-roll's, a recording's) or opens the
+//! microphone's, and the callbacks hand their samples to a
+//! `LevelMeter`: nothing is buffered or transcribed. Every
+//! `MONITOR_INTERVAL` the RMS of what came in goes out as
+//! `audio:monitor-level`.
+//!
+//! Pure: the caller passes the callback times in.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use super::vad::display_level;
+
+/// How often the level is reported: about 15 Hz, enough for a meter.
+pub const MONITOR_INTERVAL: Duration = Duration::from_millis(66);
+
+/// `audio:monitor-level` payload. Frontend mirror: `MonitorLevel`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorLevel {
+    /// RMS since the last report (0 to 1).
+    pub rms: f32,
+    /// The same on the level meter's scale (see `display_level`).
+    pub level: f32,
+}
+
+impl MonitorLevel {
+    pub fn new(rms: f32) -> Self {
+        Self {
+            rms,
+            level: display_level(rms),
+        }
+    }
+}
+
+/// Averages a stream's samples over each `MONITOR_INTERVAL`.
+#[derive(Debug, Clone, Default)]
+pub struct LevelMeter {
+    sum_squares: f64,
+    samples: u64,
+    /// When the interval under way began.
+    since: Option<Instant>,
+}
+
+impl LevelMeter {
+    /// Count a callback's `samples`, received at `now`. Returns the
+    /// interval's RMS (0 to 1) once it is over, and starts the next.
+    pub fn observe(&mut self, samples: &[i16], now: Instant) -> Option<f32> {
+        self.sum_squares += samples
+            .iter()
+            .map(|&s| f64::from(s) * f64::from(s))
+            .sum::<f64>();
+        self.samples += samples.len() as u64;
+        let since = *self.since.get_or_insert(now);
+        if now.saturating_duration_since(since) < MONITOR_INTERVAL || self.samples == 0 {
+            return None;
+        }
+        let rms = (self.sum_squares / self.samples as f64).sqrt() / 32768.0;
+        *self = Self {
+            since: Some(now),
+            ..Self::default()
+        };
+        Some(rms as f32)
+    }
+
+    /// Forget the interval under way: the next `observe` starts one.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 ms at 16 kHz of `level` (16-bit units), alternating sign.
+    fn callback(level: i16) -> Vec<i16> {
+        (0..160)
+            .map(|i| if i % 2 == 0 { level } else { -level })
+            .collect()
+    }
+
+    #[test]
+    fn reported_once_per_interval() {
+        let start = Instant::now();
+        let mut meter = LevelMeter::default();
+        let reports: Vec<f32> = (0..100)
+            .filter_map(|i| meter.observe(&callback(3277), start + Duration::from_millis(i * 10)))
+            .collect();
+        // 990 ms of callbacks, one report every 70 ms
+        assert_eq!(reports.len(), 14);
+        assert!(reports.iter().all(|&rms| (rms - 0.1).abs() < 0.001));
+    }
+
+    #[test]
+    fn each_report_covers_its_own_interval() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut meter = LevelMeter::default();
+        assert_eq!(meter.observe(&callback(16384), at(0)), None);
+        let loud = meter.observe(&callback(16384), at(70)).unwrap();
+        assert!((loud - 0.5).abs() < 0.001);
+        assert_eq!(meter.observe(&callback(0), at(100)), None);
+        assert_eq!(meter.observe(&callback(0), at(140)), Some(0.0));
+    }
+
+    #[test]
+    fn reset_drops_what_was_counted() {
+        let start = Instant::now();
+        let mut meter = LevelMeter::default();
+        meter.observe(&callback(16384), start);
+        meter.reset();
+        let later = start + Duration::from_secs(5);
+        assert_eq!(meter.observe(&callback(0), later), None);
+        assert_eq!(
+            meter.observe(&callback(0), later + MONITOR_INTERVAL),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn payload() {
+        assert_eq!(
+            serde_json::to_value(MonitorLevel::new(0.0)).unwrap(),
+            serde_json::json!({ "rms": 0.0, "level": 0.0 })
+        );
+    }
+}
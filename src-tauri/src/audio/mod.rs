@@ -1,5 +1,59 @@
+mod agc;
+mod calibration;
 mod capture;
+mod channels;
+mod clipping;
+mod devices;
+mod endpoint;
+mod file;
+mod filters;
+mod gain;
+mod level_monitor;
+#[cfg(target_os = "linux")]
+mod monitor;
+mod noise_floor;
+mod pauses;
+mod pre_roll;
+mod rate;
+mod retained;
+mod silent_input;
+mod silero;
+mod source;
+mod spill;
+mod utterance;
 mod vad;
 
-pub use capture::{AudioCapture, AudioChunk};
-pub use vad::VoiceActivityDetector;
+pub use calibration::{calibrate, NoiseCalibration, CALIBRATION_SECS};
+pub use capture::{AudioCapture, AudioCaptureError, AudioChunk, StreamFailure};
+pub use channels::ChannelMode;
+pub use clipping::{clipped_ratio, Clipping};
+pub use devices::{
+    find_input_device, input_channels, list_input_devices, AudioDevice, AudioDevices,
+    DeviceFallback,
+};
+pub use endpoint::{EndpointDetector, VoiceEnd, MAX_SPEECH_WAIT_MS};
+pub use file::{read_wav, save_wav};
+pub use filters::AudioFilters;
+pub use gain::{MAX_GAIN_DB, MIN_GAIN_DB};
+pub use level_monitor::MonitorLevel;
+pub use noise_floor::{DEFAULT_NOISE_FACTOR, MAX_NOISE_FACTOR, MIN_NOISE_FACTOR};
+pub use pauses::PauseMark;
+pub use pre_roll::{MAX_PRE_ROLL_MS, MIN_PRE_ROLL_MS};
+pub use retained::{
+    Eviction, RetainedAudio, RetainedId, RetainedUsage, RetentionTag, DEFAULT_RETAINED_AUDIO_MB,
+};
+pub use silent_input::{source_muted, SilentInput};
+pub use silero::{load as load_silero, SILERO_MODEL_FILE};
+pub use source::{
+    check_system_audio, list_capture_applications, CaptureApplication, CaptureSource,
+};
+pub use spill::{remove_stale_spills, SpillFailure, MAX_CAPTURE_SPILL_MB, MIN_CAPTURE_SPILL_MB};
+pub use utterance::{
+    Cut, Segmenter, Utterance, UtteranceAudio, UTTERANCE_LEAD_MS, UTTERANCE_QUEUE_LEN,
+};
+pub use vad::{
+    benchmark as benchmark_vad, is_silent_buffer, skip_reason, SkipReason, VadBackend,
+    VadBenchmark, VoiceActivityDetector, DEFAULT_MIN_SPEECH_MS, DEFAULT_SILENCE_TIMEOUT_MS,
+    DEFAULT_SPEECH_THRESHOLD, MAX_MIN_SPEECH_MS, MAX_VAD_THRESHOLD, MIN_RECORDING_MS,
+    MIN_SILENCE_TIMEOUT_MS, MIN_VAD_THRESHOLD,
+};
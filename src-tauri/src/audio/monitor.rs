@@ -0,0 +1,247 @@
+//! Linux system audio with nothing to set up: the default sink's
+//! monitor, read through the PulseAudio ALSA plugin's `pulse` PCM named
+//! with a device argument (`pulse:DEVICE=<sink>.monitor`, see
+//! `source::monitor_pcm`).
+//!
+//! One application's stream is a sink input, whose monitor the plugin
+//! can't name: `parec --monitor-stream` records it, as raw PCM on its
+//! standard output.
+//!
+//! cpal only opens the PCMs the ALSA configuration lists, so these are
+//! read here instead, on a thread of their own, and handed to the same
+//! sink a cpal callback feeds.
+
+use std::fmt::Display;
+use std::io::Read;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use alsa::pcm::{Access, Format, HwParams, PCM};
+use alsa::{Direction, ValueOr};
+
+use super::capture::AudioCaptureError;
+use super::source::CaptureApplication;
+
+/// Rate and channels asked of the plugin (or `parec`), which converts
+/// to whatever it is asked.
+const RATE: u32 = 48_000;
+const CHANNELS: u32 = 2;
+/// Frames read at a time: 10 ms at `RATE`.
+const PERIOD_FRAMES: usize = 480;
+/// How long the reader waits for data before looking at the stop flag.
+const WAIT_MS: u32 = 100;
+
+/// The monitor's PCM, opened and configured, not read yet.
+pub struct MonitorPcm {
+    reader: Reader,
+    name: String,
+    rate: u32,
+    channels: u16,
+}
+
+impl MonitorPcm {
+    /// Open the capture PCM `name` as interleaved 16-bit.
+    pub fn open(name: &str) -> Result<Self, AudioCaptureError> {
+        let error = |e: alsa::Error| AudioCaptureError::DeviceError(format!("{name}: {e}"));
+        // Non-blocking, so the reader can stop while nothing plays
+        let pcm = PCM::new(name, Direction::Capture, true).map_err(error)?;
+        let (rate, channels) = {
+            let params = HwParams::any(&pcm).map_err(error)?;
+            params.set_access(Access::RWInterleaved).map_err(error)?;
+            params.set_format(Format::s16()).map_err(error)?;
+            params.set_channels_near(CHANNELS).map_err(error)?;
+            params
+                .set_rate_near(RATE, ValueOr::Nearest)
+                .map_err(error)?;
+            pcm.hw_params(&params).map_err(error)?;
+            let current = pcm.hw_params_current().map_err(error)?;
+            (
+                current.get_rate().map_err(error)?,
+                current.get_channels().map_err(error)?,
+            )
+        };
+        Ok(Self {
+            reader: Reader::Pcm(pcm),
+            name: name.to_string(),
+            rate,
+            channels: channels as u16,
+        })
+    }
+
+    /// Start `parec` on `application`'s stream, as interleaved 16-bit.
+    pub fn open_application(application: &CaptureApplication) -> Result<Self, AudioCaptureError> {
+        let mut child = Command::new("parec")
+            .arg(format!("--monitor-stream={}", application.id))
+            .args(["--raw", "--format=s16le"])
+            .arg(format!("--rate={RATE}"))
+            .arg(format!("--channels={CHANNELS}"))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| AudioCaptureError::NotSupported(format!("parec: {e}")))?;
+        let Some(stdout) = child.stdout.take() else {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(AudioCaptureError::StreamError(
+                "parec: no output".to_string(),
+            ));
+        };
+        Ok(Self {
+            reader: Reader::Parec(child, stdout),
+            name: application.name.clone(),
+            rate: RATE,
+            channels: CHANNELS as u16,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Start reading: each period goes to `read`, interleaved. A read
+    /// that can't be recovered goes to `fail` as a lost device, and
+    /// ends the stream.
+    pub fn start(
+        self,
+        read: impl FnMut(&[i16]) + Send + 'static,
+        fail: impl FnMut(cpal::StreamError) + Send + 'static,
+    ) -> Result<MonitorStream, AudioCaptureError> {
+        let MonitorPcm {
+            reader,
+            name,
+            channels,
+            ..
+        } = self;
+        match reader {
+            Reader::Pcm(pcm) => start_pcm(pcm, name, channels, read, fail),
+            Reader::Parec(child, stdout) => start_parec(child, stdout, name, channels, read, fail),
+        }
+    }
+}
+
+/// What a `MonitorPcm` reads.
+enum Reader {
+    Pcm(PCM),
+    /// `parec`, and its standard output.
+    Parec(Child, ChildStdout),
+}
+
+fn start_pcm(
+    pcm: PCM,
+    name: String,
+    channels: u16,
+    mut read: impl FnMut(&[i16]) + Send + 'static,
+    mut fail: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<MonitorStream, AudioCaptureError> {
+    pcm.start()
+        .map_err(|e| AudioCaptureError::StreamError(format!("{name}: {e}")))?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopping = Arc::clone(&stop);
+    let thread = std::thread::Builder::new()
+        .name("system-audio".into())
+        .spawn(move || {
+            let io = match pcm.io_i16() {
+                Ok(io) => io,
+                Err(e) => return fail(lost(&name, e)),
+            };
+            let mut period = vec![0i16; PERIOD_FRAMES * channels as usize];
+            while !stopping.load(Ordering::Acquire) {
+                let result = match pcm.wait(Some(WAIT_MS)) {
+                    Ok(false) => continue,
+                    Ok(true) => io.readi(&mut period),
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(frames) => read(&period[..frames * channels as usize]),
+                    Err(e) if e.errno() == libc::EAGAIN => {}
+                    // Overruns and the like
+                    Err(e) => {
+                        if let Err(e) = pcm.try_recover(e, true) {
+                            return fail(lost(&name, e));
+                        }
+                    }
+                }
+            }
+            let _ = pcm.drop();
+        })
+        .map_err(|e| AudioCaptureError::StreamError(e.to_string()))?;
+    Ok(MonitorStream {
+        stop,
+        thread: Some(thread),
+        child: None,
+    })
+}
+
+fn start_parec(
+    child: Child,
+    mut stdout: ChildStdout,
+    name: String,
+    channels: u16,
+    mut read: impl FnMut(&[i16]) + Send + 'static,
+    mut fail: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<MonitorStream, AudioCaptureError> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopping = Arc::clone(&stop);
+    let thread = std::thread::Builder::new()
+        .name("system-audio".into())
+        .spawn(move || {
+            let mut bytes = vec![0u8; PERIOD_FRAMES * channels as usize * 2];
+            let mut period = vec![0i16; PERIOD_FRAMES * channels as usize];
+            // Ends when `parec` does: stopped, or its stream closed
+            while stdout.read_exact(&mut bytes).is_ok() {
+                for (sample, pair) in period.iter_mut().zip(bytes.chunks_exact(2)) {
+                    *sample = i16::from_le_bytes([pair[0], pair[1]]);
+                }
+                read(&period);
+            }
+            if !stopping.load(Ordering::Acquire) {
+                fail(lost(&name, "the application's stream ended"));
+            }
+        })
+        .map_err(|e| AudioCaptureError::StreamError(e.to_string()))?;
+    Ok(MonitorStream {
+        stop,
+        thread: Some(thread),
+        child: Some(child),
+    })
+}
+
+fn lost(name: &str, e: impl Display) -> cpal::StreamError {
+    tracing::error!("System audio read from {} failed: {}", name, e);
+    cpal::StreamError::DeviceNotAvailable
+}
+
+/// The monitor being read. Dropping it stops the reader (and `parec`)
+/// and waits for it, so no period lands after.
+pub struct MonitorStream {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    child: Option<Child>,
+}
+
+impl Drop for MonitorStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        // Its output closing ends the reader's read
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Some(mut child) = self.child.take() {
+            let _ = child.wait();
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Adaptive VAD threshold: one that follows the room's noise.
+//!
+//! A threshold fitted to a quiet office (`calibrate_noise_floor`) takes
+//! a café's chatter for speech. In adaptive mode the VAD hands each
+//! chunk's RMS to a `NoiseFloor`, which keeps a moving average of the
+//! silence, `NOISE_ADAPTATION_MS` its time constant, and sets the
+//! threshold at that times `Settings.noise_factor`, within the
+//! calibration's bounds.
+//!
+//! Only silence is averaged: while the VAD is in speech the floor
+//! holds, however long the monologue, and nothing is learned from it.
+//! Out of speech, a level that stays over the threshold for
+//! `NOISE_RESEED_MS` is the room's, and the floor jumps to the lowest
+//! of it. A caller whose speech decision rests on this threshold alone
+//! (the RMS VAD) is in speech whenever the level is over it: for it a
+//! louder room reads as speech, and the floor holds until the room is
+//! quiet again.
+//!
+//! Pure: fed RMS values and chunk lengths.
+
+use super::calibration::{MAX_SPEECH_THRESHOLD, MIN_SPEECH_THRESHOLD, NOISE_MARGIN};
+
+/// The average's time constant: a change of room is 95% followed in
+/// three of them.
+pub const NOISE_ADAPTATION_MS: u64 = 1000;
+
+/// How long a level stays over the threshold, out of speech, before it
+/// counts as noise.
+pub const NOISE_RESEED_MS: u64 = 5000;
+
+/// The threshold over the floor, unless `Settings.noise_factor` says
+/// otherwise: the calibration's margin.
+pub const DEFAULT_NOISE_FACTOR: f32 = NOISE_MARGIN;
+
+/// Bounds `set_adaptive_threshold` accepts for the factor: about +3.5
+/// dB, under which noise's own swings count as speech, and +20 dB.
+pub const MIN_NOISE_FACTOR: f32 = 1.5;
+pub const MAX_NOISE_FACTOR: f32 = 10.0;
+
+/// Tracks a recording's noise floor, and the threshold over it.
+#[derive(Debug, Clone)]
+pub struct NoiseFloor {
+    factor: f32,
+    /// The silence's average RMS, once some was heard.
+    floor: Option<f32>,
+    /// Samples over the threshold in a row.
+    loud_samples: u64,
+    /// The lowest RMS among them.
+    loud_lowest: f32,
+}
+
+impl NoiseFloor {
+    pub fn new(factor: f32) -> Self {
+        Self {
+            factor,
+            floor: None,
+            loud_samples: 0,
+            loud_lowest: f32::INFINITY,
+        }
+    }
+
+    pub fn floor(&self) -> Option<f32> {
+        self.floor
+    }
+
+    /// The floor times the factor, in bounds; None before any silence.
+    pub fn threshold(&self) -> Option<f32> {
+        self.floor
+            .map(|floor| (floor * self.factor).clamp(MIN_SPEECH_THRESHOLD, MAX_SPEECH_THRESHOLD))
+    }
+
+    /// Set the factor, from the next chunk. The floor stands.
+    pub fn set_factor(&mut self, factor: f32) {
+        self.factor = factor;
+    }
+
+    /// Count a chunk of `samples` at `sample_rate` Hz, of RMS `rms`.
+    /// `in_speech`: the VAD is in speech, the hangover included.
+    pub fn observe(&mut self, rms: f32, samples: usize, sample_rate: u32, in_speech: bool) {
+        let rate = u64::from(sample_rate.max(1));
+        if in_speech {
+            self.loud_samples = 0;
+            self.loud_lowest = f32::INFINITY;
+            return;
+        }
+        if self.threshold().is_some_and(|threshold| rms > threshold) {
+            self.loud_samples += samples as u64;
+            self.loud_lowest = self.loud_lowest.min(rms);
+            if self.loud_samples * 1000 >= NOISE_RESEED_MS * rate {
+                self.floor = Some(self.loud_lowest);
+                self.loud_samples = 0;
+                self.loud_lowest = f32::INFINITY;
+            }
+            return;
+        }
+        self.loud_samples = 0;
+        self.loud_lowest = f32::INFINITY;
+        let elapsed_ms = samples as f64 * 1000.0 / rate as f64;
+        let weight = 1.0 - (-elapsed_ms / NOISE_ADAPTATION_MS as f64).exp();
+        self.floor = Some(match self.floor {
+            Some(floor) => floor + (rms - floor) * weight as f32,
+            None => rms,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    /// `ms` of 10 ms chunks at `rms`.
+    fn feed(noise: &mut NoiseFloor, rms: f32, ms: u64, in_speech: bool) {
+        for _ in 0..ms / 10 {
+            noise.observe(rms, 160, RATE, in_speech);
+        }
+    }
+
+    fn close(actual: Option<f32>, expected: f32) -> bool {
+        actual.is_some_and(|actual| (actual - expected).abs() <= expected * 0.05)
+    }
+
+    #[test]
+    fn follows_a_quieter_room() {
+        let mut noise = NoiseFloor::new(3.0);
+        assert_eq!(noise.threshold(), None);
+        feed(&mut noise, 0.02, 1000, false);
+        assert!(close(noise.threshold(), 0.06));
+        feed(&mut noise, 0.003, 2000, false);
+        assert!(!close(noise.floor(), 0.003));
+        feed(&mut noise, 0.003, 3000, false);
+        assert!(close(noise.floor(), 0.003));
+        assert!(close(noise.threshold(), 0.009));
+    }
+
+    #[test]
+    fn follows_a_louder_room() {
+        let mut noise = NoiseFloor::new(3.0);
+        feed(&mut noise, 0.003, 5000, false);
+        // The café is over the office's threshold, and not speech
+        feed(&mut noise, 0.02, 4990, false);
+        assert!(close(noise.threshold(), 0.009));
+        feed(&mut noise, 0.02, 10, false);
+        assert!(close(noise.threshold(), 0.06));
+    }
+
+    #[test]
+    fn a_monologue_without_a_pause_is_not_noise() {
+        let mut noise = NoiseFloor::new(3.0);
+        feed(&mut noise, 0.003, 5000, false);
+        // A minute of words run together, never under the threshold
+        feed(&mut noise, 0.1, 60_000, true);
+        assert!(close(noise.threshold(), 0.009));
+        // Nor does its end count towards a reseed
+        feed(&mut noise, 0.1, 4990, false);
+        assert!(close(noise.threshold(), 0.009));
+    }
+
+    #[test]
+    fn a_monologue_is_not_noise() {
+        let mut noise = NoiseFloor::new(3.0);
+        feed(&mut noise, 0.003, 5000, false);
+        // A minute of words, 300 ms each, and the gaps between them
+        for _ in 0..200 {
+            feed(&mut noise, 0.1, 300, true);
+            feed(&mut noise, 0.004, 20, true);
+        }
+        assert!(close(noise.threshold(), 0.009));
+    }
+
+    #[test]
+    fn bounded() {
+        let mut noise = NoiseFloor::new(3.0);
+        feed(&mut noise, 0.0, 1000, false);
+        assert_eq!(noise.threshold(), Some(MIN_SPEECH_THRESHOLD));
+        noise.set_factor(MAX_NOISE_FACTOR);
+        feed(&mut noise, 0.2, 5000, false);
+        assert_eq!(noise.threshold(), Some(MAX_SPEECH_THRESHOLD));
+    }
+}
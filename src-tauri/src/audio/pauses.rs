@@ -0,0 +1,117 @@
+//! Pauses in a recording.
+//!
+//! `pause_listen` keeps the stream open but stops adding to the buffer,
+//! so the audio transcribed is the recording with its pauses cut out,
+//! and segment times count recorded audio only. `PauseLog` keeps where
+//! each pause fell in that audio and how long it lasted, for
+//! `transcript:final`: a segment at `t` happened `t` plus the pauses
+//! before it into the recording.
+//!
+//! Pure: the caller passes the times in.
+
+use serde::Serialize;
+use std::time::Instant;
+
+/// A pause, in `transcript:final`. Frontend mirror: `PauseMark`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseMark {
+    /// Where it fell in the recorded audio, in ms.
+    pub at_ms: u64,
+    /// How long it lasted.
+    pub paused_ms: u64,
+}
+
+/// A recording's pauses.
+#[derive(Debug, Clone, Default)]
+pub struct PauseLog {
+    marks: Vec<PauseMark>,
+    /// The pause under way: where, and since when.
+    open: Option<(u64, Instant)>,
+}
+
+impl PauseLog {
+    /// Pause at `at_ms` into the recorded audio. `false` if paused
+    /// already.
+    pub fn pause(&mut self, at_ms: u64, now: Instant) -> bool {
+        if self.open.is_some() {
+            return false;
+        }
+        self.open = Some((at_ms, now));
+        true
+    }
+
+    /// End the pause under way, if any.
+    pub fn resume(&mut self, now: Instant) -> bool {
+        let Some((at_ms, since)) = self.open.take() else {
+            return false;
+        };
+        self.marks.push(PauseMark {
+            at_ms,
+            paused_ms: now.saturating_duration_since(since).as_millis() as u64,
+        });
+        true
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// The pauses so far, ending the one under way at `now`; the log
+    /// starts over.
+    pub fn finish(&mut self, now: Instant) -> Vec<PauseMark> {
+        self.resume(now);
+        std::mem::take(&mut self.marks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn pauses_are_marked_in_recorded_audio() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut log = PauseLog::default();
+        assert!(!log.resume(at(0)));
+
+        // 2 s recorded, 5 s paused
+        assert!(log.pause(2000, at(2000)));
+        assert!(log.is_paused());
+        assert!(!log.pause(2000, at(3000)));
+        assert!(log.resume(at(7000)));
+        assert!(!log.is_paused());
+        // 1 s more, then paused until the stop
+        assert!(log.pause(3000, at(8000)));
+
+        assert_eq!(
+            log.finish(at(8500)),
+            [
+                PauseMark {
+                    at_ms: 2000,
+                    paused_ms: 5000
+                },
+                PauseMark {
+                    at_ms: 3000,
+                    paused_ms: 500
+                }
+            ]
+        );
+        assert!(!log.is_paused());
+        assert!(log.finish(at(9000)).is_empty());
+    }
+
+    #[test]
+    fn payload() {
+        assert_eq!(
+            serde_json::to_value(PauseMark {
+                at_ms: 1200,
+                paused_ms: 300
+            })
+            .unwrap(),
+            serde_json::json!({ "atMs": 1200, "pausedMs": 300 })
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Pre-roll: the audio from just before a recording starts.
+//!
+//! People start talking a moment before the shortcut lands, and the
+//! first word was lost. With `Settings.pre_roll_ms` set, the input
+//! stream stays open between recordings and `PreRoll` keeps the last
+//! stretch of it, at the target rate; `AudioCapture::start` puts it in
+//! front of the recording. Only while the microphone permission is
+//! granted (see `commands::permissions`), and the usage log shows the
+//! time the stream was open for it under the `pre-roll` trigger.
+
+use std::collections::VecDeque;
+
+pub const MIN_PRE_ROLL_MS: u32 = 500;
+pub const MAX_PRE_ROLL_MS: u32 = 3000;
+
+/// The last `capacity` samples pushed.
+#[derive(Debug, Clone, Default)]
+pub struct PreRoll {
+    samples: VecDeque<i16>,
+    capacity: usize,
+}
+
+impl PreRoll {
+    /// 0 keeps nothing.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Keep `capacity` samples from now on, dropping the oldest ones
+    /// that no longer fit.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    pub fn push(&mut self, samples: &[i16]) {
+        // Only the tail of a callback longer than the whole pre-roll
+        let tail = &samples[samples.len().saturating_sub(self.capacity)..];
+        self.samples.extend(tail);
+        self.trim();
+    }
+
+    /// What is kept, oldest first; empty afterwards.
+    pub fn take(&mut self) -> Vec<i16> {
+        self.samples.drain(..).collect()
+    }
+
+    fn trim(&mut self) {
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+}
+
+/// Samples in `ms` of pre-roll at `sample_rate`.
+pub fn capacity_for(ms: u32, sample_rate: u32) -> usize {
+    (u64::from(ms) * u64::from(sample_rate) / 1000) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_most_recent_samples() {
+        let mut pre_roll = PreRoll::default();
+        pre_roll.push(&[1, 2, 3]);
+        assert!(pre_roll.take().is_empty());
+
+        pre_roll.set_capacity(4);
+        pre_roll.push(&[1, 2, 3]);
+        pre_roll.push(&[4, 5]);
+        assert_eq!(pre_roll.take(), [2, 3, 4, 5]);
+        assert!(pre_roll.take().is_empty());
+
+        // A callback longer than the whole pre-roll
+        pre_roll.push(&[1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(pre_roll.take(), [4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn shrinking_drops_the_oldest() {
+        let mut pre_roll = PreRoll::default();
+        pre_roll.set_capacity(5);
+        pre_roll.push(&[1, 2, 3, 4, 5]);
+        pre_roll.set_capacity(2);
+        assert_eq!(pre_roll.take(), [4, 5]);
+        pre_roll.push(&[6]);
+        pre_roll.set_capacity(0);
+        assert!(pre_roll.take().is_empty());
+    }
+
+    #[test]
+    fn capacity_follows_the_rate() {
+        assert_eq!(capacity_for(MIN_PRE_ROLL_MS, 16_000), 8_000);
+        assert_eq!(capacity_for(MAX_PRE_ROLL_MS, 16_000), 48_000);
+        assert_eq!(capacity_for(0, 16_000), 0);
+    }
+}
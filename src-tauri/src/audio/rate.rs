@@ -0,0 +1,181 @@
+//! Input sample-rate sanity check.
+//!
+//! Some Bluetooth headsets report 48 kHz but deliver 16 kHz (or switch
+//! rates when the HFP profile kicks in). The resample ratio is then
+//! wrong and speech comes out chipmunked or slowed. `RateEstimator`
+//! counts the frames the device actually delivers per wall-clock second
+//! over the first seconds of a capture and, when that is more than
+//! `TOLERANCE` away from the reported rate, returns the measured one.
+//!
+//! Pure: the caller passes the callback times in, so it is tested with
+//! synthetic timings.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Callbacks in this first stretch are ignored: streams often deliver
+/// a burst of buffered audio right after opening.
+pub const WARMUP: Duration = Duration::from_millis(500);
+/// How long frames are counted after the warm-up.
+pub const MEASURE: Duration = Duration::from_secs(3);
+/// Relative deviation from the reported rate that counts as a mismatch.
+pub const TOLERANCE: f64 = 0.10;
+/// A measurement within this of a standard rate is snapped to it.
+const SNAP_TOLERANCE: f64 = 0.05;
+const STANDARD_RATES: &[u32] = &[8000, 11025, 16000, 22050, 24000, 32000, 44100, 48000];
+
+/// `capture:rate-corrected` payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateCorrection {
+    pub reported: u32,
+    pub measured: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateEstimator {
+    reported: u32,
+    opened: Option<Instant>,
+    counting_since: Option<Instant>,
+    frames: u64,
+    done: bool,
+}
+
+impl RateEstimator {
+    pub fn new(reported: u32) -> Self {
+        Self {
+            reported,
+            opened: None,
+            counting_since: None,
+            frames: 0,
+            done: false,
+        }
+    }
+
+    /// Feed one callback of `frames` frames, received at `now`. Returns
+    /// a correction once, at the end of the measurement, if the device
+    /// is off; after that the estimator stays quiet.
+    pub fn observe(&mut self, frames: usize, now: Instant) -> Option<RateCorrection> {
+        if self.done {
+            return None;
+        }
+        let opened = *self.opened.get_or_insert(now);
+        if now.duration_since(opened) < WARMUP {
+            return None;
+        }
+        // The frames of the callback that starts the count were captured
+        // before it, so they are not counted.
+        let Some(since) = self.counting_since else {
+            self.counting_since = Some(now);
+            return None;
+        };
+        self.frames += frames as u64;
+        let elapsed = now.duration_since(since);
+        if elapsed < MEASURE {
+            return None;
+        }
+        self.done = true;
+        let measured = self.frames as f64 / elapsed.as_secs_f64();
+        let deviation = (measured - f64::from(self.reported)).abs() / f64::from(self.reported);
+        tracing::debug!(
+            "Input rate: reported {} Hz, measured {:.0} Hz",
+            self.reported,
+            measured
+        );
+        (deviation > TOLERANCE).then(|| RateCorrection {
+            reported: self.reported,
+            measured: snap(measured),
+        })
+    }
+
+    /// Whether the measurement is over: no correction can come any more.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// The nearest standard rate if close enough, else the measurement
+/// rounded to 100 Hz.
+fn snap(measured: f64) -> u32 {
+    STANDARD_RATES
+        .iter()
+        .copied()
+        .find(|&rate| (measured - f64::from(rate)).abs() / f64::from(rate) <= SNAP_TOLERANCE)
+        .unwrap_or_else(|| ((measured / 100.0).round() * 100.0) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `secs` of callbacks every `period_ms`, each carrying the
+    /// frames a device running at `actual` Hz delivers in that time.
+    fn simulate(reported: u32, actual: u32, period_ms: u64, secs: u64) -> Vec<RateCorrection> {
+        let mut estimator = RateEstimator::new(reported);
+        let start = Instant::now();
+        let frames = (u64::from(actual) * period_ms / 1000) as usize;
+        (0..secs * 1000 / period_ms)
+            .filter_map(|i| estimator.observe(frames, start + Duration::from_millis(i * period_ms)))
+            .collect()
+    }
+
+    #[test]
+    fn honest_device_is_left_alone() {
+        assert!(simulate(48000, 48000, 10, 10).is_empty());
+        // Within tolerance (5% off) too
+        assert!(simulate(48000, 45600, 10, 10).is_empty());
+    }
+
+    #[test]
+    fn misreporting_headset_is_corrected_once() {
+        assert_eq!(
+            simulate(48000, 16000, 10, 10),
+            vec![RateCorrection {
+                reported: 48000,
+                measured: 16000
+            }]
+        );
+        assert_eq!(simulate(16000, 44100, 20, 10)[0].measured, 44100);
+    }
+
+    #[test]
+    fn correction_waits_for_the_measurement() {
+        // Warm-up plus measurement not yet over
+        assert!(simulate(48000, 16000, 10, 3).is_empty());
+    }
+
+    #[test]
+    fn done_once_measured() {
+        let mut estimator = RateEstimator::new(16000);
+        let start = Instant::now();
+        for i in 0..300 {
+            estimator.observe(160, start + Duration::from_millis(i * 10));
+        }
+        assert!(!estimator.is_done());
+        for i in 300..500 {
+            estimator.observe(160, start + Duration::from_millis(i * 10));
+        }
+        assert!(estimator.is_done());
+    }
+
+    #[test]
+    fn startup_burst_is_ignored() {
+        let mut estimator = RateEstimator::new(48000);
+        let start = Instant::now();
+        // A huge burst right after opening, then a steady 48 kHz
+        assert_eq!(estimator.observe(480_000, start), None);
+        let mut corrections = Vec::new();
+        for i in 1..500 {
+            let now = start + Duration::from_millis(i * 10);
+            corrections.extend(estimator.observe(480, now));
+        }
+        assert!(corrections.is_empty());
+    }
+
+    #[test]
+    fn odd_rates_are_rounded() {
+        assert_eq!(snap(15_900.0), 16000);
+        assert_eq!(snap(43_000.0), 44100);
+        assert_eq!(snap(36_040.0), 36000);
+    }
+}
@@ -0,0 +1,291 @@
+//! Recordings kept in memory once transcribed.
+//!
+//! Features that come back to a recording after its transcription
+//! (`retranscribe_last`, `detect_language` reusing it) keep it through
+//! `RetainedAudio` instead of holding the samples themselves: a
+//! recording is one `Arc<[i16]>` however many features hold it, each
+//! one a `RetentionTag`, and the total stays within a budget
+//! (`Settings.retained_audio_mb`; an hour of 16 kHz audio is 110 MB).
+//! Over budget, whole recordings are dropped, the largest first and the
+//! oldest among equals, the one just kept last; each drop is an
+//! `Eviction` the app reports as `memory:evicted`.
+
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// `Settings.retained_audio_mb` default: a bit over two hours.
+pub const DEFAULT_RETAINED_AUDIO_MB: u32 = 256;
+const MB: u64 = 1024 * 1024;
+const BYTES_PER_SAMPLE: u64 = std::mem::size_of::<i16>() as u64;
+const SAMPLE_RATE: u64 = 16_000;
+
+/// A feature keeping a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RetentionTag {
+    /// The last recording, for `retranscribe_last` (and
+    /// `detect_language`, which reuses it).
+    Retranscribe,
+}
+
+/// A recording in `RetainedAudio`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(transparent)]
+pub struct RetainedId(u64);
+
+struct Entry {
+    id: RetainedId,
+    samples: Arc<[i16]>,
+    tags: BTreeSet<RetentionTag>,
+}
+
+impl Entry {
+    fn bytes(&self) -> u64 {
+        self.samples.len() as u64 * BYTES_PER_SAMPLE
+    }
+}
+
+/// A recording dropped to stay within the budget. `memory:evicted`
+/// payload. Frontend mirror: `AudioEviction`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Eviction {
+    pub id: RetainedId,
+    pub bytes: u64,
+    /// The features that lost it.
+    pub tags: Vec<RetentionTag>,
+    pub budget_bytes: u64,
+}
+
+/// One kept recording, in `RetainedUsage`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetainedRecording {
+    pub id: RetainedId,
+    pub bytes: u64,
+    pub duration_ms: u64,
+    pub tags: Vec<RetentionTag>,
+}
+
+/// What `RetainedAudio` holds against its budget.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetainedUsage {
+    pub used_bytes: u64,
+    pub budget_bytes: u64,
+    /// Oldest first.
+    pub recordings: Vec<RetainedRecording>,
+}
+
+/// The recordings kept in memory, within a budget.
+pub struct RetainedAudio {
+    budget_bytes: u64,
+    next_id: u64,
+    /// Oldest first.
+    entries: Vec<Entry>,
+}
+
+impl RetainedAudio {
+    pub fn new(budget_mb: u32) -> Self {
+        Self {
+            budget_bytes: u64::from(budget_mb) * MB,
+            next_id: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Keep `samples` for `tag`. A recording already kept for another
+    /// feature is shared, not counted twice. Returns its id and the
+    /// recordings evicted to make room, this one included when it alone
+    /// is over the budget.
+    pub fn retain(
+        &mut self,
+        samples: &Arc<[i16]>,
+        tag: RetentionTag,
+    ) -> (RetainedId, Vec<Eviction>) {
+        let id = match self
+            .entries
+            .iter_mut()
+            .find(|entry| Arc::ptr_eq(&entry.samples, samples))
+        {
+            Some(entry) => {
+                entry.tags.insert(tag);
+                entry.id
+            }
+            None => {
+                let id = RetainedId(self.next_id);
+                self.next_id += 1;
+                self.entries.push(Entry {
+                    id,
+                    samples: Arc::clone(samples),
+                    tags: BTreeSet::from([tag]),
+                });
+                id
+            }
+        };
+        (id, self.enforce(Some(id)))
+    }
+
+    /// The samples of `id`; `None` once released or evicted.
+    pub fn get(&self, id: RetainedId) -> Option<Arc<[i16]>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| Arc::clone(&entry.samples))
+    }
+
+    /// `tag` no longer needs `id`; the recording goes with its last tag.
+    pub fn release(&mut self, id: RetainedId, tag: RetentionTag) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == id) {
+            entry.tags.remove(&tag);
+        }
+        self.entries.retain(|entry| !entry.tags.is_empty());
+    }
+
+    /// Change the budget, evicting what no longer fits.
+    pub fn set_budget(&mut self, budget_mb: u32) -> Vec<Eviction> {
+        self.budget_bytes = u64::from(budget_mb) * MB;
+        self.enforce(None)
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.entries.iter().map(Entry::bytes).sum()
+    }
+
+    pub fn usage(&self) -> RetainedUsage {
+        RetainedUsage {
+            used_bytes: self.used_bytes(),
+            budget_bytes: self.budget_bytes,
+            recordings: self
+                .entries
+                .iter()
+                .map(|entry| RetainedRecording {
+                    id: entry.id,
+                    bytes: entry.bytes(),
+                    duration_ms: entry.samples.len() as u64 * 1000 / SAMPLE_RATE,
+                    tags: entry.tags.iter().copied().collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Evict until within the budget: the largest first, the oldest of
+    /// equals, `newest` only once nothing else is left.
+    fn enforce(&mut self, newest: Option<RetainedId>) -> Vec<Eviction> {
+        let mut evicted = Vec::new();
+        while self.used_bytes() > self.budget_bytes {
+            let victim = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| Some(entry.id) != newest)
+                // `max_by_key` keeps the last of equals: reverse the age
+                .max_by_key(|(_, entry)| (entry.bytes(), std::cmp::Reverse(entry.id.0)))
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            let entry = self.entries.remove(victim);
+            evicted.push(Eviction {
+                id: entry.id,
+                bytes: entry.bytes(),
+                tags: entry.tags.into_iter().collect(),
+                budget_bytes: self.budget_bytes,
+            });
+        }
+        evicted
+    }
+}
+
+impl Default for RetainedAudio {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETAINED_AUDIO_MB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A recording of `mb` MB.
+    fn recording(mb: u64) -> Arc<[i16]> {
+        Arc::from(vec![0i16; (mb * MB / BYTES_PER_SAMPLE) as usize])
+    }
+
+    fn evicted_ids(evictions: &[Eviction]) -> Vec<RetainedId> {
+        evictions.iter().map(|eviction| eviction.id).collect()
+    }
+
+    #[test]
+    fn shared_recordings_count_once() {
+        let mut retained = RetainedAudio::new(4);
+        let samples = recording(3);
+        let (id, evicted) = retained.retain(&samples, RetentionTag::Retranscribe);
+        assert!(evicted.is_empty());
+        // Kept again: same id, no extra bytes
+        let (again, _) = retained.retain(&samples, RetentionTag::Retranscribe);
+        assert_eq!(again, id);
+        assert_eq!(retained.used_bytes(), 3 * MB);
+        assert!(Arc::ptr_eq(&retained.get(id).unwrap(), &samples));
+
+        retained.release(id, RetentionTag::Retranscribe);
+        assert_eq!(retained.get(id), None);
+        assert_eq!(retained.used_bytes(), 0);
+    }
+
+    #[test]
+    fn evicts_largest_then_oldest_and_the_newest_last() {
+        let mut retained = RetainedAudio::new(10);
+        let (a, _) = retained.retain(&recording(2), RetentionTag::Retranscribe);
+        let (b, _) = retained.retain(&recording(4), RetentionTag::Retranscribe);
+        let (c, _) = retained.retain(&recording(2), RetentionTag::Retranscribe);
+        assert_eq!(retained.used_bytes(), 8 * MB);
+
+        // 8 + 3 > 10: the largest goes
+        let (d, evicted) = retained.retain(&recording(3), RetentionTag::Retranscribe);
+        assert_eq!(evicted_ids(&evicted), [b]);
+        assert_eq!(
+            evicted[0],
+            Eviction {
+                id: b,
+                bytes: 4 * MB,
+                tags: vec![RetentionTag::Retranscribe],
+                budget_bytes: 10 * MB,
+            }
+        );
+        // 7 MB in 2 + 2 + 3; down to 3 MB: the 3 MB one (d, newest but
+        // not just kept), then the older of the two 2 MB ones
+        let evicted = retained.set_budget(3);
+        assert_eq!(evicted_ids(&evicted), [d, a]);
+        assert_eq!(retained.usage().recordings.len(), 1);
+        assert!(retained.get(c).is_some());
+
+        // Over the budget on its own: everything else goes first
+        let (e, evicted) = retained.retain(&recording(5), RetentionTag::Retranscribe);
+        assert_eq!(evicted_ids(&evicted), [c, e]);
+        assert_eq!(retained.used_bytes(), 0);
+    }
+
+    #[test]
+    fn zero_budget_keeps_nothing() {
+        let mut retained = RetainedAudio::new(0);
+        let (id, evicted) = retained.retain(&recording(1), RetentionTag::Retranscribe);
+        assert_eq!(evicted_ids(&evicted), [id]);
+        assert_eq!(retained.get(id), None);
+    }
+
+    #[test]
+    fn usage_payload() {
+        let mut retained = RetainedAudio::new(8);
+        retained.retain(&Arc::from(vec![0i16; 32_000]), RetentionTag::Retranscribe);
+        assert_eq!(
+            serde_json::to_value(retained.usage()).unwrap(),
+            serde_json::json!({
+                "usedBytes": 64_000,
+                "budgetBytes": 8 * MB,
+                "recordings": [
+                    { "id": 0, "bytes": 64_000, "durationMs": 2000, "tags": ["retranscribe"] }
+                ]
+            })
+        );
+    }
+}
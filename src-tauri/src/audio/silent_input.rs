@@ -0,0 +1,200 @@
+//! Silent input: a microphone muted in hardware.
+//!
+//! A muted microphone still delivers a stream, of zeros (or a bit or two
+//! of noise), and the recording ends as "no speech detected" with no
+//! hint why. The capture callbacks hand their samples to a
+//! `SilentInputDetector` before the gain, filters and AGC (which would
+//! lift the last bit of noise). It says once when `SILENT_INPUT_SECS`
+//! in a row stayed under `SILENCE_FLOOR_RMS`, far below any live
+//! microphone's noise, for the `audio:silent-input` event. A callback
+//! with signal starts it over, and so do a new stream and a new
+//! recording.
+//!
+//! On Linux `source_muted` adds the default source's mute flag from
+//! `pactl`, as a hint.
+
+use serde::Serialize;
+
+/// Seconds of silence in a row before the input is reported.
+pub const SILENT_INPUT_SECS: u32 = 3;
+
+/// RMS (in 16-bit units, about -84 dBFS) under which a callback counts
+/// as digital silence. A live microphone's noise floor is well above.
+pub const SILENCE_FLOOR_RMS: f32 = 2.0;
+
+/// `audio:silent-input` payload. Frontend mirror: `SilentInput`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SilentInput {
+    /// The device recorded from.
+    pub device: String,
+    /// Whether PulseAudio/PipeWire has the default source muted; `None`
+    /// where that can't be asked.
+    pub muted: Option<bool>,
+}
+
+/// RMS of `samples` (full scale at ±1.0), in 16-bit units; 0 for none.
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples.iter().map(|&s| f64::from(s) * f64::from(s)).sum();
+    (sum / samples.len() as f64).sqrt() as f32 * 32768.0
+}
+
+/// Counts how long a stream has been silent.
+#[derive(Debug, Clone, Default)]
+pub struct SilentInputDetector {
+    /// Samples in a row under the floor.
+    silent: u64,
+    reported: bool,
+}
+
+impl SilentInputDetector {
+    /// Count a callback's `samples` (full scale at ±1.0), at `rate` Hz
+    /// and times the amplitude `factor` of the gain. `true` once when
+    /// the silence reaches `SILENT_INPUT_SECS`, then not again until a
+    /// callback with signal starts it over.
+    pub fn observe(&mut self, samples: &[f32], factor: f32, rate: u32) -> bool {
+        if rms(samples) / factor >= SILENCE_FLOOR_RMS {
+            self.reset();
+            return false;
+        }
+        self.silent += samples.len() as u64;
+        if self.reported || self.silent < u64::from(SILENT_INPUT_SECS) * u64::from(rate) {
+            return false;
+        }
+        self.reported = true;
+        true
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// `pactl get-source-mute` output: `Mute: yes` or `Mute: no`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_mute(output: &str) -> Option<bool> {
+    match output.trim().strip_prefix("Mute:")?.trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether the default source is muted in PulseAudio/PipeWire; `None`
+/// without `pactl`. Runs a process: not on the audio thread.
+#[cfg(target_os = "linux")]
+pub fn source_muted() -> Option<bool> {
+    let output = std::process::Command::new("pactl")
+        .args(["get-source-mute", "@DEFAULT_SOURCE@"])
+        // Its output is translated otherwise
+        .env("LC_ALL", "C")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_mute(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Whether the default source is muted; only Linux can tell.
+#[cfg(not(target_os = "linux"))]
+pub fn source_muted() -> Option<bool> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 ms at 16 kHz of `level` (16-bit units) noise, alternating sign.
+    fn callback(level: i16) -> Vec<f32> {
+        let level = f32::from(level) / 32768.0;
+        (0..160)
+            .map(|i| if i % 2 == 0 { level } else { -level })
+            .collect()
+    }
+
+    #[test]
+    fn reported_once_after_the_silence() {
+        let mut detector = SilentInputDetector::default();
+        // 3 s of zeros: the last callback reaches it
+        for _ in 0..299 {
+            assert!(!detector.observe(&callback(0), 1.0, 16_000));
+        }
+        assert!(detector.observe(&callback(0), 1.0, 16_000));
+        for _ in 0..500 {
+            assert!(!detector.observe(&callback(0), 1.0, 16_000));
+        }
+    }
+
+    #[test]
+    fn a_bit_of_noise_is_still_silence() {
+        let mut detector = SilentInputDetector::default();
+        let reported = (0..300).filter(|_| detector.observe(&callback(1), 1.0, 16_000));
+        assert_eq!(reported.count(), 1);
+    }
+
+    #[test]
+    fn signal_starts_it_over() {
+        let mut detector = SilentInputDetector::default();
+        for _ in 0..299 {
+            detector.observe(&callback(0), 1.0, 16_000);
+        }
+        // A quiet room through a live microphone
+        assert!(!detector.observe(&callback(30), 1.0, 16_000));
+        for _ in 0..299 {
+            assert!(!detector.observe(&callback(0), 1.0, 16_000));
+        }
+        assert!(detector.observe(&callback(0), 1.0, 16_000));
+
+        // Reported again after signal came and went
+        assert!(!detector.observe(&callback(30), 1.0, 16_000));
+        let reported = (0..300).filter(|_| detector.observe(&callback(0), 1.0, 16_000));
+        assert_eq!(reported.count(), 1);
+    }
+
+    #[test]
+    fn counted_at_the_stream_rate() {
+        let mut detector = SilentInputDetector::default();
+        let second = vec![0.0; 48_000];
+        assert!(!detector.observe(&second, 1.0, 48_000));
+        assert!(!detector.observe(&second, 1.0, 48_000));
+        assert!(detector.observe(&second, 1.0, 48_000));
+    }
+
+    #[test]
+    fn measured_before_the_gain() {
+        let mut detector = SilentInputDetector::default();
+        // One bit of noise boosted by 30 dB: still muted
+        let boosted: Vec<f32> = callback(1).iter().map(|s| s * 31.6).collect();
+        let reported = (0..300).filter(|_| detector.observe(&boosted, 31.6, 16_000));
+        assert_eq!(reported.count(), 1);
+        // Speech cut by 20 dB is not
+        let mut detector = SilentInputDetector::default();
+        let cut: Vec<f32> = callback(300).iter().map(|s| s * 0.1).collect();
+        assert!(!(0..300).any(|_| detector.observe(&cut, 0.1, 16_000)));
+    }
+
+    #[test]
+    fn pactl_output() {
+        assert_eq!(parse_mute("Mute: yes\n"), Some(true));
+        assert_eq!(parse_mute("Mute: no\n"), Some(false));
+        assert_eq!(parse_mute("Stummschalten: ja\n"), None);
+        assert_eq!(parse_mute(""), None);
+    }
+
+    #[test]
+    fn payload() {
+        assert_eq!(
+            serde_json::to_value(SilentInput {
+                device: "USB Mic".to_string(),
+                muted: None
+            })
+            .unwrap(),
+            serde_json::json!({ "device": "USB Mic", "muted": null })
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Silero VAD: a neural speech detector, `VadBackend::Silero`.
+//!
+//! The RMS threshold takes keyboard clicks for speech and misses a
+//! quiet voice. Silero's model scores each `SILERO_FRAME` of audio with
+//! the probability that it is speech, whatever its level. The model is
+//! read from `SILERO_MODEL_FILE` in the models directory on a blocking
+//! thread when the backend is set (`AppState::load_vad_model`), never
+//! by the chunk loop; the VAD uses the threshold until it's in, and
+//! from then on when it fails to load.
+//!
+//! Inference lives in `onnx.rs`, built with the `silero-vad` feature
+//! (ONNX Runtime); everything here is plain logic so it builds and
+//! tests without it.
+
+#[cfg(feature = "silero-vad")]
+mod onnx;
+
+use std::path::Path;
+
+/// Samples the model scores at a time: 32 ms at 16 kHz.
+pub const SILERO_FRAME: usize = 512;
+
+/// The model's file, in the models directory.
+pub const SILERO_MODEL_FILE: &str = "silero_vad.onnx";
+
+/// Speech probability over which a frame is speech.
+pub const SILERO_THRESHOLD: f32 = 0.5;
+
+/// Scores frames: the ONNX model, or a stand-in in tests.
+pub trait SpeechModel: Send + Sync {
+    /// How likely (0 to 1) `frame`, `SILERO_FRAME` samples from -1 to
+    /// 1, is speech. The model carries state from one frame to the next.
+    fn probability(&mut self, frame: &[f32]) -> Result<f32, String>;
+
+    /// Forget the frames so far.
+    fn reset(&mut self);
+}
+
+/// Feeds a recording's chunks, whatever their length, to a
+/// `SpeechModel` a frame at a time.
+pub struct SileroDetector {
+    model: Box<dyn SpeechModel>,
+    /// Samples short of a frame, waiting for the next chunk.
+    pending: Vec<f32>,
+    /// The last frame's probability.
+    probability: f32,
+}
+
+impl SileroDetector {
+    pub fn new(model: Box<dyn SpeechModel>) -> Self {
+        Self {
+            model,
+            pending: Vec::with_capacity(SILERO_FRAME),
+            probability: 0.0,
+        }
+    }
+
+    /// Score a chunk: the highest probability of the frames it
+    /// completed, or the last frame's when it completed none.
+    pub fn process(&mut self, samples: &[i16]) -> Result<f32, String> {
+        let mut highest = None::<f32>;
+        for &sample in samples {
+            self.pending.push(f32::from(sample) / 32768.0);
+            if self.pending.len() < SILERO_FRAME {
+                continue;
+            }
+            let probability = self.model.probability(&self.pending)?;
+            self.pending.clear();
+            self.probability = probability;
+            highest = Some(highest.map_or(probability, |highest| highest.max(probability)));
+        }
+        Ok(highest.unwrap_or(self.probability))
+    }
+
+    /// Start over, for the next recording.
+    pub fn reset(&mut self) {
+        self.model.reset();
+        self.pending.clear();
+        self.probability = 0.0;
+    }
+}
+
+/// Load the model at `path`.
+pub fn load(path: &Path) -> Result<SileroDetector, String> {
+    #[cfg(feature = "silero-vad")]
+    return onnx::SileroModel::load(path)
+        .map(|model| SileroDetector::new(Box::new(model)))
+        .map_err(|e| {
+            format!(
+                "Could not load the Silero VAD model ({}): {}",
+                path.display(),
+                e
+            )
+        });
+    #[cfg(not(feature = "silero-vad"))]
+    {
+        let _ = path;
+        Err("Silero VAD unavailable: built without the `silero-vad` feature".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Scores a frame by its first sample, counting the frames.
+    struct Echo(Arc<AtomicUsize>);
+
+    impl SpeechModel for Echo {
+        fn probability(&mut self, frame: &[f32]) -> Result<f32, String> {
+            assert_eq!(frame.len(), SILERO_FRAME);
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(frame[0])
+        }
+
+        fn reset(&mut self) {}
+    }
+
+    fn detector() -> (SileroDetector, Arc<AtomicUsize>) {
+        let frames = Arc::new(AtomicUsize::new(0));
+        (SileroDetector::new(Box::new(Echo(frames.clone()))), frames)
+    }
+
+    #[test]
+    fn chunks_are_framed() {
+        let (mut detector, frames) = detector();
+        // 10 ms chunks: a frame every 3.2 of them
+        for _ in 0..32 {
+            detector.process(&[0; 160]).unwrap();
+        }
+        assert_eq!(frames.load(Ordering::Relaxed), 10);
+        // A chunk of several frames scores each
+        detector.process(&[0; 3 * SILERO_FRAME]).unwrap();
+        assert_eq!(frames.load(Ordering::Relaxed), 13);
+    }
+
+    #[test]
+    fn a_chunk_gets_its_likeliest_frame() {
+        let (mut detector, _) = detector();
+        let mut chunk = vec![0; 2 * SILERO_FRAME];
+        chunk[SILERO_FRAME] = 16384;
+        assert_eq!(detector.process(&chunk).unwrap(), 0.5);
+        // No frame completed: the last one's stands
+        assert_eq!(detector.process(&[0; 10]).unwrap(), 0.5);
+        detector.reset();
+        assert_eq!(detector.process(&[0; 10]).unwrap(), 0.0);
+    }
+}
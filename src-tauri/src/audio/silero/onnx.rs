@@ -0,0 +1,74 @@
+//! Silero VAD (v5) inference with ONNX Runtime.
+
+use super::{SpeechModel, SILERO_FRAME};
+use ort::session::builder::GraphOptimizationLevel;
+use ort::session::Session;
+use ort::value::Tensor;
+use std::path::Path;
+
+/// The model's recurrent state: `[2, 1, 128]`.
+const STATE_LEN: usize = 2 * 128;
+
+/// Samples of the previous frame the model sees ahead of each one at
+/// 16 kHz: its input is `[1, CONTEXT_LEN + SILERO_FRAME]`.
+const CONTEXT_LEN: usize = 64;
+
+const SAMPLE_RATE: i64 = 16_000;
+
+pub struct SileroModel {
+    session: Session,
+    state: Vec<f32>,
+    /// The end of the last frame run; silence before the first.
+    context: Vec<f32>,
+}
+
+impl SileroModel {
+    pub fn load(path: &Path) -> ort::Result<Self> {
+        // One thread: a frame is tiny, and the audio path shouldn't
+        // compete with whisper for cores.
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .with_intra_threads(1)?
+            .commit_from_file(path)?;
+        Ok(Self {
+            session,
+            state: vec![0.0; STATE_LEN],
+            context: vec![0.0; CONTEXT_LEN],
+        })
+    }
+
+    fn run(&mut self, frame: &[f32]) -> ort::Result<f32> {
+        let mut samples = Vec::with_capacity(CONTEXT_LEN + SILERO_FRAME);
+        samples.extend_from_slice(&self.context);
+        samples.extend_from_slice(frame);
+        let input = Tensor::from_array(([1usize, CONTEXT_LEN + SILERO_FRAME], samples))?;
+        let state = Tensor::from_array(([2usize, 1, 128], std::mem::take(&mut self.state)))?;
+        // A scalar
+        let sample_rate = Tensor::from_array(([0usize; 0], vec![SAMPLE_RATE]))?;
+        let outputs = self.session.run(ort::inputs![
+            "input" => input,
+            "state" => state,
+            "sr" => sample_rate,
+        ])?;
+        let (_, probability) = outputs["output"].try_extract_tensor::<f32>()?;
+        let (_, state) = outputs["stateN"].try_extract_tensor::<f32>()?;
+        self.state = state.to_vec();
+        self.context = frame[frame.len() - CONTEXT_LEN..].to_vec();
+        Ok(probability.first().copied().unwrap_or(0.0))
+    }
+}
+
+impl SpeechModel for SileroModel {
+    fn probability(&mut self, frame: &[f32]) -> Result<f32, String> {
+        self.run(frame).map_err(|e| {
+            // The state was taken for the run: start over
+            self.reset();
+            e.to_string()
+        })
+    }
+
+    fn reset(&mut self) {
+        self.state = vec![0.0; STATE_LEN];
+        self.context = vec![0.0; CONTEXT_LEN];
+    }
+}
@@ -0,0 +1,425 @@
+//! Where a recording's audio comes from: the microphone, or what the
+//! computer plays (the other side of a video call).
+//!
+//! System audio is opened as an input, per platform:
+//!
+//! - **Windows**: the default output device, which WASAPI records in
+//!   loopback mode when cpal opens it as an input.
+//! - **Linux**: the default sink's monitor, through the PulseAudio ALSA
+//!   plugin (PipeWire serves it too). `pactl` names the default sink,
+//!   and its monitor is opened as the `pulse` PCM with a device
+//!   argument (see `audio::monitor`), so nothing needs setting up.
+//!   Without `pactl` or the plugin, an ALSA device defined for a
+//!   monitor and named after it (`monitor`, or the source's `….monitor`
+//!   name) is recorded from instead. Either way nothing about the
+//!   process (the environment the plugin reads) changes for the
+//!   microphone.
+//!
+//!   One application can be recorded alone: `pactl list sink-inputs`
+//!   lists the streams playing (see `capture_applications`), and the
+//!   chosen one's is read with `parec --monitor-stream`.
+//! - **macOS**: there is no loopback; a loopback driver (BlackHole and
+//!   the like) that is installed is recorded from, else it's refused.
+//!
+//! Either way the stream goes through the same callbacks as the
+//! microphone: mixdown, gain, filters and resampling. Where one
+//! application can't be recorded alone (Windows, macOS), all of the
+//! system audio is, with a warning.
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::{Deserialize, Serialize};
+
+use super::capture::AudioCaptureError;
+
+/// `start_listen` / `set_capture_source` payload. Frontend mirror:
+/// `CaptureSource`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaptureSource {
+    #[default]
+    Microphone,
+    SystemAudio,
+}
+
+impl CaptureSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptureSource::Microphone => "microphone",
+            CaptureSource::SystemAudio => "system-audio",
+        }
+    }
+}
+
+/// An application playing audio, as `list_capture_applications` lists
+/// it. Frontend mirror: `CaptureApplication`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureApplication {
+    /// Its stream's index (a sink input), what `set_capture_application`
+    /// takes. A new one each time the application reopens its stream.
+    pub id: u32,
+    /// What `Settings.capture_application` keeps, to find the stream
+    /// again when recording starts.
+    pub name: String,
+}
+
+/// The applications in `pactl list sink-inputs`, by `application.name`
+/// (or, without one, `application.process.binary`). An application
+/// playing several streams is listed once, with its first.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn capture_applications(sink_inputs: &str) -> Vec<CaptureApplication> {
+    // Per sink input: its index, its name and its binary
+    let mut streams: Vec<(u32, Option<String>, Option<String>)> = Vec::new();
+    for line in sink_inputs.lines() {
+        if let Some(index) = line.strip_prefix("Sink Input #") {
+            if let Ok(index) = index.trim().parse() {
+                streams.push((index, None, None));
+            }
+            continue;
+        }
+        let Some((_, name, binary)) = streams.last_mut() else {
+            continue;
+        };
+        let Some((key, value)) = line.trim().split_once(" = ") else {
+            continue;
+        };
+        let value = value.trim_matches('"').to_string();
+        match key {
+            "application.name" => *name = Some(value),
+            "application.process.binary" => *binary = Some(value),
+            _ => {}
+        }
+    }
+
+    let mut applications: Vec<CaptureApplication> = Vec::new();
+    for (id, name, binary) in streams {
+        let Some(name) = name.or(binary).filter(|name| !name.is_empty()) else {
+            continue;
+        };
+        if applications.iter().all(|app| app.name != name) {
+            applications.push(CaptureApplication { id, name });
+        }
+    }
+    applications
+}
+
+/// Input devices that are loopback drivers, by name.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const LOOPBACK_DRIVERS: &[&str] = &["BlackHole", "Soundflower", "Loopback Audio"];
+
+/// Whether the input device `name` is a loopback driver.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn is_loopback_driver(name: &str) -> bool {
+    LOOPBACK_DRIVERS.iter().any(|driver| name.contains(driver))
+}
+
+/// What a sink's monitor is called, as a source or an ALSA device.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const MONITOR_SUFFIX: &str = ".monitor";
+
+/// Whether the ALSA device `name` records a sink's monitor.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn is_monitor_device(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name == "monitor" || name.ends_with(MONITOR_SUFFIX)
+}
+
+/// The sink `pactl info` reports as the default (`Default Sink: …`),
+/// for `pactl` older than `get-default-sink`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn default_sink(info: &str) -> Option<&str> {
+    info.lines()
+        .find_map(|line| line.strip_prefix("Default Sink:"))
+        .map(str::trim)
+        .filter(|sink| !sink.is_empty())
+}
+
+/// ALSA name of the `pulse` PCM recording `sink`'s monitor. `None` for
+/// a name that would not survive as an ALSA argument.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn monitor_pcm(sink: &str) -> Option<String> {
+    let plain = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-');
+    (!sink.is_empty() && sink.chars().all(plain))
+        .then(|| format!("pulse:DEVICE={sink}{MONITOR_SUFFIX}"))
+}
+
+/// An input to record from, and its config.
+pub enum Input {
+    /// A device cpal opens.
+    Device {
+        device: cpal::Device,
+        config: cpal::SupportedStreamConfig,
+    },
+    /// The default sink's monitor, or one application's stream, read
+    /// by `audio::monitor`.
+    #[cfg(target_os = "linux")]
+    Monitor(super::monitor::MonitorPcm),
+}
+
+fn config_error(e: impl std::fmt::Display) -> AudioCaptureError {
+    AudioCaptureError::DeviceError(e.to_string())
+}
+
+/// The device that records what the computer plays.
+#[cfg(target_os = "windows")]
+pub fn system_audio_input(application: Option<&str>) -> Result<Input, AudioCaptureError> {
+    all_applications(application);
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| AudioCaptureError::NotSupported("no output device".to_string()))?;
+    let config = device.default_output_config().map_err(config_error)?;
+    Ok(Input::Device { device, config })
+}
+
+/// The device that records what the computer plays: only
+/// `application`'s stream when it is given and playing.
+#[cfg(target_os = "linux")]
+pub fn system_audio_input(application: Option<&str>) -> Result<Input, AudioCaptureError> {
+    if let Some(name) = application {
+        match application_input(name) {
+            Ok(input) => return Ok(input),
+            Err(e) => tracing::warn!(
+                "Can't record \"{}\" alone ({}); recording all system audio",
+                name,
+                e
+            ),
+        }
+    }
+    let monitor = default_monitor().and_then(|name| super::monitor::MonitorPcm::open(&name));
+    let why = match monitor {
+        Ok(pcm) => return Ok(Input::Monitor(pcm)),
+        Err(e) => e,
+    };
+    // No pactl or no plugin: a monitor device from the ALSA config
+    let device = cpal::default_host()
+        .input_devices()
+        .map_err(config_error)?
+        .find(|device| device.name().is_ok_and(|name| is_monitor_device(&name)))
+        .ok_or_else(|| {
+            AudioCaptureError::NotSupported(format!(
+                "the default output's monitor can't be opened ({why}); the PulseAudio \
+                 ALSA plugin and pactl are needed (PipeWire provides both through \
+                 pipewire-pulse)"
+            ))
+        })?;
+    tracing::info!("Default monitor unavailable ({why}); using the ALSA monitor device");
+    let config = device.default_input_config().map_err(config_error)?;
+    Ok(Input::Device { device, config })
+}
+
+/// The stream `name` plays, read by `parec`.
+#[cfg(target_os = "linux")]
+fn application_input(name: &str) -> Result<Input, AudioCaptureError> {
+    let application = list_capture_applications()?
+        .into_iter()
+        .find(|application| application.name == name)
+        .ok_or_else(|| AudioCaptureError::NotSupported(format!("\"{name}\" isn't playing")))?;
+    super::monitor::MonitorPcm::open_application(&application).map(Input::Monitor)
+}
+
+/// The applications playing audio, to record one alone.
+#[cfg(target_os = "linux")]
+pub fn list_capture_applications() -> Result<Vec<CaptureApplication>, AudioCaptureError> {
+    pactl(&["list", "sink-inputs"]).map(|listed| capture_applications(&listed))
+}
+
+/// None: one application can't be recorded alone here.
+#[cfg(not(target_os = "linux"))]
+pub fn list_capture_applications() -> Result<Vec<CaptureApplication>, AudioCaptureError> {
+    Ok(Vec::new())
+}
+
+/// One application can't be recorded alone here: say so when one was
+/// asked for. All of the system audio is recorded instead.
+#[cfg(not(target_os = "linux"))]
+fn all_applications(application: Option<&str>) {
+    if let Some(name) = application {
+        tracing::warn!(
+            "Recording \"{}\" alone isn't supported here; recording all system audio",
+            name
+        );
+    }
+}
+
+/// `pulse` PCM name of the default sink's monitor, from `pactl`.
+#[cfg(target_os = "linux")]
+fn default_monitor() -> Result<String, AudioCaptureError> {
+    let sink = match pactl(&["get-default-sink"]) {
+        Ok(sink) => sink.trim().to_string(),
+        Err(_) => {
+            let info = pactl(&["info"])?;
+            default_sink(&info)
+                .ok_or_else(|| {
+                    AudioCaptureError::NotSupported("pactl reports no default sink".to_string())
+                })?
+                .to_string()
+        }
+    };
+    monitor_pcm(&sink).ok_or_else(|| {
+        AudioCaptureError::NotSupported(format!("unusable default sink name \"{sink}\""))
+    })
+}
+
+/// Standard output of `pactl args`, in the C locale (`info` and
+/// `list` are parsed).
+#[cfg(target_os = "linux")]
+fn pactl(args: &[&str]) -> Result<String, AudioCaptureError> {
+    let output = std::process::Command::new("pactl")
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .map_err(|e| AudioCaptureError::NotSupported(format!("pactl: {e}")))?;
+    if !output.status.success() {
+        return Err(AudioCaptureError::NotSupported(format!(
+            "pactl {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The device that records what the computer plays.
+#[cfg(target_os = "macos")]
+pub fn system_audio_input(application: Option<&str>) -> Result<Input, AudioCaptureError> {
+    all_applications(application);
+    let device = cpal::default_host()
+        .input_devices()
+        .map_err(config_error)?
+        .find(|device| device.name().is_ok_and(|name| is_loopback_driver(&name)))
+        .ok_or_else(|| {
+            AudioCaptureError::NotSupported(
+                "macOS has no loopback; install a loopback driver such as BlackHole".to_string(),
+            )
+        })?;
+    let config = device.default_input_config().map_err(config_error)?;
+    Ok(Input::Device { device, config })
+}
+
+/// The device that records what the computer plays.
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+pub fn system_audio_input(_application: Option<&str>) -> Result<Input, AudioCaptureError> {
+    Err(AudioCaptureError::NotSupported(
+        "not on this platform".to_string(),
+    ))
+}
+
+/// Whether system audio can be recorded here; why not if it can't.
+pub fn check_system_audio() -> Result<(), AudioCaptureError> {
+    system_audio_input(None).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_drivers_are_known_by_name() {
+        assert!(is_loopback_driver("BlackHole 2ch"));
+        assert!(is_loopback_driver("BlackHole 16ch"));
+        assert!(is_loopback_driver("Loopback Audio 2"));
+        assert!(!is_loopback_driver("MacBook Pro Microphone"));
+    }
+
+    #[test]
+    fn monitors_are_known_by_name() {
+        assert!(is_monitor_device("monitor"));
+        assert!(is_monitor_device(
+            "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor"
+        ));
+        assert!(is_monitor_device("Speakers.Monitor"));
+        assert!(!is_monitor_device("pulse"));
+        assert!(!is_monitor_device("default"));
+        assert!(!is_monitor_device("sysdefault:CARD=Monitor"));
+    }
+
+    #[test]
+    fn default_sink_from_pactl_info() {
+        let info = "Server Name: PulseAudio (on PipeWire 1.0.5)\n\
+                    Default Sink: alsa_output.pci-0000_00_1f.3.analog-stereo\n\
+                    Default Source: alsa_input.pci-0000_00_1f.3.analog-stereo\n";
+        assert_eq!(
+            default_sink(info),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo")
+        );
+        assert_eq!(default_sink("Default Sink: \n"), None);
+        assert_eq!(default_sink("Server Name: pulseaudio\n"), None);
+    }
+
+    #[test]
+    fn the_monitor_is_opened_through_the_pulse_pcm() {
+        assert_eq!(
+            monitor_pcm("alsa_output.pci-0000_00_1f.3.analog-stereo").as_deref(),
+            Some("pulse:DEVICE=alsa_output.pci-0000_00_1f.3.analog-stereo.monitor")
+        );
+        assert!(monitor_pcm("bluez_output.00_1B_66_AA_BB_CC.1").is_some());
+        // Would split or end the ALSA argument list
+        assert_eq!(monitor_pcm("sink,CARD=1"), None);
+        assert_eq!(monitor_pcm("my sink"), None);
+        assert_eq!(monitor_pcm(""), None);
+    }
+
+    #[test]
+    fn applications_from_pactl_sink_inputs() {
+        let listed = "Sink Input #42\n\
+                      \tDriver: PipeWire\n\
+                      \tSink: 57\n\
+                      \tProperties:\n\
+                      \t\tmedia.name = \"Playback\"\n\
+                      \t\tapplication.name = \"Firefox\"\n\
+                      \t\tapplication.process.binary = \"firefox\"\n\
+                      \n\
+                      Sink Input #43\n\
+                      \tProperties:\n\
+                      \t\tapplication.process.binary = \"zoom\"\n\
+                      \n\
+                      Sink Input #44\n\
+                      \tProperties:\n\
+                      \t\tapplication.name = \"Firefox\"\n\
+                      \n\
+                      Sink Input #45\n\
+                      \tProperties:\n\
+                      \t\tmedia.name = \"loopback\"\n";
+        assert_eq!(
+            capture_applications(listed),
+            vec![
+                CaptureApplication {
+                    id: 42,
+                    name: "Firefox".to_string()
+                },
+                // No application.name: the binary
+                CaptureApplication {
+                    id: 43,
+                    name: "zoom".to_string()
+                },
+                // Firefox's second stream and a nameless one aren't listed
+            ]
+        );
+        assert_eq!(capture_applications(""), vec![]);
+    }
+
+    #[test]
+    fn application_names_keep_their_spaces_and_quotes() {
+        let listed = "Sink Input #7\n\
+                      \tProperties:\n\
+                      \t\tapplication.name = \"Google Chrome\"\n\
+                      Sink Input #8\n\
+                      \tProperties:\n\
+                      \t\tapplication.name = \"a = b\"\n";
+        let names: Vec<_> = capture_applications(listed)
+            .into_iter()
+            .map(|app| app.name)
+            .collect();
+        assert_eq!(names, ["Google Chrome", "a = b"]);
+    }
+
+    #[test]
+    fn settings_payload() {
+        assert_eq!(
+            serde_json::to_value(CaptureSource::SystemAudio).unwrap(),
+            serde_json::json!("system-audio")
+        );
+        assert_eq!(CaptureSource::default(), CaptureSource::Microphone);
+        assert_eq!(CaptureSource::SystemAudio.as_str(), "system-audio");
+    }
+}
@@ -0,0 +1,429 @@
+//! Disk spill: bounded memory for very long captures.
+//!
+//! `AudioBuffer` keeps a capture in RAM, and an hour of 16 kHz audio is
+//! 115 MB. With `Settings.capture_spill_mb` set, each time the buffer
+//! holds that much it hands it to a `SpillWriter` and starts over. The
+//! audio callbacks never touch the disk: the writer's thread appends
+//! the blocks to a `SpillFile` in the cache dir, and `AudioCapture::stop`
+//! reads the file back in front of the rest. When the file can't be
+//! written the capture carries on in RAM, with an `audio:spill-failed`
+//! event saying why.
+//!
+//! The file goes when it is read back or dropped (a capture cleared,
+//! or the app exiting); `remove_stale_spills` sweeps up after a crash.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Anything smaller spills every few seconds.
+pub const MIN_CAPTURE_SPILL_MB: u32 = 1;
+pub const MAX_CAPTURE_SPILL_MB: u32 = 1024;
+
+const FILE_PREFIX: &str = "capture-";
+const FILE_EXTENSION: &str = "pcm";
+
+/// Samples converted and written at a time.
+const WRITE_SAMPLES: usize = 32 * 1024;
+/// Blocks waiting for the writer. Past that, the buffer holds on to
+/// its samples and offers them again with the next callback's.
+const QUEUED_BLOCKS: usize = 2;
+
+/// `audio:spill-failed` payload: the capture is in RAM from here on.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpillFailure {
+    pub message: String,
+}
+
+/// Samples in `mb` of 16-bit audio.
+pub fn threshold_samples(mb: u32) -> usize {
+    mb as usize * 1024 * 1024 / std::mem::size_of::<i16>()
+}
+
+/// Raw little-endian 16-bit samples in a temp file, removed on drop.
+/// Kept open while it is written, and closed before it is read back or
+/// removed (an open file couldn't be removed on Windows).
+#[derive(Debug)]
+pub struct SpillFile {
+    path: PathBuf,
+    file: Option<File>,
+    /// Samples known to be written; a failed append may leave a part
+    /// of one after them, which `read` ignores.
+    len: usize,
+}
+
+impl SpillFile {
+    /// A new, empty file in `dir`.
+    pub fn create(dir: &Path) -> io::Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "{FILE_PREFIX}{}-{}.{FILE_EXTENSION}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::create(&path)?;
+        Ok(Self {
+            path,
+            file: Some(file),
+            len: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Write `samples` after the others, `WRITE_SAMPLES` at a time. On
+    /// an error, `len` counts the pieces written before it.
+    pub fn append(&mut self, samples: &[i16]) -> io::Result<()> {
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| io::Error::other("spill file already closed"))?;
+        let mut bytes = Vec::with_capacity(samples.len().min(WRITE_SAMPLES) * 2);
+        for piece in samples.chunks(WRITE_SAMPLES) {
+            bytes.clear();
+            bytes.extend(piece.iter().flat_map(|s| s.to_le_bytes()));
+            file.write_all(&bytes)?;
+            self.len += piece.len();
+        }
+        Ok(())
+    }
+
+    /// Everything appended, oldest first. The file is removed.
+    pub fn read(mut self) -> io::Result<Vec<i16>> {
+        self.file = None;
+        let mut bytes = Vec::with_capacity(self.len * 2);
+        File::open(&self.path)?
+            .take(self.len as u64 * 2)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != self.len * 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} of {} samples left", bytes.len() / 2, self.len),
+            ));
+        }
+        Ok(bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect())
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        self.file = None;
+        if let Err(e) = fs::remove_file(&self.path) {
+            tracing::warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// How the writer's thread is doing, shared with the buffer.
+#[derive(Default)]
+struct WriterStatus {
+    failed: AtomicBool,
+    /// Why, until `take_failure`.
+    failure: Mutex<Option<String>>,
+}
+
+impl WriterStatus {
+    fn fail(&self, message: String) {
+        tracing::warn!("{}", message);
+        *self.failure.lock() = Some(message);
+        self.failed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// What the writer's thread did with the blocks: the file, and the
+/// samples it held on to after a failed write, which follow the file's.
+struct Written {
+    file: Option<SpillFile>,
+    held: Vec<i16>,
+}
+
+/// Appends blocks of samples to a `SpillFile` on a thread of its own,
+/// created with the first block. Sending never waits: a full queue
+/// gives the block back. After a failed write the thread keeps what it
+/// gets in RAM, and `finish` returns it with the file's samples.
+pub struct SpillWriter {
+    sender: SyncSender<Vec<i16>>,
+    thread: JoinHandle<Written>,
+    status: Arc<WriterStatus>,
+    /// Samples handed to the thread.
+    sent: usize,
+}
+
+impl SpillWriter {
+    /// A writer for a new file in `dir`.
+    pub fn spawn(dir: PathBuf) -> io::Result<Self> {
+        let (sender, blocks) = mpsc::sync_channel(QUEUED_BLOCKS);
+        let status = Arc::new(WriterStatus::default());
+        let thread = thread::Builder::new().name("capture-spill".into()).spawn({
+            let status = Arc::clone(&status);
+            move || write_blocks(&dir, blocks, &status)
+        })?;
+        Ok(Self {
+            sender,
+            thread,
+            status,
+            sent: 0,
+        })
+    }
+
+    /// Queue `block` for the file. Given back when the queue is full or
+    /// the writer failed: it stays with the caller for now.
+    pub fn send(&mut self, block: Vec<i16>) -> Result<(), Vec<i16>> {
+        if self.failed() {
+            return Err(block);
+        }
+        let len = block.len();
+        match self.sender.try_send(block) {
+            Ok(()) => {
+                self.sent += len;
+                Ok(())
+            }
+            Err(TrySendError::Full(block)) => Err(block),
+            Err(TrySendError::Disconnected(block)) => {
+                self.status
+                    .fail("The recording's spill writer stopped. It stays in memory.".into());
+                Err(block)
+            }
+        }
+    }
+
+    /// Whether a write failed: nothing more is sent.
+    pub fn failed(&self) -> bool {
+        self.status.failed.load(Ordering::Relaxed)
+    }
+
+    /// Why the writer failed, once.
+    pub fn take_failure(&self) -> Option<String> {
+        self.status.failure.lock().take()
+    }
+
+    /// Samples sent, written or not yet.
+    pub fn len(&self) -> usize {
+        self.sent
+    }
+
+    /// Wait for the queued blocks, then everything sent, oldest first;
+    /// the file is removed. Short of what was sent, with why, when the
+    /// file can't be read back.
+    pub fn finish(self) -> (Vec<i16>, Option<String>) {
+        drop(self.sender);
+        let Ok(Written { file, held }) = self.thread.join() else {
+            let message = "The recording's spill writer crashed; its start is lost".to_string();
+            return (Vec::new(), Some(message));
+        };
+        let Some(file) = file else {
+            return (held, None);
+        };
+        let path = file.path().display().to_string();
+        match file.read() {
+            Ok(mut samples) => {
+                samples.extend_from_slice(&held);
+                (samples, None)
+            }
+            Err(e) => {
+                let message = format!(
+                    "Couldn't read the start of the recording back from {}: {}",
+                    path, e
+                );
+                (held, Some(message))
+            }
+        }
+    }
+}
+
+/// The writer's thread: append each block, until the sender goes.
+fn write_blocks(dir: &Path, blocks: Receiver<Vec<i16>>, status: &WriterStatus) -> Written {
+    let mut file: Option<SpillFile> = None;
+    let mut held = Vec::new();
+    for block in blocks {
+        if status.failed.load(Ordering::Relaxed) {
+            held.extend_from_slice(&block);
+            continue;
+        }
+        let before = file.as_ref().map_or(0, SpillFile::len);
+        let written = match &mut file {
+            Some(spill) => spill.append(&block),
+            None => SpillFile::create(dir).and_then(|spill| file.insert(spill).append(&block)),
+        };
+        match written {
+            Ok(()) => tracing::debug!("Spilled {} samples to disk", block.len()),
+            Err(e) => {
+                // The pieces that didn't make it
+                let after = file.as_ref().map_or(0, SpillFile::len);
+                held.extend_from_slice(&block[after - before..]);
+                status.fail(format!(
+                    "Couldn't write the recording to {}: {}. It stays in memory.",
+                    dir.display(),
+                    e
+                ));
+            }
+        }
+    }
+    Written { file, held }
+}
+
+/// Remove the spill files in `dir`, left behind by a crash. Call before
+/// the first capture. How many went.
+pub fn remove_stale_spills(dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            name.starts_with(FILE_PREFIX) && path.extension().is_some_and(|e| e == FILE_EXTENSION)
+        })
+        .filter(|path| match fs::remove_file(path) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to remove {}: {}", path.display(), e);
+                false
+            }
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::time::{Duration, Instant};
+
+    /// Wait (at most a second) for the writer's thread to get there.
+    fn eventually(done: impl Fn() -> bool) {
+        let started = Instant::now();
+        while !done() {
+            assert!(started.elapsed() < Duration::from_secs(1), "timed out");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn reads_back_what_was_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = SpillFile::create(&dir.path().join("cache")).unwrap();
+        file.append(&[1, -2, i16::MIN]).unwrap();
+        file.append(&[]).unwrap();
+        file.append(&[i16::MAX]).unwrap();
+        assert_eq!(file.len(), 4);
+        let path = file.path().to_path_buf();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 8);
+
+        assert_eq!(file.read().unwrap(), [1, -2, i16::MIN, i16::MAX]);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn dropping_removes_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = SpillFile::create(dir.path()).unwrap();
+        file.append(&[7; 100]).unwrap();
+        let path = file.path().to_path_buf();
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn a_partial_append_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = SpillFile::create(dir.path()).unwrap();
+        file.append(&[1, 2]).unwrap();
+        // Half a sample, as a disk that filled up mid-write leaves
+        OpenOptions::new()
+            .append(true)
+            .open(file.path())
+            .unwrap()
+            .write_all(&[9])
+            .unwrap();
+        assert_eq!(file.read().unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn a_truncated_file_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = SpillFile::create(dir.path()).unwrap();
+        file.append(&[1, 2, 3]).unwrap();
+        File::create(file.path()).unwrap();
+        assert_eq!(
+            file.read().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn the_writer_appends_blocks_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = || fs::read_dir(dir.path()).unwrap().count();
+        let mut writer = SpillWriter::spawn(dir.path().to_path_buf()).unwrap();
+        // Created with the first block
+        assert_eq!(files(), 0);
+        writer.send(vec![1, 2]).unwrap();
+        eventually(|| files() == 1);
+        // Written in several pieces
+        let long: Vec<i16> = (0..WRITE_SAMPLES * 2 + 3).map(|i| i as i16).collect();
+        writer.send(long.clone()).unwrap();
+        assert_eq!(writer.len(), long.len() + 2);
+
+        let (samples, failure) = writer.finish();
+        assert!(failure.is_none());
+        assert_eq!(samples[..2], [1, 2]);
+        assert_eq!(samples[2..], long);
+        assert_eq!(files(), 0);
+    }
+
+    #[test]
+    fn a_failed_write_keeps_the_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let not_a_dir = dir.path().join("cache");
+        fs::write(&not_a_dir, "").unwrap();
+        let mut writer = SpillWriter::spawn(not_a_dir).unwrap();
+        writer.send(vec![1, 2]).unwrap();
+        eventually(|| writer.failed());
+        assert!(writer.take_failure().is_some());
+        assert!(writer.take_failure().is_none());
+        // Not tried again
+        assert_eq!(writer.send(vec![3]), Err(vec![3]));
+        assert_eq!(writer.finish(), (vec![1, 2], None));
+    }
+
+    #[test]
+    fn stale_files_are_swept() {
+        let dir = tempfile::tempdir().unwrap();
+        let stale = SpillFile::create(dir.path()).unwrap();
+        let path = stale.path().to_path_buf();
+        // What a crash leaves: no drop
+        std::mem::forget(stale);
+        fs::write(dir.path().join("models.json"), "{}").unwrap();
+
+        assert_eq!(remove_stale_spills(dir.path()), 1);
+        assert!(!path.exists());
+        assert!(dir.path().join("models.json").exists());
+        assert_eq!(remove_stale_spills(&dir.path().join("missing")), 0);
+    }
+
+    #[test]
+    fn threshold_in_samples() {
+        assert_eq!(threshold_samples(1), 524_288);
+        // Half a minute at 16 kHz
+        assert!(threshold_samples(MIN_CAPTURE_SPILL_MB) > 16_000 * 30);
+    }
+}
@@ -0,0 +1,196 @@
+//! Utterances: a continuous recording, transcribed as it goes.
+//!
+//! A `Continuous` recording runs for a whole meeting. The chunk task
+//! hands each VAD result to a `Segmenter`, which says where to cut the
+//! capture buffer: at the end of each utterance (the VAD's hangover,
+//! `Settings.silence_timeout_ms`, ran out), and every `IDLE_TRIM_MS` of
+//! silence before one, keeping only `UTTERANCE_LEAD_MS` of it. Each
+//! utterance is queued, at most `UTTERANCE_QUEUE_LEN` of them, for the
+//! one task that transcribes them in order; `stop_listen` queues what
+//! is left last and waits for the queue to empty.
+//!
+//! When transcription falls behind and the queue is full, the cut is
+//! put off: the utterance goes on into the next one, so nothing is
+//! dropped and the audio thread never waits.
+//!
+//! Pure: fed VAD results and chunk lengths.
+
+use serde::Serialize;
+use std::ops::Range;
+
+/// Silence kept before an utterance, for a soft first syllable.
+pub const UTTERANCE_LEAD_MS: u64 = 300;
+
+/// Silence before an utterance is dropped this often, so the buffer
+/// doesn't fill with a meeting's quiet stretches.
+pub const IDLE_TRIM_MS: u64 = 1000;
+
+/// Utterances waiting for transcription, at most.
+pub const UTTERANCE_QUEUE_LEN: usize = 4;
+
+/// Where an utterance was in the recording. In `transcript:final`.
+/// Frontend mirror: `Utterance`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Utterance {
+    /// 1 for the recording's first.
+    pub index: u32,
+    /// Wall-clock times, in ms since the Unix epoch.
+    pub started_at_ms: u64,
+    pub ended_at_ms: u64,
+    /// `stop_listen`'s flush: the recording's last.
+    pub last: bool,
+}
+
+/// An utterance's audio, cut from the capture, to transcribe.
+#[derive(Debug, Clone)]
+pub struct UtteranceAudio {
+    pub samples: Vec<i16>,
+    /// The VAD's speech in it, in ms and as ms ranges.
+    pub speech_ms: u64,
+    pub speech: Vec<Range<u64>>,
+    /// When it was cut, in ms since the Unix epoch.
+    pub ended_at_ms: u64,
+    pub last: bool,
+}
+
+impl UtteranceAudio {
+    /// It as the `index`th utterance, at `sample_rate` Hz.
+    pub fn utterance(&self, index: u32, sample_rate: u32) -> Utterance {
+        let duration_ms = self.samples.len() as u64 * 1000 / u64::from(sample_rate.max(1));
+        Utterance {
+            index,
+            started_at_ms: self.ended_at_ms.saturating_sub(duration_ms),
+            ended_at_ms: self.ended_at_ms,
+            last: self.last,
+        }
+    }
+}
+
+/// Where to cut the capture buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cut {
+    /// An utterance ended: all of it goes to transcription.
+    Utterance,
+    /// Silence: drop all but `UTTERANCE_LEAD_MS` of it.
+    Silence,
+}
+
+/// Watches a continuous recording's VAD results for utterances.
+#[derive(Debug, Clone, Default)]
+pub struct Segmenter {
+    /// Speech since the last cut.
+    heard: bool,
+    /// Samples of silence since the last trim, before any speech.
+    silent: u64,
+}
+
+impl Segmenter {
+    /// Count a chunk of `samples` at `sample_rate` Hz, which the VAD
+    /// found `is_speech`. Says where to cut, if anywhere.
+    pub fn observe(&mut self, is_speech: bool, samples: usize, sample_rate: u32) -> Option<Cut> {
+        if is_speech {
+            self.heard = true;
+            self.silent = 0;
+            return None;
+        }
+        if self.heard {
+            self.heard = false;
+            return Some(Cut::Utterance);
+        }
+        self.silent += samples as u64;
+        if self.silent * 1000 < IDLE_TRIM_MS * u64::from(sample_rate) {
+            return None;
+        }
+        self.silent = 0;
+        Some(Cut::Silence)
+    }
+
+    /// The utterance cut couldn't be made (the queue is full): it is
+    /// asked for again at the next chunk of silence, and speech meanwhile
+    /// joins it.
+    pub fn defer(&mut self) {
+        self.heard = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    /// `n` chunks of 10 ms, `is_speech` or not: the cuts asked for.
+    fn feed(segmenter: &mut Segmenter, is_speech: bool, n: usize) -> Vec<Cut> {
+        (0..n)
+            .filter_map(|_| segmenter.observe(is_speech, 160, RATE))
+            .collect()
+    }
+
+    #[test]
+    fn silence_is_trimmed_every_second() {
+        let mut segmenter = Segmenter::default();
+        assert_eq!(feed(&mut segmenter, false, 99), []);
+        assert_eq!(feed(&mut segmenter, false, 1), [Cut::Silence]);
+        assert_eq!(feed(&mut segmenter, false, 250), [Cut::Silence; 2]);
+    }
+
+    #[test]
+    fn cut_at_each_end_of_speech() {
+        let mut segmenter = Segmenter::default();
+        feed(&mut segmenter, false, 50);
+        // Speech restarts the silence count
+        assert_eq!(feed(&mut segmenter, true, 200), []);
+        assert_eq!(feed(&mut segmenter, false, 1), [Cut::Utterance]);
+        assert_eq!(feed(&mut segmenter, false, 99), []);
+        assert_eq!(feed(&mut segmenter, true, 100), []);
+        assert_eq!(feed(&mut segmenter, false, 1), [Cut::Utterance]);
+    }
+
+    #[test]
+    fn a_deferred_cut_is_asked_for_again() {
+        let mut segmenter = Segmenter::default();
+        feed(&mut segmenter, true, 100);
+        assert_eq!(feed(&mut segmenter, false, 1), [Cut::Utterance]);
+        segmenter.defer();
+        assert_eq!(feed(&mut segmenter, false, 1), [Cut::Utterance]);
+        segmenter.defer();
+        // Speaking again meanwhile: one utterance with the next
+        assert_eq!(feed(&mut segmenter, true, 100), []);
+        assert_eq!(feed(&mut segmenter, false, 1), [Cut::Utterance]);
+        // Never trimmed while it waits
+        for _ in 0..200 {
+            segmenter.defer();
+            assert_eq!(feed(&mut segmenter, false, 1), [Cut::Utterance]);
+        }
+    }
+
+    #[test]
+    fn wall_clock_times() {
+        let audio = UtteranceAudio {
+            samples: vec![0; 2 * RATE as usize],
+            speech_ms: 1500,
+            speech: Vec::new(),
+            ended_at_ms: 1_700_000_010_000,
+            last: false,
+        };
+        assert_eq!(
+            audio.utterance(3, RATE),
+            Utterance {
+                index: 3,
+                started_at_ms: 1_700_000_008_000,
+                ended_at_ms: 1_700_000_010_000,
+                last: false
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(audio.utterance(3, RATE)).unwrap(),
+            serde_json::json!({
+                "index": 3,
+                "startedAtMs": 1_700_000_008_000u64,
+                "endedAtMs": 1_700_000_010_000u64,
+                "last": false
+            })
+        );
+    }
+}
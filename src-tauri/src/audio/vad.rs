@@ -1,88 +1,465 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use super::noise_floor::NoiseFloor;
+use super::silero::{self, SileroDetector, SILERO_THRESHOLD};
+
+/// RMS level below which a whole recording counts as silence (about
+/// -50 dBFS — well under the VAD's speech threshold, above the noise
+/// floor of a typical idle laptop mic).
+pub const SILENT_BUFFER_RMS: f32 = 0.003;
+
+/// RMS (Root Mean Square) of i16 samples, normalised to 0.0 - 1.0.
+pub fn rms(samples: &[i16]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let sum: f64 = samples
+        .iter()
+        .map(|&s| {
+            let normalized = s as f64 / i16::MAX as f64;
+            normalized * normalized
+        })
+        .sum();
+
+    (sum / samples.len() as f64).sqrt() as f32
+}
+
+/// Quick energy check run before transcription: a buffer this quiet
+/// contains no speech, and feeding it to Whisper is exactly how
+/// "Thanks for watching" ghosts appear.
+pub fn is_silent_buffer(samples: &[i16]) -> bool {
+    rms(samples) < SILENT_BUFFER_RMS
+}
+
+/// RMS over which a chunk is speech, until `calibrate_noise_floor`
+/// fits one to the microphone (`Settings.speech_threshold`).
+pub const DEFAULT_SPEECH_THRESHOLD: f32 = 0.02;
+
+/// Bounds `set_vad_config` accepts for the speech threshold (RMS): about
+/// -60 dBFS, under any microphone's noise, and -6 dBFS, shouting.
+pub const MIN_VAD_THRESHOLD: f32 = 0.001;
+pub const MAX_VAD_THRESHOLD: f32 = 0.5;
+
+/// Silence after speech before the VAD says it ended, unless
+/// `Settings.silence_timeout_ms` says otherwise.
+pub const DEFAULT_SILENCE_TIMEOUT_MS: u32 = 1500;
+
+/// Under this, the pauses between words end the speech.
+pub const MIN_SILENCE_TIMEOUT_MS: u32 = 200;
+
+/// The most `Settings.min_speech_ms` can ask for.
+pub const MAX_MIN_SPEECH_MS: u32 = 5000;
+
+/// Rate of the chunks the VAD is fed: the capture's target rate.
+const CHUNK_RATE: u64 = 16_000;
+
+/// Recordings shorter than this are never transcribed.
+pub const MIN_RECORDING_MS: u64 = 500;
+
+/// Default minimum of detected speech before a recording is worth
+/// transcribing (the `minSpeechMs` setting).
+pub const DEFAULT_MIN_SPEECH_MS: u32 = 300;
+
+/// Why a finished recording was not sent to Whisper. Carried as
+/// `reason` in the `transcript:empty` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    TooShort,
+    NoSpeechDetected,
+}
+
+/// Decide whether a recording should be transcribed. Duration alone
+/// isn't enough — three seconds of silence passes a length check and
+/// then hallucinates — so the VAD's detected speech time must also
+/// reach `min_speech_ms`, unless `force` is set for voices the VAD
+/// doesn't pick up well.
+pub fn skip_reason(
+    duration_ms: u64,
+    speech_ms: u64,
+    min_speech_ms: u64,
+    force: bool,
+) -> Option<SkipReason> {
+    if duration_ms < MIN_RECORDING_MS {
+        Some(SkipReason::TooShort)
+    } else if !force && speech_ms < min_speech_ms {
+        Some(SkipReason::NoSpeechDetected)
+    } else {
+        None
+    }
+}
+
+/// Map an RMS value onto the 0–1 scale the level meter draws.
+///
+/// Logarithmic, since perceived loudness is: -40 dBFS to -10 dBFS span
+/// the meter, so an rms of 0.01 → 0, 0.1 → ~0.67, 0.32 → 1. Used for both the
+/// level and the speech threshold so the two can be drawn together.
+pub fn display_level(rms: f32) -> f32 {
+    if rms > 0.001 {
+        let db = 20.0 * rms.log10();
+        ((db + 40.0) / 30.0).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// What decides that a chunk is speech (`Settings.vad_backend`).
+/// Frontend mirror: `VadBackend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VadBackend {
+    /// Its RMS against the speech threshold.
+    #[default]
+    Rms,
+    /// Silero's model (see `audio::silero`); the RMS threshold when the
+    /// model can't be loaded.
+    Silero,
+}
+
 /// Voice Activity Detection result
 #[derive(Debug, Clone, Copy)]
 pub struct VadResult {
     /// Is speech detected
     pub is_speech: bool,
-    /// RMS level (0.0 - 1.0)
+    /// RMS level on the meter's scale (see `display_level`), or with
+    /// Silero the speech probability (0.0 - 1.0)
     pub rms_level: f32,
+    /// The speech threshold on the same scale as `rms_level`.
+    pub threshold_level: f32,
+    /// This chunk alone is loud enough to count as speech (`is_speech`
+    /// also holds through the silence hangover).
+    pub above_threshold: bool,
 }
 
 /// Voice Activity Detector
 pub struct VoiceActivityDetector {
     /// Threshold for speech detection
     speech_threshold: f32,
-    /// Minimum silence duration before stopping (in frames)
-    silence_frames_threshold: usize,
-    /// Current silence frame count
-    silence_frames: usize,
+    /// Silence after speech before it ends, in ms.
+    silence_timeout_ms: u32,
+    /// Samples of silence since the last chunk over the threshold.
+    /// Counted in samples, not chunks: their length follows the
+    /// device's callbacks.
+    silence_samples: u64,
     /// Is currently in speech segment
     in_speech: bool,
+    /// Samples above the speech threshold since the last reset. Counts
+    /// only frames that are loud themselves, not the silence hangover.
+    speech_samples: u64,
+    /// Samples processed since the last reset.
+    position: u64,
+    /// Where those loud frames were, in samples since the last reset;
+    /// adjacent frames share a span.
+    speech_spans: Vec<Range<u64>>,
+    /// In adaptive mode, the noise the threshold follows (see
+    /// `audio::noise_floor`). Kept across recordings: the room is too.
+    noise: Option<NoiseFloor>,
+    backend: VadBackend,
+    /// Silero's model file (`set_silero_model`).
+    silero_model: Option<PathBuf>,
+    silero: Silero,
+    /// Why Silero was given up for the threshold, until taken.
+    fallback: Option<String>,
+}
+
+/// Silero's model. Read by whoever calls `begin_loading`, off the VAD's
+/// lock, and handed back through `install_silero`.
+enum Silero {
+    Unloaded,
+    /// Being read; the threshold decides meanwhile.
+    Loading,
+    Loaded(SileroDetector),
+    /// Not tried again until the backend is set anew.
+    Failed,
 }
 
 impl VoiceActivityDetector {
     pub fn new() -> Self {
         Self {
-            speech_threshold: 0.02,       // Adjust based on testing
-            silence_frames_threshold: 15, // ~1.5 seconds at 10fps
-            silence_frames: 0,
+            speech_threshold: DEFAULT_SPEECH_THRESHOLD,
+            silence_timeout_ms: DEFAULT_SILENCE_TIMEOUT_MS,
+            silence_samples: 0,
             in_speech: false,
+            speech_samples: 0,
+            position: 0,
+            speech_spans: Vec::new(),
+            noise: None,
+            backend: VadBackend::default(),
+            silero_model: None,
+            silero: Silero::Unloaded,
+            fallback: None,
         }
     }
 
     /// Process audio samples and detect voice activity
     pub fn process(&mut self, samples: &[i16]) -> VadResult {
-        let rms = self.calculate_rms(samples);
-        let is_speech = rms > self.speech_threshold;
+        let mut measured = None;
+        let (level, threshold_level, above_threshold) = match self.speech_probability(samples) {
+            Some(probability) => (
+                probability,
+                SILERO_THRESHOLD,
+                probability > SILERO_THRESHOLD,
+            ),
+            None => {
+                let rms = self.calculate_rms(samples);
+                let threshold = self.current_threshold();
+                measured = Some(rms);
+                (
+                    display_level(rms),
+                    display_level(threshold),
+                    rms > threshold,
+                )
+            }
+        };
+        let start = self.position;
+        self.position += samples.len() as u64;
 
-        if is_speech {
-            self.silence_frames = 0;
+        if above_threshold {
+            self.speech_samples += samples.len() as u64;
+            match self.speech_spans.last_mut() {
+                Some(span) if span.end == start => span.end = self.position,
+                _ => self.speech_spans.push(start..self.position),
+            }
+            self.silence_samples = 0;
             self.in_speech = true;
         } else if self.in_speech {
-            self.silence_frames += 1;
-            if self.silence_frames >= self.silence_frames_threshold {
+            self.silence_samples += samples.len() as u64;
+            if self.silence_samples * 1000 >= u64::from(self.silence_timeout_ms) * CHUNK_RATE {
                 self.in_speech = false;
             }
         }
-
-        // Use logarithmic scaling for better visual representation
-        // Human perception of loudness is logarithmic
-        let display_level = if rms > 0.001 {
-            // Convert to dB-like scale, then normalize to 0-1
-            // rms of 0.01 -> ~0.3, rms of 0.1 -> ~0.7, rms of 0.3 -> ~1.0
-            let db = 20.0 * rms.log10();
-            let normalized = (db + 40.0) / 30.0; // -40dB to -10dB range -> 0 to 1
-            normalized.clamp(0.0, 1.0)
-        } else {
-            0.0
-        };
+        if let (Some(noise), Some(rms)) = (self.noise.as_mut(), measured) {
+            noise.observe(rms, samples.len(), CHUNK_RATE as u32, self.in_speech);
+        }
 
         VadResult {
             is_speech: self.in_speech,
-            rms_level: display_level,
+            rms_level: level,
+            threshold_level,
+            above_threshold,
+        }
+    }
+
+    /// Silero's probability that the chunk is speech; None when the RMS
+    /// threshold decides, Silero's model not loaded (yet) included.
+    fn speech_probability(&mut self, samples: &[i16]) -> Option<f32> {
+        if self.backend != VadBackend::Silero {
+            return None;
+        }
+        let Silero::Loaded(detector) = &mut self.silero else {
+            return None;
+        };
+        match detector.process(samples) {
+            Ok(probability) => Some(probability),
+            Err(e) => {
+                self.silero = Silero::Failed;
+                self.fall_back(format!("Silero VAD failed: {}", e));
+                None
+            }
+        }
+    }
+
+    fn fall_back(&mut self, reason: String) {
+        tracing::warn!("{}; using the RMS threshold", reason);
+        self.fallback = Some(reason);
+    }
+
+    /// Why Silero was given up for the RMS threshold, once.
+    pub fn take_fallback(&mut self) -> Option<String> {
+        self.fallback.take()
+    }
+
+    pub fn backend(&self) -> VadBackend {
+        self.backend
+    }
+
+    /// Switch detectors, from the next chunk. Silero needs its model
+    /// loaded (`begin_loading`): one that failed is tried again, and
+    /// switching away unloads it.
+    pub fn set_backend(&mut self, backend: VadBackend) {
+        if backend != VadBackend::Silero || matches!(self.silero, Silero::Failed) {
+            self.silero = Silero::Unloaded;
+        }
+        self.backend = backend;
+    }
+
+    /// Where Silero's model is, for when it loads.
+    pub fn set_silero_model(&mut self, path: PathBuf) {
+        self.silero_model = Some(path);
+    }
+
+    /// The model file to read, when Silero is on and waits for one; the
+    /// caller reads it (`silero::load`) without holding the VAD and
+    /// passes the result to `install_silero`. None otherwise, or when no
+    /// model is configured, which falls back to the threshold.
+    pub fn begin_loading(&mut self) -> Option<PathBuf> {
+        if self.backend != VadBackend::Silero || !matches!(self.silero, Silero::Unloaded) {
+            return None;
+        }
+        match self.silero_model.clone() {
+            Some(path) => {
+                self.silero = Silero::Loading;
+                Some(path)
+            }
+            None => {
+                self.silero = Silero::Failed;
+                self.fall_back("No Silero VAD model configured".to_string());
+                None
+            }
+        }
+    }
+
+    /// Use what `begin_loading` asked for, from the next chunk. Dropped
+    /// when the backend changed meanwhile.
+    pub fn install_silero(&mut self, loaded: Result<SileroDetector, String>) {
+        if !matches!(self.silero, Silero::Loading) {
+            return;
+        }
+        self.silero = match loaded {
+            Ok(detector) => {
+                tracing::info!("Silero VAD loaded");
+                Silero::Loaded(detector)
+            }
+            Err(e) => {
+                self.fall_back(e);
+                Silero::Failed
+            }
+        };
+    }
+
+    /// The RMS over which a chunk is speech, unless adaptive.
+    pub fn threshold(&self) -> f32 {
+        self.speech_threshold
+    }
+
+    /// The RMS over which a chunk is speech now: in adaptive mode the
+    /// noise's, once some silence was heard.
+    pub fn current_threshold(&self) -> f32 {
+        self.noise
+            .as_ref()
+            .and_then(NoiseFloor::threshold)
+            .unwrap_or(self.speech_threshold)
+    }
+
+    /// Follow the noise floor, the threshold `factor` times over it, or
+    /// with None go back to the set threshold. Switching the factor
+    /// keeps the floor learned so far.
+    pub fn set_adaptive(&mut self, factor: Option<f32>) {
+        match (factor, self.noise.as_mut()) {
+            (Some(factor), Some(noise)) => noise.set_factor(factor),
+            (factor, _) => self.noise = factor.map(NoiseFloor::new),
         }
     }
 
+    /// Set the speech threshold (RMS, 0 to 1). Takes effect from the
+    /// next chunk; what was detected so far stands.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.speech_threshold = threshold;
+    }
+
+    /// Silence after speech before it ends, in ms.
+    pub fn silence_timeout_ms(&self) -> u32 {
+        self.silence_timeout_ms
+    }
+
+    /// Set how long a silence ends the speech, in ms. Takes effect from
+    /// the next chunk, a silence under way included.
+    pub fn set_silence_timeout_ms(&mut self, ms: u32) {
+        self.silence_timeout_ms = ms;
+    }
+
     /// Calculate RMS (Root Mean Square) of samples
     fn calculate_rms(&self, samples: &[i16]) -> f32 {
-        if samples.is_empty() {
-            return 0.0;
-        }
+        rms(samples)
+    }
 
-        let sum: f64 = samples
-            .iter()
-            .map(|&s| {
-                let normalized = s as f64 / i16::MAX as f64;
-                normalized * normalized
-            })
-            .sum();
+    /// Detected speech since the last reset, in milliseconds
+    pub fn speech_ms(&self, sample_rate: u32) -> u64 {
+        self.speech_samples * 1000 / sample_rate.max(1) as u64
+    }
 
-        (sum / samples.len() as f64).sqrt() as f32
+    /// Detected speech since the last reset, as ms ranges from the
+    /// reset.
+    pub fn speech_spans_ms(&self, sample_rate: u32) -> Vec<Range<u64>> {
+        let to_ms = |samples: u64| samples * 1000 / sample_rate.max(1) as u64;
+        self.speech_spans
+            .iter()
+            .map(|span| to_ms(span.start)..to_ms(span.end))
+            .collect()
     }
 
     /// Reset the VAD state
     pub fn reset(&mut self) {
-        self.silence_frames = 0;
+        self.silence_samples = 0;
         self.in_speech = false;
+        self.speech_samples = 0;
+        self.position = 0;
+        self.speech_spans.clear();
+        if let Silero::Loaded(detector) = &mut self.silero {
+            detector.reset();
+        }
+    }
+
+    /// Reset, as though it happened `samples` ago: for the silence a
+    /// continuous recording keeps when it trims the capture.
+    pub fn reset_to(&mut self, samples: u64) {
+        self.reset();
+        self.position = samples;
+    }
+}
+
+/// Samples per chunk `benchmark` feeds: 10 ms, a typical callback.
+const BENCHMARK_CHUNK: usize = 160;
+
+/// What a backend costs, from `benchmark`. `benchmark_vad` payload.
+/// Frontend mirror: `VadBenchmark`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VadBenchmark {
+    pub backend: VadBackend,
+    /// Loading the model, for Silero.
+    pub load_ms: f64,
+    /// Mean time per 10 ms chunk, in µs.
+    pub chunk_us: f64,
+    /// That as a share of one core in real time (%).
+    pub cpu_percent: f64,
+    /// Why Silero fell back: the timings are then the threshold's.
+    pub error: Option<String>,
+}
+
+/// Time `backend` over `samples` (16 kHz), fed 10 ms at a time.
+pub fn benchmark(
+    backend: VadBackend,
+    silero_model: Option<PathBuf>,
+    samples: &[i16],
+) -> VadBenchmark {
+    let mut vad = VoiceActivityDetector::new();
+    if let Some(path) = silero_model {
+        vad.set_silero_model(path);
+    }
+    vad.set_backend(backend);
+    let start = Instant::now();
+    if let Some(path) = vad.begin_loading() {
+        vad.install_silero(silero::load(&path));
+    }
+    let load_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let start = Instant::now();
+    let count = samples
+        .chunks(BENCHMARK_CHUNK)
+        .map(|chunk| vad.process(chunk))
+        .count();
+    let chunk_us = start.elapsed().as_secs_f64() * 1e6 / count.max(1) as f64;
+    let chunk_duration_us = BENCHMARK_CHUNK as f64 * 1e6 / CHUNK_RATE as f64;
+    VadBenchmark {
+        backend,
+        load_ms,
+        chunk_us,
+        cpu_percent: chunk_us / chunk_duration_us * 100.0,
+        error: vad.take_fallback(),
     }
 }
 
@@ -109,6 +486,67 @@ mod tests {
         assert!(vad.calculate_rms(&loud) > 0.9);
     }
 
+    #[test]
+    fn test_silent_buffer_check() {
+        assert!(is_silent_buffer(&[]));
+        assert!(is_silent_buffer(&vec![0; 16000]));
+        // Idle-mic hiss of a few LSBs is still silence
+        let hiss: Vec<i16> = (0..16000)
+            .map(|i| if i % 2 == 0 { 40 } else { -40 })
+            .collect();
+        assert!(is_silent_buffer(&hiss));
+        // Quiet speech is not
+        assert!(!is_silent_buffer(&vec![1000; 16000]));
+    }
+
+    #[test]
+    fn test_speech_time_excludes_hangover() {
+        let mut vad = VoiceActivityDetector::new();
+        vad.process(&vec![5000; 1600]); // 100 ms loud
+        vad.process(&vec![0; 1600]); // hangover, still `is_speech`
+        assert_eq!(vad.speech_ms(16000), 100);
+        vad.reset();
+        assert_eq!(vad.speech_ms(16000), 0);
+    }
+
+    #[test]
+    fn test_speech_spans() {
+        let mut vad = VoiceActivityDetector::new();
+        vad.process(&vec![0; 1600]);
+        vad.process(&vec![5000; 1600]);
+        vad.process(&vec![5000; 1600]);
+        vad.process(&vec![0; 3200]); // hangover isn't speech
+        vad.process(&vec![5000; 800]);
+        assert_eq!(vad.speech_spans_ms(16000), [100..300, 500..550]);
+        vad.reset();
+        vad.process(&vec![5000; 1600]);
+        assert_eq!(vad.speech_spans_ms(16000)[0], 0..100);
+    }
+
+    #[test]
+    fn test_skip_reason_matrix() {
+        use SkipReason::*;
+        let min = DEFAULT_MIN_SPEECH_MS as u64;
+        // (duration, speech, force) -> expected
+        let cases = [
+            (200, 0, false, Some(TooShort)),
+            (200, 200, false, Some(TooShort)),
+            (200, 0, true, Some(TooShort)),
+            (3000, 0, false, Some(NoSpeechDetected)),
+            (3000, min - 1, false, Some(NoSpeechDetected)),
+            (3000, min, false, None),
+            (3000, 0, true, None),
+            (600, 500, false, None),
+        ];
+        for (duration, speech, force, expected) in cases {
+            assert_eq!(
+                skip_reason(duration, speech, min, force),
+                expected,
+                "duration={duration} speech={speech} force={force}"
+            );
+        }
+    }
+
     #[test]
     fn test_speech_detection() {
         let mut vad = VoiceActivityDetector::new();
@@ -123,4 +561,228 @@ mod tests {
         let result = vad.process(&loud);
         assert!(result.is_speech);
     }
+
+    #[test]
+    fn test_display_scale() {
+        assert_eq!(display_level(0.0), 0.0);
+        assert_eq!(display_level(0.001), 0.0);
+        assert_eq!(display_level(0.01), 0.0);
+        // The default speech threshold, -34 dBFS
+        assert!((display_level(0.02) - 0.2).abs() < 1e-3);
+        assert!((display_level(0.1) - 2.0 / 3.0).abs() < 1e-4);
+        assert_eq!(display_level(0.4), 1.0);
+        assert_eq!(display_level(1.0), 1.0);
+        let rising = [0.011, 0.02, 0.05, 0.1, 0.3];
+        for pair in rising.windows(2) {
+            assert!(display_level(pair[0]) < display_level(pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_threshold_shares_the_level_scale() {
+        let mut vad = VoiceActivityDetector::new();
+        let threshold = vad.speech_threshold;
+        // A constant signal's rms is its amplitude
+        let at = |rms: f32| vec![(rms * i16::MAX as f32) as i16; 1600];
+
+        let quiet = vad.process(&at(threshold * 0.8));
+        assert!(!quiet.above_threshold);
+        assert!(quiet.rms_level < quiet.threshold_level);
+
+        let loud = vad.process(&at(threshold * 1.25));
+        assert!(loud.above_threshold);
+        assert!(loud.rms_level > loud.threshold_level);
+        assert_eq!(loud.threshold_level, display_level(threshold));
+
+        // Hangover: still speech, but no longer above the threshold
+        let trailing = vad.process(&at(threshold * 0.8));
+        assert!(trailing.is_speech);
+        assert!(!trailing.above_threshold);
+    }
+
+    #[test]
+    fn test_silence_timeout_counts_time_not_chunks() {
+        let mut vad = VoiceActivityDetector::new();
+        vad.set_silence_timeout_ms(300);
+        vad.process(&vec![5000; 1600]);
+        // 10 ms callbacks: 29 of them are still the hangover...
+        for _ in 0..29 {
+            assert!(vad.process(&vec![0; 160]).is_speech);
+        }
+        // ...the 30th ends it
+        assert!(!vad.process(&vec![0; 160]).is_speech);
+
+        // Same with 100 ms chunks
+        vad.process(&vec![5000; 1600]);
+        assert!(vad.process(&vec![0; 1600]).is_speech);
+        assert!(vad.process(&vec![0; 1600]).is_speech);
+        assert!(!vad.process(&vec![0; 1600]).is_speech);
+    }
+
+    #[test]
+    fn test_default_hangover_is_1500_ms() {
+        let mut vad = VoiceActivityDetector::new();
+        assert_eq!(vad.silence_timeout_ms(), DEFAULT_SILENCE_TIMEOUT_MS);
+        vad.process(&vec![5000; 1600]);
+        let hangover = (0..300)
+            .take_while(|_| vad.process(&vec![0; 160]).is_speech)
+            .count();
+        // The chunk that reaches 1500 ms ends it
+        assert_eq!((hangover + 1) * 10, 1500);
+    }
+
+    #[test]
+    fn test_set_threshold() {
+        let mut vad = VoiceActivityDetector::new();
+        assert_eq!(vad.threshold(), DEFAULT_SPEECH_THRESHOLD);
+        let chunk = vec![(0.01 * i16::MAX as f32) as i16; 1600];
+        assert!(!vad.process(&chunk).above_threshold);
+        vad.set_threshold(0.005);
+        let result = vad.process(&chunk);
+        assert!(result.above_threshold);
+        assert_eq!(result.threshold_level, display_level(0.005));
+        assert_eq!(vad.speech_ms(16000), 100);
+    }
+
+    #[test]
+    fn test_reset_to_keeps_the_spans_in_place() {
+        let mut vad = VoiceActivityDetector::new();
+        vad.process(&vec![5000; 1600]);
+        vad.reset_to(4800);
+        assert_eq!(vad.speech_ms(16000), 0);
+        vad.process(&vec![5000; 1600]);
+        assert_eq!(vad.speech_spans_ms(16000)[0], 300..400);
+    }
+
+    /// Every frame is speech, with this probability.
+    struct Constant(f32);
+
+    impl silero::SpeechModel for Constant {
+        fn probability(&mut self, _: &[f32]) -> Result<f32, String> {
+            Ok(self.0)
+        }
+
+        fn reset(&mut self) {}
+    }
+
+    #[test]
+    fn test_silero_decides_whatever_the_level() {
+        let mut vad = VoiceActivityDetector::new();
+        vad.set_backend(VadBackend::Silero);
+        vad.silero = Silero::Loaded(SileroDetector::new(Box::new(Constant(0.9))));
+        let result = vad.process(&vec![10; 1600]);
+        assert!(result.above_threshold && result.is_speech);
+        assert_eq!(result.rms_level, 0.9);
+        assert_eq!(result.threshold_level, SILERO_THRESHOLD);
+        // Back to the threshold
+        vad.set_backend(VadBackend::Rms);
+        assert!(!vad.process(&vec![10; 1600]).above_threshold);
+    }
+
+    /// A chunk of 10 ms at `rms` (0 to 1).
+    fn chunk(rms: f32) -> Vec<i16> {
+        vec![(rms * i16::MAX as f32) as i16; 160]
+    }
+
+    #[test]
+    fn test_adaptive_threshold_follows_the_noise() {
+        let mut vad = VoiceActivityDetector::new();
+        vad.set_adaptive(Some(3.0));
+        // The set threshold until some silence was heard
+        assert_eq!(vad.current_threshold(), DEFAULT_SPEECH_THRESHOLD);
+        // A quiet room: the threshold comes down to it
+        for _ in 0..300 {
+            assert!(!vad.process(&chunk(0.003)).is_speech);
+        }
+        assert!((vad.current_threshold() - 0.009).abs() < 0.0005);
+        // Speech under the old threshold now registers
+        assert!(vad.process(&chunk(0.015)).is_speech);
+        // A long monologue doesn't move it
+        for _ in 0..1000 {
+            vad.process(&chunk(0.05));
+            vad.process(&chunk(0.004));
+        }
+        assert!((vad.current_threshold() - 0.009).abs() < 0.0005);
+        // Back to the set one
+        vad.set_adaptive(None);
+        assert_eq!(vad.current_threshold(), DEFAULT_SPEECH_THRESHOLD);
+    }
+
+    #[test]
+    fn test_adaptive_threshold_holds_in_speech() {
+        let mut vad = VoiceActivityDetector::new();
+        vad.set_adaptive(Some(3.0));
+        for _ in 0..300 {
+            vad.process(&chunk(0.003));
+        }
+        // Loud for long, with no pause: speech, and nothing is learned
+        for _ in 0..1000 {
+            assert!(vad.process(&chunk(0.03)).is_speech);
+        }
+        assert!((vad.current_threshold() - 0.009).abs() < 0.0005);
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let samples = vec![1000; 16000];
+        let rms = benchmark(VadBackend::Rms, None, &samples);
+        assert_eq!(rms.backend, VadBackend::Rms);
+        assert_eq!(rms.error, None);
+        assert!(rms.cpu_percent >= 0.0);
+        // No model: timed as the threshold, and says so
+        let silero = benchmark(VadBackend::Silero, None, &samples);
+        assert!(silero.error.is_some());
+    }
+
+    /// What the app does off the VAD's lock, here in line.
+    fn load(vad: &mut VoiceActivityDetector) {
+        if let Some(path) = vad.begin_loading() {
+            vad.install_silero(silero::load(&path));
+        }
+    }
+
+    #[test]
+    fn test_silero_falls_back_to_the_threshold() {
+        let mut vad = VoiceActivityDetector::new();
+        vad.set_silero_model(PathBuf::from("/nonexistent/silero_vad.onnx"));
+        vad.set_backend(VadBackend::Silero);
+        load(&mut vad);
+        assert!(vad.process(&vec![5000; 1600]).above_threshold);
+        assert!(!vad.process(&vec![10; 1600]).above_threshold);
+        assert!(vad.take_fallback().is_some());
+        // Said once, and not tried again
+        load(&mut vad);
+        vad.process(&vec![10; 1600]);
+        assert_eq!(vad.take_fallback(), None);
+        // Tried again when set anew
+        vad.set_backend(VadBackend::Silero);
+        load(&mut vad);
+        assert!(vad.take_fallback().is_some());
+    }
+
+    #[test]
+    fn test_silero_loads_off_the_chunks() {
+        let mut vad = VoiceActivityDetector::new();
+        vad.set_silero_model(PathBuf::from("/models/silero_vad.onnx"));
+        vad.set_backend(VadBackend::Silero);
+        // Chunks never load it: the threshold decides until it's in
+        assert!(!vad.process(&vec![10; 1600]).above_threshold);
+        let path = vad.begin_loading().unwrap();
+        assert_eq!(path, PathBuf::from("/models/silero_vad.onnx"));
+        // Asked once
+        assert_eq!(vad.begin_loading(), None);
+        assert!(!vad.process(&vec![10; 1600]).above_threshold);
+        vad.install_silero(Ok(SileroDetector::new(Box::new(Constant(0.9)))));
+        assert!(vad.process(&vec![10; 1600]).above_threshold);
+        assert_eq!(vad.take_fallback(), None);
+
+        // Switched away while it loads: the late model is dropped
+        vad.set_backend(VadBackend::Rms);
+        vad.set_backend(VadBackend::Silero);
+        vad.begin_loading().unwrap();
+        vad.set_backend(VadBackend::Rms);
+        vad.install_silero(Ok(SileroDetector::new(Box::new(Constant(0.9)))));
+        vad.set_backend(VadBackend::Silero);
+        assert!(!vad.process(&vec![10; 1600]).above_threshold);
+    }
 }
@@ -0,0 +1,731 @@
+//! `s2tui --transcribe file.wav` while the GUI is running.
+//!
+//! A second process must not load a second whisper context next to the
+//! running one (twice the memory, and the two fight over the GPU).
+//! Instead it connects to the running instance's local socket, sends
+//! the request, and prints what comes back: the job goes through the
+//! instance's transcription queue like a file dropped on the overlay.
+//!
+//! Framing is a 4-byte big-endian length followed by that many bytes of
+//! JSON. The client sends one `Request`; the instance answers with
+//! `Response::Queued` (jobs ahead of this one) and then exactly one
+//! `Done` or `Failed`.
+//!
+//! Each connection waits on a thread of its own for its job, so no
+//! more are taken than the queue holds: past that, a client is told
+//! the queue is full (`Failed`) without waiting.
+//!
+//! The socket is a Unix domain socket in a directory of our own (under
+//! the per-user runtime directory, else the temp directory), which both
+//! sides refuse unless this user owns it with mode 0700. The instance
+//! binds under a umask that keeps the socket its owner's, and hangs up
+//! on any peer running as another user. Windows has no forwarding yet:
+//! the client says so and exits with `EXIT_UNAVAILABLE`.
+
+use crate::whisper::jobs::QueueError;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Command-line switch for a one-shot file transcription.
+pub const TRANSCRIBE_ARG: &str = "--transcribe";
+
+/// The transcript was printed.
+pub const EXIT_OK: i32 = 0;
+/// The running instance tried and failed (no model, unreadable file,
+/// queue full…).
+pub const EXIT_FAILED: i32 = 1;
+/// No instance to talk to, or it stopped answering.
+pub const EXIT_UNAVAILABLE: i32 = 2;
+
+/// Largest frame either side accepts.
+pub const MAX_FRAME_BYTES: u32 = 16 * 1024 * 1024;
+/// How long the instance waits for a connected client's request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the client waits for each reply. Generous: the reply to a
+/// queued job only comes once every job ahead of it is done.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Request {
+    /// Transcribe a WAV file (absolute path: the instance's working
+    /// directory isn't the client's).
+    Transcribe { path: PathBuf },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Response {
+    /// Accepted; `ahead` transcriptions are queued or running before it.
+    Queued {
+        ahead: usize,
+    },
+    Done {
+        text: String,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+/// The file passed with `--transcribe`, if any.
+pub fn transcribe_arg(args: &[String]) -> Option<PathBuf> {
+    args.iter()
+        .position(|a| a == TRANSCRIBE_ARG)
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from)
+}
+
+pub fn write_frame<T: Serialize>(stream: &mut impl Write, message: &T) -> io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+    let len = u32::try_from(body.len())
+        .ok()
+        .filter(|len| *len <= MAX_FRAME_BYTES)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    stream.write_all(&len.to_be_bytes())?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+pub fn read_frame<T: for<'de> Deserialize<'de>>(stream: &mut impl Read) -> io::Result<T> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if len > MAX_FRAME_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes is over the limit"),
+        ));
+    }
+    let mut body = vec![0u8; len as usize];
+    stream.read_exact(&mut body)?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// What the instance side needs from the app.
+pub trait JobRunner {
+    /// Transcriptions queued or running right now.
+    fn queue_length(&self) -> usize;
+    /// Most transcriptions that may be queued or running at once.
+    fn queue_depth(&self) -> usize;
+    /// Run the file through the queue; blocks until it's done.
+    fn transcribe(&self, path: &Path) -> Result<String, String>;
+}
+
+/// The answer for a new connection when `connections` are already
+/// being served and it can't be: the queue is full, or will be once
+/// theirs are in it.
+pub fn refusal(runner: &impl JobRunner, connections: usize) -> Option<Response> {
+    let max_depth = runner.queue_depth();
+    let pending = runner.queue_length().max(connections);
+    (pending >= max_depth).then(|| Response::Failed {
+        message: QueueError::Busy { pending, max_depth }.to_string(),
+    })
+}
+
+/// Instance side of one connection.
+pub fn serve(stream: &mut (impl Read + Write), runner: &impl JobRunner) -> io::Result<()> {
+    let Request::Transcribe { path } = read_frame(stream)?;
+    tracing::info!(
+        "Transcription forwarded from the command line: {}",
+        path.display()
+    );
+    write_frame(
+        stream,
+        &Response::Queued {
+            ahead: runner.queue_length(),
+        },
+    )?;
+    let response = match runner.transcribe(&path) {
+        Ok(text) => Response::Done { text },
+        Err(message) => Response::Failed { message },
+    };
+    write_frame(stream, &response)
+}
+
+/// Client side: send the request, print progress to `err` and the
+/// transcript to `out`, and return the exit code.
+pub fn forward(
+    stream: &mut (impl Read + Write),
+    path: &Path,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> i32 {
+    let request = Request::Transcribe {
+        path: path.to_path_buf(),
+    };
+    if let Err(e) = write_frame(stream, &request) {
+        let _ = writeln!(err, "s2tui: could not reach the running instance: {e}");
+        return EXIT_UNAVAILABLE;
+    }
+    loop {
+        match read_frame(stream) {
+            Ok(Response::Queued { ahead: 0 }) => {}
+            Ok(Response::Queued { ahead }) => {
+                let _ = writeln!(err, "s2tui: queued, {ahead} transcription(s) ahead");
+            }
+            Ok(Response::Done { text }) => {
+                let _ = writeln!(out, "{text}");
+                return EXIT_OK;
+            }
+            Ok(Response::Failed { message }) => {
+                let _ = writeln!(err, "s2tui: {message}");
+                return EXIT_FAILED;
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                let _ = writeln!(err, "s2tui: timed out waiting for the running instance");
+                return EXIT_UNAVAILABLE;
+            }
+            Err(e) => {
+                let _ = writeln!(err, "s2tui: the running instance stopped answering: {e}");
+                return EXIT_UNAVAILABLE;
+            }
+        }
+    }
+}
+
+/// Where the instance listens: `s2tui/` under the per-user runtime
+/// directory, else `s2tui-<uid>/` under the temp directory, created if
+/// missing. Errors when that directory isn't private (see
+/// `private_dir`): on a shared `/tmp` anyone could have made it first.
+#[cfg(unix)]
+fn socket_path() -> io::Result<PathBuf> {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("s2tui"),
+        None => std::env::temp_dir().join(format!("s2tui-{}", current_uid())),
+    };
+    private_dir(&dir)?;
+    Ok(dir.join("s2tui.sock"))
+}
+
+/// Create `dir` with mode 0700, or check that the one already there is
+/// a real directory (not a symlink) owned by this user that no one else
+/// can enter.
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e),
+    }
+    let metadata = std::fs::symlink_metadata(dir)?;
+    let refuse = |why: &str| {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} {why}", dir.display()),
+        ))
+    };
+    if !metadata.file_type().is_dir() {
+        return refuse("is not a directory");
+    }
+    if metadata.uid() != current_uid() {
+        return refuse("belongs to another user");
+    }
+    if metadata.mode() & 0o077 != 0 {
+        return refuse("is open to other users");
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail.
+    unsafe { libc::getuid() }
+}
+
+/// User id of the process at the other end of `stream`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &std::os::unix::net::UnixStream) -> io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `cred` and `len` outlive the call, and `len` is the size
+    // of the buffer SO_PEERCRED fills.
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// User id of the process at the other end of `stream`.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn peer_uid(stream: &std::os::unix::net::UnixStream) -> io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: both out-pointers are valid for the duration of the call.
+    let result = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// `--transcribe` entry point, run instead of the GUI. Returns the exit
+/// code.
+pub fn run_forwarded(path: &Path) -> i32 {
+    let path = match std::path::absolute(path) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("s2tui: {}: {e}", path.display());
+            return EXIT_FAILED;
+        }
+    };
+    connect_and_forward(&path)
+}
+
+#[cfg(unix)]
+fn connect_and_forward(path: &Path) -> i32 {
+    use std::os::unix::net::UnixStream;
+
+    let socket = match socket_path() {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("s2tui: not connecting to the running instance: {e}");
+            return EXIT_UNAVAILABLE;
+        }
+    };
+    let mut stream = match UnixStream::connect(socket) {
+        Ok(stream) => stream,
+        Err(_) => {
+            eprintln!("s2tui: S2Tui isn't running; start it, then try again");
+            return EXIT_UNAVAILABLE;
+        }
+    };
+    if let Err(e) = stream.set_read_timeout(Some(REPLY_TIMEOUT)) {
+        eprintln!("s2tui: could not set the reply timeout: {e}");
+    }
+    forward(&mut stream, path, &mut io::stdout(), &mut io::stderr())
+}
+
+#[cfg(not(unix))]
+fn connect_and_forward(_path: &Path) -> i32 {
+    eprintln!("s2tui: --transcribe isn't supported on this platform yet");
+    EXIT_UNAVAILABLE
+}
+
+/// Runs forwarded jobs through `transcribe_file`.
+struct AppRunner(tauri::AppHandle);
+
+impl JobRunner for AppRunner {
+    fn queue_length(&self) -> usize {
+        use tauri::Manager;
+        self.0
+            .state::<crate::state::AppState>()
+            .whisper
+            .queue_length()
+    }
+
+    fn queue_depth(&self) -> usize {
+        use tauri::Manager;
+        self.0
+            .state::<crate::state::AppState>()
+            .whisper
+            .queue_depth()
+    }
+
+    fn transcribe(&self, path: &Path) -> Result<String, String> {
+        use tauri::Manager;
+        tauri::async_runtime::block_on(crate::commands::transcribe_file(
+            path.to_string_lossy().into_owned(),
+            self.0.state(),
+            self.0.clone(),
+        ))
+    }
+}
+
+/// Listen for forwarded requests, one thread per connection while the
+/// queue has room (see `refusal`). Leaves the socket alone when another
+/// instance already answers on it.
+#[cfg(unix)]
+pub fn start_server(app: &tauri::AppHandle) {
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let socket = match socket_path() {
+        Ok(socket) => socket,
+        Err(e) => {
+            tracing::warn!("Command-line forwarding disabled: {e}");
+            return;
+        }
+    };
+    if UnixStream::connect(&socket).is_ok() {
+        tracing::warn!("Another instance listens on {}", socket.display());
+        return;
+    }
+    // Left behind by a previous run that didn't exit cleanly
+    let _ = std::fs::remove_file(&socket);
+    // The socket is created 0600 rather than chmod-ed after the bind,
+    // which would leave it open in between. The umask is process-wide,
+    // so it's restored straight away.
+    // SAFETY: umask has no preconditions and cannot fail.
+    let umask = unsafe { libc::umask(0o177) };
+    let bound = UnixListener::bind(&socket);
+    // SAFETY: as above.
+    unsafe { libc::umask(umask) };
+    let listener = match bound {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::warn!(
+                "Command-line forwarding disabled ({}): {e}",
+                socket.display()
+            );
+            return;
+        }
+    };
+    let app = app.clone();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let spawned = std::thread::Builder::new()
+        .name("cli-listener".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        tracing::warn!("Command-line connection failed: {e}");
+                        continue;
+                    }
+                };
+                match peer_uid(&stream) {
+                    Ok(uid) if uid == current_uid() => {}
+                    Ok(uid) => {
+                        tracing::warn!("Command-line connection from user {uid} refused");
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Command-line connection refused: {e}");
+                        continue;
+                    }
+                }
+                let runner = AppRunner(app.clone());
+                if let Some(response) = refusal(&runner, connections.load(Ordering::Acquire)) {
+                    tracing::info!("Forwarded transcription refused: the queue is full");
+                    let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+                    let _ = write_frame(&mut stream, &response);
+                    continue;
+                }
+                connections.fetch_add(1, Ordering::AcqRel);
+                let served = Arc::clone(&connections);
+                let spawned = std::thread::Builder::new()
+                    .name("cli-request".into())
+                    .spawn(move || {
+                        let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+                        if let Err(e) = serve(&mut stream, &runner) {
+                            tracing::warn!("Forwarded transcription aborted: {e}");
+                        }
+                        served.fetch_sub(1, Ordering::AcqRel);
+                    });
+                if let Err(e) = spawned {
+                    tracing::warn!("Forwarded transcription dropped: {e}");
+                    connections.fetch_sub(1, Ordering::AcqRel);
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        tracing::warn!("Command-line forwarding disabled: {e}");
+    }
+}
+
+#[cfg(not(unix))]
+pub fn start_server(_app: &tauri::AppHandle) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    struct FakeRunner {
+        ahead: usize,
+        result: Result<String, String>,
+    }
+
+    impl JobRunner for FakeRunner {
+        fn queue_length(&self) -> usize {
+            self.ahead
+        }
+
+        fn queue_depth(&self) -> usize {
+            3
+        }
+
+        fn transcribe(&self, path: &Path) -> Result<String, String> {
+            assert_eq!(path, Path::new("/recordings/memo.wav"));
+            self.result.clone()
+        }
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn transcribe_argument() {
+        assert_eq!(
+            transcribe_arg(&strings(&["s2tui", "--transcribe", "a.wav"])),
+            Some(PathBuf::from("a.wav"))
+        );
+        assert_eq!(transcribe_arg(&strings(&["s2tui", "--transcribe"])), None);
+        assert_eq!(transcribe_arg(&strings(&["s2tui", "--portable"])), None);
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, &Response::Queued { ahead: 2 }).unwrap();
+        write_frame(
+            &mut buffer,
+            &Response::Done {
+                text: "héllo".into(),
+            },
+        )
+        .unwrap();
+        let mut reader = Cursor::new(buffer);
+        assert_eq!(
+            read_frame::<Response>(&mut reader).unwrap(),
+            Response::Queued { ahead: 2 }
+        );
+        assert_eq!(
+            read_frame::<Response>(&mut reader).unwrap(),
+            Response::Done {
+                text: "héllo".into()
+            }
+        );
+        // Nothing left
+        assert!(read_frame::<Response>(&mut reader).is_err());
+    }
+
+    #[test]
+    fn wire_format() {
+        let mut buffer = Vec::new();
+        write_frame(
+            &mut buffer,
+            &Request::Transcribe {
+                path: "/a.wav".into(),
+            },
+        )
+        .unwrap();
+        let body = br#"{"kind":"transcribe","path":"/a.wav"}"#;
+        assert_eq!(buffer[..4], (body.len() as u32).to_be_bytes());
+        assert_eq!(&buffer[4..], body);
+    }
+
+    #[test]
+    fn oversized_frames_are_refused() {
+        let mut buffer = (MAX_FRAME_BYTES + 1).to_be_bytes().to_vec();
+        buffer.extend_from_slice(b"{}");
+        let error = read_frame::<Response>(&mut Cursor::new(buffer)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn a_full_queue_turns_connections_away() {
+        let runner = FakeRunner {
+            ahead: 2,
+            result: Ok(String::new()),
+        };
+        assert_eq!(refusal(&runner, 0), None);
+        assert_eq!(refusal(&runner, 2), None);
+        // Three connections waiting fill the queue before their jobs do
+        assert_eq!(
+            refusal(&runner, 3),
+            Some(Response::Failed {
+                message: "Transcription queue is full (3 pending)".into()
+            })
+        );
+        let full = FakeRunner {
+            ahead: 3,
+            result: Ok(String::new()),
+        };
+        assert!(refusal(&full, 0).is_some());
+    }
+
+    #[test]
+    fn truncated_frame_is_an_error() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, &Response::Done { text: "cut".into() }).unwrap();
+        buffer.truncate(buffer.len() - 2);
+        assert!(read_frame::<Response>(&mut Cursor::new(buffer)).is_err());
+    }
+
+    #[cfg(unix)]
+    mod socket_pair {
+        use super::*;
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::UnixStream;
+
+        #[test]
+        fn the_socket_directory_must_be_private() {
+            let temp = tempfile::tempdir().unwrap();
+            let dir = temp.path().join("s2tui");
+            private_dir(&dir).unwrap();
+            let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+            // Already there and private: fine
+            private_dir(&dir).unwrap();
+
+            std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+            assert_eq!(
+                private_dir(&dir).unwrap_err().kind(),
+                io::ErrorKind::PermissionDenied
+            );
+
+            let link = temp.path().join("link");
+            let target = temp.path().join("target");
+            std::fs::DirBuilder::new().create(&target).unwrap();
+            std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o700)).unwrap();
+            std::os::unix::fs::symlink(&target, &link).unwrap();
+            assert!(private_dir(&link).is_err());
+        }
+
+        #[test]
+        fn the_peer_is_this_user() {
+            let (client, _instance) = UnixStream::pair().unwrap();
+            assert_eq!(peer_uid(&client).unwrap(), current_uid());
+        }
+
+        /// Serve `runner` on one end of a socket pair and forward from
+        /// the other: (exit code, stdout, stderr).
+        fn round_trip(runner: FakeRunner) -> (i32, String, String) {
+            let (mut client, mut instance) = UnixStream::pair().unwrap();
+            let server = std::thread::spawn(move || serve(&mut instance, &runner));
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let code = forward(
+                &mut client,
+                Path::new("/recordings/memo.wav"),
+                &mut out,
+                &mut err,
+            );
+            server.join().unwrap().unwrap();
+            (
+                code,
+                String::from_utf8(out).unwrap(),
+                String::from_utf8(err).unwrap(),
+            )
+        }
+
+        #[test]
+        fn transcript_is_printed() {
+            let (code, out, err) = round_trip(FakeRunner {
+                ahead: 0,
+                result: Ok("Meeting notes.".into()),
+            });
+            assert_eq!(code, EXIT_OK);
+            assert_eq!(out, "Meeting notes.\n");
+            assert_eq!(err, "");
+        }
+
+        #[test]
+        fn busy_instance_reports_the_queue_position() {
+            let (code, out, err) = round_trip(FakeRunner {
+                ahead: 2,
+                result: Ok("Later.".into()),
+            });
+            assert_eq!(code, EXIT_OK);
+            assert_eq!(out, "Later.\n");
+            assert!(err.contains("2 transcription(s) ahead"), "{err}");
+        }
+
+        #[test]
+        fn failure_sets_the_exit_code() {
+            let (code, out, err) = round_trip(FakeRunner {
+                ahead: 0,
+                result: Err("No model loaded".into()),
+            });
+            assert_eq!(code, EXIT_FAILED);
+            assert_eq!(out, "");
+            assert!(err.contains("No model loaded"), "{err}");
+        }
+
+        #[test]
+        fn silent_instance_times_out() {
+            let (mut client, _instance) = UnixStream::pair().unwrap();
+            client
+                .set_read_timeout(Some(Duration::from_millis(50)))
+                .unwrap();
+            let mut err = Vec::new();
+            let code = forward(
+                &mut client,
+                Path::new("/recordings/memo.wav"),
+                &mut Vec::new(),
+                &mut err,
+            );
+            assert_eq!(code, EXIT_UNAVAILABLE);
+            assert!(String::from_utf8(err).unwrap().contains("timed out"));
+        }
+
+        #[test]
+        fn instance_hanging_up_is_unavailable() {
+            let (mut client, instance) = UnixStream::pair().unwrap();
+            drop(instance);
+            let code = forward(
+                &mut client,
+                Path::new("/recordings/memo.wav"),
+                &mut Vec::new(),
+                &mut Vec::new(),
+            );
+            assert_eq!(code, EXIT_UNAVAILABLE);
+        }
+
+        #[test]
+        fn refused_connection_fails_at_once() {
+            let (mut client, mut instance) = UnixStream::pair().unwrap();
+            let runner = FakeRunner {
+                ahead: 3,
+                result: Ok(String::new()),
+            };
+            write_frame(&mut instance, &refusal(&runner, 0).unwrap()).unwrap();
+            let mut err = Vec::new();
+            let code = forward(
+                &mut client,
+                Path::new("/recordings/memo.wav"),
+                &mut Vec::new(),
+                &mut err,
+            );
+            assert_eq!(code, EXIT_FAILED);
+            assert!(String::from_utf8(err).unwrap().contains("queue is full"));
+        }
+
+        #[test]
+        fn garbage_request_is_rejected() {
+            let (mut client, mut instance) = UnixStream::pair().unwrap();
+            write_frame(&mut client, &serde_json::json!({ "kind": "reboot" })).unwrap();
+            let runner = FakeRunner {
+                ahead: 0,
+                result: Ok(String::new()),
+            };
+            assert!(serve(&mut instance, &runner).is_err());
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! The audio input: the devices, the gain and its control, the
+//! filters, the channel mode, the capture source (and the application
+//! it records), the noise calibration and the level monitor.
+
+use super::listen::{record_probe, ProbeError};
+use super::prelude::*;
+use super::settings::persist_and_broadcast;
+use crate::audio::{
+    AudioDevices, AudioFilters, CaptureApplication, CaptureSource, ChannelMode, NoiseCalibration,
+    CALIBRATION_SECS, MAX_GAIN_DB, MIN_GAIN_DB,
+};
+
+/// Fit the VAD's speech threshold to the microphone: record
+/// `CALIBRATION_SECS` while the user stays silent, and set the threshold
+/// a margin over the noise measured (see `audio::calibration`). Refused
+/// when something was heard meanwhile, or the room is too loud; the
+/// threshold is then left as it was.
+#[tauri::command]
+pub async fn calibrate_noise_floor(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<NoiseCalibration, String> {
+    let samples = record_probe(&state, &app, "noise-calibration", CALIBRATION_SECS)
+        .await
+        .map_err(|e| match e {
+            ProbeError::Busy => "Cannot calibrate while recording or transcribing".to_string(),
+            ProbeError::Capture(message) => message,
+        })?;
+    let calibration = crate::audio::calibrate(&samples, 16000).map_err(|e| {
+        tracing::warn!("Noise calibration refused: {}", e);
+        e.to_string()
+    })?;
+    tracing::info!(
+        "Noise floor {:.4}, speech threshold now {:.4}",
+        calibration.floor,
+        calibration.threshold
+    );
+    state.vad.write().set_threshold(calibration.threshold);
+    state.update_settings(|s| s.speech_threshold = calibration.threshold);
+    persist_and_broadcast(&state, &app)?;
+    Ok(calibration)
+}
+
+/// Send the input's level as `audio:monitor-level` about 15 times a
+/// second, without recording: the settings window's microphone test. A
+/// recording started meanwhile takes the stream over and the levels
+/// carry on. Runs until `stop_level_monitor`, or the window that started
+/// it closes.
+#[tauri::command]
+pub fn start_level_monitor(
+    window: tauri::WebviewWindow,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state
+        .audio_capture
+        .start_monitor(move |level| {
+            let _ = app.emit("audio:monitor-level", level);
+        })
+        .map_err(|e| e.to_string())?;
+    tracing::info!("Level monitor on for \"{}\"", window.label());
+    *state.level_monitor.lock() = Some(window.label().to_string());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_level_monitor(state: State<'_, AppState>) {
+    end_level_monitor(&state);
+}
+
+/// Stop the level monitor, if on.
+pub(crate) fn end_level_monitor(state: &AppState) {
+    if state.level_monitor.lock().take().is_some() {
+        tracing::info!("Level monitor off");
+    }
+    state.audio_capture.stop_monitor();
+}
+
+/// The input devices of every audio host, with the system default
+/// (the one recording uses) flagged. Hosts or devices that fail to
+/// answer come back as `warnings` instead of failing the call.
+#[tauri::command]
+pub async fn get_audio_devices() -> Result<AudioDevices, String> {
+    // ALSA probes every card: keep it off the runtime
+    let listed = tokio::task::spawn_blocking(crate::audio::list_input_devices)
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?;
+    for warning in &listed.warnings {
+        tracing::warn!("Audio devices: {}", warning);
+    }
+    Ok(listed)
+}
+
+/// Record from the input device `device_name`, an `AudioDevice::id`
+/// from `get_audio_devices` (names alone can repeat); `None` goes back
+/// to the system default. Remembered across restarts; a device missing
+/// when recording starts falls back to the default with an
+/// `audio:device-fallback` event. Refused while recording.
+#[tauri::command]
+pub async fn set_audio_device(
+    device_name: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if state.audio_capture.is_capturing() {
+        return Err("Stop recording before switching input devices".to_string());
+    }
+    if let Some(id) = &device_name {
+        let lookup = id.clone();
+        let present =
+            tokio::task::spawn_blocking(move || crate::audio::find_input_device(&lookup).is_some())
+                .await
+                .map_err(|e| format!("Task join error: {}", e))?;
+        if !present {
+            return Err(format!("No input device \"{id}\""));
+        }
+    }
+    tracing::info!("Input device: {:?}", device_name);
+    state
+        .audio_capture
+        .set_preferred_device(device_name.clone());
+    state.update_settings(|s| s.preferred_device = device_name);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Set the input gain, in dB from `MIN_GAIN_DB` to `MAX_GAIN_DB`. It
+/// applies at once, a running recording included; samples it pushes
+/// past full scale are clipped and reported with `audio:clipping`.
+#[tauri::command]
+pub fn set_input_gain(
+    gain_db: f32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if !(MIN_GAIN_DB..=MAX_GAIN_DB).contains(&gain_db) {
+        return Err(format!(
+            "Input gain must be between {MIN_GAIN_DB} and {MAX_GAIN_DB} dB (got {gain_db})"
+        ));
+    }
+    tracing::info!("Setting input gain: {} dB", gain_db);
+    state.audio_capture.set_gain(gain_db);
+    state.update_settings(|s| s.gain = gain_db);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Turn automatic gain control on or off. It levels the input towards
+/// -20 dBFS on top of the fixed gain, and applies at once, a running
+/// recording included.
+#[tauri::command]
+pub fn set_agc(enabled: bool, state: State<'_, AppState>, app: AppHandle) -> Result<(), String> {
+    tracing::info!("Automatic gain control: {}", enabled);
+    state.audio_capture.set_agc(enabled);
+    state.update_settings(|s| s.agc = enabled);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Record one of the input's channels instead of their mix, for
+/// interfaces whose other inputs are empty; checked against the
+/// channel count of the device recording opens. Applies at once, a
+/// running recording included. A device switched to later that lacks
+/// the channel is mixed.
+#[tauri::command]
+pub async fn set_channel_mode(
+    mode: ChannelMode,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if mode != ChannelMode::Mix {
+        let preferred = state.get_settings().preferred_device;
+        let channels =
+            tokio::task::spawn_blocking(move || crate::audio::input_channels(preferred.as_deref()))
+                .await
+                .map_err(|e| format!("Task join error: {}", e))?
+                .ok_or("No input device to check the channel against")?;
+        mode.check(channels)?;
+    }
+    tracing::info!("Channel mode: {:?}", mode);
+    state.audio_capture.set_channel_mode(mode);
+    state.update_settings(|s| s.channel_mode = mode);
+    persist_and_broadcast(&state, &app)
+}
+
+/// What recordings capture when `start_listen` doesn't say: the
+/// microphone, or what the computer plays. System audio is refused
+/// where it can't be recorded (macOS without a loopback driver).
+#[tauri::command]
+pub async fn set_capture_source(
+    source: CaptureSource,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if source == CaptureSource::SystemAudio {
+        tokio::task::spawn_blocking(crate::audio::check_system_audio)
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+            .map_err(|e| e.to_string())?;
+    }
+    tracing::info!("Capture source: {}", source.as_str());
+    state.update_settings(|s| s.capture_source = source);
+    persist_and_broadcast(&state, &app)
+}
+
+/// The applications playing audio, which system audio can be narrowed
+/// to (see `set_capture_application`). Empty where one application
+/// can't be recorded alone (Windows, macOS).
+#[tauri::command]
+pub async fn list_capture_applications() -> Result<Vec<CaptureApplication>, String> {
+    tokio::task::spawn_blocking(crate::audio::list_capture_applications)
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+/// Record only the application `id` (a `CaptureApplication::id` from
+/// `list_capture_applications`) when recording system audio; `None`
+/// goes back to all of it. Remembered by the application's name: one
+/// that isn't playing when recording starts, or a platform that can't
+/// record it alone, gets all of the system audio, with a warning.
+/// Refused while recording.
+#[tauri::command]
+pub async fn set_capture_application(
+    id: Option<u32>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if state.audio_capture.is_capturing() {
+        return Err("Stop recording before switching applications".to_string());
+    }
+    let name = match id {
+        Some(id) => {
+            let listed = list_capture_applications().await?;
+            let application = listed
+                .into_iter()
+                .find(|application| application.id == id)
+                .ok_or_else(|| format!("No application playing as #{id}"))?;
+            Some(application.name)
+        }
+        None => None,
+    };
+    tracing::info!("Capture application: {:?}", name);
+    state.audio_capture.set_capture_application(name.clone());
+    state.update_settings(|s| s.capture_application = name);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Turn the input filters on or off, all at once: the high-pass against
+/// rumble for now. They apply at once, a running recording included.
+#[tauri::command]
+pub fn set_audio_filters(
+    filters: AudioFilters,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    tracing::info!("Audio filters: {:?}", filters);
+    state.audio_capture.set_filters(filters);
+    state.update_settings(|s| s.audio_filters = filters);
+    persist_and_broadcast(&state, &app)
+}
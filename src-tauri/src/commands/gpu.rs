@@ -0,0 +1,119 @@
+//! GPU detection, the system health check and model recommendation.
+
+use super::models::get_available_models;
+use super::prelude::*;
+use super::settings::persist_and_broadcast;
+use crate::whisper::recommend::{self, Gpu, Hardware, Recommendation};
+use crate::whisper::GpuBackend;
+
+/// Choose the GPU models load on (`None` = device 0, the backend's
+/// default). Indices come from `get_gpu_info`; takes effect on the next
+/// model load.
+#[tauri::command]
+pub fn set_gpu_device(
+    device: Option<u32>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if let Some(index) = device {
+        let devices = crate::whisper::gpu_devices();
+        if !devices.iter().any(|d| d.index == index) {
+            return Err(format!(
+                "GPU device {index} not found ({} available)",
+                devices.len()
+            ));
+        }
+    }
+    state.update_settings(|s| s.gpu_device = device);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Get GPU backend information
+#[tauri::command]
+pub fn get_gpu_info() -> crate::whisper::GpuInfo {
+    crate::whisper::GpuInfo::detect()
+}
+
+/// Check system health (GPU/Vulkan availability). Probes run off the
+/// command thread with per-probe timeouts; pass `max_age_ms` to accept a
+/// cached complete result that recent (UI polls should).
+#[tauri::command]
+pub async fn check_system_health(max_age_ms: Option<u64>) -> crate::whisper::SystemHealthCheck {
+    crate::whisper::check_system_health(max_age_ms.map(std::time::Duration::from_millis)).await
+}
+
+/// GPU status information for the frontend
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuStatus {
+    /// Is GPU being used for transcription?
+    pub using_gpu: bool,
+    /// Current backend name
+    pub backend: String,
+    /// Was fallback to CPU used?
+    pub fallback_used: bool,
+}
+
+/// RAM, CPU cores and the GPU the health check found, with its
+/// memory: what `whisper::recommend` judges a machine by.
+pub(super) async fn detect_hardware(state: &AppState) -> Result<Hardware, String> {
+    let vulkan_available =
+        crate::whisper::check_system_health(Some(std::time::Duration::from_secs(60)))
+            .await
+            .vulkan_available;
+    let gpu_device = state.get_settings().gpu_device;
+    // The Vulkan enumeration creates an instance: keep it off the runtime
+    tokio::task::spawn_blocking(move || {
+        let gpu_info = crate::whisper::GpuInfo::detect();
+        let gpu = match gpu_info.active_backend {
+            GpuBackend::Cpu => None,
+            GpuBackend::Vulkan if !vulkan_available => None,
+            GpuBackend::Vulkan => {
+                let (device, _) = crate::whisper::resolve_gpu_device(gpu_device, &gpu_info.devices);
+                Some(Gpu {
+                    backend: GpuBackend::Vulkan,
+                    vram_bytes: crate::whisper::vulkan_device_memory(device),
+                })
+            }
+            GpuBackend::Metal => Some(Gpu {
+                backend: GpuBackend::Metal,
+                vram_bytes: None,
+            }),
+        };
+        let mut sys = sysinfo::System::new();
+        sys.refresh_memory();
+        Hardware {
+            ram_bytes: sys.total_memory(),
+            cpu_cores: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            gpu,
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Suggest a model for this machine (see `whisper::recommend`).
+#[tauri::command]
+pub async fn recommend_model(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Recommendation, String> {
+    let hardware = detect_hardware(&state).await?;
+    let available: Vec<String> = get_available_models(state, app)?
+        .into_iter()
+        .map(|model| model.file.id)
+        .collect();
+    let recommendation = recommend::recommend(&hardware, &available);
+    tracing::info!("Recommended model: {}", recommendation.reason);
+    Ok(recommendation)
+}
+
+/// Get current GPU status
+#[tauri::command]
+pub fn get_gpu_status(state: State<'_, AppState>) -> GpuStatus {
+    GpuStatus {
+        using_gpu: state.whisper.is_using_gpu(),
+        backend: state.whisper.get_backend_name(),
+        fallback_used: state.whisper.was_fallback_used(),
+    }
+}
@@ -0,0 +1,258 @@
+//! Transcript history, and its encryption (see `crate::history_vault`).
+
+use super::prelude::*;
+use super::settings::persist_and_broadcast;
+use crate::history_vault::{EncryptedHistory, HistoryKey, KeySource, VaultError};
+
+/// Cap on how many history entries we keep. Mirrors the JS-side
+/// `MAX_HISTORY` so behaviour is identical to v0.1.7.
+const MAX_HISTORY: usize = 20;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddHistoryEntry {
+    pub text: String,
+    pub model_id: Option<String>,
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub retry: bool,
+    pub diff: Option<crate::text::TextDiff>,
+}
+
+/// Prepend a transcription to the history list, capped at
+/// `MAX_HISTORY`. Returns the freshly-created entry so the caller
+/// can use the id for further operations (e.g. delete) without
+/// another round-trip.
+#[tauri::command]
+pub fn add_history_entry(
+    entry: AddHistoryEntry,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<crate::state::HistoryEntry, String> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    let new_entry = crate::state::HistoryEntry {
+        id: timestamp.to_string(),
+        text: entry.text,
+        timestamp,
+        model_id: entry.model_id,
+        duration_ms: entry.duration_ms,
+        retry: entry.retry,
+        diff: entry.diff,
+    };
+    // Privacy mode: nothing is stored, encrypted or not.
+    if state.get_settings().privacy_mode {
+        return Ok(new_entry);
+    }
+    // With encryption on, seal it first; that needs the key.
+    let sealed = if state.get_settings().history_encryption.is_some() {
+        let key = state.history_key.lock().clone();
+        let key = key.ok_or_else(|| VaultError::Locked.to_string())?;
+        Some(key.seal_entry(&new_entry).map_err(|e| e.to_string())?)
+    } else {
+        None
+    };
+    state.update_settings(|s| {
+        s.history.insert(0, new_entry.clone());
+        s.history.truncate(MAX_HISTORY);
+        if let (Some(vault), Some(sealed)) = (s.history_encryption.as_mut(), sealed) {
+            vault.entries.insert(0, sealed);
+            vault.entries.truncate(MAX_HISTORY);
+        }
+    });
+    persist_and_broadcast(&state, &app)?;
+    Ok(new_entry)
+}
+
+/// The history, newest first, optionally filtered to entries containing
+/// `query` (case-insensitive). Fails while the encrypted history is
+/// locked.
+#[tauri::command]
+pub fn get_transcript_history(
+    query: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::state::HistoryEntry>, VaultError> {
+    if state.history_locked() {
+        return Err(VaultError::Locked);
+    }
+    let history = state.get_settings().history;
+    let Some(query) = query.map(|q| q.to_lowercase()).filter(|q| !q.is_empty()) else {
+        return Ok(history);
+    };
+    Ok(history
+        .into_iter()
+        .filter(|e| e.text.to_lowercase().contains(&query))
+        .collect())
+}
+
+/// Encrypt the history at rest, with a key derived from `passphrase` or,
+/// without one, a random key kept in the OS keychain. Also re-keys an
+/// already encrypted (and unlocked) history.
+#[tauri::command]
+pub async fn enable_history_encryption(
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), VaultError> {
+    if state.history_locked() {
+        return Err(VaultError::Locked);
+    }
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+    let (source, salt, key) = off_the_workers(move || match passphrase {
+        Some(passphrase) => {
+            let salt = crate::history_vault::new_salt();
+            let key = HistoryKey::from_passphrase(&passphrase, &salt)?;
+            Ok((KeySource::Passphrase, salt.to_vec(), key))
+        }
+        None => Ok((
+            KeySource::Keychain,
+            Vec::new(),
+            HistoryKey::from_keychain(true)?,
+        )),
+    })
+    .await?;
+    let history = state.get_settings().history;
+    let vault = EncryptedHistory::create(source, &salt, &key, &history)?;
+    state.update_settings(|s| s.history_encryption = Some(vault));
+    *state.history_key.lock() = Some(key);
+    tracing::info!("History encryption enabled ({:?} key)", source);
+    persist_history(&state, &app)
+}
+
+/// Store the history in clear again. Needs it unlocked.
+#[tauri::command]
+pub fn disable_history_encryption(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), VaultError> {
+    if state.history_locked() {
+        return Err(VaultError::Locked);
+    }
+    state.update_settings(|s| s.history_encryption = None);
+    state.history_key.lock().take();
+    tracing::info!("History encryption disabled");
+    persist_history(&state, &app)
+}
+
+/// Unlock the encrypted history (`passphrase` only for passphrase
+/// vaults). Returns how many damaged entries had to be skipped.
+#[tauri::command]
+pub async fn unlock_history(
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<usize, VaultError> {
+    let Some(vault) = state.get_settings().history_encryption else {
+        return Ok(0);
+    };
+    let (key, entries, damaged) = open_vault(vault, passphrase).await?;
+    unlocked(&state, &app, key, entries);
+    Ok(damaged)
+}
+
+/// Unlock a keychain-keyed history in the background, once at startup;
+/// a passphrase one waits for `unlock_history`.
+pub(crate) fn unlock_history_at_startup(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let Some(vault) = state.get_settings().history_encryption else {
+        return;
+    };
+    if vault.key_source != KeySource::Keychain {
+        return;
+    }
+    let app = app.clone();
+    state.tasks.spawn("history-unlock", async move {
+        let state = app.state::<AppState>();
+        match open_vault(vault, None).await {
+            // Unless unlocked, reset or disabled in the meantime
+            Ok((key, entries, _)) if state.history_locked() => unlocked(&state, &app, key, entries),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Encrypted history stays locked: {}", e),
+        }
+    });
+}
+
+/// Check `vault`'s key and decrypt it.
+async fn open_vault(
+    vault: EncryptedHistory,
+    passphrase: Option<String>,
+) -> Result<(HistoryKey, Vec<crate::state::HistoryEntry>, usize), VaultError> {
+    off_the_workers(move || {
+        let key = vault.unlock(passphrase.as_deref())?;
+        let (entries, damaged) = vault.decrypt(&key)?;
+        Ok((key, entries, damaged))
+    })
+    .await
+}
+
+/// Run `f` on the blocking pool: Argon2id takes a good fraction of a
+/// second by design, and the keychain may block on its daemon or a
+/// prompt.
+async fn off_the_workers<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, VaultError> + Send + 'static,
+) -> Result<T, VaultError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| VaultError::Crypto {
+            message: e.to_string(),
+        })?
+}
+
+fn unlocked(
+    state: &AppState,
+    app: &AppHandle,
+    key: HistoryKey,
+    entries: Vec<crate::state::HistoryEntry>,
+) {
+    state.update_settings(|s| s.history = entries);
+    *state.history_key.lock() = Some(key);
+    if let Err(e) = app.emit("settings:changed", ()) {
+        tracing::warn!("settings:changed broadcast failed: {e}");
+    }
+}
+
+/// Forget the key and the decrypted entries until the next unlock.
+#[tauri::command]
+pub fn lock_history(state: State<'_, AppState>, app: AppHandle) {
+    if state.get_settings().history_encryption.is_none() {
+        return;
+    }
+    state.history_key.lock().take();
+    state.update_settings(|s| s.history.clear());
+    let _ = app.emit("settings:changed", ());
+}
+
+/// "History unreadable, start fresh?": drop the encrypted history whose
+/// key is lost and go back to an empty, unencrypted history.
+#[tauri::command]
+pub fn reset_history(state: State<'_, AppState>, app: AppHandle) -> Result<(), VaultError> {
+    tracing::warn!("Discarding the encrypted history");
+    state.history_key.lock().take();
+    state.update_settings(|s| {
+        s.history_encryption = None;
+        s.history.clear();
+    });
+    persist_history(&state, &app)
+}
+
+/// Unlike other settings, history and vault changes are written at
+/// once: a history just encrypted must not linger on disk in clear.
+fn persist_history(state: &AppState, app: &AppHandle) -> Result<(), VaultError> {
+    persist_and_broadcast(state, app)
+        .and_then(|()| state.settings_writer.flush().map(|_| ()))
+        .map_err(|message| VaultError::Storage { message })
+}
+
+/// Drop every entry from the history.
+#[tauri::command]
+pub fn clear_history(state: State<'_, AppState>, app: AppHandle) -> Result<(), String> {
+    state.update_settings(|s| {
+        s.history.clear();
+        if let Some(vault) = s.history_encryption.as_mut() {
+            vault.entries.clear();
+        }
+    });
+    persist_and_broadcast(&state, &app)
+}
@@ -0,0 +1,1446 @@
+//! Recording and transcription: `start_listen` to `stop_listen`, and
+//! everything that transcribes again (`retranscribe_last`,
+//! `transcribe_file`, `detect_language`). The status moves themselves
+//! are `AppState` methods, in `crate::listen`.
+
+use super::output::{copy_transcript, post_process_transcript};
+use super::prelude::*;
+use super::settings::persist_and_broadcast;
+use crate::audio::{
+    AudioCaptureError, AudioChunk, CaptureSource, Cut, EndpointDetector, Eviction, PauseMark,
+    RetentionTag, Segmenter, SkipReason, StreamFailure, Utterance, UtteranceAudio, VoiceEnd,
+    UTTERANCE_LEAD_MS, UTTERANCE_QUEUE_LEN,
+};
+use crate::errors::ReportErr;
+use crate::listen::{InputLoss, ListenRefusal};
+use crate::session::SessionJournal;
+use crate::state::{LastRecording, StatusChange, UtteranceQueue};
+use crate::whisper::coverage::{self, Coverage};
+use crate::whisper::jobs::{JobHandle, JobParams, QueueError};
+use crate::whisper::language::{self, LanguageReport};
+use crate::whisper::streaming::{AudioStreamer, PartialPass};
+use crate::whisper::{LanguageDetectError, TranscriptionResult, WhisperError, RETRY_CHUNK_SECS};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{mpsc, oneshot};
+
+pub use crate::listen::{ListenMode, DEGRADED_ERROR_CODE};
+
+/// Record from `source`, by default `Settings.capture_source`.
+#[tauri::command]
+pub async fn start_listen(
+    mode: ListenMode,
+    source: Option<CaptureSource>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let source = source.unwrap_or_else(|| state.get_settings().capture_source);
+    tracing::info!(
+        "Starting listen with mode: {:?}, from the {}",
+        mode,
+        source.as_str()
+    );
+
+    let change = match state.begin_listen(&mode) {
+        Ok(change) => change,
+        Err(ListenRefusal::MicrophonePermission) => {
+            app.emit("permission:required", "microphone")
+                .map_err(|e| e.to_string())?;
+            return Err(ListenRefusal::MicrophonePermission.to_string());
+        }
+        Err(refusal) => return Err(refusal.to_string()),
+    };
+    announce(&state, &app, change);
+
+    // Start audio capture
+    let audio_capture = Arc::clone(&state.audio_capture);
+    audio_capture.set_agc_gate(state.vad.read().current_threshold());
+    let chunk_rx = audio_capture.create_chunk_channel();
+    let error_rx = audio_capture.create_error_channel();
+
+    if let Err(e) = audio_capture.start(mode.as_str(), source) {
+        crate::errors::report(&app, "audio-capture", &e.to_string(), Some("start_listen"));
+        let _ = transition(&state, &app, AppStatus::Idle);
+        return Err(e.to_string());
+    }
+
+    if mode.journals() {
+        start_session_journal(&state, &app, &mode);
+    }
+
+    apply_prompt(&state);
+
+    // Spawn VAD processing task (also feeds streaming partials, if on,
+    // and stops a voice-activated recording)
+    let settings = state.get_settings();
+    let streamer = settings
+        .streaming_partials
+        .then(|| AudioStreamer::new(settings.partial_interval_ms));
+    let endpoint = matches!(mode, ListenMode::VoiceActivated).then(EndpointDetector::default);
+    let segmenter = matches!(mode, ListenMode::Continuous).then(|| {
+        let (sender, utterances) = mpsc::channel(UTTERANCE_QUEUE_LEN);
+        let (done_tx, done) = oneshot::channel();
+        *state.utterances.lock() = Some(UtteranceQueue { sender, done });
+        state.tasks.spawn(
+            "utterances",
+            transcribe_utterances(utterances, (*state).clone(), app.clone(), done_tx),
+        );
+        Segmenter::default()
+    });
+    let state_clone = (*state).clone();
+    let app_clone = app.clone();
+    state.tasks.spawn(
+        "vad-levels",
+        process_audio_chunks(
+            chunk_rx,
+            state_clone,
+            app_clone,
+            streamer,
+            endpoint,
+            segmenter,
+        ),
+    );
+    state.tasks.spawn(
+        "input-watch",
+        watch_input(error_rx, (*state).clone(), app.clone()),
+    );
+
+    Ok(())
+}
+
+/// Stop recording and transcribe. Also ends a voice-activated
+/// recording before it stops itself.
+#[tauri::command]
+pub async fn stop_listen(state: State<'_, AppState>, app: AppHandle) -> Result<String, String> {
+    tracing::info!("Stopping listen");
+    stop_recording(&state, &app).await
+}
+
+/// `stop_listen`'s flow, for the command and a voice-activated
+/// recording's end of speech: Processing, transcribe, back to Idle.
+async fn stop_recording(state: &AppState, app: &AppHandle) -> Result<String, String> {
+    let change = state.begin_stop().map_err(|e| e.to_string())?;
+    announce(state, app, change);
+
+    let result = finish_recording(state, app).await;
+    if let Some(change) = state.end_processing() {
+        announce(state, app, change);
+    }
+    result
+}
+
+/// Pause the recording: the microphone stays open, but nothing is
+/// recorded, and no `vad:level` sent, until `resume_listen`.
+/// `stop_listen` transcribes what was recorded around the pauses, and
+/// says where they were (`pauses` in `transcript:final`).
+#[tauri::command]
+pub fn pause_listen(state: State<'_, AppState>, app: AppHandle) -> Result<(), String> {
+    let change = state.begin_pause().map_err(|e| e.to_string())?;
+    state.audio_capture.pause();
+    announce(&state, &app, change);
+    Ok(())
+}
+
+/// Resume a paused recording, into the same buffer.
+#[tauri::command]
+pub fn resume_listen(state: State<'_, AppState>, app: AppHandle) -> Result<(), String> {
+    let change = state.begin_resume().map_err(|e| e.to_string())?;
+    state.audio_capture.resume();
+    announce(&state, &app, change);
+    Ok(())
+}
+
+/// Transcribe `samples` with the draft model in the background and emit
+/// `transcript:draft`, unless the final pass beat it to it.
+fn start_draft_pass(
+    state: &AppState,
+    app: &AppHandle,
+    samples: Arc<[i16]>,
+    final_done: Arc<AtomicBool>,
+) {
+    let whisper = state.whisper.clone();
+    let app = app.clone();
+    state.tasks.spawn("draft-transcription", async move {
+        let start = std::time::Instant::now();
+        let result = tokio::task::spawn_blocking(move || whisper.transcribe_draft(&samples)).await;
+        let result = match result {
+            Ok(Some(Ok(result))) => result,
+            Ok(Some(Err(e))) => {
+                tracing::warn!("Draft transcription failed: {}", e);
+                return;
+            }
+            // Unloaded meanwhile, or the task panicked
+            Ok(None) | Err(_) => return,
+        };
+        if final_done.load(Ordering::Acquire) {
+            tracing::debug!("Final transcript arrived first, dropping the draft");
+            return;
+        }
+        let _ = app.emit(
+            "transcript:draft",
+            serde_json::json!({
+                "text": result.text,
+                "segments": result.segments,
+                "transcribeDurationMs": start.elapsed().as_millis() as u64,
+            }),
+        );
+    });
+}
+
+/// Abort the recording's transcription `job_id` (its `jobId` in
+/// `transcript:final`), or every one running. `stop_listen` then fails
+/// with a "cancelled" error and nothing is output.
+#[tauri::command]
+pub async fn cancel_transcription(
+    job_id: Option<u64>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let change = state
+        .cancel_transcription_job(job_id)
+        .map_err(|e| e.to_string())?;
+    if let Some(change) = change {
+        announce(&state, &app, change);
+    }
+    tracing::info!("Cancelling transcription {:?}", job_id);
+    Ok(())
+}
+
+/// `AudioCapture::stop` on the blocking pool: it waits for the audio
+/// callbacks to settle, which would hold up an async worker.
+async fn stop_capture(state: &AppState) -> Result<Vec<i16>, AudioCaptureError> {
+    let capture = Arc::clone(&state.audio_capture);
+    tokio::task::spawn_blocking(move || capture.stop())
+        .await
+        .unwrap_or_else(|e| Err(AudioCaptureError::StreamError(e.to_string())))
+}
+
+/// `AudioCapture::restart` on the blocking pool: reopening the device
+/// probes it, and the dead stream takes its time to drop.
+async fn restart_capture(state: &AppState) -> Result<(), AudioCaptureError> {
+    let capture = Arc::clone(&state.audio_capture);
+    tokio::task::spawn_blocking(move || capture.restart())
+        .await
+        .unwrap_or_else(|e| Err(AudioCaptureError::StreamError(e.to_string())))
+}
+
+/// The part of `stop_listen` that runs in the Processing state: stop
+/// capture, transcribe and deliver the text. Once the audio is taken,
+/// the next recording can start meanwhile. A continuous recording's
+/// last utterance goes through its queue, after those still in it; its
+/// text is then the whole recording's, and the next one waits for it.
+async fn finish_recording(state: &AppState, app: &AppHandle) -> Result<String, String> {
+    // Taken first: a cut the chunk task is making completes before
+    let queue = state.utterances.lock().take();
+    // Stop audio capture and get samples
+    let samples = stop_capture(state).await;
+    let pauses = state.audio_capture.take_pauses();
+    let (speech_ms, speech) = take_speech(state);
+    let verbatim = state.verbatim.load(Ordering::Relaxed);
+    let samples = match samples {
+        Ok(samples) => samples,
+        Err(e) => {
+            state.journal.lock().take();
+            state.recording_taken();
+            return Err(e.to_string());
+        }
+    };
+
+    match queue {
+        Some(queue) => {
+            let last = UtteranceAudio {
+                samples,
+                speech_ms,
+                speech,
+                ended_at_ms: unix_ms(),
+                last: true,
+            };
+            let result = flush_utterances(queue, last).await;
+            // The session ends here: the journal closes
+            state.journal.lock().take();
+            state.recording_taken();
+            result
+        }
+        None => {
+            // The session ends here: the journal closes after the text
+            let mut journal = state.journal.lock().take();
+            state.recording_taken();
+            let recording = Recording {
+                samples,
+                speech_ms,
+                speech,
+                pauses,
+                utterance: None,
+                verbatim,
+            };
+            let result = transcribe_recording(state, app, recording).await;
+            if let (Ok(text), Some(journal)) = (&result, journal.as_mut()) {
+                append_to_journal(journal, text);
+            }
+            result
+        }
+    }
+}
+
+/// Journal `text`; a write that fails only costs crash recovery.
+fn append_to_journal(journal: &mut SessionJournal, text: &str) {
+    if let Err(e) = journal.append(text) {
+        tracing::warn!("Failed to append to session journal: {}", e);
+    }
+}
+
+/// The speech the VAD detected since its last reset, in ms and as ms
+/// ranges; the VAD starts over.
+fn take_speech(state: &AppState) -> (u64, Vec<Range<u64>>) {
+    let mut vad = state.vad.write();
+    let detected = (vad.speech_ms(16000), vad.speech_spans_ms(16000));
+    vad.reset();
+    detected
+}
+
+fn unix_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Captured audio to transcribe: a whole recording, or one utterance of
+/// a continuous one.
+struct Recording {
+    samples: Vec<i16>,
+    speech_ms: u64,
+    speech: Vec<Range<u64>>,
+    pauses: Vec<PauseMark>,
+    utterance: Option<Utterance>,
+    /// Fillers kept; the recording's listen mode decides.
+    verbatim: bool,
+}
+
+/// Transcribe `recording` and deliver the text: `transcript:final`, or
+/// `transcript:empty` when it is skipped. An utterance that isn't a
+/// continuous recording's last is skipped quietly.
+async fn transcribe_recording(
+    state: &AppState,
+    app: &AppHandle,
+    recording: Recording,
+) -> Result<String, String> {
+    let Recording {
+        samples,
+        speech_ms,
+        speech,
+        pauses,
+        utterance,
+        verbatim,
+    } = recording;
+    let samples_count = samples.len();
+    let duration = samples_count as f32 / 16000.0;
+    tracing::info!(
+        "Captured {:.2}s of audio ({} samples, {} ms of speech)",
+        duration,
+        samples_count,
+        speech_ms
+    );
+
+    let settings = state.get_settings();
+    // Skipped recordings too: "no speech detected" is a bug report as well
+    if settings.debug_save_recordings && !samples.is_empty() {
+        if settings.privacy_mode {
+            tracing::info!("Privacy mode: not saving the recording");
+        } else {
+            save_debug_recording(state, samples.clone());
+        }
+    }
+    let skip = crate::audio::skip_reason(
+        (duration * 1000.0) as u64,
+        speech_ms,
+        settings.min_speech_ms as u64,
+        settings.force_transcription,
+    )
+    .or_else(|| {
+        // Whole buffer is silence: skip Whisper entirely rather than let
+        // it invent a transcript from nothing.
+        crate::audio::is_silent_buffer(&samples).then_some(SkipReason::NoSpeechDetected)
+    });
+    if let Some(reason) = skip {
+        tracing::info!("Skipping transcription: {:?}", reason);
+        if utterance.is_none_or(|utterance| utterance.last) {
+            app.emit("transcript:empty", serde_json::json!({ "reason": reason }))
+                .map_err(|e| e.to_string())?;
+        }
+        return Err(match reason {
+            SkipReason::TooShort => "Recording too short",
+            SkipReason::NoSpeechDetected => "No speech detected",
+        }
+        .to_string());
+    }
+
+    apply_prompt(state);
+
+    // Transcribe with Whisper
+    let whisper = state.whisper.clone();
+    let translated = whisper.is_translating();
+    let transcribe_start = std::time::Instant::now();
+    let perf_app = app.clone();
+    let perf = crate::perf::SamplingSession::start(state.tasks, move |sample| {
+        let _ = perf_app.emit("perf:sample", sample);
+    });
+    let samples = Arc::<[i16]>::from(samples);
+    // With a draft model loaded, its (fast) pass runs alongside the
+    // final one; otherwise this is a single pass.
+    let final_done = Arc::new(AtomicBool::new(false));
+    if whisper.has_draft_model() {
+        start_draft_pass(state, app, Arc::clone(&samples), Arc::clone(&final_done));
+    }
+    let mut job = match whisper.submit(Arc::clone(&samples), JobParams::default()) {
+        Ok(job) => job,
+        Err(busy) => {
+            final_done.store(true, Ordering::Release);
+            return Err(reject_busy(app, busy));
+        }
+    };
+    let job_id = job.id();
+    tracing::debug!(
+        "Transcription job {} queued ({} pending)",
+        job_id,
+        whisper.queue_length()
+    );
+    state
+        .transcription_jobs
+        .lock()
+        .insert(job_id, job.cancel_token());
+    if let Some(mut progress) = job.progress() {
+        let progress_app = app.clone();
+        state.tasks.spawn("transcript-progress", async move {
+            while let Some(percent) = progress.recv().await {
+                let _ = progress_app.emit("transcript:progress", percent);
+            }
+        });
+    }
+    let result = await_with_timeout(app, job, settings.transcription_timeout_secs).await;
+    final_done.store(true, Ordering::Release);
+    state.transcription_jobs.lock().remove(&job_id);
+    let utilization = perf.finish();
+    let result = result.map_err(|e| {
+        // Cancelling is not an error; a timeout was reported already.
+        if !matches!(e, WhisperError::Cancelled | WhisperError::Timeout(_)) {
+            crate::errors::report(app, "transcription", &e.to_string(), Some("stop_listen"));
+        }
+        e.to_string()
+    })?;
+    let transcribe_duration_ms = transcribe_start.elapsed().as_millis() as u64;
+    state.audio_capture.usage_log().mark_transcribed();
+    let coverage = check_coverage(app, &result, samples_count, &speech, job_id, false);
+    note_language(state, &result);
+
+    // Carry the words actually spoken (before snippet expansion) over
+    // into the next dictation's prompt.
+    if settings.context_carry_over {
+        state
+            .dictation_context
+            .lock()
+            .push(&result.text, std::time::Instant::now());
+    }
+
+    // Voice-command stage: expand snippet triggers before output.
+    let mut result = result;
+    result.text = post_process_transcript(app, state, &result.text, verbatim);
+    crate::crash::recorder().note_transcript(&result.text);
+
+    keep_last_recording(state, app, &samples, result.text.clone(), speech, verbatim);
+
+    // Get current model from settings
+    let current_model = state.get_settings().model.clone();
+
+    app.emit(
+        "transcript:final",
+        serde_json::json!({
+            "text": result.text,
+            "segments": result.segments,
+            "decode": result.decode,
+            "duration": duration,
+            "samples": samples_count,
+            "model": current_model,
+            "transcribeDurationMs": transcribe_duration_ms,
+            "utilization": utilization,
+            "translated": translated,
+            "droppedSegments": result.dropped_segments,
+            "confidence": result.confidence,
+            "retried": result.retried,
+            "language": result.language,
+            "reproducibility": result.reproducibility,
+            "possiblyTruncated": coverage.possibly_truncated,
+            "coverage": coverage,
+            "clippedRatio": crate::audio::clipped_ratio(&samples),
+            "pauses": pauses,
+            "utterance": utterance,
+            "jobId": job_id
+        }),
+    )
+    .map_err(|e| e.to_string())?;
+
+    if settings.auto_copy && !result.text.trim().is_empty() {
+        copy_transcript(state, app, &result.text);
+    }
+
+    Ok(result.text)
+}
+
+/// Wait for `job`, giving up `timeout_secs` after it started (the time
+/// queued behind other recordings doesn't count): the job is then
+/// aborted (whisper checks the abort flag between decoder steps, so the
+/// job thread frees up for the next one) and an `error` event tells the
+/// user.
+async fn await_with_timeout(
+    app: &AppHandle,
+    mut job: JobHandle,
+    timeout_secs: u32,
+) -> Result<TranscriptionResult, WhisperError> {
+    let cancel = job.cancel_token();
+    let timeout = std::time::Duration::from_secs(u64::from(timeout_secs));
+    // The job is queued: the overlay's queue depth moved, and moves
+    // again once it is done.
+    crate::render::invalidate(app);
+    job.started().await;
+    let outcome = tokio::time::timeout(timeout, job.await_result()).await;
+    crate::render::invalidate(app);
+    match outcome {
+        Ok(result) => result,
+        Err(_) => {
+            cancel.cancel();
+            let error = WhisperError::Timeout(u64::from(timeout_secs));
+            tracing::error!("{}; aborting the job", error);
+            let message = format!(
+                "{error}. The recording was not transcribed; try again or use a smaller model."
+            );
+            crate::errors::report(app, "transcription-timeout", &message, None);
+            Err(error)
+        }
+    }
+}
+
+/// Check how far the decode got against the VAD's speech (see
+/// `whisper::coverage`). When it looks cut short, log it and emit
+/// `transcript:truncated`, which offers `retranscribe_last` in short
+/// windows unless this already was that retry (`chunked`).
+fn check_coverage(
+    app: &AppHandle,
+    result: &TranscriptionResult,
+    samples: usize,
+    speech: &[Range<u64>],
+    job_id: u64,
+    chunked: bool,
+) -> Coverage {
+    let audio_ms = samples as u64 * 1000 / 16000;
+    let coverage = coverage::assess(&result.segments, result.tokens, audio_ms, speech);
+    if coverage.possibly_truncated {
+        tracing::warn!(
+            "Transcript may be truncated: {} tokens in {} segments end at {} ms, speech goes on until {} ms",
+            coverage.tokens,
+            coverage.segments,
+            coverage.covered_ms,
+            coverage.covered_ms + coverage.gap_ms
+        );
+        if let Err(e) = app.emit(
+            "transcript:truncated",
+            serde_json::json!({
+                "jobId": job_id,
+                "coverage": coverage,
+                "canRetryChunked": !chunked
+            }),
+        ) {
+            tracing::warn!("transcript:truncated emit failed: {e}");
+        }
+    }
+    coverage
+}
+
+/// Tell the frontend a transcription was refused because the queue is
+/// full (`transcript:busy`, the structured `QueueError`) and return the
+/// command error.
+fn reject_busy(app: &AppHandle, busy: QueueError) -> String {
+    tracing::warn!("{}", busy);
+    if let Err(e) = app.emit("transcript:busy", &busy) {
+        tracing::warn!("transcript:busy emit failed: {e}");
+    }
+    busy.to_string()
+}
+
+/// Transcriptions queued or running, this one included.
+#[tauri::command]
+pub fn get_queue_length(state: State<'_, AppState>) -> usize {
+    state.whisper.queue_length()
+}
+
+/// Transcribe the last recording again with the current model and
+/// settings (after switching model or language, say). `chunked` decodes
+/// it in short windows, the retry `transcript:truncated` offers. Emits
+/// `transcript:diff` against the previous text, then `transcript:final`
+/// flagged as a retry with the same diff for the history entry. The
+/// status stays Idle: nothing is recorded.
+#[tauri::command]
+pub async fn retranscribe_last(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    chunked: Option<bool>,
+) -> Result<String, String> {
+    if state.get_status() != AppStatus::Idle {
+        return Err("Cannot re-transcribe while recording or transcribing".to_string());
+    }
+    let (audio, previous, speech, verbatim) = match state.last_recording.lock().as_ref() {
+        Some(last) => (
+            last.audio,
+            last.text.clone(),
+            last.speech.clone(),
+            last.verbatim,
+        ),
+        None => return Err("No recording to re-transcribe".to_string()),
+    };
+    let samples = state.retained_audio.lock().get(audio).ok_or_else(|| {
+        "The last recording was dropped to stay within the memory budget".to_string()
+    })?;
+    let chunked = chunked.unwrap_or(false);
+    tracing::info!(
+        "Re-transcribing last recording ({} samples{})",
+        samples.len(),
+        if chunked { ", in short windows" } else { "" }
+    );
+
+    apply_prompt(&state);
+    let whisper = state.whisper.clone();
+    let translated = whisper.is_translating();
+    let transcribe_start = std::time::Instant::now();
+    let params = JobParams {
+        chunk_secs: chunked.then_some(RETRY_CHUNK_SECS),
+        ..JobParams::default()
+    };
+    let job = whisper
+        .submit(Arc::clone(&samples), params)
+        .map_err(|busy| reject_busy(&app, busy))?;
+    let job_id = job.id();
+    let timeout_secs = state.get_settings().transcription_timeout_secs;
+    let mut result = await_with_timeout(&app, job, timeout_secs)
+        .await
+        .report_err(&app, "transcription", "retranscribe_last")
+        .map_err(|e| e.to_string())?;
+    let transcribe_duration_ms = transcribe_start.elapsed().as_millis() as u64;
+    let coverage = check_coverage(&app, &result, samples.len(), &speech, job_id, chunked);
+    note_language(&state, &result);
+    result.text = post_process_transcript(&app, &state, &result.text, verbatim);
+
+    let diff = crate::text::word_diff(&previous, &result.text);
+    if let Some(diff) = &diff {
+        app.emit("transcript:diff", diff)
+            .map_err(|e| e.to_string())?;
+    }
+    if let Some(last) = state.last_recording.lock().as_mut() {
+        last.text = result.text.clone();
+    }
+
+    app.emit(
+        "transcript:final",
+        serde_json::json!({
+            "text": result.text,
+            "segments": result.segments,
+            "decode": result.decode,
+            "duration": samples.len() as f32 / 16000.0,
+            "samples": samples.len(),
+            "model": state.get_settings().model,
+            "transcribeDurationMs": transcribe_duration_ms,
+            "translated": translated,
+            "droppedSegments": result.dropped_segments,
+            "confidence": result.confidence,
+            "retried": result.retried,
+            "language": result.language,
+            "reproducibility": result.reproducibility,
+            "possiblyTruncated": coverage.possibly_truncated,
+            "coverage": coverage,
+            "clippedRatio": crate::audio::clipped_ratio(&samples),
+            "jobId": job_id,
+            "retry": true,
+            "diff": diff
+        }),
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(result.text)
+}
+
+/// Transcribe an audio file (WAV) with the loaded model. Emits
+/// `file:progress` while it runs and `transcript:final` marked
+/// `source: "file"` with the path. The recording state is left alone,
+/// so dictation keeps working meanwhile (the job just queues).
+#[tauri::command]
+pub async fn transcribe_file(
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<String, String> {
+    let file = std::path::PathBuf::from(&path);
+    let samples = tokio::task::spawn_blocking(move || crate::audio::read_wav(&file))
+        .await
+        .map_err(|e| e.to_string())?
+        .report_err(&app, "audio-file", &path)
+        .map_err(|e| e.to_string())?;
+    let duration = samples.len() as f32 / 16000.0;
+    tracing::info!("Transcribing {} ({:.1}s)", path, duration);
+
+    apply_prompt(&state);
+    let whisper = state.whisper.clone();
+    let translated = whisper.is_translating();
+    let transcribe_start = std::time::Instant::now();
+    let samples = Arc::<[i16]>::from(samples);
+    let mut job = whisper
+        .submit(Arc::clone(&samples), JobParams::default())
+        .map_err(|busy| reject_busy(&app, busy))?;
+    let job_id = job.id();
+    if let Some(mut progress) = job.progress() {
+        let progress_app = app.clone();
+        let progress_path = path.clone();
+        state.tasks.spawn("file-progress", async move {
+            while let Some(percent) = progress.recv().await {
+                let _ = progress_app.emit(
+                    "file:progress",
+                    serde_json::json!({ "path": progress_path, "percent": percent }),
+                );
+            }
+        });
+    }
+    crate::render::invalidate(&app);
+    let result = job.await_result().await;
+    crate::render::invalidate(&app);
+    let result = result
+        .report_err(&app, "transcription", &path)
+        .map_err(|e| e.to_string())?;
+    let transcribe_duration_ms = transcribe_start.elapsed().as_millis() as u64;
+    note_language(&state, &result);
+
+    app.emit(
+        "transcript:final",
+        serde_json::json!({
+            "text": result.text,
+            "segments": result.segments,
+            "decode": result.decode,
+            "duration": duration,
+            "samples": samples.len(),
+            "model": state.get_settings().model,
+            "transcribeDurationMs": transcribe_duration_ms,
+            "translated": translated,
+            "droppedSegments": result.dropped_segments,
+            "confidence": result.confidence,
+            "retried": result.retried,
+            "language": result.language,
+            "reproducibility": result.reproducibility,
+            "clippedRatio": crate::audio::clipped_ratio(&samples),
+            "jobId": job_id,
+            "source": "file",
+            "path": path
+        }),
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(result.text)
+}
+
+/// Keep `samples` as the last recording, in place of the previous one.
+fn keep_last_recording(
+    state: &AppState,
+    app: &AppHandle,
+    samples: &Arc<[i16]>,
+    text: String,
+    speech: Vec<Range<u64>>,
+    verbatim: bool,
+) {
+    let evicted = {
+        let mut retained = state.retained_audio.lock();
+        let mut last = state.last_recording.lock();
+        if let Some(previous) = last.take() {
+            retained.release(previous.audio, RetentionTag::Retranscribe);
+        }
+        let (audio, evicted) = retained.retain(samples, RetentionTag::Retranscribe);
+        *last = Some(LastRecording {
+            audio,
+            text,
+            speech,
+            verbatim,
+        });
+        evicted
+    };
+    emit_evictions(app, evicted);
+}
+
+/// A new file for a saved recording: `recordings/recording-<time>.wav`
+/// in the data directory.
+fn recording_path() -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+    crate::paths::get()
+        .recordings_dir()
+        .join(format!("recording-{stamp}.wav"))
+}
+
+/// Write `samples` to a new file in the background, for
+/// `debug_save_recordings`.
+fn save_debug_recording(state: &AppState, samples: Vec<i16>) {
+    let path = recording_path();
+    state.tasks.spawn("save-recording", async move {
+        let saved = tokio::task::spawn_blocking(move || {
+            crate::audio::save_wav(&path, &samples).map(|()| path)
+        })
+        .await;
+        match saved {
+            Ok(Ok(path)) => tracing::info!("Saved the recording to {}", path.display()),
+            Ok(Err(e)) => tracing::warn!("Failed to save the recording: {}", e),
+            Err(e) => tracing::warn!("Recording save task failed: {}", e),
+        }
+    });
+}
+
+/// Save the last transcribed recording as a 16 kHz mono 16-bit WAV at
+/// `path`, or as a new file under `recordings/` in the data directory,
+/// and return where it went: to hear what was actually captured when a
+/// transcript comes out wrong.
+#[tauri::command]
+pub async fn save_last_recording(
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let audio = state
+        .last_recording
+        .lock()
+        .as_ref()
+        .map(|last| last.audio)
+        .ok_or_else(|| "Nothing has been recorded yet".to_string())?;
+    let samples = state.retained_audio.lock().get(audio).ok_or_else(|| {
+        "The last recording is no longer kept; raise the memory for recordings".to_string()
+    })?;
+    let path = path.map(PathBuf::from).unwrap_or_else(recording_path);
+    let path =
+        tokio::task::spawn_blocking(move || crate::audio::save_wav(&path, &samples).map(|()| path))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+            .map_err(|e| e.to_string())?;
+    tracing::info!("Saved the last recording to {}", path.display());
+    Ok(path.display().to_string())
+}
+
+/// Report the recordings `RetainedAudio` dropped as `memory:evicted`.
+pub(super) fn emit_evictions(app: &AppHandle, evicted: Vec<Eviction>) {
+    for eviction in evicted {
+        tracing::info!(
+            "Dropped a kept recording ({} bytes, {:?}) to stay within {} bytes",
+            eviction.bytes,
+            eviction.tags,
+            eviction.budget_bytes
+        );
+        if let Err(e) = app.emit("memory:evicted", &eviction) {
+            tracing::warn!("memory:evicted emit failed: {e}");
+        }
+    }
+}
+
+/// Keep how `result`'s language was settled, for
+/// `explain_language_choice`.
+fn note_language(state: &AppState, result: &TranscriptionResult) {
+    if let Some(decision) = &result.language {
+        *state.language_decision.lock() = Some((state.get_settings().model, decision.clone()));
+    }
+}
+
+/// Why the last transcription came out in its language: the detection
+/// probabilities, the rule that decided, and the settings to change
+/// when it was wrong (see `whisper::language`).
+#[tauri::command]
+pub fn explain_language_choice(state: State<'_, AppState>) -> Result<LanguageReport, String> {
+    let (model, decision) = state
+        .language_decision
+        .lock()
+        .clone()
+        .ok_or_else(|| "Nothing has been transcribed yet".to_string())?;
+    let settings = state.get_settings();
+    let model_languages: Vec<String> = settings
+        .model_languages
+        .get(&model)
+        .map(|languages| languages.iter().map(|l| l.to_code().to_string()).collect())
+        .unwrap_or_default();
+    Ok(language::explain(
+        &model,
+        &decision,
+        &model_languages,
+        !settings.language_toggle_shortcut.is_empty(),
+    ))
+}
+
+/// Seconds captured by `detect_language` when there is no recording to
+/// reuse.
+const LANGUAGE_PROBE_SECS: u64 = 3;
+
+/// Detect the spoken language with the loaded model and return the three
+/// most likely candidates. Uses the last recording, or a fresh
+/// `LANGUAGE_PROBE_SECS` capture when there is none or `fresh` is set.
+#[tauri::command]
+pub async fn detect_language(
+    fresh: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<crate::whisper::LanguageGuess>, LanguageDetectError> {
+    if state.get_status() != AppStatus::Idle {
+        return Err(LanguageDetectError::Busy);
+    }
+    // Fail on the model before making the user speak for nothing
+    match state.whisper.is_multilingual() {
+        None => return Err(LanguageDetectError::NotLoaded),
+        Some(false) => return Err(LanguageDetectError::EnglishOnly),
+        Some(true) => {}
+    }
+
+    let last = if fresh.unwrap_or(false) {
+        None
+    } else {
+        let audio = state.last_recording.lock().as_ref().map(|last| last.audio);
+        audio.and_then(|audio| state.retained_audio.lock().get(audio))
+    };
+    let samples = match last {
+        Some(samples) => samples,
+        None => Arc::from(
+            record_probe(&state, &app, "language-detection", LANGUAGE_PROBE_SECS)
+                .await
+                .map_err(|e| match e {
+                    ProbeError::Busy => LanguageDetectError::Busy,
+                    ProbeError::Capture(message) => LanguageDetectError::Capture { message },
+                })?,
+        ),
+    };
+
+    let whisper = state.whisper.clone();
+    let guesses = tokio::task::spawn_blocking(move || whisper.detect_language(&samples, 3))
+        .await
+        .map_err(|e| LanguageDetectError::Failed {
+            message: format!("Task join error: {}", e),
+        })??;
+    tracing::info!("Language detection: {:?}", guesses);
+    Ok(guesses)
+}
+
+/// Why `record_probe` recorded nothing.
+pub(super) enum ProbeError {
+    /// Recording or transcribing already.
+    Busy,
+    Capture(String),
+}
+
+/// Record `secs` of the microphone outside a recording (language
+/// detection, noise calibration), Listening meanwhile. `trigger` goes
+/// to the usage log.
+pub(super) async fn record_probe(
+    state: &AppState,
+    app: &AppHandle,
+    trigger: &str,
+    secs: u64,
+) -> Result<Vec<i16>, ProbeError> {
+    if !state.get_permissions().microphone {
+        let _ = app.emit("permission:required", "microphone");
+        return Err(ProbeError::Capture(
+            "Microphone permission required".to_string(),
+        ));
+    }
+    let change = state.begin_probe().map_err(|_| ProbeError::Busy)?;
+    announce(state, app, change);
+    let capture_error = |e: AudioCaptureError| ProbeError::Capture(e.to_string());
+    if let Err(e) = state
+        .audio_capture
+        .start(trigger, CaptureSource::Microphone)
+    {
+        let _ = transition(state, app, AppStatus::Idle);
+        return Err(capture_error(e));
+    }
+    tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+    let samples = stop_capture(state).await;
+    let _ = transition(state, app, AppStatus::Idle);
+    samples.map_err(capture_error)
+}
+
+/// Hand the engine its initial prompt: the custom vocabulary, plus the
+/// carried-over dictation context when that's on (dropped first if the
+/// idle gap has passed). See `whisper::prompt`.
+pub(super) fn apply_prompt(state: &AppState) {
+    let settings = state.get_settings();
+    let vocabulary = settings
+        .vocabulary
+        .iter()
+        .map(|v| v.term.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let context = if settings.context_carry_over {
+        let mut context = state.dictation_context.lock();
+        let idle = std::time::Duration::from_secs(settings.context_idle_reset_secs as u64);
+        if context.expire_if_idle(std::time::Instant::now(), idle) {
+            tracing::info!("Dictation context dropped after idle gap");
+        }
+        context.text()
+    } else {
+        String::new()
+    };
+    state.whisper.set_prompt(vocabulary, context);
+}
+
+/// Forget the dictation context carried over between dictations.
+#[tauri::command]
+pub fn clear_dictation_context(state: State<'_, AppState>) {
+    state.dictation_context.lock().clear();
+    tracing::info!("Dictation context cleared");
+}
+
+/// Toggle context carry-over. Turning it off also forgets the context.
+#[tauri::command]
+pub fn set_context_carry_over(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if !enabled {
+        state.dictation_context.lock().clear();
+    }
+    state.update_settings(|s| s.context_carry_over = enabled);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Set the idle gap (seconds) after which carried-over context is
+/// dropped. 0 keeps it until cleared.
+#[tauri::command]
+pub fn set_context_idle_reset_secs(
+    secs: u32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if secs > 86_400 {
+        return Err(format!("Idle reset must be at most 86400 s (got {secs})"));
+    }
+    state.update_settings(|s| s.context_idle_reset_secs = secs);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Move to `to` through the transition table and tell the frontend
+/// (`state:change` carries both the old and the new status). A refused
+/// transition is returned as the command error.
+fn transition(state: &AppState, app: &AppHandle, to: AppStatus) -> Result<(), String> {
+    let change = state.move_status(to).map_err(|e| e.to_string())?;
+    announce(state, app, change);
+    Ok(())
+}
+
+/// Tell the overlay and the frontend about a status change.
+fn announce(state: &AppState, app: &AppHandle, change: StatusChange) {
+    crate::render::invalidate(app);
+    emit_state_change(state, app, change);
+}
+
+/// Queue a `state:change`; it goes out once the frontend acknowledged
+/// the previous one (see `crate::state_events`).
+pub(super) fn emit_state_change(state: &AppState, app: &AppHandle, change: StatusChange) {
+    let app = app.clone();
+    let emission = state.state_events.queue(change, move |sequenced| {
+        if let Err(e) = app.emit("state:change", sequenced) {
+            tracing::warn!("state:change emit failed: {e}");
+        }
+    });
+    state.tasks.spawn("state-change", emission);
+}
+
+/// The frontend applied the `state:change` numbered `seq`.
+#[tauri::command]
+pub fn ack_state(seq: u64, state: State<'_, AppState>) {
+    state.state_events.ack(seq);
+}
+
+/// Open a fresh journal for a long-running session, unless privacy
+/// mode is on — then tell the user crash recovery is off instead.
+fn start_session_journal(state: &AppState, app: &AppHandle, mode: &ListenMode) {
+    if state.get_settings().privacy_mode {
+        let _ = app.emit(
+            "session:journal-disabled",
+            serde_json::json!({
+                "reason": "Privacy mode is on: nothing is written to disk, so this session cannot be recovered after a crash."
+            }),
+        );
+        return;
+    }
+    let dir = crate::paths::get().sessions_dir();
+    let journal = crate::session::SessionJournal::create(&dir, mode.as_str());
+    match journal {
+        Ok(journal) => {
+            tracing::info!("Session journal: {}", journal.path().display());
+            let _ = app.emit(
+                "session:journal",
+                serde_json::json!({ "path": journal.path().to_string_lossy() }),
+            );
+            *state.journal.lock() = Some(journal);
+        }
+        Err(e) => tracing::warn!("Could not start session journal: {}", e),
+    }
+}
+
+/// Rebuild the combined transcript from a session journal (a path from
+/// `session:journal`) after a crash.
+#[tauri::command]
+pub fn recover_session(path: String) -> Result<crate::session::RecoveredSession, String> {
+    let dir = crate::paths::get().sessions_dir();
+    let path = PathBuf::from(path);
+    if !crate::session::is_journal_in(&dir, &path) {
+        return Err(format!("Not a session journal: {}", path.display()));
+    }
+    crate::session::recover(&path).map_err(|e| e.to_string())
+}
+
+/// Process audio chunks, emit VAD levels, schedule streaming partial
+/// passes when `streamer` is given, and stop the recording at the end
+/// of speech when `endpoint` is (voice-activated).
+async fn process_audio_chunks(
+    mut rx: mpsc::UnboundedReceiver<AudioChunk>,
+    state: AppState,
+    app: AppHandle,
+    mut streamer: Option<AudioStreamer>,
+    mut endpoint: Option<EndpointDetector>,
+    mut segmenter: Option<Segmenter>,
+) {
+    tracing::info!("VAD processing started");
+
+    while let Some(chunk) = rx.recv().await {
+        // Process with VAD
+        let (result, fallback) = {
+            let mut vad = state.vad.write();
+            let result = vad.process(&chunk.samples);
+            // The AGC holds its gain under what the VAD calls silence
+            state.audio_capture.set_agc_gate(vad.current_threshold());
+            (result, vad.take_fallback())
+        };
+        if let Some(reason) = fallback {
+            crate::errors::report(&app, "vad-fallback", &reason, Some("vad_backend"));
+        }
+
+        if let Some(streamer) = streamer.as_mut() {
+            streamer.push(&chunk.samples);
+            if let Some((window, pass)) = streamer.begin_pass() {
+                spawn_partial_pass(&state, &app, window, pass);
+            }
+        }
+
+        let (_, moved) = state.render.lock().feed_level(result.rms_level);
+        if moved {
+            crate::render::invalidate(&app);
+        }
+
+        // Emit VAD level to frontend
+        let _ = app.emit(
+            "vad:level",
+            serde_json::json!({
+                "rms": result.rms_level,
+                "isSpeech": result.is_speech,
+                "threshold": result.threshold_level,
+                "aboveThreshold": result.above_threshold
+            }),
+        );
+
+        let end = endpoint.as_mut().and_then(|endpoint| {
+            endpoint.observe(result.is_speech, chunk.samples.len(), chunk.sample_rate)
+        });
+        if let Some(end) = end {
+            on_voice_end(&state, &app, end);
+        }
+        if let Some(segmenter) = segmenter.as_mut() {
+            match segmenter.observe(result.is_speech, chunk.samples.len(), chunk.sample_rate) {
+                Some(Cut::Utterance) if !queue_utterance(&state) => segmenter.defer(),
+                Some(Cut::Silence) => trim_silence(&state),
+                _ => {}
+            }
+        }
+    }
+
+    tracing::info!("VAD processing stopped");
+}
+
+/// A voice-activated recording is over: stop it as `stop_listen` would,
+/// unless the user already did. Off this task, which keeps sending levels
+/// until the capture stops.
+fn on_voice_end(state: &AppState, app: &AppHandle, end: VoiceEnd) {
+    if !state.get_status().is_recording() {
+        return;
+    }
+    tracing::info!("Voice-activated recording over: {:?}", end);
+    let _ = app.emit("listen:voice-end", serde_json::json!({ "reason": end }));
+    let tasks = state.tasks.clone();
+    let (state, app) = (state.clone(), app.clone());
+    tasks.spawn("voice-stop", async move {
+        if let Err(e) = stop_recording(&state, &app).await {
+            tracing::info!("Voice-activated recording: {}", e);
+        }
+    });
+}
+
+/// Cut a continuous recording's utterance off the capture and queue it
+/// for transcription. False, cutting nothing, when the queue is full.
+fn queue_utterance(state: &AppState) -> bool {
+    // Held through the send, so `finish_recording` queues the last after
+    let utterances = state.utterances.lock();
+    let Some(queue) = utterances.as_ref() else {
+        return true;
+    };
+    let permit = match queue.sender.try_reserve() {
+        Ok(permit) => permit,
+        Err(mpsc::error::TrySendError::Full(())) => return false,
+        // The transcriber is gone: the audio stays for the flush
+        Err(mpsc::error::TrySendError::Closed(())) => return true,
+    };
+    let samples = state.audio_capture.cut(0);
+    let (speech_ms, speech) = take_speech(state);
+    tracing::debug!(
+        "Utterance cut: {} samples, {} ms of speech",
+        samples.len(),
+        speech_ms
+    );
+    permit.send(UtteranceAudio {
+        samples,
+        speech_ms,
+        speech,
+        ended_at_ms: unix_ms(),
+        last: false,
+    });
+    true
+}
+
+/// Drop a continuous recording's silence but for the last
+/// `UTTERANCE_LEAD_MS`.
+fn trim_silence(state: &AppState) {
+    let utterances = state.utterances.lock();
+    if utterances.is_none() {
+        return;
+    }
+    let keep = UTTERANCE_LEAD_MS * 16000 / 1000;
+    state.audio_capture.cut(keep as usize);
+    state.vad.write().reset_to(keep);
+}
+
+/// Queue a continuous recording's `last` utterance and wait for the
+/// transcriber to get through the queue.
+async fn flush_utterances(queue: UtteranceQueue, last: UtteranceAudio) -> Result<String, String> {
+    let UtteranceQueue { sender, done } = queue;
+    if sender.send(last).await.is_err() {
+        tracing::warn!("Utterance transcriber gone, dropping the last utterance");
+    }
+    drop(sender);
+    done.await
+        .unwrap_or_else(|_| Err("Utterance transcription stopped".to_string()))
+}
+
+/// A continuous recording's transcriber: its utterances, in order, up to
+/// the last. `done` then gets the recording's text, what the utterances
+/// said joined, or the last one's error when none said anything.
+async fn transcribe_utterances(
+    mut utterances: mpsc::Receiver<UtteranceAudio>,
+    state: AppState,
+    app: AppHandle,
+    done: oneshot::Sender<Result<String, String>>,
+) {
+    let mut texts = Vec::new();
+    let mut index = 0;
+    while let Some(audio) = utterances.recv().await {
+        index += 1;
+        let utterance = audio.utterance(index, 16000);
+        let recording = Recording {
+            samples: audio.samples,
+            speech_ms: audio.speech_ms,
+            speech: audio.speech,
+            pauses: Vec::new(),
+            utterance: Some(utterance),
+            // The recording holds the capture until its last utterance
+            verbatim: state.verbatim.load(Ordering::Relaxed),
+        };
+        let result = transcribe_recording(&state, &app, recording).await;
+        match &result {
+            Ok(text) if !text.trim().is_empty() => {
+                if let Some(journal) = state.journal.lock().as_mut() {
+                    append_to_journal(journal, text);
+                }
+                texts.push(text.trim().to_string());
+            }
+            Ok(_) => {}
+            Err(e) => tracing::info!("Utterance {}: {}", index, e),
+        }
+        if utterance.last {
+            let text = match result {
+                Err(e) if texts.is_empty() => Err(e),
+                _ => Ok(texts.join(" ")),
+            };
+            let _ = done.send(text);
+            return;
+        }
+    }
+}
+
+/// How often a recording checks whether the system default input
+/// changed.
+const DEFAULT_INPUT_POLL: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Watch the recording's input until it ends: follow the system default
+/// device when it changes, and handle the device going away (see
+/// `on_stream_failure`). One task for both, so they never reopen the
+/// stream at the same time.
+async fn watch_input(
+    mut errors: mpsc::UnboundedReceiver<StreamFailure>,
+    state: AppState,
+    app: AppHandle,
+) {
+    let mut reopened = false;
+    let mut poll = tokio::time::interval(DEFAULT_INPUT_POLL);
+    poll.tick().await; // the first tick is immediate
+    loop {
+        tokio::select! {
+            biased;
+            failure = errors.recv() => match failure {
+                Some(failure) => {
+                    if on_stream_failure(&state, &app, failure, &mut reopened).await {
+                        return;
+                    }
+                }
+                // The next recording's channel replaced this one
+                None => return,
+            },
+            _ = poll.tick() => {
+                if !state.get_status().is_recording() {
+                    return;
+                }
+                if let Err(message) = follow_default_input(&state, &app).await {
+                    // The old stream went with the attempt: a lost
+                    // device that couldn't be reopened
+                    reopened = true;
+                    let failure = StreamFailure {
+                        message,
+                        device_lost: true,
+                    };
+                    if on_stream_failure(&state, &app, failure, &mut reopened).await {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// With no device preferred, move the recording to the system default
+/// input when that changed (AirPods plugged in mid-dictation), with an
+/// `audio:device-changed`. The buffer carries on across the switch.
+/// Fails when the new device can't be opened.
+async fn follow_default_input(state: &AppState, app: &AppHandle) -> Result<(), String> {
+    let capture = Arc::clone(&state.audio_capture);
+    // CoreAudio and ALSA probe the devices: off the runtime
+    match tokio::task::spawn_blocking(move || capture.follow_default_device()).await {
+        Ok(Ok(Some(name))) => {
+            tracing::info!("Recording moved to the default input \"{}\"", name);
+            let _ = app.emit("audio:device-changed", serde_json::json!({ "name": name }));
+        }
+        Ok(Ok(None)) => {}
+        Ok(Err(e)) => {
+            tracing::warn!("Couldn't move to the default input: {}", e);
+            return Err(e.to_string());
+        }
+        Err(e) => tracing::warn!("Default input check join error: {}", e),
+    }
+    Ok(())
+}
+
+/// The recording's device went away (a Bluetooth headset out of
+/// battery), which would leave the app Listening to nothing: reopen it
+/// once, then end the recording, transcribing what was captured if
+/// there is enough of it or else moving to Error. Each step is an
+/// `audio:error`. Other stream errors are only reported. Whether the
+/// recording ended.
+async fn on_stream_failure(
+    state: &AppState,
+    app: &AppHandle,
+    failure: StreamFailure,
+    reopened: &mut bool,
+) -> bool {
+    if !failure.device_lost || !state.get_status().is_recording() {
+        return false;
+    }
+    let captured_ms = (state.audio_capture.buffer_duration() * 1000.0) as u64;
+    let mut action = InputLoss::after(captured_ms, *reopened);
+    tracing::warn!(
+        "Input device lost after {} ms of recording: {}",
+        captured_ms,
+        failure.message
+    );
+    if action == InputLoss::Reopen {
+        *reopened = true;
+        match restart_capture(state).await {
+            Ok(()) => {
+                emit_input_loss(app, &failure.message, action);
+                return false;
+            }
+            Err(e) => {
+                tracing::warn!("Couldn't reopen the input: {}", e);
+                action = InputLoss::after(captured_ms, true);
+            }
+        }
+    }
+    emit_input_loss(app, &failure.message, action);
+
+    if action == InputLoss::Transcribe {
+        // What `stop_listen` does, unless one is already running
+        let Ok(change) = state.begin_stop() else {
+            return true;
+        };
+        announce(state, app, change);
+        if let Err(e) = finish_recording(state, app).await {
+            tracing::warn!("Recording cut short by the input loss: {}", e);
+        }
+        if let Some(change) = state.end_processing() {
+            announce(state, app, change);
+        }
+    } else if transition(state, app, AppStatus::Error).is_ok() {
+        let _ = stop_capture(state).await;
+        state.journal.lock().take();
+        state.vad.write().reset();
+    }
+    true
+}
+
+fn emit_input_loss(app: &AppHandle, message: &str, action: InputLoss) {
+    let _ = app.emit(
+        "audio:error",
+        serde_json::json!({ "message": message, "action": action }),
+    );
+}
+
+/// Transcribe a streaming window in the background and emit
+/// `transcript:partial`. Uses a detached whisper state so `stop_listen`
+/// never waits on it; a result that lands after recording stopped is
+/// dropped.
+fn spawn_partial_pass(state: &AppState, app: &AppHandle, window: Vec<i16>, pass: PartialPass) {
+    if crate::audio::is_silent_buffer(&window) {
+        return;
+    }
+    let whisper = state.whisper.clone();
+    let task_state = state.clone();
+    let app = app.clone();
+    state.tasks.spawn("partial-transcription", async move {
+        let result = tokio::task::spawn_blocking(move || {
+            let _pass = pass; // released when the decode finishes
+            whisper.transcribe_detached(&window)
+        })
+        .await;
+        match result {
+            Ok(Ok(result)) => {
+                if task_state.get_status() == AppStatus::Listening && !result.text.is_empty() {
+                    task_state.render.lock().set_partial(&result.text);
+                    crate::render::invalidate(&app);
+                    let _ = app.emit(
+                        "transcript:partial",
+                        serde_json::json!({ "text": result.text }),
+                    );
+                }
+            }
+            Ok(Err(e)) => tracing::debug!("Partial pass failed: {}", e),
+            Err(e) => tracing::warn!("Partial pass join error: {}", e),
+        }
+    });
+}
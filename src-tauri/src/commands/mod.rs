@@ -0,0 +1,221 @@
+//! The Tauri commands, one module per domain. `handler` registers
+//! them all; a command missing from it fails
+//! `every_command_is_registered`.
+
+mod audio;
+mod gpu;
+mod history;
+mod listen;
+mod models;
+mod output;
+mod permissions;
+mod prelude;
+mod ptt;
+mod settings;
+mod status;
+mod watch;
+
+pub use audio::*;
+pub use gpu::*;
+pub use history::*;
+pub use listen::*;
+pub use models::*;
+pub use output::*;
+pub use permissions::*;
+pub use ptt::*;
+pub use settings::*;
+pub use status::*;
+pub use watch::*;
+
+/// The invoke handler of every command.
+pub(crate) fn handler() -> impl Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static {
+    tauri::generate_handler![
+        listen::start_listen,
+        listen::stop_listen,
+        listen::pause_listen,
+        listen::resume_listen,
+        listen::cancel_transcription,
+        listen::retranscribe_last,
+        listen::save_last_recording,
+        listen::transcribe_file,
+        listen::detect_language,
+        listen::explain_language_choice,
+        listen::ack_state,
+        listen::get_queue_length,
+        listen::clear_dictation_context,
+        listen::set_context_carry_over,
+        listen::set_context_idle_reset_secs,
+        listen::recover_session,
+        models::set_model,
+        models::load_whisper_model,
+        models::load_draft_model,
+        models::is_model_loaded,
+        models::get_model_info,
+        models::get_model_memory,
+        models::list_required_models,
+        models::download_model,
+        models::cancel_download,
+        models::get_pending_downloads,
+        models::discard_pending_download,
+        models::import_model,
+        models::verify_model,
+        models::get_available_models,
+        models::get_model_catalog,
+        models::load_whisper_model_with_options,
+        models::load_whisper_model_from_path,
+        models::list_all_models,
+        models::validate_custom_model,
+        models::add_custom_model,
+        models::remove_custom_model,
+        models::delete_model,
+        models::set_model_disabled,
+        models::set_quantization,
+        models::set_models_dir,
+        settings::set_language,
+        settings::set_shortcut,
+        settings::set_language_toggle_shortcut,
+        settings::set_model_toggle_shortcut,
+        settings::set_learn_vocabulary_shortcut,
+        settings::set_favorite_languages,
+        settings::set_model_languages,
+        settings::set_language_cycle_mode,
+        settings::set_retained_audio_mb,
+        settings::set_capture_spill_mb,
+        settings::get_settings,
+        settings::flush_settings,
+        settings::set_translate,
+        settings::set_decode_overrides,
+        settings::set_decoding_options,
+        settings::set_advanced_decoding,
+        settings::set_no_speech_threshold,
+        settings::set_suppress_non_speech,
+        settings::set_deterministic_mode,
+        settings::set_min_speech_ms,
+        settings::set_vad_config,
+        settings::set_adaptive_threshold,
+        settings::set_vad_backend,
+        settings::benchmark_vad,
+        settings::set_force_transcription,
+        settings::set_streaming_partials,
+        settings::set_partial_interval_ms,
+        settings::set_transcription_queue_depth,
+        settings::set_transcription_timeout_secs,
+        settings::set_privacy_mode,
+        settings::set_debug_save_recordings,
+        settings::set_vulkan_warning_dismissed,
+        settings::set_welcome_dismissed,
+        output::learn_from_clipboard,
+        output::accept_vocabulary_suggestions,
+        output::set_auto_copy,
+        output::set_filler_mode,
+        output::set_filler_words,
+        output::preview_text_pipeline,
+        output::set_clipboard_dwell_ms,
+        output::set_clipboard_append,
+        output::add_snippet,
+        output::update_snippet,
+        output::delete_snippet,
+        history::add_history_entry,
+        history::clear_history,
+        history::get_transcript_history,
+        history::enable_history_encryption,
+        history::disable_history_encryption,
+        history::unlock_history,
+        history::lock_history,
+        history::reset_history,
+        status::get_mic_usage_log,
+        status::clear_mic_usage_log,
+        status::get_memory_usage,
+        status::get_app_status,
+        status::get_render_state,
+        status::get_recent_errors,
+        status::clear_recent_errors,
+        status::report_command_error,
+        status::get_task_status,
+        status::get_data_paths,
+        status::list_crash_reports,
+        status::delete_crash_report,
+        permissions::check_permissions,
+        permissions::request_microphone_permission,
+        permissions::set_pre_roll,
+        audio::get_audio_devices,
+        audio::set_audio_device,
+        audio::set_input_gain,
+        audio::set_agc,
+        audio::set_audio_filters,
+        audio::set_channel_mode,
+        audio::set_capture_source,
+        audio::list_capture_applications,
+        audio::set_capture_application,
+        audio::calibrate_noise_floor,
+        audio::start_level_monitor,
+        audio::stop_level_monitor,
+        gpu::get_gpu_info,
+        gpu::check_system_health,
+        gpu::get_gpu_status,
+        gpu::recommend_model,
+        gpu::set_gpu_device,
+        ptt::list_hid_devices,
+        ptt::start_ptt_binding,
+        ptt::bind_ptt_device,
+        ptt::unbind_ptt_device,
+        watch::watch_folder,
+        watch::unwatch_folder,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    /// Each command module, with its source.
+    const MODULES: &[(&str, &str)] = &[
+        ("audio", include_str!("audio.rs")),
+        ("gpu", include_str!("gpu.rs")),
+        ("history", include_str!("history.rs")),
+        ("listen", include_str!("listen.rs")),
+        ("models", include_str!("models.rs")),
+        ("output", include_str!("output.rs")),
+        ("permissions", include_str!("permissions.rs")),
+        ("ptt", include_str!("ptt.rs")),
+        ("settings", include_str!("settings.rs")),
+        ("status", include_str!("status.rs")),
+        ("watch", include_str!("watch.rs")),
+    ];
+
+    /// The functions marked `#[tauri::command]` in `source`.
+    fn commands(source: &str) -> Vec<&str> {
+        let lines: Vec<&str> = source.lines().map(str::trim).collect();
+        lines
+            .windows(2)
+            .filter(|pair| pair[0] == "#[tauri::command]")
+            .filter_map(|pair| pair[1].split("fn ").nth(1)?.split(['(', '<']).next())
+            .collect()
+    }
+
+    #[test]
+    fn every_command_is_registered() {
+        let source = include_str!("mod.rs");
+        let list = source.split("generate_handler![").nth(1).unwrap();
+        let mut registered: Vec<&str> = list[..list.find(']').unwrap()]
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        let mut declared: Vec<String> = MODULES
+            .iter()
+            .flat_map(|(module, source)| {
+                commands(source)
+                    .into_iter()
+                    .map(move |name| format!("{module}::{name}"))
+            })
+            .collect();
+        for command in &declared {
+            assert!(
+                registered.contains(&command.as_str()),
+                "{command} is missing from `handler`"
+            );
+        }
+        registered.sort_unstable();
+        declared.sort_unstable();
+        assert_eq!(registered, declared);
+    }
+}
@@ -0,0 +1,1775 @@
+//! Whisper models: the built-in registry, downloads, custom imports,
+//! loading and the engine settings applied on load.
+
+use super::gpu::detect_hardware;
+use super::listen::emit_state_change;
+use super::prelude::*;
+use super::settings::persist_and_broadcast;
+use crate::degraded;
+use crate::model_download::{
+    self, Continuation, DownloadError, ModelSource, PartialMeta, PendingDownload,
+};
+use crate::model_import::{self, ImportError, ImportedModel, Placement};
+use crate::model_integrity::{self, IntegrityError, ModelVerification};
+use crate::whisper::catalog::{self, CatalogModel};
+use crate::whisper::decode::{DecodeOverride, DecodingOptions};
+use crate::whisper::deterministic;
+use crate::whisper::load_failure::{LoadFailure, LoadFailureKind};
+use crate::whisper::model_files::{self, FoundModel, ModelDir, ModelLocation, CUSTOM_MODEL_ID};
+use crate::whisper::suitability;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+/// Static description of every Whisper model the app knows how to fetch.
+/// Each entry maps a short model id (the same one we persist in settings
+/// and pass to whisper-rs) to its canonical filename, the public URL of
+/// the bundled GitHub Release `models-v1` and the SHA-256 of the file
+/// hosted there. Keep this in sync with the GitHub Release contents — if
+/// you re-cut `models-v1` you must update both the URL tag (if you
+/// rename it) and the SHA below.
+struct ModelEntry {
+    /// The id we accept in commands and store in settings.
+    id: &'static str,
+    /// Canonical filename inside `<data root>/models/` (and inside the
+    /// dev-mode `src-tauri/models/` folder).
+    filename: &'static str,
+    /// Stable public URL on the `models-v1` GitHub Release.
+    url: &'static str,
+    /// Hex-encoded SHA-256 of the file at `url`. Verified after every
+    /// download; mismatch deletes the partial file and surfaces an error.
+    sha256: &'static str,
+    /// Human-friendly label used in download progress events / UI.
+    display_name: &'static str,
+    /// Total size in bytes (used for progress when the response doesn't
+    /// expose a Content-Length, e.g. transparently-compressed responses).
+    size_bytes: u64,
+    /// Pre-known capabilities for the built-in. We can't run the
+    /// validator on these on app boot because the file might not be
+    /// downloaded yet; values are taken from the upstream Whisper
+    /// architecture spec and the quantisation variant we ship.
+    is_multilingual: bool,
+    n_vocab: i32,
+    n_audio_state: i32,
+    n_audio_layer: i32,
+    quant_label: &'static str,
+    size_class: &'static str,
+}
+
+const MODEL_REGISTRY: &[ModelEntry] = &[
+    ModelEntry {
+        id: "small",
+        filename: "ggml-small.bin",
+        url: "https://github.com/AccessDevops/S2Tui/releases/download/models-v1/ggml-small.bin",
+        sha256: "ae85e4a935d7a567bd102fe55afc16bb595bdb618e11b2fc7591bc08120411bb",
+        display_name: "Small",
+        size_bytes: 190085487,
+        is_multilingual: true,
+        n_vocab: 51865,
+        n_audio_state: 768,
+        n_audio_layer: 12,
+        quant_label: "q5_1",
+        size_class: "small",
+    },
+    ModelEntry {
+        id: "large-v3-turbo",
+        filename: "ggml-large-v3-turbo.bin",
+        url: "https://github.com/AccessDevops/S2Tui/releases/download/models-v1/ggml-large-v3-turbo.bin",
+        sha256: "394221709cd5ad1f40c46e6031ca61bce88931e6e088c188294c6d5a55ffa7e2",
+        display_name: "Large V3 Turbo",
+        size_bytes: 601463531,
+        is_multilingual: true,
+        n_vocab: 51866,
+        // Turbo shares the encoder of large-v3 (n_audio_state=1280) but
+        // ships a 4-layer decoder. We surface it as size_class="large"
+        // for the user-facing label since perf characteristics line up
+        // with large more than with medium/small.
+        n_audio_state: 1280,
+        n_audio_layer: 4,
+        quant_label: "q5_0",
+        size_class: "large",
+    },
+];
+
+/// Resolve a model id to its on-disk path, dispatching between
+/// built-in (MODEL_REGISTRY) and user-imported (Settings.user_models).
+/// Used by every `load_*` / `download_*` command — without this,
+/// custom-model ids would always map to a non-existent
+/// `<models_dir>/ggml-<uuid>.bin` and load attempts would fail
+/// instantly, surfacing as a "broken" pill in the UI even though the
+/// file is fine.
+fn resolve_model_path(
+    state: &AppState,
+    app: &AppHandle,
+    model_id: &str,
+) -> Result<PathBuf, String> {
+    // Built-in lookup first — keeps the hot path identical to the
+    // pre-custom-models behaviour for existing users.
+    if let Some(entry) = MODEL_REGISTRY.iter().find(|e| e.id == model_id) {
+        let dirs = model_dirs(state, app)?;
+        let quantization = state.get_settings().quantization;
+        return Ok(builtin_model_path(&dirs, entry, &quantization));
+    }
+    // Custom user-imported model. The path is whatever the user
+    // picked at import time, stored canonical inside Settings.
+    if let Some(user_model) = state.find_user_model(model_id) {
+        return Ok(user_model.path);
+    }
+    // The file `load_whisper_model_from_path` last loaded.
+    if model_id == CUSTOM_MODEL_ID {
+        return state
+            .get_settings()
+            .custom_model_path
+            .ok_or_else(|| "No custom model path set".to_string());
+    }
+    // Any other whisper.cpp model in the models folders (downloaded
+    // from Hugging Face, or in a folder shared with other tools).
+    let quantization = state.get_settings().quantization;
+    model_files::find(&model_dirs(state, app)?, model_id, &quantization)
+        .map(|model| model.path)
+        .ok_or_else(|| format!("Unknown model id: {model_id}"))
+}
+
+/// On-disk file of a built-in: the first of `model_files::candidates`
+/// that exists in `dirs` (so a `ggml-{id}-{quantization}.bin` the user
+/// dropped in wins), else the registry filename `download_model` writes
+/// in the app's own directory.
+fn builtin_model_path(dirs: &[ModelDir], entry: &ModelEntry, quantization: &str) -> PathBuf {
+    if let Some(model) = model_files::find(dirs, entry.id, quantization) {
+        return model.path;
+    }
+    dirs.iter()
+        .find(|dir| dir.location == ModelLocation::AppData)
+        .map_or_else(
+            || PathBuf::from(entry.filename),
+            |dir| dir.path.join(entry.filename),
+        )
+}
+
+/// Directories to look for models in, in order (see
+/// `model_files::ModelLocation`): the user's `models_dir`, the app's
+/// own, then the one shipped in the bundle's resources.
+fn model_dirs(state: &AppState, app: &AppHandle) -> Result<Vec<ModelDir>, String> {
+    let mut dirs = Vec::with_capacity(3);
+    let user_dir = state.get_settings().models_dir;
+    if !user_dir.is_empty() {
+        dirs.push(ModelDir {
+            path: PathBuf::from(user_dir),
+            location: ModelLocation::User,
+        });
+    }
+    dirs.push(ModelDir {
+        path: get_models_dir(app)?,
+        location: ModelLocation::AppData,
+    });
+    if let Ok(resources) = app.path().resource_dir() {
+        dirs.push(ModelDir {
+            path: resources.join("models"),
+            location: ModelLocation::Bundled,
+        });
+    }
+    Ok(model_files::dedup_dirs(dirs))
+}
+
+/// The registry entry `path` is the download of: its filename, in the
+/// app's own or the bundled models directory. A same-named file in the
+/// user's folder may be another build of the model, so it has no
+/// known size or checksum.
+fn registry_file(dirs: &[ModelDir], path: &Path) -> Option<&'static ModelEntry> {
+    let ours = dirs
+        .iter()
+        .any(|dir| dir.location != ModelLocation::User && path.parent() == Some(&*dir.path));
+    let filename = path.file_name()?.to_str()?;
+    MODEL_REGISTRY
+        .iter()
+        .find(|entry| ours && entry.filename == filename)
+}
+
+/// `model_integrity::quick_check` before a load, so a broken file fails
+/// with "re-download" instead of whisper.cpp's own error. A failure
+/// counts towards degraded mode like a failed load.
+fn check_model_file(state: &AppState, app: &AppHandle, path: &Path) -> Result<(), String> {
+    let expected_bytes = registry_file(&model_dirs(state, app)?, path).map(|e| e.size_bytes);
+    if let Err(error) = model_integrity::quick_check(path, expected_bytes) {
+        tracing::error!("Model file refused ({}): {}", path.display(), error);
+        let msg = error.to_string();
+        note_load_failure(
+            state,
+            app,
+            &LoadFailure::new(error.load_failure_kind(), msg.clone()),
+        );
+        return Err(msg);
+    }
+    Ok(())
+}
+
+/// Build the `ModelCapabilities` value for a built-in entry. Mirrors
+/// the shape returned by `whisper::compat::validate` for user-imported
+/// models so the frontend treats both kinds uniformly.
+fn builtin_capabilities(entry: &ModelEntry) -> crate::whisper::ModelCapabilities {
+    crate::whisper::ModelCapabilities {
+        is_multilingual: entry.is_multilingual,
+        size_class: entry.size_class.to_string(),
+        quant_label: entry.quant_label.to_string(),
+        n_vocab: entry.n_vocab,
+        n_audio_state: entry.n_audio_state,
+        n_audio_layer: entry.n_audio_layer,
+        file_size_bytes: entry.size_bytes,
+    }
+}
+
+/// Resolve the directory in which Whisper models live.
+///
+/// Dev mode (`#[cfg(debug_assertions)]`) keeps reading `src-tauri/models/`
+/// directly so a maintainer who already has the bins on disk doesn't have
+/// to re-download anything — unless the dev build runs portable.
+///
+/// Otherwise it's `<data root>/models/` (see `crate::paths`) — a writable
+/// directory created on demand. Models are *no longer* shipped
+/// inside the bundle's `Resources/`; the app downloads them on first
+/// launch via `download_model` (see below), so the directory is the
+/// single mutable cache.
+fn get_models_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let _ = app; // paths are resolved once at startup
+    let paths = crate::paths::get();
+    #[cfg(debug_assertions)]
+    if paths.mode == crate::paths::DataMode::Standard {
+        let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
+        let project_root = exe_path
+            .parent() // target/debug
+            .and_then(|p| p.parent()) // target
+            .and_then(|p| p.parent()) // src-tauri
+            .ok_or("Could not find project root")?;
+        let models_dir = project_root.join("models");
+        tracing::info!("[DEV] Models directory: {}", models_dir.display());
+        return Ok(models_dir);
+    }
+    let models_dir = paths.models_dir();
+    if !models_dir.exists() {
+        std::fs::create_dir_all(&models_dir).map_err(|e| {
+            format!(
+                "Failed to create models dir {}: {}",
+                models_dir.display(),
+                e
+            )
+        })?;
+    }
+    tracing::info!("Models directory: {}", models_dir.display());
+    Ok(models_dir)
+}
+
+/// Load (or with `None`, unload) the draft model: a small model whose
+/// quick transcript is emitted as `transcript:draft` before the main
+/// model's `transcript:final`.
+#[tauri::command]
+pub async fn load_draft_model(
+    model: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let Some(model) = model else {
+        state.whisper.unload_draft_model();
+        return Ok(());
+    };
+    if model == state.get_settings().model {
+        return Err("The draft model must differ from the main model".to_string());
+    }
+    let model_path = resolve_model_path(&state, &app, &model)?;
+    tracing::info!("Loading draft model: {}", model_path.display());
+    let whisper = state.whisper.clone();
+    let result = tokio::task::spawn_blocking(move || whisper.load_draft_model(model_path))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())?;
+    tracing::info!("Draft model loaded ({})", result.backend);
+    Ok(())
+}
+
+/// Start loading `model` and return the load's id right away; loading
+/// large-v3 takes 10–20 s. Progress comes as `model:loading` events
+/// (see `whisper::loading`), the outcome as `model:loaded` or
+/// `model:load-failed`. Rejected while another load runs.
+#[tauri::command]
+pub async fn load_whisper_model(
+    model: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<u64, String> {
+    tracing::info!("Loading Whisper model: {}", model);
+    let ticket = state.model_load.try_begin().map_err(|e| e.to_string())?;
+
+    // Resolve via the shared helper so user-imported (uuid-keyed)
+    // ids land on their actual stored path, not a synthesised
+    // `ggml-<uuid>.bin` that doesn't exist.
+    let model_path = resolve_model_path(&state, &app, &model)?;
+    tracing::info!("Looking for model at: {}", model_path.display());
+
+    if !model_path.exists() {
+        tracing::error!("Model file not found: {}", model_path.display());
+        let msg = format!("Model file not found at {}", model_path.display());
+        note_load_failure(
+            &state,
+            &app,
+            &LoadFailure::new(LoadFailureKind::ModelMissing, msg.clone()),
+        );
+        return Err(msg);
+    }
+    check_model_file(&state, &app, &model_path)?;
+
+    tracing::info!("Model file found, loading...");
+
+    let load_id = ticket.id();
+    let task_state = state.inner().clone();
+    state.tasks.spawn("model-load", async move {
+        let _ticket = ticket;
+        if let Err(failure) = run_model_load(&task_state, &app, &model, model_path, load_id).await {
+            let _ = app.emit(
+                "model:load-failed",
+                serde_json::json!({
+                    "loadId": load_id,
+                    "model": model,
+                    "error": failure.to_string(),
+                    "failure": failure,
+                    "suggestions": degraded::suggestions_for(failure.kind),
+                }),
+            );
+        }
+    });
+    Ok(load_id)
+}
+
+/// How often `model:loading` is emitted.
+const LOAD_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Body of `load_whisper_model`, in the background.
+async fn run_model_load(
+    state: &AppState,
+    app: &AppHandle,
+    model: &str,
+    model_path: PathBuf,
+    load_id: u64,
+) -> Result<(), LoadFailure> {
+    use crate::whisper::loading::{LoadEstimate, LoadProgress, RssReader};
+
+    let file_bytes = std::fs::metadata(&model_path).map(|m| m.len()).unwrap_or(0);
+    let mut rss = RssReader::for_current_process();
+    let mut estimate = LoadEstimate::new(file_bytes, rss.read());
+    let started = std::time::Instant::now();
+
+    // Load on the whisper thread (after any transcription in flight)
+    let settings = state.get_settings();
+    state.whisper.set_gpu_device(settings.gpu_device);
+    let load =
+        state
+            .whisper
+            .load_model_with_options(model_path, false, settings.flash_attention, true);
+    tokio::pin!(load);
+    let mut ticks = tokio::time::interval(LOAD_PROGRESS_INTERVAL);
+    let loaded = loop {
+        tokio::select! {
+            loaded = &mut load => break loaded,
+            _ = ticks.tick() => {
+                let elapsed = started.elapsed();
+                let _ = app.emit(
+                    "model:loading",
+                    LoadProgress {
+                        load_id,
+                        model: model.to_string(),
+                        elapsed_ms: elapsed.as_millis() as u64,
+                        percent: estimate.update(elapsed, rss.read()),
+                    },
+                );
+            }
+        }
+    };
+    let loaded = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            let failure = LoadFailure::from_error(&e);
+            note_load_failure(state, app, &failure);
+            return Err(failure);
+        }
+    };
+    // Update settings first: the suitability check reads the model
+    // from them
+    state.update_settings(|s| {
+        s.model = model.to_string();
+    });
+    note_load_success(state, app, model);
+    emit_warmed(app, model, &loaded);
+
+    apply_engine_settings(state);
+
+    // The model is loaded; these only fail on the app side
+    let unexpected = |e: String| LoadFailure::new(LoadFailureKind::Unknown, e);
+    app.emit("model:loaded", model)
+        .map_err(|e| unexpected(e.to_string()))?;
+
+    persist_and_broadcast(state, app).map_err(unexpected)?;
+
+    tracing::info!("Whisper model loaded successfully: {}", model);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn is_model_loaded(state: State<'_, AppState>) -> bool {
+    state.whisper.is_loaded()
+}
+
+/// Capabilities of the loaded model, so the UI can e.g. hide language
+/// selection for an English-only model.
+#[tauri::command]
+pub fn get_model_info(
+    state: State<'_, AppState>,
+) -> Result<crate::whisper::ModelInfo, crate::whisper::ModelInfoError> {
+    state.whisper.model_info()
+}
+
+/// Memory the loaded model takes (file, RAM, VRAM), measured when it
+/// loaded.
+#[tauri::command]
+pub fn get_model_memory(
+    state: State<'_, AppState>,
+) -> Result<crate::whisper::ModelMemory, crate::whisper::ModelInfoError> {
+    state.whisper.model_memory()
+}
+
+/// Re-apply every engine-facing setting after a model load, so the
+/// engine config stays in sync with user settings even if a setter ran
+/// before the model was ready.
+fn apply_engine_settings(state: &AppState) {
+    let settings = state.get_settings();
+    let whisper_code = settings.language.to_whisper_code().map(String::from);
+    state.whisper.set_language(whisper_code.clone());
+    tracing::info!(
+        "Whisper language re-applied after model load: {}",
+        whisper_code.as_deref().unwrap_or("auto-detect")
+    );
+    state.whisper.set_translate(settings.translate);
+    state
+        .whisper
+        .set_decode_advanced(user_decode_override(&settings));
+    state
+        .whisper
+        .set_advanced_decoding(settings.advanced_decoding);
+    state
+        .whisper
+        .set_no_speech_threshold(settings.no_speech_threshold);
+    state
+        .whisper
+        .set_suppress_non_speech(settings.suppress_non_speech);
+    state
+        .whisper
+        .set_deterministic(deterministic_mode(&settings));
+    state
+        .whisper
+        .set_decode_overrides(settings.decode_overrides);
+}
+
+/// `Settings.deterministic_mode`, or `--deterministic` on the command
+/// line.
+pub(crate) fn deterministic_mode(settings: &Settings) -> bool {
+    let args: Vec<String> = std::env::args().collect();
+    settings.deterministic_mode || deterministic::requested(&args)
+}
+
+/// The "user advanced settings" decode layer: the chosen strategy plus
+/// the base temperature from the advanced decoding settings.
+pub(super) fn user_decode_override(settings: &Settings) -> DecodeOverride {
+    DecodeOverride {
+        temperature: Some(settings.advanced_decoding.temperature),
+        ..settings
+            .decoding_strategy
+            .map(DecodingOptions::to_override)
+            .unwrap_or_default()
+    }
+}
+
+/// Feed a failed model load into the degraded-mode tracker. When this
+/// failure tips the app into degraded mode, tell the overlay
+/// (`app:degraded`) and the tray tooltip.
+fn note_load_failure(state: &AppState, app: &AppHandle, failure: &LoadFailure) {
+    let message = failure.to_string();
+    crate::errors::report(
+        app,
+        "model-load",
+        &message,
+        Some(&state.get_settings().model),
+    );
+    let (reason, change) = state.record_load_failure(failure.kind, &message);
+    if let Some(change) = change {
+        crate::render::invalidate(app);
+        emit_state_change(state, app, change);
+    }
+    if let Some(reason) = reason {
+        tracing::error!(
+            "Entering degraded mode ({:?}): {}",
+            reason.cause,
+            reason.reason
+        );
+        let _ = app.emit("app:degraded", &reason);
+        crate::update_tray_tooltip(app, Some(&reason));
+    }
+}
+
+/// Counterpart of `note_load_failure`: any successful load resets the
+/// failure counter and clears degraded mode if it was active.
+fn note_load_success(state: &AppState, app: &AppHandle, model: &str) {
+    crate::crash::recorder().note_model(model, &state.whisper.get_backend_name());
+    let (cleared, change) = state.record_load_success();
+    if let Some(change) = change {
+        crate::render::invalidate(app);
+        emit_state_change(state, app, change);
+    }
+    if cleared {
+        tracing::info!("Model loaded — leaving degraded mode");
+        crate::update_tray_tooltip(app, None);
+    }
+    warn_if_unsuitable(state, app);
+}
+
+/// Emit `config:warning` if the loaded model is a poor fit for the
+/// selected language (see `whisper::suitability`), once per combination
+/// per session.
+pub(super) fn warn_if_unsuitable(state: &AppState, app: &AppHandle) {
+    let Ok(info) = state.whisper.model_info() else {
+        return;
+    };
+    let settings = state.get_settings();
+    let language = settings.language.to_whisper_code();
+    let Some(warning) = suitability::check(&info.size_class, info.is_multilingual, language) else {
+        return;
+    };
+    if state
+        .config_warnings
+        .lock()
+        .first_time(&settings.model, settings.language.to_code())
+    {
+        tracing::warn!("{} ({})", warning.message, warning.suggestion);
+        let _ = app.emit("config:warning", warning);
+    }
+}
+
+#[tauri::command]
+pub fn set_model(name: String, state: State<'_, AppState>, app: AppHandle) -> Result<(), String> {
+    tracing::info!("Setting model: {}", name);
+    state.update_settings(|s| {
+        s.model = name;
+    });
+    persist_and_broadcast(&state, &app)
+}
+
+/// Set the quantization suffix tried first for built-in model files
+/// (`"q5_0"`, `"q8_0"`, …; empty for plain `ggml-{model}.bin` only).
+/// Takes effect at the next load.
+#[tauri::command]
+pub fn set_quantization(
+    quantization: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let quantization = quantization.trim().to_lowercase();
+    if !quantization.is_empty() && !model_files::is_quantization(&quantization) {
+        return Err(format!("Unknown quantization: {quantization}"));
+    }
+    state.update_settings(|s| s.quantization = quantization);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Get list of available models on disk
+/// Dynamically scans for ggml-*.bin files and extracts model names,
+/// with the quantization suffix split off (see `model_files`). Every
+/// directory of `model_dirs` is scanned, the earlier one winning for a
+/// model id found in several; each model comes with its path, which
+/// directory it is from, its size and modification time. The file of
+/// `Settings.custom_model_path` comes last, as model `custom`.
+#[tauri::command]
+pub fn get_available_models(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<FoundModel>, String> {
+    let dirs = model_dirs(&state, &app)?;
+    for dir in &dirs {
+        tracing::info!(
+            "Scanning for models in: {} ({:?})",
+            dir.path.display(),
+            dir.location
+        );
+    }
+    let mut available = model_files::scan(&dirs);
+    if let Some(path) = state.get_settings().custom_model_path {
+        available.extend(model_files::custom(&path));
+    }
+    tracing::info!("Available models: {:?}", available);
+    Ok(available)
+}
+
+/// The official whisper.cpp models to offer for download (see
+/// `whisper::catalog`), each marked installed or not from
+/// `get_available_models` and recommended or not for this machine.
+#[tauri::command]
+pub async fn get_model_catalog(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<CatalogModel>, String> {
+    let hardware = detect_hardware(&state).await?;
+    let available = get_available_models(state, app)?;
+    let mut models = catalog::catalog(&hardware, &available);
+    // A built-in downloads from the release, already quantized
+    for model in &mut models {
+        if let Some(entry) = MODEL_REGISTRY.iter().find(|e| e.id == model.id) {
+            model.size_bytes = entry.size_bytes;
+        }
+    }
+    Ok(models)
+}
+
+/// Set the folder searched for models before the app's own
+/// (`Settings.models_dir`); an empty string clears it. It must be an
+/// existing directory.
+#[tauri::command]
+pub fn set_models_dir(
+    dir: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let dir = if dir.trim().is_empty() {
+        String::new()
+    } else {
+        let path = PathBuf::from(dir.trim());
+        if !path.is_dir() {
+            return Err(format!("Not a directory: {}", path.display()));
+        }
+        path.canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?
+            .to_string_lossy()
+            .into_owned()
+    };
+    tracing::info!("Setting models dir: {:?}", dir);
+    state.update_settings(|s| s.models_dir = dir);
+    persist_and_broadcast(&state, &app)
+}
+
+/// `model:warmed`, once a load's warm-up pass (see
+/// `WhisperEngine::load_model_with_options`) has run.
+fn emit_warmed(app: &AppHandle, model: &str, loaded: &crate::whisper::ModelLoadResult) {
+    if let Some(duration_ms) = loaded.warmup_ms {
+        let _ = app.emit(
+            "model:warmed",
+            serde_json::json!({ "model": model, "durationMs": duration_ms }),
+        );
+    }
+}
+
+/// Load Whisper model with explicit GPU/CPU control. `flash_attention`,
+/// when given, is also saved as the preference for later loads; when
+/// omitted the saved preference is used. `warmup` (default on) runs a
+/// second of silence through the model right away, so the first
+/// dictation isn't the slow one.
+#[tauri::command]
+pub async fn load_whisper_model_with_options(
+    model: String,
+    force_cpu: bool,
+    flash_attention: Option<bool>,
+    warmup: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<crate::whisper::ModelLoadResult, String> {
+    load_with_options(
+        &state,
+        &app,
+        model,
+        None,
+        force_cpu,
+        flash_attention,
+        warmup,
+    )
+    .await
+}
+
+/// Body of `load_whisper_model_with_options`, shared with
+/// `load_whisper_model_from_path`, which passes the file as
+/// `custom_path`: it becomes `Settings.custom_model_path` once loaded.
+async fn load_with_options(
+    state: &AppState,
+    app: &AppHandle,
+    model: String,
+    custom_path: Option<PathBuf>,
+    force_cpu: bool,
+    flash_attention: Option<bool>,
+    warmup: Option<bool>,
+) -> Result<crate::whisper::ModelLoadResult, String> {
+    let _ticket = state.model_load.try_begin().map_err(|e| e.to_string())?;
+    let settings = state.get_settings();
+    let flash_attention = flash_attention.unwrap_or(settings.flash_attention);
+    tracing::info!(
+        "Loading Whisper model: {} (force_cpu={}, flash_attention={})",
+        model,
+        force_cpu,
+        flash_attention
+    );
+
+    // Same resolution as `load_whisper_model`: built-in or
+    // user-imported, always via the shared helper.
+    let model_path = match &custom_path {
+        Some(path) => path.clone(),
+        None => resolve_model_path(state, app, &model)?,
+    };
+    tracing::info!("Looking for model at: {}", model_path.display());
+
+    if !model_path.exists() {
+        tracing::error!("Model file not found: {}", model_path.display());
+        let msg = format!("Model file not found at {}", model_path.display());
+        note_load_failure(
+            state,
+            app,
+            &LoadFailure::new(LoadFailureKind::ModelMissing, msg.clone()),
+        );
+        return Err(msg);
+    }
+    check_model_file(state, app, &model_path)?;
+
+    tracing::info!("Model file found, loading with options...");
+
+    // Load on the whisper thread (after any transcription in flight)
+    state.whisper.set_gpu_device(settings.gpu_device);
+    let result = state
+        .whisper
+        .load_model_with_options(
+            model_path,
+            force_cpu,
+            flash_attention,
+            warmup.unwrap_or(true),
+        )
+        .await;
+    let result = match result {
+        Ok(r) => r,
+        Err(e) => {
+            note_load_failure(state, app, &LoadFailure::from_error(&e));
+            return Err(e.to_string());
+        }
+    };
+    // Update settings first: the suitability check reads the model
+    // from them
+    state.update_settings(|s| {
+        s.model = model.clone();
+        s.flash_attention = flash_attention;
+        if custom_path.is_some() {
+            s.custom_model_path = custom_path;
+        }
+    });
+    note_load_success(state, app, &model);
+    emit_warmed(app, &model, &result);
+
+    apply_engine_settings(state);
+
+    // Emit events
+    app.emit("model:loaded", &model)
+        .map_err(|e| e.to_string())?;
+
+    // Emit GPU status event
+    if result.fallback_used {
+        app.emit("health:gpu-fallback", &result)
+            .map_err(|e| e.to_string())?;
+    }
+    if result.flash_attention_fallback {
+        app.emit("health:flash-attention-fallback", &result)
+            .map_err(|e| e.to_string())?;
+    }
+
+    tracing::info!(
+        "Whisper model loaded: {} (using_gpu={}, backend={}, fallback={})",
+        model,
+        result.using_gpu,
+        result.backend,
+        result.fallback_used
+    );
+
+    persist_and_broadcast(state, app)?;
+
+    Ok(result)
+}
+
+/// Load the model file at `path`, whatever its name (a fine-tuned
+/// model, say), as model `custom`. Remembered as
+/// `Settings.custom_model_path`, so the next launch loads it again;
+/// a failed load keeps the previous one.
+#[tauri::command]
+pub async fn load_whisper_model_from_path(
+    path: String,
+    force_cpu: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<crate::whisper::ModelLoadResult, String> {
+    let path = PathBuf::from(path.trim());
+    if !model_files::has_model_extension(&path) {
+        return Err(format!(
+            "Not a model file (expected .{}): {}",
+            model_files::MODEL_EXTENSIONS.join(" or ."),
+            path.display()
+        ));
+    }
+    if !path.is_file() {
+        return Err(format!("Model file not found at {}", path.display()));
+    }
+    let path = path.canonicalize().unwrap_or(path);
+
+    load_with_options(
+        &state,
+        &app,
+        CUSTOM_MODEL_ID.to_string(),
+        Some(path),
+        force_cpu,
+        None,
+        None,
+    )
+    .await
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequiredModelInfo {
+    pub id: String,
+    pub display_name: String,
+    pub filename: String,
+    pub size_bytes: u64,
+    pub url: String,
+    pub present: bool,
+}
+
+/// List the models the app expects to find on disk, with a `present`
+/// flag the frontend can use to decide whether to show the first-launch
+/// download dialog. The order matches MODEL_REGISTRY (small first, then
+/// large) so the dialog walks them sequentially.
+#[tauri::command]
+pub fn list_required_models(
+    state: State<AppState>,
+    app: AppHandle,
+) -> Result<Vec<RequiredModelInfo>, String> {
+    let dirs = model_dirs(&state, &app)?;
+    let quantization = state.get_settings().quantization;
+    let mut out = Vec::with_capacity(MODEL_REGISTRY.len());
+    for entry in MODEL_REGISTRY {
+        let path = builtin_model_path(&dirs, entry, &quantization);
+        out.push(RequiredModelInfo {
+            id: entry.id.to_string(),
+            display_name: entry.display_name.to_string(),
+            filename: entry.filename.to_string(),
+            size_bytes: entry.size_bytes,
+            url: entry.url.to_string(),
+            present: path.is_file(),
+        });
+    }
+    Ok(out)
+}
+
+/// What `download_model` fetches for `model`: the release asset of a
+/// built-in, or the whisper.cpp repository's file.
+fn download_source(model: &str) -> Result<ModelSource, DownloadError> {
+    match MODEL_REGISTRY.iter().find(|e| e.id == model) {
+        Some(entry) => Ok(ModelSource {
+            filename: entry.filename.to_string(),
+            url: entry.url.to_string(),
+            sha256: Some(entry.sha256.to_string()),
+            size_bytes: Some(entry.size_bytes),
+        }),
+        None => ModelSource::hugging_face(model).ok_or_else(|| DownloadError::UnknownModel {
+            model: model.to_string(),
+        }),
+    }
+}
+
+/// Download a Whisper model into the app's models directory, streaming
+/// the response so we can emit progress events to the frontend in
+/// near-realtime. Built-ins come from the `models-v1` release and are
+/// SHA-256 verified; any other id is fetched as `ggml-{id}.bin` from
+/// the whisper.cpp Hugging Face repository (see `model_download`). The
+/// file lands in a `.part` sibling first and is renamed to its final
+/// name only once complete. `cancel_download` stops it.
+///
+/// An earlier download of the model that stopped short resumes where it
+/// left off, unless the file changed on the server since (see
+/// `model_download`); `get_pending_downloads` lists them.
+///
+/// Events emitted (all carry the model id so the UI can route correctly
+/// when several downloads run sequentially):
+/// - `model:download:progress`  { model, bytesReceived, totalBytes, percent }
+/// - `model:download:complete`  { model, path }
+/// - `model:download:error`     { model, message, error: DownloadError }
+#[tauri::command]
+pub async fn download_model(
+    model: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), DownloadError> {
+    let source = download_source(&model)?;
+    let guard = state.downloads.begin(&model)?;
+
+    let models_dir = get_models_dir(&app).map_err(|message| DownloadError::Disk { message })?;
+    let final_path = models_dir.join(&source.filename);
+    let partial_path = models_dir.join(source.partial_filename());
+    let sidecar_path = models_dir.join(source.sidecar_filename());
+
+    tracing::info!(
+        "Downloading model '{}' from {} -> {}",
+        model,
+        source.url,
+        final_path.display()
+    );
+
+    // Inline async block lets us use `?` and still funnel every error
+    // through the same `model:download:error` emitter.
+    let do_download = async {
+        let client = reqwest::Client::builder()
+            .build()
+            .map_err(|e| DownloadError::Network {
+                message: format!("HTTP client init failed: {}", e),
+            })?;
+
+        // Pick up where an interrupted download of the same file stopped
+        let part_len = tokio::fs::metadata(&partial_path)
+            .await
+            .ok()
+            .map(|m| m.len());
+        let mut resume = model_download::resume_point(
+            PartialMeta::load(&sidecar_path).as_ref(),
+            &source,
+            part_len,
+        );
+        let (mut response, append_from, total_bytes) = loop {
+            let mut request = client.get(&source.url);
+            if let Some(resume) = &resume {
+                request = request
+                    .header(reqwest::header::RANGE, format!("bytes={}-", resume.offset))
+                    .header(reqwest::header::IF_RANGE, &resume.etag);
+            }
+            let response = request.send().await.map_err(|e| DownloadError::Network {
+                message: e.to_string(),
+            })?;
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+            };
+            match model_download::continuation(
+                resume.as_ref(),
+                response.status().as_u16(),
+                header(reqwest::header::CONTENT_RANGE),
+                header(reqwest::header::ETAG),
+                response.content_length(),
+                &source.url,
+            )? {
+                Continuation::Append {
+                    offset,
+                    total_bytes,
+                } => break (response, Some(offset), total_bytes),
+                Continuation::Restart { total_bytes } => break (response, None, total_bytes),
+                Continuation::Refetch => resume = None,
+            }
+        };
+        // Prefer the server's size if the redirected CDN exposes it; fall
+        // back to the registry's size_bytes so the progress bar still
+        // moves predictably even when the server doesn't tell us.
+        let total_bytes = total_bytes.or(source.size_bytes);
+
+        let mut hasher = Sha256::new();
+        let mut downloaded: u64 = 0;
+        let mut file = match append_from {
+            Some(offset) => {
+                tracing::info!("Resuming model '{}' at byte {}", model, offset);
+                if source.sha256.is_some() {
+                    let part = partial_path.clone();
+                    hasher =
+                        tokio::task::spawn_blocking(move || model_download::hash_partial(&part))
+                            .await
+                            .map_err(|e| DownloadError::Disk {
+                                message: format!("hashing task failed: {e}"),
+                            })?
+                            .map_err(|e| DownloadError::disk(&e, "Failed to read the .part"))?;
+                }
+                downloaded = offset;
+                tokio::fs::OpenOptions::new()
+                    .append(true)
+                    .open(&partial_path)
+                    .await
+                    .map_err(|e| DownloadError::disk(&e, "Failed to open temp file"))?
+            }
+            None => {
+                // Any leftover partial from a previous run goes
+                let file = tokio::fs::File::create(&partial_path)
+                    .await
+                    .map_err(|e| DownloadError::disk(&e, "Failed to open temp file"))?;
+                // Remember what this is the start of, if it can resume
+                let etag = response
+                    .headers()
+                    .get(reqwest::header::ETAG)
+                    .and_then(|value| value.to_str().ok());
+                match model_download::strong_etag(etag) {
+                    Some(etag) => PartialMeta {
+                        model: model.clone(),
+                        url: source.url.clone(),
+                        etag: etag.to_string(),
+                        total_bytes,
+                    }
+                    .save(&sidecar_path)
+                    .map_err(|e| DownloadError::disk(&e, "Failed to save the resume data"))?,
+                    None => {
+                        let _ = tokio::fs::remove_file(&sidecar_path).await;
+                    }
+                }
+                file
+            }
+        };
+        let total_bytes = total_bytes.unwrap_or(0);
+        let mut last_pct: u8 = u8::MAX;
+
+        loop {
+            let chunk = tokio::select! {
+                chunk = response.chunk() => chunk.map_err(|e| DownloadError::Network {
+                    message: format!("read failed: {}", e),
+                })?,
+                () = guard.cancelled() => return Err(DownloadError::Cancelled),
+            };
+            let Some(chunk) = chunk else { break };
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| DownloadError::disk(&e, "Failed to write the model"))?;
+            hasher.update(&chunk);
+            downloaded = downloaded.saturating_add(chunk.len() as u64);
+
+            // `checked_div` returns None when total is zero — clippy's
+            // `manual_checked_ops` (Rust 1.95) flags the equivalent
+            // `if x > 0 { a / x } else { 0 }` pattern.
+            let pct = (downloaded.min(total_bytes) * 100)
+                .checked_div(total_bytes)
+                .unwrap_or(0) as u8;
+            // Throttle: only emit when integer percent advances, plus one
+            // last update at end. Avoids flooding the bridge with hundreds
+            // of events for a 547 MB file.
+            if pct != last_pct {
+                last_pct = pct;
+                let _ = app.emit(
+                    "model:download:progress",
+                    serde_json::json!({
+                        "model": model,
+                        "bytesReceived": downloaded,
+                        "totalBytes": total_bytes,
+                        "percent": pct,
+                    }),
+                );
+            }
+        }
+
+        file.flush()
+            .await
+            .map_err(|e| DownloadError::disk(&e, "Failed to flush temp file"))?;
+        drop(file);
+
+        // SHA-256 verification — protects against partial transfers and
+        // bit-flips. Mismatch is a hard error: we'd rather fail loud than
+        // hand a corrupt model to whisper.cpp.
+        if let Some(expected) = &source.sha256 {
+            let actual = format!("{:x}", hasher.finalize());
+            if &actual != expected {
+                return Err(DownloadError::Checksum {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        tokio::fs::rename(&partial_path, &final_path)
+            .await
+            .map_err(|e| DownloadError::disk(&e, "Failed to finalize download"))?;
+        let _ = tokio::fs::remove_file(&sidecar_path).await;
+        Ok(final_path.clone())
+    };
+
+    match do_download.await {
+        Ok(path) => {
+            tracing::info!("Model '{}' downloaded to {}", model, path.display());
+            let _ = app.emit(
+                "model:download:complete",
+                serde_json::json!({ "model": model, "path": path.display().to_string() }),
+            );
+            Ok(())
+        }
+        Err(error) => {
+            tracing::error!("Model '{}' download failed: {}", model, error);
+            // Keep what arrived if the next try can resume from it; never
+            // leave a partial file behind that can't.
+            if error.keeps_partial() && sidecar_path.exists() {
+                tracing::info!("Kept the partial download of '{}' to resume", model);
+            } else {
+                let _ = tokio::fs::remove_file(&partial_path).await;
+                let _ = tokio::fs::remove_file(&sidecar_path).await;
+            }
+            let _ = app.emit(
+                "model:download:error",
+                serde_json::json!({ "model": model, "message": error.to_string(), "error": error }),
+            );
+            Err(error)
+        }
+    }
+}
+
+/// Check the file of `model` in full: header and size, then its SHA-256
+/// against the registry's when it is a built-in the app downloaded
+/// (other models have no known checksum, so only the first part
+/// applies). Hashing a large model takes seconds and runs off the
+/// async runtime.
+///
+/// Emits `model:verify:progress` { model, bytesHashed, totalBytes, percent }.
+#[tauri::command]
+pub async fn verify_model(
+    model: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<ModelVerification, IntegrityError> {
+    let unreadable = |message| IntegrityError::Unreadable { message };
+    let path = resolve_model_path(&state, &app, &model).map_err(unreadable)?;
+    let entry = registry_file(&model_dirs(&state, &app).map_err(unreadable)?, &path);
+    model_integrity::quick_check(&path, entry.map(|e| e.size_bytes))?;
+
+    tracing::info!("Verifying model '{}' at {}", model, path.display());
+    let hash_path = path.clone();
+    let progress_app = app.clone();
+    let progress_model = model.clone();
+    let sha256 = tokio::task::spawn_blocking(move || {
+        let mut last_pct: u8 = u8::MAX;
+        model_integrity::sha256_file(&hash_path, |hashed, total| {
+            let pct = (hashed.min(total) * 100).checked_div(total).unwrap_or(0) as u8;
+            // Throttled like `model:download:progress`
+            if pct != last_pct {
+                last_pct = pct;
+                let _ = progress_app.emit(
+                    "model:verify:progress",
+                    serde_json::json!({
+                        "model": progress_model,
+                        "bytesHashed": hashed,
+                        "totalBytes": total,
+                        "percent": pct,
+                    }),
+                );
+            }
+        })
+    })
+    .await
+    .map_err(|e| unreadable(format!("hashing task failed: {e}")))??;
+
+    let expected_sha256 = entry.map(|e| e.sha256.to_string());
+    if let Err(error) = model_integrity::compare(expected_sha256.as_deref(), &sha256) {
+        tracing::error!("Model '{}' failed verification: {}", model, error);
+        return Err(error);
+    }
+    let size_bytes = std::fs::metadata(&path)
+        .map_err(|e| unreadable(e.to_string()))?
+        .len();
+    Ok(ModelVerification {
+        model,
+        path,
+        size_bytes,
+        sha256,
+        expected_sha256,
+    })
+}
+
+/// Stop the download of `model`; it ends with a `cancelled`
+/// `model:download:error`, and what arrived is kept for a resume when
+/// it can. `false` when it wasn't downloading.
+#[tauri::command]
+pub fn cancel_download(model: String, state: State<'_, AppState>) -> bool {
+    state.downloads.cancel(&model)
+}
+
+/// The interrupted downloads the next `download_model` of their model
+/// resumes, for a "Resume" offer after a restart. Downloads running
+/// now aren't in it.
+#[tauri::command]
+pub fn get_pending_downloads(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<PendingDownload>, String> {
+    let dir = get_models_dir(&app)?;
+    let mut pending = model_download::pending_in(&dir);
+    pending.retain(|download| !state.downloads.is_running(&download.model));
+    Ok(pending)
+}
+
+/// Drop the interrupted download of `model`: its `.part` and what it
+/// takes to resume it. `false` when there was none.
+#[tauri::command]
+pub fn discard_pending_download(
+    model: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<bool, DownloadError> {
+    let source = download_source(&model)?;
+    // Holds off a download starting meanwhile
+    let _guard = state.downloads.begin(&model)?;
+    let dir = get_models_dir(&app).map_err(|message| DownloadError::Disk { message })?;
+    let _ = std::fs::remove_file(dir.join(source.sidecar_filename()));
+    match std::fs::remove_file(dir.join(source.partial_filename())) {
+        Ok(()) => {
+            tracing::info!("Discarded the partial download of '{}'", model);
+            Ok(true)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(DownloadError::disk(&e, "Failed to remove the .part")),
+    }
+}
+
+/// Bring the model file at `source_path` (one the user downloaded by
+/// hand, say) into the app's models directory, where
+/// `get_available_models` finds it: copied, or with `move_source`,
+/// moved. It has to pass `model_integrity::quick_check` first, so a
+/// GGUF file is refused like at load. A file of the same name and
+/// content already there is the import (`duplicate`); naming and
+/// collisions are `crate::model_import`'s.
+///
+/// Emits `model:import:progress` { source, bytesCopied, totalBytes, percent }
+/// while copying, then `model:imported` (`ImportedModel`).
+#[tauri::command]
+pub async fn import_model(
+    source_path: String,
+    move_source: Option<bool>,
+    app: AppHandle,
+) -> Result<ImportedModel, ImportError> {
+    let source = PathBuf::from(&source_path);
+    if !source.is_file() {
+        return Err(ImportError::NotFound { path: source });
+    }
+    model_integrity::quick_check(&source, None).map_err(|error| ImportError::Invalid { error })?;
+    let filename = model_import::import_filename(&source).ok_or_else(|| ImportError::BadName {
+        path: source.clone(),
+    })?;
+    let dir = get_models_dir(&app).map_err(|message| ImportError::Disk { message })?;
+    tracing::info!(
+        "Importing model {} into {} as {}",
+        source.display(),
+        dir.display(),
+        filename
+    );
+
+    let progress_app = app.clone();
+    let (path, duplicate) = tokio::task::spawn_blocking(move || {
+        // A built-in's name only takes the built-in
+        let reserved = |name: &str| {
+            MODEL_REGISTRY
+                .iter()
+                .find(|entry| entry.filename == name)
+                .map(|entry| entry.sha256.to_string())
+        };
+        let target = match model_import::place(&dir, &filename, &source, reserved)? {
+            Placement::Existing(path) => return Ok((path, true)),
+            Placement::New(path) => path,
+        };
+        let mut last_pct: u8 = u8::MAX;
+        let on_progress = |copied: u64, total: u64| {
+            let pct = (copied.min(total) * 100).checked_div(total).unwrap_or(0) as u8;
+            // Throttled like `model:download:progress`
+            if pct != last_pct {
+                last_pct = pct;
+                let _ = progress_app.emit(
+                    "model:import:progress",
+                    serde_json::json!({
+                        "source": source_path,
+                        "bytesCopied": copied,
+                        "totalBytes": total,
+                        "percent": pct,
+                    }),
+                );
+            }
+        };
+        if move_source.unwrap_or(false) {
+            model_import::move_into(&source, &target, on_progress)?;
+        } else {
+            model_import::copy_into(&source, &target, on_progress)?;
+        }
+        Ok((target, false))
+    })
+    .await
+    .map_err(|e| ImportError::Disk {
+        message: format!("import task failed: {e}"),
+    })??;
+
+    let model =
+        model_files::found_at(&path, ModelLocation::AppData).ok_or_else(|| ImportError::Disk {
+            message: format!("Imported model not found at {}", path.display()),
+        })?;
+    tracing::info!(
+        "Model imported as '{}' ({})",
+        model.file.id,
+        if duplicate { "already there" } else { "new" }
+    );
+    let imported = ImportedModel { model, duplicate };
+    let _ = app.emit("model:imported", &imported);
+    Ok(imported)
+}
+
+/// Unified view of every model the app exposes (built-in + user-imported)
+/// returned by `list_all_models`. The frontend mirrors this into its
+/// Pinia `models` slice so both kinds render through the same row UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfoResponse {
+    pub id: String,
+    pub display_name: String,
+    /// `"builtin"` for the registry-defined models (small, large-v3-turbo),
+    /// `"custom"` for user-imported entries.
+    pub kind: String,
+    pub capabilities: crate::whisper::ModelCapabilities,
+    /// Whether the user has marked this model as disabled (excluded
+    /// from the cycle shortcut).
+    pub disabled: bool,
+    /// Transient flag set when a load attempt failed this session.
+    pub broken: bool,
+    /// Custom models include the canonical path; built-ins don't
+    /// (their path is derived from `get_models_dir`).
+    pub path: Option<String>,
+    /// Built-ins include their stable URL on the `models-v1` GitHub
+    /// Release (used by the welcome window's first-launch download).
+    pub url: Option<String>,
+    /// Filename inside `<models_dir>/`. `None` for custom models since
+    /// they're addressed by their absolute `path` instead.
+    pub filename: Option<String>,
+    /// Whether the file is on disk right now. Builds the
+    /// `downloaded` flag in the frontend store. For custom models we
+    /// check the imported path; for built-ins we check
+    /// `<models_dir>/<filename>`.
+    pub present: bool,
+}
+
+/// Return the merged list of built-in + user-imported models with
+/// their capabilities and current disabled/broken state. Called once
+/// on app boot by the frontend, then re-fetched whenever
+/// `settings:updated` fires (so two windows stay in sync).
+#[tauri::command]
+pub fn list_all_models(
+    state: State<AppState>,
+    app: AppHandle,
+) -> Result<Vec<ModelInfoResponse>, String> {
+    let dirs = model_dirs(&state, &app)?;
+    let settings = state.get_settings();
+    let mut out = Vec::with_capacity(MODEL_REGISTRY.len() + settings.user_models.len());
+
+    for entry in MODEL_REGISTRY {
+        let path = builtin_model_path(&dirs, entry, &settings.quantization);
+        let file = path
+            .file_name()
+            .and_then(|name| model_files::parse_filename(&name.to_string_lossy()));
+        let mut capabilities = builtin_capabilities(entry);
+        // A quantized file on disk isn't the variant the registry ships
+        if let Some(quantization) = file.as_ref().and_then(|f| f.quantization.clone()) {
+            capabilities.quant_label = quantization;
+        }
+        out.push(ModelInfoResponse {
+            id: entry.id.to_string(),
+            display_name: entry.display_name.to_string(),
+            kind: "builtin".to_string(),
+            capabilities,
+            disabled: state.is_model_disabled(entry.id),
+            broken: state.is_model_broken(entry.id),
+            path: None,
+            url: Some(entry.url.to_string()),
+            filename: Some(file.map_or_else(|| entry.filename.to_string(), |f| f.filename)),
+            present: path.is_file(),
+        });
+    }
+
+    for um in &settings.user_models {
+        out.push(ModelInfoResponse {
+            id: um.id.clone(),
+            display_name: um.display_name.clone(),
+            kind: "custom".to_string(),
+            capabilities: um.capabilities.clone(),
+            disabled: state.is_model_disabled(&um.id),
+            broken: state.is_model_broken(&um.id),
+            path: Some(um.path.to_string_lossy().to_string()),
+            url: None,
+            filename: None,
+            present: um.path.is_file(),
+        });
+    }
+
+    Ok(out)
+}
+
+/// Run the file-format validator on a candidate path, then layer the
+/// contextual checks (already-imported, inside-managed-dir) on top.
+/// Returns the validation result on success or a structured error
+/// the frontend can switch on by `kind` to render the right modal
+/// state.
+#[tauri::command]
+pub fn validate_custom_model(
+    state: State<AppState>,
+    app: AppHandle,
+    path: String,
+) -> Result<crate::whisper::ValidationResult, crate::whisper::ModelCompatError> {
+    let candidate = PathBuf::from(&path);
+
+    // Refuse files inside our managed `models/` directory. They're
+    // either already part of MODEL_REGISTRY (about to be downloaded)
+    // or some leftover the user shouldn't be importing as a custom
+    // entry. `get_models_dir` returns Err in obscure platform setups
+    // — fall through silently in that case rather than blocking the
+    // import.
+    if let Ok(managed_dir) = get_models_dir(&app) {
+        if let (Ok(canon_candidate), Ok(canon_managed)) =
+            (candidate.canonicalize(), managed_dir.canonicalize())
+        {
+            if canon_candidate.starts_with(&canon_managed) {
+                return Err(crate::whisper::ModelCompatError::InsideManagedDir);
+            }
+        }
+    }
+
+    // Reject duplicates by canonical path. `path.canonicalize()` will
+    // fail for non-existent files, but the validator checks
+    // existence anyway via `metadata`.
+    let canon_candidate = candidate.canonicalize().ok();
+    if let Some(canon) = canon_candidate.as_ref() {
+        let settings = state.get_settings();
+        if let Some(existing) = settings
+            .user_models
+            .iter()
+            .find(|m| m.path.canonicalize().ok().as_ref() == Some(canon))
+        {
+            return Err(crate::whisper::ModelCompatError::AlreadyImported {
+                existing_display_name: existing.display_name.clone(),
+            });
+        }
+    }
+
+    crate::whisper::compat::validate(&candidate)
+}
+
+/// Step 4 errors specific to the add/remove flows. File-format errors
+/// from the validator path use `ModelCompatError`; these wrap them
+/// plus the contextual cases (active-model deletion blocking,
+/// mid-recording, etc.).
+#[derive(Debug, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum AddModelError {
+    /// The candidate file no longer validates — race window between
+    /// the `validate_custom_model` call (when the modal opened) and
+    /// the `add_custom_model` call (when the user hit Save).
+    Compat(crate::whisper::ModelCompatError),
+    /// Display name is empty / whitespace-only after trim.
+    EmptyName,
+    /// settings.json couldn't be written (disk full, permission issue,
+    /// etc.). Surfaces the underlying message so the modal can show
+    /// it; the in-memory AppState mutation is rolled back by virtue
+    /// of the AppState write lock having already been released.
+    PersistFailed { reason: String },
+}
+
+impl From<crate::whisper::ModelCompatError> for AddModelError {
+    fn from(e: crate::whisper::ModelCompatError) -> Self {
+        AddModelError::Compat(e)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum RemoveModelError {
+    /// The user is mid-recording; switching model now would kill the
+    /// in-flight transcription.
+    MidRecording,
+    /// The deleted model is currently active and we can't find an
+    /// alternative (every other model disabled or broken).
+    NoFallback,
+    /// The id doesn't match a user-imported model. Built-ins can't
+    /// be removed via this path.
+    NotFound,
+}
+
+/// Persist a user-imported Whisper model. Re-runs the validator (the
+/// file may have been replaced between the modal opening and the
+/// Save click), then commits the new entry to AppState. Returns the
+/// fully-populated `UserModel` so the frontend can persist
+/// settings.json without an extra round-trip.
+#[tauri::command]
+pub fn add_custom_model(
+    state: State<AppState>,
+    app: AppHandle,
+    name: String,
+    path: String,
+) -> Result<crate::state::UserModel, AddModelError> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err(AddModelError::EmptyName);
+    }
+
+    let candidate = PathBuf::from(&path);
+    let validation = crate::whisper::compat::validate(&candidate)?;
+
+    // Canonicalise so duplicate detection survives `./relative` paths
+    // and symlinks. Falls back to the original path on platforms
+    // where canonicalize fails (rare on the systems we ship to but
+    // worth handling).
+    let canonical = candidate.canonicalize().unwrap_or(candidate);
+
+    let added_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let model = crate::state::UserModel {
+        id: uuid::Uuid::new_v4().to_string(),
+        display_name: trimmed.to_string(),
+        path: canonical,
+        added_at,
+        capabilities: validation.capabilities,
+    };
+
+    state
+        .add_user_model(model.clone())
+        .map_err(|existing_name| {
+            AddModelError::Compat(crate::whisper::ModelCompatError::AlreadyImported {
+                existing_display_name: existing_name,
+            })
+        })?;
+
+    // English-only constraint: pre-populate the per-model language
+    // whitelist with `["auto", "en"]` so the cycle shortcut and the
+    // chip picker can treat non-English languages as "not allowed
+    // for this model" via the existing `model_languages` plumbing.
+    // The user can edit the whitelist later via the General tab.
+    if !model.capabilities.is_multilingual {
+        state.update_settings(|s| {
+            s.model_languages.insert(
+                model.id.clone(),
+                vec![
+                    crate::state::Language::auto(),
+                    crate::state::Language::from_code("en")
+                        .unwrap_or_else(crate::state::Language::auto),
+                ],
+            );
+        });
+    }
+
+    persist_and_broadcast(&state, &app).map_err(|e| AddModelError::PersistFailed { reason: e })?;
+
+    Ok(model)
+}
+
+/// Remove a user-imported model from the registry. Does NOT delete
+/// the file on disk. If the removed model is currently active,
+/// auto-switch to the first available alternative (built-in or
+/// custom, downloaded + not disabled + not broken). Returns the new
+/// active model id, or `None` if the removed model wasn't active.
+#[tauri::command]
+pub fn remove_custom_model(
+    state: State<AppState>,
+    app: AppHandle,
+    id: String,
+) -> Result<Option<String>, RemoveModelError> {
+    // Refuse to mutate while a recording is in flight.
+    if state.get_status() != AppStatus::Idle {
+        return Err(RemoveModelError::MidRecording);
+    }
+
+    // Confirm the id matches a user model (built-ins aren't removable
+    // through this path).
+    if state.find_user_model(&id).is_none() {
+        return Err(RemoveModelError::NotFound);
+    }
+
+    let active_was_target = state.get_settings().model == id;
+    let new_active: Option<String> = if active_was_target {
+        // Find the first available alternative. Order matters — we
+        // walk the same order `list_all_models` exposes (built-ins
+        // first, then user models). Built-ins must be on disk;
+        // disabled/broken excluded; the model being deleted excluded.
+        let dirs = model_dirs(&state, &app).map_err(|_| RemoveModelError::NoFallback)?;
+
+        let settings = state.get_settings();
+        let mut candidate: Option<String> = None;
+
+        for entry in MODEL_REGISTRY {
+            if entry.id == id {
+                continue;
+            }
+            let on_disk = builtin_model_path(&dirs, entry, &settings.quantization).is_file();
+            if !on_disk {
+                continue;
+            }
+            if state.is_model_disabled(entry.id) || state.is_model_broken(entry.id) {
+                continue;
+            }
+            candidate = Some(entry.id.to_string());
+            break;
+        }
+        if candidate.is_none() {
+            for um in &settings.user_models {
+                if um.id == id {
+                    continue;
+                }
+                if !um.path.is_file() {
+                    continue;
+                }
+                if state.is_model_disabled(&um.id) || state.is_model_broken(&um.id) {
+                    continue;
+                }
+                candidate = Some(um.id.clone());
+                break;
+            }
+        }
+
+        let next = candidate.ok_or(RemoveModelError::NoFallback)?;
+        state.update_settings(|s| s.model = next.clone());
+        Some(next)
+    } else {
+        None
+    };
+
+    // remove_user_model also clears the disabled/broken flags for
+    // this id; cf. state.rs.
+    state.remove_user_model(&id);
+
+    persist_and_broadcast(&state, &app).map_err(|_| RemoveModelError::NoFallback)?;
+
+    Ok(new_active)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum DeleteModelError {
+    /// The id resolves to no model file.
+    UnknownModel { message: String },
+    /// Resolved, but there's no file there (never downloaded, or
+    /// already deleted).
+    NotFound { path: PathBuf },
+    /// Shipped in the app's resources: the installer manages it.
+    Bundled { path: PathBuf },
+    /// The loaded model; pass `force` to unload it first.
+    Loaded { model: String },
+    /// `force` would unload the model a recording is using.
+    MidRecording,
+    /// A model load is running.
+    Loading,
+    /// The file couldn't be removed (permissions, or still open on
+    /// Windows).
+    Disk { message: String },
+}
+
+/// `delete_model` result and `model:deleted` payload. Frontend mirror:
+/// `DeletedModel`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedModel {
+    pub model: String,
+    pub path: PathBuf,
+    pub freed_bytes: u64,
+    /// The model was loaded and got unloaded (`force`).
+    pub unloaded: bool,
+}
+
+/// Delete the file of `model`, found the way `load_whisper_model`
+/// finds it. The loaded model is refused unless `force`, which unloads
+/// it first; bundled models always are. A user-imported model is
+/// unregistered too, its file being gone.
+#[tauri::command]
+pub async fn delete_model(
+    model: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<DeletedModel, DeleteModelError> {
+    // No load may pick the file up meanwhile
+    let _ticket = state
+        .model_load
+        .try_begin()
+        .map_err(|_| DeleteModelError::Loading)?;
+    let path = resolve_model_path(&state, &app, &model)
+        .map_err(|message| DeleteModelError::UnknownModel { message })?;
+    let metadata = match std::fs::metadata(&path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Err(DeleteModelError::NotFound { path }),
+    };
+    let dirs = model_dirs(&state, &app).map_err(|message| DeleteModelError::Disk { message })?;
+    if model_files::location_of(&dirs, &path) == Some(ModelLocation::Bundled) {
+        return Err(DeleteModelError::Bundled { path });
+    }
+
+    let loaded = state
+        .whisper
+        .model_info()
+        .is_ok_and(|info| same_file(Path::new(&info.path), &path));
+    if loaded {
+        if !force.unwrap_or(false) {
+            return Err(DeleteModelError::Loaded { model });
+        }
+        if state.get_status() != AppStatus::Idle {
+            return Err(DeleteModelError::MidRecording);
+        }
+        state
+            .whisper
+            .unload_model()
+            .await
+            .map_err(|e| DeleteModelError::Disk {
+                message: e.to_string(),
+            })?;
+    }
+
+    std::fs::remove_file(&path).map_err(|e| DeleteModelError::Disk {
+        message: e.to_string(),
+    })?;
+    tracing::info!(
+        "Deleted model {} ({}, {} bytes)",
+        model,
+        path.display(),
+        metadata.len()
+    );
+    if state.find_user_model(&model).is_some() {
+        state.remove_user_model(&model);
+        persist_and_broadcast(&state, &app)
+            .map_err(|message| DeleteModelError::Disk { message })?;
+    }
+
+    let deleted = DeletedModel {
+        model,
+        path,
+        freed_bytes: metadata.len(),
+        unloaded: loaded,
+    };
+    let _ = app.emit("model:deleted", &deleted);
+    Ok(deleted)
+}
+
+/// Whether `a` and `b` are the same file, through symlinks.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Toggle the disabled flag on a model id. Applies to built-ins and
+/// custom models alike. Disabled models are skipped by the cycle
+/// shortcuts; selecting one via Settings re-enables it implicitly
+/// (the frontend handles the implicit re-enable; this command is
+/// the explicit toggle).
+#[tauri::command]
+pub fn set_model_disabled(
+    state: State<AppState>,
+    app: AppHandle,
+    id: String,
+    disabled: bool,
+) -> Result<(), String> {
+    state.set_model_disabled(&id, disabled);
+    persist_and_broadcast(&state, &app)
+}
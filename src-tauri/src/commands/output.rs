@@ -0,0 +1,348 @@
+//! What happens to a transcript: the text pipeline, the clipboard,
+//! snippets and vocabulary.
+
+use super::prelude::*;
+use super::settings::persist_and_broadcast;
+use crate::output::{Delivery, SystemClipboard};
+use crate::state::VocabularyEntry;
+use crate::text::fillers::FillerMode;
+use crate::text::{PipelineOutput, PipelineSettings, Snippet, VocabSuggestion};
+
+/// Route a finished transcript to the clipboard through the output
+/// coordinator (see `crate::output`).
+pub(super) fn copy_transcript(state: &AppState, app: &AppHandle, text: &str) {
+    let settings = state.get_settings();
+    let delivery = {
+        let mut output = state.output.lock();
+        output.configure(
+            std::time::Duration::from_millis(settings.clipboard_dwell_ms as u64),
+            settings.clipboard_append,
+        );
+        output.submit(text, std::time::Instant::now(), &mut SystemClipboard(app))
+    };
+    report_delivery(state, app, delivery);
+}
+
+/// Emit `output:copied` / `output:superseded` for a delivery, and
+/// schedule the deferred write if a transcript is waiting on the dwell.
+fn report_delivery(state: &AppState, app: &AppHandle, delivery: Delivery) {
+    for text in &delivery.superseded {
+        tracing::info!("Un-pasted transcript replaced on the clipboard");
+        let _ = app.emit("output:superseded", serde_json::json!({ "text": text }));
+    }
+    if let Some(text) = &delivery.written {
+        let _ = app.emit("output:copied", serde_json::json!({ "text": text }));
+    }
+    if let Some(at) = delivery.retry_at {
+        let task_state = state.clone();
+        let app = app.clone();
+        state.tasks.spawn("clipboard-dwell", async move {
+            tokio::time::sleep_until(at.into()).await;
+            let delivery = task_state
+                .output
+                .lock()
+                .flush(std::time::Instant::now(), &mut SystemClipboard(&app));
+            report_delivery(&task_state, &app, delivery);
+        });
+    }
+}
+
+/// Text stages applied to a finished transcript before it's emitted.
+/// Currently: snippet expansion.
+pub(super) fn post_process_transcript(
+    app: &AppHandle,
+    state: &AppState,
+    text: &str,
+    verbatim: bool,
+) -> String {
+    let mut settings = pipeline_settings(&state.get_settings());
+    if verbatim {
+        settings.filler_mode = FillerMode::Keep;
+    }
+    crate::text::pipeline::run(text, &settings, &template_context(app, &settings))
+}
+
+/// The text pipeline's view of the settings.
+fn pipeline_settings(settings: &Settings) -> PipelineSettings {
+    let language = settings.language.to_whisper_code().map(String::from);
+    // On auto-detect every language's extra words apply
+    let extra_fillers = match &language {
+        Some(code) => settings.filler_words.get(code).cloned().unwrap_or_default(),
+        None => settings.filler_words.values().flatten().cloned().collect(),
+    };
+    PipelineSettings {
+        strip_annotations: settings.suppress_non_speech,
+        filler_mode: settings.filler_mode,
+        language,
+        extra_fillers,
+        snippets: settings.snippets.clone(),
+    }
+}
+
+/// Date, time and (only when a snippet asks for it) clipboard for the
+/// snippet templates.
+fn template_context(app: &AppHandle, settings: &PipelineSettings) -> crate::text::TemplateContext {
+    let now = chrono::Local::now();
+    let clipboard = if settings
+        .snippets
+        .iter()
+        .any(|s| crate::text::snippets::uses_clipboard(&s.body))
+    {
+        use tauri_plugin_clipboard_manager::ClipboardExt;
+        app.clipboard().read_text().unwrap_or_default()
+    } else {
+        String::new()
+    };
+    crate::text::TemplateContext {
+        date: now.format("%Y-%m-%d").to_string(),
+        time: now.format("%H:%M").to_string(),
+        clipboard,
+    }
+}
+
+/// Run the text pipeline on `input` with the current settings, or with
+/// `overrides` in their place, and report what each stage did. Nothing
+/// is stored, copied or counted: this only previews settings.
+#[tauri::command]
+pub fn preview_text_pipeline(
+    input: String,
+    overrides: Option<PipelineSettings>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> PipelineOutput {
+    let settings = overrides.unwrap_or_else(|| pipeline_settings(&state.get_settings()));
+    crate::text::pipeline::preview(&input, &settings, &template_context(&app, &settings))
+}
+
+/// Shared body of `learn_from_clipboard` and its shortcut: diff the
+/// clipboard against the latest transcript and emit the candidate
+/// terms as `vocabulary:suggestions`. Terms already in the vocabulary
+/// are filtered out; an empty list is not emitted.
+pub(super) fn learn_vocabulary(
+    app: &AppHandle,
+    state: &AppState,
+) -> Result<Vec<VocabSuggestion>, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let settings = state.get_settings();
+    let last = settings
+        .history
+        .first()
+        .ok_or_else(|| "No transcription to compare against".to_string())?;
+    let clipboard = app
+        .clipboard()
+        .read_text()
+        .map_err(|e| format!("Failed to read clipboard: {}", e))?;
+
+    let suggestions: Vec<VocabSuggestion> =
+        crate::text::extract_vocabulary_candidates(&last.text, &clipboard)
+            .into_iter()
+            .filter(|s| !settings.vocabulary.iter().any(|v| v.term == s.term))
+            .collect();
+    tracing::info!("Vocabulary learning: {} suggestion(s)", suggestions.len());
+
+    if !suggestions.is_empty() {
+        app.emit("vocabulary:suggestions", &suggestions)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(suggestions)
+}
+
+/// Learn from a hand-corrected transcript: the user fixes the last
+/// transcription in their editor, copies it, and calls this. Returns
+/// the suggestions (also emitted as `vocabulary:suggestions`); nothing
+/// is persisted until `accept_vocabulary_suggestions`.
+#[tauri::command]
+pub fn learn_from_clipboard(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Vec<VocabSuggestion>, String> {
+    learn_vocabulary(&app, &state)
+}
+
+/// Add the suggestions the user accepted to the custom vocabulary. A
+/// term that's already there just gains the new misrecognition.
+#[tauri::command]
+pub fn accept_vocabulary_suggestions(
+    suggestions: Vec<VocabSuggestion>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| {
+        for suggestion in suggestions {
+            match s.vocabulary.iter_mut().find(|v| v.term == suggestion.term) {
+                Some(entry) => {
+                    if !entry.heard.contains(&suggestion.heard) {
+                        entry.heard.push(suggestion.heard);
+                    }
+                }
+                None => s.vocabulary.push(VocabularyEntry {
+                    term: suggestion.term,
+                    heard: vec![suggestion.heard],
+                }),
+            }
+        }
+    });
+    persist_and_broadcast(&state, &app)
+}
+
+/// Payload of `add_snippet` / `update_snippet`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetInput {
+    pub name: String,
+    pub trigger: String,
+    pub body: String,
+}
+
+/// Reject empty triggers and triggers that collide (after
+/// normalisation) with another snippet's.
+fn validate_snippet(
+    input: &SnippetInput,
+    others: &[Snippet],
+    own_id: Option<&str>,
+) -> Result<(), String> {
+    let words = crate::text::snippets::trigger_words(&input.trigger);
+    if words.is_empty() {
+        return Err("Snippet trigger must contain at least one word".to_string());
+    }
+    let clash = others.iter().find(|s| {
+        Some(s.id.as_str()) != own_id && crate::text::snippets::trigger_words(&s.trigger) == words
+    });
+    if let Some(other) = clash {
+        return Err(format!("Trigger already used by snippet '{}'", other.name));
+    }
+    Ok(())
+}
+
+/// Create a snippet. Returns it with its freshly-assigned id.
+#[tauri::command]
+pub fn add_snippet(
+    snippet: SnippetInput,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Snippet, String> {
+    validate_snippet(&snippet, &state.get_settings().snippets, None)?;
+    let created = Snippet {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: snippet.name,
+        trigger: snippet.trigger,
+        body: snippet.body,
+    };
+    state.update_settings(|s| s.snippets.push(created.clone()));
+    persist_and_broadcast(&state, &app)?;
+    Ok(created)
+}
+
+/// Replace the name, trigger and body of an existing snippet.
+#[tauri::command]
+pub fn update_snippet(
+    id: String,
+    snippet: SnippetInput,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let settings = state.get_settings();
+    if !settings.snippets.iter().any(|s| s.id == id) {
+        return Err(format!("Unknown snippet: {}", id));
+    }
+    validate_snippet(&snippet, &settings.snippets, Some(&id))?;
+    state.update_settings(|s| {
+        if let Some(existing) = s.snippets.iter_mut().find(|s| s.id == id) {
+            existing.name = snippet.name;
+            existing.trigger = snippet.trigger;
+            existing.body = snippet.body;
+        }
+    });
+    persist_and_broadcast(&state, &app)
+}
+
+/// Delete a snippet. Unknown ids are a no-op.
+#[tauri::command]
+pub fn delete_snippet(
+    id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.snippets.retain(|snippet| snippet.id != id));
+    persist_and_broadcast(&state, &app)
+}
+
+/// Toggle the auto-copy-to-clipboard behaviour. v0.1.7 had this
+/// living in JS; centralising it here keeps the Settings struct as
+/// the sole source of truth.
+#[tauri::command]
+pub fn set_auto_copy(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.auto_copy = enabled);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Set the minimum time between two clipboard writes (0 disables it).
+#[tauri::command]
+pub fn set_clipboard_dwell_ms(
+    ms: u32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if ms > 10_000 {
+        return Err(format!(
+            "Clipboard dwell must be at most 10000 ms (got {ms})"
+        ));
+    }
+    state.update_settings(|s| s.clipboard_dwell_ms = ms);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Toggle append mode: transcripts arriving within the dwell time are
+/// joined into one clipboard write instead of waiting their turn.
+#[tauri::command]
+pub fn set_clipboard_append(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.clipboard_append = enabled);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Keep, remove or mark filler words in dictation.
+#[tauri::command]
+pub fn set_filler_mode(
+    mode: FillerMode,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.filler_mode = mode);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Replace the extra filler words of `language` (a language code). An
+/// empty list removes the entry.
+#[tauri::command]
+pub fn set_filler_words(
+    language: String,
+    words: Vec<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let words: Vec<String> = words
+        .iter()
+        .map(|w| w.trim().to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.iter().any(|w| w.contains(char::is_whitespace)) {
+        return Err("Filler words must be single words".to_string());
+    }
+    state.update_settings(|s| {
+        if words.is_empty() {
+            s.filler_words.remove(&language);
+        } else {
+            s.filler_words.insert(language, words);
+        }
+    });
+    persist_and_broadcast(&state, &app)
+}
@@ -0,0 +1,73 @@
+//! The microphone permission, and the pre-roll that needs it.
+
+use super::prelude::*;
+use super::settings::persist_and_broadcast;
+use crate::audio::{MAX_PRE_ROLL_MS, MIN_PRE_ROLL_MS};
+use crate::state::Permissions;
+
+#[tauri::command]
+pub fn check_permissions(state: State<'_, AppState>) -> Permissions {
+    let microphone = check_microphone_permission();
+
+    let perms = Permissions { microphone };
+    state.set_permissions(perms.clone());
+    apply_pre_roll(&state);
+    perms
+}
+
+/// Check if microphone permission is granted
+fn check_microphone_permission() -> bool {
+    crate::platform::is_microphone_authorized()
+}
+
+/// Request microphone permission from the system
+/// On macOS, this triggers the native permission dialog
+/// Returns true if permission was granted
+#[tauri::command]
+pub async fn request_microphone_permission(state: State<'_, AppState>) -> Result<bool, String> {
+    tracing::info!("Requesting microphone permission");
+
+    // Run in blocking task since it waits for user response
+    let granted = tokio::task::spawn_blocking(crate::platform::request_microphone_permission)
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?;
+
+    // Update permissions state
+    if granted {
+        state.set_permissions(Permissions { microphone: true });
+        apply_pre_roll(&state);
+    }
+
+    tracing::info!("Microphone permission granted: {}", granted);
+    Ok(granted)
+}
+
+/// Hold the microphone open for `Settings.pre_roll_ms`, or close it:
+/// only while its permission is granted.
+fn apply_pre_roll(state: &AppState) {
+    let ms = if state.get_permissions().microphone {
+        state.get_settings().pre_roll_ms
+    } else {
+        0
+    };
+    if let Err(e) = state.audio_capture.set_pre_roll(ms) {
+        tracing::warn!("Pre-roll unavailable: {}", e);
+    }
+}
+
+/// Keep `ms` of audio from before each recording, so the words said
+/// just before the shortcut aren't lost; 0 turns it off. Anything else
+/// must be within `MIN_PRE_ROLL_MS..=MAX_PRE_ROLL_MS`. The microphone
+/// then stays open between recordings, which the usage log shows.
+#[tauri::command]
+pub fn set_pre_roll(ms: u32, state: State<'_, AppState>, app: AppHandle) -> Result<(), String> {
+    if ms != 0 && !(MIN_PRE_ROLL_MS..=MAX_PRE_ROLL_MS).contains(&ms) {
+        return Err(format!(
+            "Pre-roll must be 0 or between {MIN_PRE_ROLL_MS} and {MAX_PRE_ROLL_MS} ms (got {ms})"
+        ));
+    }
+    tracing::info!("Setting pre-roll: {} ms", ms);
+    state.update_settings(|s| s.pre_roll_ms = ms);
+    apply_pre_roll(&state);
+    persist_and_broadcast(&state, &app)
+}
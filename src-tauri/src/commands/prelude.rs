@@ -0,0 +1,8 @@
+//! Imports most command modules share; each one starts with
+//! `use super::prelude::*;`.
+
+pub(super) use crate::state::{AppState, AppStatus, Settings};
+pub(super) use serde::{Deserialize, Serialize};
+pub(super) use std::path::{Path, PathBuf};
+pub(super) use std::sync::Arc;
+pub(super) use tauri::{AppHandle, Emitter, Manager, State};
@@ -0,0 +1,88 @@
+//! HID push-to-talk (foot pedals) — see `crate::ptt`.
+
+use super::prelude::*;
+use super::settings::persist_and_broadcast;
+use crate::ptt::{HidBinding, HidDeviceInfo, PttError, PttEvent};
+
+/// Start (or restart) the reader for `binding`, forwarding pedal edges
+/// as `ptt:pressed` / `ptt:released` and plug state as `ptt:device`.
+pub(crate) fn start_ptt_reader(app: &AppHandle, binding: HidBinding) {
+    let state = app.state::<AppState>();
+    let app = app.clone();
+    state.ptt.start(&state.tasks, binding, move |event| {
+        let result = match event {
+            PttEvent::Pressed => app.emit("ptt:pressed", ()),
+            PttEvent::Released => app.emit("ptt:released", ()),
+            PttEvent::Connected => app.emit("ptt:device", serde_json::json!({ "connected": true })),
+            PttEvent::Disconnected => {
+                app.emit("ptt:device", serde_json::json!({ "connected": false }))
+            }
+        };
+        if let Err(e) = result {
+            tracing::error!("Failed to emit PTT event: {}", e);
+        }
+    });
+}
+
+/// Attached HID devices that could serve as a PTT pedal (keyboards and
+/// mice are filtered out).
+#[tauri::command]
+pub fn list_hid_devices() -> Result<Vec<HidDeviceInfo>, PttError> {
+    crate::ptt::list_devices()
+}
+
+/// Wait for the user to press the pedal and report which device it
+/// was. The returned device is the only one `bind_ptt_device` accepts.
+#[tauri::command]
+pub async fn start_ptt_binding(
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<HidDeviceInfo, PttError> {
+    // The bound device is opened by the reader too; pause it so the
+    // press isn't swallowed there.
+    state.ptt.stop();
+    let captured =
+        tokio::task::spawn_blocking(|| crate::ptt::capture_binding(crate::ptt::BINDING_TIMEOUT))
+            .await
+            .map_err(|e| PttError::OpenFailed {
+                reason: format!("Task join error: {}", e),
+            })
+            .and_then(|r| r);
+    if let Ok(info) = &captured {
+        state.ptt.confirm(info.binding());
+        crate::render::invalidate(&app);
+    }
+    // Resume the existing binding until the new one is confirmed.
+    if let Some(binding) = state.get_settings().ptt_device {
+        start_ptt_reader(&app, binding);
+    }
+    captured
+}
+
+/// Bind the device confirmed by `start_ptt_binding` as push-to-talk.
+#[tauri::command]
+pub fn bind_ptt_device(
+    vendor_id: u16,
+    product_id: u16,
+    usage: u16,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), PttError> {
+    let binding = state
+        .ptt
+        .take_confirmed(vendor_id, product_id, usage)
+        .ok_or(PttError::NotConfirmed)?;
+    crate::render::invalidate(&app);
+    tracing::info!("Binding PTT device {:04x}:{:04x}", vendor_id, product_id);
+    state.update_settings(|s| s.ptt_device = Some(binding));
+    start_ptt_reader(&app, binding);
+    persist_and_broadcast(&state, &app).map_err(|reason| PttError::PersistFailed { reason })
+}
+
+/// Remove the PTT device binding and stop its reader.
+#[tauri::command]
+pub fn unbind_ptt_device(state: State<'_, AppState>, app: AppHandle) -> Result<(), String> {
+    state.ptt.stop();
+    state.update_settings(|s| s.ptt_device = None);
+    persist_and_broadcast(&state, &app)
+}
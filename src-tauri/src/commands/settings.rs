@@ -0,0 +1,779 @@
+//! Persisted-state plumbing and the settings setters. The single
+//! source of truth lives in AppState; every mutator routes through
+//! `persist_and_broadcast` so the disk follows memory within one
+//! debounce interval and every other window picks up the change via
+//! one event.
+
+use super::listen::emit_evictions;
+use super::models::{deterministic_mode, user_decode_override, warn_if_unsuitable};
+use super::output::learn_vocabulary;
+use super::prelude::*;
+use crate::audio::{
+    VadBackend, VadBenchmark, MAX_CAPTURE_SPILL_MB, MAX_MIN_SPEECH_MS, MAX_NOISE_FACTOR,
+    MAX_VAD_THRESHOLD, MIN_CAPTURE_SPILL_MB, MIN_NOISE_FACTOR, MIN_SILENCE_TIMEOUT_MS,
+    MIN_VAD_THRESHOLD, SILERO_MODEL_FILE,
+};
+use crate::state::Language;
+use crate::whisper::decode::{
+    AdvancedDecoding, AdvancedDecodingError, DecodeOverride, DecodingOptions, MAX_CANDIDATES,
+};
+use crate::whisper::ENGLISH_ONLY_TRANSLATE_ERROR;
+
+/// Set the memory for recordings kept after their transcription
+/// (`Settings.retained_audio_mb`). Recordings that no longer fit are
+/// dropped right away, each with a `memory:evicted`; 0 keeps none, so
+/// `retranscribe_last` has nothing to work with.
+#[tauri::command]
+pub fn set_retained_audio_mb(
+    mb: u32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    tracing::info!("Setting retained audio budget: {} MB", mb);
+    state.update_settings(|s| s.retained_audio_mb = mb);
+    let evicted = state.retained_audio.lock().set_budget(mb);
+    emit_evictions(&app, evicted);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Bound the memory a long capture takes (`Settings.capture_spill_mb`):
+/// past `mb` of audio it moves to a temp file, read back at the end. 0
+/// keeps it all in RAM; anything else must be within
+/// `MIN_CAPTURE_SPILL_MB..=MAX_CAPTURE_SPILL_MB`. Applies to a running
+/// capture too.
+#[tauri::command]
+pub fn set_capture_spill_mb(
+    mb: u32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if mb != 0 && !(MIN_CAPTURE_SPILL_MB..=MAX_CAPTURE_SPILL_MB).contains(&mb) {
+        return Err(format!(
+            "Capture spill must be 0 or between {MIN_CAPTURE_SPILL_MB} and {MAX_CAPTURE_SPILL_MB} MB (got {mb})"
+        ));
+    }
+    tracing::info!("Setting capture spill threshold: {} MB", mb);
+    state.update_settings(|s| s.capture_spill_mb = mb);
+    state
+        .audio_capture
+        .set_spill(crate::paths::get().cache_dir(), mb);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Privacy mode: never write transcripts to disk, which also turns off
+/// session crash recovery.
+#[tauri::command]
+pub fn set_privacy_mode(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.privacy_mode = enabled);
+    if enabled {
+        // Stop journaling a session that's already running
+        state.journal.lock().take();
+    }
+    persist_and_broadcast(&state, &app)
+}
+
+/// Save every recording as a WAV under `recordings/` in the data
+/// directory, for bug reports. Privacy mode overrides it.
+#[tauri::command]
+pub fn set_debug_save_recordings(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    tracing::info!("Saving recordings: {}", enabled);
+    state.update_settings(|s| s.debug_save_recordings = enabled);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Schedule the current Settings for `settings.json` (written within
+/// `SETTINGS_FLUSH_INTERVAL`, see `crate::debounce`) and emit
+/// `settings:changed`. Called by every mutator command after the
+/// in-memory mutation. The two operations are paired here (rather
+/// than at each call site) so a future setter can't forget the
+/// broadcast and silently leak desync between windows.
+pub(super) fn persist_and_broadcast(state: &AppState, app: &AppHandle) -> Result<(), String> {
+    state.settings_writer.mark_dirty();
+    if let Err(e) = app.emit("settings:changed", ()) {
+        tracing::warn!("settings:changed broadcast failed: {e}");
+    }
+    // The overlay shows the model and language
+    crate::render::invalidate(app);
+    Ok(())
+}
+
+/// Read the full Settings out of AppState. Frontend windows call
+/// this on boot (via `useSettingsSync`) and on every
+/// `settings:changed` event to refresh their local Pinia cache.
+#[tauri::command]
+pub fn get_settings(state: State<'_, AppState>) -> Settings {
+    state.get_settings()
+}
+
+/// Write pending settings changes now rather than at the next
+/// debounce tick.
+#[tauri::command]
+pub fn flush_settings(state: State<'_, AppState>) -> Result<(), String> {
+    state.settings_writer.flush().map(|_| ())
+}
+
+#[tauri::command]
+pub fn set_language(
+    lang: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    tracing::info!("Setting language: {}", lang);
+    // Validate against the canonical Whisper language list. Unknown codes
+    // collapse to auto-detect rather than crashing or silently selecting the
+    // wrong language.
+    let language = Language::from_code(&lang).unwrap_or_else(Language::auto);
+    let whisper_code = language.to_whisper_code().map(String::from);
+    state.update_settings(|s| {
+        s.language = language;
+    });
+
+    // Propagate the selection to the whisper engine. Without this, the engine
+    // keeps its default (`None` = auto-detect) regardless of the UI choice.
+    state.whisper.set_language(whisper_code.clone());
+    tracing::info!(
+        "Whisper language set to: {}",
+        whisper_code.as_deref().unwrap_or("auto-detect")
+    );
+    warn_if_unsuitable(&state, &app);
+
+    persist_and_broadcast(&state, &app)
+}
+
+/// Re-register every shortcut declared in settings (main + language toggle + model toggle).
+/// Always replaces the whole set atomically: any change to one shortcut routes through here so
+/// that we never end up with a stale registration referencing the wrong key combination.
+pub fn register_all_shortcuts(app: &AppHandle, state: &AppState) -> Result<(), String> {
+    use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+    let settings = state.get_settings();
+    let shortcut_manager = app.global_shortcut();
+
+    if let Err(e) = shortcut_manager.unregister_all() {
+        tracing::warn!("Failed to unregister existing shortcuts: {}", e);
+    }
+
+    if !settings.shortcut.is_empty() {
+        let main_shortcut: Shortcut = settings
+            .shortcut
+            .parse()
+            .map_err(|e| format!("Invalid main shortcut format: {}", e))?;
+
+        shortcut_manager
+            .on_shortcut(main_shortcut, move |app, _shortcut, event| {
+                if event.state == ShortcutState::Pressed {
+                    tracing::info!("Main shortcut triggered");
+                    if let Err(e) = app.emit("shortcut:triggered", ()) {
+                        tracing::error!("Failed to emit shortcut event: {}", e);
+                    }
+                }
+            })
+            .map_err(|e| {
+                format!(
+                    "Failed to register main shortcut '{}': {}. It may already be used by another application.",
+                    settings.shortcut, e
+                )
+            })?;
+        tracing::info!("Main shortcut registered: {}", settings.shortcut);
+    }
+
+    if !settings.language_toggle_shortcut.is_empty() {
+        let lang_shortcut: Shortcut = settings
+            .language_toggle_shortcut
+            .parse()
+            .map_err(|e| format!("Invalid language toggle shortcut format: {}", e))?;
+
+        shortcut_manager
+            .on_shortcut(lang_shortcut, move |app, _shortcut, event| {
+                if event.state == ShortcutState::Pressed {
+                    tracing::info!("Language toggle shortcut triggered");
+                    if let Err(e) = app.emit("shortcut:toggle-language", ()) {
+                        tracing::error!("Failed to emit language toggle event: {}", e);
+                    }
+                }
+            })
+            .map_err(|e| {
+                format!(
+                    "Failed to register language toggle shortcut '{}': {}",
+                    settings.language_toggle_shortcut, e
+                )
+            })?;
+        tracing::info!(
+            "Language toggle shortcut registered: {}",
+            settings.language_toggle_shortcut
+        );
+    }
+
+    if !settings.model_toggle_shortcut.is_empty() {
+        let model_shortcut: Shortcut = settings
+            .model_toggle_shortcut
+            .parse()
+            .map_err(|e| format!("Invalid model toggle shortcut format: {}", e))?;
+
+        shortcut_manager
+            .on_shortcut(model_shortcut, move |app, _shortcut, event| {
+                if event.state == ShortcutState::Pressed {
+                    tracing::info!("Model toggle shortcut triggered");
+                    if let Err(e) = app.emit("shortcut:toggle-model", ()) {
+                        tracing::error!("Failed to emit model toggle event: {}", e);
+                    }
+                }
+            })
+            .map_err(|e| {
+                format!(
+                    "Failed to register model toggle shortcut '{}': {}",
+                    settings.model_toggle_shortcut, e
+                )
+            })?;
+        tracing::info!(
+            "Model toggle shortcut registered: {}",
+            settings.model_toggle_shortcut
+        );
+    }
+
+    if !settings.learn_vocabulary_shortcut.is_empty() {
+        let learn_shortcut: Shortcut = settings
+            .learn_vocabulary_shortcut
+            .parse()
+            .map_err(|e| format!("Invalid learn vocabulary shortcut format: {}", e))?;
+
+        shortcut_manager
+            .on_shortcut(learn_shortcut, move |app, _shortcut, event| {
+                if event.state == ShortcutState::Pressed {
+                    tracing::info!("Learn vocabulary shortcut triggered");
+                    let state = app.state::<AppState>();
+                    if let Err(e) = learn_vocabulary(app, &state) {
+                        tracing::warn!("Vocabulary learning failed: {}", e);
+                    }
+                }
+            })
+            .map_err(|e| {
+                format!(
+                    "Failed to register learn vocabulary shortcut '{}': {}",
+                    settings.learn_vocabulary_shortcut, e
+                )
+            })?;
+        tracing::info!(
+            "Learn vocabulary shortcut registered: {}",
+            settings.learn_vocabulary_shortcut
+        );
+    }
+
+    Ok(())
+}
+
+/// Update the main listen shortcut. Persists the new value and re-registers every shortcut.
+#[tauri::command]
+pub fn set_shortcut(
+    shortcut: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    tracing::info!("Setting main shortcut: {}", shortcut);
+    state.update_settings(|s| {
+        s.shortcut = shortcut.clone();
+    });
+    register_all_shortcuts(&app, &state)?;
+    persist_and_broadcast(&state, &app)
+}
+
+/// Update the language-cycle shortcut (empty string clears it).
+#[tauri::command]
+pub fn set_language_toggle_shortcut(
+    shortcut: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    tracing::info!("Setting language toggle shortcut: {}", shortcut);
+    state.update_settings(|s| {
+        s.language_toggle_shortcut = shortcut.clone();
+    });
+    register_all_shortcuts(&app, &state)?;
+    persist_and_broadcast(&state, &app)
+}
+
+/// Update the model-cycle shortcut (empty string clears it).
+#[tauri::command]
+pub fn set_model_toggle_shortcut(
+    shortcut: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    tracing::info!("Setting model toggle shortcut: {}", shortcut);
+    state.update_settings(|s| {
+        s.model_toggle_shortcut = shortcut.clone();
+    });
+    register_all_shortcuts(&app, &state)?;
+    persist_and_broadcast(&state, &app)
+}
+
+/// Update the learn-from-clipboard shortcut (empty string clears it).
+#[tauri::command]
+pub fn set_learn_vocabulary_shortcut(
+    shortcut: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    tracing::info!("Setting learn vocabulary shortcut: {}", shortcut);
+    state.update_settings(|s| {
+        s.learn_vocabulary_shortcut = shortcut.clone();
+    });
+    register_all_shortcuts(&app, &state)?;
+    persist_and_broadcast(&state, &app)
+}
+
+/// Update the favorite languages cycled by the language shortcut.
+/// Codes that don't match a known [`Language`] are silently dropped.
+#[tauri::command]
+pub fn set_favorite_languages(
+    languages: Vec<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let parsed: Vec<Language> = languages
+        .iter()
+        .filter_map(|code| Language::from_code(code))
+        .collect();
+    tracing::info!(
+        "Setting favorite languages: {:?}",
+        parsed.iter().map(|l| l.to_code()).collect::<Vec<_>>()
+    );
+    state.update_settings(|s| {
+        s.favorite_languages = parsed;
+    });
+    persist_and_broadcast(&state, &app)
+}
+
+/// Replace the whitelist of languages enabled for a single model.
+/// Useful for fine-tuned per-language models (e.g. a Latvian-only model).
+#[tauri::command]
+pub fn set_model_languages(
+    model: String,
+    languages: Vec<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let parsed: Vec<Language> = languages
+        .iter()
+        .filter_map(|code| Language::from_code(code))
+        .collect();
+    tracing::info!(
+        "Setting languages for model '{}': {:?}",
+        model,
+        parsed.iter().map(|l| l.to_code()).collect::<Vec<_>>()
+    );
+    state.update_settings(|s| {
+        s.model_languages.insert(model, parsed);
+    });
+    persist_and_broadcast(&state, &app)
+}
+
+/// Update the language-cycle behaviour. Two literal values are accepted:
+/// `"model-first"` (the v0.1.6 default — model is sticky, cycle filtered
+/// to favourites the current model supports) and `"language-first"`
+/// (cycle through every favourite, auto-switching to the most-capable
+/// compatible model when needed). Any other input is rejected so a
+/// hand-edited settings.json with garbage doesn't reach the listener.
+#[tauri::command]
+pub fn set_language_cycle_mode(
+    mode: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if mode != "model-first" && mode != "language-first" {
+        return Err(format!(
+            "Invalid language cycle mode: {} (expected 'model-first' or 'language-first')",
+            mode
+        ));
+    }
+    tracing::info!("Language cycle mode set to: {}", mode);
+    state.update_settings(|s| {
+        s.language_cycle_mode = mode;
+    });
+    persist_and_broadcast(&state, &app)
+}
+
+/// Choose the global decoding strategy (`None` restores the defaults,
+/// including the built-in beam search for CJK languages).
+#[tauri::command]
+pub fn set_decoding_options(
+    options: Option<DecodingOptions>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    match options {
+        Some(DecodingOptions::Greedy { best_of }) if best_of == 0 || best_of > MAX_CANDIDATES => {
+            return Err(format!("bestOf must be between 1 and {}", MAX_CANDIDATES));
+        }
+        Some(DecodingOptions::BeamSearch { beam_size, .. })
+            if beam_size == 0 || beam_size > MAX_CANDIDATES =>
+        {
+            return Err(format!("beamSize must be between 1 and {}", MAX_CANDIDATES));
+        }
+        _ => {}
+    }
+    tracing::info!("Setting decoding options: {:?}", options);
+    state.update_settings(|s| s.decoding_strategy = options);
+    state
+        .whisper
+        .set_decode_advanced(user_decode_override(&state.get_settings()));
+    persist_and_broadcast(&state, &app)
+}
+
+/// Set temperature and fallback thresholds. Out-of-range values are
+/// rejected with a structured error rather than clamped.
+#[tauri::command]
+pub fn set_advanced_decoding(
+    advanced: AdvancedDecoding,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), AdvancedDecodingError> {
+    advanced.validate()?;
+    tracing::info!("Setting advanced decoding: {:?}", advanced);
+    state.update_settings(|s| s.advanced_decoding = advanced);
+    state.whisper.set_advanced_decoding(advanced);
+    state
+        .whisper
+        .set_decode_advanced(user_decode_override(&state.get_settings()));
+    persist_and_broadcast(&state, &app)
+        .map_err(|reason| AdvancedDecodingError::PersistFailed { reason })
+}
+
+/// Set how much VAD-detected speech (ms) a recording needs before it is
+/// transcribed. 0 disables the check.
+#[tauri::command]
+pub fn set_min_speech_ms(
+    ms: u32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    check_min_speech_ms(ms)?;
+    state.update_settings(|s| s.min_speech_ms = ms);
+    persist_and_broadcast(&state, &app)
+}
+
+fn check_min_speech_ms(ms: u32) -> Result<(), String> {
+    if ms > MAX_MIN_SPEECH_MS {
+        return Err(format!(
+            "Minimum speech must be at most {MAX_MIN_SPEECH_MS} ms (got {ms})"
+        ));
+    }
+    Ok(())
+}
+
+/// Tune the VAD: the RMS `threshold` over which a chunk is speech
+/// (`MIN_VAD_THRESHOLD` to `MAX_VAD_THRESHOLD`), the silence
+/// (`silence_timeout_ms`, at least `MIN_SILENCE_TIMEOUT_MS`) that ends
+/// speech, and the speech a recording needs to be transcribed (see
+/// `set_min_speech_ms`). Values out of range are refused, and nothing
+/// is changed. The VAD takes them from its next chunk, a running
+/// recording included.
+#[tauri::command]
+pub fn set_vad_config(
+    threshold: f32,
+    silence_timeout_ms: u32,
+    min_speech_ms: u32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if !(MIN_VAD_THRESHOLD..=MAX_VAD_THRESHOLD).contains(&threshold) {
+        return Err(format!(
+            "Speech threshold must be between {MIN_VAD_THRESHOLD} and {MAX_VAD_THRESHOLD} (got {threshold})"
+        ));
+    }
+    if silence_timeout_ms < MIN_SILENCE_TIMEOUT_MS {
+        return Err(format!(
+            "Silence timeout must be at least {MIN_SILENCE_TIMEOUT_MS} ms (got {silence_timeout_ms})"
+        ));
+    }
+    check_min_speech_ms(min_speech_ms)?;
+    tracing::info!(
+        "VAD: threshold {}, silence timeout {} ms, minimum speech {} ms",
+        threshold,
+        silence_timeout_ms,
+        min_speech_ms
+    );
+    {
+        let mut vad = state.vad.write();
+        vad.set_threshold(threshold);
+        vad.set_silence_timeout_ms(silence_timeout_ms);
+    }
+    state.update_settings(|s| {
+        s.speech_threshold = threshold;
+        s.silence_timeout_ms = silence_timeout_ms;
+        s.min_speech_ms = min_speech_ms;
+    });
+    persist_and_broadcast(&state, &app)
+}
+
+/// Let the VAD threshold follow the room's noise, `factor` times over
+/// the floor (`MIN_NOISE_FACTOR` to `MAX_NOISE_FACTOR`; see
+/// `audio::noise_floor`), or go back to the set threshold. A factor
+/// out of range is refused. Takes effect from the next chunk.
+#[tauri::command]
+pub fn set_adaptive_threshold(
+    enabled: bool,
+    factor: f32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if !(MIN_NOISE_FACTOR..=MAX_NOISE_FACTOR).contains(&factor) {
+        return Err(format!(
+            "Noise factor must be between {MIN_NOISE_FACTOR} and {MAX_NOISE_FACTOR} (got {factor})"
+        ));
+    }
+    tracing::info!("Adaptive VAD threshold: {} (factor {})", enabled, factor);
+    state.vad.write().set_adaptive(enabled.then_some(factor));
+    state.update_settings(|s| {
+        s.adaptive_threshold = enabled;
+        s.noise_factor = factor;
+    });
+    persist_and_broadcast(&state, &app)
+}
+
+/// Choose what detects speech: the RMS threshold or Silero's model
+/// (see `audio::silero`). The model loads in the background, the
+/// threshold deciding until it's in; when it can't, the threshold stays
+/// and an `error:occurred` says why at the next chunk.
+#[tauri::command]
+pub fn set_vad_backend(
+    backend: VadBackend,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    tracing::info!("VAD backend: {:?}", backend);
+    state.vad.write().set_backend(backend);
+    state.load_vad_model();
+    state.update_settings(|s| s.vad_backend = backend);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Seconds of audio `benchmark_vad` runs each backend over.
+const VAD_BENCHMARK_SECS: usize = 10;
+
+/// Time each VAD backend on synthetic audio (bursts of a tone over
+/// noise), for choosing between them: CPU per chunk and Silero's load
+/// time. Nothing is recorded, and the VAD in use is left alone.
+#[tauri::command]
+pub async fn benchmark_vad() -> Result<Vec<VadBenchmark>, String> {
+    let model = crate::paths::get().models_dir().join(SILERO_MODEL_FILE);
+    tokio::task::spawn_blocking(move || {
+        let samples = benchmark_audio(VAD_BENCHMARK_SECS * 16000);
+        [VadBackend::Rms, VadBackend::Silero]
+            .into_iter()
+            .map(|backend| crate::audio::benchmark_vad(backend, Some(model.clone()), &samples))
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))
+}
+
+/// Half a second of a 220 Hz tone every second, over noise.
+fn benchmark_audio(len: usize) -> Vec<i16> {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|i| {
+            let noise = rng.gen_range(-300.0..300.0);
+            let tone = if i % 16000 < 8000 {
+                let t = i as f32 / 16000.0;
+                8000.0 * (2.0 * std::f32::consts::PI * 220.0 * t).sin()
+            } else {
+                0.0
+            };
+            (noise + tone) as i16
+        })
+        .collect()
+}
+
+/// Always transcribe, even when the VAD heard (almost) no speech. For
+/// voices the level-based VAD doesn't pick up reliably.
+#[tauri::command]
+pub fn set_force_transcription(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.force_transcription = enabled);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Toggle streaming partials: interim `transcript:partial` events while
+/// recording. Takes effect from the next recording.
+#[tauri::command]
+pub fn set_streaming_partials(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.streaming_partials = enabled);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Set how often (ms of recorded audio) a streaming partial pass runs.
+#[tauri::command]
+pub fn set_partial_interval_ms(
+    ms: u32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if !(500..=10_000).contains(&ms) {
+        return Err(format!(
+            "Partial interval must be between 500 and 10000 ms (got {ms})"
+        ));
+    }
+    state.update_settings(|s| s.partial_interval_ms = ms);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Set how many transcriptions may be queued or running at once.
+#[tauri::command]
+pub fn set_transcription_queue_depth(
+    depth: u32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if !(1..=16).contains(&depth) {
+        return Err(format!(
+            "Queue depth must be between 1 and 16 (got {depth})"
+        ));
+    }
+    state.whisper.set_queue_depth(depth as usize);
+    state.update_settings(|s| s.transcription_queue_depth = depth);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Set how long (seconds) a transcription may run before it is aborted.
+#[tauri::command]
+pub fn set_transcription_timeout_secs(
+    secs: u32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if !(10..=3600).contains(&secs) {
+        return Err(format!(
+            "Transcription timeout must be between 10 and 3600 s (got {secs})"
+        ));
+    }
+    state.update_settings(|s| s.transcription_timeout_secs = secs);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Toggle non-speech suppression. Off keeps annotations such as
+/// `[Music]` or `(applause)` in the transcript.
+#[tauri::command]
+pub fn set_suppress_non_speech(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.suppress_non_speech = enabled);
+    state.whisper.set_suppress_non_speech(enabled);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Toggle deterministic mode (see `whisper::deterministic`). Off has no
+/// effect while `--deterministic` was passed.
+#[tauri::command]
+pub fn set_deterministic_mode(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.deterministic_mode = enabled);
+    state
+        .whisper
+        .set_deterministic(deterministic_mode(&state.get_settings()));
+    persist_and_broadcast(&state, &app)
+}
+
+/// Set the no-speech probability above which transcript segments are
+/// dropped. Must be a probability in `0.0..=1.0`; 1.0 disables the filter.
+#[tauri::command]
+pub fn set_no_speech_threshold(
+    threshold: f32,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(format!(
+            "No-speech threshold must be between 0.0 and 1.0 (got {threshold})"
+        ));
+    }
+    tracing::info!("Setting no-speech threshold: {}", threshold);
+    state.update_settings(|s| s.no_speech_threshold = threshold);
+    state.whisper.set_no_speech_threshold(threshold);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Replace the per-language decode overrides. Keys must be known
+/// language codes (`"auto"` is rejected — it has no fixed language to
+/// tune for).
+#[tauri::command]
+pub fn set_decode_overrides(
+    overrides: std::collections::HashMap<String, DecodeOverride>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if let Some(bad) = overrides
+        .keys()
+        .find(|code| code.as_str() == "auto" || Language::from_code(code).is_none())
+    {
+        return Err(format!(
+            "Unknown language code for decode override: {}",
+            bad
+        ));
+    }
+    state.whisper.set_decode_overrides(overrides.clone());
+    state.update_settings(|s| s.decode_overrides = overrides);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Toggle translate-to-English. Rejected up front when the loaded
+/// model is English-only; with no model loaded the flag is stored and
+/// re-checked by `start_listen` once one is.
+#[tauri::command]
+pub fn set_translate(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    if enabled && state.whisper.is_multilingual() == Some(false) {
+        return Err(ENGLISH_ONLY_TRANSLATE_ERROR.to_string());
+    }
+    state.update_settings(|s| s.translate = enabled);
+    state.whisper.set_translate(enabled);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Persist that the user dismissed the Vulkan-not-available warning.
+/// v0.1.7 wrote this directly via the JS plugin-store; same idea as
+/// `set_auto_copy`.
+#[tauri::command]
+pub fn set_vulkan_warning_dismissed(
+    dismissed: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.vulkan_warning_dismissed = dismissed);
+    persist_and_broadcast(&state, &app)
+}
+
+/// Persist that the user dismissed the welcome window.
+#[tauri::command]
+pub fn set_welcome_dismissed(
+    dismissed: bool,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), String> {
+    state.update_settings(|s| s.welcome_dismissed = dismissed);
+    persist_and_broadcast(&state, &app)
+}
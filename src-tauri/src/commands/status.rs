@@ -0,0 +1,142 @@
+//! Diagnostics: app status, crash reports, the microphone log, recent
+//! errors and memory use.
+
+use super::prelude::*;
+use crate::audio::RetainedUsage;
+use crate::degraded::DegradedReason;
+use crate::errors::RecentError;
+use crate::mic_log::{MicUsageEntry, UsageRange};
+use crate::render::RenderState;
+
+/// What the app holds in memory besides its own code. Returned by
+/// `get_memory_usage`. Frontend mirror: `MemoryUsage`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    /// Recordings kept after their transcription, against their budget.
+    pub retained_audio: RetainedUsage,
+    /// `None` without a loaded model.
+    pub model: Option<crate::whisper::ModelMemory>,
+    /// Estimated as the history's size in JSON.
+    pub history_bytes: u64,
+}
+
+/// Memory taken by the kept recordings, the loaded model and the
+/// transcript history.
+#[tauri::command]
+pub fn get_memory_usage(state: State<'_, AppState>) -> MemoryUsage {
+    let history_bytes =
+        serde_json::to_vec(&state.get_settings().history).map_or(0, |json| json.len() as u64);
+    MemoryUsage {
+        retained_audio: state.retained_audio.lock().usage(),
+        model: state.whisper.model_memory().ok(),
+        history_bytes,
+    }
+}
+
+/// Crash reports left in `<data root>/crashes/`, newest first.
+#[tauri::command]
+pub fn list_crash_reports() -> Vec<crate::crash::CrashReportSummary> {
+    crate::crash::recorder()
+        .dir()
+        .map(|dir| crate::crash::list_reports(&dir))
+        .unwrap_or_default()
+}
+
+/// Delete one crash report (a path from `list_crash_reports`).
+#[tauri::command]
+pub fn delete_crash_report(path: String) -> Result<(), String> {
+    let dir = crate::crash::recorder()
+        .dir()
+        .ok_or("Crash reporting is not initialised")?;
+    crate::crash::delete_report(&dir, std::path::Path::new(&path))
+}
+
+/// Microphone sessions (when the mic was open, what opened it, whether
+/// the audio was transcribed) that started in `range`, oldest first.
+/// Kept apart from the history and written in privacy mode too: it
+/// holds no text.
+#[tauri::command]
+pub fn get_mic_usage_log(
+    range: Option<UsageRange>,
+    state: State<'_, AppState>,
+) -> Vec<MicUsageEntry> {
+    state
+        .audio_capture
+        .usage_log()
+        .query(range.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn clear_mic_usage_log(state: State<'_, AppState>) -> Result<(), String> {
+    state
+        .audio_capture
+        .usage_log()
+        .clear()
+        .map_err(|e| format!("Failed to clear mic usage log: {}", e))
+}
+
+/// Where data is stored (standard vs portable), and why the temp
+/// fallback is in use if it is.
+#[tauri::command]
+pub fn get_data_paths() -> crate::paths::DataPaths {
+    crate::paths::get().clone()
+}
+
+/// Every tracked background task (running plus recently ended), for
+/// the diagnostics panel.
+#[tauri::command]
+pub fn get_task_status(state: State<'_, AppState>) -> Vec<crate::tasks::TaskInfo> {
+    state.tasks.status()
+}
+
+/// Snapshot returned by `get_app_status`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppStatusReport {
+    pub status: AppStatus,
+    /// Present only while `status == Degraded`.
+    pub degraded: Option<DegradedReason>,
+    /// The history is encrypted and waiting for `unlock_history`.
+    pub history_locked: bool,
+    /// Errors since the last `clear_recent_errors`, for the badge.
+    pub error_count: u32,
+}
+
+/// Current app status plus, when degraded, the reason and suggestions.
+/// Lets a window that missed the `app:degraded` event catch up.
+#[tauri::command]
+pub fn get_app_status(state: State<'_, AppState>) -> AppStatusReport {
+    AppStatusReport {
+        status: state.get_status(),
+        degraded: state.degraded_reason(),
+        history_locked: state.history_locked(),
+        error_count: state.recent_errors.lock().unseen(),
+    }
+}
+
+/// Everything the overlay draws, in one snapshot. The overlay pulls it
+/// on mount and again on every `render:invalidate`.
+#[tauri::command]
+pub fn get_render_state(state: State<'_, AppState>) -> RenderState {
+    crate::render::snapshot(&state)
+}
+
+/// The last recorded errors, newest first.
+#[tauri::command]
+pub fn get_recent_errors(state: State<'_, AppState>) -> Vec<RecentError> {
+    state.recent_errors.lock().recent()
+}
+
+/// Record the error a command failed with, as the frontend's `invoke`
+/// saw it (see `errors::report_command`).
+#[tauri::command]
+pub fn report_command_error(command: String, message: String, app: AppHandle) {
+    crate::errors::report_command(&app, &command, &message);
+}
+
+/// Forget the recorded errors and reset the badge.
+#[tauri::command]
+pub fn clear_recent_errors(state: State<'_, AppState>) {
+    state.recent_errors.lock().clear();
+}
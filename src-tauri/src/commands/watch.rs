@@ -0,0 +1,278 @@
+//! Watched folders — see `crate::watch`.
+
+use super::listen::apply_prompt;
+use super::prelude::*;
+use super::settings::persist_and_broadcast;
+use crate::watch::{
+    self, AudioKind, Manifest, ManifestEntry, Outcome, Settling, WatchError, WatchFormat,
+    WatchedFolder,
+};
+use crate::whisper::jobs::{JobParams, QueueError};
+
+/// Start the folder watcher and its `folder-watch` task, then watch the
+/// saved folders and offer what they hold, so the files that appeared
+/// while the app was closed are transcribed. Once, at startup.
+pub(crate) fn start_folder_watch(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let mut found = match state.folder_watcher.start() {
+        Ok(found) => found,
+        Err(e) => {
+            tracing::error!("Watched folders are off: {}", e);
+            return;
+        }
+    };
+    for folder in state.get_settings().watched_folders {
+        if let Err(e) = state.folder_watcher.add(&folder.path) {
+            // Unplugged drive, say: the scan on next launch catches up
+            tracing::warn!("Not watching {}: {}", folder.path.display(), e);
+            continue;
+        }
+        offer_folder(&state, &folder);
+    }
+    let app = app.clone();
+    state.tasks.spawn("folder-watch", async move {
+        let mut settling = Settling::default();
+        let mut tick = tokio::time::interval(watch::SETTLE_TICK);
+        loop {
+            tokio::select! {
+                path = found.recv() => match path {
+                    Some(path) if AudioKind::of(&path).is_some() => {
+                        settling.observe(path, std::time::Instant::now());
+                    }
+                    Some(_) => {}
+                    None => break,
+                },
+                _ = tick.tick() => {
+                    let ready = settling.settled(std::time::Instant::now(), |path| {
+                        std::fs::metadata(path).ok().map(|m| m.len())
+                    });
+                    let state = app.state::<AppState>();
+                    for path in ready {
+                        if !transcribe_watched(&state, &app, &path).await {
+                            // No model yet or the queue is full: wait again
+                            settling.observe(path, std::time::Instant::now());
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Hand every audio file in `folder` to the `folder-watch` task; the
+/// manifest skips the ones already done.
+fn offer_folder(state: &AppState, folder: &WatchedFolder) {
+    for path in watch::scan(&folder.path) {
+        state.folder_watcher.offer(path);
+    }
+}
+
+/// Deal with a settled file of a watched folder and record the outcome
+/// in the folder's manifest. `false` when it has to wait: no model is
+/// loaded, or the transcription queue is full.
+async fn transcribe_watched(state: &AppState, app: &AppHandle, path: &Path) -> bool {
+    let watched_folders = state.get_settings().watched_folders;
+    // Unwatched meanwhile
+    let Some(folder) = watched_folders.iter().find(|f| f.contains(path)) else {
+        return true;
+    };
+    let (Some(name), Ok((size_bytes, modified))) = (
+        path.file_name().map(|n| n.to_string_lossy().into_owned()),
+        watch::file_stamp(path),
+    ) else {
+        return true;
+    };
+    let mut manifest = Manifest::load(&Manifest::path_in(
+        &crate::paths::get().watch_dir(),
+        &folder.path,
+    ));
+    if manifest.is_done(&name, size_bytes, modified) {
+        return true;
+    }
+
+    let mut sha256 = None;
+    let outcome = if AudioKind::of(path) == Some(AudioKind::Unsupported) {
+        Outcome::Unsupported
+    } else {
+        if !state.whisper.is_loaded() {
+            return false;
+        }
+        let hash_path = path.to_path_buf();
+        sha256 = tokio::task::spawn_blocking(move || {
+            crate::model_integrity::sha256_file(&hash_path, |_, _| {})
+        })
+        .await
+        .ok()
+        .and_then(Result::ok);
+        let duplicate = sha256
+            .as_deref()
+            .and_then(|sha256| manifest.duplicate_of(&name, size_bytes, sha256));
+        match duplicate {
+            Some(of) => Outcome::Duplicate { of: of.to_string() },
+            None => match transcribe_watched_file(state, app, folder, path).await {
+                Ok(Ok(output)) => Outcome::Transcribed { output },
+                Ok(Err(error)) => Outcome::Failed { error },
+                Err(busy) => {
+                    tracing::debug!("{} waits: {}", path.display(), busy);
+                    return false;
+                }
+            },
+        }
+    };
+
+    let input = path.display().to_string();
+    let (event, payload) = match &outcome {
+        Outcome::Transcribed { output } => {
+            tracing::info!("Transcribed {} to {}", input, output.display());
+            (
+                "watch:transcribed",
+                serde_json::json!({ "input": input, "output": output }),
+            )
+        }
+        Outcome::Duplicate { of } => (
+            "watch:skipped",
+            serde_json::json!({ "input": input, "reason": "duplicate", "of": of }),
+        ),
+        Outcome::Unsupported => (
+            "watch:skipped",
+            serde_json::json!({ "input": input, "reason": "unsupported" }),
+        ),
+        Outcome::Failed { error } => {
+            crate::errors::report(app, "watch", error, Some(&input));
+            (
+                "watch:failed",
+                serde_json::json!({ "input": input, "error": error }),
+            )
+        }
+    };
+    manifest.record(
+        &name,
+        ManifestEntry {
+            size_bytes,
+            modified,
+            sha256,
+            outcome,
+        },
+    );
+    if let Err(e) = manifest.save() {
+        tracing::warn!(
+            "Failed to save the manifest of {}: {}",
+            folder.path.display(),
+            e
+        );
+    }
+    let _ = app.emit(event, payload);
+    true
+}
+
+/// Transcribe `path` and write the transcript where `folder` puts it.
+/// The outer error is a full queue, to try again later; the inner one
+/// a failure, recorded.
+async fn transcribe_watched_file(
+    state: &AppState,
+    app: &AppHandle,
+    folder: &WatchedFolder,
+    path: &Path,
+) -> Result<Result<PathBuf, String>, QueueError> {
+    let file = path.to_path_buf();
+    let samples = match tokio::task::spawn_blocking(move || crate::audio::read_wav(&file)).await {
+        Ok(Ok(samples)) => samples,
+        Ok(Err(e)) => return Ok(Err(e.to_string())),
+        Err(e) => return Ok(Err(e.to_string())),
+    };
+    apply_prompt(state);
+    let job = state
+        .whisper
+        .submit(Arc::from(samples), JobParams::default())?;
+    crate::render::invalidate(app);
+    let result = job.await_result().await;
+    crate::render::invalidate(app);
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => return Ok(Err(e.to_string())),
+    };
+    let output = folder.output_path(path);
+    let transcript = folder.format.render(&result.text, &result.segments);
+    Ok(tokio::fs::write(&output, transcript)
+        .await
+        .map(|()| output.clone())
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e)))
+}
+
+/// `path` as a canonical folder.
+fn canonical_folder(path: &str) -> Result<PathBuf, WatchError> {
+    let not_a_folder = || WatchError::NotAFolder {
+        path: path.to_string(),
+    };
+    let path = PathBuf::from(path.trim());
+    if !path.is_dir() {
+        return Err(not_a_folder());
+    }
+    path.canonicalize().map_err(|_| not_a_folder())
+}
+
+/// Transcribe the audio files that appear in `path` into `format`, next
+/// to them or into `output_dir`. The files already there are
+/// transcribed too, unless an earlier watch of the folder did them.
+/// Only WAV is transcribed; other audio is skipped.
+///
+/// Events emitted, for each file:
+/// - `watch:transcribed` { input, output }
+/// - `watch:skipped`     { input, reason: "duplicate" | "unsupported", of? }
+/// - `watch:failed`      { input, error }
+#[tauri::command]
+pub fn watch_folder(
+    path: String,
+    format: WatchFormat,
+    output_dir: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WatchedFolder, WatchError> {
+    let folder = WatchedFolder {
+        path: canonical_folder(&path)?,
+        format,
+        output_dir: output_dir
+            .filter(|dir| !dir.trim().is_empty())
+            .map(|dir| canonical_folder(&dir))
+            .transpose()?,
+    };
+    let watched = state.get_settings().watched_folders;
+    if watched.iter().any(|f| f.path == folder.path) {
+        return Err(WatchError::AlreadyWatched {
+            path: folder.path.display().to_string(),
+        });
+    }
+    state.folder_watcher.add(&folder.path)?;
+    tracing::info!("Watching {} ({:?})", folder.path.display(), folder.format);
+    state.update_settings(|s| s.watched_folders.push(folder.clone()));
+    offer_folder(&state, &folder);
+    persist_and_broadcast(&state, &app).map_err(|reason| WatchError::PersistFailed { reason })?;
+    Ok(folder)
+}
+
+/// Stop watching `path`. Its manifest is kept: watching it again won't
+/// transcribe the same files twice.
+#[tauri::command]
+pub fn unwatch_folder(
+    path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<(), WatchError> {
+    let given = PathBuf::from(path.trim());
+    // The folder may be gone by now
+    let canonical = given.canonicalize().unwrap_or_else(|_| given.clone());
+    let mut removed = None;
+    state.update_settings(|s| {
+        if let Some(i) = s
+            .watched_folders
+            .iter()
+            .position(|f| f.path == canonical || f.path == given)
+        {
+            removed = Some(s.watched_folders.remove(i));
+        }
+    });
+    let folder = removed.ok_or(WatchError::NotWatched { path })?;
+    state.folder_watcher.remove(&folder.path);
+    tracing::info!("Stopped watching {}", folder.path.display());
+    persist_and_broadcast(&state, &app).map_err(|reason| WatchError::PersistFailed { reason })
+}
@@ -0,0 +1,425 @@
+//! Offline crash reports.
+//!
+//! When the app dies inside whisper.cpp or a GPU driver there is
+//! usually nothing to attach to an issue. A panic hook (and, with the
+//! `native-crash-handler` feature, a signal/exception handler) writes a
+//! small JSON report into `<data root>/crashes/` (see `paths`): panic message and
+//! backtrace, the tail of the log, the active model and backend, the
+//! last command the frontend invoked, and the recent errors (`errors`). Nothing is ever uploaded — on the
+//! next launch the app emits `crash:report-available` and the user
+//! decides what to do with the file.
+//!
+//! Privacy: transcript text must never end up in a report. No code path
+//! logs transcripts, but as a backstop the most recent transcript is
+//! kept in memory and scrubbed from every string in the report before
+//! it is written.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::errors::{RecentError, MAX_RECENT_ERRORS};
+
+/// Log lines kept for the report's `logTail`.
+pub const LOG_TAIL_LINES: usize = 200;
+
+/// Replacement for scrubbed transcript text.
+const REDACTED: &str = "[redacted]";
+
+/// One crash report as written to disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    /// RFC 3339 local time of the crash.
+    pub created_at: String,
+    /// `"panic"` or `"native"`.
+    pub kind: String,
+    pub message: String,
+    pub backtrace: Option<String>,
+    pub app_version: String,
+    pub os: String,
+    pub model: Option<String>,
+    pub backend: Option<String>,
+    pub last_command: Option<String>,
+    pub log_tail: Vec<String>,
+    /// Newest first. Absent from reports written before it existed.
+    #[serde(default)]
+    pub recent_errors: Vec<RecentError>,
+}
+
+/// Entry returned by `list_crash_reports`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReportSummary {
+    pub path: String,
+    pub created_at: Option<String>,
+    pub kind: Option<String>,
+    pub message: Option<String>,
+}
+
+/// What the app was doing, kept up to date while it runs so the crash
+/// handlers never have to reach into `AppState` (whose locks may be
+/// held by the thread that just died).
+#[derive(Debug, Default)]
+struct Context {
+    model: Option<String>,
+    backend: Option<String>,
+    last_command: Option<String>,
+    last_transcript: Option<String>,
+}
+
+/// Collects crash context and turns it into reports.
+#[derive(Debug, Default)]
+pub struct CrashRecorder {
+    dir: Mutex<Option<PathBuf>>,
+    context: Mutex<Context>,
+    log_tail: Mutex<VecDeque<String>>,
+    recent_errors: Mutex<VecDeque<RecentError>>,
+}
+
+impl CrashRecorder {
+    /// Directory reports are written to; `None` until `set_dir`.
+    pub fn dir(&self) -> Option<PathBuf> {
+        self.dir.lock().clone()
+    }
+
+    pub fn set_dir(&self, dir: PathBuf) {
+        *self.dir.lock() = Some(dir);
+    }
+
+    pub fn note_command(&self, command: &str) {
+        self.context.lock().last_command = Some(command.to_string());
+    }
+
+    pub fn note_model(&self, model: &str, backend: &str) {
+        let mut ctx = self.context.lock();
+        ctx.model = Some(model.to_string());
+        ctx.backend = Some(backend.to_string());
+    }
+
+    /// Remember the latest transcript so it can be scrubbed from
+    /// reports. Only ever held in memory.
+    pub fn note_transcript(&self, text: &str) {
+        let text = text.trim();
+        self.context.lock().last_transcript = (!text.is_empty()).then(|| text.to_string());
+    }
+
+    /// Mirror of the `AppState` error ring (see `errors::report`).
+    pub fn note_error(&self, error: &RecentError) {
+        let mut errors = self.recent_errors.lock();
+        if errors.len() == MAX_RECENT_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(error.clone());
+    }
+
+    pub fn push_log_line(&self, line: String) {
+        let mut tail = self.log_tail.lock();
+        if tail.len() == LOG_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+
+    /// Assemble a report. Uses `try_lock` throughout: a crash handler
+    /// must not deadlock on a lock the crashing thread was holding, so
+    /// a busy lock just means that part of the report is missing.
+    pub fn build_report(
+        &self,
+        kind: &str,
+        message: &str,
+        backtrace: Option<String>,
+    ) -> CrashReport {
+        let (model, backend, last_command, transcript) = match self.context.try_lock() {
+            Some(ctx) => (
+                ctx.model.clone(),
+                ctx.backend.clone(),
+                ctx.last_command.clone(),
+                ctx.last_transcript.clone(),
+            ),
+            None => (None, None, None, None),
+        };
+        let log_tail = self
+            .log_tail
+            .try_lock()
+            .map(|tail| tail.iter().cloned().collect())
+            .unwrap_or_default();
+        let recent_errors = self
+            .recent_errors
+            .try_lock()
+            .map(|errors| errors.iter().rev().cloned().collect())
+            .unwrap_or_default();
+
+        let mut report = CrashReport {
+            created_at: chrono::Local::now().to_rfc3339(),
+            kind: kind.to_string(),
+            message: message.to_string(),
+            backtrace,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            model,
+            backend,
+            last_command,
+            log_tail,
+            recent_errors,
+        };
+        if let Some(transcript) = transcript {
+            scrub_report(&mut report, &transcript);
+        }
+        report
+    }
+
+    /// Build and write a report into the configured directory.
+    pub fn write(&self, kind: &str, message: &str, backtrace: Option<String>) -> Option<PathBuf> {
+        let dir = self.dir.try_lock()?.clone()?;
+        let report = self.build_report(kind, message, backtrace);
+        write_report(&dir, &report).ok()
+    }
+}
+
+/// Process-wide recorder used by the hooks and the tracing layer.
+pub fn recorder() -> &'static CrashRecorder {
+    static RECORDER: OnceLock<CrashRecorder> = OnceLock::new();
+    RECORDER.get_or_init(CrashRecorder::default)
+}
+
+/// Replace every occurrence of the transcript — and of each of its
+/// lines, for multi-line dictation — with a placeholder.
+fn scrub_report(report: &mut CrashReport, transcript: &str) {
+    let mut needles: Vec<&str> = std::iter::once(transcript)
+        .chain(transcript.lines().map(str::trim))
+        .filter(|s| !s.is_empty())
+        .collect();
+    // Longest first so a full match isn't left half-replaced by a line.
+    needles.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    let scrub = |s: &mut String| {
+        for needle in &needles {
+            if s.contains(needle) {
+                *s = s.replace(needle, REDACTED);
+            }
+        }
+    };
+    scrub(&mut report.message);
+    if let Some(bt) = report.backtrace.as_mut() {
+        scrub(bt);
+    }
+    if let Some(cmd) = report.last_command.as_mut() {
+        scrub(cmd);
+    }
+    report.log_tail.iter_mut().for_each(scrub);
+    report
+        .recent_errors
+        .iter_mut()
+        .for_each(|e| scrub(&mut e.message));
+}
+
+/// Write `report` as `crash-<timestamp>.json` inside `dir`.
+pub fn write_report(dir: &Path, report: &CrashReport) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+    let path = dir.join(format!("crash-{stamp}.json"));
+    let json = serde_json::to_vec_pretty(report)?;
+    std::fs::write(&path, json)?;
+    Ok(path)
+}
+
+/// Every report in `dir`, newest first. Unreadable files are still
+/// listed so the user can delete them.
+pub fn list_reports(dir: &Path) -> Vec<CrashReportSummary> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| is_report_file(p))
+        .collect();
+    paths.sort();
+    paths.reverse();
+    paths
+        .into_iter()
+        .map(|path| {
+            let report = std::fs::read(&path)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<CrashReport>(&bytes).ok());
+            CrashReportSummary {
+                path: path.to_string_lossy().into_owned(),
+                created_at: report.as_ref().map(|r| r.created_at.clone()),
+                kind: report.as_ref().map(|r| r.kind.clone()),
+                message: report.map(|r| r.message),
+            }
+        })
+        .collect()
+}
+
+/// Delete one report. Refuses anything that isn't a report file
+/// directly inside `dir`, so the command can't be used to delete
+/// arbitrary files.
+pub fn delete_report(dir: &Path, path: &Path) -> Result<(), String> {
+    let dir = dir.canonicalize().map_err(|e| e.to_string())?;
+    let path = path.canonicalize().map_err(|e| e.to_string())?;
+    if path.parent() != Some(dir.as_path()) || !is_report_file(&path) {
+        return Err(format!("Not a crash report: {}", path.display()));
+    }
+    std::fs::remove_file(&path).map_err(|e| e.to_string())
+}
+
+fn is_report_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with("crash-") && n.ends_with(".json"))
+}
+
+/// Install the panic hook. The default hook still runs first so the
+/// panic shows up on stderr as before; with `panic = "abort"` in
+/// release builds this is the last code that runs.
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let mut message = payload;
+        if let Some(location) = info.location() {
+            let _ = write!(message, " at {location}");
+        }
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        if let Some(path) = recorder().write("panic", &message, Some(backtrace)) {
+            eprintln!("Crash report written to {}", path.display());
+        }
+    }));
+}
+
+/// Install the native crash handler (segfaults, aborts inside
+/// whisper.cpp or GPU drivers). The handler stays attached for the
+/// life of the process.
+///
+/// This writes the same JSON report, without a backtrace, from inside
+/// the signal/exception handler. That is best-effort — allocating
+/// there isn't async-signal-safe — but the process is going down
+/// anyway, and a partial report beats none.
+#[cfg(feature = "native-crash-handler")]
+pub fn install_native_handler() {
+    static HANDLER: OnceLock<crash_handler::CrashHandler> = OnceLock::new();
+    let handler = crash_handler::CrashHandler::attach(unsafe {
+        crash_handler::make_crash_event(|_ctx: &crash_handler::CrashContext| {
+            recorder().write("native", "Native crash (signal or exception)", None);
+            crash_handler::CrashEventResult::Handled(false)
+        })
+    });
+    match handler {
+        Ok(handler) => {
+            let _ = HANDLER.set(handler);
+        }
+        Err(e) => tracing::warn!("Failed to attach native crash handler: {}", e),
+    }
+}
+
+/// Feeds the crash recorder's log tail. Only the event message is
+/// kept — structured fields are dropped.
+pub struct LogTailLayer;
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for LogTailLayer {
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        struct MessageVisitor(String);
+        impl tracing::field::Visit for MessageVisitor {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    let _ = write!(self.0, "{value:?}");
+                }
+            }
+        }
+
+        let meta = event.metadata();
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+        recorder().push_log_line(format!(
+            "{} {} {}: {}",
+            chrono::Local::now().format("%H:%M:%S%.3f"),
+            meta.level(),
+            meta.target(),
+            visitor.0
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcript_never_reaches_the_report() {
+        let recorder = CrashRecorder::default();
+        let secret = "my bank PIN is 4321\nand the vault code is 9876";
+        recorder.note_command("stop_listen");
+        recorder.note_model("base.en", "Vulkan");
+        recorder.note_transcript(secret);
+        recorder.push_log_line(format!("INFO s2tui: got {secret}"));
+        recorder.push_log_line("INFO s2tui: and the vault code is 9876".to_string());
+
+        let report = recorder.build_report(
+            "panic",
+            "index out of bounds in 'my bank PIN is 4321'",
+            Some("frame 0: and the vault code is 9876".to_string()),
+        );
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("4321"), "{json}");
+        assert!(!json.contains("9876"), "{json}");
+        assert!(json.contains(REDACTED));
+        assert_eq!(report.last_command.as_deref(), Some("stop_listen"));
+        assert_eq!(report.model.as_deref(), Some("base.en"));
+        assert_eq!(report.backend.as_deref(), Some("Vulkan"));
+    }
+
+    #[test]
+    fn log_tail_is_bounded() {
+        let recorder = CrashRecorder::default();
+        for i in 0..LOG_TAIL_LINES + 10 {
+            recorder.push_log_line(format!("line {i}"));
+        }
+        let report = recorder.build_report("panic", "boom", None);
+        assert_eq!(report.log_tail.len(), LOG_TAIL_LINES);
+        assert_eq!(report.log_tail[0], "line 10");
+    }
+
+    #[test]
+    fn write_list_delete_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = CrashRecorder::default();
+        recorder.set_dir(dir.path().to_path_buf());
+        let path = recorder.write("panic", "boom", None).unwrap();
+
+        let listed = list_reports(dir.path());
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].message.as_deref(), Some("boom"));
+        assert_eq!(listed[0].kind.as_deref(), Some("panic"));
+
+        delete_report(dir.path(), &path).unwrap();
+        assert!(list_reports(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn delete_refuses_files_outside_the_crash_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let other = tempfile::tempdir().unwrap();
+        let outside = other.path().join("crash-1.json");
+        std::fs::write(&outside, "{}").unwrap();
+        assert!(delete_report(dir.path(), &outside).is_err());
+        assert!(outside.exists());
+
+        let not_report = dir.path().join("settings.json");
+        std::fs::write(&not_report, "{}").unwrap();
+        assert!(delete_report(dir.path(), &not_report).is_err());
+        assert!(not_report.exists());
+    }
+}
@@ -0,0 +1,268 @@
+//! Coalesced writes to disk.
+//!
+//! Dragging a slider in the settings window calls a setter dozens of
+//! times a second, and each call used to rewrite `settings.json`. Now
+//! the setters only mark the settings dirty; a background task writes
+//! them at most once per interval, reading whatever they are by then,
+//! so the intermediate states never reach the disk. The first change
+//! after a quiet period is written right away and a change is never
+//! more than one interval away from the disk, which bounds what a
+//! crash can lose.
+//!
+//! Every write, the background one and `flush` (app exit,
+//! `flush_settings`), holds the same lock, so they never interleave.
+//!
+//! Nothing here is settings-specific: the write is a closure.
+//! Tauri-free; the clock is tokio's, so tests can pause it.
+
+use parking_lot::Mutex;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+type Flusher = Arc<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// When the next write is due.
+#[derive(Debug)]
+pub struct Throttle {
+    interval: Duration,
+    /// First change not written yet.
+    dirty_since: Option<Instant>,
+    last_flush: Option<Instant>,
+}
+
+impl Throttle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            dirty_since: None,
+            last_flush: None,
+        }
+    }
+
+    /// Record a change at `now`; `true` when it is the first one since
+    /// the last write.
+    pub fn mark(&mut self, now: Instant) -> bool {
+        if self.dirty_since.is_some() {
+            return false;
+        }
+        self.dirty_since = Some(now);
+        true
+    }
+
+    /// When to write; `None` when there is nothing to write.
+    pub fn flush_at(&self) -> Option<Instant> {
+        let since = self.dirty_since?;
+        Some(match self.last_flush {
+            Some(last) => since.max(last + self.interval),
+            None => since,
+        })
+    }
+
+    /// Start a write at `now`: `false` when there was nothing to write.
+    pub fn take(&mut self, now: Instant) -> bool {
+        if self.dirty_since.take().is_none() {
+            return false;
+        }
+        self.last_flush = Some(now);
+        true
+    }
+}
+
+struct Shared {
+    throttle: Mutex<Throttle>,
+    dirty: Notify,
+    /// Held across every write.
+    writing: Mutex<()>,
+    flusher: Mutex<Option<Flusher>>,
+}
+
+/// Writes something at most once per interval. Cheap to clone; lives
+/// in `AppState`.
+#[derive(Clone)]
+pub struct Debouncer {
+    shared: Arc<Shared>,
+}
+
+impl Debouncer {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                throttle: Mutex::new(Throttle::new(interval)),
+                dirty: Notify::new(),
+                writing: Mutex::new(()),
+                flusher: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// What a write does. Changes marked before this is set are written
+    /// by the first flush after it.
+    pub fn set_flusher(&self, flusher: impl Fn() -> Result<(), String> + Send + Sync + 'static) {
+        *self.shared.flusher.lock() = Some(Arc::new(flusher));
+    }
+
+    /// Something changed; it will be written within the interval.
+    pub fn mark_dirty(&self) {
+        if self.shared.throttle.lock().mark(Instant::now()) {
+            self.shared.dirty.notify_one();
+        }
+    }
+
+    /// Write now if anything changed. `Ok(false)` when there was
+    /// nothing to write. A failed write stays due and is retried an
+    /// interval later.
+    pub fn flush(&self) -> Result<bool, String> {
+        let _writing = self.shared.writing.lock();
+        let Some(flusher) = self.shared.flusher.lock().clone() else {
+            return Ok(false);
+        };
+        let now = Instant::now();
+        if !self.shared.throttle.lock().take(now) {
+            return Ok(false);
+        }
+        flusher().inspect_err(|_| {
+            self.shared.throttle.lock().mark(now);
+            self.shared.dirty.notify_one();
+        })?;
+        Ok(true)
+    }
+
+    /// The background writer. Spawn it once; it runs until aborted.
+    pub fn run(&self) -> impl Future<Output = ()> + Send + 'static {
+        let debouncer = self.clone();
+        async move {
+            loop {
+                let due = debouncer.shared.throttle.lock().flush_at();
+                match due {
+                    // `notify_one` keeps a permit when nobody waits yet,
+                    // so a change marked since the check isn't missed.
+                    None => debouncer.shared.dirty.notified().await,
+                    Some(at) => {
+                        tokio::time::sleep_until(at).await;
+                        if let Err(e) = debouncer.flush() {
+                            tracing::warn!("Deferred write failed, retrying: {}", e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const INTERVAL: Duration = Duration::from_millis(500);
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// (value written, when)
+    type WriteLog = Arc<Mutex<Vec<(u32, Instant)>>>;
+
+    /// A debouncer writing `value` into the returned log.
+    fn recording(value: Arc<AtomicU32>) -> (Debouncer, WriteLog) {
+        let debouncer = Debouncer::new(INTERVAL);
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let log = writes.clone();
+        debouncer.set_flusher(move || {
+            log.lock()
+                .push((value.load(Ordering::SeqCst), Instant::now()));
+            Ok(())
+        });
+        (debouncer, writes)
+    }
+
+    #[test]
+    fn throttle_writes_at_most_once_per_interval() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(INTERVAL);
+        assert_eq!(throttle.flush_at(), None);
+        // First change after a quiet period: due at once
+        assert!(throttle.mark(start));
+        assert_eq!(throttle.flush_at(), Some(start));
+        assert!(throttle.take(start));
+        assert!(!throttle.take(start));
+        // Changes right after a write wait for the interval, and only
+        // the first one reports
+        assert!(throttle.mark(start + ms(10)));
+        assert!(!throttle.mark(start + ms(200)));
+        assert_eq!(throttle.flush_at(), Some(start + INTERVAL));
+        assert!(throttle.take(start + INTERVAL));
+        // Long after: due at once again
+        throttle.mark(start + ms(5000));
+        assert_eq!(throttle.flush_at(), Some(start + ms(5000)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slider_drag_is_coalesced() {
+        let value = Arc::new(AtomicU32::new(0));
+        let (debouncer, writes) = recording(value.clone());
+        let start = Instant::now();
+        tokio::spawn(debouncer.run());
+        // 60 changes over 1.2 s, one every 20 ms
+        for step in 1..=60 {
+            value.store(step, Ordering::SeqCst);
+            debouncer.mark_dirty();
+            tokio::time::sleep(ms(20)).await;
+        }
+        tokio::time::sleep(INTERVAL).await;
+        let writes = writes.lock();
+        // The first change at once, then one write per interval, the
+        // last with the final value
+        assert_eq!(writes.len(), 4);
+        assert_eq!(writes[0].0, 1);
+        assert_eq!(writes[3].0, 60);
+        for pair in writes.windows(2) {
+            assert!(pair[1].1 - pair[0].1 >= INTERVAL);
+        }
+        // No change waited longer than the interval
+        assert_eq!(writes[0].1, start);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exit_flush_writes_pending_changes() {
+        let value = Arc::new(AtomicU32::new(0));
+        let (debouncer, writes) = recording(value.clone());
+        tokio::spawn(debouncer.run());
+        value.store(1, Ordering::SeqCst);
+        debouncer.mark_dirty();
+        tokio::time::sleep(ms(10)).await;
+        // Within the interval of the first write: deferred...
+        value.store(2, Ordering::SeqCst);
+        debouncer.mark_dirty();
+        tokio::time::sleep(ms(10)).await;
+        assert_eq!(writes.lock().len(), 1);
+        // ...until the app exits
+        assert_eq!(debouncer.flush(), Ok(true));
+        assert_eq!(writes.lock().last().unwrap().0, 2);
+        assert_eq!(debouncer.flush(), Ok(false));
+        // The background writer has nothing left to do
+        tokio::time::sleep(INTERVAL * 2).await;
+        assert_eq!(writes.lock().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_write_is_retried() {
+        let debouncer = Debouncer::new(INTERVAL);
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        debouncer.set_flusher(move || match counter.fetch_add(1, Ordering::SeqCst) {
+            0 => Err("disk full".to_string()),
+            _ => Ok(()),
+        });
+        tokio::spawn(debouncer.run());
+        debouncer.mark_dirty();
+        tokio::time::sleep(ms(10)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        tokio::time::sleep(INTERVAL).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(debouncer.flush(), Ok(false));
+    }
+}
@@ -0,0 +1,243 @@
+//! Degraded ("text-only") mode bookkeeping.
+//!
+//! When every model load attempt fails — missing or corrupt file, no
+//! disk space, GPU init failure followed by a CPU OOM — dictation can't
+//! work at all. Rather than letting each `start_listen` fail with a
+//! confusing engine error, the app enters `AppStatus::Degraded` after
+//! the startup auto-load *and* one retry have both failed, tells the
+//! user why, and suggests what to do about it. A file that can't load
+//! (truncated, corrupt, too new) won't do better on a retry, so those
+//! degrade on the first failure.
+//!
+//! The classification itself is `whisper::load_failure`.
+//!
+//! Everything in here is pure logic over the classified load error so
+//! the transitions and the suggestion list are unit-testable without a
+//! Tauri runtime or a real model file.
+
+use crate::whisper::load_failure::LoadFailureKind;
+use serde::Serialize;
+
+/// Consecutive failed loads before we give up and go degraded. One for
+/// the startup auto-load, one for the retry (either the frontend's
+/// automatic CPU retry or the user's first manual attempt).
+pub const FAILURES_BEFORE_DEGRADED: u32 = 2;
+
+/// One actionable hint shown in the degraded banner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DegradedSuggestion {
+    FreeDiskSpace,
+    RedownloadModel,
+    SwitchToCpu,
+    TrySmallerModel,
+    UpdateApp,
+}
+
+/// Payload of the `app:degraded` event and of `get_app_status`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DegradedReason {
+    pub cause: LoadFailureKind,
+    /// The last raw load error, kept verbatim for the details panel.
+    pub reason: String,
+    pub suggestions: Vec<DegradedSuggestion>,
+}
+
+/// Ordered list of suggestions for a given cause. The most likely fix
+/// comes first; "re-download" is always offered as a last resort since
+/// it fixes the widest range of file-level problems.
+pub fn suggestions_for(cause: LoadFailureKind) -> Vec<DegradedSuggestion> {
+    use DegradedSuggestion::*;
+    match cause {
+        LoadFailureKind::ModelMissing => vec![RedownloadModel],
+        // Downloads often stop on a full disk
+        LoadFailureKind::Truncated => vec![RedownloadModel, FreeDiskSpace],
+        LoadFailureKind::ModelCorrupt => vec![RedownloadModel],
+        LoadFailureKind::UnsupportedVersion => vec![UpdateApp, RedownloadModel],
+        LoadFailureKind::DiskFull => vec![FreeDiskSpace, RedownloadModel],
+        LoadFailureKind::OutOfMemory => vec![TrySmallerModel, SwitchToCpu],
+        LoadFailureKind::GpuInit => vec![SwitchToCpu, TrySmallerModel],
+        LoadFailureKind::Unknown => vec![SwitchToCpu, FreeDiskSpace, RedownloadModel],
+    }
+}
+
+/// Tracks consecutive load failures and decides when to enter / leave
+/// degraded mode. Lives inside `AppStateInner`.
+#[derive(Debug, Default)]
+pub struct DegradedTracker {
+    consecutive_failures: u32,
+    reason: Option<DegradedReason>,
+}
+
+impl DegradedTracker {
+    /// Record a failed load. Returns `Some(reason)` exactly when this
+    /// failure is the one that tips the app into degraded mode, or when
+    /// we're already degraded and the reason changed (so the UI can
+    /// refresh its banner). Returns `None` otherwise. A failure a retry
+    /// can't fix (`LoadFailureKind::retry_can_help`) degrades at once.
+    pub fn record_failure(
+        &mut self,
+        cause: LoadFailureKind,
+        message: &str,
+    ) -> Option<DegradedReason> {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.consecutive_failures < FAILURES_BEFORE_DEGRADED && cause.retry_can_help() {
+            return None;
+        }
+        let reason = DegradedReason {
+            cause,
+            reason: message.to_string(),
+            suggestions: suggestions_for(cause),
+        };
+        if self.reason.as_ref() == Some(&reason) {
+            return None;
+        }
+        self.reason = Some(reason.clone());
+        Some(reason)
+    }
+
+    /// Record a successful load. Returns `true` if this cleared a
+    /// degraded state (the caller then restores `Idle`).
+    pub fn record_success(&mut self) -> bool {
+        self.consecutive_failures = 0;
+        self.reason.take().is_some()
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.reason.is_some()
+    }
+
+    pub fn reason(&self) -> Option<&DegradedReason> {
+        self.reason.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whisper::load_failure::{FileFacts, LoadFailure};
+    use crate::whisper::WhisperError;
+
+    #[test]
+    fn single_failure_does_not_degrade() {
+        let mut t = DegradedTracker::default();
+        assert!(t
+            .record_failure(LoadFailureKind::GpuInit, "vulkan init failed")
+            .is_none());
+        assert!(!t.is_degraded());
+    }
+
+    #[test]
+    fn auto_load_plus_retry_failure_degrades() {
+        let mut t = DegradedTracker::default();
+        t.record_failure(LoadFailureKind::GpuInit, "vulkan init failed");
+        let reason = t
+            .record_failure(
+                LoadFailureKind::OutOfMemory,
+                "CPU loading failed: out of memory",
+            )
+            .expect("second failure must degrade");
+        assert!(t.is_degraded());
+        // The *latest* cause wins — it's the one the retry hit.
+        assert_eq!(reason.cause, LoadFailureKind::OutOfMemory);
+        assert_eq!(
+            reason.suggestions,
+            vec![
+                DegradedSuggestion::TrySmallerModel,
+                DegradedSuggestion::SwitchToCpu
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_identical_failure_is_not_reannounced() {
+        let mut t = DegradedTracker::default();
+        t.record_failure(LoadFailureKind::ModelMissing, "gone");
+        assert!(t
+            .record_failure(LoadFailureKind::ModelMissing, "gone")
+            .is_some());
+        assert!(t
+            .record_failure(LoadFailureKind::ModelMissing, "gone")
+            .is_none());
+        assert!(t.is_degraded());
+    }
+
+    #[test]
+    fn success_clears_degraded_and_resets_counter() {
+        let mut t = DegradedTracker::default();
+        t.record_failure(LoadFailureKind::DiskFull, "no space left on device");
+        t.record_failure(LoadFailureKind::DiskFull, "no space left on device");
+        assert!(t.record_success());
+        assert!(!t.is_degraded());
+        // Counter reset: one new failure isn't enough to re-degrade.
+        assert!(t
+            .record_failure(LoadFailureKind::DiskFull, "no space left on device")
+            .is_none());
+        // Success while healthy reports nothing to clear.
+        assert!(!t.record_success());
+    }
+
+    #[test]
+    fn file_problems_degrade_at_once() {
+        let mut t = DegradedTracker::default();
+        let reason = t
+            .record_failure(
+                LoadFailureKind::UnsupportedVersion,
+                "invalid model (bad ftype value 3008)",
+            )
+            .expect("retrying can't help");
+        assert_eq!(
+            reason.suggestions,
+            vec![
+                DegradedSuggestion::UpdateApp,
+                DegradedSuggestion::RedownloadModel
+            ]
+        );
+        assert!(t.is_degraded());
+    }
+
+    #[test]
+    fn classifies_engine_errors() {
+        assert_eq!(
+            LoadFailureKind::from_error(&WhisperError::ModelNotFound("/x.bin".into())),
+            LoadFailureKind::ModelMissing
+        );
+        assert_eq!(
+            LoadFailureKind::from_error(&WhisperError::LoadError(LoadFailure::classify(
+                "CPU loading failed: failed to allocate buffer of size 1073741824".into(),
+                Vec::new(),
+                &FileFacts::default()
+            ))),
+            LoadFailureKind::OutOfMemory
+        );
+        assert_eq!(
+            LoadFailureKind::from_message("CPU loading failed: No space left on device"),
+            LoadFailureKind::DiskFull
+        );
+        assert_eq!(
+            LoadFailureKind::from_message("CPU loading failed: invalid model data (bad magic)"),
+            LoadFailureKind::ModelCorrupt
+        );
+        assert_eq!(
+            LoadFailureKind::from_message("something odd"),
+            LoadFailureKind::Unknown
+        );
+    }
+
+    #[test]
+    fn every_cause_has_at_least_one_suggestion() {
+        for cause in [
+            LoadFailureKind::ModelMissing,
+            LoadFailureKind::Truncated,
+            LoadFailureKind::ModelCorrupt,
+            LoadFailureKind::UnsupportedVersion,
+            LoadFailureKind::OutOfMemory,
+            LoadFailureKind::DiskFull,
+            LoadFailureKind::GpuInit,
+            LoadFailureKind::Unknown,
+        ] {
+            assert!(!suggestions_for(cause).is_empty(), "{cause:?}");
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Recent errors, kept for the user to look at later.
+//!
+//! Errors that happen while the overlay is hidden (a stream error, a
+//! failed model auto-load) would otherwise only reach the log. Every
+//! error path goes through `report` — directly, or through
+//! `ReportErr::report_err` for a `Result` — which records the error in
+//! the `AppState` ring (the last `MAX_RECENT_ERRORS`), mirrors it into
+//! the crash recorder so it lands in crash reports, and emits
+//! `error:occurred`. The number of errors since the last
+//! `clear_recent_errors` is the badge count in `get_app_status`.
+//!
+//! Every command that fails is reported too, by `report_command`. Tauri
+//! hands the invoke handler the call but not what the command returns,
+//! so that goes through the frontend's `invoke` (`src/utils/invoke.ts`),
+//! which sees every rejection and calls `report_command_error`. An
+//! error the command reported itself, under a more specific code, isn't
+//! recorded twice.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::crash::CrashRecorder;
+use crate::state::AppState;
+
+/// Errors kept in the ring.
+pub const MAX_RECENT_ERRORS: usize = 50;
+
+/// How far back `report_command` looks for the same error reported
+/// from inside the command.
+const ALREADY_REPORTED_MS: i64 = 10_000;
+
+/// One recorded error; also the `error:occurred` payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentError {
+    /// Stable kebab-case identifier, e.g. `"transcription-timeout"`.
+    pub code: String,
+    pub message: String,
+    /// Unix time, milliseconds.
+    pub timestamp: i64,
+    /// What was going on (a command name, a model, a device).
+    pub context: Option<String>,
+}
+
+/// Ring of the most recent errors, oldest first.
+#[derive(Debug, Default)]
+pub struct ErrorLog {
+    entries: VecDeque<RecentError>,
+    unseen: u32,
+}
+
+impl ErrorLog {
+    pub fn push(&mut self, error: RecentError) {
+        if self.entries.len() == MAX_RECENT_ERRORS {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(error);
+        self.unseen = self.unseen.saturating_add(1);
+    }
+
+    /// Newest first.
+    pub fn recent(&self) -> Vec<RecentError> {
+        self.entries.iter().rev().cloned().collect()
+    }
+
+    /// Errors since the last `clear` (not capped by the ring size).
+    pub fn unseen(&self) -> u32 {
+        self.unseen
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.unseen = 0;
+    }
+
+    /// Whether `message` was recorded at `since` (Unix ms) or later.
+    pub fn has_since(&self, message: &str, since: i64) -> bool {
+        self.entries
+            .iter()
+            .rev()
+            .take_while(|error| error.timestamp >= since)
+            .any(|error| error.message == message)
+    }
+}
+
+/// The funnel's bookkeeping half: build the error and record it in
+/// `log` and `recorder`.
+fn record(
+    log: &Mutex<ErrorLog>,
+    recorder: &CrashRecorder,
+    code: &str,
+    message: &str,
+    context: Option<&str>,
+    timestamp: i64,
+) -> RecentError {
+    let error = RecentError {
+        code: code.to_string(),
+        message: message.to_string(),
+        timestamp,
+        context: context.map(str::to_string),
+    };
+    log.lock().push(error.clone());
+    recorder.note_error(&error);
+    error
+}
+
+/// Record an error and tell the frontend (`error:occurred`). Safe to
+/// call before `AppState` is managed: the error then only reaches the
+/// log and the crash recorder.
+pub fn report(app: &AppHandle, code: &str, message: &str, context: Option<&str>) {
+    tracing::warn!("[{}] {}", code, message);
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    let recorder = crate::crash::recorder();
+    let error = match app.try_state::<AppState>() {
+        Some(state) => record(
+            &state.recent_errors,
+            recorder,
+            code,
+            message,
+            context,
+            timestamp,
+        ),
+        None => record(
+            &Mutex::default(),
+            recorder,
+            code,
+            message,
+            context,
+            timestamp,
+        ),
+    };
+    if let Err(e) = app.emit("error:occurred", &error) {
+        tracing::warn!("error:occurred emit failed: {e}");
+    }
+}
+
+/// `report` the error `command` failed with (code `command-failed`),
+/// unless the command already reported it.
+pub fn report_command(app: &AppHandle, command: &str, message: &str) {
+    let since = chrono::Utc::now().timestamp_millis() - ALREADY_REPORTED_MS;
+    let reported = app
+        .try_state::<AppState>()
+        .is_some_and(|state| state.recent_errors.lock().has_since(message, since));
+    if !reported {
+        report(app, "command-failed", message, Some(command));
+    }
+}
+
+/// `report` the error of a failed result, passing it through.
+pub trait ReportErr {
+    fn report_err(self, app: &AppHandle, code: &str, context: &str) -> Self;
+}
+
+impl<T, E: Display> ReportErr for Result<T, E> {
+    fn report_err(self, app: &AppHandle, code: &str, context: &str) -> Self {
+        if let Err(e) = &self {
+            report(app, code, &e.to_string(), Some(context));
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(n: i64) -> RecentError {
+        RecentError {
+            code: "test".to_string(),
+            message: format!("error {n}"),
+            timestamp: n,
+            context: None,
+        }
+    }
+
+    #[test]
+    fn ring_keeps_the_newest() {
+        let mut log = ErrorLog::default();
+        for n in 0..(MAX_RECENT_ERRORS as i64 + 5) {
+            log.push(error(n));
+        }
+        let recent = log.recent();
+        assert_eq!(recent.len(), MAX_RECENT_ERRORS);
+        assert_eq!(recent[0].timestamp, MAX_RECENT_ERRORS as i64 + 4);
+        assert_eq!(recent.last().unwrap().timestamp, 5);
+        // The badge counts every error, not just the kept ones
+        assert_eq!(log.unseen(), MAX_RECENT_ERRORS as u32 + 5);
+    }
+
+    #[test]
+    fn clear_resets_the_badge() {
+        let mut log = ErrorLog::default();
+        log.push(error(1));
+        log.clear();
+        assert!(log.recent().is_empty());
+        assert_eq!(log.unseen(), 0);
+        log.push(error(2));
+        assert_eq!(log.unseen(), 1);
+    }
+
+    #[test]
+    fn an_error_already_reported_is_found() {
+        let mut log = ErrorLog::default();
+        for n in 1..=3 {
+            log.push(error(n));
+        }
+        assert!(log.has_since("error 3", 2));
+        assert!(log.has_since("error 2", 2));
+        // Too old
+        assert!(!log.has_since("error 1", 2));
+        assert!(!log.has_since("error 4", 0));
+    }
+
+    #[test]
+    fn funnel_feeds_the_ring_and_the_crash_recorder() {
+        let log = Mutex::new(ErrorLog::default());
+        let recorder = CrashRecorder::default();
+        let recorded = record(
+            &log,
+            &recorder,
+            "model-load",
+            "file is truncated",
+            Some("ggml-small.bin"),
+            42,
+        );
+        assert_eq!(log.lock().recent(), vec![recorded.clone()]);
+        let report = recorder.build_report("panic", "boom", None);
+        assert_eq!(report.recent_errors, vec![recorded]);
+    }
+
+    #[test]
+    fn payload_shape() {
+        let json = serde_json::to_value(error(7)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "code": "test",
+                "message": "error 7",
+                "timestamp": 7,
+                "context": null
+            })
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Encryption at rest for the transcript history.
+//!
+//! With encryption on, `settings.json` holds an `EncryptedHistory`
+//! instead of the plain `history` list: every entry is sealed on its
+//! own with XChaCha20-Poly1305 under a random 24-byte nonce, so adding
+//! an entry never re-encrypts the others. The 32-byte key comes either
+//! from a user passphrase (Argon2id with the vault's salt) or from a
+//! random key kept in the OS keychain.
+//!
+//! A sealed `verifier` of a known constant tells a wrong key (or a
+//! keychain entry that was replaced) apart from a damaged entry: the
+//! first makes the whole vault unreadable, the second only drops that
+//! entry. Nothing here panics on bad input — a vault whose key is gone
+//! is reported as such and the user can start fresh.
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::state::HistoryEntry;
+
+const KEYCHAIN_SERVICE: &str = "s2tui";
+const KEYCHAIN_USER: &str = "history-key";
+/// Plaintext of the verifier.
+const VERIFIER: &[u8] = b"s2tui-history-v1";
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+
+/// Where the key comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeySource {
+    Passphrase,
+    Keychain,
+}
+
+/// One sealed value: hex nonce and hex ciphertext (tag included).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sealed {
+    pub nonce: String,
+    pub data: String,
+}
+
+/// The encrypted history as persisted in `settings.json`, newest first
+/// like the plain list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedHistory {
+    pub key_source: KeySource,
+    /// Argon2 salt (hex). Unused for `Keychain`.
+    pub salt: String,
+    pub verifier: Sealed,
+    pub entries: Vec<Sealed>,
+}
+
+/// Structured error of the history commands.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum VaultError {
+    #[error("History is locked")]
+    Locked,
+    #[error("A passphrase is required to unlock the history")]
+    PassphraseRequired,
+    #[error("Wrong passphrase or key")]
+    WrongKey,
+    /// The keychain no longer has the key: the history can't be read.
+    #[error("History key unavailable: {message}")]
+    KeyUnavailable { message: String },
+    #[error("History encryption failed: {message}")]
+    Crypto { message: String },
+    #[error("Failed to save the history: {message}")]
+    Storage { message: String },
+}
+
+/// An unlocked vault key, wiped from memory when dropped.
+#[derive(Clone)]
+pub struct HistoryKey(Zeroizing<[u8; 32]>);
+
+impl std::fmt::Debug for HistoryKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("HistoryKey(..)")
+    }
+}
+
+impl HistoryKey {
+    /// Derive from a passphrase with Argon2id. Slow on purpose: call it
+    /// off the async workers.
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self, VaultError> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        argon2::Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, key.as_mut_slice())
+            .map_err(|e| VaultError::Crypto {
+                message: e.to_string(),
+            })?;
+        Ok(Self(key))
+    }
+
+    /// The keychain key, created on first use when `create` is set. The
+    /// keychain may block (or prompt), so off the async workers too.
+    pub fn from_keychain(create: bool) -> Result<Self, VaultError> {
+        let unavailable = |e: keyring::Error| VaultError::KeyUnavailable {
+            message: e.to_string(),
+        };
+        let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_USER).map_err(unavailable)?;
+        match entry.get_password() {
+            Ok(hex) => decode_hex(&Zeroizing::new(hex))
+                .map(Zeroizing::new)
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes.as_slice()).ok())
+                .map(|key| Self(Zeroizing::new(key)))
+                .ok_or(VaultError::KeyUnavailable {
+                    message: "the keychain entry is not a history key".to_string(),
+                }),
+            Err(keyring::Error::NoEntry) if create => {
+                let key = Self(Zeroizing::new(rand::random()));
+                entry
+                    .set_password(&Zeroizing::new(encode_hex(key.0.as_slice())))
+                    .map_err(unavailable)?;
+                Ok(key)
+            }
+            Err(e) => Err(unavailable(e)),
+        }
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(self.0.as_slice()))
+    }
+
+    fn seal(&self, plaintext: &[u8]) -> Result<Sealed, VaultError> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let data = self
+            .cipher()
+            .encrypt(&nonce, plaintext)
+            .map_err(|e| VaultError::Crypto {
+                message: e.to_string(),
+            })?;
+        Ok(Sealed {
+            nonce: encode_hex(&nonce),
+            data: encode_hex(&data),
+        })
+    }
+
+    /// `None` on a wrong key, a tampered value or malformed hex.
+    fn open(&self, sealed: &Sealed) -> Option<Vec<u8>> {
+        let nonce = decode_hex(&sealed.nonce).filter(|n| n.len() == NONCE_LEN)?;
+        let data = decode_hex(&sealed.data)?;
+        self.cipher()
+            .decrypt(XNonce::from_slice(&nonce), data.as_slice())
+            .ok()
+    }
+
+    pub fn seal_entry(&self, entry: &HistoryEntry) -> Result<Sealed, VaultError> {
+        let json = serde_json::to_vec(entry).map_err(|e| VaultError::Crypto {
+            message: e.to_string(),
+        })?;
+        self.seal(&json)
+    }
+}
+
+impl EncryptedHistory {
+    /// A new vault holding `entries`. `key` must come from `key_source`
+    /// (with `salt` for a passphrase).
+    pub fn create(
+        key_source: KeySource,
+        salt: &[u8],
+        key: &HistoryKey,
+        entries: &[HistoryEntry],
+    ) -> Result<Self, VaultError> {
+        Ok(Self {
+            key_source,
+            salt: encode_hex(salt),
+            verifier: key.seal(VERIFIER)?,
+            entries: entries
+                .iter()
+                .map(|e| key.seal_entry(e))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// The key for this vault, checked against the verifier.
+    pub fn unlock(&self, passphrase: Option<&str>) -> Result<HistoryKey, VaultError> {
+        let key = match self.key_source {
+            KeySource::Passphrase => {
+                let passphrase = passphrase.ok_or(VaultError::PassphraseRequired)?;
+                let salt = decode_hex(&self.salt).ok_or(VaultError::WrongKey)?;
+                HistoryKey::from_passphrase(passphrase, &salt)?
+            }
+            KeySource::Keychain => HistoryKey::from_keychain(false)?,
+        };
+        self.check(&key)?;
+        Ok(key)
+    }
+
+    fn check(&self, key: &HistoryKey) -> Result<(), VaultError> {
+        match key.open(&self.verifier) {
+            Some(plain) if plain == VERIFIER => Ok(()),
+            _ => Err(VaultError::WrongKey),
+        }
+    }
+
+    /// Decrypt every entry, newest first. Entries that fail to decrypt
+    /// under a verified key are damaged; they are skipped and counted.
+    pub fn decrypt(&self, key: &HistoryKey) -> Result<(Vec<HistoryEntry>, usize), VaultError> {
+        self.check(key)?;
+        let mut entries = Vec::with_capacity(self.entries.len());
+        let mut damaged = 0;
+        for sealed in &self.entries {
+            match key
+                .open(sealed)
+                .and_then(|json| serde_json::from_slice(&json).ok())
+            {
+                Some(entry) => entries.push(entry),
+                None => damaged += 1,
+            }
+        }
+        if damaged > 0 {
+            tracing::warn!("Skipped {} unreadable history entries", damaged);
+        }
+        Ok((entries, damaged))
+    }
+}
+
+/// Fresh random salt for a passphrase vault.
+pub fn new_salt() -> [u8; SALT_LEN] {
+    rand::random()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, text: &str) -> HistoryEntry {
+        HistoryEntry {
+            id: id.to_string(),
+            text: text.to_string(),
+            timestamp: 1_700_000_000_000,
+            model_id: Some("base".to_string()),
+            duration_ms: Some(1200),
+            retry: false,
+            diff: None,
+        }
+    }
+
+    fn passphrase_vault(passphrase: &str, entries: &[HistoryEntry]) -> EncryptedHistory {
+        let salt = new_salt();
+        let key = HistoryKey::from_passphrase(passphrase, &salt).unwrap();
+        EncryptedHistory::create(KeySource::Passphrase, &salt, &key, entries).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let entries = vec![entry("2", "second"), entry("1", "first")];
+        let vault = passphrase_vault("correct horse", &entries);
+        let key = vault.unlock(Some("correct horse")).unwrap();
+        assert_eq!(vault.decrypt(&key).unwrap(), (entries, 0));
+
+        // And through the settings file format
+        let json = serde_json::to_string(&vault).unwrap();
+        assert!(!json.contains("second"), "plaintext leaked: {json}");
+        let back: EncryptedHistory = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, vault);
+    }
+
+    #[test]
+    fn nonces_are_per_entry() {
+        let vault = passphrase_vault("pw", &[entry("1", "same"), entry("1", "same")]);
+        assert_ne!(vault.entries[0].nonce, vault.entries[1].nonce);
+        assert_ne!(vault.entries[0].data, vault.entries[1].data);
+    }
+
+    #[test]
+    fn wrong_or_missing_passphrase() {
+        let vault = passphrase_vault("right", &[entry("1", "secret")]);
+        assert_eq!(
+            vault.unlock(Some("wrong")).unwrap_err(),
+            VaultError::WrongKey
+        );
+        assert_eq!(
+            vault.unlock(None).unwrap_err(),
+            VaultError::PassphraseRequired
+        );
+
+        let other = HistoryKey::from_passphrase("right", &new_salt()).unwrap();
+        assert_eq!(vault.decrypt(&other).unwrap_err(), VaultError::WrongKey);
+    }
+
+    #[test]
+    fn damaged_entry_is_skipped() {
+        let mut vault = passphrase_vault("pw", &[entry("2", "b"), entry("1", "a")]);
+        // Flip a ciphertext byte: the tag no longer matches
+        let data = &mut vault.entries[0].data;
+        let flipped = if data.starts_with('0') { "1" } else { "0" };
+        data.replace_range(0..1, flipped);
+        vault.entries.push(Sealed {
+            nonce: "zz".into(),
+            data: "not hex".into(),
+        });
+
+        let key = vault.unlock(Some("pw")).unwrap();
+        let (entries, damaged) = vault.decrypt(&key).unwrap();
+        assert_eq!(entries, vec![entry("1", "a")]);
+        assert_eq!(damaged, 2);
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(encode_hex(&[0x00, 0xab, 0x7f]), "00ab7f");
+        assert_eq!(decode_hex("00ab7f"), Some(vec![0x00, 0xab, 0x7f]));
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("é0"), None);
+    }
+
+    #[test]
+    fn error_shape() {
+        assert_eq!(
+            serde_json::to_value(VaultError::KeyUnavailable {
+                message: "gone".into()
+            })
+            .unwrap(),
+            serde_json::json!({ "kind": "keyUnavailable", "message": "gone" })
+        );
+    }
+}
@@ -1,7 +1,29 @@
 mod audio;
+mod cli;
 mod commands;
+mod crash;
+mod debounce;
+mod degraded;
+mod errors;
+mod history_vault;
+mod listen;
+mod mic_log;
+mod model_download;
+mod model_import;
+mod model_integrity;
+mod output;
+mod paths;
+mod perf;
 mod platform;
+mod ptt;
+mod render;
+mod session;
+mod startup;
 mod state;
+mod state_events;
+mod tasks;
+mod text;
+mod watch;
 mod whisper;
 
 use tauri::{
@@ -17,37 +39,54 @@ pub use state::AppState;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // `--transcribe file.wav`: hand the file to the running instance
+    // and exit, without starting a second GUI (or whisper context).
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(path) = cli::transcribe_arg(&args) {
+        std::process::exit(cli::run_forwarded(&path));
+    }
+
+    let timings = startup::StartupTimings::start();
+
     // Initialize tracing
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
         .with(tracing_subscriber::EnvFilter::from_default_env())
+        .with(crash::LogTailLayer)
         .init();
+    // whisper.cpp's own log, so failed loads can say why
+    whisper::native_log::install();
 
-    // Check GPU availability on Windows/Linux BEFORE starting full Tauri app
-    // If Vulkan is not available, launch only the warning window
-    #[cfg(any(target_os = "windows", target_os = "linux"))]
-    {
-        if !whisper::is_vulkan_available_at_startup() {
-            tracing::warn!("Vulkan not available - launching warning window only");
-            run_vulkan_warning_mode();
-            return;
-        }
-        tracing::info!("Vulkan detected - starting full application");
-    }
-
-    run_full_app();
+    // The GPU probe used to run here, before Tauri, and cost 0.5–2 s on
+    // every launch. It now runs in the background once the tray and
+    // overlay are up — see `spawn_gpu_probe`.
+    run_full_app(timings);
 }
 
 /// Run the full application with all features
-fn run_full_app() {
+fn run_full_app(timings: startup::StartupTimings) {
     tauri::Builder::default()
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_os::init())
+        .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_dialog::init())
-        .setup(|app| {
+        .setup(move |app| {
+            timings.mark("tauri-init");
+
+            // Resolve data paths (standard / portable) before anything
+            // touches disk.
+            let data_paths = paths::init(app.handle());
+            if let Some(reason) = &data_paths.fallback_reason {
+                let _ = app.emit(
+                    "paths:fallback",
+                    serde_json::json!({ "root": data_paths.root, "reason": reason }),
+                );
+            }
+            setup_crash_reporting(app.handle());
+
             // Initialize app state. Pull persisted Settings from disk
             // so the AppState boots with the user's last-known values
             // — frontend caches sync from this on first
@@ -55,11 +94,135 @@ fn run_full_app() {
             // graceful fallback to `Settings::default()` (logged).
             let state = AppState::new();
             let persisted = crate::state::Settings::load_from_disk(app.handle());
+            state
+                .whisper
+                .set_queue_depth(persisted.transcription_queue_depth as usize);
+            state
+                .retained_audio
+                .lock()
+                .set_budget(persisted.retained_audio_mb);
             state.update_settings(|s| *s = persisted);
+            if commands::deterministic_mode(&state.get_settings()) {
+                tracing::info!("Deterministic mode: decodes are reproducible, and slower");
+                state.whisper.set_deterministic(true);
+            }
+            let handle = app.handle().clone();
+            state.tasks.set_panic_reporter(move |panic| {
+                let _ = handle.emit("task:panicked", &panic);
+            });
+            state
+                .tasks
+                .set_runtime(tauri::async_runtime::handle().inner().clone());
+            state
+                .audio_capture
+                .usage_log()
+                .set_path(data_paths.mic_usage_file());
+            state
+                .audio_capture
+                .set_rate_overrides(state.get_settings().sample_rate_overrides);
+            state
+                .audio_capture
+                .set_preferred_device(state.get_settings().preferred_device);
+            state
+                .audio_capture
+                .set_capture_application(state.get_settings().capture_application);
+            state.audio_capture.set_gain(
+                state
+                    .get_settings()
+                    .gain
+                    .clamp(audio::MIN_GAIN_DB, audio::MAX_GAIN_DB),
+            );
+            state
+                .audio_capture
+                .set_filters(state.get_settings().audio_filters);
+            state.audio_capture.set_agc(state.get_settings().agc);
+            {
+                let settings = state.get_settings();
+                let mut vad = state.vad.write();
+                vad.set_threshold(settings.speech_threshold);
+                vad.set_silence_timeout_ms(settings.silence_timeout_ms);
+                vad.set_silero_model(data_paths.models_dir().join(audio::SILERO_MODEL_FILE));
+                vad.set_backend(settings.vad_backend);
+                vad.set_adaptive(
+                    settings.adaptive_threshold.then_some(
+                        settings
+                            .noise_factor
+                            .clamp(audio::MIN_NOISE_FACTOR, audio::MAX_NOISE_FACTOR),
+                    ),
+                );
+            }
+            state.load_vad_model();
+            state
+                .audio_capture
+                .set_channel_mode(state.get_settings().channel_mode);
+            let cache_dir = data_paths.cache_dir();
+            let stale = audio::remove_stale_spills(&cache_dir);
+            if stale > 0 {
+                tracing::info!("Removed {} spilled captures left by a crash", stale);
+            }
+            state
+                .audio_capture
+                .set_spill(cache_dir, state.get_settings().capture_spill_mb);
+            let handle = app.handle().clone();
+            state.audio_capture.set_clipping_reporter(move |clipping| {
+                let _ = handle.emit("audio:clipping", clipping);
+            });
+            let handle = app.handle().clone();
+            state
+                .audio_capture
+                .set_silent_input_reporter(move |mut silent| {
+                    // Off the audio thread: asking runs `pactl`
+                    let handle = handle.clone();
+                    tauri::async_runtime::spawn_blocking(move || {
+                        silent.muted = audio::source_muted();
+                        let _ = handle.emit("audio:silent-input", silent);
+                    });
+                });
+            let handle = app.handle().clone();
+            state.audio_capture.set_spill_reporter(move |failure| {
+                let _ = handle.emit("audio:spill-failed", failure);
+            });
+            let handle = app.handle().clone();
+            state.audio_capture.set_fallback_reporter(move |fallback| {
+                let _ = handle.emit("audio:device-fallback", fallback);
+            });
+            let handle = app.handle().clone();
+            state.audio_capture.set_error_reporter(move |message| {
+                errors::report(&handle, "audio-stream", &message, None);
+            });
+            let handle = app.handle().clone();
+            state
+                .audio_capture
+                .set_rate_reporter(move |correction, overrides| {
+                    let _ = handle.emit("capture:rate-corrected", correction);
+                    // Off the audio thread: persisting touches the disk.
+                    let handle = handle.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let state = handle.state::<AppState>();
+                        state.update_settings(|s| s.sample_rate_overrides = overrides);
+                        state.settings_writer.mark_dirty();
+                    });
+                });
+            let (writer_state, handle) = (state.clone(), app.handle().clone());
+            state
+                .settings_writer
+                .set_flusher(move || writer_state.get_settings().persist(&handle));
+            state
+                .tasks
+                .spawn("settings-writer", state.settings_writer.run());
+            let ptt_device = state.get_settings().ptt_device;
             app.manage(state);
+            if let Some(binding) = ptt_device {
+                commands::start_ptt_reader(app.handle(), binding);
+            }
+            commands::start_folder_watch(app.handle());
+            commands::unlock_history_at_startup(app.handle());
+            cli::start_server(app.handle());
+            timings.mark("state");
 
             // Setup global shortcut
             setup_global_shortcut(app.handle())?;
+            timings.mark("shortcuts");
 
             // Configure overlay window with platform-specific behavior
             if let Some(window) = app.get_webview_window("main") {
@@ -76,53 +239,84 @@ fn run_full_app() {
 
             // Setup system tray
             setup_system_tray(app)?;
+            timings.mark("overlay-and-tray");
+
+            spawn_gpu_probe(app.handle(), timings.clone());
 
             tracing::info!("S2Tui initialized successfully");
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
-            commands::start_listen,
-            commands::stop_listen,
-            commands::set_model,
-            commands::set_language,
-            commands::set_shortcut,
-            commands::set_language_toggle_shortcut,
-            commands::set_model_toggle_shortcut,
-            commands::set_favorite_languages,
-            commands::set_model_languages,
-            commands::set_language_cycle_mode,
-            commands::load_whisper_model,
-            commands::is_model_loaded,
-            commands::list_required_models,
-            commands::download_model,
-            commands::check_permissions,
-            commands::request_microphone_permission,
-            commands::get_available_models,
-            commands::get_gpu_info,
-            commands::check_system_health,
-            commands::get_gpu_status,
-            commands::load_whisper_model_with_options,
-            commands::list_all_models,
-            commands::validate_custom_model,
-            commands::add_custom_model,
-            commands::remove_custom_model,
-            commands::set_model_disabled,
-            commands::get_settings,
-            commands::set_auto_copy,
-            commands::set_vulkan_warning_dismissed,
-            commands::set_welcome_dismissed,
-            commands::add_history_entry,
-            commands::clear_history,
-        ])
-        .run(tauri::generate_context!())
+        .invoke_handler(with_command_tracking(commands::handler()))
+        .build(tauri::generate_context!())
         .unwrap_or_else(|e| {
             tracing::error!("Failed to run Tauri application: {}", e);
             std::process::exit(1);
+        })
+        .run(|app, event| {
+            if let tauri::RunEvent::WindowEvent {
+                label,
+                event: tauri::WindowEvent::Destroyed,
+                ..
+            } = &event
+            {
+                // The level monitor's window is gone: nobody to show it to
+                let state = app.state::<AppState>();
+                let owner = state.level_monitor.lock().clone();
+                if owner.as_deref() == Some(label.as_str()) {
+                    commands::end_level_monitor(&state);
+                }
+            }
+            // Orderly shutdown: write the settings still waiting on the
+            // debouncer, then abort and await every tracked task so
+            // nothing is left holding the mic or a half-written file.
+            if let tauri::RunEvent::Exit = event {
+                let state = app.state::<AppState>();
+                if let Err(e) = state.settings_writer.flush() {
+                    tracing::error!("Failed to save settings on exit: {}", e);
+                }
+                tauri::async_runtime::block_on(
+                    state.tasks.shutdown(std::time::Duration::from_secs(2)),
+                );
+                state.whisper.shutdown();
+                // A capture still running may have a spill file
+                state.audio_capture.clear_buffer();
+            }
         });
 }
 
 // Window configuration is now handled by the platform module
 
+/// Record each invoked command name for crash reports before handing
+/// the call to the generated handler. What the command returns never
+/// comes back through here: failures are reported from the frontend's
+/// `invoke` (see `errors::report_command`).
+fn with_command_tracking<R: tauri::Runtime>(
+    handler: impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        crash::recorder().note_command(invoke.message.command());
+        handler(invoke)
+    }
+}
+
+/// Point the crash recorder at `<data root>/crashes/`, install the
+/// hooks, and announce reports left behind by previous runs.
+fn setup_crash_reporting(app: &tauri::AppHandle) {
+    let dir = paths::get().crashes_dir();
+    crash::recorder().set_dir(dir.clone());
+    crash::install_panic_hook();
+    #[cfg(feature = "native-crash-handler")]
+    crash::install_native_handler();
+
+    for report in crash::list_reports(&dir) {
+        tracing::info!("Crash report from a previous run: {}", report.path);
+        let _ = app.emit(
+            "crash:report-available",
+            serde_json::json!({ "path": report.path }),
+        );
+    }
+}
+
 fn setup_global_shortcut(app: &tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>> {
     use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 
@@ -169,6 +363,27 @@ fn setup_global_shortcut(app: &tauri::AppHandle) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
+/// Id of the single system-tray icon, used to look it up again later.
+const TRAY_ID: &str = "main";
+const TRAY_TOOLTIP: &str = "S2Tui - Speech to Text";
+
+/// Reflect degraded mode in the tray tooltip (or restore the default
+/// one when `reason` is `None`). No-op if the tray failed to build.
+pub(crate) fn update_tray_tooltip(
+    app: &tauri::AppHandle,
+    reason: Option<&degraded::DegradedReason>,
+) {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let text = match reason {
+            Some(r) => format!("{TRAY_TOOLTIP} (degraded: {})", r.reason),
+            None => TRAY_TOOLTIP.to_string(),
+        };
+        if let Err(e) = tray.set_tooltip(Some(text)) {
+            tracing::warn!("Failed to update tray tooltip: {}", e);
+        }
+    }
+}
+
 fn setup_system_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
     // Create tray menu
     let show_item = MenuItem::with_id(app, "show", "Show S2Tui", true, None::<&str>)?;
@@ -191,10 +406,10 @@ fn setup_system_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
     };
 
     // Build and store the tray icon
-    let _tray = TrayIconBuilder::new()
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(icon)
         .menu(&menu)
-        .tooltip("S2Tui - Speech to Text")
+        .tooltip(TRAY_TOOLTIP)
         .on_menu_event(|app, event| match event.id.as_ref() {
             "show" => {
                 if let Some(window) = app.get_webview_window("main") {
@@ -231,48 +446,78 @@ fn setup_system_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
-/// Run the application in Vulkan warning mode only.
-/// This launches a minimal Tauri app with only the vulkan-warning window,
-/// blocking further use until Vulkan is installed.
-#[cfg(any(target_os = "windows", target_os = "linux"))]
-fn run_vulkan_warning_mode() {
-    use tauri::WebviewWindowBuilder;
+/// Probe the GPU off the startup path, then report `startup:timings`.
+///
+/// On Windows/Linux the cheap loader-library check runs synchronously
+/// (it's what most launches need to know); the thorough probe — which
+/// can take seconds — runs on a blocking thread. If it fails, the app
+/// keeps running on CPU and the Vulkan warning opens as a regular
+/// window, unless the user already dismissed it for good.
+fn spawn_gpu_probe(app: &tauri::AppHandle, timings: startup::StartupTimings) {
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    {
+        let library_present = whisper::is_vulkan_library_present();
+        timings.mark("gpu-quick-check");
+        tracing::info!("Vulkan loader library present: {}", library_present);
+    }
 
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_clipboard_manager::init())
-        .plugin(tauri_plugin_os::init())
-        .plugin(tauri_plugin_process::init())
-        .setup(|app| {
-            // Create only the vulkan-warning window
-            let window = WebviewWindowBuilder::new(
-                app,
-                "vulkan-warning",
-                tauri::WebviewUrl::App("vulkan-warning.html".into()),
-            )
-            .title("S2Tui - Vulkan Required")
-            .inner_size(520.0, 620.0)
-            .min_inner_size(450.0, 500.0)
-            .resizable(true)
-            .center()
-            .decorations(true)
-            .build()?;
-
-            // Exit app when window is closed
-            let app_handle = app.handle().clone();
-            window.on_window_event(move |event| {
-                if let tauri::WindowEvent::CloseRequested { .. } = event {
-                    app_handle.exit(0);
+    let tasks = app.state::<AppState>().tasks.clone();
+    let app = app.clone();
+    tasks.spawn("gpu-probe", async move {
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        {
+            let available = tauri::async_runtime::spawn_blocking(whisper::probe_vulkan)
+                .await
+                .unwrap_or(false);
+            timings.mark("gpu-probe");
+            let _ = app.emit(
+                "gpu:probe",
+                serde_json::json!({ "vulkanAvailable": available }),
+            );
+            if available {
+                tracing::info!("Vulkan detected - GPU acceleration available");
+            } else {
+                tracing::warn!("Vulkan not available - continuing on CPU");
+                let dismissed = app
+                    .state::<AppState>()
+                    .get_settings()
+                    .vulkan_warning_dismissed;
+                if !dismissed {
+                    if let Err(e) = open_vulkan_warning_window(&app) {
+                        tracing::error!("Failed to open Vulkan warning window: {}", e);
+                    }
                 }
-            });
+            }
+        }
 
-            tracing::info!("Vulkan warning window launched");
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![commands::check_system_health,])
-        .run(tauri::generate_context!())
-        .unwrap_or_else(|e| {
-            tracing::error!("Failed to run Vulkan warning mode: {}", e);
-            std::process::exit(1);
-        });
+        let report = timings.report();
+        tracing::info!("Startup complete in {} ms", report.total_ms);
+        let _ = app.emit("startup:timings", &report);
+    });
+}
+
+/// Open the Vulkan warning as an in-app window. Unlike the old
+/// pre-Tauri gate, closing it just closes the window — the rest of the
+/// app keeps running on CPU.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn open_vulkan_warning_window(app: &tauri::AppHandle) -> tauri::Result<()> {
+    use tauri::WebviewWindowBuilder;
+
+    if let Some(window) = app.get_webview_window("vulkan-warning") {
+        return window.set_focus();
+    }
+    WebviewWindowBuilder::new(
+        app,
+        "vulkan-warning",
+        tauri::WebviewUrl::App("vulkan-warning.html".into()),
+    )
+    .title("S2Tui - Vulkan Required")
+    .inner_size(520.0, 620.0)
+    .min_inner_size(450.0, 500.0)
+    .resizable(true)
+    .center()
+    .decorations(true)
+    .build()?;
+    tracing::info!("Vulkan warning window opened");
+    Ok(())
 }
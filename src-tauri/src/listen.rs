@@ -0,0 +1,512 @@
+//! The recording lifecycle, without Tauri.
+//!
+//! `start_listen`, `stop_listen` and `cancel_transcription` used to run
+//! their checks and status changes inline, behind a command `State`.
+//! They are `AppState` methods here, so the shortcut controller and
+//! the CLI can drive a recording the same way; the commands add what
+//! needs the app: the microphone, the events and the overlay.
+//!
+//! A recording can start while earlier ones are transcribed: once a
+//! stopped recording has handed its audio over (`recording_taken`),
+//! Processing gives way to Listening, and `end_processing` returns to
+//! Idle after the last one.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use thiserror::Error;
+
+use crate::audio::MIN_RECORDING_MS;
+use crate::state::{AppState, AppStatus, StatusChange, StatusTransitionError};
+use crate::whisper::jobs::CancelToken;
+use crate::whisper::suitability::ENGLISH_ONLY_LANGUAGE_ERROR;
+use crate::whisper::ENGLISH_ONLY_TRANSLATE_ERROR;
+
+/// Error prefix returned by `start_listen` while the app is degraded.
+/// The frontend matches on it to show the degraded banner instead of a
+/// generic error toast.
+pub const DEGRADED_ERROR_CODE: &str = "app-degraded";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListenMode {
+    Toggle,
+    PushToTalk,
+    VoiceActivated,
+    /// Runs until stopped, transcribed an utterance at a time (see
+    /// `audio::utterance`).
+    Continuous,
+}
+
+impl ListenMode {
+    /// Hands-free modes that can run for a long time, whose utterances
+    /// go to the crash-recovery journal (see `crate::session`).
+    pub fn journals(&self) -> bool {
+        matches!(self, ListenMode::VoiceActivated | ListenMode::Continuous)
+    }
+
+    /// Hands-free sessions are meetings more than dictation: their
+    /// transcripts are kept verbatim (fillers included).
+    pub fn verbatim(&self) -> bool {
+        matches!(self, ListenMode::VoiceActivated | ListenMode::Continuous)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListenMode::Toggle => "toggle",
+            ListenMode::PushToTalk => "push-to-talk",
+            ListenMode::VoiceActivated => "voice-activated",
+            ListenMode::Continuous => "continuous",
+        }
+    }
+}
+
+/// Why a recording can't start. The messages are `start_listen`'s
+/// errors.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ListenRefusal {
+    /// No model could be loaded: fail fast with a stable code the
+    /// frontend can switch on instead of a confusing engine error later.
+    #[error("{}: {reason}", DEGRADED_ERROR_CODE)]
+    Degraded { reason: String },
+    /// Translation on an English-only model would only produce garbage.
+    #[error("{}", ENGLISH_ONLY_TRANSLATE_ERROR)]
+    EnglishOnlyTranslate,
+    /// Same for a non-English language on an English-only model.
+    #[error("{}", ENGLISH_ONLY_LANGUAGE_ERROR)]
+    EnglishOnlyLanguage,
+    #[error("Microphone permission required")]
+    MicrophonePermission,
+    /// A recording is already running, or the app is busy.
+    #[error(transparent)]
+    Status(#[from] StatusTransitionError),
+}
+
+/// The recordings between `begin_stop` and `end_processing`.
+#[derive(Debug, Default)]
+pub struct RecordingsInFlight {
+    /// Stopped and not yet processed.
+    recordings: usize,
+    /// The last one stopped hasn't taken its audio yet: the capture and
+    /// the VAD are still its.
+    stopping: bool,
+}
+
+/// Why there is no transcription to cancel.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CancelRefusal {
+    #[error("No transcription to cancel")]
+    NoJob,
+    #[error(transparent)]
+    Status(#[from] StatusTransitionError),
+}
+
+/// What a recording whose input device went away does next. In the
+/// `audio:error` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InputLoss {
+    /// Reopen the stream, on the default device if the preferred one is
+    /// gone. Once per recording.
+    Reopen,
+    /// Stop, and transcribe what was captured.
+    Transcribe,
+    /// Stop, with too little captured to transcribe: the app moves to
+    /// Error.
+    Fail,
+}
+
+impl InputLoss {
+    /// After `captured_ms` of audio, and a reopen already tried or not.
+    pub fn after(captured_ms: u64, reopened: bool) -> Self {
+        if !reopened {
+            InputLoss::Reopen
+        } else if captured_ms >= MIN_RECORDING_MS {
+            InputLoss::Transcribe
+        } else {
+            InputLoss::Fail
+        }
+    }
+}
+
+impl AppState {
+    /// Move to `to` through the transition table, keeping the overlay's
+    /// render state in step. Telling the frontend is the caller's part.
+    pub fn move_status(&self, to: AppStatus) -> Result<StatusChange, StatusTransitionError> {
+        let change = self.transition(to)?;
+        self.render
+            .lock()
+            .on_status(change.from, change.to, std::time::Instant::now());
+        Ok(change)
+    }
+
+    /// Why a recording can't start now, if it can't: checked before
+    /// recording rather than after, in the order the user can fix them.
+    pub fn listen_refusal(&self) -> Option<ListenRefusal> {
+        if let Some(reason) = self.degraded_reason() {
+            return Some(ListenRefusal::Degraded {
+                reason: reason.reason,
+            });
+        }
+        let english_only = self.whisper.is_multilingual() == Some(false);
+        if english_only && self.whisper.is_translating() {
+            return Some(ListenRefusal::EnglishOnlyTranslate);
+        }
+        let language = self.get_settings().language;
+        if english_only && language.to_whisper_code().is_some_and(|l| l != "en") {
+            return Some(ListenRefusal::EnglishOnlyLanguage);
+        }
+        if !self.get_permissions().microphone {
+            return Some(ListenRefusal::MicrophonePermission);
+        }
+        None
+    }
+
+    /// Start a `mode` recording: claim Listening before the device is
+    /// touched, so a second start racing this one is refused instead of
+    /// opening capture twice. Opening the device is the caller's part;
+    /// if that fails, it moves back to Idle.
+    pub fn begin_listen(&self, mode: &ListenMode) -> Result<StatusChange, ListenRefusal> {
+        if let Some(refusal) = self.listen_refusal() {
+            return Err(refusal);
+        }
+        let processing = self.processing.lock();
+        let from = self.get_status();
+        if from == AppStatus::Processing && processing.stopping {
+            return Err(StatusTransitionError {
+                from,
+                to: AppStatus::Listening,
+            }
+            .into());
+        }
+        let change = self.move_status(AppStatus::Listening)?;
+        drop(processing);
+        self.render.lock().set_listen_mode(mode.as_str());
+        self.verbatim.store(mode.verbatim(), Ordering::Relaxed);
+        Ok(change)
+    }
+
+    /// Claim Listening for a probe (language detection, noise
+    /// calibration): never behind a transcription, whose recording the
+    /// probe's would be taken for.
+    pub fn begin_probe(&self) -> Result<StatusChange, StatusTransitionError> {
+        let processing = self.processing.lock();
+        let from = self.get_status();
+        if processing.recordings > 0 {
+            return Err(StatusTransitionError {
+                from,
+                to: AppStatus::Listening,
+            });
+        }
+        self.move_status(AppStatus::Listening)
+    }
+
+    /// End the recording: Processing until `end_processing`. Refused
+    /// with no recording running (a late PTT release, a double-fired
+    /// shortcut). No new recording starts before `recording_taken`.
+    pub fn begin_stop(&self) -> Result<StatusChange, StatusTransitionError> {
+        let mut processing = self.processing.lock();
+        let change = self.move_status(AppStatus::Processing)?;
+        processing.recordings += 1;
+        processing.stopping = true;
+        Ok(change)
+    }
+
+    /// The stopped recording has taken its audio off the capture and the
+    /// VAD: the next one can start while it is transcribed.
+    pub fn recording_taken(&self) {
+        self.processing.lock().stopping = false;
+    }
+
+    /// Pause the recording: Paused until `begin_resume`. Stopping from
+    /// there is allowed.
+    pub fn begin_pause(&self) -> Result<StatusChange, StatusTransitionError> {
+        self.move_status(AppStatus::Paused)
+    }
+
+    /// Back to Listening, from Paused only (the table also lets Error
+    /// start a recording).
+    pub fn begin_resume(&self) -> Result<StatusChange, StatusTransitionError> {
+        let from = self.get_status();
+        if from != AppStatus::Paused {
+            return Err(StatusTransitionError {
+                from,
+                to: AppStatus::Listening,
+            });
+        }
+        self.move_status(AppStatus::Listening)
+    }
+
+    /// A recording is processed, whatever happened (a cancelled job
+    /// lands here too). Back to Idle after the last one, unless
+    /// something else already moved the status on: a new recording.
+    pub fn end_processing(&self) -> Option<StatusChange> {
+        let mut processing = self.processing.lock();
+        processing.recordings = processing.recordings.saturating_sub(1);
+        if processing.recordings > 0 {
+            return None;
+        }
+        processing.stopping = false;
+        match self.get_status() {
+            AppStatus::Processing | AppStatus::Cancelling => self.move_status(AppStatus::Idle).ok(),
+            _ => None,
+        }
+    }
+
+    /// Abort transcription `job_id`, or all of them: their results are
+    /// then a "cancelled" error and nothing is output. Cancelling, until
+    /// they are processed, when nothing else is under way; a recording
+    /// running meanwhile keeps its status.
+    pub fn cancel_transcription_job(
+        &self,
+        job_id: Option<u64>,
+    ) -> Result<Option<StatusChange>, CancelRefusal> {
+        let tokens: Vec<CancelToken> = self
+            .transcription_jobs
+            .lock()
+            .iter()
+            .filter(|(id, _)| job_id.is_none_or(|job_id| **id == job_id))
+            .map(|(_, token)| token.clone())
+            .collect();
+        if tokens.is_empty() {
+            return Err(CancelRefusal::NoJob);
+        }
+        let change = match self.get_status() {
+            AppStatus::Processing | AppStatus::Cancelling => {
+                Some(self.move_status(AppStatus::Cancelling)?)
+            }
+            _ => None,
+        };
+        for token in tokens {
+            token.cancel();
+        }
+        Ok(change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Permissions;
+    use crate::whisper::load_failure::LoadFailureKind;
+
+    fn ready() -> AppState {
+        let state = AppState::new();
+        state.set_permissions(Permissions { microphone: true });
+        state
+    }
+
+    #[test]
+    fn listening_claims_the_status_once() {
+        let state = ready();
+        let change = state.begin_listen(&ListenMode::VoiceActivated).unwrap();
+        assert_eq!(
+            change,
+            StatusChange {
+                from: AppStatus::Idle,
+                to: AppStatus::Listening
+            }
+        );
+        assert!(state.verbatim.load(Ordering::Relaxed));
+        assert_eq!(
+            crate::render::snapshot(&state).listen_mode.as_deref(),
+            Some("voice-activated")
+        );
+
+        // A second start while listening
+        let refusal = state.begin_listen(&ListenMode::Toggle).unwrap_err();
+        assert!(matches!(refusal, ListenRefusal::Status(_)));
+        assert!(state.verbatim.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn refusals_leave_the_status_alone() {
+        let state = AppState::new();
+        assert_eq!(
+            state.begin_listen(&ListenMode::Toggle),
+            Err(ListenRefusal::MicrophonePermission)
+        );
+        assert_eq!(
+            ListenRefusal::MicrophonePermission.to_string(),
+            "Microphone permission required"
+        );
+
+        state.set_permissions(Permissions { microphone: true });
+        state.record_load_failure(LoadFailureKind::ModelCorrupt, "bad magic");
+        let refusal = state.begin_listen(&ListenMode::Toggle).unwrap_err();
+        assert_eq!(refusal.to_string(), "app-degraded: bad magic");
+        assert_eq!(state.get_status(), AppStatus::Degraded);
+        assert_eq!(crate::render::snapshot(&state).listen_mode, None);
+    }
+
+    #[test]
+    fn stopping_processes_then_idles() {
+        let state = ready();
+        assert!(state.begin_stop().is_err());
+        assert_eq!(state.end_processing(), None);
+
+        state.begin_listen(&ListenMode::PushToTalk).unwrap();
+        assert!(!state.verbatim.load(Ordering::Relaxed));
+        state.begin_stop().unwrap();
+        assert_eq!(state.get_status(), AppStatus::Processing);
+        // A stop that arrives after the recording already ended
+        assert!(state.begin_stop().is_err());
+        assert_eq!(
+            state.end_processing(),
+            Some(StatusChange {
+                from: AppStatus::Processing,
+                to: AppStatus::Idle
+            })
+        );
+        assert_eq!(crate::render::snapshot(&state).listen_mode, None);
+        assert_eq!(state.end_processing(), None);
+    }
+
+    #[test]
+    fn a_load_failure_waits_for_the_session_to_end() {
+        let state = ready();
+        state.begin_listen(&ListenMode::Toggle).unwrap();
+        let (reason, change) =
+            state.record_load_failure(LoadFailureKind::ModelCorrupt, "bad magic");
+        assert!(reason.is_some());
+        assert_eq!(change, None);
+        assert_eq!(state.get_status(), AppStatus::Listening);
+        state.begin_stop().unwrap();
+        assert_eq!(
+            state.end_processing(),
+            Some(StatusChange {
+                from: AppStatus::Processing,
+                to: AppStatus::Degraded
+            })
+        );
+        assert_eq!(state.get_status(), AppStatus::Degraded);
+    }
+
+    #[test]
+    fn pausing_keeps_the_recording() {
+        let state = ready();
+        assert!(state.begin_pause().is_err());
+        assert!(state.begin_resume().is_err());
+
+        state.begin_listen(&ListenMode::Toggle).unwrap();
+        assert!(state.begin_resume().is_err());
+        state.begin_pause().unwrap();
+        assert!(state.get_status().is_recording());
+        assert!(state.begin_pause().is_err());
+        assert_eq!(
+            state.begin_resume(),
+            Ok(StatusChange {
+                from: AppStatus::Paused,
+                to: AppStatus::Listening
+            })
+        );
+
+        // Stopped while paused
+        state.begin_pause().unwrap();
+        state.begin_stop().unwrap();
+        assert!(!state.get_status().is_recording());
+        assert!(state.begin_resume().is_err());
+        assert_eq!(state.end_processing().map(|c| c.to), Some(AppStatus::Idle));
+    }
+
+    #[test]
+    fn a_lost_input_is_reopened_once() {
+        assert_eq!(InputLoss::after(0, false), InputLoss::Reopen);
+        assert_eq!(InputLoss::after(60_000, false), InputLoss::Reopen);
+        // What was captured is worth transcribing...
+        assert_eq!(InputLoss::after(60_000, true), InputLoss::Transcribe);
+        assert_eq!(
+            InputLoss::after(MIN_RECORDING_MS, true),
+            InputLoss::Transcribe
+        );
+        // ...unless it would be skipped as too short
+        assert_eq!(
+            InputLoss::after(MIN_RECORDING_MS - 1, true),
+            InputLoss::Fail
+        );
+        assert_eq!(
+            serde_json::to_value(InputLoss::Transcribe).unwrap(),
+            "transcribe"
+        );
+    }
+
+    #[test]
+    fn cancelling_needs_a_job() {
+        let state = ready();
+        assert_eq!(
+            state.cancel_transcription_job(None),
+            Err(CancelRefusal::NoJob)
+        );
+
+        state.begin_listen(&ListenMode::Toggle).unwrap();
+        state.begin_stop().unwrap();
+        state.recording_taken();
+        let token = CancelToken::default();
+        state.transcription_jobs.lock().insert(1, token.clone());
+        assert_eq!(
+            state.cancel_transcription_job(Some(2)),
+            Err(CancelRefusal::NoJob)
+        );
+        state.cancel_transcription_job(Some(1)).unwrap();
+        assert!(token.is_cancelled());
+        assert_eq!(state.get_status(), AppStatus::Cancelling);
+        // Already cancelling
+        assert!(matches!(
+            state.cancel_transcription_job(None),
+            Err(CancelRefusal::Status(_))
+        ));
+        assert_eq!(state.end_processing().map(|c| c.to), Some(AppStatus::Idle));
+    }
+
+    #[test]
+    fn a_recording_starts_once_the_last_one_is_taken() {
+        let state = ready();
+        state.begin_listen(&ListenMode::PushToTalk).unwrap();
+        state.begin_stop().unwrap();
+        // Its audio is still on the capture
+        assert!(matches!(
+            state.begin_listen(&ListenMode::PushToTalk),
+            Err(ListenRefusal::Status(_))
+        ));
+        state.recording_taken();
+        state
+            .transcription_jobs
+            .lock()
+            .insert(1, CancelToken::default());
+        assert_eq!(
+            state.begin_listen(&ListenMode::PushToTalk),
+            Ok(StatusChange {
+                from: AppStatus::Processing,
+                to: AppStatus::Listening
+            })
+        );
+        // No probe behind a transcription
+        state.begin_stop().unwrap();
+        state.recording_taken();
+        assert!(state.begin_probe().is_err());
+
+        // The first is done: the second is still processed
+        state.transcription_jobs.lock().remove(&1);
+        assert_eq!(state.end_processing(), None);
+        assert_eq!(state.get_status(), AppStatus::Processing);
+        assert_eq!(state.end_processing().map(|c| c.to), Some(AppStatus::Idle));
+        assert!(state.begin_probe().is_ok());
+    }
+
+    #[test]
+    fn a_job_is_cancelled_under_a_new_recording() {
+        let state = ready();
+        state.begin_listen(&ListenMode::Toggle).unwrap();
+        state.begin_stop().unwrap();
+        state.recording_taken();
+        let token = CancelToken::default();
+        state.transcription_jobs.lock().insert(7, token.clone());
+        state.begin_listen(&ListenMode::Toggle).unwrap();
+
+        assert_eq!(state.cancel_transcription_job(None), Ok(None));
+        assert!(token.is_cancelled());
+        assert_eq!(state.get_status(), AppStatus::Listening);
+        // The cancelled job ends under the recording
+        assert_eq!(state.end_processing(), None);
+        assert_eq!(state.get_status(), AppStatus::Listening);
+    }
+}
@@ -0,0 +1,397 @@
+//! Microphone usage log, for privacy review.
+//!
+//! Every capture session — including ones that were discarded or never
+//! transcribed — leaves a record of when the microphone was open, what
+//! opened it and how much audio was captured. The log holds no text, so
+//! it is written in privacy mode too, and it is separate from the
+//! transcript history.
+//!
+//! Storage is an append-only JSONL file: an `opened` line when the input
+//! stream starts, a `closed` line when it stops, and a `transcribed`
+//! line if the audio went to Whisper. Lines are folded back into one
+//! `MicUsageEntry` per session on read; a session whose `closed` line is
+//! missing (crash while recording) is reported with no end time. When
+//! the file grows past `MAX_LOG_BYTES` it is rotated to `<name>.1`,
+//! replacing the previous rotation.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Size at which the log is rotated. At ~150 bytes per session that is
+/// well over a thousand sessions per file.
+pub const MAX_LOG_BYTES: u64 = 256 * 1024;
+
+/// One line of the log file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+enum LogLine {
+    Opened {
+        id: u64,
+        /// Unix time in ms.
+        started_at: i64,
+        trigger: String,
+    },
+    Closed {
+        id: u64,
+        ended_at: i64,
+        duration_ms: u64,
+        bytes_captured: u64,
+    },
+    Transcribed {
+        id: u64,
+    },
+}
+
+/// One capture session.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MicUsageEntry {
+    /// Unix time in ms.
+    pub started_at: i64,
+    /// `None` when the app exited without closing the stream.
+    pub ended_at: Option<i64>,
+    pub duration_ms: Option<u64>,
+    /// What opened the microphone (listen mode).
+    pub trigger: String,
+    pub transcribed: bool,
+    pub bytes_captured: u64,
+}
+
+/// Time range for `get_mic_usage_log`, in Unix ms. Sessions that started
+/// in `[from, to)` are returned; either bound may be omitted.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRange {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl UsageRange {
+    fn contains(&self, at: i64) -> bool {
+        self.from.is_none_or(|from| at >= from) && self.to.is_none_or(|to| at < to)
+    }
+}
+
+#[derive(Debug, Default)]
+struct LogState {
+    path: Option<PathBuf>,
+    /// Session whose stream is open: id and when it opened.
+    open: Option<(u64, Instant)>,
+    /// Last closed session, for `mark_transcribed`.
+    last_closed: Option<u64>,
+}
+
+/// The log. Does nothing until `set_path` is called, so the capture code
+/// runs unchanged in tests.
+#[derive(Debug, Default)]
+pub struct MicUsageLog {
+    state: Mutex<LogState>,
+}
+
+impl MicUsageLog {
+    pub fn set_path(&self, path: PathBuf) {
+        self.state.lock().path = Some(path);
+    }
+
+    /// The input stream opened.
+    pub fn opened(&self, trigger: &str) {
+        let mut state = self.state.lock();
+        let started_at = now_ms();
+        // Unix ms doubles as the id; bump on the (sub-ms) collision.
+        let id = match state.last_closed {
+            Some(last) if last >= started_at as u64 => last + 1,
+            _ => started_at as u64,
+        };
+        state.open = Some((id, Instant::now()));
+        let Some(path) = state.path.clone() else {
+            return;
+        };
+        drop(state);
+        rotate_if_needed(&path, MAX_LOG_BYTES);
+        append(
+            &path,
+            &LogLine::Opened {
+                id,
+                started_at,
+                trigger: trigger.to_string(),
+            },
+        );
+    }
+
+    /// The input stream closed after capturing `bytes_captured` bytes.
+    pub fn closed(&self, bytes_captured: u64) {
+        let mut state = self.state.lock();
+        let Some((id, opened)) = state.open.take() else {
+            return;
+        };
+        state.last_closed = Some(id);
+        let Some(path) = state.path.clone() else {
+            return;
+        };
+        drop(state);
+        append(
+            &path,
+            &LogLine::Closed {
+                id,
+                ended_at: now_ms(),
+                duration_ms: opened.elapsed().as_millis() as u64,
+                bytes_captured,
+            },
+        );
+    }
+
+    /// The last closed session's audio was transcribed.
+    pub fn mark_transcribed(&self) {
+        let state = self.state.lock();
+        let (Some(id), Some(path)) = (state.last_closed, state.path.clone()) else {
+            return;
+        };
+        drop(state);
+        append(&path, &LogLine::Transcribed { id });
+    }
+
+    /// Sessions in `range`, oldest first.
+    pub fn query(&self, range: UsageRange) -> Vec<MicUsageEntry> {
+        let Some(path) = self.state.lock().path.clone() else {
+            return Vec::new();
+        };
+        let mut lines = read_lines(&rotated_path(&path));
+        lines.extend(read_lines(&path));
+        fold(lines)
+            .into_iter()
+            .filter(|entry| range.contains(entry.started_at))
+            .collect()
+    }
+
+    /// Delete the log, rotation included.
+    pub fn clear(&self) -> std::io::Result<()> {
+        let Some(path) = self.state.lock().path.clone() else {
+            return Ok(());
+        };
+        for file in [rotated_path(&path), path] {
+            match std::fs::remove_file(&file) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".1");
+    PathBuf::from(name)
+}
+
+fn rotate_if_needed(path: &Path, max_bytes: u64) {
+    let too_big = std::fs::metadata(path).is_ok_and(|m| m.len() >= max_bytes);
+    if too_big {
+        if let Err(e) = std::fs::rename(path, rotated_path(path)) {
+            tracing::warn!("Failed to rotate mic usage log: {}", e);
+        }
+    }
+}
+
+/// Append one line. Failures are logged, never surfaced: losing an
+/// audit line must not stop a recording.
+fn append(path: &Path, line: &LogLine) {
+    let result = (|| {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = File::options().create(true).append(true).open(path)?;
+        let mut json = serde_json::to_string(line)?;
+        json.push('\n');
+        file.write_all(json.as_bytes())
+    })();
+    if let Err(e) = result {
+        tracing::warn!("Failed to write mic usage log: {}", e);
+    }
+}
+
+/// Parse a log file, skipping unreadable lines (a write cut short by a
+/// crash). A missing file is an empty log.
+fn read_lines(path: &Path) -> Vec<LogLine> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+/// Fold the lines into one entry per session, in order of opening.
+/// `closed`/`transcribed` lines whose `opened` line was rotated away are
+/// dropped.
+fn fold(lines: Vec<LogLine>) -> Vec<MicUsageEntry> {
+    let mut entries: Vec<(u64, MicUsageEntry)> = Vec::new();
+    for line in lines {
+        match line {
+            LogLine::Opened {
+                id,
+                started_at,
+                trigger,
+            } => entries.push((
+                id,
+                MicUsageEntry {
+                    started_at,
+                    ended_at: None,
+                    duration_ms: None,
+                    trigger,
+                    transcribed: false,
+                    bytes_captured: 0,
+                },
+            )),
+            LogLine::Closed {
+                id,
+                ended_at,
+                duration_ms,
+                bytes_captured,
+            } => {
+                if let Some((_, entry)) = entries.iter_mut().rev().find(|(i, _)| *i == id) {
+                    entry.ended_at = Some(ended_at);
+                    entry.duration_ms = Some(duration_ms);
+                    entry.bytes_captured = bytes_captured;
+                }
+            }
+            LogLine::Transcribed { id } => {
+                if let Some((_, entry)) = entries.iter_mut().rev().find(|(i, _)| *i == id) {
+                    entry.transcribed = true;
+                }
+            }
+        }
+    }
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_in(dir: &Path) -> MicUsageLog {
+        let log = MicUsageLog::default();
+        log.set_path(dir.join("mic-usage.jsonl"));
+        log
+    }
+
+    #[test]
+    fn sessions_are_logged_open_to_close() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = log_in(dir.path());
+
+        log.opened("toggle");
+        log.closed(64_000);
+        log.mark_transcribed();
+        log.opened("push-to-talk");
+        log.closed(3_200);
+
+        let entries = log.query(UsageRange::default());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].trigger, "toggle");
+        assert_eq!(entries[0].bytes_captured, 64_000);
+        assert!(entries[0].transcribed);
+        assert!(entries[0].ended_at.is_some());
+        assert_eq!(entries[1].trigger, "push-to-talk");
+        assert!(!entries[1].transcribed, "discarded session is still logged");
+    }
+
+    #[test]
+    fn unclosed_session_has_no_end() {
+        let dir = tempfile::tempdir().unwrap();
+        log_in(dir.path()).opened("voice-activated");
+
+        // A fresh log (app restart) still reads the crashed session
+        let entries = log_in(dir.path()).query(UsageRange::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].ended_at, None);
+        assert_eq!(entries[0].duration_ms, None);
+    }
+
+    #[test]
+    fn range_queries() {
+        let opened = |id: u64| LogLine::Opened {
+            id,
+            started_at: id as i64,
+            trigger: "toggle".into(),
+        };
+        let entries = fold(vec![opened(1000), opened(2000), opened(3000)]);
+        let starts = |range: UsageRange| -> Vec<i64> {
+            entries
+                .iter()
+                .filter(|e| range.contains(e.started_at))
+                .map(|e| e.started_at)
+                .collect()
+        };
+        assert_eq!(starts(UsageRange::default()), vec![1000, 2000, 3000]);
+        let range = UsageRange {
+            from: Some(2000),
+            to: None,
+        };
+        assert_eq!(starts(range), vec![2000, 3000]);
+        let range = UsageRange {
+            from: None,
+            to: Some(2000),
+        };
+        assert_eq!(starts(range), vec![1000], "`to` is exclusive");
+        let range = UsageRange {
+            from: Some(1500),
+            to: Some(2500),
+        };
+        assert_eq!(starts(range), vec![2000]);
+    }
+
+    #[test]
+    fn rotation_keeps_one_previous_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = log_in(dir.path());
+        let path = dir.path().join("mic-usage.jsonl");
+
+        log.opened("toggle");
+        log.closed(1);
+        rotate_if_needed(&path, 1);
+        assert!(rotated_path(&path).exists());
+        assert!(!path.exists());
+        log.opened("toggle");
+        log.closed(2);
+
+        // Both files are read
+        let entries = log.query(UsageRange::default());
+        assert_eq!(
+            entries.iter().map(|e| e.bytes_captured).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        log.clear().unwrap();
+        assert!(log.query(UsageRange::default()).is_empty());
+        assert!(!rotated_path(&path).exists());
+    }
+
+    #[test]
+    fn torn_last_line_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = log_in(dir.path());
+        log.opened("toggle");
+        log.closed(10);
+        let path = dir.path().join("mic-usage.jsonl");
+        let mut file = File::options().append(true).open(&path).unwrap();
+        file.write_all(b"{\"type\":\"opened\",\"id\":9").unwrap();
+
+        assert_eq!(log.query(UsageRange::default()).len(), 1);
+    }
+}
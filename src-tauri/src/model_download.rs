@@ -0,0 +1,648 @@
+//! Where model downloads come from, how they fail, and how they are
+//! cancelled.
+//!
+//! The built-in models come from the pinned `models-v1` release with a
+//! checksum (`MODEL_REGISTRY` in `commands`); any other whisper.cpp
+//! model is fetched by name from the official `ggerganov/whisper.cpp`
+//! repository on Hugging Face. The streaming itself lives in
+//! `commands::download_model`: the response goes to `<filename>.part`,
+//! renamed once complete, so a `ggml-*.bin` on disk is always whole.
+//!
+//! A download that stops short (the network drops, it is cancelled,
+//! the app quits) keeps its `.part` when the server gave the file a
+//! strong ETag, recorded with the total size in a `<filename>.part.json`
+//! sidecar (`PartialMeta`). The next `download_model` of the model asks
+//! for the rest with a `Range` request, `If-Range` the ETag: a server
+//! whose file changed since answers with the whole new file instead,
+//! and the download starts over. `pending_in` lists what can resume.
+//!
+//! No HTTP here: the resume decisions take the headers as strings, so
+//! they are tested without a server.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::Notify;
+
+use crate::whisper::jobs::CancelToken;
+use crate::whisper::model_files;
+
+/// Files of the official whisper.cpp repository.
+pub const HF_REPO_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+const SIDECAR_SUFFIX: &str = ".part.json";
+
+/// A model download failed. Returned by `download_model` and carried
+/// as `error` by `model:download:error`.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum DownloadError {
+    #[error("Unknown model id: {model}")]
+    UnknownModel { model: String },
+    #[error("{model} is already downloading")]
+    AlreadyRunning { model: String },
+    #[error("Network error: {message}")]
+    Network { message: String },
+    #[error("Model not found at {url}")]
+    NotFound { url: String },
+    #[error("HTTP {status} from {url}")]
+    Http { status: u16, url: String },
+    #[error("Not enough disk space for the model: {message}")]
+    DiskFull { message: String },
+    #[error("Disk error: {message}")]
+    Disk { message: String },
+    #[error("Checksum mismatch (expected {expected}, got {actual})")]
+    Checksum { expected: String, actual: String },
+    #[error("Download cancelled")]
+    Cancelled,
+}
+
+impl DownloadError {
+    /// The error for a response with `status`; `None` on success.
+    pub fn from_status(status: u16, url: &str) -> Option<Self> {
+        match status {
+            200..=299 => None,
+            404 => Some(Self::NotFound {
+                url: url.to_string(),
+            }),
+            _ => Some(Self::Http {
+                status,
+                url: url.to_string(),
+            }),
+        }
+    }
+
+    /// Whether the `.part` file is worth keeping for a resume: the
+    /// transfer stopped, but what arrived is good.
+    pub fn keeps_partial(&self) -> bool {
+        match self {
+            Self::Network { .. } | Self::Cancelled | Self::DiskFull { .. } => true,
+            // The server is down for now
+            Self::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Classify a file error, `doing` saying what failed.
+    pub fn disk(e: &io::Error, doing: &str) -> Self {
+        let message = format!("{doing}: {e}");
+        if is_disk_full(e) {
+            Self::DiskFull { message }
+        } else {
+            Self::Disk { message }
+        }
+    }
+}
+
+fn is_disk_full(e: &io::Error) -> bool {
+    // ENOSPC, ERROR_DISK_FULL and ERROR_HANDLE_DISK_FULL all map here
+    e.kind() == io::ErrorKind::StorageFull
+}
+
+/// What to fetch for a model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelSource {
+    pub filename: String,
+    pub url: String,
+    /// Hex SHA-256 to verify against, when known.
+    pub sha256: Option<String>,
+    /// For progress when the response has no Content-Length.
+    pub size_bytes: Option<u64>,
+}
+
+impl ModelSource {
+    /// `ggml-{id}.bin` from the whisper.cpp repository: `base.en`,
+    /// `medium`, `large-v3-turbo-q5_0`, … `None` for ids that aren't a
+    /// model filename stem.
+    pub fn hugging_face(id: &str) -> Option<Self> {
+        let valid = id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"-._".contains(&b))
+            && !id.starts_with('.');
+        let filename = format!("ggml-{id}.bin");
+        (valid && model_files::parse_filename(&filename).is_some()).then(|| Self {
+            url: format!("{HF_REPO_URL}/{filename}"),
+            filename,
+            sha256: None,
+            size_bytes: None,
+        })
+    }
+
+    /// Where the download is written until it is complete.
+    pub fn partial_filename(&self) -> String {
+        format!("{}.part", self.filename)
+    }
+
+    /// Where what it takes to resume the `.part` file is kept.
+    pub fn sidecar_filename(&self) -> String {
+        format!("{}{SIDECAR_SUFFIX}", self.filename)
+    }
+}
+
+/// The `.part.json` sidecar of an interrupted download: which file the
+/// `.part` is the start of.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialMeta {
+    pub model: String,
+    pub url: String,
+    /// Strong ETag of the file; without one there is no safe resume
+    /// and no sidecar.
+    pub etag: String,
+    pub total_bytes: Option<u64>,
+}
+
+impl PartialMeta {
+    /// The sidecar at `path`; `None` when missing or unreadable, which
+    /// only costs a download from scratch.
+    pub fn load(path: &Path) -> Option<Self> {
+        serde_json::from_slice(&fs::read(path).ok()?).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec(self).map_err(io::Error::other)?)
+    }
+}
+
+/// `etag` if it is a strong one: a weak ETag (`W/"…"`) can't go in an
+/// `If-Range`.
+pub fn strong_etag(etag: Option<&str>) -> Option<&str> {
+    etag.filter(|etag| !etag.starts_with("W/"))
+}
+
+/// Where a download picks up: `offset` bytes into the file tagged `etag`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resume {
+    pub offset: u64,
+    pub etag: String,
+}
+
+/// Where the download of `source` resumes, given its sidecar and the
+/// length of its `.part` file; `None` to start from scratch. A part
+/// as long as the whole file failed its checksum or its rename: it
+/// starts over too.
+pub fn resume_point(
+    meta: Option<&PartialMeta>,
+    source: &ModelSource,
+    part_len: Option<u64>,
+) -> Option<Resume> {
+    let meta = meta.filter(|meta| meta.url == source.url)?;
+    let offset = part_len.filter(|&len| len > 0)?;
+    if meta.total_bytes.is_some_and(|total| offset >= total) {
+        return None;
+    }
+    Some(Resume {
+        offset,
+        etag: meta.etag.clone(),
+    })
+}
+
+/// `(start, total)` of a `Content-Range: bytes start-end/total` header;
+/// the total is `None` when the server sends `*`.
+pub fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (span, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let start = span.split_once('-')?.0.trim().parse().ok()?;
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+    Some((start, total))
+}
+
+/// What a response means for the `.part` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Continuation {
+    /// The rest of the file, from `offset`: append. `total_bytes` is
+    /// the whole file's.
+    Append {
+        offset: u64,
+        total_bytes: Option<u64>,
+    },
+    /// The whole file, including when it changed since the `.part`
+    /// started (`If-Range`): write it from scratch.
+    Restart { total_bytes: Option<u64> },
+    /// Not the range asked for, or none can be served (416): ask again
+    /// for the whole file.
+    Refetch,
+}
+
+/// Read the response to a request for `url`, ranged when `resume`.
+pub fn continuation(
+    resume: Option<&Resume>,
+    status: u16,
+    content_range: Option<&str>,
+    etag: Option<&str>,
+    content_length: Option<u64>,
+    url: &str,
+) -> Result<Continuation, DownloadError> {
+    if let Some(resume) = resume {
+        match status {
+            416 => return Ok(Continuation::Refetch),
+            206 => {
+                let same_file = etag.is_none_or(|etag| etag == resume.etag);
+                return Ok(match content_range.and_then(parse_content_range) {
+                    Some((start, total)) if start == resume.offset && same_file => {
+                        Continuation::Append {
+                            offset: resume.offset,
+                            total_bytes: total
+                                .or(content_length.map(|length| resume.offset + length)),
+                        }
+                    }
+                    _ => Continuation::Refetch,
+                });
+            }
+            _ => {}
+        }
+    }
+    match DownloadError::from_status(status, url) {
+        Some(error) => Err(error),
+        None => Ok(Continuation::Restart {
+            total_bytes: content_length,
+        }),
+    }
+}
+
+/// A hasher fed with the `.part` file so far, to go on with the rest.
+pub fn hash_partial(path: &Path) -> io::Result<Sha256> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hasher)
+}
+
+/// An interrupted download `download_model` resumes. Frontend mirror:
+/// `PendingDownload`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingDownload {
+    pub model: String,
+    pub filename: String,
+    pub bytes_downloaded: u64,
+    pub total_bytes: Option<u64>,
+}
+
+/// The downloads interrupted in the models directory `dir`, by
+/// filename: every sidecar whose `.part` is still there.
+pub fn pending_in(dir: &Path) -> Vec<PendingDownload> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut pending: Vec<PendingDownload> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let filename = name.strip_suffix(SIDECAR_SUFFIX)?.to_string();
+            let meta = PartialMeta::load(&entry.path())?;
+            let part = fs::metadata(dir.join(format!("{filename}.part"))).ok()?;
+            Some(PendingDownload {
+                model: meta.model,
+                filename,
+                bytes_downloaded: part.len(),
+                total_bytes: meta.total_bytes,
+            })
+        })
+        .collect();
+    pending.sort_by(|a, b| a.filename.cmp(&b.filename));
+    pending
+}
+
+/// Downloads in flight, one per model.
+#[derive(Clone, Default)]
+pub struct Downloads(Arc<Mutex<HashMap<String, DownloadTicket>>>);
+
+impl Downloads {
+    /// Register a download of `model`; it ends when the ticket drops.
+    pub fn begin(&self, model: &str) -> Result<DownloadGuard, DownloadError> {
+        let mut running = self.0.lock();
+        if running.contains_key(model) {
+            return Err(DownloadError::AlreadyRunning {
+                model: model.to_string(),
+            });
+        }
+        let ticket = DownloadTicket::default();
+        running.insert(model.to_string(), ticket.clone());
+        Ok(DownloadGuard {
+            model: model.to_string(),
+            ticket,
+            downloads: self.clone(),
+        })
+    }
+
+    pub fn is_running(&self, model: &str) -> bool {
+        self.0.lock().contains_key(model)
+    }
+
+    /// Cancel the download of `model`; `false` when none is running.
+    pub fn cancel(&self, model: &str) -> bool {
+        match self.0.lock().get(model) {
+            Some(ticket) => {
+                ticket.token.cancel();
+                ticket.wake.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct DownloadTicket {
+    token: CancelToken,
+    wake: Arc<Notify>,
+}
+
+/// A running download, registered in `Downloads` until dropped.
+pub struct DownloadGuard {
+    model: String,
+    ticket: DownloadTicket,
+    downloads: Downloads,
+}
+
+impl DownloadGuard {
+    pub fn is_cancelled(&self) -> bool {
+        self.ticket.token.is_cancelled()
+    }
+
+    /// Resolves once the download is cancelled.
+    pub async fn cancelled(&self) {
+        // `notify_one` keeps a permit when nobody waits yet
+        while !self.is_cancelled() {
+            self.ticket.wake.notified().await;
+        }
+    }
+}
+
+impl Drop for DownloadGuard {
+    fn drop(&mut self) {
+        self.downloads.0.lock().remove(&self.model);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn hugging_face_urls() {
+        let source = ModelSource::hugging_face("large-v3-turbo-q5_0").unwrap();
+        assert_eq!(source.filename, "ggml-large-v3-turbo-q5_0.bin");
+        assert_eq!(
+            source.url,
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3-turbo-q5_0.bin"
+        );
+        assert_eq!(
+            source.partial_filename(),
+            "ggml-large-v3-turbo-q5_0.bin.part"
+        );
+        assert_eq!(source.sha256, None);
+        assert!(ModelSource::hugging_face("base.en").is_some());
+    }
+
+    #[test]
+    fn ids_that_arent_filenames_are_refused() {
+        for id in [
+            "",
+            "../../etc/passwd",
+            "a/b",
+            "Medium",
+            "small bin",
+            ".hidden",
+            "-q5_0",
+        ] {
+            assert_eq!(ModelSource::hugging_face(id), None, "{id}");
+        }
+    }
+
+    #[test]
+    fn http_statuses() {
+        let url = "https://example.invalid/ggml-x.bin";
+        assert_eq!(DownloadError::from_status(200, url), None);
+        assert_eq!(
+            DownloadError::from_status(404, url),
+            Some(DownloadError::NotFound { url: url.into() })
+        );
+        assert_eq!(
+            DownloadError::from_status(503, url),
+            Some(DownloadError::Http {
+                status: 503,
+                url: url.into()
+            })
+        );
+    }
+
+    #[test]
+    fn disk_full_is_told_apart() {
+        let full = io::Error::from(io::ErrorKind::StorageFull);
+        assert!(matches!(
+            DownloadError::disk(&full, "write"),
+            DownloadError::DiskFull { .. }
+        ));
+        #[cfg(unix)]
+        assert!(matches!(
+            DownloadError::disk(&io::Error::from_raw_os_error(28), "write"),
+            DownloadError::DiskFull { .. }
+        ));
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(matches!(
+            DownloadError::disk(&denied, "open"),
+            DownloadError::Disk { .. }
+        ));
+    }
+
+    fn meta(source: &ModelSource) -> PartialMeta {
+        PartialMeta {
+            model: "large-v3".into(),
+            url: source.url.clone(),
+            etag: "\"abc\"".into(),
+            total_bytes: Some(1000),
+        }
+    }
+
+    #[test]
+    fn resumes_only_what_it_can() {
+        let source = ModelSource::hugging_face("large-v3").unwrap();
+        let meta = meta(&source);
+        assert_eq!(
+            resume_point(Some(&meta), &source, Some(400)),
+            Some(Resume {
+                offset: 400,
+                etag: "\"abc\"".into()
+            })
+        );
+        assert_eq!(resume_point(None, &source, Some(400)), None);
+        for part_len in [None, Some(0), Some(1000)] {
+            assert_eq!(resume_point(Some(&meta), &source, part_len), None);
+        }
+        // The sidecar of another source's file
+        let other = ModelSource::hugging_face("medium").unwrap();
+        assert_eq!(resume_point(Some(&meta), &other, Some(400)), None);
+
+        assert_eq!(strong_etag(Some("\"abc\"")), Some("\"abc\""));
+        assert_eq!(strong_etag(Some("W/\"abc\"")), None);
+        assert!(DownloadError::Cancelled.keeps_partial());
+        assert!(DownloadError::Http {
+            status: 503,
+            url: source.url.clone()
+        }
+        .keeps_partial());
+        assert!(!DownloadError::Checksum {
+            expected: "a".into(),
+            actual: "b".into()
+        }
+        .keeps_partial());
+    }
+
+    #[test]
+    fn content_ranges() {
+        assert_eq!(
+            parse_content_range("bytes 400-999/1000"),
+            Some((400, Some(1000)))
+        );
+        assert_eq!(parse_content_range("bytes 400-999/*"), Some((400, None)));
+        for value in ["400-999/1000", "bytes */1000", "bytes 400-999"] {
+            assert_eq!(parse_content_range(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn responses_to_a_resume() {
+        let url = "https://example.invalid/ggml-x.bin";
+        let resume = Resume {
+            offset: 400,
+            etag: "\"abc\"".into(),
+        };
+        let read =
+            |status, range, etag| continuation(Some(&resume), status, range, etag, Some(600), url);
+        assert_eq!(
+            read(206, Some("bytes 400-999/1000"), Some("\"abc\"")),
+            Ok(Continuation::Append {
+                offset: 400,
+                total_bytes: Some(1000)
+            })
+        );
+        assert_eq!(
+            read(206, Some("bytes 400-999/*"), None),
+            Ok(Continuation::Append {
+                offset: 400,
+                total_bytes: Some(1000)
+            })
+        );
+        // `If-Range` failed: the whole, changed file
+        assert_eq!(
+            read(200, None, Some("\"def\"")),
+            Ok(Continuation::Restart {
+                total_bytes: Some(600)
+            })
+        );
+        assert_eq!(
+            read(206, Some("bytes 0-599/600"), None),
+            Ok(Continuation::Refetch)
+        );
+        assert_eq!(
+            read(206, Some("bytes 400-999/1000"), Some("\"def\"")),
+            Ok(Continuation::Refetch)
+        );
+        assert_eq!(read(416, None, None), Ok(Continuation::Refetch));
+        assert_eq!(
+            continuation(None, 416, None, None, None, url),
+            Err(DownloadError::Http {
+                status: 416,
+                url: url.into()
+            })
+        );
+        assert!(matches!(
+            read(404, None, None),
+            Err(DownloadError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn pending_downloads_need_their_part() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = ModelSource::hugging_face("large-v3").unwrap();
+        meta(&source)
+            .save(&dir.path().join(source.sidecar_filename()))
+            .unwrap();
+        assert!(pending_in(dir.path()).is_empty());
+
+        fs::write(dir.path().join(source.partial_filename()), [0u8; 400]).unwrap();
+        // A part without a sidecar doesn't resume
+        fs::write(dir.path().join("ggml-medium.bin.part"), [0u8; 10]).unwrap();
+        fs::write(dir.path().join("ggml-small.bin.part.json"), "{ not json").unwrap();
+        assert_eq!(
+            pending_in(dir.path()),
+            [PendingDownload {
+                model: "large-v3".into(),
+                filename: "ggml-large-v3.bin".into(),
+                bytes_downloaded: 400,
+                total_bytes: Some(1000),
+            }]
+        );
+        assert_eq!(
+            PartialMeta::load(&dir.path().join(source.sidecar_filename())),
+            Some(meta(&source))
+        );
+        assert!(pending_in(&dir.path().join("gone")).is_empty());
+
+        let hasher = hash_partial(&dir.path().join(source.partial_filename())).unwrap();
+        assert_eq!(hasher.finalize(), Sha256::digest([0u8; 400]));
+    }
+
+    #[test]
+    fn one_download_per_model() {
+        let downloads = Downloads::default();
+        let small = downloads.begin("small").unwrap();
+        assert!(matches!(
+            downloads.begin("small"),
+            Err(DownloadError::AlreadyRunning { .. })
+        ));
+        let _base = downloads.begin("base").unwrap();
+        assert!(downloads.is_running("small"));
+        drop(small);
+        assert!(!downloads.is_running("small"));
+        assert!(downloads.begin("small").is_ok());
+        assert!(!downloads.cancel("medium"));
+    }
+
+    #[tokio::test]
+    async fn cancel_wakes_the_download() {
+        let downloads = Downloads::default();
+        let guard = downloads.begin("small").unwrap();
+        let canceller = downloads.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            assert!(canceller.cancel("small"));
+        });
+        tokio::time::timeout(Duration::from_secs(1), guard.cancelled())
+            .await
+            .expect("cancel didn't wake the download");
+        assert!(guard.is_cancelled());
+    }
+
+    #[test]
+    fn payload() {
+        let json = serde_json::to_value(DownloadError::Http {
+            status: 500,
+            url: "u".into(),
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "kind": "http", "status": 500, "url": "u" })
+        );
+        assert_eq!(
+            serde_json::to_value(DownloadError::Cancelled).unwrap(),
+            serde_json::json!({ "kind": "cancelled" })
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! Model files brought into the app's models directory.
+//!
+//! A model downloaded by hand sits in Downloads, where `model_files`
+//! never looks. `import_model` copies it (or moves it) next to the
+//! app's downloads under a name the scanner knows, `ggml-{id}.bin`.
+//! A file of that name already there is the same model when the
+//! content is the same, and the import stops there; otherwise the new
+//! one takes the next free `ggml-{id}-2.bin`, `-3`, … The copy goes
+//! through `<name>.import`, so an interrupted import never leaves a
+//! half-written model under a real name. (Not `.part`: that one is a
+//! download's, kept to resume it.)
+//!
+//! The copy reports its progress through a callback; `import_model`
+//! turns it into events.
+
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::model_integrity::{self, IntegrityError};
+use crate::whisper::model_files::{self, FoundModel};
+
+/// Copy size; also how often progress is reported.
+const COPY_CHUNK: usize = 1024 * 1024;
+/// Names tried before giving up on a free one.
+const MAX_SUFFIX: u32 = 100;
+
+/// An import that didn't happen. Frontend mirror: `ImportModelError`.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ImportError {
+    #[error("No such file: {}", path.display())]
+    NotFound { path: PathBuf },
+    /// Not a whisper model, or not a whole one.
+    #[error("{error}")]
+    Invalid { error: IntegrityError },
+    /// Nothing of the name makes a model id.
+    #[error("Can't name a model after {}", path.display())]
+    BadName { path: PathBuf },
+    #[error("{message}")]
+    Disk { message: String },
+}
+
+impl ImportError {
+    fn disk(e: &io::Error, context: &str) -> Self {
+        Self::Disk {
+            message: format!("{context}: {e}"),
+        }
+    }
+}
+
+/// `import_model` result and `model:imported` payload. Frontend
+/// mirror: `ImportedModel`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedModel {
+    pub model: FoundModel,
+    /// The same file was already there: nothing was copied.
+    pub duplicate: bool,
+}
+
+/// The name `source` is imported as: its own when it is already
+/// `ggml-….bin`, else `ggml-{stem}.bin` with the stem lowercased and
+/// anything but letters, digits, `.`, `_` and `-` made a dash.
+pub fn import_filename(source: &Path) -> Option<String> {
+    let name = source.file_name()?.to_str()?;
+    if model_files::parse_filename(name).is_some() {
+        return Some(name.to_string());
+    }
+    let stem = source.file_stem()?.to_str()?;
+    let id: String = stem
+        .strip_prefix("ggml-")
+        .unwrap_or(stem)
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '.' | '_' | '-' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '-',
+        })
+        .collect();
+    let filename = format!("ggml-{}.bin", id.trim_matches(['-', '.']));
+    model_files::parse_filename(&filename).map(|file| file.filename)
+}
+
+/// `filename` with `-{n}` after the id: before `.en` and the
+/// quantization, so both still parse.
+fn suffixed(filename: &str, n: u32) -> String {
+    let Some(file) = model_files::parse_filename(filename) else {
+        return format!("{n}-{filename}");
+    };
+    let id = match file.id.strip_suffix(".en") {
+        Some(base) => format!("{base}-{n}.en"),
+        None => format!("{}-{n}", file.id),
+    };
+    match file.quantization {
+        Some(quantization) => format!("ggml-{id}-{quantization}.bin"),
+        None => format!("ggml-{id}.bin"),
+    }
+}
+
+/// Where an import goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placement {
+    /// The file there has the same content.
+    Existing(PathBuf),
+    /// A free name.
+    New(PathBuf),
+}
+
+/// Where `source` goes in `dir` under `filename`. A taken name holding
+/// the same bytes is the answer; a different file moves the import on
+/// to the next suffix. `reserved(name)` is the SHA-256 a name is kept
+/// for though no file has it yet (a built-in, until downloaded): only
+/// that content may take it. `source` is hashed once, and only if a
+/// name is taken.
+pub fn place(
+    dir: &Path,
+    filename: &str,
+    source: &Path,
+    reserved: impl Fn(&str) -> Option<String>,
+) -> Result<Placement, ImportError> {
+    let size = std::fs::metadata(source)
+        .map_err(|e| ImportError::disk(&e, "Failed to read the model"))?
+        .len();
+    let mut source_sha256: Option<String> = None;
+    let mut sha256 = || -> Result<String, ImportError> {
+        if let Some(sha256) = &source_sha256 {
+            return Ok(sha256.clone());
+        }
+        let sha256 = model_integrity::sha256_file(source, |_, _| {})
+            .map_err(|error| ImportError::Invalid { error })?;
+        source_sha256 = Some(sha256.clone());
+        Ok(sha256)
+    };
+    for n in 1..=MAX_SUFFIX {
+        let name = if n == 1 {
+            filename.to_string()
+        } else {
+            suffixed(filename, n)
+        };
+        let path = dir.join(&name);
+        match std::fs::metadata(&path) {
+            Ok(existing) => {
+                if existing.len() == size
+                    && model_integrity::sha256_file(&path, |_, _| {}).ok() == Some(sha256()?)
+                {
+                    return Ok(Placement::Existing(path));
+                }
+            }
+            Err(_) => match reserved(&name) {
+                Some(expected) if !expected.eq_ignore_ascii_case(&sha256()?) => {}
+                _ => return Ok(Placement::New(path)),
+            },
+        }
+    }
+    Err(ImportError::Disk {
+        message: format!("No free name for {filename} in {}", dir.display()),
+    })
+}
+
+/// Copy `source` to `target` through `<target>.import`, calling
+/// `on_progress(copied, total)` after every `COPY_CHUNK`. The partial
+/// file is created anew, never reused: one already there belongs to
+/// another import, and this one fails. It is removed on failure.
+/// Blocking: a large model takes seconds.
+pub fn copy_into(
+    source: &Path,
+    target: &Path,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<(), ImportError> {
+    let mut partial = target.as_os_str().to_owned();
+    partial.push(".import");
+    let partial = PathBuf::from(partial);
+    let to = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&partial)
+        .map_err(|e| ImportError::disk(&e, "Failed to create the copy"))?;
+    let result = copy_through(source, to, &mut on_progress).and_then(|()| {
+        std::fs::rename(&partial, target)
+            .map_err(|e| ImportError::disk(&e, "Failed to finalize the copy"))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+fn copy_through(
+    source: &Path,
+    mut to: File,
+    on_progress: &mut impl FnMut(u64, u64),
+) -> Result<(), ImportError> {
+    let mut from =
+        File::open(source).map_err(|e| ImportError::disk(&e, "Failed to open the model"))?;
+    let total = from
+        .metadata()
+        .map_err(|e| ImportError::disk(&e, "Failed to read the model"))?
+        .len();
+    let mut buffer = vec![0u8; COPY_CHUNK];
+    let mut copied = 0u64;
+    loop {
+        let read = match from.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(ImportError::disk(&e, "Failed to read the model")),
+        };
+        to.write_all(&buffer[..read])
+            .map_err(|e| ImportError::disk(&e, "Failed to write the copy"))?;
+        copied += read as u64;
+        on_progress(copied, total);
+    }
+    to.sync_all()
+        .map_err(|e| ImportError::disk(&e, "Failed to write the copy"))
+}
+
+/// Move `source` to `target`: a rename on the same volume, else
+/// `copy_into` and the source removed once the copy is whole. A source
+/// that can't be removed is only logged; the model is imported.
+pub fn move_into(
+    source: &Path,
+    target: &Path,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<(), ImportError> {
+    if std::fs::rename(source, target).is_ok() {
+        let size = std::fs::metadata(target).map_or(0, |m| m.len());
+        on_progress(size, size);
+        return Ok(());
+    }
+    copy_into(source, target, on_progress)?;
+    if let Err(e) = std::fs::remove_file(source) {
+        tracing::warn!("Imported model left at {}: {}", source.display(), e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_become_model_ids() {
+        for (source, filename) in [
+            ("/dl/ggml-small.en-q8_0.bin", Some("ggml-small.en-q8_0.bin")),
+            (
+                "/dl/Whisper Large V3.bin",
+                Some("ggml-whisper-large-v3.bin"),
+            ),
+            ("/dl/ggml-medium.gguf", Some("ggml-medium.bin")),
+            ("/dl/distil_small", Some("ggml-distil_small.bin")),
+            ("/dl/--.bin", None),
+        ] {
+            assert_eq!(
+                import_filename(Path::new(source)).as_deref(),
+                filename,
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn suffixes_keep_the_parts() {
+        assert_eq!(suffixed("ggml-small.bin", 2), "ggml-small-2.bin");
+        assert_eq!(
+            suffixed("ggml-base.en-q5_1.bin", 3),
+            "ggml-base-3.en-q5_1.bin"
+        );
+        let parsed = model_files::parse_filename(&suffixed("ggml-base.en-q5_1.bin", 3)).unwrap();
+        assert!(parsed.english_only);
+        assert_eq!(parsed.quantization.as_deref(), Some("q5_1"));
+    }
+
+    #[test]
+    fn taken_names_compare_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("download.bin");
+        std::fs::write(&source, b"model a").unwrap();
+        let models = dir.path().join("models");
+        std::fs::create_dir(&models).unwrap();
+        let none = |_: &str| None;
+
+        assert_eq!(
+            place(&models, "ggml-small.bin", &source, none).unwrap(),
+            Placement::New(models.join("ggml-small.bin"))
+        );
+        // The same model already imported
+        std::fs::write(models.join("ggml-small.bin"), b"model a").unwrap();
+        assert_eq!(
+            place(&models, "ggml-small.bin", &source, none).unwrap(),
+            Placement::Existing(models.join("ggml-small.bin"))
+        );
+        // Another model of that name
+        std::fs::write(models.join("ggml-small.bin"), b"model b").unwrap();
+        assert_eq!(
+            place(&models, "ggml-small.bin", &source, none).unwrap(),
+            Placement::New(models.join("ggml-small-2.bin"))
+        );
+        // A built-in's name is for the built-in
+        let reserved = |name: &str| (name == "ggml-base.bin").then(|| "00".repeat(32));
+        assert_eq!(
+            place(&models, "ggml-base.bin", &source, reserved).unwrap(),
+            Placement::New(models.join("ggml-base-2.bin"))
+        );
+    }
+
+    #[test]
+    fn copies_with_progress_and_moves() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("download.bin");
+        std::fs::write(&source, vec![7u8; COPY_CHUNK + 10]).unwrap();
+        let target = dir.path().join("ggml-small.bin");
+
+        let mut reports = Vec::new();
+        copy_into(&source, &target, |copied, total| {
+            reports.push((copied, total))
+        })
+        .unwrap();
+        let total = COPY_CHUNK as u64 + 10;
+        assert_eq!(reports, [(COPY_CHUNK as u64, total), (total, total)]);
+        assert_eq!(
+            std::fs::read(&target).unwrap(),
+            std::fs::read(&source).unwrap()
+        );
+        assert!(!dir.path().join("ggml-small.bin.import").exists());
+
+        let moved = dir.path().join("ggml-base.bin");
+        move_into(&source, &moved, |_, _| {}).unwrap();
+        assert!(!source.exists());
+        assert_eq!(std::fs::metadata(&moved).unwrap().len(), total);
+    }
+
+    #[test]
+    fn failed_copies_leave_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("ggml-small.bin");
+        let error = copy_into(&dir.path().join("gone.bin"), &target, |_, _| {}).unwrap_err();
+        assert!(matches!(error, ImportError::Disk { .. }));
+        // Into a directory that isn't there
+        let source = dir.path().join("download.bin");
+        std::fs::write(&source, b"model").unwrap();
+        let target = dir.path().join("missing").join("ggml-small.bin");
+        assert!(copy_into(&source, &target, |_, _| {}).is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn leaves_another_copy_and_a_download_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("download.bin");
+        std::fs::write(&source, b"model").unwrap();
+        let target = dir.path().join("ggml-small.bin");
+        let download = dir.path().join("ggml-small.bin.part");
+        std::fs::write(&download, b"half a download").unwrap();
+        let other = dir.path().join("ggml-small.bin.import");
+        std::fs::write(&other, b"another import").unwrap();
+
+        assert!(copy_into(&source, &target, |_, _| {}).is_err());
+        assert_eq!(std::fs::read(&other).unwrap(), b"another import");
+        assert!(!target.exists());
+
+        std::fs::remove_file(&other).unwrap();
+        copy_into(&source, &target, |_, _| {}).unwrap();
+        assert_eq!(std::fs::read(&download).unwrap(), b"half a download");
+        assert_eq!(std::fs::read(&target).unwrap(), b"model");
+    }
+}
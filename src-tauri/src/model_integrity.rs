@@ -0,0 +1,318 @@
+//! Is a model file whole?
+//!
+//! A half-copied or half-downloaded model used to reach whisper.cpp and
+//! fail there with a ggml message nobody could act on. `quick_check`
+//! runs before every load: the ggml header (`whisper::compat`) and the
+//! size, which has to match the registry's for a built-in the app
+//! downloaded itself, or at least look like a model otherwise.
+//! `sha256_file` hashes the whole file for `verify_model`; the
+//! checksums known are the built-ins' (`MODEL_REGISTRY` in `commands`).
+//!
+//! `sha256_file` reports its progress through a callback: `verify_model`
+//! emits it, the folder watcher ignores it.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::whisper::compat::{self, ModelCompatError};
+use crate::whisper::load_failure::LoadFailureKind;
+
+/// Smaller than any whisper model: tiny quantized to q5_1, the smallest
+/// official one, is 31 MB.
+pub const MIN_MODEL_BYTES: u64 = 10 * 1024 * 1024;
+/// Read size while hashing; also how often progress is reported.
+const HASH_CHUNK: usize = 1024 * 1024;
+
+/// A model file that can't be used as it is. Returned by
+/// `verify_model`; its message is the error of a load it stopped.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum IntegrityError {
+    #[error("Model file unreadable: {message}")]
+    Unreadable { message: String },
+    #[error("{}", incomplete_message(*size_bytes, *expected_bytes))]
+    Incomplete {
+        size_bytes: u64,
+        /// The registry's size; `None` when the file is just too small
+        /// for any model.
+        expected_bytes: Option<u64>,
+    },
+    #[error("Model file corrupt or incomplete, re-download it: {reason}")]
+    Corrupt { reason: String },
+    #[error("Model format not supported by this version, update the app: {reason}")]
+    Unsupported { reason: String },
+    #[error("Model file corrupt or incomplete, re-download it: checksum mismatch (expected {expected}, got {actual})")]
+    Checksum { expected: String, actual: String },
+}
+
+fn incomplete_message(size_bytes: u64, expected_bytes: Option<u64>) -> String {
+    match expected_bytes {
+        Some(expected) => format!(
+            "Model file corrupt or incomplete, re-download it: {size_bytes} of {expected} bytes"
+        ),
+        None => {
+            format!("Model file corrupt or incomplete, re-download it: only {size_bytes} bytes")
+        }
+    }
+}
+
+impl IntegrityError {
+    fn unreadable(e: &io::Error) -> Self {
+        Self::Unreadable {
+            message: e.to_string(),
+        }
+    }
+
+    /// The same problem as a failed load.
+    pub fn load_failure_kind(&self) -> LoadFailureKind {
+        match self {
+            Self::Unreadable { .. } => LoadFailureKind::Unknown,
+            Self::Incomplete { .. } => LoadFailureKind::Truncated,
+            Self::Corrupt { .. } | Self::Checksum { .. } => LoadFailureKind::ModelCorrupt,
+            Self::Unsupported { .. } => LoadFailureKind::UnsupportedVersion,
+        }
+    }
+}
+
+/// Check `path` before a load: a whisper ggml header, and `expected_bytes`
+/// exactly when given (a built-in's registry size), else at least
+/// `MIN_MODEL_BYTES`. Reads 48 bytes.
+pub fn quick_check(path: &Path, expected_bytes: Option<u64>) -> Result<(), IntegrityError> {
+    let size_bytes = std::fs::metadata(path)
+        .map_err(|e| IntegrityError::unreadable(&e))?
+        .len();
+    compat::validate(path).map_err(|error| from_header(&error))?;
+    match expected_bytes {
+        Some(expected) if size_bytes < expected => Err(IntegrityError::Incomplete {
+            size_bytes,
+            expected_bytes: Some(expected),
+        }),
+        Some(expected) if size_bytes > expected => Err(IntegrityError::Corrupt {
+            reason: format!("{size_bytes} bytes, {expected} expected"),
+        }),
+        Some(_) => Ok(()),
+        None if size_bytes < MIN_MODEL_BYTES => Err(IntegrityError::Incomplete {
+            size_bytes,
+            expected_bytes: None,
+        }),
+        None => Ok(()),
+    }
+}
+
+fn from_header(error: &ModelCompatError) -> IntegrityError {
+    let reason = match error {
+        ModelCompatError::Unreadable { os_error } => {
+            return IntegrityError::Unreadable {
+                message: os_error.clone(),
+            }
+        }
+        ModelCompatError::Truncated { size_bytes } => {
+            return IntegrityError::Incomplete {
+                size_bytes: *size_bytes,
+                expected_bytes: None,
+            }
+        }
+        ModelCompatError::BadMagic { found_hex, .. } => {
+            format!("not a ggml model (starts with {found_hex})")
+        }
+        ModelCompatError::NotWhisper { explanation, .. } => explanation.clone(),
+        ModelCompatError::UnknownQuant { ftype } => format!("unknown quantization type {ftype}"),
+        // `validate` returns none of the others
+        other => format!("{other:?}"),
+    };
+    match LoadFailureKind::from_header(error) {
+        Some(LoadFailureKind::UnsupportedVersion) => IntegrityError::Unsupported { reason },
+        _ => IntegrityError::Corrupt { reason },
+    }
+}
+
+/// Hex SHA-256 of `path`. `on_progress(hashed, total)` is called after
+/// every `HASH_CHUNK`. Blocking: a large model takes seconds.
+pub fn sha256_file(
+    path: &Path,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<String, IntegrityError> {
+    let mut file = File::open(path).map_err(|e| IntegrityError::unreadable(&e))?;
+    let total = file
+        .metadata()
+        .map_err(|e| IntegrityError::unreadable(&e))?
+        .len();
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_CHUNK];
+    let mut hashed = 0u64;
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(IntegrityError::unreadable(&e)),
+        };
+        hasher.update(&buffer[..read]);
+        hashed += read as u64;
+        on_progress(hashed, total);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// `actual` against the known checksum, if any.
+pub fn compare(expected: Option<&str>, actual: &str) -> Result<(), IntegrityError> {
+    match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(actual) => Err(IntegrityError::Checksum {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// `verify_model` result. Frontend mirror: `ModelVerification`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelVerification {
+    pub model: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub sha256: String,
+    /// The checksum it matched; `None` when none is known for the file,
+    /// so only the header and size were checked.
+    pub expected_sha256: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// A whisper tiny header, q5_1.
+    const HPARAMS_TINY: [i32; 11] = [51865, 1500, 384, 6, 4, 448, 384, 6, 4, 80, 2009];
+
+    /// A model file of `len` bytes starting with `magic` and the tiny
+    /// header.
+    fn model_file(dir: &Path, magic: u32, hparams: [i32; 11], len: u64) -> PathBuf {
+        let path = dir.join("ggml-tiny.bin");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(&magic.to_le_bytes()).unwrap();
+        for value in hparams {
+            file.write_all(&value.to_le_bytes()).unwrap();
+        }
+        file.set_len(len).unwrap();
+        path
+    }
+
+    const GGML: u32 = 0x6767_6d6c;
+
+    #[test]
+    fn whole_files_pass() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = model_file(dir.path(), GGML, HPARAMS_TINY, 31_000_000);
+        assert_eq!(quick_check(&path, None), Ok(()));
+        assert_eq!(quick_check(&path, Some(31_000_000)), Ok(()));
+    }
+
+    #[test]
+    fn short_files_are_incomplete() {
+        let dir = tempfile::tempdir().unwrap();
+        // Cut off halfway through a built-in's download
+        let path = model_file(dir.path(), GGML, HPARAMS_TINY, 90_000_000);
+        let error = quick_check(&path, Some(190_085_487)).unwrap_err();
+        assert_eq!(
+            error,
+            IntegrityError::Incomplete {
+                size_bytes: 90_000_000,
+                expected_bytes: Some(190_085_487)
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Model file corrupt or incomplete, re-download it: 90000000 of 190085487 bytes"
+        );
+        assert_eq!(error.load_failure_kind(), LoadFailureKind::Truncated);
+        // No size known: too small for any model
+        let path = model_file(dir.path(), GGML, HPARAMS_TINY, 4096);
+        assert!(matches!(
+            quick_check(&path, None),
+            Err(IntegrityError::Incomplete {
+                size_bytes: 4096,
+                expected_bytes: None
+            })
+        ));
+        // Not even a header
+        std::fs::write(&path, b"ggml").unwrap();
+        assert!(matches!(
+            quick_check(&path, None),
+            Err(IntegrityError::Incomplete { size_bytes: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        // An HTML error page saved as the model
+        let path = dir.path().join("ggml-small.bin");
+        std::fs::write(&path, vec![b'<'; 64]).unwrap();
+        let error = quick_check(&path, None).unwrap_err();
+        assert!(matches!(error, IntegrityError::Corrupt { .. }));
+        assert!(error
+            .to_string()
+            .starts_with("Model file corrupt or incomplete, re-download it"));
+        // GGUF: a newer format
+        let path = model_file(dir.path(), 0x4655_4747, HPARAMS_TINY, 31_000_000);
+        let error = quick_check(&path, None).unwrap_err();
+        assert!(matches!(error, IntegrityError::Unsupported { .. }));
+        assert_eq!(
+            error.load_failure_kind(),
+            LoadFailureKind::UnsupportedVersion
+        );
+        // Larger than the built-in it is named after
+        let path = model_file(dir.path(), GGML, HPARAMS_TINY, 31_000_000);
+        assert!(matches!(
+            quick_check(&path, Some(30_000_000)),
+            Err(IntegrityError::Corrupt { .. })
+        ));
+        assert!(matches!(
+            quick_check(&dir.path().join("gone.bin"), None),
+            Err(IntegrityError::Unreadable { .. })
+        ));
+    }
+
+    #[test]
+    fn hashes_with_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc");
+        std::fs::write(&path, b"abc").unwrap();
+        let mut reports = Vec::new();
+        let sha256 = sha256_file(&path, |hashed, total| reports.push((hashed, total))).unwrap();
+        assert_eq!(
+            sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(reports, [(3, 3)]);
+
+        let path = model_file(dir.path(), GGML, HPARAMS_TINY, 3 * HASH_CHUNK as u64 - 5);
+        let mut reports = Vec::new();
+        sha256_file(&path, |hashed, _| reports.push(hashed)).unwrap();
+        assert_eq!(reports.len(), 3);
+        assert_eq!(*reports.last().unwrap(), 3 * HASH_CHUNK as u64 - 5);
+    }
+
+    #[test]
+    fn checksums_are_compared_when_known() {
+        let actual = "ae85e4a935d7a567bd102fe55afc16bb595bdb618e11b2fc7591bc08120411bb";
+        assert_eq!(compare(None, actual), Ok(()));
+        assert_eq!(compare(Some(&actual.to_uppercase()), actual), Ok(()));
+        let error = compare(Some("00"), actual).unwrap_err();
+        assert_eq!(error.load_failure_kind(), LoadFailureKind::ModelCorrupt);
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({ "kind": "checksum", "expected": "00", "actual": actual })
+        );
+    }
+}
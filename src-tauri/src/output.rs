@@ -0,0 +1,276 @@
+//! Transcript output coordination.
+//!
+//! With auto-copy on, every finished transcript goes to the clipboard.
+//! When dictations finish in quick succession (hands-free sessions),
+//! utterance N+1 can overwrite the clipboard before the user pasted
+//! utterance N. `OutputCoordinator` sits between the transcripts and the
+//! clipboard:
+//!
+//! - writes are spaced at least `dwell` apart; a transcript arriving
+//!   sooner waits (and, if another arrives meanwhile, the newer one wins
+//!   unless append mode is on);
+//! - in append mode, a transcript arriving within `dwell` of the
+//!   previous write is joined onto it, so one clipboard write holds both;
+//! - when a write replaces our previous one that is still on the
+//!   clipboard — so it may never have been pasted — the replaced text is
+//!   reported as superseded (`output:superseded`).
+//!
+//! The coordinator takes the time and the clipboard as arguments so the
+//! decision logic can be tested with a mock clock and clipboard.
+
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_CLIPBOARD_DWELL_MS: u32 = 1000;
+
+/// The clipboard as the coordinator sees it.
+pub trait Clipboard {
+    fn read(&self) -> Option<String>;
+    fn write(&mut self, text: &str) -> Result<(), String>;
+}
+
+/// The system clipboard, via the clipboard-manager plugin.
+pub struct SystemClipboard<'a>(pub &'a tauri::AppHandle);
+
+impl Clipboard for SystemClipboard<'_> {
+    fn read(&self) -> Option<String> {
+        use tauri_plugin_clipboard_manager::ClipboardExt;
+        self.0.clipboard().read_text().ok()
+    }
+
+    fn write(&mut self, text: &str) -> Result<(), String> {
+        use tauri_plugin_clipboard_manager::ClipboardExt;
+        self.0
+            .clipboard()
+            .write_text(text)
+            .map_err(|e| format!("Failed to write clipboard: {}", e))
+    }
+}
+
+/// Result of a `submit` or `flush`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Delivery {
+    /// Text now on the clipboard.
+    pub written: Option<String>,
+    /// Our texts replaced before they were (known to be) pasted.
+    pub superseded: Vec<String>,
+    /// A transcript is waiting for the dwell time; call `flush` then.
+    pub retry_at: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+pub struct OutputCoordinator {
+    dwell: Duration,
+    append: bool,
+    /// Our last clipboard write and when it happened.
+    last: Option<(Instant, String)>,
+    /// Transcript waiting for the dwell time to pass.
+    pending: Option<String>,
+}
+
+impl OutputCoordinator {
+    pub fn configure(&mut self, dwell: Duration, append: bool) {
+        self.dwell = dwell;
+        self.append = append;
+    }
+
+    /// Hand a finished transcript over for the clipboard.
+    pub fn submit(&mut self, text: &str, now: Instant, clipboard: &mut dyn Clipboard) -> Delivery {
+        let mut delivery = Delivery::default();
+        let text = match self.pending.take() {
+            Some(pending) if self.append => join(&pending, text),
+            Some(pending) => {
+                // Never reached the clipboard; the newer transcript wins.
+                delivery.superseded.push(pending);
+                text.to_string()
+            }
+            None => text.to_string(),
+        };
+
+        let Some(ready_at) = self.dwell_until(now) else {
+            self.write(text, now, clipboard, &mut delivery);
+            return delivery;
+        };
+        if self.append && self.last_still_on(clipboard) {
+            // Grow the clipboard instead of replacing it.
+            let (_, last) = self.last.take().expect("checked by last_still_on");
+            let text = join(&last, &text);
+            if let Err(e) = clipboard.write(&text) {
+                tracing::warn!("{}", e);
+                return delivery;
+            }
+            self.last = Some((now, text.clone()));
+            delivery.written = Some(text);
+            return delivery;
+        }
+        self.pending = Some(text);
+        delivery.retry_at = Some(ready_at);
+        delivery
+    }
+
+    /// Write the waiting transcript once the dwell time has passed.
+    pub fn flush(&mut self, now: Instant, clipboard: &mut dyn Clipboard) -> Delivery {
+        let mut delivery = Delivery::default();
+        if self.pending.is_none() {
+            return delivery;
+        }
+        if let Some(ready_at) = self.dwell_until(now) {
+            delivery.retry_at = Some(ready_at);
+            return delivery;
+        }
+        let text = self.pending.take().expect("checked above");
+        self.write(text, now, clipboard, &mut delivery);
+        delivery
+    }
+
+    /// When the dwell since our last write ends, if it hasn't yet.
+    fn dwell_until(&self, now: Instant) -> Option<Instant> {
+        let (at, _) = self.last.as_ref()?;
+        let ready_at = *at + self.dwell;
+        (now < ready_at).then_some(ready_at)
+    }
+
+    fn last_still_on(&self, clipboard: &dyn Clipboard) -> bool {
+        match (&self.last, clipboard.read()) {
+            (Some((_, last)), Some(current)) => *last == current,
+            _ => false,
+        }
+    }
+
+    fn write(
+        &mut self,
+        text: String,
+        now: Instant,
+        clipboard: &mut dyn Clipboard,
+        delivery: &mut Delivery,
+    ) {
+        let replaces_ours = self.last_still_on(clipboard);
+        if let Err(e) = clipboard.write(&text) {
+            tracing::warn!("{}", e);
+            return;
+        }
+        if replaces_ours {
+            if let Some((_, last)) = self.last.take() {
+                delivery.superseded.push(last);
+            }
+        }
+        self.last = Some((now, text.clone()));
+        delivery.written = Some(text);
+    }
+}
+
+fn join(a: &str, b: &str) -> String {
+    format!("{} {}", a.trim_end(), b.trim_start())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MockClipboard(Option<String>);
+
+    impl Clipboard for MockClipboard {
+        fn read(&self) -> Option<String> {
+            self.0.clone()
+        }
+
+        fn write(&mut self, text: &str) -> Result<(), String> {
+            self.0 = Some(text.to_string());
+            Ok(())
+        }
+    }
+
+    fn coordinator(dwell_ms: u64, append: bool) -> OutputCoordinator {
+        let mut coordinator = OutputCoordinator::default();
+        coordinator.configure(Duration::from_millis(dwell_ms), append);
+        coordinator
+    }
+
+    #[test]
+    fn rapid_writes_wait_for_the_dwell() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut clipboard = MockClipboard::default();
+        let mut out = coordinator(1000, false);
+
+        assert_eq!(
+            out.submit("one", ms(0), &mut clipboard).written.as_deref(),
+            Some("one")
+        );
+        let delivery = out.submit("two", ms(300), &mut clipboard);
+        assert_eq!(delivery.written, None);
+        assert_eq!(delivery.retry_at, Some(ms(1000)));
+        assert_eq!(clipboard.0.as_deref(), Some("one"));
+
+        // Too early: still waiting
+        assert_eq!(out.flush(ms(900), &mut clipboard).retry_at, Some(ms(1000)));
+
+        let delivery = out.flush(ms(1000), &mut clipboard);
+        assert_eq!(delivery.written.as_deref(), Some("two"));
+        assert_eq!(delivery.superseded, vec!["one".to_string()]);
+        assert_eq!(out.flush(ms(1200), &mut clipboard), Delivery::default());
+    }
+
+    #[test]
+    fn newer_pending_transcript_wins_without_append() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut clipboard = MockClipboard::default();
+        let mut out = coordinator(1000, false);
+
+        out.submit("one", ms(0), &mut clipboard);
+        out.submit("two", ms(200), &mut clipboard);
+        let delivery = out.submit("three", ms(400), &mut clipboard);
+        assert_eq!(delivery.superseded, vec!["two".to_string()]);
+        assert_eq!(
+            out.flush(ms(1000), &mut clipboard).written.as_deref(),
+            Some("three")
+        );
+    }
+
+    #[test]
+    fn append_mode_joins_rapid_utterances() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut clipboard = MockClipboard::default();
+        let mut out = coordinator(1000, true);
+
+        out.submit("Hello there.", ms(0), &mut clipboard);
+        let delivery = out.submit("General Kenobi.", ms(500), &mut clipboard);
+        assert_eq!(
+            delivery.written.as_deref(),
+            Some("Hello there. General Kenobi.")
+        );
+        assert!(delivery.superseded.is_empty());
+
+        // The user copied something else meanwhile: don't glue onto it,
+        // wait for the dwell instead.
+        clipboard.0 = Some("unrelated".into());
+        let delivery = out.submit("Again.", ms(900), &mut clipboard);
+        assert_eq!(delivery.written, None);
+        assert_eq!(delivery.retry_at, Some(ms(1500)));
+        let delivery = out.flush(ms(1500), &mut clipboard);
+        assert_eq!(delivery.written.as_deref(), Some("Again."));
+        assert!(
+            delivery.superseded.is_empty(),
+            "clipboard held the user's text"
+        );
+    }
+
+    #[test]
+    fn superseded_only_when_our_text_is_still_there() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut clipboard = MockClipboard::default();
+        let mut out = coordinator(0, false);
+
+        out.submit("one", ms(0), &mut clipboard);
+        let delivery = out.submit("two", ms(10), &mut clipboard);
+        assert_eq!(delivery.superseded, vec!["one".to_string()]);
+
+        clipboard.0 = Some("user copied this".into());
+        let delivery = out.submit("three", ms(20), &mut clipboard);
+        assert_eq!(delivery.written.as_deref(), Some("three"));
+        assert!(delivery.superseded.is_empty());
+    }
+}
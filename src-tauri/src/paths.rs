@@ -0,0 +1,230 @@
+//! Where the app keeps its data.
+//!
+//! Every on-disk location (settings store, models, crash reports,
+//! session journals) is resolved here so portable mode only has to be
+//! handled once. History and stats live inside the settings store and
+//! follow it.
+//!
+//! - **Standard**: the platform app-data directory.
+//! - **Portable**: `data/` next to the executable. Enabled by a
+//!   `portable.flag` file beside the binary or the `--portable`
+//!   argument — for USB sticks and managed desktops where nothing may
+//!   be written to the user profile.
+//!
+//! If the chosen root isn't writable (read-only media, locked-down
+//! install dir) the app falls back to a temp directory and reports why,
+//! so it still starts — it just won't remember anything across reboots.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// File beside the executable that turns portable mode on.
+pub const PORTABLE_FLAG_FILE: &str = "portable.flag";
+
+/// Command-line switch that turns portable mode on.
+pub const PORTABLE_ARG: &str = "--portable";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataMode {
+    Standard,
+    Portable,
+}
+
+/// Resolved data locations. Returned as-is by `get_data_paths`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataPaths {
+    pub mode: DataMode,
+    pub root: PathBuf,
+    /// Set when the preferred root wasn't writable and `root` is a
+    /// temp directory instead.
+    pub fallback_reason: Option<String>,
+}
+
+impl DataPaths {
+    /// The settings store. Absolute, so tauri-plugin-store uses it
+    /// verbatim instead of resolving it against app data.
+    pub fn settings_file(&self) -> PathBuf {
+        self.root.join(crate::state::SETTINGS_STORE_FILE)
+    }
+
+    pub fn models_dir(&self) -> PathBuf {
+        self.root.join("models")
+    }
+
+    pub fn crashes_dir(&self) -> PathBuf {
+        self.root.join("crashes")
+    }
+
+    pub fn sessions_dir(&self) -> PathBuf {
+        self.root.join("sessions")
+    }
+
+    /// Recordings saved by `save_last_recording` and
+    /// `debug_save_recordings`.
+    pub fn recordings_dir(&self) -> PathBuf {
+        self.root.join("recordings")
+    }
+
+    /// Temp files: captures spilled to disk (see `audio::spill`).
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join("cache")
+    }
+
+    /// The watched folders' manifests (see `crate::watch`).
+    pub fn watch_dir(&self) -> PathBuf {
+        self.root.join("watch")
+    }
+
+    pub fn mic_usage_file(&self) -> PathBuf {
+        self.root.join("mic-usage.jsonl")
+    }
+}
+
+/// Portable when the flag file sits next to the executable or the
+/// switch was passed.
+pub fn detect_mode(exe_dir: &Path, args: &[String]) -> DataMode {
+    if exe_dir.join(PORTABLE_FLAG_FILE).is_file() || args.iter().any(|a| a == PORTABLE_ARG) {
+        DataMode::Portable
+    } else {
+        DataMode::Standard
+    }
+}
+
+/// Pick the root for `mode`, falling back to `temp_dir` when it can't
+/// be written.
+pub fn resolve(mode: DataMode, exe_dir: &Path, app_data_dir: &Path, temp_dir: &Path) -> DataPaths {
+    let preferred = match mode {
+        DataMode::Portable => exe_dir.join("data"),
+        DataMode::Standard => app_data_dir.to_path_buf(),
+    };
+    match ensure_writable(&preferred) {
+        Ok(()) => DataPaths {
+            mode,
+            root: preferred,
+            fallback_reason: None,
+        },
+        Err(e) => {
+            let fallback = temp_dir.join("S2Tui");
+            let reason = format!("{} is not writable ({})", preferred.display(), e);
+            tracing::warn!("{}; using {} instead", reason, fallback.display());
+            let _ = std::fs::create_dir_all(&fallback);
+            DataPaths {
+                mode,
+                root: fallback,
+                fallback_reason: Some(reason),
+            }
+        }
+    }
+}
+
+/// Create `dir` if needed and prove we can write to it.
+fn ensure_writable(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(".write-test");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
+}
+
+static PATHS: OnceLock<DataPaths> = OnceLock::new();
+
+/// Resolve the data paths once, at startup, before anything touches
+/// disk. Later calls return the first result.
+pub fn init(app: &tauri::AppHandle) -> &'static DataPaths {
+    use tauri::Manager;
+    PATHS.get_or_init(|| {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf))
+            .unwrap_or_default();
+        let args: Vec<String> = std::env::args().collect();
+        let mode = detect_mode(&exe_dir, &args);
+        let app_data_dir = app
+            .path()
+            .app_data_dir()
+            .unwrap_or_else(|_| std::env::temp_dir().join("S2Tui"));
+        let paths = resolve(mode, &exe_dir, &app_data_dir, &std::env::temp_dir());
+        tracing::info!(
+            "Data directory ({:?}): {}",
+            paths.mode,
+            paths.root.display()
+        );
+        paths
+    })
+}
+
+/// The resolved data paths. Panics if called before `init` — which runs
+/// first thing in `setup`, before any command can be invoked.
+pub fn get() -> &'static DataPaths {
+    PATHS.get().expect("paths::init must run during setup")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn mode_detection() {
+        let exe = tempfile::tempdir().unwrap();
+        assert_eq!(
+            detect_mode(exe.path(), &args(&["s2tui"])),
+            DataMode::Standard
+        );
+        assert_eq!(
+            detect_mode(exe.path(), &args(&["s2tui", "--portable"])),
+            DataMode::Portable
+        );
+        std::fs::write(exe.path().join(PORTABLE_FLAG_FILE), "").unwrap();
+        assert_eq!(
+            detect_mode(exe.path(), &args(&["s2tui"])),
+            DataMode::Portable
+        );
+    }
+
+    #[test]
+    fn standard_mode_uses_app_data() {
+        let exe = tempfile::tempdir().unwrap();
+        let app_data = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = resolve(DataMode::Standard, exe.path(), app_data.path(), tmp.path());
+        assert_eq!(paths.root, app_data.path());
+        assert!(paths.fallback_reason.is_none());
+        assert_eq!(paths.models_dir(), app_data.path().join("models"));
+        assert_eq!(paths.settings_file(), app_data.path().join("settings.json"));
+    }
+
+    #[test]
+    fn portable_mode_uses_data_beside_exe() {
+        let exe = tempfile::tempdir().unwrap();
+        let app_data = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let paths = resolve(DataMode::Portable, exe.path(), app_data.path(), tmp.path());
+        assert_eq!(paths.root, exe.path().join("data"));
+        assert!(paths.root.is_dir());
+        assert_eq!(paths.crashes_dir(), exe.path().join("data").join("crashes"));
+        assert_eq!(
+            paths.sessions_dir(),
+            exe.path().join("data").join("sessions")
+        );
+    }
+
+    #[test]
+    fn unwritable_root_falls_back_to_temp() {
+        let exe = tempfile::tempdir().unwrap();
+        let app_data = tempfile::tempdir().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        // A file where the data dir should be can't be created or written
+        // to, whatever the test's privileges are.
+        std::fs::write(exe.path().join("data"), "").unwrap();
+        let paths = resolve(DataMode::Portable, exe.path(), app_data.path(), tmp.path());
+        assert_eq!(paths.mode, DataMode::Portable);
+        assert_eq!(paths.root, tmp.path().join("S2Tui"));
+        assert!(paths.fallback_reason.is_some());
+    }
+}
@@ -0,0 +1,483 @@
+//! CPU/GPU utilization sampling around a transcription.
+//!
+//! While a decode runs, [`run_sampling_loop`] polls a [`CpuReader`] and,
+//! when one is available, a [`GpuReader`] at [`SAMPLE_INTERVAL`]. Each
+//! reading is streamed as a `perf:sample` event for the live graph and
+//! folded into a min/avg/max [`UtilizationSummary`] attached to the
+//! transcription's performance payload.
+//!
+//! GPU counters are best effort: only NVIDIA exposes a cheap query
+//! (`nvidia-smi`). Anywhere else — or as soon as a GPU read fails or
+//! takes longer than [`NVIDIA_SMI_TIMEOUT`] — the sampler silently
+//! degrades to CPU-only metrics. Readings are taken on the blocking
+//! thread, outside the lock the summary sits behind.
+//!
+//! Readers and the clock are traits so the loop is testable with fakes.
+
+use crate::tasks::TaskRegistry;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+/// 4 Hz.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long one `nvidia-smi` query may take; past that it is killed
+/// and the GPU counter given up.
+pub const NVIDIA_SMI_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Source of process CPU usage, in percent of the whole machine (0–100).
+pub trait CpuReader: Send {
+    fn read(&mut self) -> Option<f32>;
+}
+
+/// Source of GPU utilization in percent (0–100). Returning `None` means
+/// the counter is gone; the sampler stops asking.
+pub trait GpuReader: Send {
+    fn read(&mut self) -> Option<f32>;
+}
+
+/// Time source for the sampling loop.
+pub trait Clock {
+    /// Time since the loop started.
+    fn elapsed(&self) -> Duration;
+    fn sleep(&self, d: Duration);
+}
+
+/// One reading, payload of `perf:sample`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerfSample {
+    pub t_ms: u64,
+    pub cpu_percent: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpu_percent: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageStats {
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+    pub samples: u32,
+}
+
+/// min/avg/max over one transcription. `gpu` is `None` when no GPU
+/// counter was readable at any point.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UtilizationSummary {
+    pub cpu: Option<UsageStats>,
+    pub gpu: Option<UsageStats>,
+}
+
+#[derive(Debug, Default)]
+struct Accumulator {
+    min: f32,
+    max: f32,
+    sum: f64,
+    n: u32,
+}
+
+impl Accumulator {
+    fn push(&mut self, v: f32) {
+        if self.n == 0 {
+            self.min = v;
+            self.max = v;
+        } else {
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+        }
+        self.sum += v as f64;
+        self.n += 1;
+    }
+
+    fn stats(&self) -> Option<UsageStats> {
+        (self.n > 0).then(|| UsageStats {
+            min: self.min,
+            avg: (self.sum / self.n as f64) as f32,
+            max: self.max,
+            samples: self.n,
+        })
+    }
+}
+
+/// Polls the readers.
+pub struct UtilizationSampler {
+    cpu: Box<dyn CpuReader>,
+    gpu: Option<Box<dyn GpuReader>>,
+}
+
+impl UtilizationSampler {
+    pub fn new(cpu: Box<dyn CpuReader>, gpu: Option<Box<dyn GpuReader>>) -> Self {
+        Self { cpu, gpu }
+    }
+
+    /// Take one reading at `t`. Returns `None` if the CPU reader had
+    /// nothing (e.g. the very first sysinfo refresh).
+    pub fn sample(&mut self, t: Duration) -> Option<PerfSample> {
+        let gpu_percent = match self.gpu.as_mut().map(|g| g.read()) {
+            Some(Some(v)) => Some(v),
+            Some(None) => {
+                tracing::debug!("GPU utilization counter unavailable, continuing CPU-only");
+                self.gpu = None;
+                None
+            }
+            None => None,
+        };
+        let cpu_percent = self.cpu.read()?;
+        Some(PerfSample {
+            t_ms: t.as_millis() as u64,
+            cpu_percent,
+            gpu_percent,
+        })
+    }
+}
+
+/// The readings so far, accumulated.
+#[derive(Debug, Default)]
+pub struct UtilizationStats {
+    cpu: Accumulator,
+    gpu: Accumulator,
+}
+
+impl UtilizationStats {
+    pub fn push(&mut self, sample: &PerfSample) {
+        self.cpu.push(sample.cpu_percent);
+        if let Some(v) = sample.gpu_percent {
+            self.gpu.push(v);
+        }
+    }
+
+    pub fn summary(&self) -> UtilizationSummary {
+        UtilizationSummary {
+            cpu: self.cpu.stats(),
+            gpu: self.gpu.stats(),
+        }
+    }
+}
+
+/// Sample every `interval` until `stop` is set, calling `on_sample`
+/// for each reading. Blocking — run it on a blocking thread. The
+/// readings are taken outside `stats`' mutex (held only to record one)
+/// so the owner can read the summary the moment the decode ends instead
+/// of waiting out the current sleep or a slow GPU query.
+pub fn run_sampling_loop(
+    sampler: &mut UtilizationSampler,
+    stats: &Mutex<UtilizationStats>,
+    clock: &dyn Clock,
+    interval: Duration,
+    stop: &AtomicBool,
+    mut on_sample: impl FnMut(&PerfSample),
+) -> UtilizationSummary {
+    while !stop.load(Ordering::Acquire) {
+        clock.sleep(interval);
+        if stop.load(Ordering::Acquire) {
+            break;
+        }
+        if let Some(sample) = sampler.sample(clock.elapsed()) {
+            stats.lock().push(&sample);
+            on_sample(&sample);
+        }
+    }
+    stats.lock().summary()
+}
+
+/// A sampling run bracketing one decode.
+pub struct SamplingSession {
+    stats: Arc<Mutex<UtilizationStats>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl SamplingSession {
+    /// Start sampling on a tracked blocking task, which also sets up
+    /// the readers (the first run probes `nvidia-smi`). `on_sample`
+    /// runs on that task for every reading.
+    pub fn start(
+        tasks: &TaskRegistry,
+        on_sample: impl FnMut(&PerfSample) + Send + 'static,
+    ) -> Self {
+        let stats = Arc::new(Mutex::new(UtilizationStats::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (loop_stats, loop_stop) = (stats.clone(), stop.clone());
+        tasks.spawn("perf-sampler", async move {
+            let _ = tokio::task::spawn_blocking(move || {
+                let gpu =
+                    nvidia_smi_available().then(|| Box::new(NvidiaSmiReader) as Box<dyn GpuReader>);
+                let mut sampler =
+                    UtilizationSampler::new(Box::new(ProcessCpuReader::for_current_process()), gpu);
+                let clock = SystemClock::start();
+                run_sampling_loop(
+                    &mut sampler,
+                    &loop_stats,
+                    &clock,
+                    SAMPLE_INTERVAL,
+                    &loop_stop,
+                    on_sample,
+                )
+            })
+            .await;
+        });
+        Self { stats, stop }
+    }
+
+    /// Stop sampling and return the statistics gathered so far.
+    pub fn finish(self) -> UtilizationSummary {
+        self.stop.store(true, Ordering::Release);
+        self.stats.lock().summary()
+    }
+}
+
+// ============================================================================
+// Real implementations
+// ============================================================================
+
+/// Wall clock.
+pub struct SystemClock(Instant);
+
+impl SystemClock {
+    pub fn start() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Clock for SystemClock {
+    fn elapsed(&self) -> Duration {
+        self.0.elapsed()
+    }
+
+    fn sleep(&self, d: Duration) {
+        std::thread::sleep(d);
+    }
+}
+
+/// This process's CPU usage via sysinfo, refreshing only our own pid.
+pub struct ProcessCpuReader {
+    sys: sysinfo::System,
+    pid: sysinfo::Pid,
+    n_cpus: f32,
+}
+
+impl ProcessCpuReader {
+    pub fn for_current_process() -> Self {
+        let n_cpus = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1) as f32;
+        let mut reader = Self {
+            sys: sysinfo::System::new(),
+            pid: sysinfo::Pid::from_u32(std::process::id()),
+            n_cpus,
+        };
+        // CPU usage is a diff between two refreshes; prime the first one.
+        reader.refresh();
+        reader
+    }
+
+    fn refresh(&mut self) -> bool {
+        self.sys
+            .refresh_process_specifics(self.pid, sysinfo::ProcessRefreshKind::new().with_cpu())
+    }
+}
+
+impl CpuReader for ProcessCpuReader {
+    fn read(&mut self) -> Option<f32> {
+        if !self.refresh() {
+            return None;
+        }
+        // sysinfo reports per-core percent (400% = 4 busy cores);
+        // normalise to the whole machine.
+        let usage = self.sys.process(self.pid)?.cpu_usage() / self.n_cpus;
+        Some(usage.clamp(0.0, 100.0))
+    }
+}
+
+/// NVIDIA GPU utilization via `nvidia-smi`. Only used once
+/// [`nvidia_smi_available`] confirmed the tool works.
+pub struct NvidiaSmiReader;
+
+/// Whether `nvidia-smi` answers a query. Probed once per process so
+/// machines without it pay a single failed spawn.
+fn nvidia_smi_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| NvidiaSmiReader.read().is_some())
+}
+
+impl GpuReader for NvidiaSmiReader {
+    fn read(&mut self) -> Option<f32> {
+        let mut query = Command::new("nvidia-smi");
+        query.args([
+            "--query-gpu=utilization.gpu",
+            "--format=csv,noheader,nounits",
+        ]);
+        parse_nvidia_smi(&output_within(&mut query, NVIDIA_SMI_TIMEOUT)?)
+    }
+}
+
+/// Standard output of `command` if it succeeds within `timeout`; it is
+/// killed otherwise. Polls, so only for a blocking thread. The output
+/// is read once it exits: enough for a few lines, which the pipe holds.
+fn output_within(command: &mut Command, timeout: Duration) -> Option<String> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().ok()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            tracing::debug!("{:?} timed out after {:?}", command.get_program(), timeout);
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    if !status.success() {
+        return None;
+    }
+    let mut stdout = String::new();
+    child.stdout.take()?.read_to_string(&mut stdout).ok()?;
+    Some(stdout)
+}
+
+/// First GPU's utilization from `nvidia-smi` CSV output.
+fn parse_nvidia_smi(stdout: &str) -> Option<f32> {
+    stdout.lines().next()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+
+    struct FakeClock(Cell<Duration>);
+
+    impl Clock for FakeClock {
+        fn elapsed(&self) -> Duration {
+            self.0.get()
+        }
+        fn sleep(&self, d: Duration) {
+            self.0.set(self.0.get() + d);
+        }
+    }
+
+    struct Script(VecDeque<Option<f32>>);
+
+    impl CpuReader for Script {
+        fn read(&mut self) -> Option<f32> {
+            self.0.pop_front().flatten()
+        }
+    }
+
+    impl GpuReader for Script {
+        fn read(&mut self) -> Option<f32> {
+            self.0.pop_front().flatten()
+        }
+    }
+
+    fn script(values: &[Option<f32>]) -> Box<Script> {
+        Box::new(Script(values.iter().copied().collect()))
+    }
+
+    /// Run the loop until `n` samples were emitted.
+    fn run(mut sampler: UtilizationSampler, n: usize) -> (Vec<PerfSample>, UtilizationSummary) {
+        let stats = Mutex::new(UtilizationStats::default());
+        let clock = FakeClock(Cell::new(Duration::ZERO));
+        let stop = AtomicBool::new(false);
+        let mut seen = Vec::new();
+        let summary =
+            run_sampling_loop(&mut sampler, &stats, &clock, SAMPLE_INTERVAL, &stop, |s| {
+                seen.push(*s);
+                if seen.len() == n {
+                    stop.store(true, Ordering::Release);
+                }
+            });
+        (seen, summary)
+    }
+
+    #[test]
+    fn samples_at_4hz_and_summarises() {
+        let sampler = UtilizationSampler::new(
+            script(&[Some(10.0), Some(30.0), Some(20.0)]),
+            Some(script(&[Some(50.0), Some(70.0), Some(90.0)])),
+        );
+        let (samples, summary) = run(sampler, 3);
+        assert_eq!(
+            samples.iter().map(|s| s.t_ms).collect::<Vec<_>>(),
+            [250, 500, 750]
+        );
+        let cpu = summary.cpu.unwrap();
+        assert_eq!(
+            (cpu.min, cpu.avg, cpu.max, cpu.samples),
+            (10.0, 20.0, 30.0, 3)
+        );
+        let gpu = summary.gpu.unwrap();
+        assert_eq!((gpu.min, gpu.avg, gpu.max), (50.0, 70.0, 90.0));
+    }
+
+    #[test]
+    fn degrades_to_cpu_only_when_gpu_counter_fails() {
+        let sampler = UtilizationSampler::new(
+            script(&[Some(10.0), Some(20.0), Some(30.0)]),
+            Some(script(&[Some(40.0), None, Some(99.0)])),
+        );
+        let (samples, summary) = run(sampler, 3);
+        assert_eq!(samples[0].gpu_percent, Some(40.0));
+        assert_eq!(samples[1].gpu_percent, None);
+        // The reader is dropped after the first failure, never re-polled.
+        assert_eq!(samples[2].gpu_percent, None);
+        assert_eq!(summary.gpu.unwrap().samples, 1);
+        assert_eq!(summary.cpu.unwrap().samples, 3);
+    }
+
+    #[test]
+    fn no_gpu_reader_means_no_gpu_stats() {
+        let sampler = UtilizationSampler::new(script(&[Some(5.0)]), None);
+        let (_, summary) = run(sampler, 1);
+        assert!(summary.gpu.is_none());
+        assert_eq!(summary.cpu.unwrap().avg, 5.0);
+    }
+
+    #[test]
+    fn stop_before_first_tick_yields_empty_summary() {
+        let mut sampler = UtilizationSampler::new(script(&[Some(5.0)]), None);
+        let stats = Mutex::new(UtilizationStats::default());
+        let clock = FakeClock(Cell::new(Duration::ZERO));
+        let stop = AtomicBool::new(true);
+        let summary =
+            run_sampling_loop(&mut sampler, &stats, &clock, SAMPLE_INTERVAL, &stop, |_| {
+                panic!("no sample expected")
+            });
+        assert!(summary.cpu.is_none() && summary.gpu.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_hung_query_is_given_up() {
+        let started = Instant::now();
+        let output = output_within(Command::new("sleep").arg("5"), Duration::from_millis(100));
+        assert_eq!(output, None);
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let output = output_within(Command::new("echo").arg("42"), Duration::from_secs(5));
+        assert_eq!(output.as_deref(), Some("42\n"));
+    }
+
+    #[test]
+    fn parses_nvidia_smi_output() {
+        assert_eq!(parse_nvidia_smi("37\n12\n"), Some(37.0));
+        assert_eq!(parse_nvidia_smi(" 0 \n"), Some(0.0));
+        assert_eq!(parse_nvidia_smi("[N/A]\n"), None);
+        assert_eq!(parse_nvidia_smi(""), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! hidapi-backed device access for `ptt` (feature `hid-ptt`).
+
+use super::{
+    is_ptt_candidate, HidBinding, HidDeviceInfo, PedalEdge, PedalTracker, PttError, PttEvent,
+};
+use hidapi::{DeviceInfo, HidApi, HidDevice};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Read timeout; also bounds how long `stop` takes to be noticed.
+const READ_TIMEOUT_MS: i32 = 100;
+
+/// Delay between reopen attempts while the device is unplugged.
+const REOPEN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Largest input report we expect from a pedal.
+const REPORT_SIZE: usize = 64;
+
+/// hidapi keeps global state, so the whole app shares one handle.
+static API: Mutex<Option<HidApi>> = Mutex::new(None);
+
+/// Run `f` against a freshly refreshed device list.
+fn with_api<T>(f: impl FnOnce(&HidApi) -> T) -> Result<T, PttError> {
+    let mut guard = API.lock();
+    let api = match guard.take() {
+        Some(mut api) => {
+            api.refresh_devices().map_err(|e| PttError::OpenFailed {
+                reason: e.to_string(),
+            })?;
+            api
+        }
+        None => HidApi::new().map_err(|e| PttError::OpenFailed {
+            reason: e.to_string(),
+        })?,
+    };
+    Ok(f(guard.insert(api)))
+}
+
+fn device_info(d: &DeviceInfo) -> HidDeviceInfo {
+    HidDeviceInfo {
+        vendor_id: d.vendor_id(),
+        product_id: d.product_id(),
+        usage_page: d.usage_page(),
+        usage: d.usage(),
+        manufacturer: d.manufacturer_string().map(String::from),
+        product: d.product_string().map(String::from),
+        path: d.path().to_string_lossy().into_owned(),
+    }
+}
+
+pub(super) fn list_devices() -> Result<Vec<HidDeviceInfo>, PttError> {
+    with_api(|api| {
+        api.device_list()
+            .filter(|d| is_ptt_candidate(d.usage_page(), d.usage()))
+            .map(device_info)
+            .collect()
+    })
+}
+
+/// hidapi only says "failed to open". On Linux, retry the hidraw node
+/// directly to tell a permissions problem apart from anything else.
+fn open_error(info: &HidDeviceInfo, err: hidapi::HidError) -> PttError {
+    #[cfg(target_os = "linux")]
+    if let Err(e) = std::fs::File::open(&info.path) {
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            return PttError::PermissionDenied {
+                path: info.path.clone(),
+                hint: super::udev_hint(info.vendor_id, info.product_id),
+            };
+        }
+    }
+    PttError::OpenFailed {
+        reason: format!("{}: {}", info.path, err),
+    }
+}
+
+fn open(d: &DeviceInfo, api: &HidApi) -> Result<(HidDeviceInfo, HidDevice), PttError> {
+    let info = device_info(d);
+    match d.open_device(api) {
+        Ok(device) => Ok((info, device)),
+        Err(e) => Err(open_error(&info, e)),
+    }
+}
+
+pub(super) fn capture_binding(timeout: Duration) -> Result<HidDeviceInfo, PttError> {
+    let opened = with_api(|api| {
+        api.device_list()
+            .filter(|d| is_ptt_candidate(d.usage_page(), d.usage()))
+            .map(|d| open(d, api))
+            .collect::<Vec<_>>()
+    })?;
+
+    let mut first_error = None;
+    let mut devices = Vec::new();
+    for result in opened {
+        match result {
+            Ok((info, device)) => devices.push((info, device, PedalTracker::default())),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if devices.is_empty() {
+        // Surfacing the open error is what tells a Linux user they need
+        // a udev rule rather than "nothing was pressed".
+        return Err(first_error.unwrap_or(PttError::BindingTimeout));
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut buf = [0u8; REPORT_SIZE];
+    while Instant::now() < deadline {
+        for (info, device, tracker) in devices.iter_mut() {
+            if let Ok(n) = device.read_timeout(&mut buf, 0) {
+                if n > 0 && tracker.feed(&buf[..n]) == Some(PedalEdge::Down) {
+                    tracing::info!(
+                        "PTT binding captured: {:04x}:{:04x} ({})",
+                        info.vendor_id,
+                        info.product_id,
+                        info.product.as_deref().unwrap_or("unknown")
+                    );
+                    return Ok(info.clone());
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Err(PttError::BindingTimeout)
+}
+
+fn open_binding(binding: &HidBinding) -> Result<HidDevice, PttError> {
+    with_api(|api| {
+        let d = api
+            .device_list()
+            .find(|d| {
+                d.vendor_id() == binding.vendor_id
+                    && d.product_id() == binding.product_id
+                    && d.usage_page() == binding.usage_page
+                    && d.usage() == binding.usage
+            })
+            .ok_or_else(|| PttError::OpenFailed {
+                reason: "device not connected".to_string(),
+            })?;
+        open(d, api).map(|(_, device)| device)
+    })?
+}
+
+/// Reader loop for the bound device. Survives unplug/replug: on a read
+/// error it releases any held press (so a recording never sticks on),
+/// reports the disconnect, and keeps trying to reopen.
+pub(super) fn run_reader(binding: HidBinding, stop: &AtomicBool, on_event: impl Fn(PttEvent)) {
+    let mut was_connected = false;
+    let mut last_error = None;
+    while !stop.load(Ordering::Relaxed) {
+        let device = match open_binding(&binding) {
+            Ok(device) => device,
+            Err(e) => {
+                if was_connected {
+                    was_connected = false;
+                    on_event(PttEvent::Disconnected);
+                }
+                if last_error.as_ref() != Some(&e) {
+                    tracing::warn!("PTT device unavailable: {:?}", e);
+                    last_error = Some(e);
+                }
+                std::thread::sleep(REOPEN_INTERVAL);
+                continue;
+            }
+        };
+        tracing::info!(
+            "PTT device connected: {:04x}:{:04x}",
+            binding.vendor_id,
+            binding.product_id
+        );
+        was_connected = true;
+        last_error = None;
+        on_event(PttEvent::Connected);
+
+        let mut tracker = PedalTracker::default();
+        let mut buf = [0u8; REPORT_SIZE];
+        while !stop.load(Ordering::Relaxed) {
+            match device.read_timeout(&mut buf, READ_TIMEOUT_MS) {
+                Ok(0) => {}
+                Ok(n) => match tracker.feed(&buf[..n]) {
+                    Some(PedalEdge::Down) => on_event(PttEvent::Pressed),
+                    Some(PedalEdge::Up) => on_event(PttEvent::Released),
+                    None => {}
+                },
+                Err(e) => {
+                    tracing::warn!("PTT device read failed (unplugged?): {}", e);
+                    break;
+                }
+            }
+        }
+        if tracker.is_pressed() {
+            on_event(PttEvent::Released);
+        }
+        if !stop.load(Ordering::Relaxed) {
+            was_connected = false;
+            on_event(PttEvent::Disconnected);
+        }
+    }
+}
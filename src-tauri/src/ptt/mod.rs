@@ -0,0 +1,332 @@
+//! Push-to-talk from HID devices (USB foot pedals).
+//!
+//! Foot pedals enumerate as generic HID devices rather than keyboards,
+//! so global shortcuts never see them. With the `hid-ptt` feature a
+//! reader task watches the bound device and turns button down/up into
+//! `ptt:pressed` / `ptt:released` events, which the frontend handles
+//! exactly like a held push-to-talk shortcut.
+//!
+//! Binding is two-step: `start_ptt_binding` waits for the user to press
+//! the pedal and reports which device it was; `bind_ptt_device` then
+//! only accepts that confirmed device. This keeps a typo'd vendor/product
+//! id (or a keyboard with a vendor usage page) from becoming the PTT
+//! trigger.
+//!
+//! Device I/O lives in `hid.rs`; everything here is plain logic so it
+//! builds and tests without the feature.
+
+#[cfg(feature = "hid-ptt")]
+mod hid;
+
+use crate::tasks::TaskRegistry;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long `start_ptt_binding` waits for a press.
+pub const BINDING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a confirmed press stays valid for `bind_ptt_device`.
+pub const CONFIRMATION_WINDOW: Duration = Duration::from_secs(120);
+
+/// A persisted PTT device binding. Frontend mirror: `pttDevice`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HidBinding {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub usage_page: u16,
+    pub usage: u16,
+}
+
+/// One candidate device, as returned by `list_hid_devices`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HidDeviceInfo {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub usage_page: u16,
+    pub usage: u16,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    /// OS device path (`/dev/hidrawN` on Linux).
+    pub path: String,
+}
+
+impl HidDeviceInfo {
+    pub fn binding(&self) -> HidBinding {
+        HidBinding {
+            vendor_id: self.vendor_id,
+            product_id: self.product_id,
+            usage_page: self.usage_page,
+            usage: self.usage,
+        }
+    }
+}
+
+/// Structured error for the PTT device commands.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum PttError {
+    /// Built without the `hid-ptt` feature.
+    Unsupported,
+    /// The OS refused to open the device. On Linux this almost always
+    /// means the hidraw node isn't readable by the user; `hint` carries
+    /// the udev rule that fixes it.
+    PermissionDenied { path: String, hint: String },
+    /// Opening or enumerating failed for another reason.
+    OpenFailed { reason: String },
+    /// Nothing was pressed within `BINDING_TIMEOUT`.
+    BindingTimeout,
+    /// `bind_ptt_device` was called for a device that wasn't confirmed
+    /// by a press in `start_ptt_binding` (or the confirmation expired).
+    NotConfirmed,
+    /// settings.json couldn't be written.
+    PersistFailed { reason: String },
+}
+
+/// The udev rule that grants the logged-in user access to one device.
+pub fn udev_hint(vendor_id: u16, product_id: u16) -> String {
+    format!(
+        "Add a udev rule, e.g. /etc/udev/rules.d/70-s2tui-pedal.rules containing \
+         KERNEL==\"hidraw*\", ATTRS{{idVendor}}==\"{vendor_id:04x}\", \
+         ATTRS{{idProduct}}==\"{product_id:04x}\", TAG+=\"uaccess\" \
+         then run `sudo udevadm control --reload-rules && sudo udevadm trigger` \
+         and replug the device."
+    )
+}
+
+/// Keyboards and mice (Generic Desktop 0x06 / 0x02) are already handled
+/// by the OS and by global shortcuts; everything else may be a pedal.
+pub fn is_ptt_candidate(usage_page: u16, usage: u16) -> bool {
+    const GENERIC_DESKTOP: u16 = 0x01;
+    !(usage_page == GENERIC_DESKTOP && matches!(usage, 0x02 | 0x06))
+}
+
+/// Button transition on the bound device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PedalEdge {
+    Down,
+    Up,
+}
+
+/// What the reader reports back to the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PttEvent {
+    Pressed,
+    Released,
+    Connected,
+    Disconnected,
+}
+
+/// Turns raw input reports into edges. Pedals report a button bitmask
+/// that is all zeros when nothing is held, so "any bit set" is pressed.
+#[derive(Debug, Default)]
+pub struct PedalTracker {
+    pressed: bool,
+}
+
+impl PedalTracker {
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    pub fn feed(&mut self, report: &[u8]) -> Option<PedalEdge> {
+        let pressed = report.iter().any(|&b| b != 0);
+        if pressed == self.pressed {
+            return None;
+        }
+        self.pressed = pressed;
+        Some(if pressed {
+            PedalEdge::Down
+        } else {
+            PedalEdge::Up
+        })
+    }
+}
+
+/// Owns the running reader and the pending binding confirmation.
+#[derive(Default)]
+pub struct PttController {
+    stop: Mutex<Option<Arc<AtomicBool>>>,
+    confirmed: Mutex<Option<(HidBinding, Instant)>>,
+}
+
+impl PttController {
+    /// Remember a device the user just pressed.
+    pub fn confirm(&self, binding: HidBinding) {
+        *self.confirmed.lock() = Some((binding, Instant::now()));
+    }
+
+    /// The device awaiting `bind_ptt_device`, while still fresh.
+    pub fn pending(&self) -> Option<HidBinding> {
+        (*self.confirmed.lock())
+            .filter(|(_, at)| at.elapsed() <= CONFIRMATION_WINDOW)
+            .map(|(binding, _)| binding)
+    }
+
+    /// Consume the pending confirmation if it is for this device and
+    /// still fresh. Returns the full binding (with usage page).
+    pub fn take_confirmed(
+        &self,
+        vendor_id: u16,
+        product_id: u16,
+        usage: u16,
+    ) -> Option<HidBinding> {
+        self.take_confirmed_at(vendor_id, product_id, usage, Instant::now())
+    }
+
+    fn take_confirmed_at(
+        &self,
+        vendor_id: u16,
+        product_id: u16,
+        usage: u16,
+        now: Instant,
+    ) -> Option<HidBinding> {
+        let mut confirmed = self.confirmed.lock();
+        let (binding, at) = (*confirmed)?;
+        let same_device = binding.vendor_id == vendor_id
+            && binding.product_id == product_id
+            && binding.usage == usage;
+        if !same_device || now.duration_since(at) > CONFIRMATION_WINDOW {
+            return None;
+        }
+        *confirmed = None;
+        Some(binding)
+    }
+
+    /// (Re)start the reader for `binding`, replacing any running one.
+    pub fn start(
+        &self,
+        tasks: &TaskRegistry,
+        binding: HidBinding,
+        on_event: impl Fn(PttEvent) + Send + 'static,
+    ) {
+        self.stop();
+        let stop = Arc::new(AtomicBool::new(false));
+        *self.stop.lock() = Some(stop.clone());
+
+        #[cfg(feature = "hid-ptt")]
+        tasks.spawn("hid-ptt", async move {
+            let _ = tokio::task::spawn_blocking(move || hid::run_reader(binding, &stop, on_event))
+                .await;
+        });
+        #[cfg(not(feature = "hid-ptt"))]
+        {
+            let _ = (tasks, on_event, stop);
+            tracing::warn!(
+                "Ignoring PTT binding {:04x}:{:04x}: built without the `hid-ptt` feature",
+                binding.vendor_id,
+                binding.product_id
+            );
+        }
+    }
+
+    /// Stop the reader, if any. It notices within one read timeout.
+    pub fn stop(&self) {
+        if let Some(stop) = self.stop.lock().take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Candidate PTT devices currently attached.
+pub fn list_devices() -> Result<Vec<HidDeviceInfo>, PttError> {
+    #[cfg(feature = "hid-ptt")]
+    return hid::list_devices();
+    #[cfg(not(feature = "hid-ptt"))]
+    Err(PttError::Unsupported)
+}
+
+/// Block until a button is pressed on any candidate device (or
+/// `timeout` passes) and return that device.
+pub fn capture_binding(timeout: Duration) -> Result<HidDeviceInfo, PttError> {
+    #[cfg(feature = "hid-ptt")]
+    return hid::capture_binding(timeout);
+    #[cfg(not(feature = "hid-ptt"))]
+    {
+        let _ = timeout;
+        Err(PttError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEDAL: HidBinding = HidBinding {
+        vendor_id: 0x05f3,
+        product_id: 0x00ff,
+        usage_page: 0x0c,
+        usage: 0x03,
+    };
+
+    #[test]
+    fn tracker_emits_edges_only_on_change() {
+        let mut t = PedalTracker::default();
+        assert_eq!(t.feed(&[0, 0]), None);
+        assert_eq!(t.feed(&[0, 2]), Some(PedalEdge::Down));
+        // A second pedal joining while the first is held is not a new press
+        assert_eq!(t.feed(&[0, 6]), None);
+        assert!(t.is_pressed());
+        assert_eq!(t.feed(&[0, 0]), Some(PedalEdge::Up));
+        assert_eq!(t.feed(&[]), None);
+    }
+
+    #[test]
+    fn keyboards_and_mice_are_not_candidates() {
+        assert!(!is_ptt_candidate(0x01, 0x06));
+        assert!(!is_ptt_candidate(0x01, 0x02));
+        assert!(is_ptt_candidate(0x01, 0x04)); // joystick-class pedals
+        assert!(is_ptt_candidate(0x0c, 0x01)); // consumer control
+        assert!(is_ptt_candidate(0xff00, 0x01)); // vendor-defined
+    }
+
+    #[test]
+    fn binding_requires_fresh_matching_confirmation() {
+        let ctl = PttController::default();
+        assert_eq!(
+            ctl.take_confirmed(PEDAL.vendor_id, PEDAL.product_id, PEDAL.usage),
+            None
+        );
+
+        ctl.confirm(PEDAL);
+        // Wrong device doesn't consume the confirmation
+        assert_eq!(
+            ctl.take_confirmed(0x1234, PEDAL.product_id, PEDAL.usage),
+            None
+        );
+        assert_eq!(
+            ctl.take_confirmed(PEDAL.vendor_id, PEDAL.product_id, PEDAL.usage),
+            Some(PEDAL)
+        );
+        // Single use
+        assert_eq!(
+            ctl.take_confirmed(PEDAL.vendor_id, PEDAL.product_id, PEDAL.usage),
+            None
+        );
+        assert_eq!(ctl.pending(), None);
+
+        ctl.confirm(PEDAL);
+        assert_eq!(ctl.pending(), Some(PEDAL));
+        let later = Instant::now() + CONFIRMATION_WINDOW + Duration::from_secs(1);
+        assert_eq!(
+            ctl.take_confirmed_at(PEDAL.vendor_id, PEDAL.product_id, PEDAL.usage, later),
+            None
+        );
+    }
+
+    #[test]
+    fn udev_hint_names_the_device() {
+        let hint = udev_hint(0x05f3, 0x00ff);
+        assert!(hint.contains("ATTRS{idVendor}==\"05f3\""));
+        assert!(hint.contains("ATTRS{idProduct}==\"00ff\""));
+        assert!(hint.contains("uaccess"));
+    }
+}
@@ -0,0 +1,309 @@
+//! What the overlay draws, as one snapshot.
+//!
+//! The overlay used to rebuild its state from a dozen event types and
+//! drifted after any missed one (a suspended webview misses them all).
+//! Now `get_render_state` returns everything it draws at once, and the
+//! backend emits a payload-less `render:invalidate` whenever one of
+//! those things changes; the overlay just pulls again.
+//!
+//! Most of the snapshot is read straight from `AppState`. The few
+//! things nothing else keeps — when the recording started (minus
+//! pauses), the smoothed level, the tail of the last partial — live in
+//! the `RenderTracker`, fed by status transitions and the audio loop.
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::ptt::HidBinding;
+use crate::state::{AppState, AppStatus};
+
+/// Characters of the partial transcript kept for the overlay.
+pub const PARTIAL_TAIL_CHARS: usize = 120;
+/// Weight of a new level reading in the moving average.
+const LEVEL_SMOOTHING: f32 = 0.3;
+/// Level change that is worth a `render:invalidate`; smaller moves are
+/// picked up by the next pull anyway.
+const LEVEL_STEP: f32 = 0.05;
+
+/// Something waiting on the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum PendingConfirmation {
+    /// A pedal was pressed in `start_ptt_binding` and awaits
+    /// `bind_ptt_device`.
+    PttBinding { vendor_id: u16, product_id: u16 },
+}
+
+impl From<HidBinding> for PendingConfirmation {
+    fn from(binding: HidBinding) -> Self {
+        Self::PttBinding {
+            vendor_id: binding.vendor_id,
+            product_id: binding.product_id,
+        }
+    }
+}
+
+/// `get_render_state` result.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderState {
+    pub status: AppStatus,
+    /// Smoothed input level, 0–1.
+    pub level: f32,
+    /// Recording time so far, pauses excluded; `None` when not recording.
+    pub elapsed_ms: Option<u64>,
+    pub model: String,
+    pub language: String,
+    /// How the running recording was started (`"toggle"`,
+    /// `"push-to-talk"`, `"voice-activated"`).
+    pub listen_mode: Option<String>,
+    /// End of the latest partial transcript of this recording.
+    pub partial_tail: Option<String>,
+    /// Transcriptions queued or running.
+    pub queue_depth: usize,
+    pub pending_confirmation: Option<PendingConfirmation>,
+}
+
+/// Overlay state that has no other home.
+#[derive(Debug, Default)]
+pub struct RenderTracker {
+    /// Recording time banked before the last pause.
+    recorded: Duration,
+    /// Start of the current stretch of recording, while not paused.
+    recording_since: Option<Instant>,
+    recording: bool,
+    listen_mode: Option<String>,
+    level: f32,
+    /// Level at the last invalidation it caused.
+    notified_level: f32,
+    partial: Option<String>,
+}
+
+impl RenderTracker {
+    /// Follow a status transition: start, pause, resume or stop the
+    /// recording clock. A new recording also drops the last partial.
+    pub fn on_status(&mut self, from: AppStatus, to: AppStatus, now: Instant) {
+        match (from, to) {
+            (AppStatus::Paused, AppStatus::Listening) => self.recording_since = Some(now),
+            (_, AppStatus::Listening) => {
+                self.recorded = Duration::ZERO;
+                self.recording_since = Some(now);
+                self.recording = true;
+                self.partial = None;
+            }
+            (_, AppStatus::Paused) => {
+                if let Some(since) = self.recording_since.take() {
+                    self.recorded += now.duration_since(since);
+                }
+            }
+            _ => {
+                self.recording = false;
+                self.recording_since = None;
+                self.listen_mode = None;
+                self.level = 0.0;
+                self.notified_level = 0.0;
+            }
+        }
+    }
+
+    pub fn set_listen_mode(&mut self, mode: &str) {
+        self.listen_mode = Some(mode.to_string());
+    }
+
+    /// Feed a level reading; returns the smoothed level, and whether it
+    /// moved enough since the last invalidation to warrant another.
+    pub fn feed_level(&mut self, level: f32) -> (f32, bool) {
+        self.level += (level.clamp(0.0, 1.0) - self.level) * LEVEL_SMOOTHING;
+        let moved = (self.level - self.notified_level).abs() >= LEVEL_STEP;
+        if moved {
+            self.notified_level = self.level;
+        }
+        (self.level, moved)
+    }
+
+    pub fn set_partial(&mut self, text: &str) {
+        self.partial = Some(tail(text, PARTIAL_TAIL_CHARS));
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Option<Duration> {
+        self.recording.then(|| {
+            self.recorded
+                + self
+                    .recording_since
+                    .map_or(Duration::ZERO, |since| now.duration_since(since))
+        })
+    }
+}
+
+/// The last `max_chars` characters of `text`, cut at a word boundary
+/// when there is one.
+fn tail(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    let count = text.chars().count();
+    if count <= max_chars {
+        return text.to_string();
+    }
+    let start = text
+        .char_indices()
+        .nth(count - max_chars)
+        .map_or(0, |(i, _)| i);
+    let cut = &text[start..];
+    // Unless the cut already fell between two words, drop the
+    // partial word it starts in.
+    let starts_on_word = text[..start].ends_with(char::is_whitespace);
+    match cut.find(char::is_whitespace) {
+        Some(space) if !starts_on_word => cut[space..].trim_start().to_string(),
+        _ => cut.to_string(),
+    }
+}
+
+/// Assemble the snapshot.
+pub fn snapshot(state: &AppState) -> RenderState {
+    let settings = state.get_settings();
+    let tracker = state.render.lock();
+    RenderState {
+        status: state.get_status(),
+        level: tracker.level,
+        elapsed_ms: tracker
+            .elapsed(Instant::now())
+            .map(|elapsed| elapsed.as_millis() as u64),
+        model: settings.model,
+        language: settings.language.to_code().to_string(),
+        listen_mode: tracker.listen_mode.clone(),
+        partial_tail: tracker.partial.clone(),
+        queue_depth: state.whisper.queue_length(),
+        pending_confirmation: state.ptt.pending().map(Into::into),
+    }
+}
+
+/// Tell the overlay its snapshot is stale.
+pub fn invalidate(app: &AppHandle) {
+    if let Err(e) = app.emit("render:invalidate", ()) {
+        tracing::warn!("render:invalidate emit failed: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn clock_runs_while_listening_and_skips_pauses() {
+        let mut tracker = RenderTracker::default();
+        let t0 = Instant::now();
+        assert_eq!(tracker.elapsed(t0), None);
+
+        tracker.on_status(AppStatus::Idle, AppStatus::Listening, t0);
+        assert_eq!(tracker.elapsed(t0 + ms(1500)), Some(ms(1500)));
+
+        tracker.on_status(AppStatus::Listening, AppStatus::Paused, t0 + ms(2000));
+        assert_eq!(tracker.elapsed(t0 + ms(9000)), Some(ms(2000)));
+
+        tracker.on_status(AppStatus::Paused, AppStatus::Listening, t0 + ms(10_000));
+        assert_eq!(tracker.elapsed(t0 + ms(10_500)), Some(ms(2500)));
+
+        tracker.on_status(AppStatus::Listening, AppStatus::Processing, t0 + ms(11_000));
+        assert_eq!(tracker.elapsed(t0 + ms(12_000)), None);
+    }
+
+    #[test]
+    fn new_recording_resets_clock_and_partial() {
+        let mut tracker = RenderTracker::default();
+        let t0 = Instant::now();
+        tracker.on_status(AppStatus::Idle, AppStatus::Listening, t0);
+        tracker.set_listen_mode("toggle");
+        tracker.set_partial("hello there");
+        tracker.on_status(AppStatus::Listening, AppStatus::Processing, t0 + ms(4000));
+        // The partial stays up while the final pass runs
+        assert_eq!(tracker.partial.as_deref(), Some("hello there"));
+        assert_eq!(tracker.listen_mode, None);
+
+        tracker.on_status(AppStatus::Idle, AppStatus::Listening, t0 + ms(5000));
+        assert_eq!(tracker.partial, None);
+        assert_eq!(tracker.elapsed(t0 + ms(5100)), Some(ms(100)));
+    }
+
+    #[test]
+    fn level_is_smoothed_and_small_moves_stay_quiet() {
+        let mut tracker = RenderTracker::default();
+        let (level, moved) = tracker.feed_level(1.0);
+        assert!((level - LEVEL_SMOOTHING).abs() < 1e-6);
+        assert!(moved);
+        // Converges towards the input without invalidating every chunk
+        let invalidations = (0..50).filter(|_| tracker.feed_level(1.0).1).count();
+        assert!(invalidations < 10, "{invalidations}");
+        assert!(tracker.feed_level(1.0).0 > 0.99);
+        assert!(!tracker.feed_level(1.2).1);
+    }
+
+    #[test]
+    fn partial_tail_cuts_at_a_word() {
+        assert_eq!(tail("  short text ", 120), "short text");
+        assert_eq!(tail("one two three four", 9), "four");
+        // The cut falls right after a space: keep the whole word
+        assert_eq!(tail("one two three", 5), "three");
+        // No space to cut at
+        assert_eq!(tail("abcdefghij", 4), "ghij");
+        // Multi-byte characters
+        assert_eq!(tail("déjà vu été", 3), "été");
+    }
+
+    #[test]
+    fn snapshot_schema() {
+        let snapshot = RenderState {
+            status: AppStatus::Listening,
+            level: 0.5,
+            elapsed_ms: Some(1200),
+            model: "small".to_string(),
+            language: "fr".to_string(),
+            listen_mode: Some("push-to-talk".to_string()),
+            partial_tail: Some("bonjour".to_string()),
+            queue_depth: 1,
+            pending_confirmation: Some(PendingConfirmation::PttBinding {
+                vendor_id: 0x05f3,
+                product_id: 0x00ff,
+            }),
+        };
+        assert_eq!(
+            serde_json::to_value(&snapshot).unwrap(),
+            serde_json::json!({
+                "status": "listening",
+                "level": 0.5,
+                "elapsedMs": 1200,
+                "model": "small",
+                "language": "fr",
+                "listenMode": "push-to-talk",
+                "partialTail": "bonjour",
+                "queueDepth": 1,
+                "pendingConfirmation": {
+                    "kind": "pttBinding",
+                    "vendorId": 0x05f3,
+                    "productId": 0x00ff
+                }
+            })
+        );
+
+        let idle = RenderState {
+            status: AppStatus::Idle,
+            level: 0.0,
+            elapsed_ms: None,
+            listen_mode: None,
+            partial_tail: None,
+            queue_depth: 0,
+            pending_confirmation: None,
+            ..snapshot
+        };
+        let json = serde_json::to_value(&idle).unwrap();
+        assert_eq!(json["elapsedMs"], serde_json::Value::Null);
+        assert_eq!(json["pendingConfirmation"], serde_json::Value::Null);
+    }
+}
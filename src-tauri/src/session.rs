@@ -0,0 +1,221 @@
+//! Incremental session journal.
+//!
+//! Long hands-free sessions can run for hours; losing the whole
+//! transcript to a crash at the end is not acceptable. While such a
+//! session runs, every finalized utterance is appended to a JSONL file
+//! in `<data root>/sessions/` and flushed immediately, one file per
+//! session. `recover` rebuilds the combined transcript from a journal
+//! left behind by a crash — a half-written last line is expected and
+//! simply dropped.
+//!
+//! File format: a `session` header line, then one `utterance` line per
+//! finalized utterance.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// One line of a journal file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum JournalLine {
+    Session {
+        /// RFC 3339 local time the session started.
+        started_at: String,
+        mode: String,
+    },
+    Utterance(Utterance),
+}
+
+/// A finalized utterance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Utterance {
+    pub index: u32,
+    /// Offset from the session start.
+    pub at_ms: u64,
+    pub text: String,
+}
+
+/// Writer for one session's journal file.
+pub struct SessionJournal {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    started: std::time::Instant,
+    next_index: u32,
+}
+
+impl SessionJournal {
+    /// Start a new journal file in `dir` (created if missing).
+    pub fn create(dir: &Path, mode: &str) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let now = chrono::Local::now();
+        let path = dir.join(format!("session-{}.jsonl", now.format("%Y%m%d-%H%M%S%.3f")));
+        let file = File::options().create_new(true).append(true).open(&path)?;
+        let mut journal = Self {
+            path,
+            writer: BufWriter::new(file),
+            started: std::time::Instant::now(),
+            next_index: 0,
+        };
+        journal.write_line(&JournalLine::Session {
+            started_at: now.to_rfc3339(),
+            mode: mode.to_string(),
+        })?;
+        Ok(journal)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one utterance and flush it to disk.
+    pub fn append(&mut self, text: &str) -> std::io::Result<()> {
+        let utterance = Utterance {
+            index: self.next_index,
+            at_ms: self.started.elapsed().as_millis() as u64,
+            text: text.to_string(),
+        };
+        self.write_line(&JournalLine::Utterance(utterance))?;
+        self.next_index += 1;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &JournalLine) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.writer, line)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+/// Transcript rebuilt from a journal.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveredSession {
+    pub path: String,
+    pub started_at: Option<String>,
+    pub mode: Option<String>,
+    pub utterances: Vec<Utterance>,
+    /// Utterances joined with spaces, in order.
+    pub text: String,
+    /// Lines that couldn't be parsed (normally just a half-written last
+    /// line from the crash).
+    pub skipped_lines: u32,
+}
+
+/// Rebuild the combined transcript from a journal file.
+pub fn recover(path: &Path) -> std::io::Result<RecoveredSession> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut session = RecoveredSession {
+        path: path.to_string_lossy().into_owned(),
+        started_at: None,
+        mode: None,
+        utterances: Vec::new(),
+        text: String::new(),
+        skipped_lines: 0,
+    };
+    for line in reader.split(b'\n') {
+        let line = line?;
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        match serde_json::from_slice::<JournalLine>(&line) {
+            Ok(JournalLine::Session { started_at, mode }) => {
+                session.started_at = Some(started_at);
+                session.mode = Some(mode);
+            }
+            Ok(JournalLine::Utterance(u)) => session.utterances.push(u),
+            Err(_) => session.skipped_lines += 1,
+        }
+    }
+    session.utterances.sort_by_key(|u| u.index);
+    session.text = session
+        .utterances
+        .iter()
+        .map(|u| u.text.trim())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(session)
+}
+
+/// Whether `path` names a journal file directly inside `dir`.
+pub fn is_journal_in(dir: &Path, path: &Path) -> bool {
+    let (Ok(dir), Ok(path)) = (dir.canonicalize(), path.canonicalize()) else {
+        return false;
+    };
+    path.parent() == Some(dir.as_path())
+        && path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with("session-") && n.ends_with(".jsonl"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = SessionJournal::create(dir.path(), "voice-activated").unwrap();
+        journal.append("hello there").unwrap();
+        journal.append("  ").unwrap();
+        journal.append("general kenobi").unwrap();
+
+        let session = recover(journal.path()).unwrap();
+        assert_eq!(session.mode.as_deref(), Some("voice-activated"));
+        assert!(session.started_at.is_some());
+        assert_eq!(session.utterances.len(), 3);
+        assert_eq!(session.text, "hello there general kenobi");
+        assert_eq!(session.skipped_lines, 0);
+    }
+
+    #[test]
+    fn each_session_gets_its_own_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = SessionJournal::create(dir.path(), "voice-activated").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let b = SessionJournal::create(dir.path(), "voice-activated").unwrap();
+        assert_ne!(a.path(), b.path());
+    }
+
+    #[test]
+    fn truncated_last_line_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = {
+            let mut journal = SessionJournal::create(dir.path(), "voice-activated").unwrap();
+            journal.append("first").unwrap();
+            journal.append("second").unwrap();
+            journal.path().to_path_buf()
+        };
+        // Simulate a crash mid-write
+        let mut file = File::options().append(true).open(&path).unwrap();
+        file.write_all(br#"{"type":"utterance","index":2,"atMs":9,"te"#)
+            .unwrap();
+
+        let session = recover(&path).unwrap();
+        assert_eq!(session.text, "first second");
+        assert_eq!(session.skipped_lines, 1);
+    }
+
+    #[test]
+    fn journal_path_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = SessionJournal::create(dir.path(), "voice-activated").unwrap();
+        assert!(is_journal_in(dir.path(), journal.path()));
+
+        let other = dir.path().join("settings.json");
+        std::fs::write(&other, "{}").unwrap();
+        assert!(!is_journal_in(dir.path(), &other));
+        assert!(!is_journal_in(
+            dir.path(),
+            &dir.path().join("missing.jsonl")
+        ));
+    }
+}
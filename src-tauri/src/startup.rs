@@ -0,0 +1,89 @@
+//! Startup phase timing, reported once as the `startup:timings` event
+//! so cold-start regressions show up in logs and bug reports.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupPhase {
+    pub name: &'static str,
+    /// Duration of this phase alone.
+    pub duration_ms: u64,
+    /// Time since process start when the phase ended.
+    pub at_ms: u64,
+}
+
+/// Payload of `startup:timings`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupReport {
+    pub phases: Vec<StartupPhase>,
+    pub total_ms: u64,
+}
+
+struct Inner {
+    origin: Instant,
+    last: Instant,
+    phases: Vec<StartupPhase>,
+}
+
+/// Records phase boundaries from `run()` onwards. Cheap to clone so the
+/// background GPU probe can add its own phase.
+#[derive(Clone)]
+pub struct StartupTimings(Arc<Mutex<Inner>>);
+
+impl StartupTimings {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self(Arc::new(Mutex::new(Inner {
+            origin: now,
+            last: now,
+            phases: Vec::new(),
+        })))
+    }
+
+    /// Close the current phase under `name` and start the next one.
+    pub fn mark(&self, name: &'static str) {
+        let mut inner = self.0.lock();
+        let now = Instant::now();
+        let phase = StartupPhase {
+            name,
+            duration_ms: now.duration_since(inner.last).as_millis() as u64,
+            at_ms: now.duration_since(inner.origin).as_millis() as u64,
+        };
+        tracing::info!("Startup: {} took {} ms", phase.name, phase.duration_ms);
+        inner.phases.push(phase);
+        inner.last = now;
+    }
+
+    pub fn report(&self) -> StartupReport {
+        let inner = self.0.lock();
+        StartupReport {
+            phases: inner.phases.clone(),
+            total_ms: inner.phases.last().map(|p| p.at_ms).unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_accumulate_in_order() {
+        let t = StartupTimings::start();
+        t.mark("a");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        t.mark("b");
+        let r = t.report();
+        assert_eq!(
+            r.phases.iter().map(|p| p.name).collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert!(r.phases[1].duration_ms >= 5);
+        assert_eq!(r.total_ms, r.phases[1].at_ms);
+    }
+}
@@ -1,10 +1,40 @@
-use crate::audio::{AudioCapture, VoiceActivityDetector};
-use crate::whisper::{ModelCapabilities, WhisperWorker};
-use parking_lot::RwLock;
+use crate::audio::{
+    AudioCapture, AudioFilters, CaptureSource, ChannelMode, RetainedAudio, RetainedId,
+    UtteranceAudio, VadBackend, VoiceActivityDetector, DEFAULT_MIN_SPEECH_MS, DEFAULT_NOISE_FACTOR,
+    DEFAULT_RETAINED_AUDIO_MB, DEFAULT_SILENCE_TIMEOUT_MS, DEFAULT_SPEECH_THRESHOLD,
+};
+use crate::debounce::Debouncer;
+use crate::degraded::{DegradedReason, DegradedTracker};
+use crate::errors::ErrorLog;
+use crate::history_vault::{EncryptedHistory, HistoryKey};
+use crate::listen::RecordingsInFlight;
+use crate::model_download::Downloads;
+use crate::output::{OutputCoordinator, DEFAULT_CLIPBOARD_DWELL_MS};
+use crate::ptt::{HidBinding, PttController};
+use crate::render::RenderTracker;
+use crate::session::SessionJournal;
+use crate::state_events::StateEvents;
+use crate::tasks::TaskRegistry;
+use crate::text::fillers::FillerMode;
+use crate::text::{Snippet, TextDiff};
+use crate::watch::{FolderWatcher, WatchedFolder};
+use crate::whisper::decode::{AdvancedDecoding, DecodeOverride, DecodingOptions};
+use crate::whisper::jobs::CancelToken;
+use crate::whisper::language::LanguageDecision;
+use crate::whisper::load_failure::LoadFailureKind;
+use crate::whisper::loading::LoadSlot;
+use crate::whisper::prompt::DictationContext;
+use crate::whisper::streaming::DEFAULT_PARTIAL_INTERVAL_MS;
+use crate::whisper::suitability::WarningLimiter;
+use crate::whisper::{ModelCapabilities, WhisperWorker, DEFAULT_NO_SPEECH_THRESHOLD};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Duration;
 use tauri::AppHandle;
 use tauri_plugin_store::StoreExt;
 
@@ -16,14 +46,78 @@ pub const SETTINGS_STORE_FILE: &str = "settings.json";
 /// The pre-v0.1.8 frontend wrote here too, so v0.1.7 settings.json
 /// files load transparently.
 pub const SETTINGS_STORE_KEY: &str = "settings";
+/// Settings are written to disk at most this often; see
+/// `crate::debounce`.
+pub const SETTINGS_FLUSH_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AppStatus {
     Idle,
     Listening,
+    /// Recording suspended; capture resumes into the same buffer.
+    Paused,
     Processing,
+    /// A recording or transcription is being abandoned; returns to
+    /// `Idle` once the work has stopped.
+    Cancelling,
     Error,
+    /// Every model load failed (auto-load + one retry). Dictation is
+    /// refused until a later load succeeds. See `crate::degraded`.
+    Degraded,
+}
+
+impl AppStatus {
+    pub const ALL: [AppStatus; 7] = [
+        AppStatus::Idle,
+        AppStatus::Listening,
+        AppStatus::Paused,
+        AppStatus::Processing,
+        AppStatus::Cancelling,
+        AppStatus::Error,
+        AppStatus::Degraded,
+    ];
+
+    /// A recording is under way, paused or not.
+    pub fn is_recording(self) -> bool {
+        matches!(self, AppStatus::Listening | AppStatus::Paused)
+    }
+
+    /// The transition table. Anything not listed — including staying
+    /// put — is a bug or a race (a second `start_listen` while
+    /// listening, a `stop_listen` that arrives after the recording
+    /// already ended) and is refused by `AppState::transition`. A
+    /// recording can start while the last one is transcribed; see
+    /// `crate::listen` for when.
+    pub fn can_transition_to(self, next: AppStatus) -> bool {
+        use AppStatus::*;
+        matches!(
+            (self, next),
+            (Idle, Listening | Error | Degraded)
+                | (Listening, Paused | Processing | Cancelling | Idle | Error)
+                | (Paused, Listening | Processing | Cancelling | Error)
+                | (Processing, Idle | Listening | Cancelling | Error)
+                | (Cancelling, Idle | Error)
+                | (Error, Idle | Listening | Degraded)
+                | (Degraded, Idle)
+        )
+    }
+}
+
+/// A status change that happened. Also the `state:change` payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StatusChange {
+    pub from: AppStatus,
+    pub to: AppStatus,
+}
+
+/// A transition the table doesn't allow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, thiserror::Error)]
+#[serde(rename_all = "camelCase")]
+#[error("invalid status transition {from:?} -> {to:?}")]
+pub struct StatusTransitionError {
+    pub from: AppStatus,
+    pub to: AppStatus,
 }
 
 /// All language codes Whisper actually understands. Kept as a static array
@@ -128,6 +222,23 @@ pub struct HistoryEntry {
     pub model_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub duration_ms: Option<u64>,
+    /// Produced by `retranscribe_last` rather than a new recording.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub retry: bool,
+    /// For retries: what changed from the previous transcription.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<TextDiff>,
+}
+
+/// A custom-vocabulary term, usually learned from a user correction
+/// (see `crate::text::vocab`). `heard` keeps the misrecognitions that
+/// led to it so a later corrections pass can map them back.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VocabularyEntry {
+    pub term: String,
+    #[serde(default)]
+    pub heard: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -139,8 +250,8 @@ pub struct Settings {
     pub language: Language,
     pub model: String,
     pub shortcut: String,
-    /// Whether transcripts copy to the clipboard automatically after
-    /// the `transcript:final` event. Frontend mirror: `autoCopy`.
+    /// Whether finished transcripts are copied to the clipboard (through
+    /// `crate::output`). Frontend mirror: `autoCopy`.
     #[serde(default = "default_auto_copy")]
     pub auto_copy: bool,
     /// Optional shortcut to cycle through favorite languages. Empty = unbound.
@@ -175,6 +286,11 @@ pub struct Settings {
     /// Order is the import order = the order they appear in the cycle.
     #[serde(default)]
     pub user_models: Vec<UserModel>,
+    /// The file last loaded by `load_whisper_model_from_path`, whatever
+    /// its name; model id `custom` while selected. Frontend mirror:
+    /// `customModelPath`.
+    #[serde(default)]
+    pub custom_model_path: Option<PathBuf>,
     /// Model ids (built-in or user-imported) the user has marked as
     /// disabled. Disabled models are skipped by the cycle shortcuts
     /// but stay selectable manually via Settings (which implicitly
@@ -186,6 +302,12 @@ pub struct Settings {
     /// the user's recent transcripts survive restarts.
     #[serde(default)]
     pub history: Vec<HistoryEntry>,
+    /// When set, the history is encrypted at rest: this is what gets
+    /// persisted, and `history` only holds the decrypted entries in
+    /// memory while unlocked (see `crate::history_vault`). Not mirrored
+    /// in the frontend; `get_app_status` reports `historyLocked`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_encryption: Option<EncryptedHistory>,
     /// Whether the user dismissed the Vulkan-not-available warning
     /// permanently. Frontend mirror: `vulkanWarningDismissed`.
     #[serde(default)]
@@ -194,6 +316,210 @@ pub struct Settings {
     /// permanently. Frontend mirror: `welcomeDismissed`.
     #[serde(default)]
     pub welcome_dismissed: bool,
+    /// Translate dictation to English instead of transcribing in the
+    /// spoken language. Requires a multilingual model. Frontend mirror:
+    /// `translate`.
+    #[serde(default)]
+    pub translate: bool,
+    /// Custom vocabulary (proper nouns, domain terms). Only grows
+    /// through `accept_vocabulary_suggestions`. Frontend mirror:
+    /// `vocabulary`.
+    #[serde(default)]
+    pub vocabulary: Vec<VocabularyEntry>,
+    /// Optional shortcut running `learn_from_clipboard`. Empty = unbound.
+    #[serde(default)]
+    pub learn_vocabulary_shortcut: String,
+    /// Per-language decode overrides keyed by language code (e.g. `"ja"`).
+    /// Layered on top of the built-in language defaults — see
+    /// `crate::whisper::decode`. Frontend mirror: `decodeOverrides`.
+    #[serde(default)]
+    pub decode_overrides: HashMap<String, DecodeOverride>,
+    /// Global decoding strategy (greedy `bestOf` or beam search with
+    /// `beamSize`/`patience`). `None` = engine defaults plus built-in
+    /// per-language tuning. Frontend mirror: `decodingStrategy`.
+    #[serde(default)]
+    pub decoding_strategy: Option<DecodingOptions>,
+    /// Voice-triggered text snippets, in creation order. Managed by the
+    /// `add_snippet` / `update_snippet` / `delete_snippet` commands.
+    /// Frontend mirror: `snippets`.
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+    /// Filler words ("um", "euh"): keep, remove or mark. Voice-activated
+    /// (verbatim) sessions always keep them. Frontend mirror: `fillerMode`.
+    #[serde(default)]
+    pub filler_mode: FillerMode,
+    /// Extra filler words by language code, on top of the built-in
+    /// lexicons (see `text::fillers`). Frontend mirror: `fillerWords`.
+    #[serde(default)]
+    pub filler_words: HashMap<String, Vec<String>>,
+    /// Temperature and fallback thresholds (whisper.cpp defaults).
+    /// Frontend mirror: `advancedDecoding`.
+    #[serde(default)]
+    pub advanced_decoding: AdvancedDecoding,
+    /// Segments whose no-speech probability exceeds this are dropped as
+    /// hallucinations. Frontend mirror: `noSpeechThreshold`.
+    #[serde(default = "default_no_speech_threshold")]
+    pub no_speech_threshold: f32,
+    /// Suppress non-speech annotations (`[Music]`, `(applause)`, ...).
+    /// Frontend mirror: `suppressNonSpeech`.
+    #[serde(default = "default_suppress_non_speech")]
+    pub suppress_non_speech: bool,
+    /// Decode reproducibly, for debugging (see `whisper::deterministic`).
+    /// `--deterministic` turns it on for a session without this.
+    /// Frontend mirror: `deterministicMode`.
+    #[serde(default)]
+    pub deterministic_mode: bool,
+    /// VAD-detected speech (ms) a recording needs to be transcribed.
+    /// Frontend mirror: `minSpeechMs`.
+    #[serde(default = "default_min_speech_ms")]
+    pub min_speech_ms: u32,
+    /// RMS (0 to 1) over which the VAD counts a chunk as speech. Set
+    /// via `calibrate_noise_floor` or `set_vad_config`. Frontend mirror:
+    /// `speechThreshold`.
+    #[serde(default = "default_speech_threshold")]
+    pub speech_threshold: f32,
+    /// Silence (ms) after which the VAD says speech ended. Set via
+    /// `set_vad_config`. Frontend mirror: `silenceTimeoutMs`.
+    #[serde(default = "default_silence_timeout_ms")]
+    pub silence_timeout_ms: u32,
+    /// Follow the room's noise instead of `speech_threshold`: the
+    /// threshold is then the noise floor times `noise_factor` (see
+    /// `audio::noise_floor`). Set via `set_adaptive_threshold`.
+    /// Frontend mirror: `adaptiveThreshold`.
+    #[serde(default)]
+    pub adaptive_threshold: bool,
+    /// Frontend mirror: `noiseFactor`.
+    #[serde(default = "default_noise_factor")]
+    pub noise_factor: f32,
+    /// What detects speech: the RMS threshold above, or Silero's model.
+    /// Set via `set_vad_backend`. Frontend mirror: `vadBackend`.
+    #[serde(default)]
+    pub vad_backend: VadBackend,
+    /// Transcribe even when the VAD detected too little speech.
+    /// Frontend mirror: `forceTranscription`.
+    #[serde(default)]
+    pub force_transcription: bool,
+    /// Never write transcripts to disk; disables session crash recovery.
+    /// Frontend mirror: `privacyMode`.
+    #[serde(default)]
+    pub privacy_mode: bool,
+    /// Save every recording under `recordings/` in the data directory,
+    /// for bug reports; not in privacy mode. Frontend mirror:
+    /// `debugSaveRecordings`.
+    #[serde(default)]
+    pub debug_save_recordings: bool,
+    /// Emit `transcript:partial` while recording. Frontend mirror:
+    /// `streamingPartials`.
+    #[serde(default)]
+    pub streaming_partials: bool,
+    /// How often a partial pass runs, in ms of recorded audio.
+    /// Frontend mirror: `partialIntervalMs`.
+    #[serde(default = "default_partial_interval_ms")]
+    pub partial_interval_ms: u32,
+    /// Transcriptions that may be queued or running at once; more are
+    /// refused as busy. Frontend mirror: `transcriptionQueueDepth`.
+    #[serde(default = "default_transcription_queue_depth")]
+    pub transcription_queue_depth: u32,
+    /// A transcription running longer than this (seconds) is aborted.
+    /// Frontend mirror: `transcriptionTimeoutSecs`.
+    #[serde(default = "default_transcription_timeout_secs")]
+    pub transcription_timeout_secs: u32,
+    /// Prepend recent dictation to the prompt of the next one.
+    /// Frontend mirror: `contextCarryOver`.
+    #[serde(default)]
+    pub context_carry_over: bool,
+    /// Idle gap (seconds) after which carried-over context is dropped;
+    /// 0 keeps it for the whole app session. Frontend mirror:
+    /// `contextIdleResetSecs`.
+    #[serde(default = "default_context_idle_reset_secs")]
+    pub context_idle_reset_secs: u32,
+    /// Minimum time between two clipboard writes. Frontend mirror:
+    /// `clipboardDwellMs`.
+    #[serde(default = "default_clipboard_dwell_ms")]
+    pub clipboard_dwell_ms: u32,
+    /// Join transcripts arriving within the dwell time into one
+    /// clipboard write. Frontend mirror: `clipboardAppend`.
+    #[serde(default)]
+    pub clipboard_append: bool,
+    /// Load models with flash attention. Set through
+    /// `load_whisper_model_with_options`. Frontend mirror: `flashAttention`.
+    #[serde(default)]
+    pub flash_attention: bool,
+    /// GPU index models load on; `None` uses device 0. Frontend
+    /// mirror: `gpuDevice`.
+    #[serde(default)]
+    pub gpu_device: Option<u32>,
+    /// Quantization suffix tried first when resolving a built-in model's
+    /// file (`ggml-{model}-{quantization}.bin`); empty tries only the
+    /// plain name. Frontend mirror: `quantization`.
+    #[serde(default = "default_quantization")]
+    pub quantization: String,
+    /// Folder searched for models before the app's own (a folder
+    /// shared with other whisper tools, say); empty for none. See
+    /// `model_files::ModelLocation`. Frontend mirror: `modelsDir`.
+    #[serde(default)]
+    pub models_dir: String,
+    /// HID device (foot pedal) bound as push-to-talk, if any. Set via
+    /// `start_ptt_binding` + `bind_ptt_device`. Frontend mirror: `pttDevice`.
+    #[serde(default)]
+    pub ptt_device: Option<HidBinding>,
+    /// Input rates measured for devices that misreport theirs, by
+    /// device name (see `audio::rate`). Backend-only, not mirrored.
+    #[serde(default)]
+    pub sample_rate_overrides: HashMap<String, u32>,
+    /// Input device to record from, by `AudioDevice::id`; `None` for
+    /// the system default. Set via `set_audio_device`. Frontend mirror:
+    /// `preferredDevice`.
+    #[serde(default)]
+    pub preferred_device: Option<String>,
+    /// Input gain in dB, `MIN_GAIN_DB..=MAX_GAIN_DB` (see `audio::gain`).
+    /// Set via `set_input_gain`. Frontend mirror: `gain`.
+    #[serde(default)]
+    pub gain: f32,
+    /// Automatic gain control on the input (see `audio::agc`). Set via
+    /// `set_agc`. Frontend mirror: `agc`.
+    #[serde(default)]
+    pub agc: bool,
+    /// Filters on the input (see `audio::filters`). Set via
+    /// `set_audio_filters`. Frontend mirror: `audioFilters`.
+    #[serde(default)]
+    pub audio_filters: AudioFilters,
+    /// Which of the input's channels are recorded (see
+    /// `audio::channels`). Set via `set_channel_mode`. Frontend mirror:
+    /// `channelMode`.
+    #[serde(default)]
+    pub channel_mode: ChannelMode,
+    /// What recordings capture by default: the microphone or the system
+    /// audio (see `audio::source`). Set via `set_capture_source`.
+    /// Frontend mirror: `captureSource`.
+    #[serde(default)]
+    pub capture_source: CaptureSource,
+    /// The application system audio is recorded from alone, by
+    /// `CaptureApplication::name`; `None` for all of it. Set via
+    /// `set_capture_application`. Frontend mirror: `captureApplication`.
+    #[serde(default)]
+    pub capture_application: Option<String>,
+    /// Audio kept from before each recording, in ms; 0 for none, else
+    /// `MIN_PRE_ROLL_MS..=MAX_PRE_ROLL_MS`. Holds the microphone open
+    /// between recordings (see `audio::pre_roll`). Set via
+    /// `set_pre_roll`. Frontend mirror: `preRollMs`.
+    #[serde(default)]
+    pub pre_roll_ms: u32,
+    /// Folders whose new audio files are transcribed, in the order
+    /// added. Set via `watch_folder` / `unwatch_folder`. Frontend
+    /// mirror: `watchedFolders`.
+    #[serde(default)]
+    pub watched_folders: Vec<WatchedFolder>,
+    /// Memory for recordings kept after their transcription, in MB
+    /// (see `audio::retained`). Frontend mirror: `retainedAudioMb`.
+    #[serde(default = "default_retained_audio_mb")]
+    pub retained_audio_mb: u32,
+    /// Past this many MB of audio in memory, a capture moves to a temp
+    /// file in the cache dir (see `audio::spill`); 0 keeps it all in
+    /// RAM. Set via `set_capture_spill_mb`. Frontend mirror:
+    /// `captureSpillMb`.
+    #[serde(default)]
+    pub capture_spill_mb: u32,
 }
 
 fn default_auto_copy() -> bool {
@@ -202,6 +528,58 @@ fn default_auto_copy() -> bool {
     true
 }
 
+fn default_transcription_queue_depth() -> u32 {
+    crate::whisper::jobs::DEFAULT_MAX_QUEUE_DEPTH as u32
+}
+
+fn default_transcription_timeout_secs() -> u32 {
+    120
+}
+
+fn default_quantization() -> String {
+    "q5_0".to_string()
+}
+
+fn default_min_speech_ms() -> u32 {
+    DEFAULT_MIN_SPEECH_MS
+}
+
+fn default_speech_threshold() -> f32 {
+    DEFAULT_SPEECH_THRESHOLD
+}
+
+fn default_silence_timeout_ms() -> u32 {
+    DEFAULT_SILENCE_TIMEOUT_MS
+}
+
+fn default_noise_factor() -> f32 {
+    DEFAULT_NOISE_FACTOR
+}
+
+fn default_clipboard_dwell_ms() -> u32 {
+    DEFAULT_CLIPBOARD_DWELL_MS
+}
+
+fn default_context_idle_reset_secs() -> u32 {
+    300
+}
+
+fn default_partial_interval_ms() -> u32 {
+    DEFAULT_PARTIAL_INTERVAL_MS
+}
+
+fn default_suppress_non_speech() -> bool {
+    true
+}
+
+fn default_no_speech_threshold() -> f32 {
+    DEFAULT_NO_SPEECH_THRESHOLD
+}
+
+fn default_retained_audio_mb() -> u32 {
+    DEFAULT_RETAINED_AUDIO_MB
+}
+
 fn default_language_cycle_mode() -> String {
     "model-first".to_string()
 }
@@ -219,25 +597,79 @@ impl Default for Settings {
             model_languages: HashMap::new(),
             language_cycle_mode: default_language_cycle_mode(),
             user_models: Vec::new(),
+            custom_model_path: None,
             disabled_models: Vec::new(),
             history: Vec::new(),
+            history_encryption: None,
             vulkan_warning_dismissed: false,
             welcome_dismissed: false,
+            translate: false,
+            vocabulary: Vec::new(),
+            learn_vocabulary_shortcut: String::new(),
+            decode_overrides: HashMap::new(),
+            decoding_strategy: None,
+            snippets: Vec::new(),
+            filler_mode: FillerMode::default(),
+            filler_words: HashMap::new(),
+            advanced_decoding: AdvancedDecoding::default(),
+            no_speech_threshold: default_no_speech_threshold(),
+            suppress_non_speech: default_suppress_non_speech(),
+            deterministic_mode: false,
+            min_speech_ms: default_min_speech_ms(),
+            speech_threshold: default_speech_threshold(),
+            silence_timeout_ms: default_silence_timeout_ms(),
+            adaptive_threshold: false,
+            noise_factor: default_noise_factor(),
+            vad_backend: VadBackend::default(),
+            force_transcription: false,
+            privacy_mode: false,
+            debug_save_recordings: false,
+            streaming_partials: false,
+            partial_interval_ms: default_partial_interval_ms(),
+            transcription_queue_depth: default_transcription_queue_depth(),
+            transcription_timeout_secs: default_transcription_timeout_secs(),
+            context_carry_over: false,
+            context_idle_reset_secs: default_context_idle_reset_secs(),
+            clipboard_dwell_ms: default_clipboard_dwell_ms(),
+            clipboard_append: false,
+            gpu_device: None,
+            quantization: default_quantization(),
+            models_dir: String::new(),
+            flash_attention: false,
+            ptt_device: None,
+            sample_rate_overrides: HashMap::new(),
+            preferred_device: None,
+            gain: 0.0,
+            agc: false,
+            audio_filters: AudioFilters::default(),
+            channel_mode: ChannelMode::default(),
+            capture_source: CaptureSource::default(),
+            capture_application: None,
+            pre_roll_ms: 0,
+            watched_folders: Vec::new(),
+            retained_audio_mb: default_retained_audio_mb(),
+            capture_spill_mb: 0,
         }
     }
 }
 
 impl Settings {
     /// Persist the current Settings to `settings.json` via
-    /// `tauri-plugin-store`. Called by every mutator command after
-    /// the in-memory mutation, so the disk and the AppState never
-    /// drift. Returns the Tauri-plugin-store error stringified for
+    /// `tauri-plugin-store`. Called by `AppState::settings_writer`,
+    /// which mutator commands mark dirty after the in-memory mutation,
+    /// so the disk trails the AppState by at most
+    /// `SETTINGS_FLUSH_INTERVAL`. Returns the Tauri-plugin-store error stringified for
     /// easy `?`-propagation in command results.
     pub fn persist(&self, app: &AppHandle) -> Result<(), String> {
         let store = app
-            .store(SETTINGS_STORE_FILE)
+            .store(crate::paths::get().settings_file())
             .map_err(|e| format!("open store {SETTINGS_STORE_FILE}: {e}"))?;
-        let value = serde_json::to_value(self).map_err(|e| format!("serialise Settings: {e}"))?;
+        let mut value =
+            serde_json::to_value(self).map_err(|e| format!("serialise Settings: {e}"))?;
+        // An encrypted history must never reach the disk in clear.
+        if self.history_encryption.is_some() {
+            value["history"] = serde_json::Value::Array(Vec::new());
+        }
         store.set(SETTINGS_STORE_KEY, value);
         store.save().map_err(|e| format!("save store: {e}"))?;
         Ok(())
@@ -248,7 +680,7 @@ impl Settings {
     /// that's the first-launch case and any legitimate I/O error
     /// is logged but doesn't block app boot.
     pub fn load_from_disk(app: &AppHandle) -> Self {
-        let store = match app.store(SETTINGS_STORE_FILE) {
+        let store = match app.store(crate::paths::get().settings_file()) {
             Ok(s) => s,
             Err(e) => {
                 tracing::warn!("settings store unavailable, using defaults: {e}");
@@ -286,6 +718,31 @@ pub struct AppStateInner {
     /// re-attempt (the file may have been fixed, the GPU driver
     /// updated, etc.). Cycle shortcuts skip ids in this set.
     pub broken_models: HashSet<String>,
+    /// Consecutive model-load failures and, once past the threshold,
+    /// the reason the app is running degraded. Not persisted.
+    pub degraded: DegradedTracker,
+}
+
+impl AppStateInner {
+    fn transition(&mut self, to: AppStatus) -> Result<StatusChange, StatusTransitionError> {
+        let from = self.status;
+        if !from.can_transition_to(to) {
+            let err = StatusTransitionError { from, to };
+            tracing::warn!("Refused: {}", err);
+            return Err(err);
+        }
+        self.status = to;
+        // A load that failed during a session degrades the app once the
+        // session is over
+        if to == AppStatus::Idle && self.degraded.is_degraded() {
+            self.status = AppStatus::Degraded;
+            return Ok(StatusChange {
+                from,
+                to: AppStatus::Degraded,
+            });
+        }
+        Ok(StatusChange { from, to })
+    }
 }
 
 impl Default for AppStateInner {
@@ -296,6 +753,7 @@ impl Default for AppStateInner {
             permissions: Permissions::default(),
             vu_level: 0.0,
             broken_models: HashSet::new(),
+            degraded: DegradedTracker::default(),
         }
     }
 }
@@ -306,6 +764,80 @@ pub struct AppState {
     pub audio_capture: Arc<AudioCapture>,
     pub vad: Arc<RwLock<VoiceActivityDetector>>,
     pub whisper: Arc<WhisperWorker>,
+    /// Every background task the app spawns. See `crate::tasks`.
+    pub tasks: TaskRegistry,
+    /// HID push-to-talk reader and binding flow. See `crate::ptt`.
+    pub ptt: Arc<PttController>,
+    /// Crash-recovery journal of the running session, if it keeps one.
+    pub journal: Arc<Mutex<Option<SessionJournal>>>,
+    /// Recent dictation carried into the next prompt. Not persisted.
+    pub dictation_context: Arc<Mutex<DictationContext>>,
+    /// Spaces and merges clipboard writes. See `crate::output`.
+    pub output: Arc<Mutex<OutputCoordinator>>,
+    /// The last transcribed recording, for `retranscribe_last`. Not
+    /// persisted.
+    pub last_recording: Arc<Mutex<Option<LastRecording>>>,
+    /// Recordings kept in memory, within `Settings.retained_audio_mb`.
+    /// See `audio::retained`.
+    pub retained_audio: Arc<Mutex<RetainedAudio>>,
+    /// How the language of the last transcription was settled, with the
+    /// model it ran on, for `explain_language_choice`. Not persisted.
+    pub language_decision: Arc<Mutex<Option<(String, LanguageDecision)>>>,
+    /// Cancel tokens of the recordings' transcription jobs running, by
+    /// job id.
+    pub transcription_jobs: Arc<Mutex<BTreeMap<u64, CancelToken>>>,
+    /// Recordings stopped and still processed. See `crate::listen`.
+    pub processing: Arc<Mutex<RecordingsInFlight>>,
+    /// Model × language combinations already warned about this session.
+    pub config_warnings: Arc<Mutex<WarningLimiter>>,
+    /// Key of the encrypted history while it is unlocked.
+    pub history_key: Arc<Mutex<Option<HistoryKey>>>,
+    /// Recent errors; fed only through `errors::report`.
+    pub recent_errors: Arc<Mutex<ErrorLog>>,
+    /// Overlay bits for `get_render_state`. See `crate::render`.
+    pub render: Arc<Mutex<RenderTracker>>,
+    /// The running recording is transcribed verbatim (no filler
+    /// removal). Set by `start_listen` from the listen mode.
+    pub verbatim: Arc<AtomicBool>,
+    /// The model load in progress, if any; one at a time.
+    pub model_load: LoadSlot,
+    /// Orders `state:change` emissions behind the frontend's acks. See
+    /// `crate::state_events`.
+    pub state_events: StateEvents,
+    /// Writes the settings to disk, coalescing bursts of changes. See
+    /// `crate::debounce`.
+    pub settings_writer: Debouncer,
+    /// Model downloads in flight. See `crate::model_download`.
+    pub downloads: Downloads,
+    /// Watches `Settings.watched_folders`. See `crate::watch`.
+    pub folder_watcher: Arc<FolderWatcher>,
+    /// Label of the window the level monitor runs for; it stops when
+    /// that window closes.
+    pub level_monitor: Arc<Mutex<Option<String>>>,
+    /// The running continuous recording's utterances, if any.
+    pub utterances: Arc<Mutex<Option<UtteranceQueue>>>,
+}
+
+/// Where a continuous recording's utterances go (see
+/// `audio::utterance`): the chunk task cuts them into `sender`, and
+/// `done` has the transcribing task's outcome once the last one is
+/// through.
+pub struct UtteranceQueue {
+    pub sender: tokio::sync::mpsc::Sender<UtteranceAudio>,
+    pub done: tokio::sync::oneshot::Receiver<Result<String, String>>,
+}
+
+/// The last transcribed recording.
+pub struct LastRecording {
+    /// Its audio in `retained_audio`; gone once evicted.
+    pub audio: RetainedId,
+    pub text: String,
+    /// The VAD's speech spans (ms), to check a re-transcription's
+    /// coverage against.
+    pub speech: Vec<Range<u64>>,
+    /// Transcribed with its fillers kept; a re-transcription keeps them
+    /// too.
+    pub verbatim: bool,
 }
 
 impl AppState {
@@ -315,6 +847,28 @@ impl AppState {
             audio_capture: Arc::new(AudioCapture::new()),
             vad: Arc::new(RwLock::new(VoiceActivityDetector::new())),
             whisper: Arc::new(WhisperWorker::new()),
+            tasks: TaskRegistry::new(),
+            ptt: Arc::new(PttController::default()),
+            journal: Arc::new(Mutex::new(None)),
+            dictation_context: Arc::new(Mutex::new(DictationContext::default())),
+            output: Arc::new(Mutex::new(OutputCoordinator::default())),
+            last_recording: Arc::new(Mutex::new(None)),
+            retained_audio: Arc::new(Mutex::new(RetainedAudio::default())),
+            language_decision: Arc::new(Mutex::new(None)),
+            transcription_jobs: Arc::new(Mutex::new(BTreeMap::new())),
+            processing: Arc::new(Mutex::new(RecordingsInFlight::default())),
+            config_warnings: Arc::new(Mutex::new(WarningLimiter::default())),
+            history_key: Arc::new(Mutex::new(None)),
+            recent_errors: Arc::new(Mutex::new(ErrorLog::default())),
+            render: Arc::new(Mutex::new(RenderTracker::default())),
+            verbatim: Arc::new(AtomicBool::new(false)),
+            model_load: LoadSlot::default(),
+            state_events: StateEvents::default(),
+            settings_writer: Debouncer::new(SETTINGS_FLUSH_INTERVAL),
+            downloads: Downloads::default(),
+            folder_watcher: Arc::new(FolderWatcher::default()),
+            level_monitor: Arc::new(Mutex::new(None)),
+            utterances: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -322,8 +876,10 @@ impl AppState {
         self.inner.read().status
     }
 
-    pub fn set_status(&self, status: AppStatus) {
-        self.inner.write().status = status;
+    /// Move to `to` if the transition table allows it. Refused
+    /// transitions leave the status untouched and are logged.
+    pub fn transition(&self, to: AppStatus) -> Result<StatusChange, StatusTransitionError> {
+        self.inner.write().transition(to)
     }
 
     pub fn get_settings(&self) -> Settings {
@@ -337,6 +893,11 @@ impl AppState {
         f(&mut self.inner.write().settings);
     }
 
+    /// History encryption is on and the key isn't loaded.
+    pub fn history_locked(&self) -> bool {
+        self.inner.read().settings.history_encryption.is_some() && self.history_key.lock().is_none()
+    }
+
     pub fn get_permissions(&self) -> Permissions {
         self.inner.read().permissions.clone()
     }
@@ -353,6 +914,21 @@ impl AppState {
         self.inner.write().vu_level = level.clamp(0.0, 1.0);
     }
 
+    /// Load Silero's model on a blocking thread when the VAD waits for
+    /// it, and install it once read: the chunk loop never holds the VAD
+    /// over the file, and the threshold decides meanwhile. Call after
+    /// setting the backend.
+    pub fn load_vad_model(&self) {
+        let Some(path) = self.vad.write().begin_loading() else {
+            return;
+        };
+        let vad = Arc::clone(&self.vad);
+        tauri::async_runtime::spawn_blocking(move || {
+            let loaded = crate::audio::load_silero(&path);
+            vad.write().install_silero(loaded);
+        });
+    }
+
     // ---- Custom-model registry helpers --------------------------------
     // All three mutating helpers below take the write lock for the full
     // read-modify-write so two concurrent imports can't lose entries.
@@ -422,6 +998,49 @@ impl AppState {
         self.inner.read().broken_models.contains(id)
     }
 
+    // ---- Degraded mode ----------------------------------------------
+
+    /// Record a failed model load. Returns the new degraded reason when
+    /// this failure tips the app into (or changes) degraded mode, and the
+    /// status change if there was one; the status flip happens here under
+    /// the same lock so a concurrent `start_listen` can't slip through
+    /// between the two. Only from Idle or Error: a recording under way
+    /// finishes first, and its way back to Idle leads to Degraded.
+    pub fn record_load_failure(
+        &self,
+        cause: LoadFailureKind,
+        message: &str,
+    ) -> (Option<DegradedReason>, Option<StatusChange>) {
+        let mut inner = self.inner.write();
+        let reason = inner.degraded.record_failure(cause, message);
+        let change = if inner.degraded.is_degraded()
+            && matches!(inner.status, AppStatus::Idle | AppStatus::Error)
+        {
+            inner.transition(AppStatus::Degraded).ok()
+        } else {
+            None
+        };
+        (reason, change)
+    }
+
+    /// Record a successful model load. Returns `true` if it cleared a
+    /// degraded state, and the status change if status went back to
+    /// `Idle`.
+    pub fn record_load_success(&self) -> (bool, Option<StatusChange>) {
+        let mut inner = self.inner.write();
+        let cleared = inner.degraded.record_success();
+        let change = if cleared && inner.status == AppStatus::Degraded {
+            inner.transition(AppStatus::Idle).ok()
+        } else {
+            None
+        };
+        (cleared, change)
+    }
+
+    pub fn degraded_reason(&self) -> Option<DegradedReason> {
+        self.inner.read().degraded.reason().cloned()
+    }
+
     /// Look up a user-imported model by id.
     pub fn find_user_model(&self, id: &str) -> Option<UserModel> {
         self.inner
@@ -554,6 +1173,8 @@ mod tests {
             timestamp: 1_700_000_000,
             model_id: Some("small".into()),
             duration_ms: Some(1234),
+            retry: true,
+            diff: crate::text::word_diff("hello", "hello there"),
         });
         let json = serde_json::to_string(&s).expect("serialise");
         let back: Settings = serde_json::from_str(&json).expect("deserialise");
@@ -561,6 +1182,8 @@ mod tests {
         assert_eq!(back.disabled_models, vec!["small".to_string()]);
         assert_eq!(back.history.len(), 1);
         assert_eq!(back.history[0].id, "abc");
+        assert!(back.history[0].retry);
+        assert_eq!(back.history[0].diff, s.history[0].diff);
         assert_eq!(back.history[0].duration_ms, Some(1234));
     }
 
@@ -643,6 +1266,83 @@ mod tests {
         assert!(!state.is_model_disabled("small"));
     }
 
+    #[test]
+    fn load_failures_flip_status_to_degraded_and_success_restores_idle() {
+        let state = AppState::new();
+        assert!(state
+            .record_load_failure(LoadFailureKind::GpuInit, "vulkan")
+            .0
+            .is_none());
+        assert_eq!(state.get_status(), AppStatus::Idle);
+        let (reason, change) = state.record_load_failure(LoadFailureKind::GpuInit, "vulkan");
+        assert!(reason.is_some());
+        assert_eq!(
+            change,
+            Some(StatusChange {
+                from: AppStatus::Idle,
+                to: AppStatus::Degraded
+            })
+        );
+        assert_eq!(state.get_status(), AppStatus::Degraded);
+        assert!(state.degraded_reason().is_some());
+
+        let (cleared, change) = state.record_load_success();
+        assert!(cleared);
+        assert_eq!(
+            change,
+            Some(StatusChange {
+                from: AppStatus::Degraded,
+                to: AppStatus::Idle
+            })
+        );
+        assert_eq!(state.get_status(), AppStatus::Idle);
+        assert!(state.degraded_reason().is_none());
+    }
+
+    #[test]
+    fn every_status_can_get_back_to_idle() {
+        for start in AppStatus::ALL {
+            let mut seen = vec![start];
+            let mut frontier = vec![start];
+            while let Some(status) = frontier.pop() {
+                for next in AppStatus::ALL {
+                    if status.can_transition_to(next) && !seen.contains(&next) {
+                        seen.push(next);
+                        frontier.push(next);
+                    }
+                }
+            }
+            assert!(seen.contains(&AppStatus::Idle), "{start:?} is a dead end");
+            if start == AppStatus::Idle {
+                assert_eq!(seen.len(), AppStatus::ALL.len(), "unreachable status");
+            }
+        }
+    }
+
+    #[test]
+    fn racy_transitions_are_refused() {
+        let state = AppState::new();
+        // A late stop_listen after the recording already ended
+        let err = state.transition(AppStatus::Processing).unwrap_err();
+        assert_eq!(
+            err,
+            StatusTransitionError {
+                from: AppStatus::Idle,
+                to: AppStatus::Processing
+            }
+        );
+        assert_eq!(state.get_status(), AppStatus::Idle);
+
+        state.transition(AppStatus::Listening).unwrap();
+        // Double start (shortcut + PTT pressed together)
+        assert!(state.transition(AppStatus::Listening).is_err());
+        state.transition(AppStatus::Processing).unwrap();
+        // A second stop_listen while transcribing
+        assert!(state.transition(AppStatus::Processing).is_err());
+        assert_eq!(state.get_status(), AppStatus::Processing);
+        state.transition(AppStatus::Idle).unwrap();
+    }
+
     #[test]
     fn broken_models_are_transient_and_per_id() {
         let state = AppState::new();
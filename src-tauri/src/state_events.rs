@@ -0,0 +1,255 @@
+//! `state:change` emission with an acknowledgment handshake.
+//!
+//! `stop_listen` used to sleep 50 ms after entering Processing so Vue
+//! wouldn't batch "processing" and "idle" into one render. Now every
+//! `state:change` carries a sequence number the frontend hands back
+//! through `ack_state` once it has applied it. The backend never waits
+//! for that itself — transcription starts right away — but the *next*
+//! `state:change` is held until the previous one is acknowledged or
+//! `ACK_TIMEOUT` has passed since it went out. A frontend that acks at
+//! once sees no delay; a slow or non-acking webview still gets its
+//! states one render apart.
+//!
+//! Tauri-free: the emit is a closure, and `StateEvents::queue` returns
+//! the future to spawn.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Notify};
+
+use crate::state::StatusChange;
+
+/// Longest an emission waits for the previous one's ack.
+pub const ACK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// `state:change` payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SequencedChange {
+    #[serde(flatten)]
+    pub change: StatusChange,
+    pub seq: u64,
+}
+
+/// Sequence numbers handed out and acknowledged.
+#[derive(Debug, Default)]
+pub struct AckGate {
+    /// Last sequence number sent.
+    sent: u64,
+    /// Highest sequence number acknowledged.
+    acked: u64,
+    /// When `sent` went out, while it is unacknowledged.
+    awaiting_since: Option<Instant>,
+}
+
+impl AckGate {
+    /// Number the emission going out at `now`.
+    pub fn send(&mut self, now: Instant) -> u64 {
+        self.sent += 1;
+        self.awaiting_since = Some(now);
+        self.sent
+    }
+
+    /// Record an ack. Acks of numbers never sent, or older than one
+    /// already acknowledged, are ignored (`false`).
+    pub fn ack(&mut self, seq: u64) -> bool {
+        if seq > self.sent || seq <= self.acked {
+            return false;
+        }
+        self.acked = seq;
+        if seq == self.sent {
+            self.awaiting_since = None;
+        }
+        true
+    }
+
+    /// When the next emission may go out; `None` for right away.
+    pub fn hold_until(&self) -> Option<Instant> {
+        self.awaiting_since.map(|since| since + ACK_TIMEOUT)
+    }
+}
+
+/// Orders `state:change` emissions and holds each behind the previous
+/// one's ack.
+#[derive(Clone, Default)]
+pub struct StateEvents {
+    gate: Arc<Mutex<AckGate>>,
+    acked: Arc<Notify>,
+    /// Resolves once the emission queued last has gone out.
+    last: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
+}
+
+impl StateEvents {
+    /// The frontend applied `seq`.
+    pub fn ack(&self, seq: u64) {
+        if self.gate.lock().ack(seq) {
+            self.acked.notify_one();
+        }
+    }
+
+    /// Queue `change`: the returned future waits for every emission
+    /// queued before it, then for the ack (or timeout), then calls
+    /// `emit`. Spawn it; emissions go out in `queue` order.
+    pub fn queue<F>(&self, change: StatusChange, emit: F) -> impl Future<Output = ()> + Send
+    where
+        F: FnOnce(SequencedChange) + Send,
+    {
+        let (done, next) = oneshot::channel();
+        let previous = self.last.lock().replace(next);
+        let events = self.clone();
+        async move {
+            if let Some(previous) = previous {
+                // A dropped sender (the emit panicked) releases us too
+                let _ = previous.await;
+            }
+            events.wait_for_ack().await;
+            let seq = events.gate.lock().send(Instant::now());
+            emit(SequencedChange { change, seq });
+            let _ = done.send(());
+        }
+    }
+
+    async fn wait_for_ack(&self) {
+        loop {
+            let Some(deadline) = self.gate.lock().hold_until() else {
+                return;
+            };
+            // `notify_one` keeps a permit when nobody waits yet, so an
+            // ack landing between the check and here isn't missed.
+            let acked = tokio::time::timeout_at(deadline.into(), self.acked.notified());
+            if acked.await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AppStatus;
+
+    fn change(from: AppStatus, to: AppStatus) -> StatusChange {
+        StatusChange { from, to }
+    }
+
+    /// Queue `changes` and record (seq, to, elapsed since start) as each
+    /// goes out.
+    fn queue_all(
+        events: &StateEvents,
+        changes: &[StatusChange],
+    ) -> Arc<Mutex<Vec<(u64, AppStatus, Duration)>>> {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let start = Instant::now();
+        for &change in changes {
+            let sink = sent.clone();
+            tokio::spawn(events.queue(change, move |sequenced| {
+                sink.lock()
+                    .push((sequenced.seq, sequenced.change.to, start.elapsed()));
+            }));
+        }
+        sent
+    }
+
+    async fn wait_for(sent: &Mutex<Vec<(u64, AppStatus, Duration)>>, n: usize) {
+        for _ in 0..200 {
+            if sent.lock().len() >= n {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("only {} of {n} emissions went out", sent.lock().len());
+    }
+
+    #[test]
+    fn ack_releases_the_gate() {
+        let mut gate = AckGate::default();
+        assert_eq!(gate.hold_until(), None);
+        let now = Instant::now();
+        let seq = gate.send(now);
+        assert_eq!(gate.hold_until(), Some(now + ACK_TIMEOUT));
+        assert!(gate.ack(seq));
+        assert_eq!(gate.hold_until(), None);
+    }
+
+    #[test]
+    fn out_of_order_acks() {
+        let mut gate = AckGate::default();
+        let now = Instant::now();
+        let first = gate.send(now);
+        let second = gate.send(now);
+        // A late ack of an older change doesn't release the newer one
+        assert!(gate.ack(first));
+        assert!(gate.hold_until().is_some());
+        assert!(gate.ack(second));
+        assert_eq!(gate.hold_until(), None);
+        // Stale and never-sent numbers are ignored
+        let third = gate.send(now);
+        assert!(!gate.ack(first));
+        assert!(!gate.ack(third + 1));
+        assert!(gate.hold_until().is_some());
+        assert!(gate.ack(third));
+    }
+
+    #[tokio::test]
+    async fn ack_before_timeout_sends_the_next_change_early() {
+        let events = StateEvents::default();
+        let sent = queue_all(
+            &events,
+            &[
+                change(AppStatus::Listening, AppStatus::Processing),
+                change(AppStatus::Processing, AppStatus::Idle),
+            ],
+        );
+        wait_for(&sent, 1).await;
+        assert_eq!(sent.lock()[0].0, 1);
+        events.ack(1);
+        wait_for(&sent, 2).await;
+        let sent = sent.lock();
+        assert_eq!(sent[1].0, 2);
+        assert_eq!(sent[1].1, AppStatus::Idle);
+        assert!(sent[1].2 < ACK_TIMEOUT, "{:?}", sent[1].2);
+    }
+
+    #[tokio::test]
+    async fn unacknowledged_change_holds_the_next_until_timeout() {
+        let events = StateEvents::default();
+        let sent = queue_all(
+            &events,
+            &[
+                change(AppStatus::Idle, AppStatus::Listening),
+                change(AppStatus::Listening, AppStatus::Processing),
+                change(AppStatus::Processing, AppStatus::Idle),
+            ],
+        );
+        wait_for(&sent, 3).await;
+        let sent = sent.lock();
+        // In order, each one a timeout after the one before
+        let order: Vec<_> = sent.iter().map(|(seq, to, _)| (*seq, *to)).collect();
+        assert_eq!(
+            order,
+            [
+                (1, AppStatus::Listening),
+                (2, AppStatus::Processing),
+                (3, AppStatus::Idle)
+            ]
+        );
+        assert!(sent[1].2 - sent[0].2 >= ACK_TIMEOUT);
+        assert!(sent[2].2 - sent[1].2 >= ACK_TIMEOUT);
+    }
+
+    #[test]
+    fn payload() {
+        let json = serde_json::to_value(SequencedChange {
+            change: change(AppStatus::Listening, AppStatus::Processing),
+            seq: 7,
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "from": "listening", "to": "processing", "seq": 7 })
+        );
+    }
+}
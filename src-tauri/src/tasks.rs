@@ -0,0 +1,305 @@
+//! Tracked background tasks.
+//!
+//! Every long-lived `tokio::spawn` in the app goes through
+//! [`TaskRegistry::spawn`] instead of being detached. The registry keeps
+//! a name and an abort handle per task, records how each one ended, and
+//! reports panics through a callback (wired to the `task:panicked`
+//! event in `lib.rs`) so they can't vanish silently. On exit,
+//! [`TaskRegistry::shutdown`] aborts whatever is still running and
+//! waits for it to unwind.
+//!
+//! Tauri-free on purpose so the panic path is unit-testable.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::task::{AbortHandle, JoinHandle};
+
+/// Finished tasks kept around for `get_task_status` after they exit.
+const MAX_FINISHED_TASKS: usize = 32;
+
+/// Lifecycle of a tracked task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    Running,
+    Finished,
+    Panicked,
+    Cancelled,
+}
+
+/// Diagnostics snapshot of one task, returned by `get_task_status`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskInfo {
+    pub id: u64,
+    pub name: String,
+    pub state: TaskState,
+    /// Panic message, only set when `state == Panicked`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Payload of the `task:panicked` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskPanic {
+    pub name: String,
+    pub message: String,
+}
+
+type PanicReporter = Arc<dyn Fn(TaskPanic) + Send + Sync>;
+
+struct Entry {
+    info: TaskInfo,
+    abort: AbortHandle,
+    supervisor: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: BTreeMap<u64, Entry>,
+    reporter: Option<PanicReporter>,
+    /// Runtime used when `spawn` is called from outside one (e.g. the
+    /// synchronous Tauri `setup` hook).
+    runtime: Option<Handle>,
+}
+
+/// Registry of named background tasks. Cheap to clone; lives in
+/// `AppState`.
+#[derive(Clone, Default)]
+pub struct TaskRegistry {
+    inner: Arc<Mutex<Inner>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Install the callback invoked whenever a tracked task panics.
+    pub fn set_panic_reporter(&self, reporter: impl Fn(TaskPanic) + Send + Sync + 'static) {
+        self.inner.lock().reporter = Some(Arc::new(reporter));
+    }
+
+    /// Runtime to spawn on when called outside a tokio context.
+    pub fn set_runtime(&self, handle: Handle) {
+        self.inner.lock().runtime = Some(handle);
+    }
+
+    /// Spawn `future` on the current tokio runtime (or the one given to
+    /// `set_runtime`) under `name` and track it until it ends. Returns
+    /// the task id.
+    pub fn spawn<F>(&self, name: &str, future: F) -> u64
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let runtime = Handle::try_current().unwrap_or_else(|_| {
+            self.inner
+                .lock()
+                .runtime
+                .clone()
+                .expect("TaskRegistry::spawn outside a runtime before set_runtime")
+        });
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let task = runtime.spawn(future);
+        let abort = task.abort_handle();
+
+        // Hold the lock across the supervisor spawn so the entry exists
+        // before the supervisor can try to update it, even when the task
+        // finishes immediately.
+        let mut inner = self.inner.lock();
+        let registry = self.clone();
+        let name_owned = name.to_string();
+        let supervisor = runtime.spawn(async move {
+            let (state, message) = match task.await {
+                Ok(()) => (TaskState::Finished, None),
+                Err(e) if e.is_cancelled() => (TaskState::Cancelled, None),
+                Err(e) => (TaskState::Panicked, Some(panic_message(e.into_panic()))),
+            };
+            registry.finish(id, state, message);
+        });
+        inner.entries.insert(
+            id,
+            Entry {
+                info: TaskInfo {
+                    id,
+                    name: name_owned,
+                    state: TaskState::Running,
+                    message: None,
+                },
+                abort,
+                supervisor: Some(supervisor),
+            },
+        );
+        prune_finished(&mut inner.entries);
+        id
+    }
+
+    fn finish(&self, id: u64, state: TaskState, message: Option<String>) {
+        let (reporter, panic) = {
+            let mut inner = self.inner.lock();
+            let Some(entry) = inner.entries.get_mut(&id) else {
+                return;
+            };
+            entry.info.state = state;
+            entry.info.message = message.clone();
+            entry.supervisor = None;
+            let panic = (state == TaskState::Panicked).then(|| TaskPanic {
+                name: entry.info.name.clone(),
+                message: message.unwrap_or_default(),
+            });
+            (inner.reporter.clone(), panic)
+        };
+        match state {
+            TaskState::Panicked => {
+                let panic = panic.expect("panicked state always builds a payload");
+                tracing::error!("Task '{}' panicked: {}", panic.name, panic.message);
+                if let Some(reporter) = reporter {
+                    reporter(panic);
+                }
+            }
+            _ => tracing::debug!("Task {} ended: {:?}", id, state),
+        }
+    }
+
+    /// Snapshot of every running task plus the most recent finished ones.
+    pub fn status(&self) -> Vec<TaskInfo> {
+        self.inner
+            .lock()
+            .entries
+            .values()
+            .map(|e| e.info.clone())
+            .collect()
+    }
+
+    /// Abort every running task and wait (up to `timeout`) for them to
+    /// unwind. Called once on app exit.
+    pub async fn shutdown(&self, timeout: Duration) {
+        let supervisors: Vec<JoinHandle<()>> = {
+            let mut inner = self.inner.lock();
+            inner
+                .entries
+                .values_mut()
+                .filter_map(|e| {
+                    e.abort.abort();
+                    e.supervisor.take()
+                })
+                .collect()
+        };
+        if supervisors.is_empty() {
+            return;
+        }
+        tracing::info!("Shutting down {} background task(s)", supervisors.len());
+        let all = async {
+            for s in supervisors {
+                let _ = s.await;
+            }
+        };
+        if tokio::time::timeout(timeout, all).await.is_err() {
+            tracing::warn!("Background tasks did not stop within {:?}", timeout);
+        }
+    }
+}
+
+/// Drop the oldest non-running entries beyond `MAX_FINISHED_TASKS`.
+fn prune_finished(entries: &mut BTreeMap<u64, Entry>) {
+    let finished: Vec<u64> = entries
+        .iter()
+        .filter(|(_, e)| e.info.state != TaskState::Running)
+        .map(|(id, _)| *id)
+        .collect();
+    let excess = finished.len().saturating_sub(MAX_FINISHED_TASKS);
+    for id in finished.into_iter().take(excess) {
+        entries.remove(&id);
+    }
+}
+
+/// What a caught panic said.
+pub(crate) fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn wait_until(registry: &TaskRegistry, id: u64, state: TaskState) -> TaskInfo {
+        for _ in 0..200 {
+            if let Some(info) = registry.status().into_iter().find(|t| t.id == id) {
+                if info.state == state {
+                    return info;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("task {id} never reached {state:?}");
+    }
+
+    #[tokio::test]
+    async fn panicking_task_is_reported_not_lost() {
+        let registry = TaskRegistry::new();
+        let reported = Arc::new(Mutex::new(Vec::<TaskPanic>::new()));
+        let sink = reported.clone();
+        registry.set_panic_reporter(move |p| sink.lock().push(p));
+
+        let id = registry.spawn("doomed", async {
+            panic!("deliberate test panic");
+        });
+
+        let info = wait_until(&registry, id, TaskState::Panicked).await;
+        assert_eq!(info.name, "doomed");
+        assert_eq!(info.message.as_deref(), Some("deliberate test panic"));
+
+        let reported = reported.lock();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].name, "doomed");
+        assert_eq!(reported[0].message, "deliberate test panic");
+    }
+
+    #[tokio::test]
+    async fn normal_exit_is_recorded_as_finished() {
+        let registry = TaskRegistry::new();
+        let id = registry.spawn("quick", async {});
+        let info = wait_until(&registry, id, TaskState::Finished).await;
+        assert!(info.message.is_none());
+    }
+
+    #[tokio::test]
+    async fn shutdown_cancels_running_tasks() {
+        let registry = TaskRegistry::new();
+        let id = registry.spawn("forever", std::future::pending());
+        assert_eq!(registry.status()[0].state, TaskState::Running);
+
+        registry.shutdown(Duration::from_secs(1)).await;
+        let info = wait_until(&registry, id, TaskState::Cancelled).await;
+        assert_eq!(info.name, "forever");
+    }
+
+    #[tokio::test]
+    async fn finished_entries_are_bounded() {
+        let registry = TaskRegistry::new();
+        let mut last = 0;
+        for _ in 0..(MAX_FINISHED_TASKS + 10) {
+            last = registry.spawn("tick", async {});
+            wait_until(&registry, last, TaskState::Finished).await;
+        }
+        // One more spawn triggers the prune.
+        registry.spawn("tail", std::future::pending());
+        let status = registry.status();
+        assert!(status.len() <= MAX_FINISHED_TASKS + 1);
+        assert!(status.iter().any(|t| t.id == last));
+        registry.shutdown(Duration::from_secs(1)).await;
+    }
+}
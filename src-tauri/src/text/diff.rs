@@ -0,0 +1,269 @@
+//! Word-level diff between two transcriptions of the same audio.
+//!
+//! When a recording is transcribed again (`retranscribe_last`), the UI
+//! highlights what changed instead of showing two walls of text. Words
+//! are whitespace-separated tokens — `split_whitespace` splits on every
+//! Unicode space, so CJK full-width spaces and NBSPs separate words too —
+//! compared exactly, punctuation included: "it's" becoming "its" is a
+//! change worth showing.
+//!
+//! The diff is Myers' O((N+M)·D) algorithm, which is fast when the texts
+//! are close (the usual case: a few words differ). Its trace still grows
+//! with D², so inputs over `MAX_DIFF_WORDS` on either side are not
+//! diffed at all.
+
+use serde::{Deserialize, Serialize};
+
+/// Above this many words on either side no diff is computed. Roughly
+/// seven minutes of dictation.
+pub const MAX_DIFF_WORDS: usize = 1000;
+
+/// A run of consecutive inserted (or deleted) words.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffSpan {
+    /// Index of the first word: in the new text for insertions, in the
+    /// previous text for deletions.
+    pub start: usize,
+    /// Number of words in the run.
+    pub len: usize,
+    /// The words, joined by single spaces.
+    pub text: String,
+}
+
+/// `transcript:diff` payload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDiff {
+    pub insertions: Vec<DiffSpan>,
+    pub deletions: Vec<DiffSpan>,
+    /// Unchanged words over the word count of the longer text; 1.0 for
+    /// identical (or two empty) texts, 0.0 for completely different ones.
+    pub unchanged_ratio: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal,
+    /// Index into the new words.
+    Insert(usize),
+    /// Index into the previous words.
+    Delete(usize),
+}
+
+/// Diff `previous` against `new`. `None` when either side is over
+/// `MAX_DIFF_WORDS`.
+pub fn word_diff(previous: &str, new: &str) -> Option<TextDiff> {
+    let a: Vec<&str> = previous.split_whitespace().collect();
+    let b: Vec<&str> = new.split_whitespace().collect();
+    if a.len() > MAX_DIFF_WORDS || b.len() > MAX_DIFF_WORDS {
+        tracing::debug!(
+            "Skipping diff: {} / {} words (max {})",
+            a.len(),
+            b.len(),
+            MAX_DIFF_WORDS
+        );
+        return None;
+    }
+
+    let edits = myers(&a, &b);
+    let unchanged = edits.iter().filter(|e| **e == Edit::Equal).count();
+    let longest = a.len().max(b.len());
+    let unchanged_ratio = if longest == 0 {
+        1.0
+    } else {
+        unchanged as f32 / longest as f32
+    };
+
+    let mut diff = TextDiff {
+        insertions: Vec::new(),
+        deletions: Vec::new(),
+        unchanged_ratio,
+    };
+    for edit in edits {
+        match edit {
+            Edit::Equal => {}
+            Edit::Insert(j) => extend_spans(&mut diff.insertions, j, b[j]),
+            Edit::Delete(i) => extend_spans(&mut diff.deletions, i, a[i]),
+        }
+    }
+    Some(diff)
+}
+
+/// Append word `index` to the last span if it continues it, else start
+/// a new span.
+fn extend_spans(spans: &mut Vec<DiffSpan>, index: usize, word: &str) {
+    if let Some(last) = spans.last_mut() {
+        if last.start + last.len == index {
+            last.len += 1;
+            last.text.push(' ');
+            last.text.push_str(word);
+            return;
+        }
+    }
+    spans.push(DiffSpan {
+        start: index,
+        len: 1,
+        text: word.to_string(),
+    });
+}
+
+/// Shortest edit script from `a` to `b`, in order.
+fn myers(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let offset = max + 1;
+    // v[offset + k] = furthest x reached on diagonal k
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    // trace[d] = the diagonals -d..=d of `v` after round d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let down =
+                k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]);
+            let mut x = if down {
+                v[(offset + k + 1) as usize]
+            } else {
+                v[(offset + k - 1) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+                break 'search;
+            }
+        }
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+    }
+
+    // Walk the trace back from (n, m).
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let prev = &trace[(d - 1) as usize];
+        let at = |k: isize| prev[(k + d - 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if x == prev_x {
+            edits.push(Edit::Insert(prev_y as usize));
+        } else {
+            edits.push(Edit::Delete(prev_x as usize));
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    // Round 0 is a pure snake from the origin.
+    edits.extend(std::iter::repeat_n(Edit::Equal, x as usize));
+    edits.reverse();
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: usize, len: usize, text: &str) -> DiffSpan {
+        DiffSpan {
+            start,
+            len,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn identical_texts() {
+        let diff = word_diff("the quick brown fox", "the  quick\nbrown fox").unwrap();
+        assert!(diff.insertions.is_empty());
+        assert!(diff.deletions.is_empty());
+        assert_eq!(diff.unchanged_ratio, 1.0);
+    }
+
+    #[test]
+    fn empty_inputs() {
+        let diff = word_diff("", "").unwrap();
+        assert_eq!(diff.unchanged_ratio, 1.0);
+        assert!(diff.insertions.is_empty() && diff.deletions.is_empty());
+
+        let diff = word_diff("", "hello world").unwrap();
+        assert_eq!(diff.insertions, vec![span(0, 2, "hello world")]);
+        assert!(diff.deletions.is_empty());
+        assert_eq!(diff.unchanged_ratio, 0.0);
+
+        let diff = word_diff("hello world", "   ").unwrap();
+        assert_eq!(diff.deletions, vec![span(0, 2, "hello world")]);
+        assert!(diff.insertions.is_empty());
+    }
+
+    #[test]
+    fn completely_different_texts() {
+        let diff = word_diff("one two three", "four five").unwrap();
+        assert_eq!(diff.deletions, vec![span(0, 3, "one two three")]);
+        assert_eq!(diff.insertions, vec![span(0, 2, "four five")]);
+        assert_eq!(diff.unchanged_ratio, 0.0);
+    }
+
+    #[test]
+    fn replacement_in_the_middle() {
+        let diff = word_diff("run cube cuddle get pods now", "run kubectl get pods now").unwrap();
+        assert_eq!(diff.deletions, vec![span(1, 2, "cube cuddle")]);
+        assert_eq!(diff.insertions, vec![span(1, 1, "kubectl")]);
+        assert_eq!(diff.unchanged_ratio, 4.0 / 6.0);
+    }
+
+    #[test]
+    fn separate_runs_stay_separate() {
+        let diff = word_diff("a b c d e", "x b c y e z").unwrap();
+        assert_eq!(diff.deletions, vec![span(0, 1, "a"), span(3, 1, "d")]);
+        assert_eq!(
+            diff.insertions,
+            vec![span(0, 1, "x"), span(3, 1, "y"), span(5, 1, "z")]
+        );
+    }
+
+    #[test]
+    fn unicode_words_and_spaces() {
+        // Full-width space (U+3000) and NBSP separate words.
+        let diff = word_diff("東京\u{3000}大阪 café", "東京\u{3000}京都\u{a0}café").unwrap();
+        assert_eq!(diff.deletions, vec![span(1, 1, "大阪")]);
+        assert_eq!(diff.insertions, vec![span(1, 1, "京都")]);
+        // Case and punctuation changes count.
+        let diff = word_diff("Its fine.", "it's fine").unwrap();
+        assert_eq!(diff.deletions, vec![span(0, 2, "Its fine.")]);
+        assert_eq!(diff.insertions, vec![span(0, 2, "it's fine")]);
+    }
+
+    #[test]
+    fn edit_script_is_minimal() {
+        // "a b c a b b a" -> "c b a b a c": the classic Myers example, D = 5
+        let a: Vec<&str> = "a b c a b b a".split(' ').collect();
+        let b: Vec<&str> = "c b a b a c".split(' ').collect();
+        let edits = myers(&a, &b);
+        let changes = edits.iter().filter(|e| **e != Edit::Equal).count();
+        assert_eq!(changes, 5);
+        assert_eq!(edits.len() - changes, 4);
+    }
+
+    #[test]
+    fn oversized_inputs_are_not_diffed() {
+        let long = "word ".repeat(MAX_DIFF_WORDS + 1);
+        assert!(word_diff(&long, "word").is_none());
+        assert!(word_diff("word", &long).is_none());
+        let at_cap = "word ".repeat(MAX_DIFF_WORDS);
+        assert!(word_diff(&at_cap, &at_cap).is_some());
+    }
+}
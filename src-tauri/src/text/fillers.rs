@@ -0,0 +1,358 @@
+//! Filler words ("um", "euh", "äh"): kept, removed or marked.
+//!
+//! Matching is per whole word, case-insensitive, ignoring the
+//! punctuation around it: "Um," is a filler, "umbrella" and "um-hum"
+//! are not. Removal takes the filler's own comma with it, and the one
+//! before it when the two enclosed it ("It was, uh, huge." → "It was
+//! huge."), hands a sentence-ending mark over to the previous word ("I
+//! think, uh." → "I think.") and re-capitalizes the next word when the
+//! filler opened a sentence; the whitespace around other words is left
+//! as it was.
+//!
+//! Lexicons are embedded per language and extended from the settings.
+//! With the language on auto-detect, every lexicon applies except the
+//! entries that are ordinary words somewhere ("ben", "este", German
+//! "er"), which need a known language.
+
+use serde::{Deserialize, Serialize};
+
+/// What the filler stage does. Frontend mirror: `FillerMode`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FillerMode {
+    #[default]
+    Keep,
+    Remove,
+    /// Wrap in brackets: "um" → "[um]".
+    Mark,
+}
+
+/// Built-in fillers by language code.
+const LEXICONS: &[(&str, &[&str])] = &[
+    ("en", &["um", "umm", "uh", "uhm", "erm", "er", "hmm"]),
+    ("fr", &["euh", "heu", "ben", "bah", "hum"]),
+    ("de", &["äh", "ähm", "öh", "öhm", "hm"]),
+    ("es", &["este", "eh", "em", "mmm"]),
+];
+
+/// Lexicon entries that are ordinary words in some language.
+const AMBIGUOUS: &[&str] = &["er", "ben", "bah", "hum", "este", "em"];
+
+/// Fillers for `language` (a Whisper code; `None` = auto-detect) plus
+/// the user's `extra` words, lowercased.
+pub fn lexicon(language: Option<&str>, extra: &[String]) -> Vec<String> {
+    let builtin = LEXICONS
+        .iter()
+        .filter(|(code, _)| language.is_none_or(|language| *code == language))
+        .flat_map(|(_, words)| words.iter())
+        .filter(|word| language.is_some() || !AMBIGUOUS.contains(word))
+        .map(|word| word.to_string());
+    let mut words: Vec<String> = builtin
+        .chain(extra.iter().map(|word| word.trim().to_lowercase()))
+        .filter(|word| !word.is_empty())
+        .collect();
+    words.sort();
+    words.dedup();
+    words
+}
+
+/// Apply `mode` to the `fillers` in `text`.
+pub fn apply(text: &str, mode: FillerMode, fillers: &[String]) -> String {
+    if mode == FillerMode::Keep || fillers.is_empty() {
+        return text.to_string();
+    }
+    let is_filler = |core: &str| !core.is_empty() && fillers.contains(&core.to_lowercase());
+    let (tokens, trailing) = tokenize(text);
+    let mut out: Vec<(String, String)> = Vec::with_capacity(tokens.len());
+    // Whitespace of a removed word that the next word takes over.
+    let mut pending_space: Option<&str> = None;
+    let mut capitalize_next = false;
+    let mut after_removal = false;
+
+    for (space, word) in tokens {
+        let (lead, core, trail) = split_punctuation(word);
+        if !is_filler(core) {
+            // Punctuation standing alone after a removed filler ("um ,")
+            // belongs to it.
+            if after_removal && core.is_empty() {
+                drop_enclosing_comma(&mut out, word);
+                hand_over_terminal(&mut out, word);
+                continue;
+            }
+            after_removal = false;
+            let space = pending_space.take().unwrap_or(space);
+            let word = if capitalize_next && !core.is_empty() {
+                capitalize_next = false;
+                format!("{lead}{}{trail}", capitalize(core))
+            } else {
+                word.to_string()
+            };
+            out.push((space.to_string(), word));
+            continue;
+        }
+        match mode {
+            FillerMode::Mark => {
+                let marked = if lead.ends_with('[') && trail.starts_with(']') {
+                    word.to_string()
+                } else {
+                    format!("{lead}[{core}]{trail}")
+                };
+                out.push((space.to_string(), marked));
+                after_removal = false;
+            }
+            FillerMode::Remove | FillerMode::Keep => {
+                let sentence_start = out
+                    .last()
+                    .is_none_or(|(_, previous)| ends_sentence(previous));
+                if sentence_start && core.starts_with(char::is_uppercase) {
+                    capitalize_next = true;
+                }
+                // Keep the text's leading whitespace, and line breaks
+                if (out.is_empty() && pending_space.is_none()) || space.contains('\n') {
+                    pending_space = Some(space);
+                }
+                drop_enclosing_comma(&mut out, trail);
+                hand_over_terminal(&mut out, trail);
+                after_removal = true;
+            }
+        }
+    }
+
+    let mut result: String = out
+        .into_iter()
+        .flat_map(|(space, word)| [space, word])
+        .collect();
+    if !result.is_empty() {
+        result.push_str(trailing);
+    }
+    result
+}
+
+/// Words with the whitespace before each, and the whitespace after the
+/// last one.
+fn tokenize(text: &str) -> (Vec<(&str, &str)>, &str) {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    loop {
+        let word_start = rest
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len());
+        if word_start == rest.len() {
+            return (tokens, rest);
+        }
+        let (space, tail) = rest.split_at(word_start);
+        let word_end = tail.find(char::is_whitespace).unwrap_or(tail.len());
+        let (word, tail) = tail.split_at(word_end);
+        tokens.push((space, word));
+        rest = tail;
+    }
+}
+
+/// (leading punctuation, word, trailing punctuation)
+fn split_punctuation(word: &str) -> (&str, &str, &str) {
+    let core_start = word.find(char::is_alphanumeric).unwrap_or(word.len());
+    let core_end = word.rfind(char::is_alphanumeric).map_or(core_start, |i| {
+        i + word[i..].chars().next().map_or(0, char::len_utf8)
+    });
+    (
+        &word[..core_start],
+        &word[core_start..core_end],
+        &word[core_end..],
+    )
+}
+
+fn ends_sentence(word: &str) -> bool {
+    word.trim_end_matches(['"', '\'', ')', '»'])
+        .ends_with(['.', '?', '!'])
+}
+
+/// A removed filler followed by a comma takes the comma before it too:
+/// the two set it apart. A comma standing alone goes with its word.
+fn drop_enclosing_comma(out: &mut Vec<(String, String)>, punctuation: &str) {
+    if !punctuation.contains(',') {
+        return;
+    }
+    let Some((_, previous)) = out.last_mut() else {
+        return;
+    };
+    if let Some(trimmed) = previous.strip_suffix(',') {
+        previous.truncate(trimmed.len());
+        if previous.is_empty() {
+            out.pop();
+        }
+    }
+}
+
+/// A removed filler's sentence-ending mark moves to the previous word,
+/// replacing a dangling comma there. Its commas just go.
+fn hand_over_terminal(out: &mut [(String, String)], punctuation: &str) {
+    if !punctuation.contains(['.', '?', '!']) {
+        return;
+    }
+    let Some((_, previous)) = out.last_mut() else {
+        return;
+    };
+    let terminal: String = punctuation
+        .chars()
+        .filter(|c| !matches!(c, ',' | ';' | ':'))
+        .collect();
+    let trimmed = previous.trim_end_matches([',', ';', ':']).len();
+    previous.truncate(trimmed);
+    if !ends_sentence(previous) {
+        previous.push_str(&terminal);
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (input, expected) with `mode` and the lexicon of `language`.
+    fn check(language: Option<&str>, mode: FillerMode, cases: &[(&str, &str)]) {
+        let fillers = lexicon(language, &[]);
+        for (input, expected) in cases {
+            assert_eq!(
+                apply(input, mode, &fillers),
+                *expected,
+                "{language:?} {mode:?} {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn english() {
+        check(
+            Some("en"),
+            FillerMode::Remove,
+            &[
+                ("So, um, I think we should go.", "So I think we should go."),
+                ("Um, the plan is ready.", "The plan is ready."),
+                ("We need uh three more.", "We need three more."),
+                ("I was thinking, uh.", "I was thinking."),
+                ("It's fine, erm... really.", "It's fine... really."),
+                (
+                    "Bring the umbrella, uh, please.",
+                    "Bring the umbrella please.",
+                ),
+                // Only a comma that went with the filler goes
+                ("So, uh I think.", "So, I think."),
+                ("Um um, okay.", "Okay."),
+                ("Done. Uh, next item.", "Done. Next item."),
+                ("He said um-hum.", "He said um-hum."),
+                ("Um.", ""),
+            ],
+        );
+    }
+
+    #[test]
+    fn french() {
+        check(
+            Some("fr"),
+            FillerMode::Remove,
+            &[
+                ("Euh, je pense que oui.", "Je pense que oui."),
+                ("Alors ben on y va.", "Alors on y va."),
+                ("C'est, euh, compliqué.", "C'est compliqué."),
+                ("Le bleu de Bahreïn.", "Le bleu de Bahreïn."),
+            ],
+        );
+    }
+
+    #[test]
+    fn german() {
+        check(
+            Some("de"),
+            FillerMode::Remove,
+            &[
+                ("Ähm, wir treffen uns morgen.", "Wir treffen uns morgen."),
+                ("Das ist äh wichtig.", "Das ist wichtig."),
+                // Not a filler in German
+                ("Er kommt später.", "Er kommt später."),
+            ],
+        );
+    }
+
+    #[test]
+    fn spanish() {
+        check(
+            Some("es"),
+            FillerMode::Remove,
+            &[
+                ("Este, no sé qué decir.", "No sé qué decir."),
+                ("Vamos eh mañana.", "Vamos mañana."),
+            ],
+        );
+    }
+
+    #[test]
+    fn mark_wraps_in_brackets() {
+        check(
+            Some("en"),
+            FillerMode::Mark,
+            &[
+                ("So, um, I think.", "So, [um], I think."),
+                ("Uh.", "[Uh]."),
+                ("Already [um] marked.", "Already [um] marked."),
+            ],
+        );
+    }
+
+    #[test]
+    fn keep_changes_nothing() {
+        check(
+            Some("en"),
+            FillerMode::Keep,
+            &[("Um, uh, erm.", "Um, uh, erm.")],
+        );
+    }
+
+    #[test]
+    fn auto_detect_skips_ambiguous_words() {
+        check(
+            None,
+            FillerMode::Remove,
+            &[
+                ("Um, Ben est là, euh, ähm.", "Ben est là."),
+                ("Er kommt, äh, später.", "Er kommt später."),
+            ],
+        );
+    }
+
+    #[test]
+    fn whitespace_is_preserved_around_kept_words() {
+        check(
+            Some("en"),
+            FillerMode::Remove,
+            &[
+                (
+                    "  um first line\nsecond  line ",
+                    "  first line\nsecond  line ",
+                ),
+                ("line one\nuh line two", "line one\nline two"),
+                ("a , um , b", "a b"),
+            ],
+        );
+    }
+
+    #[test]
+    fn lexicon_is_extendable() {
+        let fillers = lexicon(Some("en"), &["  Like ".to_string(), String::new()]);
+        assert!(fillers.contains(&"like".to_string()));
+        assert!(fillers.contains(&"um".to_string()));
+        assert!(!fillers.contains(&"euh".to_string()));
+        assert_eq!(
+            apply("It was, like, huge.", FillerMode::Remove, &fillers),
+            "It was huge."
+        );
+        // No built-in lexicon for the language: only the extras
+        assert_eq!(lexicon(Some("ja"), &["えーと".to_string()]), vec!["えーと"]);
+    }
+}
@@ -0,0 +1,13 @@
+//! Pure text post-processing helpers (no Tauri, no engine). Everything
+//! here operates on plain strings so it can be fixture-tested directly.
+
+pub mod diff;
+pub mod fillers;
+pub mod pipeline;
+pub mod snippets;
+pub mod vocab;
+
+pub use diff::{word_diff, TextDiff};
+pub use pipeline::{PipelineOutput, PipelineSettings};
+pub use snippets::{expand_snippets, Snippet, TemplateContext};
+pub use vocab::{extract_vocabulary_candidates, VocabSuggestion};
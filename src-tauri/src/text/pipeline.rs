@@ -0,0 +1,262 @@
+//! The text pipeline: what happens to a transcript between the engine
+//! and the output.
+//!
+//! Stages run in a fixed order on the joined text, each one only when
+//! its setting enables it:
+//!
+//! 1. `annotations` — strip non-speech annotations (`[Music]`, ...).
+//!    The engine already does it per segment; running it on the whole
+//!    text again is a no-op for dictation but lets a preview show it.
+//! 2. `fillers` — remove or mark filler words ("um", "euh"). See
+//!    `text::fillers`.
+//! 3. `snippets` — expand voice-trigger snippets.
+//!
+//! `run` takes an explicit `PipelineSettings` snapshot and a
+//! `TemplateContext` instead of reading app state, so dictation and
+//! `preview_text_pipeline` go through the exact same code, and the
+//! pipeline can be tested on plain strings. Only the preview, through
+//! `preview`, pays for a word diff of each stage.
+
+use serde::{Deserialize, Serialize};
+
+use crate::text::fillers::{self, FillerMode};
+use crate::text::{expand_snippets, word_diff, Snippet, TemplateContext, TextDiff};
+use crate::whisper::annotations::strip_annotations;
+
+/// The settings the pipeline reads. Built from `Settings` for dictation;
+/// the preview may pass its own.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineSettings {
+    /// `Settings.suppress_non_speech`.
+    #[serde(default)]
+    pub strip_annotations: bool,
+    /// `Settings.filler_mode`; always `Keep` for verbatim (voice-activated)
+    /// sessions.
+    #[serde(default)]
+    pub filler_mode: FillerMode,
+    /// Whisper code of the dictation language, for the filler lexicon;
+    /// `None` = auto-detect.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// `Settings.filler_words` for that language.
+    #[serde(default)]
+    pub extra_fillers: Vec<String>,
+    /// `Settings.snippets`.
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Stage {
+    Annotations,
+    Fillers,
+    Snippets,
+}
+
+/// What one stage did.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageReport {
+    pub stage: Stage,
+    /// The text after this stage.
+    pub output: String,
+    /// Word diff against the stage's input; `None` when the stage
+    /// changed nothing (or the text is too long to diff).
+    pub diff: Option<TextDiff>,
+}
+
+/// `preview_text_pipeline` result.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineOutput {
+    pub text: String,
+    /// One report per enabled stage, in order.
+    pub stages: Vec<StageReport>,
+}
+
+/// Run every enabled stage on `input`.
+pub fn run(input: &str, settings: &PipelineSettings, ctx: &TemplateContext) -> String {
+    run_stages(input, settings, ctx, |_, _, _| {})
+}
+
+/// `run`, with a report of what each stage did.
+pub fn preview(input: &str, settings: &PipelineSettings, ctx: &TemplateContext) -> PipelineOutput {
+    let mut stages = Vec::new();
+    let text = run_stages(input, settings, ctx, |stage, input, output| {
+        stages.push(StageReport {
+            stage,
+            output: output.to_string(),
+            diff: if output == input {
+                None
+            } else {
+                word_diff(input, output)
+            },
+        })
+    });
+    PipelineOutput { text, stages }
+}
+
+/// The stages, each one's input and output passed to `report`.
+fn run_stages(
+    input: &str,
+    settings: &PipelineSettings,
+    ctx: &TemplateContext,
+    mut report: impl FnMut(Stage, &str, &str),
+) -> String {
+    let mut text = input.to_string();
+    let mut apply = |stage: Stage, f: &dyn Fn(&str) -> String| {
+        let output = f(&text);
+        report(stage, &text, &output);
+        text = output;
+    };
+
+    if settings.strip_annotations {
+        apply(Stage::Annotations, &strip_annotations);
+    }
+    if settings.filler_mode != FillerMode::Keep {
+        let lexicon = fillers::lexicon(settings.language.as_deref(), &settings.extra_fillers);
+        apply(Stage::Fillers, &|text| {
+            fillers::apply(text, settings.filler_mode, &lexicon)
+        });
+    }
+    if !settings.snippets.is_empty() {
+        apply(Stage::Snippets, &|text| {
+            expand_snippets(text, &settings.snippets, ctx)
+        });
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(trigger: &str, body: &str) -> Snippet {
+        Snippet {
+            id: trigger.to_string(),
+            name: trigger.to_string(),
+            trigger: trigger.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    fn ctx() -> TemplateContext {
+        TemplateContext {
+            date: "2024-05-01".to_string(),
+            time: "09:30".to_string(),
+            clipboard: "https://example.com".to_string(),
+        }
+    }
+
+    fn all_stages() -> PipelineSettings {
+        PipelineSettings {
+            strip_annotations: true,
+            filler_mode: FillerMode::Remove,
+            language: Some("en".to_string()),
+            extra_fillers: Vec::new(),
+            snippets: vec![
+                snippet("insert signature", "Best,\nAlex"),
+                snippet("today's date", "{date}"),
+                snippet("paste link", "{clipboard}"),
+            ],
+        }
+    }
+
+    /// (input, settings, expected output)
+    #[test]
+    fn golden() {
+        let none = PipelineSettings::default();
+        let annotations_only = PipelineSettings {
+            strip_annotations: true,
+            ..PipelineSettings::default()
+        };
+        let snippets_only = PipelineSettings {
+            strip_annotations: false,
+            filler_mode: FillerMode::Keep,
+            ..all_stages()
+        };
+        let mark_fillers = PipelineSettings {
+            filler_mode: FillerMode::Mark,
+            ..all_stages()
+        };
+        let cases: &[(&str, &PipelineSettings, &str)] = &[
+            ("Hello [Music] world.", &none, "Hello [Music] world."),
+            ("Hello [Music] world.", &annotations_only, "Hello world."),
+            ("Hello [Music] world.", &all_stages(), "Hello world."),
+            (
+                "Thanks. Insert signature.",
+                &snippets_only,
+                "Thanks. Best,\nAlex",
+            ),
+            (
+                "[BLANK_AUDIO] Due on today's date, see paste link.",
+                &all_stages(),
+                "Due on 2024-05-01, see https://example.com.",
+            ),
+            (
+                "[BLANK_AUDIO] Due on today's date.",
+                &snippets_only,
+                "[BLANK_AUDIO] Due on 2024-05-01.",
+            ),
+            (
+                "f(x) and array[0] (laughs)",
+                &all_stages(),
+                "f(x) and array[0]",
+            ),
+            ("Um, insert signature.", &all_stages(), "Best,\nAlex"),
+            (
+                "So, uh, [Music] due on today's date.",
+                &all_stages(),
+                "So due on 2024-05-01.",
+            ),
+            ("So, uh, ready.", &snippets_only, "So, uh, ready."),
+            ("So, uh, ready.", &mark_fillers, "So, [uh], ready."),
+            ("", &all_stages(), ""),
+        ];
+        for (input, settings, expected) in cases {
+            assert_eq!(run(input, settings, &ctx()), *expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn report_lists_enabled_stages_in_order() {
+        let out = preview("(applause) um insert signature", &all_stages(), &ctx());
+        let stages: Vec<Stage> = out.stages.iter().map(|s| s.stage).collect();
+        assert_eq!(
+            stages,
+            vec![Stage::Annotations, Stage::Fillers, Stage::Snippets]
+        );
+        assert_eq!(out.stages[0].output, "um insert signature");
+        assert_eq!(out.stages[1].output, "insert signature");
+        assert_eq!(out.stages[2].output, out.text);
+        assert!(out.stages.iter().all(|s| s.diff.is_some()));
+
+        let out = preview("nothing to do", &PipelineSettings::default(), &ctx());
+        assert!(out.stages.is_empty());
+        assert_eq!(out.text, "nothing to do");
+    }
+
+    #[test]
+    fn unchanged_stage_has_no_diff() {
+        let out = preview("plain text", &all_stages(), &ctx());
+        assert_eq!(out.stages.len(), 3);
+        assert!(out.stages.iter().all(|s| s.diff.is_none()));
+    }
+
+    #[test]
+    fn overrides_deserialize_with_defaults() {
+        let settings: PipelineSettings =
+            serde_json::from_str(r#"{ "stripAnnotations": true }"#).unwrap();
+        assert!(settings.strip_annotations);
+        assert_eq!(settings.filler_mode, FillerMode::Keep);
+        assert!(settings.snippets.is_empty());
+
+        let settings: PipelineSettings =
+            serde_json::from_str(r#"{ "fillerMode": "mark", "language": "fr" }"#).unwrap();
+        assert_eq!(settings.filler_mode, FillerMode::Mark);
+        assert_eq!(settings.language.as_deref(), Some("fr"));
+    }
+}
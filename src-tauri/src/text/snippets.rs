@@ -0,0 +1,221 @@
+//! User-defined snippets expanded by voice trigger.
+//!
+//! Saying a trigger phrase ("insert signature") replaces it in the
+//! transcript with the snippet's body, after rendering its template
+//! tokens. Matching is case- and punctuation-insensitive, respects word
+//! boundaries, and the longest trigger wins when several match at the
+//! same position.
+
+use serde::{Deserialize, Serialize};
+
+/// A persisted snippet. Lives in `Settings.snippets`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+    pub id: String,
+    pub name: String,
+    /// Spoken phrase, e.g. "insert signature".
+    pub trigger: String,
+    /// Replacement text. May span several lines and contain `{date}`,
+    /// `{time}` and `{clipboard}` tokens.
+    pub body: String,
+}
+
+/// Values substituted into snippet bodies. Built by the caller so this
+/// module stays clock- and clipboard-free.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub date: String,
+    pub time: String,
+    pub clipboard: String,
+}
+
+/// Whether `body` needs the clipboard, so callers can skip reading it.
+pub fn uses_clipboard(body: &str) -> bool {
+    body.contains("{clipboard}")
+}
+
+/// Substitute template tokens. Unknown `{tokens}` are left as-is so a
+/// literal brace in a code snippet survives. Line endings are
+/// normalised to `\n`; both the clipboard and the paste paths accept
+/// that on every platform.
+pub fn render_template(body: &str, ctx: &TemplateContext) -> String {
+    body.replace("\r\n", "\n")
+        .replace("{date}", &ctx.date)
+        .replace("{time}", &ctx.time)
+        .replace("{clipboard}", &ctx.clipboard)
+}
+
+/// Comparison key for a word: lowercase, surrounding punctuation dropped.
+fn normalize_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// Normalised trigger words; empty if the trigger has no usable word.
+pub fn trigger_words(trigger: &str) -> Vec<String> {
+    trigger
+        .split_whitespace()
+        .map(normalize_word)
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// Replace every trigger phrase in `text` with its rendered snippet.
+pub fn expand_snippets(text: &str, snippets: &[Snippet], ctx: &TemplateContext) -> String {
+    // Longest trigger first so "insert signature long" beats
+    // "insert signature" at the same position.
+    let mut triggers: Vec<(Vec<String>, &Snippet)> = snippets
+        .iter()
+        .map(|s| (trigger_words(&s.trigger), s))
+        .filter(|(words, _)| !words.is_empty())
+        .collect();
+    if triggers.is_empty() {
+        return text.to_string();
+    }
+    triggers.sort_by_key(|t| std::cmp::Reverse(t.0.len()));
+
+    // (start, end, normalised) byte spans of each whitespace-separated word
+    let mut words: Vec<(usize, usize, String)> = Vec::new();
+    let mut offset = 0;
+    for raw in text.split_whitespace() {
+        let start = offset + text[offset..].find(raw).unwrap_or(0);
+        let end = start + raw.len();
+        words.push((start, end, normalize_word(raw)));
+        offset = end;
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut copied_to = 0;
+    let mut i = 0;
+    while i < words.len() {
+        let hit = triggers.iter().find(|(trigger, _)| {
+            i + trigger.len() <= words.len()
+                && trigger
+                    .iter()
+                    .zip(&words[i..i + trigger.len()])
+                    .all(|(t, w)| *t == w.2)
+        });
+        match hit {
+            Some((trigger, snippet)) => {
+                // Punctuation Whisper attached around the trigger is kept
+                // ("see my link, or" stays a list), except trailing
+                // punctuation after a multi-line body: a sentence-final
+                // "." under a signature block is noise.
+                let first = &text[words[i].0..words[i].1];
+                let lead = first.len()
+                    - first
+                        .trim_start_matches(|c: char| !c.is_alphanumeric())
+                        .len();
+                let last_end = words[i + trigger.len() - 1].1;
+                let last = &text[words[i + trigger.len() - 1].0..last_end];
+                let trail =
+                    last.len() - last.trim_end_matches(|c: char| !c.is_alphanumeric()).len();
+                let rendered = render_template(&snippet.body, ctx);
+                out.push_str(&text[copied_to..words[i].0 + lead]);
+                out.push_str(&rendered);
+                copied_to = if rendered.contains('\n') {
+                    last_end
+                } else {
+                    last_end - trail
+                };
+                i += trigger.len();
+            }
+            None => i += 1,
+        }
+    }
+    out.push_str(&text[copied_to..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(trigger: &str, body: &str) -> Snippet {
+        Snippet {
+            id: trigger.to_string(),
+            name: trigger.to_string(),
+            trigger: trigger.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    fn ctx() -> TemplateContext {
+        TemplateContext {
+            date: "2026-05-09".into(),
+            time: "14:30".into(),
+            clipboard: "https://example.com".into(),
+        }
+    }
+
+    #[test]
+    fn renders_all_tokens_and_keeps_unknown_ones() {
+        assert_eq!(
+            render_template("On {date} at {time}: {clipboard} {unknown}", &ctx()),
+            "On 2026-05-09 at 14:30: https://example.com {unknown}"
+        );
+    }
+
+    #[test]
+    fn normalises_crlf_bodies() {
+        assert_eq!(render_template("Best,\r\nAlex", &ctx()), "Best,\nAlex");
+    }
+
+    #[test]
+    fn expands_multiline_signature_ignoring_case_and_punctuation() {
+        let snippets = [snippet("insert signature", "Best regards,\nAlex\n{date}")];
+        assert_eq!(
+            expand_snippets(
+                "Thanks for the update. Insert signature.",
+                &snippets,
+                &ctx()
+            ),
+            "Thanks for the update. Best regards,\nAlex\n2026-05-09"
+        );
+    }
+
+    #[test]
+    fn longest_trigger_wins() {
+        let snippets = [
+            snippet("insert signature", "SHORT"),
+            snippet("insert signature formal", "FORMAL"),
+        ];
+        assert_eq!(
+            expand_snippets("insert signature formal please", &snippets, &ctx()),
+            "FORMAL please"
+        );
+        assert_eq!(
+            expand_snippets("insert signature please", &snippets, &ctx()),
+            "SHORT please"
+        );
+    }
+
+    #[test]
+    fn respects_word_boundaries() {
+        let snippets = [snippet("sig", "SIGNATURE")];
+        assert_eq!(
+            expand_snippets("The signal was weak", &snippets, &ctx()),
+            "The signal was weak"
+        );
+        assert_eq!(
+            expand_snippets("add sig here", &snippets, &ctx()),
+            "add SIGNATURE here"
+        );
+    }
+
+    #[test]
+    fn expands_every_occurrence() {
+        let snippets = [snippet("my link", "{clipboard}")];
+        assert_eq!(
+            expand_snippets("See my link, or my link.", &snippets, &ctx()),
+            "See https://example.com, or https://example.com."
+        );
+    }
+
+    #[test]
+    fn empty_triggers_are_ignored() {
+        let snippets = [snippet("  ...  ", "NOPE")];
+        assert_eq!(expand_snippets("hello ...", &snippets, &ctx()), "hello ...");
+    }
+}
@@ -0,0 +1,317 @@
+//! Vocabulary learning from user corrections.
+//!
+//! The user dictates, Whisper gets a name wrong ("cube cuddle" for
+//! "kubectl"), the user fixes it by hand and copies the corrected text.
+//! `learn_from_clipboard` then diffs the clipboard against the most
+//! recent transcript word by word and hands the replaced words that look
+//! like proper nouns or domain terms back to the UI as suggestions for
+//! the custom vocabulary. Nothing is added without the user accepting.
+
+use serde::{Deserialize, Serialize};
+
+/// Above this many tokens on either side we don't bother diffing — the
+/// clipboard is almost certainly not an edited transcript, and the LCS
+/// table would grow quadratically.
+const MAX_DIFF_TOKENS: usize = 400;
+
+/// Minimum share of unchanged words for the clipboard to count as a
+/// corrected version of the transcript rather than unrelated text.
+const MIN_SHARED_RATIO: f32 = 0.5;
+
+/// Replacements longer than this (on either side) are rewrites, not
+/// misrecognitions of a single term.
+const MAX_HUNK_WORDS: usize = 4;
+
+/// Frequent words that are never worth adding to the vocabulary, even
+/// when the user capitalised them or they start a sentence. English and
+/// French cover the bulk of our users; anything missing here still has
+/// to pass the term-shape heuristics below.
+const COMMON_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "he", "her", "his", "i",
+    "if", "in", "is", "it", "its", "me", "my", "no", "not", "of", "on", "or", "our", "she", "so",
+    "that", "the", "their", "then", "there", "they", "this", "to", "was", "we", "were", "what",
+    "when", "which", "who", "will", "with", "yes", "you", "your", "au", "aux", "ce", "cette",
+    "dans", "de", "des", "du", "elle", "en", "est", "et", "il", "je", "la", "le", "les", "mais",
+    "mon", "nous", "ou", "par", "pas", "pour", "que", "qui", "sa", "se", "son", "sur", "tu", "un",
+    "une", "vous",
+];
+
+/// A term the user taught us by correcting a transcript.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VocabSuggestion {
+    /// The corrected spelling, as the user typed it.
+    pub term: String,
+    /// What Whisper produced instead.
+    pub heard: String,
+}
+
+/// One token of the input, with the surrounding punctuation stripped for
+/// comparison but remembered for sentence-boundary detection.
+#[derive(Debug, Clone)]
+struct Token<'a> {
+    core: &'a str,
+    sentence_initial: bool,
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut sentence_initial = true;
+    for raw in text.split_whitespace() {
+        let core = raw.trim_matches(|c: char| !c.is_alphanumeric());
+        if !core.is_empty() {
+            tokens.push(Token {
+                core,
+                sentence_initial,
+            });
+        }
+        sentence_initial = raw.ends_with(['.', '!', '?', '…', ':']);
+    }
+    tokens
+}
+
+/// A contiguous run of changed words between two unchanged anchors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Hunk<'a> {
+    removed: Vec<&'a str>,
+    added: Vec<&'a str>,
+    /// Whether the first added word opens a sentence in the corrected text.
+    added_sentence_initial: bool,
+}
+
+/// Word-level LCS diff. Returns the changed hunks and the number of
+/// words common to both sides.
+fn word_diff<'a>(before: &[Token<'a>], after: &[Token<'a>]) -> (Vec<Hunk<'a>>, usize) {
+    let (n, m) = (before.len(), after.len());
+    // lcs[i][j] = LCS length of before[i..] and after[j..]
+    let mut lcs = vec![vec![0u16; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if before[i].core == after[j].core {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk<'a>> = None;
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && before[i].core == after[j].core {
+            hunks.extend(current.take());
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let hunk = current.get_or_insert_with(|| Hunk {
+            removed: Vec::new(),
+            added: Vec::new(),
+            added_sentence_initial: false,
+        });
+        if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            if hunk.added.is_empty() {
+                hunk.added_sentence_initial = after[j].sentence_initial;
+            }
+            hunk.added.push(after[j].core);
+            j += 1;
+        } else {
+            hunk.removed.push(before[i].core);
+            i += 1;
+        }
+    }
+    hunks.extend(current);
+    (hunks, lcs[0][0] as usize)
+}
+
+fn is_common_word(word: &str) -> bool {
+    let lower = word.to_lowercase();
+    COMMON_WORDS.contains(&lower.as_str())
+}
+
+/// Shape heuristics for "proper noun or domain term". We have no
+/// dictionary, so this leans on what the user's correction tells us:
+/// internal capitals (`OpenAI`, `iOS`), letters mixed with digits
+/// (`k8s`, `GPT4`), acronyms, or a capitalised word mid-sentence.
+fn looks_like_term(word: &str, sentence_initial: bool) -> bool {
+    if word.chars().count() < 2 || is_common_word(word) {
+        return false;
+    }
+    let has_alpha = word.chars().any(char::is_alphabetic);
+    let has_digit = word.chars().any(|c| c.is_ascii_digit());
+    if !has_alpha {
+        return false;
+    }
+    if has_digit {
+        return true;
+    }
+    let mut chars = word.chars();
+    let first_upper = chars.next().is_some_and(char::is_uppercase);
+    let inner_upper = chars.any(char::is_uppercase);
+    inner_upper || (first_upper && !sentence_initial)
+}
+
+/// Diff `transcript` against the user's `corrected` text and return the
+/// replaced words worth proposing as vocabulary. Returns an empty list
+/// when the two texts don't look like versions of the same dictation.
+pub fn extract_vocabulary_candidates(transcript: &str, corrected: &str) -> Vec<VocabSuggestion> {
+    let before = tokenize(transcript);
+    let after = tokenize(corrected);
+    if before.is_empty()
+        || after.is_empty()
+        || before.len() > MAX_DIFF_TOKENS
+        || after.len() > MAX_DIFF_TOKENS
+    {
+        return Vec::new();
+    }
+
+    let (hunks, shared) = word_diff(&before, &after);
+    let average = (before.len() + after.len()) as f32 / 2.0;
+    if (shared as f32) < average * MIN_SHARED_RATIO {
+        return Vec::new();
+    }
+
+    let mut out: Vec<VocabSuggestion> = Vec::new();
+    for hunk in hunks {
+        // Pure insertions/deletions are edits, not misrecognitions.
+        if hunk.removed.is_empty() || hunk.added.is_empty() {
+            continue;
+        }
+        if hunk.removed.len() > MAX_HUNK_WORDS || hunk.added.len() > MAX_HUNK_WORDS {
+            continue;
+        }
+        let heard = hunk.removed.join(" ");
+        let term = hunk.added.join(" ");
+        // "the" → "The" at the start of a sentence is grammar, not vocabulary.
+        if hunk.added_sentence_initial && heard.to_lowercase() == term.to_lowercase() {
+            continue;
+        }
+        let all_terms = hunk
+            .added
+            .iter()
+            .enumerate()
+            .all(|(k, w)| looks_like_term(w, k == 0 && hunk.added_sentence_initial));
+        let suggestion = if all_terms {
+            Some(term)
+        } else if hunk.added.len() == 1 && hunk.removed.len() > 1 {
+            // "cube cuddle" → "kubectl": several heard words collapsed
+            // into one unfamiliar token is itself a strong signal.
+            (!is_common_word(hunk.added[0])).then(|| hunk.added[0].to_string())
+        } else {
+            None
+        };
+        if let Some(term) = suggestion {
+            if !out.iter().any(|s| s.term == term) {
+                out.push(VocabSuggestion { term, heard });
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(before: &str, after: &str) -> Vec<(String, String)> {
+        extract_vocabulary_candidates(before, after)
+            .into_iter()
+            .map(|s| (s.term, s.heard))
+            .collect()
+    }
+
+    #[test]
+    fn proper_noun_replacing_misheard_word() {
+        assert_eq!(
+            terms(
+                "I had a call with jean claude about the deployment.",
+                "I had a call with Jean-Claude about the deployment."
+            ),
+            vec![("Jean-Claude".into(), "jean claude".into())]
+        );
+    }
+
+    #[test]
+    fn domain_term_collapsed_from_several_words() {
+        assert_eq!(
+            terms(
+                "Run cube cuddle get pods in the staging namespace.",
+                "Run kubectl get pods in the staging namespace."
+            ),
+            vec![("kubectl".into(), "cube cuddle".into())]
+        );
+    }
+
+    #[test]
+    fn camel_case_and_alphanumeric_terms() {
+        assert_eq!(
+            terms(
+                "We benchmarked open ai against gpt four on the k eight s cluster.",
+                "We benchmarked OpenAI against GPT4 on the k8s cluster."
+            ),
+            vec![
+                ("OpenAI".into(), "open ai".into()),
+                ("GPT4".into(), "gpt four".into()),
+                ("k8s".into(), "k eight s".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn french_dictation_with_brand_name() {
+        assert_eq!(
+            terms(
+                "Il faut mettre à jour le fichier de config de tôt rie.",
+                "Il faut mettre à jour le fichier de config de Tauri."
+            ),
+            vec![("Tauri".into(), "tôt rie".into())]
+        );
+    }
+
+    #[test]
+    fn ordinary_word_fixes_are_ignored() {
+        // Typo-level corrections of common lowercase words.
+        assert!(terms(
+            "The meeting is on tuesday at tree o'clock.",
+            "The meeting is on tuesday at three o'clock."
+        )
+        .is_empty());
+        // Sentence-initial capitalisation fix.
+        assert!(terms(
+            "the build passed. the tests too.",
+            "The build passed. The tests too."
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn unrelated_clipboard_yields_nothing() {
+        assert!(terms(
+            "Please send the quarterly report to Marie before Friday.",
+            "fn main() { println!(\"Hello, World\"); }"
+        )
+        .is_empty());
+        assert!(terms("", "Something").is_empty());
+    }
+
+    #[test]
+    fn pure_insertions_are_not_suggested() {
+        assert!(terms(
+            "Ship it on Monday.",
+            "Ship it on Monday, Kubernetes permitting."
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn duplicates_are_collapsed() {
+        assert_eq!(
+            terms(
+                "We moved the queue to post gress because post gress already stores the orders.",
+                "We moved the queue to Postgres because Postgres already stores the orders."
+            ),
+            vec![("Postgres".into(), "post gress".into())]
+        );
+    }
+}
@@ -0,0 +1,559 @@
+//! Watched folders: audio files that appear in them get transcribed.
+//!
+//! `watch_folder` adds a folder to `Settings.watched_folders`; the
+//! notify watcher (`watcher.rs`) reports the files created or renamed
+//! there, and the `folder-watch` task takes each one once its size has
+//! held still for `STABLE_FOR` (a phone sync writes a memo in several
+//! goes). The transcript goes next to the audio or into the folder's
+//! `output_dir`, in its `format`.
+//!
+//! What was done with every file is kept in a per-folder `Manifest` in
+//! the app data directory, not in the folder: the startup scan offers
+//! every audio file again and the manifest weeds out the ones already
+//! handled, so only those that appeared while the app was closed are
+//! transcribed. A copy of a file already transcribed (`memo (1).wav`
+//! from a sync conflict) is skipped, and so is audio in a format
+//! `audio::read_wav` can't decode.
+//!
+//! Everything here is plain logic so it tests without a watcher.
+
+mod watcher;
+
+pub use watcher::FolderWatcher;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::whisper::worker::Segment;
+
+/// How long a file's size must hold still before it is transcribed.
+pub const STABLE_FOR: Duration = Duration::from_secs(3);
+/// How often the `folder-watch` task looks at the files settling.
+pub const SETTLE_TICK: Duration = Duration::from_secs(1);
+
+/// Decoded by `audio::read_wav`.
+const SUPPORTED_EXTENSIONS: &[&str] = &["wav"];
+/// Audio, but not decodable yet: reported once, then left alone.
+const UNSUPPORTED_EXTENSIONS: &[&str] = &[
+    "mp3", "m4a", "aac", "ogg", "oga", "opus", "flac", "amr", "3gp", "webm", "wma", "caf", "aiff",
+];
+
+/// What a watched folder's transcripts are written as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchFormat {
+    /// The text alone.
+    #[default]
+    Txt,
+    Srt,
+    Vtt,
+    /// `{ text, segments }`, segments as in `transcript:final`.
+    Json,
+}
+
+impl WatchFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Txt => "txt",
+            Self::Srt => "srt",
+            Self::Vtt => "vtt",
+            Self::Json => "json",
+        }
+    }
+
+    /// The transcript `text`, timed by `segments`, in this format.
+    pub fn render(self, text: &str, segments: &[Segment]) -> String {
+        match self {
+            Self::Txt => format!("{}\n", text.trim()),
+            Self::Srt => segments
+                .iter()
+                .filter(|segment| !segment.text.trim().is_empty())
+                .enumerate()
+                .map(|(i, segment)| {
+                    format!(
+                        "{}\n{} --> {}\n{}\n\n",
+                        i + 1,
+                        timestamp(segment.start_ms, ','),
+                        timestamp(segment.end_ms, ','),
+                        segment.text.trim()
+                    )
+                })
+                .collect(),
+            Self::Vtt => {
+                let cues: String = segments
+                    .iter()
+                    .filter(|segment| !segment.text.trim().is_empty())
+                    .map(|segment| {
+                        format!(
+                            "{} --> {}\n{}\n\n",
+                            timestamp(segment.start_ms, '.'),
+                            timestamp(segment.end_ms, '.'),
+                            segment.text.trim()
+                        )
+                    })
+                    .collect();
+                format!("WEBVTT\n\n{cues}")
+            }
+            Self::Json => {
+                let json = serde_json::json!({ "text": text.trim(), "segments": segments });
+                format!("{json:#}\n")
+            }
+        }
+    }
+}
+
+/// `hh:mm:ss,mmm` (SubRip) or `hh:mm:ss.mmm` (WebVTT).
+fn timestamp(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{separator}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// A folder whose new audio files are transcribed. Frontend mirror:
+/// `WatchedFolder`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedFolder {
+    /// Canonical, as `watch_folder` resolved it.
+    pub path: PathBuf,
+    pub format: WatchFormat,
+    /// Where transcripts go; `None` for next to the audio.
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+}
+
+impl WatchedFolder {
+    /// Whether `file` sits directly in this folder (subfolders aren't
+    /// watched).
+    pub fn contains(&self, file: &Path) -> bool {
+        file.parent() == Some(&*self.path)
+    }
+
+    /// Where the transcript of `input` goes: its name with the format's
+    /// extension, numbered when that is taken (`memo (2).txt`) so an
+    /// earlier transcript is never overwritten.
+    pub fn output_path(&self, input: &Path) -> PathBuf {
+        let dir = self.output_dir.as_deref().unwrap_or(&self.path);
+        let stem = input
+            .file_stem()
+            .map_or_else(|| "transcript".into(), |s| s.to_string_lossy());
+        let extension = self.format.extension();
+        let mut path = dir.join(format!("{stem}.{extension}"));
+        let mut n = 2;
+        while path.exists() {
+            path = dir.join(format!("{stem} ({n}).{extension}"));
+            n += 1;
+        }
+        path
+    }
+}
+
+/// `watch_folder` / `unwatch_folder` refused.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum WatchError {
+    #[error("Not a folder: {path}")]
+    NotAFolder { path: String },
+    #[error("{path} is already watched")]
+    AlreadyWatched { path: String },
+    #[error("{path} isn't watched")]
+    NotWatched { path: String },
+    #[error("Folder watcher failed: {message}")]
+    Watcher { message: String },
+    #[error("Failed to save the watched folders: {reason}")]
+    PersistFailed { reason: String },
+}
+
+/// Whether a file is audio, and whether it can be transcribed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioKind {
+    Supported,
+    Unsupported,
+}
+
+impl AudioKind {
+    /// By extension; `None` for anything that isn't audio (transcripts,
+    /// a sync tool's temporary files, hidden files).
+    pub fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        if name.starts_with('.') {
+            return None;
+        }
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        if SUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Self::Supported)
+        } else if UNSUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Self::Unsupported)
+        } else {
+            None
+        }
+    }
+}
+
+/// The audio files in `folder`, by name. Unreadable folders have none.
+pub fn scan(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .map(|entry| entry.path())
+        .filter(|path| AudioKind::of(path).is_some())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Files waiting for their size to hold still.
+#[derive(Debug, Default)]
+pub struct Settling {
+    /// Last size seen (`None` before the first look) and since when.
+    files: HashMap<PathBuf, (Option<u64>, Instant)>,
+}
+
+impl Settling {
+    /// Start (or keep) waiting on `path`.
+    pub fn observe(&mut self, path: PathBuf, now: Instant) {
+        self.files.entry(path).or_insert((None, now));
+    }
+
+    /// The files whose size, read with `size_of`, hasn't changed for
+    /// `STABLE_FOR`; they stop being watched. Files gone meanwhile are
+    /// dropped, empty ones keep waiting.
+    pub fn settled(
+        &mut self,
+        now: Instant,
+        size_of: impl Fn(&Path) -> Option<u64>,
+    ) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        self.files.retain(|path, (size, since)| {
+            let Some(current) = size_of(path) else {
+                return false;
+            };
+            if *size != Some(current) {
+                *size = Some(current);
+                *since = now;
+            } else if current > 0 && now.duration_since(*since) >= STABLE_FOR {
+                ready.push(path.clone());
+                return false;
+            }
+            true
+        });
+        ready.sort();
+        ready
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// What became of a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Outcome {
+    Transcribed {
+        output: PathBuf,
+    },
+    /// Same contents as `of`, already transcribed.
+    Duplicate {
+        of: String,
+    },
+    Unsupported,
+    Failed {
+        error: String,
+    },
+}
+
+/// One file of a folder's manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub size_bytes: u64,
+    /// Unix ms; a file rewritten under the same name is done again.
+    pub modified: Option<i64>,
+    /// Hex SHA-256, for telling copies apart; `None` when the file
+    /// wasn't read.
+    #[serde(default)]
+    pub sha256: Option<String>,
+    pub outcome: Outcome,
+}
+
+/// The files of one watched folder already dealt with, by name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(skip)]
+    path: PathBuf,
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    /// The manifest of `folder`, kept in `dir`: named after a hash of
+    /// the folder's path, so two folders named `Memos` don't clash.
+    pub fn path_in(dir: &Path, folder: &Path) -> PathBuf {
+        let hash = format!("{:x}", Sha256::digest(folder.to_string_lossy().as_bytes()));
+        dir.join(format!("{}.json", &hash[..16]))
+    }
+
+    /// Read the manifest at `path`. Missing or unreadable is empty: at
+    /// worst files are transcribed again (under a new name).
+    pub fn load(path: &Path) -> Self {
+        let files = match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice::<Self>(&bytes)
+                .map(|manifest| manifest.files)
+                .unwrap_or_else(|e| {
+                    tracing::warn!(
+                        "Ignoring unreadable watch manifest {}: {}",
+                        path.display(),
+                        e
+                    );
+                    BTreeMap::new()
+                }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path: path.to_path_buf(),
+            files,
+        }
+    }
+
+    /// Write it back, through a temporary file so a crash mid-write
+    /// doesn't lose it.
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let partial = self.path.with_extension("json.part");
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        fs::write(&partial, json)?;
+        fs::rename(&partial, &self.path)
+    }
+
+    /// `name` was dealt with as it is now.
+    pub fn is_done(&self, name: &str, size_bytes: u64, modified: Option<i64>) -> bool {
+        self.files
+            .get(name)
+            .is_some_and(|entry| entry.size_bytes == size_bytes && entry.modified == modified)
+    }
+
+    /// Another file with these contents was transcribed.
+    pub fn duplicate_of(&self, name: &str, size_bytes: u64, sha256: &str) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|(other, entry)| {
+                *other != name
+                    && entry.size_bytes == size_bytes
+                    && entry.sha256.as_deref() == Some(sha256)
+                    && matches!(entry.outcome, Outcome::Transcribed { .. })
+            })
+            .map(|(other, _)| other.as_str())
+    }
+
+    pub fn record(&mut self, name: &str, entry: ManifestEntry) {
+        self.files.insert(name.to_string(), entry);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ManifestEntry> {
+        self.files.get(name)
+    }
+}
+
+/// Size and modification time (Unix ms) of `path`, as the manifest
+/// keeps them.
+pub fn file_stamp(path: &Path) -> io::Result<(u64, Option<i64>)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_millis() as i64);
+    Ok((metadata.len(), modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start_ms: u64, end_ms: u64, text: &str) -> Segment {
+        Segment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+            confidence: None,
+        }
+    }
+
+    #[test]
+    fn formats() {
+        let segments = [
+            segment(0, 2500, " Buy milk."),
+            segment(2500, 3000, " "),
+            segment(3_725_040, 3_727_000, " And bread."),
+        ];
+        let text = " Buy milk. And bread.";
+        assert_eq!(
+            WatchFormat::Txt.render(text, &segments),
+            "Buy milk. And bread.\n"
+        );
+        assert_eq!(
+            WatchFormat::Srt.render(text, &segments),
+            "1\n00:00:00,000 --> 00:00:02,500\nBuy milk.\n\n\
+             2\n01:02:05,040 --> 01:02:07,000\nAnd bread.\n\n"
+        );
+        assert_eq!(
+            WatchFormat::Vtt.render(text, &segments),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:02.500\nBuy milk.\n\n\
+             01:02:05.040 --> 01:02:07.000\nAnd bread.\n\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&WatchFormat::Json.render(text, &segments)).unwrap();
+        assert_eq!(json["text"], "Buy milk. And bread.");
+        assert_eq!(json["segments"][2]["startMs"], 3_725_040);
+    }
+
+    #[test]
+    fn audio_by_extension() {
+        assert_eq!(
+            AudioKind::of(Path::new("/m/memo.wav")),
+            Some(AudioKind::Supported)
+        );
+        assert_eq!(
+            AudioKind::of(Path::new("/m/Memo.WAV")),
+            Some(AudioKind::Supported)
+        );
+        assert_eq!(
+            AudioKind::of(Path::new("/m/memo.m4a")),
+            Some(AudioKind::Unsupported)
+        );
+        for path in ["/m/memo.txt", "/m/.memo.wav", "/m/memo.wav.tmp", "/m/memo"] {
+            assert_eq!(AudioKind::of(Path::new(path)), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn files_wait_until_their_size_holds() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut settling = Settling::default();
+        let memo = PathBuf::from("/m/memo.wav");
+        settling.observe(memo.clone(), at(0));
+        // Seen again while syncing: still one file
+        settling.observe(memo.clone(), at(0));
+
+        let size = std::cell::Cell::new(Some(4096));
+        let size_of = |_: &Path| size.get();
+        assert!(settling.settled(at(0), size_of).is_empty());
+        size.set(Some(8192));
+        assert!(settling.settled(at(2), size_of).is_empty());
+        // Still growing: the wait starts over
+        assert!(settling.settled(at(4), size_of).is_empty());
+        assert_eq!(
+            settling.settled(at(5), size_of),
+            std::slice::from_ref(&memo)
+        );
+        assert!(settling.is_empty());
+
+        // Deleted before it settled
+        settling.observe(memo.clone(), at(10));
+        size.set(None);
+        assert!(settling.settled(at(20), size_of).is_empty());
+        assert!(settling.is_empty());
+        // Empty files are still being created
+        settling.observe(memo, at(30));
+        size.set(Some(0));
+        settling.settled(at(30), size_of);
+        assert!(settling.settled(at(40), size_of).is_empty());
+        assert!(!settling.is_empty());
+    }
+
+    #[test]
+    fn outputs_never_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let mut folder = WatchedFolder {
+            path: dir.path().to_path_buf(),
+            format: WatchFormat::Srt,
+            output_dir: None,
+        };
+        let input = dir.path().join("memo.wav");
+        assert!(folder.contains(&input));
+        assert!(!folder.contains(&dir.path().join("sub/memo.wav")));
+        assert_eq!(folder.output_path(&input), dir.path().join("memo.srt"));
+        fs::write(dir.path().join("memo.srt"), "").unwrap();
+        fs::write(dir.path().join("memo (2).srt"), "").unwrap();
+        assert_eq!(folder.output_path(&input), dir.path().join("memo (3).srt"));
+        folder.output_dir = Some(out.path().to_path_buf());
+        assert_eq!(folder.output_path(&input), out.path().join("memo.srt"));
+    }
+
+    #[test]
+    fn scan_lists_audio_only() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.wav", "a.m4a", "a.txt", ".hidden.wav"] {
+            fs::write(dir.path().join(name), "x").unwrap();
+        }
+        fs::create_dir(dir.path().join("sub.wav")).unwrap();
+        assert_eq!(
+            scan(dir.path()),
+            [dir.path().join("a.m4a"), dir.path().join("b.wav")]
+        );
+        assert!(scan(&dir.path().join("gone")).is_empty());
+    }
+
+    #[test]
+    fn manifest_round_trip() {
+        let data = tempfile::tempdir().unwrap();
+        let path = Manifest::path_in(data.path(), Path::new("/home/me/Memos"));
+        assert_ne!(
+            path,
+            Manifest::path_in(data.path(), Path::new("/work/Memos"))
+        );
+        let mut manifest = Manifest::load(&path);
+        assert!(!manifest.is_done("memo.wav", 10, Some(1)));
+        manifest.record(
+            "memo.wav",
+            ManifestEntry {
+                size_bytes: 10,
+                modified: Some(1),
+                sha256: Some("ab".into()),
+                outcome: Outcome::Transcribed {
+                    output: "/home/me/Memos/memo.txt".into(),
+                },
+            },
+        );
+        manifest.save().unwrap();
+
+        let manifest = Manifest::load(&path);
+        assert!(manifest.is_done("memo.wav", 10, Some(1)));
+        // Rewritten under the same name
+        assert!(!manifest.is_done("memo.wav", 12, Some(2)));
+        assert_eq!(
+            manifest.duplicate_of("memo (1).wav", 10, "ab"),
+            Some("memo.wav")
+        );
+        assert_eq!(manifest.duplicate_of("memo.wav", 10, "ab"), None);
+        assert_eq!(manifest.duplicate_of("other.wav", 10, "cd"), None);
+
+        fs::write(&path, "{ not json").unwrap();
+        assert!(!Manifest::load(&path).is_done("memo.wav", 10, Some(1)));
+    }
+}
@@ -0,0 +1,80 @@
+//! The notify watcher over the watched folders.
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+use super::WatchError;
+
+/// One OS watcher for every watched folder. The paths it sees are sent
+/// to the `folder-watch` task, which owns the receiving end.
+#[derive(Default)]
+pub struct FolderWatcher {
+    running: Mutex<Option<Running>>,
+}
+
+struct Running {
+    watcher: RecommendedWatcher,
+    found: mpsc::UnboundedSender<PathBuf>,
+}
+
+fn watcher_error(e: notify::Error) -> WatchError {
+    WatchError::Watcher {
+        message: e.to_string(),
+    }
+}
+
+impl FolderWatcher {
+    /// Start the watcher, watching nothing yet; the files created or
+    /// renamed in the folders `add`ed later come out of the receiver.
+    pub fn start(&self) -> Result<mpsc::UnboundedReceiver<PathBuf>, WatchError> {
+        let (found, receiver) = mpsc::unbounded_channel();
+        let events = found.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            match event {
+                // A rename is how most sync tools finish a file
+                Ok(event) if event.kind.is_create() || event.kind.is_modify() => {
+                    for path in event.paths {
+                        let _ = events.send(path);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Folder watcher error: {}", e),
+            }
+        })
+        .map_err(watcher_error)?;
+        *self.running.lock() = Some(Running { watcher, found });
+        Ok(receiver)
+    }
+
+    pub fn add(&self, folder: &Path) -> Result<(), WatchError> {
+        match self.running.lock().as_mut() {
+            Some(running) => running
+                .watcher
+                .watch(folder, RecursiveMode::NonRecursive)
+                .map_err(watcher_error),
+            None => Err(WatchError::Watcher {
+                message: "not started".to_string(),
+            }),
+        }
+    }
+
+    /// Stop watching `folder`. A folder deleted meanwhile is already
+    /// unwatched, so errors are only logged.
+    pub fn remove(&self, folder: &Path) {
+        if let Some(running) = self.running.lock().as_mut() {
+            if let Err(e) = running.watcher.unwatch(folder) {
+                tracing::debug!("Unwatching {}: {}", folder.display(), e);
+            }
+        }
+    }
+
+    /// Hand `path` over as if the watcher had seen it: the files a scan
+    /// found.
+    pub fn offer(&self, path: PathBuf) {
+        if let Some(running) = self.running.lock().as_ref() {
+            let _ = running.found.send(path);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Post-filter for non-speech annotations.
+//!
+//! `suppress_nst` stops whisper.cpp from *sampling* the dedicated
+//! non-speech tokens, but annotations such as `[BLANK_AUDIO]`,
+//! `[Music]` or `(applause)` still come through spelled out in ordinary
+//! text tokens. This strips them after decoding.
+//!
+//! Only a bracketed group standing on its own (whitespace or punctuation
+//! on both sides) whose content looks like an annotation is removed, so
+//! `f(x)`, `array[0]`, `(see above)` and `[1]` survive.
+
+/// Sound descriptions whisper picked up from subtitle training data.
+/// Matched case-insensitively against the whole bracket content.
+const KNOWN_ANNOTATIONS: &[&str] = &[
+    "applause",
+    "background noise",
+    "beep",
+    "blank_audio",
+    "blank audio",
+    "breathing",
+    "chuckles",
+    "clapping",
+    "coughing",
+    "coughs",
+    "crowd",
+    "inaudible",
+    "laughing",
+    "laughs",
+    "laughter",
+    "music",
+    "music playing",
+    "no audio",
+    "noise",
+    "silence",
+    "sighs",
+    "sigh",
+    "sound",
+    "static",
+    "typing",
+    "upbeat music",
+    "wind",
+];
+
+/// Remove standalone non-speech annotations and tidy the whitespace
+/// they leave behind. Text without annotations is returned unchanged.
+pub fn strip_annotations(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut stripped = false;
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some(end) = annotation_end(&chars, i) {
+            stripped = true;
+            i = end;
+            continue;
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    if stripped {
+        tidy_whitespace(&out)
+    } else {
+        out
+    }
+}
+
+/// If an annotation starts at `start`, the index just past its closing
+/// bracket.
+fn annotation_end(chars: &[char], start: usize) -> Option<usize> {
+    let close = match chars[start] {
+        '[' => ']',
+        '(' => ')',
+        _ => return None,
+    };
+    if start > 0 && is_word_char(chars[start - 1]) {
+        return None;
+    }
+    let len = chars[start + 1..].iter().position(|&c| c == close)?;
+    let end = start + 1 + len + 1;
+    if chars.get(end).copied().is_some_and(is_word_char) {
+        return None;
+    }
+    let inner: String = chars[start + 1..end - 1].iter().collect();
+    is_annotation(&inner, chars[start] == '[').then_some(end)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_annotation(inner: &str, square: bool) -> bool {
+    let trimmed = inner.trim_matches(|c: char| c.is_whitespace() || c == '*' || c == '♪');
+    if trimmed.is_empty() {
+        // `[ ♪ ]`, `(*)` — pure decoration
+        return !inner.is_empty();
+    }
+    let lower = trimmed.to_lowercase();
+    if KNOWN_ANNOTATIONS.contains(&lower.as_str()) {
+        return true;
+    }
+    // Square-bracket tags in SHOUTING_CASE (`[BLANK_AUDIO]`,
+    // `[SPEAKING FOREIGN LANGUAGE]`) are always annotations. Parentheses
+    // are too common in real prose to apply the same rule.
+    square
+        && trimmed.chars().filter(|c| c.is_alphabetic()).count() >= 3
+        && trimmed
+            .chars()
+            .all(|c| c.is_uppercase() || c == '_' || c == ' ' || c == '-')
+}
+
+/// Collapse runs of spaces and drop spaces left in front of punctuation.
+fn tidy_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        let attaches = word.starts_with(['.', ',', '!', '?', ';', ':']);
+        if !out.is_empty() && !attaches {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_mixed_annotations() {
+        assert_eq!(
+            strip_annotations("[Music] hello [Music] world"),
+            "hello world"
+        );
+        assert_eq!(strip_annotations("[BLANK_AUDIO]"), "");
+        assert_eq!(
+            strip_annotations("So (applause) thank you. (laughs)"),
+            "So thank you."
+        );
+        assert_eq!(strip_annotations("♪ [ ♪ ] la la"), "♪ la la");
+    }
+
+    #[test]
+    fn keeps_legitimate_brackets() {
+        for text in [
+            "call f(x) with array[0]",
+            "as noted (see above) it works",
+            "the results [1] are in",
+            "wait ... what",
+        ] {
+            assert_eq!(strip_annotations(text), text);
+        }
+    }
+
+    #[test]
+    fn shouting_case_only_counts_in_square_brackets() {
+        assert_eq!(
+            strip_annotations("[SPEAKING FOREIGN LANGUAGE] bonjour"),
+            "bonjour"
+        );
+        assert_eq!(
+            strip_annotations("the (NASA) mission"),
+            "the (NASA) mission"
+        );
+        assert_eq!(strip_annotations("see [AB] above"), "see [AB] above");
+    }
+
+    #[test]
+    fn removal_does_not_leave_space_before_punctuation() {
+        assert_eq!(strip_annotations("done [Music]."), "done.");
+        assert_eq!(strip_annotations("yes [noise], fine"), "yes, fine");
+    }
+}
@@ -0,0 +1,266 @@
+//! The official whisper.cpp models the download UI offers.
+//!
+//! A compiled-in table (`CATALOG`): each model with its approximate
+//! download size, whether it is English-only, the quantized variants
+//! the whisper.cpp repository has of it, and the hardware that runs it
+//! (`recommend::requirements`). Which ones are installed, which fit
+//! this machine and which one it should use are worked out on every
+//! call to `catalog`, from the models on disk and the hardware.
+//!
+//! Every id here downloads with `download_model`.
+
+use serde::Serialize;
+
+use super::model_files::FoundModel;
+use super::recommend::{self, Hardware, Requirements};
+
+/// Sizes are given in MB, as download pages do.
+const MB: u64 = 1_000_000;
+
+struct Entry {
+    id: &'static str,
+    display_name: &'static str,
+    size_mb: u64,
+    /// Quantization and size in MB of each variant.
+    variants: &'static [(&'static str, u64)],
+}
+
+const TINY_VARIANTS: &[(&str, u64)] = &[("q5_1", 32), ("q8_0", 44)];
+const BASE_VARIANTS: &[(&str, u64)] = &[("q5_1", 60), ("q8_0", 82)];
+const SMALL_VARIANTS: &[(&str, u64)] = &[("q5_1", 190), ("q8_0", 264)];
+const MEDIUM_VARIANTS: &[(&str, u64)] = &[("q5_0", 539), ("q8_0", 823)];
+
+/// Smallest first, each multilingual model before its English-only one.
+const CATALOG: &[Entry] = &[
+    Entry {
+        id: "tiny",
+        display_name: "Tiny",
+        size_mb: 78,
+        variants: TINY_VARIANTS,
+    },
+    Entry {
+        id: "tiny.en",
+        display_name: "Tiny (English)",
+        size_mb: 78,
+        variants: TINY_VARIANTS,
+    },
+    Entry {
+        id: "base",
+        display_name: "Base",
+        size_mb: 148,
+        variants: BASE_VARIANTS,
+    },
+    Entry {
+        id: "base.en",
+        display_name: "Base (English)",
+        size_mb: 148,
+        variants: BASE_VARIANTS,
+    },
+    Entry {
+        id: "small",
+        display_name: "Small",
+        size_mb: 488,
+        variants: SMALL_VARIANTS,
+    },
+    Entry {
+        id: "small.en",
+        display_name: "Small (English)",
+        size_mb: 488,
+        variants: SMALL_VARIANTS,
+    },
+    Entry {
+        id: "medium",
+        display_name: "Medium",
+        size_mb: 1533,
+        variants: MEDIUM_VARIANTS,
+    },
+    Entry {
+        id: "medium.en",
+        display_name: "Medium (English)",
+        size_mb: 1533,
+        variants: MEDIUM_VARIANTS,
+    },
+    Entry {
+        id: "large-v3-turbo",
+        display_name: "Large V3 Turbo",
+        size_mb: 1624,
+        variants: &[("q5_0", 574), ("q8_0", 874)],
+    },
+    Entry {
+        id: "large-v3",
+        display_name: "Large V3",
+        size_mb: 3095,
+        variants: &[("q5_0", 1081)],
+    },
+];
+
+/// A quantized variant of a `CatalogModel`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogVariant {
+    /// What `download_model` takes: `small-q5_1`.
+    pub id: String,
+    pub quantization: String,
+    pub size_bytes: u64,
+    pub installed: bool,
+}
+
+/// A `get_model_catalog` entry. Frontend mirror: `CatalogModel`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogModel {
+    pub id: String,
+    pub display_name: String,
+    /// Approximate download size.
+    pub size_bytes: u64,
+    pub english_only: bool,
+    pub variants: Vec<CatalogVariant>,
+    /// What runs it at dictation speed.
+    pub requirements: Requirements,
+    /// This machine meets `requirements`.
+    pub fits: bool,
+    /// The model `recommend_model` picks for this machine.
+    pub recommended: bool,
+    /// Its unquantized file is on disk.
+    pub installed: bool,
+}
+
+/// The catalog for `hardware`, given the models on disk.
+pub fn catalog(hardware: &Hardware, available: &[FoundModel]) -> Vec<CatalogModel> {
+    let installed = |id: &str, quantization: Option<&str>| {
+        available
+            .iter()
+            .any(|model| model.file.id == id && model.file.quantization.as_deref() == quantization)
+    };
+    let ids: Vec<String> = available
+        .iter()
+        .map(|model| model.file.id.clone())
+        .collect();
+    let pick = recommend::recommend(hardware, &ids).model;
+    CATALOG
+        .iter()
+        .map(|entry| {
+            // Every catalog model is in the tiers
+            let requirements = recommend::requirements(entry.id).expect("catalog model tier");
+            CatalogModel {
+                id: entry.id.to_string(),
+                display_name: entry.display_name.to_string(),
+                size_bytes: entry.size_mb * MB,
+                english_only: entry.id.ends_with(".en"),
+                variants: entry
+                    .variants
+                    .iter()
+                    .map(|&(quantization, size_mb)| CatalogVariant {
+                        id: format!("{}-{quantization}", entry.id),
+                        quantization: quantization.to_string(),
+                        size_bytes: size_mb * MB,
+                        installed: installed(entry.id, Some(quantization)),
+                    })
+                    .collect(),
+                requirements,
+                fits: recommend::fits(&requirements, hardware),
+                recommended: entry.id == pick,
+                installed: installed(entry.id, None),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whisper::model_files::{self, ModelDir, ModelLocation};
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn laptop() -> Hardware {
+        Hardware {
+            ram_bytes: 8 * GB,
+            cpu_cores: 4,
+            gpu: None,
+        }
+    }
+
+    #[test]
+    fn every_entry_downloads() {
+        for model in catalog(&laptop(), &[]) {
+            assert!(
+                crate::model_download::ModelSource::hugging_face(&model.id).is_some(),
+                "{}",
+                model.id
+            );
+            for variant in &model.variants {
+                let file =
+                    model_files::parse_filename(&format!("ggml-{}.bin", variant.id)).unwrap();
+                assert_eq!(file.id, model.id);
+                assert_eq!(file.quantization.as_deref(), Some(&*variant.quantization));
+            }
+        }
+    }
+
+    #[test]
+    fn flags_follow_the_disk_and_the_machine() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["ggml-base.bin", "ggml-small.en-q5_1.bin"] {
+            std::fs::write(dir.path().join(name), "x").unwrap();
+        }
+        let available = model_files::scan(&[ModelDir {
+            path: dir.path().to_path_buf(),
+            location: ModelLocation::AppData,
+        }]);
+        let catalog = catalog(&laptop(), &available);
+        let model = |id: &str| catalog.iter().find(|model| model.id == id).unwrap();
+
+        assert!(model("base").installed);
+        assert!(!model("base.en").installed);
+        let small_en = model("small.en");
+        assert!(!small_en.installed);
+        assert_eq!(
+            small_en
+                .variants
+                .iter()
+                .map(|variant| (variant.id.as_str(), variant.installed))
+                .collect::<Vec<_>>(),
+            [("small.en-q5_1", true), ("small.en-q8_0", false)]
+        );
+
+        // 8 GB and 4 cores, no GPU: small, even though it isn't on disk
+        assert_eq!(
+            catalog
+                .iter()
+                .filter(|model| model.recommended)
+                .map(|model| model.id.as_str())
+                .collect::<Vec<_>>(),
+            ["small"]
+        );
+        assert!(model("small.en").fits);
+        assert!(!model("medium").fits);
+        assert!(!model("large-v3-turbo").fits);
+    }
+
+    #[test]
+    fn payload() {
+        let json = serde_json::to_value(&catalog(&laptop(), &[])[0]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "id": "tiny",
+                "displayName": "Tiny",
+                "sizeBytes": 78_000_000,
+                "englishOnly": false,
+                "variants": [
+                    { "id": "tiny-q5_1", "quantization": "q5_1", "sizeBytes": 32_000_000, "installed": false },
+                    { "id": "tiny-q8_0", "quantization": "q8_0", "sizeBytes": 44_000_000, "installed": false }
+                ],
+                "requirements": {
+                    "minRamGb": 0,
+                    "minVramGb": 0,
+                    "cpu": { "ramGb": 0, "cores": 0 }
+                },
+                "fits": true,
+                "recommended": false,
+                "installed": false
+            })
+        );
+    }
+}
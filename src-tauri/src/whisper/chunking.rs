@@ -0,0 +1,322 @@
+//! Long recordings, transcribed in overlapping windows.
+//!
+//! Whisper sees 30 s at a time; past that, whisper.cpp slides its own
+//! window but quality drops badly towards the end of long dictations.
+//! Recordings longer than `CHUNK_THRESHOLD_SECS` are cut into windows of
+//! `CHUNK_SECS` overlapping by `OVERLAP_SECS`, decoded one after the
+//! other (each with the previous window's text as prompt), and stitched
+//! back together here.
+//!
+//! Stitching shifts every window's segments to recording time, then
+//! removes what the overlap decoded twice: segments of the next window
+//! that end before the kept text does are dropped whole, and a segment
+//! straddling the join loses the leading words that repeat the end of
+//! the kept text.
+
+use std::ops::Range;
+
+use super::worker::Segment;
+
+/// Recordings up to this long are decoded in one pass.
+pub const CHUNK_THRESHOLD_SECS: usize = 28;
+pub const CHUNK_SECS: usize = 25;
+pub const OVERLAP_SECS: usize = 3;
+/// Window length when a decode that stopped early is retried (see
+/// `whisper::coverage`): each window's text stays far from filling the
+/// text context.
+pub const RETRY_CHUNK_SECS: usize = 10;
+/// A last window with less new audio than this is folded into the one
+/// before it instead.
+const MIN_TAIL_SECS: usize = 2;
+/// Most words compared when de-duplicating a join.
+const MAX_OVERLAP_WORDS: usize = 24;
+const SAMPLE_RATE: usize = 16000;
+
+/// Sample ranges to decode, in order. One range covering everything
+/// when the recording is short enough.
+pub fn windows(len: usize) -> Vec<Range<usize>> {
+    if len <= CHUNK_THRESHOLD_SECS * SAMPLE_RATE {
+        // A window as long as the recording: left whole
+        return windows_of(len, CHUNK_THRESHOLD_SECS);
+    }
+    windows_of(len, CHUNK_SECS)
+}
+
+/// Windows of `chunk_secs` overlapping by `OVERLAP_SECS`, whatever the
+/// recording's length. `chunk_secs` must exceed the overlap.
+pub fn windows_of(len: usize, chunk_secs: usize) -> Vec<Range<usize>> {
+    let mut windows = Vec::new();
+    let chunk = chunk_secs * SAMPLE_RATE;
+    let step = (chunk_secs - OVERLAP_SECS) * SAMPLE_RATE;
+    let mut start = 0;
+    loop {
+        let mut end = (start + chunk).min(len);
+        if len - end < MIN_TAIL_SECS * SAMPLE_RATE {
+            end = len;
+        }
+        windows.push(start..end);
+        if end == len {
+            return windows;
+        }
+        start += step;
+    }
+}
+
+/// Offset of the sample at `index`, in ms.
+pub fn offset_ms(index: usize) -> u64 {
+    (index as u64 * 1000) / SAMPLE_RATE as u64
+}
+
+/// The segments of one window, timed relative to the window.
+#[derive(Debug, Clone)]
+pub struct WindowSegments {
+    pub offset_ms: u64,
+    pub segments: Vec<Segment>,
+}
+
+/// Merge decoded windows (in order) into one timeline.
+pub fn stitch(windows: Vec<WindowSegments>) -> Vec<Segment> {
+    let mut kept: Vec<Segment> = Vec::new();
+    for window in windows {
+        let covered = kept.last().map_or(0, |s| s.end_ms);
+        let mut first = true;
+        for mut segment in window.segments {
+            segment.start_ms += window.offset_ms;
+            segment.end_ms += window.offset_ms;
+            if segment.end_ms <= covered {
+                continue;
+            }
+            if first && segment.start_ms < covered {
+                let repeated = repeated_words(&kept, &segment.text);
+                if repeated > 0 {
+                    segment.text = drop_words(&segment.text, repeated);
+                    segment.start_ms = covered;
+                }
+            }
+            first = false;
+            if !segment.text.is_empty() {
+                kept.push(segment);
+            }
+        }
+    }
+    kept
+}
+
+/// Comparison form of a word: lowercase, no surrounding punctuation.
+fn normalize(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// Length of the longest run of leading words of `text` that repeats
+/// the last words of `kept`.
+fn repeated_words(kept: &[Segment], text: &str) -> usize {
+    let mut tail: Vec<String> = kept
+        .iter()
+        .rev()
+        .flat_map(|s| s.text.split_whitespace().rev())
+        .take(MAX_OVERLAP_WORDS)
+        .map(normalize)
+        .collect();
+    tail.reverse();
+    let head: Vec<String> = text
+        .split_whitespace()
+        .take(MAX_OVERLAP_WORDS)
+        .map(normalize)
+        .collect();
+    (1..=head.len().min(tail.len()))
+        .rev()
+        .find(|&n| tail[tail.len() - n..] == head[..n])
+        .unwrap_or(0)
+}
+
+fn drop_words(text: &str, n: usize) -> String {
+    text.split_whitespace()
+        .skip(n)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Recording-wide confidence from the kept segments, weighted by text
+/// length (the per-token statistics stay inside each window's decode).
+pub fn overall_confidence(segments: &[Segment]) -> Option<f32> {
+    let (sum, weight) = segments
+        .iter()
+        .filter_map(|s| s.confidence.map(|c| (c, s.text.chars().count() as f32)))
+        .fold((0.0, 0.0), |(sum, weight), (c, w)| {
+            (sum + c * w, weight + w)
+        });
+    (weight > 0.0).then(|| sum / weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: usize = SAMPLE_RATE;
+
+    fn segment(start_ms: u64, end_ms: u64, text: &str) -> Segment {
+        Segment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+            confidence: Some(0.9),
+        }
+    }
+
+    fn texts(segments: &[Segment]) -> Vec<&str> {
+        segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn short_recordings_are_one_window() {
+        for len in [10 * SECOND, CHUNK_THRESHOLD_SECS * SECOND] {
+            let windows = windows(len);
+            assert_eq!(windows.len(), 1);
+            assert_eq!(windows[0], 0..len);
+        }
+    }
+
+    #[test]
+    fn long_recordings_overlap() {
+        let len = 60 * SECOND;
+        let windows = windows(len);
+        assert_eq!(
+            windows,
+            [0..25 * SECOND, 22 * SECOND..47 * SECOND, 44 * SECOND..len]
+        );
+        for pair in windows.windows(2) {
+            assert_eq!(pair[0].end - pair[1].start, OVERLAP_SECS * SECOND);
+        }
+    }
+
+    #[test]
+    fn a_short_tail_is_folded_into_the_last_window() {
+        // 47 s + 1 s: the third window would bring only 1 s of new audio
+        let len = 48 * SECOND;
+        assert_eq!(windows(len), [0..25 * SECOND, 22 * SECOND..len]);
+    }
+
+    #[test]
+    fn retry_windows_split_short_recordings_too() {
+        let len = 20 * SECOND;
+        assert_eq!(
+            windows_of(len, RETRY_CHUNK_SECS),
+            [0..10 * SECOND, 7 * SECOND..17 * SECOND, 14 * SECOND..len]
+        );
+        // Nothing to split
+        let windows = windows_of(5 * SECOND, RETRY_CHUNK_SECS);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0], 0..5 * SECOND);
+    }
+
+    #[test]
+    fn timestamps_are_offset_per_window() {
+        let stitched = stitch(vec![
+            WindowSegments {
+                offset_ms: 0,
+                segments: vec![segment(0, 4000, "First part.")],
+            },
+            WindowSegments {
+                offset_ms: 22_000,
+                segments: vec![segment(3500, 8000, "Second part.")],
+            },
+        ]);
+        assert_eq!(stitched[1].start_ms, 25_500);
+        assert_eq!(stitched[1].end_ms, 30_000);
+        assert_eq!(texts(&stitched), vec!["First part.", "Second part."]);
+    }
+
+    #[test]
+    fn segments_inside_the_overlap_are_not_repeated() {
+        let stitched = stitch(vec![
+            WindowSegments {
+                offset_ms: 0,
+                segments: vec![
+                    segment(0, 20_000, "We reviewed the budget."),
+                    segment(20_000, 24_800, "Then we moved on."),
+                ],
+            },
+            WindowSegments {
+                offset_ms: 22_000,
+                // Decoded again, entirely within the overlap
+                segments: vec![
+                    segment(0, 2_500, "moved on."),
+                    segment(2_900, 9_000, "Next item is hiring."),
+                ],
+            },
+        ]);
+        assert_eq!(
+            texts(&stitched),
+            vec![
+                "We reviewed the budget.",
+                "Then we moved on.",
+                "Next item is hiring."
+            ]
+        );
+    }
+
+    #[test]
+    fn straddling_segment_loses_the_repeated_words() {
+        let stitched = stitch(vec![
+            WindowSegments {
+                offset_ms: 0,
+                segments: vec![segment(0, 25_000, "and the total was forty two")],
+            },
+            WindowSegments {
+                offset_ms: 22_000,
+                segments: vec![segment(1_000, 6_000, "Was forty two, which is fine.")],
+            },
+        ]);
+        assert_eq!(
+            texts(&stitched),
+            vec!["and the total was forty two", "which is fine."]
+        );
+        // Starts where the kept text ends
+        assert_eq!(stitched[1].start_ms, 25_000);
+    }
+
+    #[test]
+    fn matching_words_after_the_overlap_are_kept() {
+        // Starts after the kept text ends: a repeat there was spoken
+        let stitched = stitch(vec![
+            WindowSegments {
+                offset_ms: 0,
+                segments: vec![segment(0, 24_000, "say it again")],
+            },
+            WindowSegments {
+                offset_ms: 22_000,
+                segments: vec![segment(2_500, 5_000, "again and again")],
+            },
+        ]);
+        assert_eq!(texts(&stitched), vec!["say it again", "again and again"]);
+    }
+
+    #[test]
+    fn fully_repeated_segment_disappears() {
+        let stitched = stitch(vec![
+            WindowSegments {
+                offset_ms: 0,
+                segments: vec![segment(0, 25_000, "the end of it")],
+            },
+            WindowSegments {
+                offset_ms: 22_000,
+                segments: vec![
+                    segment(2_000, 3_500, "of it"),
+                    segment(3_500, 7_000, "Fresh start."),
+                ],
+            },
+        ]);
+        assert_eq!(texts(&stitched), vec!["the end of it", "Fresh start."]);
+    }
+
+    #[test]
+    fn confidence_is_length_weighted() {
+        let mut short = segment(0, 1000, "ok");
+        short.confidence = Some(0.2);
+        let long = segment(1000, 5000, "a much longer sentence here");
+        let confidence = overall_confidence(&[short, long]).unwrap();
+        assert!(confidence > 0.8 && confidence < 0.9, "{confidence}");
+        assert_eq!(overall_confidence(&[]), None);
+    }
+}
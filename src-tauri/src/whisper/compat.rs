@@ -149,7 +149,7 @@ pub struct ValidationResult {
     pub warnings: Vec<ImportWarning>,
 }
 
-fn ftype_to_label(ftype: i32) -> &'static str {
+pub(crate) fn ftype_to_label(ftype: i32) -> &'static str {
     // Matches the `ggml_ftype` enum in `whisper-rs-sys` /
     // `whisper.cpp/ggml/include/ggml.h:440-464`. Keep in sync with
     // upstream when bumping whisper-rs.
@@ -181,7 +181,7 @@ fn ftype_to_label(ftype: i32) -> &'static str {
     }
 }
 
-fn n_audio_state_to_size_class(n_audio_state: i32) -> &'static str {
+pub(crate) fn n_audio_state_to_size_class(n_audio_state: i32) -> &'static str {
     // Standard Whisper model-size matrix from the OpenAI paper. A
     // community fine-tune with non-standard dims hits "unknown" and
     // gets flagged by the `NonStandardSizeClass` warning.
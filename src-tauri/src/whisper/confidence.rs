@@ -0,0 +1,96 @@
+//! Transcript confidence from token probabilities.
+//!
+//! A segment's confidence is the geometric mean of its text tokens'
+//! probabilities, i.e. `exp` of their average log-probability, which
+//! lands in 0–1 and — unlike the arithmetic mean — is dragged down by a
+//! single very unlikely token, the usual sign of a misheard word.
+//! Special tokens (timestamps, end-of-text, ...) are not counted.
+
+/// Probabilities are clamped to this before `ln`, so a token whisper
+/// gave 0 doesn't turn the whole segment into `-inf`.
+const MIN_PROBABILITY: f32 = 1e-6;
+
+/// Running sum of token log-probabilities.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenStats {
+    sum_logprob: f64,
+    tokens: u32,
+}
+
+impl TokenStats {
+    pub fn push(&mut self, probability: f32) {
+        self.sum_logprob += f64::from(probability.clamp(MIN_PROBABILITY, 1.0)).ln();
+        self.tokens += 1;
+    }
+
+    /// Add another segment's tokens (token-weighted, for the overall
+    /// confidence).
+    pub fn merge(&mut self, other: TokenStats) {
+        self.sum_logprob += other.sum_logprob;
+        self.tokens += other.tokens;
+    }
+
+    pub fn count(&self) -> u32 {
+        self.tokens
+    }
+
+    /// `exp(mean log p)`; `None` without tokens.
+    pub fn confidence(&self) -> Option<f32> {
+        (self.tokens > 0).then(|| (self.sum_logprob / f64::from(self.tokens)).exp() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(probabilities: &[f32]) -> TokenStats {
+        let mut stats = TokenStats::default();
+        for &p in probabilities {
+            stats.push(p);
+        }
+        stats
+    }
+
+    fn close(a: Option<f32>, b: f32) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-5)
+    }
+
+    #[test]
+    fn empty_segment_has_no_confidence() {
+        assert_eq!(TokenStats::default().confidence(), None);
+        // Merging nothing changes nothing
+        let mut total = stats(&[0.5]);
+        total.merge(TokenStats::default());
+        assert!(close(total.confidence(), 0.5));
+    }
+
+    #[test]
+    fn single_token_is_its_probability() {
+        assert!(close(stats(&[0.8]).confidence(), 0.8));
+        assert!(close(stats(&[1.0]).confidence(), 1.0));
+    }
+
+    #[test]
+    fn geometric_mean_of_tokens() {
+        assert!(close(stats(&[0.9, 0.4]).confidence(), 0.6));
+        // One bad token weighs more than in an arithmetic mean
+        let confidence = stats(&[0.99, 0.99, 0.01]).confidence().unwrap();
+        assert!(confidence < 0.25, "{confidence}");
+    }
+
+    #[test]
+    fn zero_and_out_of_range_probabilities_are_clamped() {
+        let confidence = stats(&[0.0, 1.0]).confidence().unwrap();
+        assert!(confidence.is_finite() && confidence > 0.0);
+        assert!(close(stats(&[1.5]).confidence(), 1.0));
+    }
+
+    #[test]
+    fn overall_is_token_weighted() {
+        let mut total = stats(&[0.9, 0.4]);
+        total.merge(stats(&[0.6]));
+        // (0.9 · 0.4 · 0.6)^(1/3) = 0.6
+        assert!(close(total.confidence(), 0.6));
+    }
+}
@@ -0,0 +1,186 @@
+//! Did the decode get to the end of what was said?
+//!
+//! A decode sometimes stops early (the text context fills up, or
+//! whisper.cpp gives up on a window) and the transcript simply ends
+//! mid-sentence. Compare where the last segment ends with where the
+//! VAD last heard speech: more than `TRUNCATION_GAP_MS` of speech-bearing
+//! audio past the last segment flags the result as possibly truncated.
+//! Trailing silence (a pause before releasing the key) doesn't count.
+
+use serde::Serialize;
+use std::ops::Range;
+
+use super::worker::Segment;
+
+/// Speech-bearing audio past the last segment beyond which a decode
+/// counts as cut short.
+pub const TRUNCATION_GAP_MS: u64 = 3000;
+/// Less speech than this past the last segment is a cough or a click,
+/// not a lost sentence.
+const MIN_UNCOVERED_SPEECH_MS: u64 = 500;
+
+/// Totals of one decode against the recording. Carried by
+/// `transcript:final` and `transcript:truncated`. Frontend mirror:
+/// `DecodeCoverage`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Coverage {
+    /// Text tokens generated.
+    pub tokens: u32,
+    pub segments: usize,
+    pub audio_ms: u64,
+    /// End of the last segment; 0 without segments.
+    pub covered_ms: u64,
+    /// From `covered_ms` to the end of the last speech after it.
+    pub gap_ms: u64,
+    /// Detected speech within the gap.
+    pub uncovered_speech_ms: u64,
+    pub possibly_truncated: bool,
+}
+
+/// Assess `segments` (recording time) of a decode of `audio_ms`, given
+/// the VAD's speech spans in ms.
+pub fn assess(segments: &[Segment], tokens: u32, audio_ms: u64, speech: &[Range<u64>]) -> Coverage {
+    let covered_ms = segments.iter().map(|s| s.end_ms).max().unwrap_or(0);
+    let uncovered: Vec<Range<u64>> = speech
+        .iter()
+        .map(|span| span.start.max(covered_ms)..span.end.min(audio_ms))
+        .filter(|span| span.end > span.start)
+        .collect();
+    let gap_ms = uncovered.last().map_or(0, |span| span.end - covered_ms);
+    let uncovered_speech_ms = uncovered.iter().map(|span| span.end - span.start).sum();
+    Coverage {
+        tokens,
+        segments: segments.len(),
+        audio_ms,
+        covered_ms,
+        gap_ms,
+        uncovered_speech_ms,
+        possibly_truncated: gap_ms > TRUNCATION_GAP_MS
+            && uncovered_speech_ms >= MIN_UNCOVERED_SPEECH_MS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(pairs: &[(u64, u64)]) -> Vec<Range<u64>> {
+        pairs.iter().map(|&(start, end)| start..end).collect()
+    }
+
+    fn segment(start_ms: u64, end_ms: u64) -> Segment {
+        Segment {
+            start_ms,
+            end_ms,
+            text: "words".to_string(),
+            confidence: Some(0.9),
+        }
+    }
+
+    #[test]
+    fn complete_decode() {
+        let coverage = assess(
+            &[segment(0, 4000), segment(4000, 9500)],
+            42,
+            12_000,
+            &spans(&[(300, 4200), (4600, 9400)]),
+        );
+        assert_eq!(
+            coverage,
+            Coverage {
+                tokens: 42,
+                segments: 2,
+                audio_ms: 12_000,
+                covered_ms: 9500,
+                gap_ms: 0,
+                uncovered_speech_ms: 0,
+                possibly_truncated: false,
+            }
+        );
+    }
+
+    #[test]
+    fn speech_after_the_last_segment_is_truncation() {
+        // Decode stopped at 8 s; speech went on until 14 s
+        let coverage = assess(
+            &[segment(0, 8000)],
+            30,
+            15_000,
+            &spans(&[(200, 7800), (8400, 11_000), (11_600, 14_000)]),
+        );
+        assert_eq!(coverage.gap_ms, 6000);
+        assert_eq!(coverage.uncovered_speech_ms, 5000);
+        assert!(coverage.possibly_truncated);
+    }
+
+    #[test]
+    fn trailing_silence_is_not_truncation() {
+        // Ten seconds of silence before the key was released
+        let coverage = assess(&[segment(0, 5000)], 20, 15_000, &spans(&[(100, 4900)]));
+        assert_eq!(coverage.gap_ms, 0);
+        assert!(!coverage.possibly_truncated);
+    }
+
+    #[test]
+    fn short_tails_and_blips_are_not_truncation() {
+        // A last sentence the segment timestamps end a little early on
+        let coverage = assess(
+            &[segment(0, 8000)],
+            20,
+            12_000,
+            &spans(&[(0, 7000), (7500, 10_500)]),
+        );
+        assert_eq!(coverage.gap_ms, 2500);
+        assert!(!coverage.possibly_truncated);
+        // A click long after the last word: wide gap, barely any speech
+        let coverage = assess(
+            &[segment(0, 3000)],
+            20,
+            12_000,
+            &spans(&[(0, 2900), (9000, 9200)]),
+        );
+        assert_eq!(coverage.gap_ms, 6200);
+        assert_eq!(coverage.uncovered_speech_ms, 200);
+        assert!(!coverage.possibly_truncated);
+    }
+
+    #[test]
+    fn no_segments_with_speech_is_truncation() {
+        let coverage = assess(&[], 0, 6000, &spans(&[(500, 5000)]));
+        assert_eq!(coverage.covered_ms, 0);
+        assert_eq!(coverage.gap_ms, 5000);
+        assert!(coverage.possibly_truncated);
+    }
+
+    #[test]
+    fn speech_spans_are_clipped_to_the_audio() {
+        // The VAD saw the last chunk, the capture buffer ended earlier
+        let coverage = assess(
+            &[segment(0, 2000)],
+            10,
+            6000,
+            &spans(&[(0, 1900), (2500, 6400)]),
+        );
+        assert_eq!(coverage.gap_ms, 4000);
+        assert_eq!(coverage.uncovered_speech_ms, 3500);
+        assert!(coverage.possibly_truncated);
+    }
+
+    #[test]
+    fn payload() {
+        let json = serde_json::to_value(assess(&[segment(0, 1000)], 5, 2000, &[])).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "tokens": 5,
+                "segments": 1,
+                "audioMs": 2000,
+                "coveredMs": 1000,
+                "gapMs": 0,
+                "uncoveredSpeechMs": 0,
+                "possiblyTruncated": false
+            })
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Decode-parameter resolution.
+//!
+//! Some languages decode noticeably better with different settings —
+//! Chinese and Japanese gain a lot from beam search, English is fine
+//! with greedy. The effective parameter set for a transcription is
+//! built by layering partial [`DecodeOverride`]s, lowest precedence
+//! first:
+//!
+//! 1. engine defaults ([`DecodeParams::default`])
+//! 2. the model preset (per-model tuning, if any)
+//! 3. the built-in per-language defaults ([`builtin_language_override`])
+//! 4. the user's advanced settings (global, all languages)
+//! 5. the user's per-language override from settings
+//!
+//! A later layer only replaces the fields it actually sets. The most
+//! specific user choice wins; built-in language defaults never override
+//! anything the user configured explicitly.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Sampling strategy handed to whisper.cpp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DecodeStrategy {
+    Greedy,
+    BeamSearch,
+}
+
+/// Fully resolved decode parameters. Echoed back in the transcription
+/// result so the UI can show what was actually used.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeParams {
+    pub strategy: DecodeStrategy,
+    /// Candidates sampled per step. Only meaningful for `Greedy`.
+    pub best_of: u32,
+    /// Only meaningful for `BeamSearch`.
+    pub beam_size: u32,
+    /// Beam-search patience; negative = whisper.cpp default. Only
+    /// meaningful for `BeamSearch`.
+    pub patience: f32,
+    pub temperature: f32,
+    pub suppress_nst: bool,
+}
+
+impl Default for DecodeParams {
+    /// The historical hard-coded set: deterministic greedy decoding with
+    /// non-speech tokens suppressed.
+    fn default() -> Self {
+        Self {
+            strategy: DecodeStrategy::Greedy,
+            best_of: 1,
+            beam_size: 5,
+            patience: -1.0,
+            temperature: 0.0,
+            suppress_nst: true,
+        }
+    }
+}
+
+/// Upper bound for `best_of` / `beam_size`. whisper.cpp allocates one
+/// decoder per candidate (`WHISPER_MAX_DECODERS` = 8).
+pub const MAX_CANDIDATES: u32 = 8;
+
+/// User-facing decoding choice (the `decodingStrategy` setting). Maps
+/// onto the "user advanced settings" layer.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "strategy",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum DecodingOptions {
+    Greedy { best_of: u32 },
+    BeamSearch { beam_size: u32, patience: f32 },
+}
+
+impl DecodingOptions {
+    pub fn to_override(self) -> DecodeOverride {
+        match self {
+            DecodingOptions::Greedy { best_of } => DecodeOverride {
+                strategy: Some(DecodeStrategy::Greedy),
+                best_of: Some(best_of),
+                ..Default::default()
+            },
+            DecodingOptions::BeamSearch {
+                beam_size,
+                patience,
+            } => DecodeOverride {
+                strategy: Some(DecodeStrategy::BeamSearch),
+                beam_size: Some(beam_size),
+                patience: Some(patience),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Partial set of decode parameters. `None` = inherit from the layer below.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<DecodeStrategy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_of: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beam_size: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patience: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suppress_nst: Option<bool>,
+}
+
+impl DecodeOverride {
+    fn apply(&self, params: &mut DecodeParams) {
+        if let Some(v) = self.strategy {
+            params.strategy = v;
+        }
+        if let Some(v) = self.best_of {
+            params.best_of = v.clamp(1, MAX_CANDIDATES);
+        }
+        if let Some(v) = self.beam_size {
+            params.beam_size = v.clamp(1, MAX_CANDIDATES);
+        }
+        if let Some(v) = self.patience {
+            params.patience = if v < 0.0 { -1.0 } else { v };
+        }
+        if let Some(v) = self.temperature {
+            params.temperature = v.clamp(0.0, 1.0);
+        }
+        if let Some(v) = self.suppress_nst {
+            params.suppress_nst = v;
+        }
+    }
+}
+
+/// Temperature-fallback knobs (the `advancedDecoding` setting). When a
+/// decode looks degenerate — compression ratio above `entropy_thold`
+/// or average log-probability below `logprob_thold` — whisper.cpp
+/// retries at `temperature + temperature_inc`, and so on up to 1.0.
+/// Defaults are whisper.cpp's own.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AdvancedDecoding {
+    pub temperature: f32,
+    /// 0.0 disables fallback entirely.
+    pub temperature_inc: f32,
+    pub entropy_thold: f32,
+    pub logprob_thold: f32,
+}
+
+impl Default for AdvancedDecoding {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            entropy_thold: 2.4,
+            logprob_thold: -1.0,
+        }
+    }
+}
+
+/// Why `set_advanced_decoding` rejected a value. Values are never
+/// clamped silently on this path — the UI shows the allowed range.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum AdvancedDecodingError {
+    OutOfRange {
+        field: &'static str,
+        value: f32,
+        min: f32,
+        max: f32,
+    },
+    /// settings.json couldn't be written.
+    PersistFailed { reason: String },
+}
+
+impl AdvancedDecoding {
+    /// Check every field against its allowed range (NaN fails too).
+    pub fn validate(&self) -> Result<(), AdvancedDecodingError> {
+        let checks: [(&'static str, f32, f32, f32); 4] = [
+            ("temperature", self.temperature, 0.0, 1.0),
+            ("temperatureInc", self.temperature_inc, 0.0, 1.0),
+            ("entropyThold", self.entropy_thold, 0.0, 10.0),
+            ("logprobThold", self.logprob_thold, -10.0, 0.0),
+        ];
+        for (field, value, min, max) in checks {
+            if !(min..=max).contains(&value) {
+                return Err(AdvancedDecodingError::OutOfRange {
+                    field,
+                    value,
+                    min,
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Embedded per-language defaults. CJK languages get beam search; every
+/// other language keeps the engine defaults.
+pub fn builtin_language_override(language: &str) -> Option<DecodeOverride> {
+    match language {
+        "zh" | "ja" | "ko" => Some(DecodeOverride {
+            strategy: Some(DecodeStrategy::BeamSearch),
+            beam_size: Some(5),
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+/// Resolve the effective decode parameters for one request. `language`
+/// is the effective language (`None` for auto-detect, which skips both
+/// per-language layers). Pure — see the module docs for the order.
+pub fn resolve_decode_params(
+    model_preset: Option<&DecodeOverride>,
+    language: Option<&str>,
+    user_advanced: Option<&DecodeOverride>,
+    user_language_overrides: &HashMap<String, DecodeOverride>,
+) -> DecodeParams {
+    let mut params = DecodeParams::default();
+    if let Some(preset) = model_preset {
+        preset.apply(&mut params);
+    }
+    if let Some(builtin) = language.and_then(builtin_language_override) {
+        builtin.apply(&mut params);
+    }
+    if let Some(advanced) = user_advanced {
+        advanced.apply(&mut params);
+    }
+    if let Some(user) = language.and_then(|l| user_language_overrides.get(l)) {
+        user.apply(&mut params);
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beam(size: u32) -> DecodeOverride {
+        DecodeOverride {
+            strategy: Some(DecodeStrategy::BeamSearch),
+            beam_size: Some(size),
+            ..Default::default()
+        }
+    }
+
+    fn greedy() -> DecodeOverride {
+        DecodeOverride {
+            strategy: Some(DecodeStrategy::Greedy),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn english_without_overrides_uses_defaults() {
+        let p = resolve_decode_params(None, Some("en"), None, &HashMap::new());
+        assert_eq!(p, DecodeParams::default());
+    }
+
+    #[test]
+    fn auto_detect_skips_language_layers() {
+        let mut user = HashMap::new();
+        user.insert("ja".to_string(), greedy());
+        let p = resolve_decode_params(None, None, None, &user);
+        assert_eq!(p.strategy, DecodeStrategy::Greedy);
+    }
+
+    #[test]
+    fn cjk_gets_builtin_beam_search() {
+        for lang in ["zh", "ja"] {
+            let p = resolve_decode_params(None, Some(lang), None, &HashMap::new());
+            assert_eq!(p.strategy, DecodeStrategy::BeamSearch, "{lang}");
+            assert_eq!(p.beam_size, 5);
+            // Untouched fields still come from the defaults.
+            assert!(p.suppress_nst);
+        }
+    }
+
+    #[test]
+    fn builtin_language_beats_model_preset() {
+        let preset = DecodeOverride {
+            temperature: Some(0.2),
+            ..greedy()
+        };
+        let p = resolve_decode_params(Some(&preset), Some("zh"), None, &HashMap::new());
+        assert_eq!(p.strategy, DecodeStrategy::BeamSearch);
+        // The preset's other fields survive.
+        assert_eq!(p.temperature, 0.2);
+    }
+
+    #[test]
+    fn user_advanced_beats_builtin_language() {
+        let p = resolve_decode_params(None, Some("ja"), Some(&greedy()), &HashMap::new());
+        assert_eq!(p.strategy, DecodeStrategy::Greedy);
+    }
+
+    #[test]
+    fn user_language_override_beats_everything() {
+        let mut user = HashMap::new();
+        user.insert(
+            "ja".to_string(),
+            DecodeOverride {
+                suppress_nst: Some(false),
+                ..beam(8)
+            },
+        );
+        let p = resolve_decode_params(Some(&greedy()), Some("ja"), Some(&greedy()), &user);
+        assert_eq!(p.strategy, DecodeStrategy::BeamSearch);
+        assert_eq!(p.beam_size, 8);
+        assert!(!p.suppress_nst);
+    }
+
+    #[test]
+    fn user_language_override_only_applies_to_its_language() {
+        let mut user = HashMap::new();
+        user.insert("fr".to_string(), beam(3));
+        let p = resolve_decode_params(None, Some("de"), None, &user);
+        assert_eq!(p.strategy, DecodeStrategy::Greedy);
+        let p = resolve_decode_params(None, Some("fr"), None, &user);
+        assert_eq!(p.beam_size, 3);
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let o = DecodeOverride {
+            beam_size: Some(0),
+            best_of: Some(64),
+            patience: Some(-3.0),
+            temperature: Some(3.0),
+            ..Default::default()
+        };
+        let p = resolve_decode_params(None, None, Some(&o), &HashMap::new());
+        assert_eq!(p.beam_size, 1);
+        assert_eq!(p.best_of, MAX_CANDIDATES);
+        assert_eq!(p.patience, -1.0);
+        assert_eq!(p.temperature, 1.0);
+    }
+
+    #[test]
+    fn advanced_defaults_match_whisper_cpp_and_validate() {
+        let d = AdvancedDecoding::default();
+        assert_eq!(
+            (
+                d.temperature,
+                d.temperature_inc,
+                d.entropy_thold,
+                d.logprob_thold
+            ),
+            (0.0, 0.2, 2.4, -1.0)
+        );
+        assert!(d.validate().is_ok());
+    }
+
+    #[test]
+    fn advanced_out_of_range_is_reported_not_clamped() {
+        let bad = AdvancedDecoding {
+            temperature: 1.5,
+            ..Default::default()
+        };
+        assert_eq!(
+            bad.validate(),
+            Err(AdvancedDecodingError::OutOfRange {
+                field: "temperature",
+                value: 1.5,
+                min: 0.0,
+                max: 1.0
+            })
+        );
+        let bad = AdvancedDecoding {
+            logprob_thold: 0.5,
+            ..Default::default()
+        };
+        assert!(matches!(
+            bad.validate(),
+            Err(AdvancedDecodingError::OutOfRange {
+                field: "logprobThold",
+                ..
+            })
+        ));
+        let nan = AdvancedDecoding {
+            entropy_thold: f32::NAN,
+            ..Default::default()
+        };
+        assert!(nan.validate().is_err());
+    }
+
+    #[test]
+    fn advanced_partial_json_fills_defaults() {
+        let a: AdvancedDecoding = serde_json::from_str(r#"{"temperatureInc":0.0}"#).unwrap();
+        assert_eq!(a.temperature_inc, 0.0);
+        assert_eq!(a.entropy_thold, 2.4);
+    }
+
+    #[test]
+    fn decoding_options_wire_format() {
+        let beam: DecodingOptions =
+            serde_json::from_str(r#"{"strategy":"beam-search","beamSize":4,"patience":1.5}"#)
+                .unwrap();
+        assert_eq!(
+            beam,
+            DecodingOptions::BeamSearch {
+                beam_size: 4,
+                patience: 1.5
+            }
+        );
+        let p = resolve_decode_params(None, Some("en"), Some(&beam.to_override()), &HashMap::new());
+        assert_eq!(p.strategy, DecodeStrategy::BeamSearch);
+        assert_eq!(p.beam_size, 4);
+        assert_eq!(p.patience, 1.5);
+
+        let greedy: DecodingOptions =
+            serde_json::from_str(r#"{"strategy":"greedy","bestOf":3}"#).unwrap();
+        let p = resolve_decode_params(
+            None,
+            Some("ja"),
+            Some(&greedy.to_override()),
+            &HashMap::new(),
+        );
+        assert_eq!(p.strategy, DecodeStrategy::Greedy);
+        assert_eq!(p.best_of, 3);
+    }
+}
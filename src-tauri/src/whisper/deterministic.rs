@@ -0,0 +1,189 @@
+//! Deterministic mode, for chasing accuracy regressions: the same audio
+//! on the same model always decodes to the same text.
+//!
+//! whisper.cpp is deterministic at temperature 0 with greedy decoding
+//! on a given backend, save for a few things the app adds or leaves
+//! on by default. The mode (`Settings.deterministic_mode`, or
+//! `--deterministic` for one session) pins them:
+//! - a fixed thread count (`DETERMINISTIC_THREADS`), since the split of
+//!   the work changes float summation order;
+//! - greedy sampling of a single candidate at temperature 0, whatever
+//!   the decoding settings;
+//! - no temperature fallback, which samples with whisper.cpp's RNG, and
+//!   no quality retry (`whisper::quality`), which decodes above 0;
+//! - a fresh decoding state per run, the RNG included;
+//! - no dictation context carried over from the previous transcript.
+//!
+//! Every transcription carries a `Reproducibility` record, the input's
+//! hash and the parameters it ran with, so two runs compare as JSON.
+//!
+//! The pinning is plain functions over the decode settings; the worker
+//! applies them.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+use crate::whisper::decode::{AdvancedDecoding, DecodeParams, DecodeStrategy};
+
+/// Command-line switch turning the mode on for the session, whatever the
+/// setting.
+pub const DETERMINISTIC_ARG: &str = "--deterministic";
+
+/// Threads in deterministic mode: the same on every machine, so a run
+/// on a laptop compares with one on a workstation.
+pub const DETERMINISTIC_THREADS: i32 = 4;
+
+/// Whether `--deterministic` was passed.
+pub fn requested(args: &[String]) -> bool {
+    args.iter().any(|a| a == DETERMINISTIC_ARG)
+}
+
+/// `decode` as deterministic mode runs it: greedy, one candidate,
+/// temperature 0.
+pub fn pin_decode(decode: DecodeParams) -> DecodeParams {
+    DecodeParams {
+        strategy: DecodeStrategy::Greedy,
+        best_of: 1,
+        temperature: 0.0,
+        ..decode
+    }
+}
+
+/// `advanced` without the temperature fallback.
+pub fn pin_fallback(advanced: AdvancedDecoding) -> AdvancedDecoding {
+    AdvancedDecoding {
+        temperature: 0.0,
+        temperature_inc: 0.0,
+        ..advanced
+    }
+}
+
+/// Samples hashed at a time by `samples_sha256`.
+const HASH_CHUNK: usize = 4096;
+
+/// Hex SHA-256 of `samples` as little-endian 16-bit PCM.
+pub fn samples_sha256(samples: &[i16]) -> String {
+    let mut hasher = Sha256::new();
+    let mut bytes = Vec::with_capacity(HASH_CHUNK * 2);
+    for chunk in samples.chunks(HASH_CHUNK) {
+        bytes.clear();
+        bytes.extend(chunk.iter().flat_map(|sample| sample.to_le_bytes()));
+        hasher.update(&bytes);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Everything a decode ran with, resolved.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedParams {
+    pub model_path: PathBuf,
+    pub using_gpu: bool,
+    pub deterministic: bool,
+    /// The requested language; `None` auto-detects.
+    pub language: Option<String>,
+    pub translate: bool,
+    pub n_threads: i32,
+    pub decode: DecodeParams,
+    pub fallback: AdvancedDecoding,
+    pub no_speech_threshold: f32,
+    pub suppress_non_speech: bool,
+    pub initial_prompt: String,
+    pub prompt_context: String,
+    pub chunk_secs: Option<usize>,
+}
+
+/// What a transcription needs to be reproduced: its input and
+/// parameters. In the result payload as `reproducibility`. Frontend
+/// mirror: `Reproducibility`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reproducibility {
+    pub samples_sha256: String,
+    pub samples: usize,
+    pub params: ResolvedParams,
+}
+
+impl Reproducibility {
+    pub fn new(samples: &[i16], params: ResolvedParams) -> Self {
+        Self {
+            samples_sha256: samples_sha256(samples),
+            samples: samples.len(),
+            params,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn flag_is_found_anywhere() {
+        assert!(requested(&args(&[
+            "s2tui",
+            "--portable",
+            "--deterministic"
+        ])));
+        assert!(!requested(&args(&["s2tui", "--portable"])));
+    }
+
+    #[test]
+    fn pinning_removes_sampling() {
+        let beam = DecodeParams {
+            strategy: DecodeStrategy::BeamSearch,
+            best_of: 5,
+            beam_size: 8,
+            temperature: 0.4,
+            ..DecodeParams::default()
+        };
+        let pinned = pin_decode(beam);
+        assert_eq!(pinned.strategy, DecodeStrategy::Greedy);
+        assert_eq!(pinned.best_of, 1);
+        assert_eq!(pinned.temperature, 0.0);
+        assert_eq!(pinned.suppress_nst, beam.suppress_nst);
+        // Pinned twice is pinned once
+        assert_eq!(pin_decode(pinned), pinned);
+
+        let fallback = pin_fallback(AdvancedDecoding::default());
+        assert_eq!(fallback.temperature_inc, 0.0);
+        assert_eq!(
+            fallback.entropy_thold,
+            AdvancedDecoding::default().entropy_thold
+        );
+    }
+
+    #[test]
+    fn samples_hash_by_content() {
+        let samples = [0i16, 1, -1, i16::MAX];
+        // The bytes of little-endian PCM
+        let bytes = [0u8, 0, 1, 0, 0xff, 0xff, 0xff, 0x7f];
+        assert_eq!(
+            samples_sha256(&samples),
+            format!("{:x}", Sha256::digest(bytes))
+        );
+        assert_ne!(samples_sha256(&samples), samples_sha256(&samples[..3]));
+        // SHA-256 of no bytes
+        assert_eq!(
+            samples_sha256(&[]),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn samples_hash_across_chunks() {
+        let samples: Vec<i16> = (0..HASH_CHUNK as i32 * 2 + 7)
+            .map(|i| (i * 37) as i16)
+            .collect();
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert_eq!(
+            samples_sha256(&samples),
+            format!("{:x}", Sha256::digest(bytes))
+        );
+    }
+}
@@ -1,6 +1,8 @@
 //! GPU backend detection and management for Whisper
 
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
 
 #[cfg(all(
     feature = "gpu-vulkan",
@@ -152,12 +154,30 @@ fn quick_vulkan_check() -> bool {
     }
 
     // Fallback: check for library files
+    vulkan_library_files_present()
+}
+
+/// Loader library on disk? Pure filesystem lookups — no process spawn,
+/// no driver init — so it's cheap enough for the startup path.
+#[cfg(all(
+    feature = "gpu-vulkan",
+    any(target_os = "windows", target_os = "linux")
+))]
+fn vulkan_library_files_present() -> bool {
     #[cfg(target_os = "linux")]
     {
-        if Path::new("/usr/lib/x86_64-linux-gnu/libvulkan.so").exists()
-            || Path::new("/usr/lib64/libvulkan.so").exists()
-            || Path::new("/usr/lib/libvulkan.so").exists()
-        {
+        // The unversioned `.so` only ships with the -dev packages; the
+        // runtime loader is `.so.1`.
+        const CANDIDATES: &[&str] = &[
+            "/usr/lib/x86_64-linux-gnu/libvulkan.so.1",
+            "/usr/lib/x86_64-linux-gnu/libvulkan.so",
+            "/usr/lib/aarch64-linux-gnu/libvulkan.so.1",
+            "/usr/lib64/libvulkan.so.1",
+            "/usr/lib64/libvulkan.so",
+            "/usr/lib/libvulkan.so.1",
+            "/usr/lib/libvulkan.so",
+        ];
+        if CANDIDATES.iter().any(|p| Path::new(p).exists()) {
             tracing::debug!("Vulkan: Linux library found");
             return true;
         }
@@ -181,6 +201,17 @@ fn quick_vulkan_check() -> bool {
     any(target_os = "windows", target_os = "linux")
 ))]
 fn is_vulkan_truly_available() -> bool {
+    vulkan_device_names().is_some_and(|names| !names.is_empty())
+}
+
+/// Names of the Vulkan physical devices, in enumeration order — the
+/// order ggml's Vulkan backend numbers them, so the position is the
+/// `gpu_device` index. `None` when Vulkan can't be initialized.
+#[cfg(all(
+    feature = "gpu-vulkan",
+    any(target_os = "windows", target_os = "linux")
+))]
+fn vulkan_device_names() -> Option<Vec<String>> {
     use ash::{vk, Entry};
 
     // Step 1: Load the Vulkan library
@@ -191,7 +222,7 @@ fn is_vulkan_truly_available() -> bool {
         }
         Err(e) => {
             tracing::debug!("Vulkan: Failed to load entry: {}", e);
-            return false;
+            return None;
         }
     };
 
@@ -213,33 +244,31 @@ fn is_vulkan_truly_available() -> bool {
         }
         Err(e) => {
             tracing::debug!("Vulkan: Failed to create instance: {:?}", e);
-            return false;
+            return None;
         }
     };
 
-    // Step 3: Check if at least one physical device (GPU) exists
-    let has_gpu = match unsafe { instance.enumerate_physical_devices() } {
+    // Step 3: Enumerate the physical devices (GPUs)
+    let names = match unsafe { instance.enumerate_physical_devices() } {
         Ok(devices) => {
-            let count = devices.len();
-            tracing::debug!("Vulkan: Found {} physical device(s)", count);
-
-            if count > 0 {
-                // Log device info for debugging
-                for (i, device) in devices.iter().enumerate() {
+            tracing::debug!("Vulkan: Found {} physical device(s)", devices.len());
+            let names: Vec<String> = devices
+                .iter()
+                .map(|device| {
                     let props = unsafe { instance.get_physical_device_properties(*device) };
-                    let device_name = unsafe {
-                        std::ffi::CStr::from_ptr(props.device_name.as_ptr()).to_string_lossy()
-                    };
-                    tracing::debug!("Vulkan: Device {}: {}", i, device_name);
-                }
-                true
-            } else {
-                false
+                    unsafe { std::ffi::CStr::from_ptr(props.device_name.as_ptr()) }
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            for (i, name) in names.iter().enumerate() {
+                tracing::debug!("Vulkan: Device {}: {}", i, name);
             }
+            Some(names)
         }
         Err(e) => {
             tracing::debug!("Vulkan: Failed to enumerate devices: {:?}", e);
-            false
+            None
         }
     };
 
@@ -247,7 +276,7 @@ fn is_vulkan_truly_available() -> bool {
     unsafe { instance.destroy_instance(None) };
     tracing::debug!("Vulkan: Instance destroyed, check complete");
 
-    has_gpu
+    names
 }
 
 #[cfg(not(all(
@@ -260,20 +289,33 @@ fn is_vulkan_available() -> bool {
 }
 
 // ============================================================================
-// Public Vulkan Detection (for startup check in lib.rs)
+// Public Vulkan Detection (for startup in lib.rs)
 // ============================================================================
 
-/// Public function to check Vulkan availability at application startup.
-/// This is called BEFORE Tauri initialization to decide whether to show
-/// the Vulkan warning window or proceed with normal app launch.
+/// Cheap startup check: is the Vulkan loader library on disk? Runs
+/// synchronously during setup; the conclusive answer comes from
+/// [`probe_vulkan`] on a background task.
 ///
-/// macOS doesn't have this gate — Metal is always available — so the
-/// function (and its re-export in `whisper/mod.rs`) is intentionally
-/// only defined on Windows/Linux. The single call site in `lib.rs` is
-/// gated the same way; keeping the symbol macOS-absent stops clippy
-/// from flagging it as dead code on that platform.
+/// macOS doesn't need either function — Metal is always available — so
+/// both (and their re-exports in `whisper/mod.rs`) are only defined on
+/// Windows/Linux, like their call sites in `lib.rs`.
 #[cfg(any(target_os = "windows", target_os = "linux"))]
-pub fn is_vulkan_available_at_startup() -> bool {
+pub fn is_vulkan_library_present() -> bool {
+    #[cfg(feature = "gpu-vulkan")]
+    {
+        vulkan_library_files_present()
+    }
+    #[cfg(not(feature = "gpu-vulkan"))]
+    {
+        false
+    }
+}
+
+/// Thorough Vulkan probe: vulkaninfo, loader, instance creation and
+/// device enumeration. Takes 0.5–2 s on some systems, so it must stay
+/// off the startup critical path. Cached after the first call.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+pub fn probe_vulkan() -> bool {
     #[cfg(feature = "gpu-vulkan")]
     {
         is_vulkan_available()
@@ -284,6 +326,229 @@ pub fn is_vulkan_available_at_startup() -> bool {
     }
 }
 
+// ============================================================================
+// GPU Device Selection
+// ============================================================================
+
+/// A GPU the active backend can run on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpuDevice {
+    /// Index to pass as `WhisperContextParameters::gpu_device`
+    pub index: u32,
+    pub name: String,
+}
+
+/// GPUs usable by the active backend, cached after the first call (the
+/// Vulkan enumeration creates an instance, which is slow on some drivers).
+pub fn gpu_devices() -> Vec<GpuDevice> {
+    static DEVICES: std::sync::OnceLock<Vec<GpuDevice>> = std::sync::OnceLock::new();
+    DEVICES.get_or_init(enumerate_gpu_devices).clone()
+}
+
+fn enumerate_gpu_devices() -> Vec<GpuDevice> {
+    let names: Vec<String> = match detect_active_backend() {
+        GpuBackend::Cpu => Vec::new(),
+        // Metal always runs on the system default GPU.
+        GpuBackend::Metal => vec!["Metal (system default)".to_string()],
+        #[cfg(all(
+            feature = "gpu-vulkan",
+            any(target_os = "windows", target_os = "linux")
+        ))]
+        GpuBackend::Vulkan => vulkan_device_names().unwrap_or_default(),
+        #[cfg(not(all(
+            feature = "gpu-vulkan",
+            any(target_os = "windows", target_os = "linux")
+        )))]
+        GpuBackend::Vulkan => Vec::new(),
+    };
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| GpuDevice {
+            index: index as u32,
+            name,
+        })
+        .collect()
+}
+
+/// Pick the device index to load on. A configured index that no longer
+/// exists (GPU removed, drivers reordered) falls back to device 0; the
+/// second value is the warning to report in that case.
+pub fn resolve_gpu_device(requested: Option<u32>, devices: &[GpuDevice]) -> (u32, Option<String>) {
+    match requested {
+        None | Some(0) => (0, None),
+        Some(index) if devices.iter().any(|d| d.index == index) => (index, None),
+        Some(index) => (
+            0,
+            Some(format!(
+                "GPU device {} not found ({} available), using device 0",
+                index,
+                devices.len()
+            )),
+        ),
+    }
+}
+
+// ============================================================================
+// VRAM Usage
+// ============================================================================
+
+/// Reads how much device-local memory this process holds on one GPU,
+/// through `VK_EXT_memory_budget`. Keeps its Vulkan instance open so a
+/// before/after pair around a model load costs one instance creation.
+#[cfg(all(
+    feature = "gpu-vulkan",
+    any(target_os = "windows", target_os = "linux")
+))]
+pub struct VramProbe {
+    _entry: ash::Entry,
+    instance: ash::Instance,
+    device: ash::vk::PhysicalDevice,
+}
+
+#[cfg(all(
+    feature = "gpu-vulkan",
+    any(target_os = "windows", target_os = "linux")
+))]
+impl VramProbe {
+    /// `None` when the backend isn't Vulkan, or the device doesn't
+    /// support the memory budget extension (Vulkan 1.1 needed).
+    pub fn open(device_index: u32) -> Option<Self> {
+        use ash::{vk, Entry};
+
+        if detect_active_backend() != GpuBackend::Vulkan {
+            return None;
+        }
+        let entry = unsafe { Entry::load() }.ok()?;
+        let app_info = vk::ApplicationInfo {
+            api_version: vk::make_api_version(0, 1, 1, 0),
+            ..Default::default()
+        };
+        let create_info = vk::InstanceCreateInfo {
+            p_application_info: &app_info,
+            ..Default::default()
+        };
+        let instance = unsafe { entry.create_instance(&create_info, None) }.ok()?;
+        let device = unsafe { instance.enumerate_physical_devices() }
+            .ok()
+            .and_then(|devices| devices.get(device_index as usize).copied())
+            .filter(|device| {
+                let props = unsafe { instance.get_physical_device_properties(*device) };
+                let extensions = unsafe { instance.enumerate_device_extension_properties(*device) }
+                    .unwrap_or_default();
+                vk::api_version_minor(props.api_version) >= 1
+                    && extensions.iter().any(|ext| {
+                        ext.extension_name_as_c_str() == Ok(ash::ext::memory_budget::NAME)
+                    })
+            });
+        let Some(device) = device else {
+            tracing::debug!("Vulkan: no memory budget on device {}", device_index);
+            unsafe { instance.destroy_instance(None) };
+            return None;
+        };
+        Some(Self {
+            _entry: entry,
+            instance,
+            device,
+        })
+    }
+
+    /// Bytes of device-local memory in use by this process.
+    pub fn usage(&self) -> u64 {
+        use ash::vk;
+
+        let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut props = vk::PhysicalDeviceMemoryProperties2::default().push_next(&mut budget);
+        unsafe {
+            self.instance
+                .get_physical_device_memory_properties2(self.device, &mut props)
+        };
+        let memory = props.memory_properties;
+        memory.memory_heaps[..memory.memory_heap_count as usize]
+            .iter()
+            .zip(budget.heap_usage)
+            .filter(|(heap, _)| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|(_, usage)| usage)
+            .sum()
+    }
+}
+
+#[cfg(all(
+    feature = "gpu-vulkan",
+    any(target_os = "windows", target_os = "linux")
+))]
+impl Drop for VramProbe {
+    fn drop(&mut self) {
+        unsafe { self.instance.destroy_instance(None) };
+    }
+}
+
+/// Total device-local memory of Vulkan device `device_index`, in
+/// bytes. `None` when the backend isn't Vulkan or the device is gone.
+#[cfg(all(
+    feature = "gpu-vulkan",
+    any(target_os = "windows", target_os = "linux")
+))]
+pub fn vulkan_device_memory(device_index: u32) -> Option<u64> {
+    use ash::{vk, Entry};
+
+    if detect_active_backend() != GpuBackend::Vulkan {
+        return None;
+    }
+    let entry = unsafe { Entry::load() }.ok()?;
+    let app_info = vk::ApplicationInfo {
+        api_version: vk::make_api_version(0, 1, 0, 0),
+        ..Default::default()
+    };
+    let create_info = vk::InstanceCreateInfo {
+        p_application_info: &app_info,
+        ..Default::default()
+    };
+    let instance = unsafe { entry.create_instance(&create_info, None) }.ok()?;
+    let total = unsafe { instance.enumerate_physical_devices() }
+        .ok()
+        .and_then(|devices| devices.get(device_index as usize).copied())
+        .map(|device| {
+            let memory = unsafe { instance.get_physical_device_memory_properties(device) };
+            memory.memory_heaps[..memory.memory_heap_count as usize]
+                .iter()
+                .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+                .map(|heap| heap.size)
+                .sum()
+        });
+    unsafe { instance.destroy_instance(None) };
+    total
+}
+
+#[cfg(not(all(
+    feature = "gpu-vulkan",
+    any(target_os = "windows", target_os = "linux")
+)))]
+pub fn vulkan_device_memory(_device_index: u32) -> Option<u64> {
+    None
+}
+
+/// Without Vulkan there is nothing to query; VRAM is estimated instead.
+#[cfg(not(all(
+    feature = "gpu-vulkan",
+    any(target_os = "windows", target_os = "linux")
+)))]
+pub struct VramProbe;
+
+#[cfg(not(all(
+    feature = "gpu-vulkan",
+    any(target_os = "windows", target_os = "linux")
+)))]
+impl VramProbe {
+    pub fn open(_device_index: u32) -> Option<Self> {
+        None
+    }
+
+    pub fn usage(&self) -> u64 {
+        0
+    }
+}
+
 /// Information about GPU support in this build
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpuInfo {
@@ -293,6 +558,8 @@ pub struct GpuInfo {
     pub compiled_backends: Vec<GpuBackend>,
     /// Whether hardware acceleration is actually being used
     pub hardware_accelerated: bool,
+    /// Devices the active backend can run on, by index
+    pub devices: Vec<GpuDevice>,
 }
 
 impl GpuInfo {
@@ -306,6 +573,7 @@ impl GpuInfo {
             active_backend,
             compiled_backends,
             hardware_accelerated,
+            devices: gpu_devices(),
         }
     }
 }
@@ -330,6 +598,14 @@ pub struct SystemHealthCheck {
     pub install_guide: Option<VulkanInstallGuide>,
     /// L'application peut-elle fonctionner sans Vulkan ? (toujours true)
     pub can_run_without_vulkan: bool,
+    /// Image système en lecture seule (ostree, NixOS, SteamOS) : les
+    /// paquets ne s'installent pas avec le gestionnaire habituel
+    #[serde(default)]
+    pub system_is_immutable: bool,
+    /// Probes that didn't finish within `PROBE_TIMEOUT`; their fields
+    /// hold conservative defaults.
+    #[serde(default)]
+    pub probe_timeouts: Vec<String>,
 }
 
 /// Informations sur le système d'exploitation
@@ -342,6 +618,38 @@ pub struct OsInfo {
     pub version: Option<String>,
     /// Distribution Linux (ubuntu, fedora, arch, etc.)
     pub distribution: Option<String>,
+    /// Type d'installation Linux (immuable, conteneur…)
+    #[serde(default)]
+    pub environment: LinuxEnvironment,
+}
+
+/// How the Linux system is installed, which decides how Vulkan gets on it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LinuxEnvironment {
+    /// Regular distribution with a mutable package manager (or not Linux)
+    #[default]
+    Standard,
+    /// rpm-ostree image (Silverblue, Kinoite, Bazzite…)
+    Ostree,
+    /// NixOS: packages come from the system configuration
+    NixOs,
+    /// SteamOS (Steam Deck): read-only image, pacman changes are wiped
+    SteamOs,
+    /// Running inside a Flatpak sandbox: Vulkan comes from the runtime
+    Flatpak,
+    /// Docker/Podman/toolbox/distrobox container
+    Container,
+}
+
+impl LinuxEnvironment {
+    /// The system image is read-only to the usual package manager.
+    pub fn is_immutable(self) -> bool {
+        matches!(
+            self,
+            LinuxEnvironment::Ostree | LinuxEnvironment::NixOs | LinuxEnvironment::SteamOs
+        )
+    }
 }
 
 /// Guide d'installation Vulkan
@@ -376,9 +684,9 @@ pub struct DownloadLink {
 // OS Detection Functions
 // ============================================================================
 
-/// Détecte les informations sur le système d'exploitation
-fn detect_os_info() -> OsInfo {
-    let platform = if cfg!(target_os = "windows") {
+/// Plateforme courante: "windows", "linux", "macos"
+fn current_platform() -> String {
+    if cfg!(target_os = "windows") {
         "windows"
     } else if cfg!(target_os = "linux") {
         "linux"
@@ -387,22 +695,85 @@ fn detect_os_info() -> OsInfo {
     } else {
         "unknown"
     }
-    .to_string();
+    .to_string()
+}
 
+/// Détecte les informations sur le système d'exploitation
+fn detect_os_info() -> OsInfo {
+    let platform = current_platform();
     let version = detect_os_version();
     let distribution = if cfg!(target_os = "linux") {
         detect_linux_distribution()
     } else {
         None
     };
+    let environment = if cfg!(target_os = "linux") {
+        classify_linux_environment(&EnvironmentMarkers::detect(), distribution.as_deref())
+    } else {
+        LinuxEnvironment::Standard
+    };
 
     OsInfo {
         platform,
         version,
         distribution,
+        environment,
+    }
+}
+
+/// Filesystem and environment hints read by `classify_linux_environment`.
+#[derive(Debug, Default)]
+struct EnvironmentMarkers {
+    /// Value of the `container` environment variable (set by systemd-nspawn,
+    /// podman, toolbox and Flatpak)
+    container_var: Option<String>,
+    /// `/.flatpak-info`
+    flatpak_info: bool,
+    /// `/.dockerenv` or `/run/.containerenv`
+    container_file: bool,
+    /// `/run/ostree-booted`
+    ostree_booted: bool,
+    /// `/etc/NIXOS`
+    nixos: bool,
+}
+
+impl EnvironmentMarkers {
+    fn detect() -> Self {
+        let exists = |p: &str| std::path::Path::new(p).exists();
+        EnvironmentMarkers {
+            container_var: std::env::var("container").ok(),
+            flatpak_info: exists("/.flatpak-info"),
+            container_file: exists("/.dockerenv") || exists("/run/.containerenv"),
+            ostree_booted: exists("/run/ostree-booted"),
+            nixos: exists("/etc/NIXOS"),
+        }
     }
 }
 
+/// Sandboxes first: inside a container, the host's kind of system doesn't
+/// matter (a toolbox on Silverblue has a mutable dnf of its own).
+fn classify_linux_environment(
+    markers: &EnvironmentMarkers,
+    distribution: Option<&str>,
+) -> LinuxEnvironment {
+    if markers.flatpak_info || markers.container_var.as_deref() == Some("flatpak") {
+        return LinuxEnvironment::Flatpak;
+    }
+    if markers.container_file || markers.container_var.is_some() {
+        return LinuxEnvironment::Container;
+    }
+    if markers.ostree_booted {
+        return LinuxEnvironment::Ostree;
+    }
+    if markers.nixos || distribution == Some("nixos") {
+        return LinuxEnvironment::NixOs;
+    }
+    if distribution == Some("steamos") {
+        return LinuxEnvironment::SteamOs;
+    }
+    LinuxEnvironment::Standard
+}
+
 /// Détecte la version de l'OS
 fn detect_os_version() -> Option<String> {
     #[cfg(target_os = "windows")]
@@ -517,7 +888,7 @@ fn get_vulkan_version() -> Option<String> {
 fn generate_install_guide(os: &OsInfo) -> VulkanInstallGuide {
     match os.platform.as_str() {
         "windows" => generate_windows_guide(),
-        "linux" => generate_linux_guide(os.distribution.as_deref()),
+        "linux" => generate_linux_guide(os.distribution.as_deref(), os.environment),
         _ => generate_generic_guide(),
     }
 }
@@ -552,8 +923,23 @@ fn generate_windows_guide() -> VulkanInstallGuide {
     }
 }
 
-fn generate_linux_guide(distribution: Option<&str>) -> VulkanInstallGuide {
-    let (title, commands) = match distribution {
+fn generate_linux_guide(
+    distribution: Option<&str>,
+    environment: LinuxEnvironment,
+) -> VulkanInstallGuide {
+    match environment {
+        LinuxEnvironment::Standard => generate_package_manager_guide(distribution),
+        LinuxEnvironment::Ostree => generate_ostree_guide(),
+        LinuxEnvironment::NixOs => generate_nixos_guide(),
+        LinuxEnvironment::SteamOs => generate_steamos_guide(),
+        LinuxEnvironment::Flatpak => generate_flatpak_guide(),
+        LinuxEnvironment::Container => generate_container_guide(distribution),
+    }
+}
+
+/// Install commands for the distribution's package manager.
+fn distribution_commands(distribution: Option<&str>) -> (&'static str, Vec<String>) {
+    match distribution {
         Some("ubuntu") | Some("debian") | Some("linuxmint") | Some("pop") => (
             "Install Vulkan on Ubuntu/Debian",
             vec![
@@ -582,8 +968,11 @@ fn generate_linux_guide(distribution: Option<&str>) -> VulkanInstallGuide {
                 "sudo dnf install -y vulkan-loader vulkan-tools mesa-vulkan-drivers".to_string(),
             ],
         ),
-    };
+    }
+}
 
+fn generate_package_manager_guide(distribution: Option<&str>) -> VulkanInstallGuide {
+    let (title, commands) = distribution_commands(distribution);
     VulkanInstallGuide {
         title: title.to_string(),
         description: "Install Vulkan packages using your package manager.".to_string(),
@@ -597,6 +986,90 @@ fn generate_linux_guide(distribution: Option<&str>) -> VulkanInstallGuide {
     }
 }
 
+fn generate_ostree_guide() -> VulkanInstallGuide {
+    VulkanInstallGuide {
+        title: "Install Vulkan on an rpm-ostree system".to_string(),
+        description: "This system image is read-only: layer the Vulkan packages with rpm-ostree, then reboot into the new deployment.".to_string(),
+        steps: vec![
+            "Open a terminal".to_string(),
+            "Layer the packages with the commands below".to_string(),
+            "Reboot, then relaunch S2Tui".to_string(),
+        ],
+        download_urls: vec![],
+        terminal_commands: Some(vec![
+            "rpm-ostree install vulkan-loader vulkan-tools mesa-vulkan-drivers".to_string(),
+            "systemctl reboot".to_string(),
+        ]),
+    }
+}
+
+fn generate_nixos_guide() -> VulkanInstallGuide {
+    VulkanInstallGuide {
+        title: "Enable Vulkan on NixOS".to_string(),
+        description: "Packages installed imperatively don't provide drivers on NixOS. Enable graphics support in your system configuration and rebuild.".to_string(),
+        steps: vec![
+            "Add the snippet below to /etc/nixos/configuration.nix".to_string(),
+            "Rebuild the system".to_string(),
+            "Relaunch S2Tui".to_string(),
+        ],
+        download_urls: vec![],
+        terminal_commands: Some(vec![
+            "# /etc/nixos/configuration.nix".to_string(),
+            "hardware.graphics.enable = true;  # hardware.opengl.enable before NixOS 24.11".to_string(),
+            "environment.systemPackages = [ pkgs.vulkan-tools ];".to_string(),
+            "sudo nixos-rebuild switch".to_string(),
+        ]),
+    }
+}
+
+fn generate_steamos_guide() -> VulkanInstallGuide {
+    VulkanInstallGuide {
+        title: "Vulkan on SteamOS".to_string(),
+        description: "SteamOS ships its Vulkan drivers in the read-only system image; packages installed with pacman are wiped by the next update. A missing Vulkan driver usually means an outdated or damaged image.".to_string(),
+        steps: vec![
+            "Install pending SteamOS updates (Settings > System)".to_string(),
+            "If Vulkan is still unavailable, repair the system image from the recovery menu".to_string(),
+            "Relaunch S2Tui".to_string(),
+        ],
+        download_urls: vec![],
+        terminal_commands: None,
+    }
+}
+
+fn generate_flatpak_guide() -> VulkanInstallGuide {
+    VulkanInstallGuide {
+        title: "Vulkan in the Flatpak sandbox".to_string(),
+        description: "Inside Flatpak, Vulkan comes from the GL runtime extension, not from host packages. Updating installs the extension matching your driver (including the NVIDIA one).".to_string(),
+        steps: vec![
+            "Open a terminal on the host".to_string(),
+            "Update the Flatpak runtimes with the commands below".to_string(),
+            "Relaunch S2Tui".to_string(),
+        ],
+        download_urls: vec![],
+        terminal_commands: Some(vec![
+            "flatpak update".to_string(),
+            "flatpak list --runtime | grep org.freedesktop.Platform.GL".to_string(),
+        ]),
+    }
+}
+
+fn generate_container_guide(distribution: Option<&str>) -> VulkanInstallGuide {
+    let (_, install) = distribution_commands(distribution);
+    let mut commands = vec!["# Inside the container:".to_string()];
+    commands.extend(install);
+    VulkanInstallGuide {
+        title: "Install Vulkan in a container".to_string(),
+        description: "S2Tui is running in a container. The GPU must be passed through from the host (--device /dev/dri, or the NVIDIA Container Toolkit) and the Vulkan loader installed inside the container.".to_string(),
+        steps: vec![
+            "Make sure the container was started with GPU access".to_string(),
+            "Install the Vulkan packages inside the container".to_string(),
+            "Relaunch S2Tui".to_string(),
+        ],
+        download_urls: vec![],
+        terminal_commands: Some(commands),
+    }
+}
+
 fn generate_generic_guide() -> VulkanInstallGuide {
     VulkanInstallGuide {
         title: "GPU Acceleration".to_string(),
@@ -613,52 +1086,152 @@ fn generate_generic_guide() -> VulkanInstallGuide {
 // Main Health Check Function
 // ============================================================================
 
-/// Effectue une vérification complète de la santé système pour GPU/Vulkan
-pub fn check_system_health() -> SystemHealthCheck {
-    let os_info = detect_os_info();
-    let gpu_backend = detect_active_backend();
+/// Time limit for each health probe. A hung ICD or a slow disk must not
+/// hold the caller for longer than this per probe.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
 
-    // Sur Windows/Linux avec gpu-vulkan, vérifier Vulkan
-    #[cfg(all(
-        feature = "gpu-vulkan",
-        any(target_os = "windows", target_os = "linux")
-    ))]
-    let vulkan_available = is_vulkan_available();
+/// Last complete health check, so repeated UI polls are instant.
+static HEALTH_CACHE: Mutex<HealthCache> = parking_lot::const_mutex(HealthCache { last: None });
 
-    #[cfg(not(all(
-        feature = "gpu-vulkan",
-        any(target_os = "windows", target_os = "linux")
-    )))]
-    let vulkan_available = false;
+struct HealthCache {
+    last: Option<(Instant, SystemHealthCheck)>,
+}
+
+impl HealthCache {
+    fn get(&self, max_age: Duration, now: Instant) -> Option<SystemHealthCheck> {
+        self.last
+            .as_ref()
+            .filter(|(at, _)| now.duration_since(*at) <= max_age)
+            .map(|(_, health)| health.clone())
+    }
+
+    /// Only complete results are cached; a partial one would otherwise
+    /// keep being served after the slow probe recovered.
+    fn store(&mut self, health: &SystemHealthCheck, now: Instant) {
+        if health.probe_timeouts.is_empty() {
+            self.last = Some((now, health.clone()));
+        }
+    }
+}
+
+/// Run `probe` on the blocking pool, giving up after `timeout`. A probe
+/// that doesn't finish is recorded in `unfinished` and yields `None`;
+/// it can't be interrupted, so it keeps running in the background and
+/// its result is discarded.
+async fn run_probe<T, F>(
+    name: &str,
+    timeout: Duration,
+    unfinished: &mut Vec<String>,
+    probe: F,
+) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    match tokio::time::timeout(timeout, tokio::task::spawn_blocking(probe)).await {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(e)) => {
+            tracing::warn!("Health probe {name} failed: {e}");
+            unfinished.push(name.to_string());
+            None
+        }
+        Err(_) => {
+            tracing::warn!("Health probe {name} timed out after {timeout:?}");
+            unfinished.push(name.to_string());
+            None
+        }
+    }
+}
+
+/// Effectue une vérification complète de la santé système pour GPU/Vulkan.
+///
+/// Each probe runs on the blocking pool with its own `PROBE_TIMEOUT`;
+/// whatever didn't finish is listed in `probe_timeouts` and the rest is
+/// still returned. With `max_age`, a complete result at most that old
+/// is served from cache without probing.
+pub async fn check_system_health(max_age: Option<Duration>) -> SystemHealthCheck {
+    if let Some(max_age) = max_age {
+        if let Some(cached) = HEALTH_CACHE.lock().get(max_age, Instant::now()) {
+            return cached;
+        }
+    }
+
+    let mut probe_timeouts = Vec::new();
+    let os_info = run_probe(
+        "os-info",
+        PROBE_TIMEOUT,
+        &mut probe_timeouts,
+        detect_os_info,
+    )
+    .await
+    .unwrap_or_else(|| OsInfo {
+        platform: current_platform(),
+        version: None,
+        distribution: None,
+        environment: LinuxEnvironment::Standard,
+    });
+
+    // Sur Windows/Linux avec gpu-vulkan, vérifier Vulkan
+    let vulkan = run_probe("vulkan", PROBE_TIMEOUT, &mut probe_timeouts, || {
+        #[cfg(all(
+            feature = "gpu-vulkan",
+            any(target_os = "windows", target_os = "linux")
+        ))]
+        let available = is_vulkan_available();
+
+        #[cfg(not(all(
+            feature = "gpu-vulkan",
+            any(target_os = "windows", target_os = "linux")
+        )))]
+        let available = false;
+
+        (available, detect_active_backend())
+    })
+    .await;
+    let (vulkan_available, gpu_backend) = vulkan.unwrap_or((false, GpuBackend::Cpu));
 
     let vulkan_version = if vulkan_available {
-        get_vulkan_version()
+        run_probe(
+            "vulkan-version",
+            PROBE_TIMEOUT,
+            &mut probe_timeouts,
+            get_vulkan_version,
+        )
+        .await
+        .flatten()
     } else {
         None
     };
 
-    // Générer le guide d'installation si Vulkan non disponible
-    let install_guide = if !vulkan_available && os_info.platform != "macos" {
+    // Générer le guide d'installation si Vulkan non disponible — seulement
+    // si la sonde a répondu, un timeout ne prouve rien.
+    let install_guide = if vulkan.is_some() && !vulkan_available && os_info.platform != "macos" {
         Some(generate_install_guide(&os_info))
     } else {
         None
     };
 
     tracing::info!(
-        "System health check: platform={}, vulkan={}, backend={:?}",
+        "System health check: platform={}, vulkan={}, backend={:?}, timeouts={:?}",
         os_info.platform,
         vulkan_available,
-        gpu_backend
+        gpu_backend,
+        probe_timeouts
     );
 
-    SystemHealthCheck {
+    let system_is_immutable = os_info.environment.is_immutable();
+    let health = SystemHealthCheck {
         vulkan_available,
         vulkan_version,
         gpu_backend,
         os_info,
         install_guide,
         can_run_without_vulkan: true, // Toujours true car on a le fallback CPU
-    }
+        system_is_immutable,
+        probe_timeouts,
+    };
+    HEALTH_CACHE.lock().store(&health, Instant::now());
+    health
 }
 
 #[cfg(test)]
@@ -690,6 +1263,29 @@ mod tests {
         assert!(info.compiled_backends.contains(&info.active_backend));
     }
 
+    #[test]
+    fn test_resolve_gpu_device() {
+        let devices = vec![
+            GpuDevice {
+                index: 0,
+                name: "Intel(R) UHD Graphics".into(),
+            },
+            GpuDevice {
+                index: 1,
+                name: "NVIDIA GeForce RTX 3060".into(),
+            },
+        ];
+        assert_eq!(resolve_gpu_device(None, &devices), (0, None));
+        assert_eq!(resolve_gpu_device(Some(1), &devices), (1, None));
+
+        let (index, warning) = resolve_gpu_device(Some(2), &devices);
+        assert_eq!(index, 0);
+        assert!(warning.unwrap().contains("device 2 not found"));
+
+        // Device 0 needs no enumeration (CPU builds report no devices)
+        assert_eq!(resolve_gpu_device(Some(0), &[]), (0, None));
+    }
+
     #[test]
     fn test_os_detection() {
         let os = detect_os_info();
@@ -697,13 +1293,63 @@ mod tests {
         assert!(!os.platform.is_empty());
     }
 
-    #[test]
-    fn test_system_health_check() {
-        let health = check_system_health();
+    #[tokio::test]
+    async fn test_system_health_check() {
+        let health = check_system_health(None).await;
         println!("System Health: {:?}", health);
         assert!(health.can_run_without_vulkan);
     }
 
+    #[tokio::test]
+    async fn test_slow_probe_times_out() {
+        let mut unfinished = Vec::new();
+        let fast = run_probe("fast", Duration::from_millis(500), &mut unfinished, || 42).await;
+        let slow = run_probe("slow", Duration::from_millis(20), &mut unfinished, || {
+            std::thread::sleep(Duration::from_millis(300));
+            42
+        })
+        .await;
+        assert_eq!(fast, Some(42));
+        assert_eq!(slow, None);
+        assert_eq!(unfinished, vec!["slow".to_string()]);
+    }
+
+    fn health_with_timeouts(probe_timeouts: Vec<String>) -> SystemHealthCheck {
+        SystemHealthCheck {
+            vulkan_available: false,
+            vulkan_version: None,
+            gpu_backend: GpuBackend::Cpu,
+            os_info: OsInfo {
+                platform: current_platform(),
+                version: None,
+                distribution: None,
+                environment: LinuxEnvironment::Standard,
+            },
+            install_guide: None,
+            can_run_without_vulkan: true,
+            system_is_immutable: false,
+            probe_timeouts,
+        }
+    }
+
+    #[test]
+    fn test_health_cache() {
+        let t0 = Instant::now();
+        let max_age = Duration::from_secs(30);
+        let mut cache = HealthCache { last: None };
+
+        // Partial results are never cached
+        cache.store(&health_with_timeouts(vec!["vulkan".into()]), t0);
+        assert!(cache.get(max_age, t0).is_none());
+
+        cache.store(&health_with_timeouts(vec![]), t0);
+        assert!(cache.get(max_age, t0 + Duration::from_secs(10)).is_some());
+        assert!(cache.get(max_age, t0 + Duration::from_secs(31)).is_none());
+        assert!(cache
+            .get(Duration::ZERO, t0 + Duration::from_millis(1))
+            .is_none());
+    }
+
     #[test]
     fn test_install_guide_windows() {
         let guide = generate_windows_guide();
@@ -713,8 +1359,117 @@ mod tests {
 
     #[test]
     fn test_install_guide_linux_ubuntu() {
-        let guide = generate_linux_guide(Some("ubuntu"));
+        let guide = generate_linux_guide(Some("ubuntu"), LinuxEnvironment::Standard);
         assert!(guide.terminal_commands.is_some());
         assert!(guide.download_urls.is_empty());
     }
+
+    fn commands(guide: &VulkanInstallGuide) -> String {
+        guide
+            .terminal_commands
+            .clone()
+            .unwrap_or_default()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_classify_linux_environment() {
+        let markers = |f: fn(&mut EnvironmentMarkers)| {
+            let mut m = EnvironmentMarkers::default();
+            f(&mut m);
+            m
+        };
+        let classify = classify_linux_environment;
+        assert_eq!(
+            classify(&EnvironmentMarkers::default(), Some("ubuntu")),
+            LinuxEnvironment::Standard
+        );
+        assert_eq!(
+            classify(&markers(|m| m.ostree_booted = true), Some("fedora")),
+            LinuxEnvironment::Ostree
+        );
+        assert_eq!(
+            classify(&markers(|m| m.nixos = true), None),
+            LinuxEnvironment::NixOs
+        );
+        assert_eq!(
+            classify(&EnvironmentMarkers::default(), Some("nixos")),
+            LinuxEnvironment::NixOs
+        );
+        assert_eq!(
+            classify(&EnvironmentMarkers::default(), Some("steamos")),
+            LinuxEnvironment::SteamOs
+        );
+        assert_eq!(
+            classify(&markers(|m| m.flatpak_info = true), Some("fedora")),
+            LinuxEnvironment::Flatpak
+        );
+        assert_eq!(
+            classify(&markers(|m| m.container_var = Some("flatpak".into())), None),
+            LinuxEnvironment::Flatpak
+        );
+        assert_eq!(
+            classify(&markers(|m| m.container_file = true), Some("debian")),
+            LinuxEnvironment::Container
+        );
+        // A toolbox on Silverblue: the container wins over the host's ostree
+        let toolbox = markers(|m| {
+            m.container_var = Some("oci".into());
+            m.ostree_booted = true;
+        });
+        assert_eq!(
+            classify(&toolbox, Some("fedora")),
+            LinuxEnvironment::Container
+        );
+    }
+
+    #[test]
+    fn test_immutable_flag() {
+        assert!(LinuxEnvironment::Ostree.is_immutable());
+        assert!(LinuxEnvironment::NixOs.is_immutable());
+        assert!(LinuxEnvironment::SteamOs.is_immutable());
+        assert!(!LinuxEnvironment::Standard.is_immutable());
+        assert!(!LinuxEnvironment::Flatpak.is_immutable());
+        assert!(!LinuxEnvironment::Container.is_immutable());
+    }
+
+    #[test]
+    fn test_install_guide_ostree() {
+        let guide = generate_linux_guide(Some("fedora"), LinuxEnvironment::Ostree);
+        let commands = commands(&guide);
+        assert!(commands.contains("rpm-ostree install"));
+        assert!(!commands.contains("dnf"));
+    }
+
+    #[test]
+    fn test_install_guide_nixos() {
+        let guide = generate_linux_guide(Some("nixos"), LinuxEnvironment::NixOs);
+        let commands = commands(&guide);
+        assert!(commands.contains("hardware.graphics.enable = true;"));
+        assert!(commands.contains("nixos-rebuild switch"));
+    }
+
+    #[test]
+    fn test_install_guide_steamos() {
+        let guide = generate_linux_guide(Some("steamos"), LinuxEnvironment::SteamOs);
+        assert!(
+            guide.terminal_commands.is_none(),
+            "no pacman on a read-only image"
+        );
+    }
+
+    #[test]
+    fn test_install_guide_flatpak() {
+        let guide = generate_linux_guide(Some("fedora"), LinuxEnvironment::Flatpak);
+        let commands = commands(&guide);
+        assert!(commands.contains("flatpak update"));
+        assert!(!commands.contains("dnf"));
+    }
+
+    #[test]
+    fn test_install_guide_container() {
+        let guide = generate_linux_guide(Some("ubuntu"), LinuxEnvironment::Container);
+        assert!(guide.description.contains("/dev/dri"));
+        assert!(commands(&guide).contains("apt install"));
+    }
 }
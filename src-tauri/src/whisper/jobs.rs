@@ -0,0 +1,651 @@
+//! Job-based transcription API.
+//!
+//! Callers used to wrap `WhisperWorker::transcribe` in `spawn_blocking`
+//! themselves and bolt progress and cancellation on at every call site.
+//! Instead, a `JobRunner` owns a long-lived thread that takes
+//! `TranscribeJob`s off a channel, one at a time and in submission
+//! order, and runs them on its engine. Each submission returns a
+//! `JobHandle`:
+//!
+//! - `await_result()` resolves with the transcription (a oneshot
+//!   channel; awaiting it never blocks a runtime thread);
+//! - `progress()` hands out the job's progress stream (percent, 0–100);
+//! - `started()` resolves once the job leaves the queue for the engine,
+//!   so a time limit can leave out the wait behind other jobs;
+//! - `cancel_token()` cancels it: the job is dropped if it hasn't
+//!   started, or the engine is asked to abort it if it has; either way
+//!   the result is `WhisperError::Cancelled`.
+//!
+//! The queue is bounded: `submit` counts the jobs queued or running and
+//! refuses a new one with `QueueError::Busy` once `max_depth` are
+//! pending, rather than piling up utterances that would come out long
+//! after they were spoken.
+//!
+//! The thread owns the engine outright: model loads and settings
+//! changes reach it on the same channel (`call` / `post`), between
+//! transcriptions and in order, so nothing else ever locks it and
+//! every GPU call comes from that one thread. `shutdown` stops it once
+//! the work queued ahead is done. A command that panics fails alone:
+//! its caller gets an error, the engine drops what the command may
+//! have left half done (`JobEngine::recover`), and the thread goes on
+//! with the next one.
+//!
+//! The engine is anything implementing `JobEngine`, so the runner is
+//! tested with a stub instead of a model.
+
+use serde::Serialize;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::whisper::progress::ProgressCallback;
+use crate::whisper::worker::{TranscriptionResult, WhisperError};
+
+/// Shared cancellation flag of a job.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// Default for `JobRunner::set_max_depth` (and the
+/// `transcription_queue_depth` setting).
+pub const DEFAULT_MAX_QUEUE_DEPTH: usize = 3;
+
+/// Submission refused.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum QueueError {
+    /// `pending` jobs are already queued or running.
+    #[error("Transcription queue is full ({pending} pending)")]
+    Busy { pending: usize, max_depth: usize },
+}
+
+/// Per-job overrides of the engine's configuration.
+#[derive(Debug, Clone, Default)]
+pub struct JobParams {
+    /// `Some` replaces the configured language for this job only.
+    pub language: Option<String>,
+    /// `Some` decodes in windows of this many seconds whatever the
+    /// recording's length (the retry of a decode that stopped early).
+    pub chunk_secs: Option<usize>,
+}
+
+/// What the runner thread needs from an engine.
+pub trait JobEngine: Send + 'static {
+    /// Transcribe `samples`, reporting progress to `on_progress` and
+    /// giving up with `WhisperError::Cancelled` once `cancel` is set.
+    fn run(
+        &mut self,
+        samples: &[i16],
+        params: &JobParams,
+        on_progress: Option<ProgressCallback>,
+        cancel: &CancelToken,
+    ) -> Result<TranscriptionResult, WhisperError>;
+
+    /// One of the engine's commands panicked: rebuild whatever it may
+    /// have left half done, before the next command runs.
+    fn recover(&mut self);
+}
+
+/// One queued transcription.
+pub struct TranscribeJob {
+    pub id: u64,
+    pub samples: Arc<[i16]>,
+    pub params: JobParams,
+    pub on_progress: Option<ProgressCallback>,
+    pub cancel_token: CancelToken,
+    started_tx: oneshot::Sender<()>,
+    result_tx: oneshot::Sender<Result<TranscriptionResult, WhisperError>>,
+}
+
+/// An engine call other than a transcription.
+type EngineCall<E> = Box<dyn FnOnce(&mut E) + Send>;
+
+/// What the job thread takes off its channel.
+enum Command<E> {
+    Transcribe(TranscribeJob),
+    /// Model loads, settings: run between transcriptions.
+    Call(EngineCall<E>),
+    Shutdown,
+}
+
+/// The caller's side of a submitted job.
+pub struct JobHandle {
+    id: u64,
+    cancel_token: CancelToken,
+    progress_rx: Option<mpsc::UnboundedReceiver<u8>>,
+    started_rx: Option<oneshot::Receiver<()>>,
+    result_rx: oneshot::Receiver<Result<TranscriptionResult, WhisperError>>,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// A token that cancels this job, for whoever outlives the handle
+    /// (the handle itself is consumed by `await_result`). Cancelling a
+    /// finished job does nothing.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel_token.clone()
+    }
+
+    /// The job's progress stream; `None` after the first call. It ends
+    /// when the job does.
+    pub fn progress(&mut self) -> Option<mpsc::UnboundedReceiver<u8>> {
+        self.progress_rx.take()
+    }
+
+    /// Wait until the engine starts on the job. Also resolves if it
+    /// never will (cancelled while queued, thread gone): the result is
+    /// then already on its way. Cancel-safe: waiting again after a
+    /// timeout picks up where it left off.
+    pub async fn started(&mut self) {
+        if let Some(started) = &mut self.started_rx {
+            let _ = started.await;
+            self.started_rx = None;
+        }
+    }
+
+    /// Wait for the job's result.
+    pub async fn await_result(self) -> Result<TranscriptionResult, WhisperError> {
+        self.result_rx.await.unwrap_or_else(|_| Err(runner_gone()))
+    }
+}
+
+pub fn runner_gone() -> WhisperError {
+    WhisperError::TranscriptionError("Transcription worker stopped".to_string())
+}
+
+/// Owns the transcription thread and, through it, the engine.
+/// Dropping the runner (or `shutdown`) lets the thread finish the
+/// queued work and exit.
+pub struct JobRunner<E> {
+    commands_tx: std_mpsc::Sender<Command<E>>,
+    next_id: AtomicU64,
+    /// Jobs queued or running; the thread decrements it.
+    pending: Arc<AtomicUsize>,
+    max_depth: AtomicUsize,
+}
+
+impl<E: JobEngine> JobRunner<E> {
+    pub fn spawn(engine: E) -> Self {
+        let (commands_tx, commands_rx) = std_mpsc::channel();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let thread_pending = Arc::clone(&pending);
+        std::thread::Builder::new()
+            .name("whisper-jobs".to_string())
+            .spawn(move || run_jobs(engine, commands_rx, &thread_pending))
+            .expect("failed to spawn the transcription thread");
+        Self {
+            commands_tx,
+            next_id: AtomicU64::new(1),
+            pending,
+            max_depth: AtomicUsize::new(DEFAULT_MAX_QUEUE_DEPTH),
+        }
+    }
+
+    /// Jobs queued or running.
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    /// How many jobs may be pending at once (at least 1). Jobs already
+    /// queued are kept when lowering it.
+    pub fn set_max_depth(&self, max_depth: usize) {
+        self.max_depth.store(max_depth.max(1), Ordering::Release);
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth.load(Ordering::Acquire)
+    }
+
+    /// Queue a job; progress is delivered through the handle.
+    pub fn submit(&self, samples: Arc<[i16]>, params: JobParams) -> Result<JobHandle, QueueError> {
+        let (progress_tx, progress_rx) = mpsc::unbounded_channel();
+        let on_progress: ProgressCallback = Box::new(move |percent| {
+            let _ = progress_tx.send(percent);
+        });
+        let mut handle = self.submit_with(samples, params, Some(on_progress))?;
+        handle.progress_rx = Some(progress_rx);
+        Ok(handle)
+    }
+
+    /// Queue a job reporting progress to a callback instead of a stream.
+    pub fn submit_with(
+        &self,
+        samples: Arc<[i16]>,
+        params: JobParams,
+        on_progress: Option<ProgressCallback>,
+    ) -> Result<JobHandle, QueueError> {
+        let max_depth = self.max_depth();
+        self.pending
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| {
+                (pending < max_depth).then_some(pending + 1)
+            })
+            .map_err(|pending| QueueError::Busy { pending, max_depth })?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let cancel_token = CancelToken::default();
+        let (started_tx, started_rx) = oneshot::channel();
+        let (result_tx, result_rx) = oneshot::channel();
+        let job = TranscribeJob {
+            id,
+            samples,
+            params,
+            on_progress,
+            cancel_token: cancel_token.clone(),
+            started_tx,
+            result_tx,
+        };
+        // The thread only exits once every sender is gone, so this can
+        // only fail if it panicked; the handle then reports it.
+        if self.commands_tx.send(Command::Transcribe(job)).is_err() {
+            tracing::error!("Transcription thread is gone, job {} dropped", id);
+            self.pending.fetch_sub(1, Ordering::AcqRel);
+        }
+        Ok(JobHandle {
+            id,
+            cancel_token,
+            progress_rx: None,
+            started_rx: Some(started_rx),
+            result_rx,
+        })
+    }
+
+    /// Run `call` on the engine once the work queued ahead of it is
+    /// done; its return value arrives on the receiver (which reports a
+    /// closed channel if the thread is gone).
+    pub fn call<R: Send + 'static>(
+        &self,
+        call: impl FnOnce(&mut E) -> R + Send + 'static,
+    ) -> oneshot::Receiver<R> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.post(move |engine| {
+            let _ = reply_tx.send(call(engine));
+        });
+        reply_rx
+    }
+
+    /// `call` without waiting for it: settings changes.
+    pub fn post(&self, call: impl FnOnce(&mut E) + Send + 'static) {
+        if self
+            .commands_tx
+            .send(Command::Call(Box::new(call)))
+            .is_err()
+        {
+            tracing::error!("Transcription thread is gone, engine call dropped");
+        }
+    }
+
+    /// Stop the thread after the work already queued. Later
+    /// submissions fail with the thread-gone error.
+    pub fn shutdown(&self) {
+        let _ = self.commands_tx.send(Command::Shutdown);
+    }
+}
+
+fn run_jobs<E: JobEngine>(
+    mut engine: E,
+    commands_rx: std_mpsc::Receiver<Command<E>>,
+    pending: &AtomicUsize,
+) {
+    for command in commands_rx {
+        let job = match command {
+            Command::Transcribe(job) => job,
+            Command::Call(call) => {
+                // Its reply sender goes with the unwind: the caller sees
+                // the call fail
+                if let Err(panic) = catch_unwind(AssertUnwindSafe(|| call(&mut engine))) {
+                    recover(&mut engine, "An engine call", panic);
+                }
+                continue;
+            }
+            Command::Shutdown => break,
+        };
+        let result = if job.cancel_token.is_cancelled() {
+            tracing::debug!("Job {} cancelled before it started", job.id);
+            Err(WhisperError::Cancelled)
+        } else {
+            tracing::debug!("Running job {} ({} samples)", job.id, job.samples.len());
+            let _ = job.started_tx.send(());
+            let run = catch_unwind(AssertUnwindSafe(|| {
+                engine.run(
+                    &job.samples,
+                    &job.params,
+                    job.on_progress,
+                    &job.cancel_token,
+                )
+            }));
+            run.unwrap_or_else(|panic| {
+                let message = recover(&mut engine, &format!("Job {}", job.id), panic);
+                Err(WhisperError::TranscriptionError(format!(
+                    "the engine panicked: {message}"
+                )))
+            })
+        };
+        // Free the slot before answering, so a caller reacting to the
+        // result already sees the shorter queue.
+        pending.fetch_sub(1, Ordering::AcqRel);
+        // The caller may have stopped waiting; that's fine.
+        let _ = job.result_tx.send(result);
+    }
+    tracing::debug!("Transcription thread exiting");
+}
+
+/// Log `what`'s panic and let the engine rebuild; returns the panic's
+/// message.
+fn recover<E: JobEngine>(
+    engine: &mut E,
+    what: &str,
+    panic: Box<dyn std::any::Any + Send>,
+) -> String {
+    let message = crate::tasks::panic_message(panic);
+    tracing::error!("{} panicked: {}; rebuilding the engine", what, message);
+    engine.recover();
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whisper::decode::DecodeParams;
+    use std::sync::mpsc::{Receiver, Sender};
+    use std::time::Duration;
+
+    /// Echoes the sample count and language as text. Each job waits for
+    /// a go-ahead on `gate` (when set) so tests control the interleaving.
+    /// A job in French panics.
+    struct StubEngine {
+        gate: Option<Receiver<()>>,
+        recovered: usize,
+    }
+
+    impl JobEngine for StubEngine {
+        fn run(
+            &mut self,
+            samples: &[i16],
+            params: &JobParams,
+            on_progress: Option<ProgressCallback>,
+            cancel: &CancelToken,
+        ) -> Result<TranscriptionResult, WhisperError> {
+            if let Some(gate) = &self.gate {
+                gate.recv().unwrap();
+            }
+            if cancel.is_cancelled() {
+                return Err(WhisperError::Cancelled);
+            }
+            if params.language.as_deref() == Some("fr") && samples.is_empty() {
+                panic!("decoder blew up");
+            }
+            if let Some(mut on_progress) = on_progress {
+                on_progress(50);
+                on_progress(100);
+            }
+            let language = params.language.as_deref().unwrap_or("auto");
+            Ok(TranscriptionResult::from_segments(
+                vec![crate::whisper::worker::Segment {
+                    start_ms: 0,
+                    end_ms: 0,
+                    text: format!("{} {}", samples.len(), language),
+                    confidence: None,
+                }],
+                DecodeParams::default(),
+            ))
+        }
+
+        fn recover(&mut self) {
+            self.recovered += 1;
+        }
+    }
+
+    fn gated() -> (JobRunner<StubEngine>, Sender<()>) {
+        let (go, gate) = std::sync::mpsc::channel();
+        (
+            JobRunner::spawn(StubEngine {
+                gate: Some(gate),
+                recovered: 0,
+            }),
+            go,
+        )
+    }
+
+    #[tokio::test]
+    async fn result_and_progress() {
+        let runner = JobRunner::spawn(StubEngine {
+            gate: None,
+            recovered: 0,
+        });
+        let params = JobParams {
+            language: Some("fr".into()),
+            ..JobParams::default()
+        };
+        let mut handle = runner.submit(Arc::from(vec![0; 3]), params).unwrap();
+        let mut progress = handle.progress().unwrap();
+        assert!(handle.progress().is_none(), "the stream is handed out once");
+
+        assert_eq!(handle.await_result().await.unwrap().text, "3 fr");
+        assert_eq!(progress.recv().await, Some(50));
+        assert_eq!(progress.recv().await, Some(100));
+        assert_eq!(progress.recv().await, None, "stream ends with the job");
+    }
+
+    #[tokio::test]
+    async fn jobs_run_in_submission_order() {
+        let (runner, go) = gated();
+        let handles: Vec<_> = (1..=3)
+            .map(|n| {
+                runner
+                    .submit_with(Arc::from(vec![0; n]), JobParams::default(), None)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            handles.iter().map(JobHandle::id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        for _ in 0..3 {
+            go.send(()).unwrap();
+        }
+        let mut texts = Vec::new();
+        for handle in handles {
+            texts.push(handle.await_result().await.unwrap().text);
+        }
+        assert_eq!(texts, vec!["1 auto", "2 auto", "3 auto"]);
+    }
+
+    #[tokio::test]
+    async fn a_job_starts_once_the_one_ahead_is_done() {
+        let (runner, go) = gated();
+        let mut first = runner
+            .submit_with(Arc::from(vec![0; 1]), JobParams::default(), None)
+            .unwrap();
+        let mut second = runner
+            .submit_with(Arc::from(vec![0; 2]), JobParams::default(), None)
+            .unwrap();
+        first.started().await;
+        let waiting = tokio::time::timeout(Duration::from_millis(50), second.started()).await;
+        assert!(waiting.is_err(), "queued behind the first job");
+
+        go.send(()).unwrap();
+        go.send(()).unwrap();
+        second.started().await;
+        assert_eq!(first.await_result().await.unwrap().text, "1 auto");
+        assert_eq!(second.await_result().await.unwrap().text, "2 auto");
+
+        // Cancelled while queued: it never starts, and doesn't hang
+        let _running = runner
+            .submit_with(Arc::from(vec![0; 1]), JobParams::default(), None)
+            .unwrap();
+        let mut dropped = runner
+            .submit_with(Arc::from(vec![0; 3]), JobParams::default(), None)
+            .unwrap();
+        dropped.cancel_token().cancel();
+        go.send(()).unwrap();
+        dropped.started().await;
+        assert!(matches!(
+            dropped.await_result().await,
+            Err(WhisperError::Cancelled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn cancel_queued_and_running_jobs() {
+        let (runner, go) = gated();
+        let running = runner
+            .submit_with(Arc::from(vec![0; 1]), JobParams::default(), None)
+            .unwrap();
+        let queued = runner
+            .submit_with(Arc::from(vec![0; 2]), JobParams::default(), None)
+            .unwrap();
+        let kept = runner
+            .submit_with(Arc::from(vec![0; 3]), JobParams::default(), None)
+            .unwrap();
+
+        // The first job is already inside the engine, waiting on the gate.
+        running.cancel_token().cancel();
+        queued.cancel_token().cancel();
+        go.send(()).unwrap();
+        assert!(matches!(
+            running.await_result().await,
+            Err(WhisperError::Cancelled)
+        ));
+        // The queued job never reached the engine (it would have taken
+        // a gate slot).
+        assert!(matches!(
+            queued.await_result().await,
+            Err(WhisperError::Cancelled)
+        ));
+        go.send(()).unwrap();
+        assert_eq!(kept.await_result().await.unwrap().text, "3 auto");
+    }
+
+    #[tokio::test]
+    async fn queue_depth_is_bounded() {
+        let (runner, go) = gated();
+        let submit = |n| runner.submit_with(Arc::from(vec![0; n]), JobParams::default(), None);
+        let first = submit(1).unwrap();
+        let second = submit(2).unwrap();
+        let third = submit(3).unwrap();
+        assert_eq!(runner.pending(), 3);
+        assert_eq!(
+            submit(4).err(),
+            Some(QueueError::Busy {
+                pending: 3,
+                max_depth: DEFAULT_MAX_QUEUE_DEPTH
+            })
+        );
+
+        go.send(()).unwrap();
+        first.await_result().await.unwrap();
+        assert_eq!(runner.pending(), 2);
+        // The rejected job didn't take an id
+        let fourth = submit(4).unwrap();
+        assert_eq!(fourth.id(), 4);
+
+        for _ in 0..3 {
+            go.send(()).unwrap();
+        }
+        for handle in [second, third, fourth] {
+            handle.await_result().await.unwrap();
+        }
+        assert_eq!(runner.pending(), 0);
+
+        runner.set_max_depth(0);
+        let fifth = submit(5).expect("a depth of 0 still means 1");
+        assert!(submit(6).is_err());
+        go.send(()).unwrap();
+        fifth.await_result().await.unwrap();
+    }
+
+    #[test]
+    fn busy_error_shape() {
+        let busy = QueueError::Busy {
+            pending: 3,
+            max_depth: 3,
+        };
+        assert_eq!(
+            serde_json::to_value(&busy).unwrap(),
+            serde_json::json!({ "kind": "busy", "pending": 3, "maxDepth": 3 })
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_run_in_order_with_jobs() {
+        let (runner, go) = gated();
+        let before = runner
+            .submit_with(Arc::from(vec![0; 1]), JobParams::default(), None)
+            .unwrap();
+        // Queued behind the running job: waits for it
+        let swapped = runner.call(|engine: &mut StubEngine| engine.gate.take().is_some());
+        let after = runner
+            .submit_with(Arc::from(vec![0; 2]), JobParams::default(), None)
+            .unwrap();
+        go.send(()).unwrap();
+        assert_eq!(before.await_result().await.unwrap().text, "1 auto");
+        assert!(swapped.await.unwrap());
+        // The gate is gone: the next job runs without a go-ahead
+        assert_eq!(after.await_result().await.unwrap().text, "2 auto");
+    }
+
+    #[tokio::test]
+    async fn a_panic_fails_only_its_command() {
+        let runner = JobRunner::spawn(StubEngine {
+            gate: None,
+            recovered: 0,
+        });
+        let french = JobParams {
+            language: Some("fr".into()),
+            ..JobParams::default()
+        };
+        let crashed = runner.submit_with(Arc::from(vec![]), french, None).unwrap();
+        let Err(WhisperError::TranscriptionError(message)) = crashed.await_result().await else {
+            panic!("the panic should fail the job");
+        };
+        assert!(message.contains("decoder blew up"), "{message}");
+        assert_eq!(runner.pending(), 0);
+
+        let call: oneshot::Receiver<()> = runner.call(|_| panic!("settings blew up"));
+        assert!(call.await.is_err());
+
+        // The thread carries on, with a rebuilt engine
+        let after = runner
+            .submit_with(Arc::from(vec![0; 2]), JobParams::default(), None)
+            .unwrap();
+        assert_eq!(after.await_result().await.unwrap().text, "2 auto");
+        assert_eq!(runner.call(|engine| engine.recovered).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_the_thread() {
+        let runner = JobRunner::spawn(StubEngine {
+            gate: None,
+            recovered: 0,
+        });
+        runner.post(|engine| engine.gate = None);
+        runner.shutdown();
+        let late = runner.call(|_| ());
+        assert!(late.await.is_err(), "no engine to answer after shutdown");
+        let job = runner
+            .submit_with(Arc::from(vec![0; 1]), JobParams::default(), None)
+            .unwrap();
+        assert!(matches!(
+            job.await_result().await,
+            Err(WhisperError::TranscriptionError(_))
+        ));
+    }
+}
@@ -0,0 +1,361 @@
+//! Why a transcript came out in the language it did.
+//!
+//! Every decode records a `LanguageDecision`: the language setting, the
+//! probabilities whisper's detection came up with, and the rule that
+//! settled it. Three rules, first match wins: an English-only model
+//! decodes English whatever the settings, a fixed `language` setting is
+//! used as is, and on auto-detect the most likely language is taken.
+//! The last decision is kept in `AppState`; `explain_language_choice`
+//! renders it with `explain` into a `LanguageReport` that names the
+//! settings to change when the outcome was wrong.
+
+use serde::Serialize;
+
+use super::LanguageGuess;
+
+/// Candidates kept from the detection.
+pub const DETECTION_CANDIDATES: usize = 5;
+/// A runner-up within this probability of the winner makes the
+/// detection a coin toss.
+const CLOSE_CALL_MARGIN: f32 = 0.2;
+/// Below this much audio the detection has little to go on.
+const SHORT_AUDIO_MS: u64 = 3000;
+
+/// Which rule settled the language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LanguageRule {
+    /// The model only knows English.
+    EnglishOnlyModel,
+    /// The `language` setting names a language.
+    Setting,
+    /// `language` is auto: whisper's detection picked.
+    Detected,
+}
+
+/// How the language of one decode was settled. Frontend mirror:
+/// `LanguageDecision`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageDecision {
+    pub rule: LanguageRule,
+    /// The language asked for; `None` on auto-detect.
+    pub requested: Option<String>,
+    /// What the decode ran in; `None` when it isn't known.
+    pub language: Option<String>,
+    /// Detection's most likely languages, most likely first. Empty
+    /// unless `rule` is `Detected` and the detection ran.
+    pub candidates: Vec<LanguageGuess>,
+    pub audio_ms: u64,
+}
+
+impl LanguageDecision {
+    /// Settle the language of a decode of `audio_ms`. `detect` runs
+    /// whisper's detection and is only called on auto-detect with a
+    /// multilingual model; an empty result leaves the pick to whisper.
+    pub fn decide(
+        requested: Option<&str>,
+        multilingual: bool,
+        audio_ms: u64,
+        detect: impl FnOnce() -> Vec<LanguageGuess>,
+    ) -> Self {
+        let (rule, language, candidates) = match requested {
+            _ if !multilingual => (
+                LanguageRule::EnglishOnlyModel,
+                Some("en".to_string()),
+                Vec::new(),
+            ),
+            Some(code) => (LanguageRule::Setting, Some(code.to_string()), Vec::new()),
+            None => {
+                let candidates = detect();
+                let top = candidates.first().map(|guess| guess.code.clone());
+                (LanguageRule::Detected, top, candidates)
+            }
+        };
+        Self {
+            rule,
+            requested: requested.map(String::from),
+            language,
+            candidates,
+            audio_ms,
+        }
+    }
+
+    /// The runner-up was within `CLOSE_CALL_MARGIN` of the winner.
+    pub fn close_call(&self) -> bool {
+        match self.candidates.as_slice() {
+            [first, second, ..] => first.probability - second.probability < CLOSE_CALL_MARGIN,
+            _ => false,
+        }
+    }
+}
+
+/// Something to change for a different outcome.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageFix {
+    /// The setting to change, by its frontend name; `None` when the fix
+    /// is about the recording.
+    pub setting: Option<&'static str>,
+    pub advice: String,
+}
+
+/// `explain_language_choice` result. Frontend mirror: `LanguageReport`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageReport {
+    pub model: String,
+    pub decision: LanguageDecision,
+    /// The model's `modelLanguages`. Only the language shortcut honours
+    /// it; auto-detect considers every language.
+    pub model_languages: Vec<String>,
+    pub close_call: bool,
+    /// One sentence on what decided.
+    pub summary: String,
+    pub fixes: Vec<LanguageFix>,
+}
+
+/// Render `decision`, taken with `model`. `model_languages` is the
+/// model's enabled list and `has_toggle_shortcut` whether the language
+/// shortcut is bound.
+pub fn explain(
+    model: &str,
+    decision: &LanguageDecision,
+    model_languages: &[String],
+    has_toggle_shortcut: bool,
+) -> LanguageReport {
+    let close_call = decision.close_call();
+    let language = decision
+        .language
+        .as_deref()
+        .unwrap_or("an unknown language");
+    let mut fixes = Vec::new();
+    let summary = match decision.rule {
+        LanguageRule::EnglishOnlyModel => {
+            fixes.push(fix(
+                Some("model"),
+                format!("{model} only knows English: pick a multilingual model (one without `.en`) to transcribe other languages."),
+            ));
+            format!("{model} is an English-only model, so it transcribed in English whatever the language setting.")
+        }
+        LanguageRule::Setting => {
+            fixes.push(fix(
+                Some("language"),
+                "Set it to the language you spoke, or to auto-detect if you switch between languages.".to_string(),
+            ));
+            format!("The language setting is {language}, so the transcript is in {language}; nothing was detected.")
+        }
+        LanguageRule::Detected => {
+            fixes.push(fix(
+                Some("language"),
+                "Set it to the language you speak to skip the detection.".to_string(),
+            ));
+            if close_call {
+                fixes.push(fix(
+                    None,
+                    "The detection was nearly a tie: a few more words before pausing give it more to go on.".to_string(),
+                ));
+            }
+            if decision.audio_ms < SHORT_AUDIO_MS {
+                fixes.push(fix(
+                    None,
+                    format!(
+                        "Only {:.1} s of audio to detect from; longer recordings are detected more reliably.",
+                        decision.audio_ms as f64 / 1000.0
+                    ),
+                ));
+            }
+            if let Some(code) = &decision.language {
+                if !model_languages.is_empty() && !model_languages.contains(code) {
+                    fixes.push(fix(
+                        Some("language"),
+                        format!(
+                            "{code} isn't among the languages enabled for {model} ({}), but auto-detect doesn't go by that list: set the language to one of them.",
+                            model_languages.join(", ")
+                        ),
+                    ));
+                }
+            }
+            describe_detection(decision)
+        }
+    };
+    if decision.rule != LanguageRule::EnglishOnlyModel && !has_toggle_shortcut {
+        fixes.push(fix(
+            Some("languageToggleShortcut"),
+            "Bind it to switch between your favoriteLanguages without opening the settings."
+                .to_string(),
+        ));
+    }
+    LanguageReport {
+        model: model.to_string(),
+        decision: decision.clone(),
+        model_languages: model_languages.to_vec(),
+        close_call,
+        summary,
+        fixes,
+    }
+}
+
+fn describe_detection(decision: &LanguageDecision) -> String {
+    match decision.candidates.as_slice() {
+        [] => "The language setting is auto-detect; whisper picked the language itself and didn't report its probabilities.".to_string(),
+        [first] => format!(
+            "The language setting is auto-detect and whisper detected {} ({}).",
+            first.code,
+            percent(first.probability)
+        ),
+        [first, second, ..] => format!(
+            "The language setting is auto-detect and whisper detected {} ({}), ahead of {} ({}).",
+            first.code,
+            percent(first.probability),
+            second.code,
+            percent(second.probability)
+        ),
+    }
+}
+
+fn percent(probability: f32) -> String {
+    format!("{:.0}%", probability * 100.0)
+}
+
+fn fix(setting: Option<&'static str>, advice: String) -> LanguageFix {
+    LanguageFix { setting, advice }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guesses(pairs: &[(&str, f32)]) -> Vec<LanguageGuess> {
+        pairs
+            .iter()
+            .map(|&(code, probability)| LanguageGuess {
+                code: code.to_string(),
+                probability,
+            })
+            .collect()
+    }
+
+    fn settings_named(report: &LanguageReport) -> Vec<Option<&'static str>> {
+        report.fixes.iter().map(|fix| fix.setting).collect()
+    }
+
+    #[test]
+    fn english_only_model_wins_over_everything() {
+        for requested in [None, Some("fr")] {
+            let decision = LanguageDecision::decide(requested, false, 5000, || {
+                panic!("an English-only model can't detect")
+            });
+            assert_eq!(decision.rule, LanguageRule::EnglishOnlyModel);
+            assert_eq!(decision.language.as_deref(), Some("en"));
+            assert!(decision.candidates.is_empty());
+        }
+        let decision = LanguageDecision::decide(None, false, 5000, Vec::new);
+        let report = explain("base.en", &decision, &[], false);
+        assert_eq!(settings_named(&report), [Some("model")]);
+        assert!(report.summary.contains("English-only"));
+    }
+
+    #[test]
+    fn setting_skips_detection() {
+        let decision = LanguageDecision::decide(Some("de"), true, 5000, || {
+            panic!("a fixed language isn't detected")
+        });
+        assert_eq!(decision.rule, LanguageRule::Setting);
+        assert_eq!(decision.requested.as_deref(), Some("de"));
+        assert_eq!(decision.language.as_deref(), Some("de"));
+
+        let report = explain("small", &decision, &[], false);
+        assert_eq!(
+            settings_named(&report),
+            [Some("language"), Some("languageToggleShortcut")]
+        );
+        assert!(report.summary.contains("setting is de"));
+        // With the shortcut bound, it isn't suggested
+        let report = explain("small", &decision, &[], true);
+        assert_eq!(settings_named(&report), [Some("language")]);
+    }
+
+    #[test]
+    fn detection_takes_the_most_likely() {
+        let decision =
+            LanguageDecision::decide(None, true, 8000, || guesses(&[("fr", 0.91), ("en", 0.05)]));
+        assert_eq!(decision.rule, LanguageRule::Detected);
+        assert_eq!(decision.requested, None);
+        assert_eq!(decision.language.as_deref(), Some("fr"));
+        assert!(!decision.close_call());
+
+        let report = explain("small", &decision, &[], true);
+        assert_eq!(
+            report.summary,
+            "The language setting is auto-detect and whisper detected fr (91%), ahead of en (5%)."
+        );
+        assert_eq!(settings_named(&report), [Some("language")]);
+    }
+
+    #[test]
+    fn close_and_short_detections_say_so() {
+        let decision = LanguageDecision::decide(None, true, 1500, || {
+            guesses(&[("nl", 0.48), ("de", 0.41), ("en", 0.05)])
+        });
+        assert!(decision.close_call());
+        let report = explain("small", &decision, &[], true);
+        assert!(report.close_call);
+        assert_eq!(settings_named(&report), [Some("language"), None, None]);
+        assert!(report.fixes[2].advice.contains("1.5 s"));
+    }
+
+    #[test]
+    fn detection_outside_the_model_languages_is_pointed_out() {
+        let decision =
+            LanguageDecision::decide(None, true, 6000, || guesses(&[("pt", 0.7), ("es", 0.2)]));
+        let enabled = vec!["es".to_string(), "en".to_string()];
+        let report = explain("lv-finetune", &decision, &enabled, true);
+        assert_eq!(report.model_languages, enabled);
+        assert_eq!(report.fixes.len(), 2);
+        assert!(report.fixes[1].advice.contains("(es, en)"));
+        // Inside the list: nothing to add
+        let decision =
+            LanguageDecision::decide(None, true, 6000, || guesses(&[("es", 0.7), ("pt", 0.2)]));
+        assert_eq!(
+            explain("lv-finetune", &decision, &enabled, true)
+                .fixes
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn failed_detection_leaves_the_pick_to_whisper() {
+        let decision = LanguageDecision::decide(None, true, 6000, Vec::new);
+        assert_eq!(decision.rule, LanguageRule::Detected);
+        assert_eq!(decision.language, None);
+        assert!(!decision.close_call());
+        let report = explain("small", &decision, &[], true);
+        assert!(report.summary.contains("didn't report"));
+    }
+
+    #[test]
+    fn payload() {
+        let decision = LanguageDecision::decide(Some("ja"), true, 2000, Vec::new);
+        let json = serde_json::to_value(explain("small", &decision, &[], true)).unwrap();
+        assert_eq!(
+            json["decision"],
+            serde_json::json!({
+                "rule": "setting",
+                "requested": "ja",
+                "language": "ja",
+                "candidates": [],
+                "audioMs": 2000
+            })
+        );
+        assert_eq!(
+            json["fixes"][0],
+            serde_json::json!({
+                "setting": "language",
+                "advice": "Set it to the language you spoke, or to auto-detect if you switch between languages."
+            })
+        );
+        assert_eq!(json["closeCall"], false);
+    }
+}
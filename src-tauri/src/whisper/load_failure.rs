@@ -0,0 +1,422 @@
+//! Why a model failed to load.
+//!
+//! whisper-rs reports every failed load the same way; what went wrong
+//! is in whisper.cpp's log (see `native_log`) and in the file itself.
+//! It matters for what comes next: a truncated download wants a
+//! re-download, a model too large for the memory a smaller model, a
+//! file from a newer whisper.cpp an app update, a GPU that won't start
+//! the CPU. `classify` reads both into a `LoadFailureKind`. It is pure,
+//! and tested against the messages whisper.cpp and ggml actually log.
+
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use super::compat::{self, ModelCompatError};
+use super::WhisperError;
+
+/// `BadMagic` of a GGUF file, the format that followed ggml's. whisper.cpp
+/// doesn't read it yet.
+const GGUF_MAGIC_HEX: &str = "0x46554747";
+
+/// What a failed model load comes down to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoadFailureKind {
+    /// The model file isn't on disk (never downloaded, deleted, moved).
+    ModelMissing,
+    /// The file ends early: an interrupted download or copy.
+    Truncated,
+    /// The file is there but isn't a whisper model whisper.cpp can
+    /// make sense of (bit rot, wrong format).
+    ModelCorrupt,
+    /// A model format or quantization newer than this whisper.cpp.
+    UnsupportedVersion,
+    /// Allocation failed while loading weights, in RAM or VRAM.
+    OutOfMemory,
+    /// The disk is full (typically hit while a download was finishing).
+    DiskFull,
+    /// The GPU backend failed to start.
+    GpuInit,
+    /// Anything we couldn't classify.
+    Unknown,
+}
+
+/// Substrings of lowercased messages, most specific first. The
+/// comments quote what whisper.cpp and ggml log.
+const PATTERNS: &[(&str, LoadFailureKind)] = &[
+    // Our own check, before the engine is asked
+    ("not found", LoadFailureKind::ModelMissing),
+    ("no such file", LoadFailureKind::ModelMissing),
+    // "whisper_init_from_file_with_params_no_state: failed to open '…'"
+    ("failed to open", LoadFailureKind::ModelMissing),
+    ("no space left", LoadFailureKind::DiskFull),
+    ("disk full", LoadFailureKind::DiskFull),
+    // "whisper_model_load: ERROR not all tensors loaded from model file
+    // - expected 245, got 122": the tensors stop where the file does
+    ("not all tensors loaded", LoadFailureKind::Truncated),
+    ("unexpected end", LoadFailureKind::Truncated),
+    // "whisper_model_load: invalid model (bad ftype value 3008)"
+    ("bad ftype", LoadFailureKind::UnsupportedVersion),
+    // "whisper_model_load: unknown tensor 'decoder.…' in model file"
+    ("unknown tensor", LoadFailureKind::UnsupportedVersion),
+    // "ggml_backend_cpu_buffer_type_alloc_buffer: failed to allocate
+    // buffer of size 3094293504", "ggml_gallocr_reserve_n: failed to
+    // allocate Vulkan0 buffer of size …"
+    ("buffer of size", LoadFailureKind::OutOfMemory),
+    // "whisper_kv_cache_init: failed to allocate memory for the kv cache"
+    ("failed to allocate memory", LoadFailureKind::OutOfMemory),
+    // "ggml_vulkan: Device memory allocation of size 1073741824 failed."
+    ("memory allocation of size", LoadFailureKind::OutOfMemory),
+    // "vk::Device::allocateMemory: ErrorOutOfDeviceMemory"
+    ("outofdevicememory", LoadFailureKind::OutOfMemory),
+    ("outofhostmemory", LoadFailureKind::OutOfMemory),
+    ("out of memory", LoadFailureKind::OutOfMemory),
+    ("not enough memory", LoadFailureKind::OutOfMemory),
+    // "whisper_backend_init_gpu: failed to initialize Vulkan0 backend"
+    ("failed to initialize", LoadFailureKind::GpuInit),
+    // "ggml_metal_init: error: failed to initialize the Metal library"
+    ("vulkan", LoadFailureKind::GpuInit),
+    ("metal", LoadFailureKind::GpuInit),
+    ("gpu", LoadFailureKind::GpuInit),
+];
+
+/// Substrings that only say the file was refused. Consulted after the
+/// file itself, which usually tells more.
+const REFUSED_PATTERNS: &[&str] = &[
+    // "whisper_model_load: invalid model data (bad magic)"
+    "bad magic",
+    "invalid model",
+    // "whisper_model_load: tensor 'encoder.conv1.weight' has wrong
+    // shape in model file: got [3, 80, 384], expected [3, 128, 384]"
+    "wrong shape",
+    "wrong size",
+    // "whisper_init_with_params_no_state: failed to load model"
+    "failed to load model",
+];
+
+impl LoadFailureKind {
+    /// Classify an engine error.
+    pub fn from_error(err: &WhisperError) -> Self {
+        match err {
+            WhisperError::ModelNotFound(_) => Self::ModelMissing,
+            WhisperError::LoadError(failure) => failure.kind,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Classify an error message alone (also used for command-level
+    /// failures that never reached the engine, e.g. "file not found").
+    pub fn from_message(message: &str) -> Self {
+        classify(&[message], &FileFacts::default())
+    }
+
+    /// What a header `compat::validate` refused says about the file;
+    /// `None` for problems that aren't the file's contents.
+    pub fn from_header(error: &ModelCompatError) -> Option<Self> {
+        match error {
+            ModelCompatError::Truncated { .. } => Some(Self::Truncated),
+            ModelCompatError::BadMagic { found_hex, .. } if found_hex == GGUF_MAGIC_HEX => {
+                Some(Self::UnsupportedVersion)
+            }
+            ModelCompatError::BadMagic { .. } | ModelCompatError::NotWhisper { .. } => {
+                Some(Self::ModelCorrupt)
+            }
+            ModelCompatError::UnknownQuant { .. } => Some(Self::UnsupportedVersion),
+            _ => None,
+        }
+    }
+
+    /// Whether loading the same file again, on the CPU or later, can
+    /// go differently. Not when the file is the problem: the engine
+    /// skips its CPU fallback and the app goes degraded at once.
+    pub fn retry_can_help(self) -> bool {
+        !matches!(
+            self,
+            Self::Truncated | Self::ModelCorrupt | Self::UnsupportedVersion
+        )
+    }
+}
+
+/// What the model file looked like after a failed load.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FileFacts {
+    pub size_bytes: u64,
+    /// What `compat::validate` found wrong with the header.
+    pub header: Option<ModelCompatError>,
+    /// Memory available at the time; `None` when unknown.
+    pub available_memory: Option<u64>,
+}
+
+impl FileFacts {
+    pub fn inspect(path: &Path) -> Self {
+        let mut sys = sysinfo::System::new();
+        sys.refresh_memory();
+        Self {
+            size_bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            header: compat::validate(path).err(),
+            available_memory: Some(sys.available_memory()).filter(|&bytes| bytes > 0),
+        }
+    }
+
+    /// The kind the file alone points to, if any.
+    fn kind(&self) -> Option<LoadFailureKind> {
+        let from_header = self.header.as_ref().and_then(LoadFailureKind::from_header);
+        from_header.or_else(|| {
+            self.available_memory
+                .is_some_and(|available| self.size_bytes > available)
+                .then_some(LoadFailureKind::OutOfMemory)
+        })
+    }
+}
+
+/// Classify a failed load from its messages (ours and whisper.cpp's
+/// log) and the file: a specific message first, then the file, then a
+/// message that only says the file was refused.
+pub fn classify(messages: &[&str], file: &FileFacts) -> LoadFailureKind {
+    let text = messages.join("\n").to_lowercase();
+    PATTERNS
+        .iter()
+        .find(|(pattern, _)| text.contains(pattern))
+        .map(|&(_, kind)| kind)
+        .or_else(|| file.kind())
+        .or_else(|| {
+            REFUSED_PATTERNS
+                .iter()
+                .any(|pattern| text.contains(pattern))
+                .then_some(LoadFailureKind::ModelCorrupt)
+        })
+        .unwrap_or(LoadFailureKind::Unknown)
+}
+
+/// A failed model load, carried by `WhisperError::LoadError` and as
+/// `failure` by `model:load-failed`. Frontend mirror: `LoadFailure`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadFailure {
+    pub kind: LoadFailureKind,
+    /// What failed, e.g. "CPU loading failed: Failed to create a new
+    /// whisper context."
+    pub message: String,
+    /// whisper.cpp's warnings and errors during the load, verbatim.
+    pub log: Vec<String>,
+}
+
+impl LoadFailure {
+    pub fn new(kind: LoadFailureKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            log: Vec::new(),
+        }
+    }
+
+    /// Classify `message` and `log` of a failed load of a file
+    /// described by `file`.
+    pub fn classify(message: String, log: Vec<String>, file: &FileFacts) -> Self {
+        let mut messages = vec![message.as_str()];
+        messages.extend(log.iter().map(String::as_str));
+        Self {
+            kind: classify(&messages, file),
+            message,
+            log,
+        }
+    }
+
+    /// Say what was being done: "{doing}: {message}". After `classify`,
+    /// so that "GPU loading failed" doesn't read as a GPU problem.
+    pub fn context(mut self, doing: &str) -> Self {
+        self.message = format!("{doing}: {}", self.message);
+        self
+    }
+
+    /// The failure behind an engine error.
+    pub fn from_error(err: &WhisperError) -> Self {
+        match err {
+            WhisperError::LoadError(failure) => failure.clone(),
+            other => Self::new(LoadFailureKind::from_error(other), other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for LoadFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        // The first line is the cause, the later ones its fallout
+        match self.log.first() {
+            Some(line) => write!(f, " ({line})"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTEXT_FAILED: &str = "Failed to create a new whisper context.";
+
+    fn healthy_file() -> FileFacts {
+        FileFacts {
+            size_bytes: 147_951_465,
+            header: None,
+            available_memory: Some(8 << 30),
+        }
+    }
+
+    #[test]
+    fn whisper_cpp_log_corpus() {
+        use LoadFailureKind::*;
+        for (log, expected) in [
+            (
+                "whisper_init_from_file_with_params_no_state: failed to open '/home/me/models/ggml-small.bin'",
+                ModelMissing,
+            ),
+            (
+                "whisper_model_load: ERROR not all tensors loaded from model file - expected 245, got 122",
+                Truncated,
+            ),
+            ("whisper_model_load: invalid model (bad ftype value 3008)", UnsupportedVersion),
+            (
+                "whisper_model_load: unknown tensor 'decoder.blocks.0.attn.q_norm.weight' in model file",
+                UnsupportedVersion,
+            ),
+            (
+                "ggml_backend_cpu_buffer_type_alloc_buffer: failed to allocate buffer of size 3094293504",
+                OutOfMemory,
+            ),
+            (
+                "ggml_gallocr_reserve_n: failed to allocate Vulkan0 buffer of size 188744448",
+                OutOfMemory,
+            ),
+            ("ggml_vulkan: Device memory allocation of size 1073741824 failed.", OutOfMemory),
+            ("vk::Device::allocateMemory: ErrorOutOfDeviceMemory", OutOfMemory),
+            ("whisper_kv_cache_init: failed to allocate memory for the kv cache", OutOfMemory),
+            ("whisper_backend_init_gpu: failed to initialize Vulkan0 backend", GpuInit),
+            ("ggml_metal_init: error: failed to initialize the Metal library", GpuInit),
+            ("whisper_model_load: invalid model data (bad magic)", ModelCorrupt),
+            (
+                "whisper_model_load: tensor 'encoder.conv1.weight' has wrong shape in model file: got [3, 80, 384], expected [3, 128, 384]",
+                ModelCorrupt,
+            ),
+            ("whisper_init_with_params_no_state: failed to load model", ModelCorrupt),
+            // Mentions allocation without running out of memory
+            ("failed to allocate context for unsupported model", Unknown),
+        ] {
+            assert_eq!(
+                LoadFailure::classify(CONTEXT_FAILED.into(), vec![log.into()], &healthy_file()).kind,
+                expected,
+                "{log}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_cause_outranks_its_fallout() {
+        // whisper.cpp logs the allocation first, then that the load failed
+        let failure = LoadFailure::classify(
+            CONTEXT_FAILED.into(),
+            vec![
+                "ggml_backend_cpu_buffer_type_alloc_buffer: failed to allocate buffer of size 3094293504".into(),
+                "whisper_model_load: failed to load model".into(),
+            ],
+            &healthy_file(),
+        )
+        .context("GPU loading failed");
+        assert_eq!(failure.kind, LoadFailureKind::OutOfMemory);
+        assert_eq!(
+            failure.to_string(),
+            "GPU loading failed: Failed to create a new whisper context. (ggml_backend_cpu_buffer_type_alloc_buffer: failed to allocate buffer of size 3094293504)"
+        );
+    }
+
+    #[test]
+    fn the_file_speaks_when_the_log_doesnt() {
+        let classify = |file: FileFacts| classify(&[CONTEXT_FAILED], &file);
+        assert_eq!(classify(healthy_file()), LoadFailureKind::Unknown);
+        assert_eq!(
+            classify(FileFacts {
+                header: Some(ModelCompatError::Truncated { size_bytes: 12 }),
+                ..healthy_file()
+            }),
+            LoadFailureKind::Truncated
+        );
+        assert_eq!(
+            classify(FileFacts {
+                header: Some(ModelCompatError::UnknownQuant { ftype: 31 }),
+                ..healthy_file()
+            }),
+            LoadFailureKind::UnsupportedVersion
+        );
+        let bad_magic = |found: &str| FileFacts {
+            header: Some(ModelCompatError::BadMagic {
+                found_hex: found.into(),
+                expected_hex: "0x67676d6c".into(),
+            }),
+            ..healthy_file()
+        };
+        assert_eq!(
+            classify(bad_magic("0x46554747")),
+            LoadFailureKind::UnsupportedVersion
+        );
+        assert_eq!(
+            classify(bad_magic("0x04034b50")),
+            LoadFailureKind::ModelCorrupt
+        );
+        assert_eq!(
+            classify(FileFacts {
+                size_bytes: 3_094_000_000,
+                available_memory: Some(2 << 30),
+                ..healthy_file()
+            }),
+            LoadFailureKind::OutOfMemory
+        );
+    }
+
+    #[test]
+    fn the_file_outranks_a_bare_refusal() {
+        // "bad magic" on a GGUF file: a newer format, not a broken one
+        let file = FileFacts {
+            header: Some(ModelCompatError::BadMagic {
+                found_hex: GGUF_MAGIC_HEX.into(),
+                expected_hex: "0x67676d6c".into(),
+            }),
+            ..healthy_file()
+        };
+        assert_eq!(
+            classify(
+                &["whisper_model_load: invalid model data (bad magic)"],
+                &file
+            ),
+            LoadFailureKind::UnsupportedVersion
+        );
+    }
+
+    #[test]
+    fn only_file_problems_rule_out_a_retry() {
+        use LoadFailureKind::*;
+        for kind in [Truncated, ModelCorrupt, UnsupportedVersion] {
+            assert!(!kind.retry_can_help(), "{kind:?}");
+        }
+        for kind in [ModelMissing, OutOfMemory, DiskFull, GpuInit, Unknown] {
+            assert!(kind.retry_can_help(), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn payload() {
+        let failure = LoadFailure::classify(
+            CONTEXT_FAILED.into(),
+            vec!["whisper_model_load: invalid model (bad ftype value 3008)".into()],
+            &healthy_file(),
+        );
+        assert_eq!(
+            serde_json::to_value(&failure).unwrap(),
+            serde_json::json!({
+                "kind": "unsupported-version",
+                "message": CONTEXT_FAILED,
+                "log": ["whisper_model_load: invalid model (bad ftype value 3008)"]
+            })
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Model loads: one at a time, with an estimated progress.
+//!
+//! whisper.cpp loads a model in one blocking call and reports nothing
+//! until it returns, which for large-v3 is 10–20 s. The estimate
+//! combines two signals, sampled while that call runs:
+//!
+//! - memory: the process RSS grows by roughly the file size as the
+//!   weights are read (less on GPU, where they end up in VRAM);
+//! - time: an exponential approach whose time constant is the file
+//!   size over `ASSUMED_BYTES_PER_SEC`, so the bar keeps moving when
+//!   the RSS doesn't.
+//!
+//! It takes the higher of the two, never goes backwards, and stops at
+//! `MAX_ESTIMATE_PERCENT` until the load really is done.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Estimates stop here; only the end of the load is 100 %.
+pub const MAX_ESTIMATE_PERCENT: f32 = 95.0;
+/// Rough read + setup throughput, for the time-based estimate.
+const ASSUMED_BYTES_PER_SEC: f64 = 150.0 * 1024.0 * 1024.0;
+
+/// `model:loading` payload.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadProgress {
+    pub load_id: u64,
+    pub model: String,
+    pub elapsed_ms: u64,
+    pub percent: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("A model load is already in progress")]
+pub struct LoadInProgress;
+
+#[derive(Debug, Default)]
+struct SlotInner {
+    current: Option<u64>,
+    next_id: u64,
+}
+
+/// Admits one model load at a time.
+#[derive(Debug, Clone, Default)]
+pub struct LoadSlot(Arc<Mutex<SlotInner>>);
+
+impl LoadSlot {
+    /// Claim the slot for a new load; released when the ticket drops.
+    pub fn try_begin(&self) -> Result<LoadTicket, LoadInProgress> {
+        let mut inner = self.0.lock();
+        if inner.current.is_some() {
+            return Err(LoadInProgress);
+        }
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.current = Some(id);
+        Ok(LoadTicket {
+            id,
+            slot: self.clone(),
+        })
+    }
+}
+
+/// Holds the `LoadSlot` for the duration of one load.
+#[derive(Debug)]
+pub struct LoadTicket {
+    id: u64,
+    slot: LoadSlot,
+}
+
+impl LoadTicket {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for LoadTicket {
+    fn drop(&mut self) {
+        let mut inner = self.slot.0.lock();
+        if inner.current == Some(self.id) {
+            inner.current = None;
+        }
+    }
+}
+
+/// Progress estimate for one load.
+#[derive(Debug, Clone)]
+pub struct LoadEstimate {
+    file_bytes: u64,
+    /// RSS when the load started; `None` when it couldn't be read.
+    base_rss: Option<u64>,
+    last: f32,
+}
+
+impl LoadEstimate {
+    pub fn new(file_bytes: u64, base_rss: Option<u64>) -> Self {
+        Self {
+            file_bytes: file_bytes.max(1),
+            base_rss,
+            last: 0.0,
+        }
+    }
+
+    /// Percent done after `elapsed`, with the current RSS if known.
+    pub fn update(&mut self, elapsed: Duration, rss: Option<u64>) -> f32 {
+        let time_constant = self.file_bytes as f64 / ASSUMED_BYTES_PER_SEC;
+        let by_time = 1.0 - (-elapsed.as_secs_f64() / time_constant).exp();
+        let by_memory = match (self.base_rss, rss) {
+            (Some(base), Some(rss)) => rss.saturating_sub(base) as f64 / self.file_bytes as f64,
+            _ => 0.0,
+        };
+        let percent = (by_time.max(by_memory).min(1.0) as f32) * MAX_ESTIMATE_PERCENT;
+        self.last = self.last.max(percent);
+        self.last
+    }
+}
+
+/// Resident memory of this process, in bytes.
+pub struct RssReader {
+    sys: sysinfo::System,
+    pid: sysinfo::Pid,
+}
+
+impl RssReader {
+    pub fn for_current_process() -> Self {
+        Self {
+            sys: sysinfo::System::new(),
+            pid: sysinfo::Pid::from_u32(std::process::id()),
+        }
+    }
+
+    pub fn read(&mut self) -> Option<u64> {
+        let refreshed = self
+            .sys
+            .refresh_process_specifics(self.pid, sysinfo::ProcessRefreshKind::new().with_memory());
+        refreshed
+            .then(|| self.sys.process(self.pid).map(|p| p.memory()))
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn secs(s: f64) -> Duration {
+        Duration::from_secs_f64(s)
+    }
+
+    #[test]
+    fn one_load_at_a_time() {
+        let slot = LoadSlot::default();
+        let first = slot.try_begin().unwrap();
+        assert_eq!(slot.try_begin().unwrap_err(), LoadInProgress);
+        // Clones share the slot
+        assert!(slot.clone().try_begin().is_err());
+        let first_id = first.id();
+        drop(first);
+        let second = slot.try_begin().unwrap();
+        assert!(second.id() > first_id);
+    }
+
+    #[test]
+    fn estimate_follows_memory_growth() {
+        let mut estimate = LoadEstimate::new(3000 * MB, Some(200 * MB));
+        // Half the file resident after a moment: well past the time curve
+        let percent = estimate.update(secs(0.5), Some(1700 * MB));
+        assert!(
+            (percent - MAX_ESTIMATE_PERCENT / 2.0).abs() < 0.5,
+            "{percent}"
+        );
+    }
+
+    #[test]
+    fn estimate_moves_on_time_alone() {
+        // GPU load: the RSS barely moves, or can't be read at all
+        let mut estimate = LoadEstimate::new(1500 * MB, None);
+        let early = estimate.update(secs(1.0), None);
+        let later = estimate.update(secs(8.0), None);
+        assert!(early > 0.0 && later > early, "{early} {later}");
+        assert!(later < MAX_ESTIMATE_PERCENT);
+    }
+
+    #[test]
+    fn estimate_never_goes_back_or_reaches_100() {
+        let mut estimate = LoadEstimate::new(500 * MB, Some(100 * MB));
+        let high = estimate.update(secs(1.0), Some(500 * MB));
+        // Memory released mid-load (a buffer freed): no step back
+        assert_eq!(estimate.update(secs(1.2), Some(150 * MB)), high);
+        let done = estimate.update(secs(600.0), Some(2000 * MB));
+        assert_eq!(done, MAX_ESTIMATE_PERCENT);
+    }
+
+    #[test]
+    fn progress_payload() {
+        let json = serde_json::to_value(LoadProgress {
+            load_id: 3,
+            model: "large-v3".to_string(),
+            elapsed_ms: 1500,
+            percent: 40.0,
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "loadId": 3,
+                "model": "large-v3",
+                "elapsedMs": 1500,
+                "percent": 40.0
+            })
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! How much memory a loaded model takes.
+//!
+//! Measured around the context creation in `WhisperEngine::load_context`:
+//! the process RSS before and after (the weights on CPU, plus whatever
+//! the GPU backend keeps in host memory), and on Vulkan the
+//! device-local memory this process holds, through
+//! `VK_EXT_memory_budget`. Where VRAM can't be read (Metal, drivers
+//! without the extension) it is estimated from the file size.
+
+use serde::Serialize;
+
+use super::gpu::VramProbe;
+use super::loading::RssReader;
+
+/// VRAM over the file size for the estimate: compute buffers and the
+/// KV cache come on top of the weights.
+const VRAM_OVERHEAD: f64 = 1.25;
+const MB: f64 = 1024.0 * 1024.0;
+
+/// Where `ModelMemory::vram_mb` comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VramSource {
+    Measured,
+    Estimated,
+}
+
+/// Footprint of the loaded model, in MB. `None` where a figure couldn't
+/// be read on this platform. Returned by `get_model_memory` and in
+/// `ModelLoadResult`. Frontend mirror: `ModelMemory`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelMemory {
+    pub file_mb: Option<f64>,
+    /// Growth of the process RSS over the load.
+    pub ram_mb: Option<f64>,
+    /// `None` when the model runs on CPU.
+    pub vram_mb: Option<f64>,
+    pub vram_source: Option<VramSource>,
+}
+
+/// Readings taken before a load, to diff against after it.
+pub struct LoadMeter {
+    rss: RssReader,
+    rss_before: Option<u64>,
+    vram: Option<(VramProbe, u64)>,
+}
+
+impl LoadMeter {
+    /// Start measuring a load; `gpu_device` is the GPU it goes to, if
+    /// any.
+    pub fn start(gpu_device: Option<u32>) -> Self {
+        let mut rss = RssReader::for_current_process();
+        let vram = gpu_device.and_then(VramProbe::open).map(|probe| {
+            let before = probe.usage();
+            (probe, before)
+        });
+        Self {
+            rss_before: rss.read(),
+            rss,
+            vram,
+        }
+    }
+
+    /// The footprint once the load is done; `on_gpu` says where it ended
+    /// up.
+    pub fn finish(mut self, file_bytes: Option<u64>, on_gpu: bool) -> ModelMemory {
+        let rss = self.rss_before.zip(self.rss.read());
+        let vram = self
+            .vram
+            .take()
+            .filter(|_| on_gpu)
+            .map(|(probe, before)| (before, probe.usage()));
+        footprint(file_bytes, rss, vram, on_gpu)
+    }
+}
+
+/// Assemble the figures from (before, after) byte readings.
+fn footprint(
+    file_bytes: Option<u64>,
+    rss: Option<(u64, u64)>,
+    vram: Option<(u64, u64)>,
+    on_gpu: bool,
+) -> ModelMemory {
+    let measured_vram = vram
+        .map(|(before, after)| after.saturating_sub(before))
+        // Nothing new on the device: the driver doesn't report this
+        // process's allocations, so the reading says nothing.
+        .filter(|&delta| delta > 0);
+    let (vram_mb, vram_source) = match (on_gpu, measured_vram, file_bytes) {
+        (false, _, _) => (None, None),
+        (true, Some(delta), _) => (Some(to_mb(delta)), Some(VramSource::Measured)),
+        (true, None, Some(file)) => (
+            Some(round_mb(file as f64 * VRAM_OVERHEAD / MB)),
+            Some(VramSource::Estimated),
+        ),
+        (true, None, None) => (None, None),
+    };
+    ModelMemory {
+        file_mb: file_bytes.map(to_mb),
+        ram_mb: rss.map(|(before, after)| to_mb(after.saturating_sub(before))),
+        vram_mb,
+        vram_source,
+    }
+}
+
+fn to_mb(bytes: u64) -> f64 {
+    round_mb(bytes as f64 / MB)
+}
+
+/// One decimal is plenty for display.
+fn round_mb(mb: f64) -> f64 {
+    (mb * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn cpu_load_has_no_vram() {
+        let memory = footprint(
+            Some(190 * MIB),
+            Some((300 * MIB, 520 * MIB)),
+            Some((0, 400 * MIB)),
+            false,
+        );
+        assert_eq!(
+            memory,
+            ModelMemory {
+                file_mb: Some(190.0),
+                ram_mb: Some(220.0),
+                vram_mb: None,
+                vram_source: None,
+            }
+        );
+    }
+
+    #[test]
+    fn measured_vram_is_the_device_delta() {
+        let memory = footprint(
+            Some(574 * MIB),
+            Some((300 * MIB, 380 * MIB)),
+            Some((100 * MIB, 800 * MIB)),
+            true,
+        );
+        assert_eq!(memory.vram_mb, Some(700.0));
+        assert_eq!(memory.vram_source, Some(VramSource::Measured));
+        assert_eq!(memory.ram_mb, Some(80.0));
+    }
+
+    #[test]
+    fn vram_is_estimated_without_a_reading() {
+        for vram in [None, Some((200 * MIB, 200 * MIB))] {
+            let memory = footprint(Some(1000 * MIB), None, vram, true);
+            assert_eq!(memory.vram_mb, Some(1250.0));
+            assert_eq!(memory.vram_source, Some(VramSource::Estimated));
+            assert_eq!(memory.ram_mb, None);
+        }
+        let memory = footprint(None, None, None, true);
+        assert_eq!((memory.vram_mb, memory.vram_source), (None, None));
+    }
+
+    #[test]
+    fn shrinking_rss_reads_as_zero() {
+        // The previous model's buffers freed during the load
+        let memory = footprint(None, Some((900 * MIB, 850 * MIB)), None, false);
+        assert_eq!(memory.ram_mb, Some(0.0));
+        assert_eq!(to_mb(1536 * 1024), 1.5);
+    }
+
+    #[test]
+    fn payload() {
+        let json =
+            serde_json::to_value(footprint(Some(100 * MIB), None, Some((0, 150 * MIB)), true))
+                .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "fileMb": 100.0,
+                "ramMb": null,
+                "vramMb": 150.0,
+                "vramSource": "measured"
+            })
+        );
+    }
+}
@@ -1,16 +1,45 @@
+pub mod annotations;
+pub mod catalog;
+mod chunking;
 pub mod compat;
+mod confidence;
+pub mod coverage;
+pub mod decode;
+pub mod deterministic;
 mod gpu;
+pub mod jobs;
+pub mod language;
+pub mod load_failure;
+pub mod loading;
+mod memory;
+pub mod model_files;
+pub mod native_log;
+pub mod progress;
+pub mod prompt;
+mod quality;
+pub mod recommend;
+pub mod streaming;
+pub mod suitability;
 mod worker;
 
+pub use chunking::RETRY_CHUNK_SECS;
 // `ImportWarning` is referenced via `ValidationResult.warnings`; the
 // re-export keeps it available for direct match expressions in later
 // steps (Step 9 will pattern-match on it for memory pre-flight).
 #[allow(unused_imports)]
 pub use compat::{ImportWarning, ModelCapabilities, ModelCompatError, ValidationResult};
-pub use gpu::{check_system_health, detect_active_backend, GpuBackend, GpuInfo, SystemHealthCheck};
-// macOS doesn't ship a Vulkan-startup-check (Metal is always available),
-// so only re-export the symbol on platforms where it actually exists.
-// Mirrors the cfg gate in gpu.rs and the single call site in lib.rs.
+pub use gpu::{
+    check_system_health, detect_active_backend, gpu_devices, resolve_gpu_device,
+    vulkan_device_memory, GpuBackend, GpuInfo, SystemHealthCheck,
+};
+// macOS doesn't ship a Vulkan startup probe (Metal is always available),
+// so only re-export the symbols on platforms where they actually exist.
+// Mirrors the cfg gate in gpu.rs and the call sites in lib.rs.
 #[cfg(any(target_os = "windows", target_os = "linux"))]
-pub use gpu::is_vulkan_available_at_startup;
-pub use worker::{ModelLoadResult, WhisperWorker};
+pub use gpu::{is_vulkan_library_present, probe_vulkan};
+pub use memory::ModelMemory;
+pub use worker::{
+    LanguageDetectError, LanguageGuess, ModelInfo, ModelInfoError, ModelLoadResult,
+    TranscriptionResult, WhisperError, WhisperWorker, DEFAULT_NO_SPEECH_THRESHOLD,
+    ENGLISH_ONLY_TRANSLATE_ERROR,
+};
@@ -0,0 +1,504 @@
+//! Model filenames: `ggml-{id}.bin`, optionally with a quantization
+//! suffix, `ggml-{id}-{quantization}.bin`.
+//!
+//! The suffix is told apart from the id by its shape (`q5_0`, `q8_0`,
+//! `q4_k`, `f16`, …), so ids that contain dashes themselves
+//! (`large-v3-turbo`) parse as expected: `ggml-large-v3-turbo-q5_0.bin`
+//! is `large-v3-turbo` quantized `q5_0`.
+//!
+//! Models are looked up in several directories, in order (see
+//! `ModelLocation`); a model id found in an earlier one hides the same
+//! id further down, so a model in the user's own folder wins over the
+//! app's copy.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// A model file found on disk. Frontend mirror: `AvailableModel`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelFile {
+    pub id: String,
+    /// `None` for files without a suffix.
+    pub quantization: Option<String>,
+    pub filename: String,
+    /// An English-only variant: the id ends in `.en`.
+    pub english_only: bool,
+}
+
+/// Where a models directory comes from, in search order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelLocation {
+    /// `Settings.models_dir`, e.g. a folder shared with other whisper
+    /// tools.
+    User,
+    /// The app's own `models/`, where downloads go.
+    AppData,
+    /// Shipped with the app, in its resource directory.
+    Bundled,
+    /// Not a directory: the file `load_whisper_model_from_path` loaded,
+    /// `Settings.custom_model_path`.
+    Custom,
+}
+
+/// Model id of `Settings.custom_model_path`.
+pub const CUSTOM_MODEL_ID: &str = "custom";
+
+/// Extensions `load_whisper_model_from_path` accepts.
+pub const MODEL_EXTENSIONS: [&str; 2] = ["bin", "gguf"];
+
+/// A directory to look for models in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelDir {
+    pub path: PathBuf,
+    pub location: ModelLocation,
+}
+
+/// A model file found by `scan`. Frontend mirror: `AvailableModel`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FoundModel {
+    #[serde(flatten)]
+    pub file: ModelFile,
+    /// Absolute path of the file.
+    pub path: PathBuf,
+    pub source: ModelLocation,
+    pub size_bytes: u64,
+    /// Last modified, Unix time in milliseconds; `None` where the
+    /// platform doesn't tell.
+    pub modified: Option<i64>,
+}
+
+impl FoundModel {
+    /// `file` at `path`, with its size and modification time.
+    fn read(file: ModelFile, path: PathBuf, source: ModelLocation) -> Self {
+        let metadata = std::fs::metadata(&path).ok();
+        Self {
+            file,
+            size_bytes: metadata.as_ref().map_or(0, |m| m.len()),
+            modified: metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_millis() as i64),
+            path,
+            source,
+        }
+    }
+}
+
+/// Every model file in `dirs`, sorted by filename. An id found in an
+/// earlier directory hides the files of that id in later ones.
+/// Unreadable directories are skipped.
+pub fn scan(dirs: &[ModelDir]) -> Vec<FoundModel> {
+    let mut found: Vec<FoundModel> = Vec::new();
+    for dir in dirs {
+        let entries = match std::fs::read_dir(&dir.path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::debug!("Skipping models dir {}: {}", dir.path.display(), e);
+                continue;
+            }
+        };
+        let mut here: Vec<FoundModel> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| {
+                let file = parse_filename(&entry.file_name().to_string_lossy())?;
+                Some(FoundModel::read(file, entry.path(), dir.location))
+            })
+            .collect();
+        here.retain(|model| !found.iter().any(|seen| seen.file.id == model.file.id));
+        found.extend(here);
+    }
+    found.sort_by(|a, b| a.file.filename.cmp(&b.file.filename));
+    found
+}
+
+/// The file to load for `id`: the first directory with any of its
+/// `candidates` wins, so an earlier directory's plain file beats a
+/// later one's quantized file.
+pub fn find(dirs: &[ModelDir], id: &str, quantization: &str) -> Option<FoundModel> {
+    let names = candidates(id, quantization);
+    dirs.iter().find_map(|dir| {
+        names.iter().find_map(|name| {
+            let path = dir.path.join(name);
+            let file = parse_filename(name).filter(|_| path.is_file())?;
+            Some(FoundModel::read(file, path, dir.location))
+        })
+    })
+}
+
+/// The `ggml-….bin` file at `path`, in a directory of `location`.
+/// `None` when it isn't one.
+pub fn found_at(path: &Path, location: ModelLocation) -> Option<FoundModel> {
+    let file = parse_filename(&path.file_name()?.to_string_lossy())?;
+    path.is_file()
+        .then(|| FoundModel::read(file, path.to_path_buf(), location))
+}
+
+/// The file at `path`, named anything, as model `CUSTOM_MODEL_ID`.
+/// `None` when it isn't a file.
+pub fn custom(path: &Path) -> Option<FoundModel> {
+    if !path.is_file() {
+        return None;
+    }
+    let filename = path.file_name()?.to_string_lossy().into_owned();
+    // A conventional name still tells the quantization
+    let parsed = parse_filename(&filename);
+    let file = ModelFile {
+        id: CUSTOM_MODEL_ID.to_string(),
+        quantization: parsed.as_ref().and_then(|f| f.quantization.clone()),
+        english_only: parsed.is_some_and(|f| f.english_only),
+        filename,
+    };
+    Some(FoundModel::read(
+        file,
+        path.to_path_buf(),
+        ModelLocation::Custom,
+    ))
+}
+
+/// Whether `path` has one of `MODEL_EXTENSIONS`, in any case.
+pub fn has_model_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            MODEL_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// `dirs` without repeats: the same directory reached twice (a user
+/// folder that is the app's own, say) keeps its first place.
+pub fn dedup_dirs(dirs: Vec<ModelDir>) -> Vec<ModelDir> {
+    let mut kept: Vec<ModelDir> = Vec::with_capacity(dirs.len());
+    for dir in dirs {
+        let key = canonical(&dir.path);
+        if !kept.iter().any(|other| canonical(&other.path) == key) {
+            kept.push(dir);
+        }
+    }
+    kept
+}
+
+/// Which of `dirs` `path` is in, following symlinks: a link in the
+/// app's folder to a bundled model is the bundled model. `None` for a
+/// file elsewhere (an imported model).
+pub fn location_of(dirs: &[ModelDir], path: &Path) -> Option<ModelLocation> {
+    let parent = canonical(path).parent()?.to_path_buf();
+    dirs.iter()
+        .find(|dir| canonical(&dir.path) == parent)
+        .map(|dir| dir.location)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Split a model filename into id and quantization. `None` when it
+/// isn't `ggml-….bin`.
+pub fn parse_filename(filename: &str) -> Option<ModelFile> {
+    let stem = filename.strip_prefix("ggml-")?.strip_suffix(".bin")?;
+    let (id, quantization) = match stem.rsplit_once('-') {
+        Some((id, suffix)) if is_quantization(suffix) => (id, Some(suffix.to_string())),
+        _ => (stem, None),
+    };
+    (!id.is_empty()).then(|| ModelFile {
+        id: id.to_string(),
+        quantization,
+        filename: filename.to_string(),
+        english_only: id.ends_with(".en"),
+    })
+}
+
+/// Filenames to try for `id`, in order: the quantized file first when
+/// `quantization` is set, then the plain one.
+pub fn candidates(id: &str, quantization: &str) -> Vec<String> {
+    let mut names = Vec::with_capacity(2);
+    if !quantization.is_empty() {
+        names.push(format!("ggml-{id}-{quantization}.bin"));
+    }
+    names.push(format!("ggml-{id}.bin"));
+    names
+}
+
+/// Whether `label` names a ggml quantization: `f16`, `f32`, or `q`
+/// followed by the bit width and optional `_` parts (`q5_0`, `q4_k`).
+pub fn is_quantization(label: &str) -> bool {
+    if matches!(label, "f16" | "f32") {
+        return true;
+    }
+    let Some(rest) = label.strip_prefix('q') else {
+        return false;
+    };
+    let mut parts = rest.split('_');
+    let bits = parts.next().unwrap_or_default();
+    !bits.is_empty()
+        && bits.bytes().all(|b| b.is_ascii_digit())
+        && parts.all(|part| {
+            !part.is_empty()
+                && part
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || b.is_ascii_lowercase())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ids_and_suffixes() {
+        for (filename, id, quantization) in [
+            ("ggml-small.en-q8_0.bin", "small.en", Some("q8_0")),
+            ("ggml-base.en.bin", "base.en", None),
+            (
+                "ggml-large-v3-turbo-q5_0.bin",
+                "large-v3-turbo",
+                Some("q5_0"),
+            ),
+            ("ggml-large-v3-turbo.bin", "large-v3-turbo", None),
+            ("ggml-base-q4_k.bin", "base", Some("q4_k")),
+            ("ggml-medium-f16.bin", "medium", Some("f16")),
+            ("ggml-small.bin", "small", None),
+            // Dashes that aren't a quantization stay in the id
+            ("ggml-large-v3.bin", "large-v3", None),
+            ("ggml-my-model-q.bin", "my-model-q", None),
+        ] {
+            let parsed = parse_filename(filename).unwrap();
+            assert_eq!(parsed.id, id, "{filename}");
+            assert_eq!(parsed.quantization.as_deref(), quantization, "{filename}");
+            assert_eq!(parsed.filename, filename);
+            assert_eq!(parsed.english_only, id.ends_with(".en"), "{filename}");
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        for filename in [
+            "small.bin",
+            "ggml-small.gguf",
+            "ggml-.bin",
+            "ggml--q5_0.bin",
+            "notes.txt",
+        ] {
+            assert_eq!(parse_filename(filename), None, "{filename}");
+        }
+    }
+
+    #[test]
+    fn quantized_name_is_tried_first() {
+        assert_eq!(
+            candidates("large-v3-turbo", "q5_0"),
+            ["ggml-large-v3-turbo-q5_0.bin", "ggml-large-v3-turbo.bin"]
+        );
+        assert_eq!(candidates("small", ""), ["ggml-small.bin"]);
+    }
+
+    fn write(dir: &Path, name: &str) {
+        std::fs::write(dir.join(name), b"ggml").unwrap();
+    }
+
+    fn dir(path: &Path, location: ModelLocation) -> ModelDir {
+        ModelDir {
+            path: path.to_path_buf(),
+            location,
+        }
+    }
+
+    #[test]
+    fn earlier_directories_win() {
+        let user = tempfile::tempdir().unwrap();
+        let app_data = tempfile::tempdir().unwrap();
+        let bundled = tempfile::tempdir().unwrap();
+        write(user.path(), "ggml-small.bin");
+        write(app_data.path(), "ggml-small.bin");
+        write(app_data.path(), "ggml-small-q5_0.bin");
+        write(app_data.path(), "ggml-base.bin");
+        write(app_data.path(), "ggml-base.bin.part");
+        write(bundled.path(), "ggml-base.bin");
+        write(bundled.path(), "ggml-tiny.bin");
+        std::fs::create_dir(bundled.path().join("ggml-dir.bin")).unwrap();
+        let dirs = [
+            dir(user.path(), ModelLocation::User),
+            dir(app_data.path(), ModelLocation::AppData),
+            dir(bundled.path(), ModelLocation::Bundled),
+            // Missing directories are skipped
+            dir(&user.path().join("gone"), ModelLocation::User),
+        ];
+
+        let found = scan(&dirs);
+        assert_eq!(
+            found
+                .iter()
+                .map(|m| (m.file.filename.as_str(), m.source))
+                .collect::<Vec<_>>(),
+            [
+                ("ggml-base.bin", ModelLocation::AppData),
+                ("ggml-small.bin", ModelLocation::User),
+                ("ggml-tiny.bin", ModelLocation::Bundled),
+            ]
+        );
+        assert_eq!(found[1].path, user.path().join("ggml-small.bin"));
+
+        // Loading goes by the same order, quantized or not
+        let small = find(&dirs, "small", "q5_0").unwrap();
+        assert_eq!(small.source, ModelLocation::User);
+        assert_eq!(small.file.quantization, None);
+        assert_eq!(
+            find(&dirs[1..], "small", "q5_0").unwrap().file.filename,
+            "ggml-small-q5_0.bin"
+        );
+        assert_eq!(
+            find(&dirs, "tiny", "").unwrap().source,
+            ModelLocation::Bundled
+        );
+        assert_eq!(find(&dirs, "medium", ""), None);
+    }
+
+    #[test]
+    fn entries_carry_size_date_and_variant() {
+        let fixture = tempfile::tempdir().unwrap();
+        std::fs::write(fixture.path().join("ggml-base.en.bin"), vec![0u8; 1024]).unwrap();
+        std::fs::write(fixture.path().join("ggml-large-v3-turbo-q5_0.bin"), b"ggml").unwrap();
+        write(fixture.path(), "README.md");
+        let found = scan(&[dir(fixture.path(), ModelLocation::User)]);
+        assert_eq!(found.len(), 2);
+
+        let base = &found[0];
+        assert_eq!(base.file.id, "base.en");
+        assert!(base.file.english_only);
+        assert_eq!(base.size_bytes, 1024);
+        let turbo = &found[1];
+        assert_eq!(turbo.file.id, "large-v3-turbo");
+        assert_eq!(turbo.file.quantization.as_deref(), Some("q5_0"));
+        assert!(!turbo.file.english_only);
+        assert_eq!(turbo.size_bytes, 4);
+        // Written just now
+        let now = std::time::SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        let modified = base.modified.expect("no modification time");
+        assert!((now - modified).abs() < 60_000, "{modified} vs {now}");
+
+        assert_eq!(
+            find(&[dir(fixture.path(), ModelLocation::User)], "base.en", ""),
+            Some(base.clone())
+        );
+    }
+
+    #[test]
+    fn repeated_directories_keep_their_first_place() {
+        let shared = tempfile::tempdir().unwrap();
+        let other = tempfile::tempdir().unwrap();
+        let dirs = dedup_dirs(vec![
+            dir(shared.path(), ModelLocation::User),
+            dir(other.path(), ModelLocation::AppData),
+            dir(&shared.path().join("."), ModelLocation::Bundled),
+        ]);
+        assert_eq!(
+            dirs.iter().map(|d| d.location).collect::<Vec<_>>(),
+            [ModelLocation::User, ModelLocation::AppData]
+        );
+    }
+
+    #[test]
+    fn files_are_placed_by_their_real_directory() {
+        let app_data = tempfile::tempdir().unwrap();
+        let bundled = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        write(app_data.path(), "ggml-base.bin");
+        write(bundled.path(), "ggml-tiny.bin");
+        write(elsewhere.path(), "ggml-custom.bin");
+        let dirs = [
+            dir(app_data.path(), ModelLocation::AppData),
+            dir(bundled.path(), ModelLocation::Bundled),
+        ];
+        assert_eq!(
+            location_of(&dirs, &app_data.path().join("ggml-base.bin")),
+            Some(ModelLocation::AppData)
+        );
+        assert_eq!(
+            location_of(&dirs, &bundled.path().join("ggml-tiny.bin")),
+            Some(ModelLocation::Bundled)
+        );
+        assert_eq!(
+            location_of(&dirs, &elsewhere.path().join("ggml-custom.bin")),
+            None
+        );
+        #[cfg(unix)]
+        {
+            let link = app_data.path().join("ggml-tiny.bin");
+            std::os::unix::fs::symlink(bundled.path().join("ggml-tiny.bin"), &link).unwrap();
+            assert_eq!(location_of(&dirs, &link), Some(ModelLocation::Bundled));
+        }
+    }
+
+    #[test]
+    fn custom_files_keep_their_name() {
+        let fixture = tempfile::tempdir().unwrap();
+        let path = fixture.path().join("whisper-finetuned-medical.gguf");
+        std::fs::write(&path, vec![0u8; 64]).unwrap();
+        let found = custom(&path).unwrap();
+        assert_eq!(found.file.id, CUSTOM_MODEL_ID);
+        assert_eq!(found.file.filename, "whisper-finetuned-medical.gguf");
+        assert_eq!(found.file.quantization, None);
+        assert_eq!(found.source, ModelLocation::Custom);
+        assert_eq!(found.size_bytes, 64);
+        // A conventional name still says what it is
+        let path = fixture.path().join("ggml-base.en-q5_1.bin");
+        write(fixture.path(), "ggml-base.en-q5_1.bin");
+        let found = custom(&path).unwrap();
+        assert_eq!(found.file.quantization.as_deref(), Some("q5_1"));
+        assert!(found.file.english_only);
+
+        assert_eq!(custom(&fixture.path().join("gone.bin")), None);
+        assert_eq!(custom(fixture.path()), None);
+    }
+
+    #[test]
+    fn model_extensions() {
+        assert!(has_model_extension(Path::new("/m/tuned.bin")));
+        assert!(has_model_extension(Path::new("/m/tuned.GGUF")));
+        assert!(!has_model_extension(Path::new("/m/tuned.pt")));
+        assert!(!has_model_extension(Path::new("/m/tuned")));
+    }
+
+    #[test]
+    fn payload() {
+        let model = FoundModel {
+            file: parse_filename("ggml-small-q5_0.bin").unwrap(),
+            path: PathBuf::from("/models/ggml-small-q5_0.bin"),
+            source: ModelLocation::AppData,
+            size_bytes: 190_085_487,
+            modified: Some(1_700_000_000_000),
+        };
+        assert_eq!(
+            serde_json::to_value(model).unwrap(),
+            serde_json::json!({
+                "id": "small",
+                "quantization": "q5_0",
+                "filename": "ggml-small-q5_0.bin",
+                "englishOnly": false,
+                "path": "/models/ggml-small-q5_0.bin",
+                "source": "appdata",
+                "sizeBytes": 190_085_487,
+                "modified": 1_700_000_000_000i64
+            })
+        );
+    }
+
+    #[test]
+    fn quantization_labels() {
+        for label in ["q4_0", "q5_1", "q8_0", "q4_k", "q2_k", "f16", "f32"] {
+            assert!(is_quantization(label), "{label}");
+        }
+        for label in ["", "q", "v3", "turbo", "q_0", "q5_", "Q5_0", "en"] {
+            assert!(!is_quantization(label), "{label}");
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! whisper.cpp's log, routed into `tracing`.
+//!
+//! whisper.cpp (and ggml through it) writes its diagnostics to stderr,
+//! out of reach of the app: a failed load only comes back from
+//! whisper-rs as "Failed to create a new whisper context.". `install`
+//! points its logger here instead. Every line goes to `tracing` under
+//! the `whisper_cpp` target (so into the crash log tail too), and the
+//! warnings and errors are kept in a short tail that a `Capture` reads
+//! back after a failed load.
+
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::ffi::{c_char, c_void, CStr};
+use std::sync::Once;
+use whisper_rs::whisper_rs_sys::{
+    self, ggml_log_level, ggml_log_level_GGML_LOG_LEVEL_CONT as CONT,
+    ggml_log_level_GGML_LOG_LEVEL_ERROR as ERROR, ggml_log_level_GGML_LOG_LEVEL_WARN as WARN,
+};
+
+/// Warnings and errors kept.
+const TAIL_LINES: usize = 32;
+
+struct Tail {
+    /// Sequence number of the next line.
+    next: u64,
+    lines: VecDeque<(u64, String)>,
+    /// The last line was kept, so a continuation extends it.
+    last_kept: bool,
+}
+
+static TAIL: Mutex<Tail> = Mutex::new(Tail {
+    next: 0,
+    lines: VecDeque::new(),
+    last_kept: false,
+});
+
+/// Route whisper.cpp's log here. Once, at startup; later calls do
+/// nothing.
+pub fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        // SAFETY: `forward` is a plain function living for the whole
+        // program and ignores the null user data.
+        unsafe { whisper_rs_sys::whisper_log_set(Some(forward), std::ptr::null_mut()) }
+    });
+}
+
+unsafe extern "C" fn forward(level: ggml_log_level, text: *const c_char, _: *mut c_void) {
+    if text.is_null() {
+        return;
+    }
+    // SAFETY: whisper.cpp passes a NUL-terminated string valid for the
+    // duration of the call.
+    let text = unsafe { CStr::from_ptr(text) }.to_string_lossy();
+    record(level, text.trim_end());
+}
+
+fn record(level: ggml_log_level, line: &str) {
+    match level {
+        ERROR => tracing::error!(target: "whisper_cpp", "{}", line),
+        WARN => tracing::warn!(target: "whisper_cpp", "{}", line),
+        // Info is a page of hparams and buffer sizes on every load
+        _ => tracing::debug!(target: "whisper_cpp", "{}", line),
+    }
+    let mut tail = TAIL.lock();
+    match level {
+        ERROR | WARN => {
+            let seq = tail.next;
+            tail.next += 1;
+            if tail.lines.len() == TAIL_LINES {
+                tail.lines.pop_front();
+            }
+            tail.lines.push_back((seq, line.to_string()));
+            tail.last_kept = true;
+        }
+        CONT if tail.last_kept => {
+            if let Some((_, last)) = tail.lines.back_mut() {
+                last.push_str(line);
+            }
+        }
+        _ => tail.last_kept = false,
+    }
+}
+
+/// The warnings and errors logged from `start` on.
+pub struct Capture {
+    from: u64,
+}
+
+impl Capture {
+    pub fn start() -> Self {
+        Self {
+            from: TAIL.lock().next,
+        }
+    }
+
+    /// Oldest first. Lines pushed out of the tail in the meantime are
+    /// lost; a load logs far fewer than `TAIL_LINES`.
+    pub fn lines(&self) -> Vec<String> {
+        TAIL.lock()
+            .lines
+            .iter()
+            .filter(|(seq, _)| *seq >= self.from)
+            .map(|(_, line)| line.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use whisper_rs_sys::ggml_log_level_GGML_LOG_LEVEL_INFO as INFO;
+
+    #[test]
+    fn captures_warnings_and_errors_since_the_start() {
+        record(ERROR, "from an earlier load");
+        let capture = Capture::start();
+        record(INFO, "whisper_model_load: n_vocab       = 51865");
+        record(WARN, "whisper_backend_init_gpu: no GPU found");
+        record(
+            ERROR,
+            "ggml_backend_cpu_buffer_type_alloc_buffer: failed to allocate buffer",
+        );
+        record(CONT, " of size 3094293504");
+        record(INFO, "whisper_model_load: model size    = 1533.14 MB");
+        record(CONT, " (not kept)");
+        let lines = capture.lines();
+        // Other tests log into the same tail
+        let ours: Vec<&str> = lines
+            .iter()
+            .map(String::as_str)
+            .filter(|line| line.starts_with("whisper_") || line.starts_with("ggml_"))
+            .collect();
+        assert_eq!(
+            ours,
+            [
+                "whisper_backend_init_gpu: no GPU found",
+                "ggml_backend_cpu_buffer_type_alloc_buffer: failed to allocate buffer of size 3094293504",
+            ]
+        );
+        assert!(!lines.iter().any(|line| line == "from an earlier load"));
+    }
+}
@@ -0,0 +1,78 @@
+//! Transcription progress reporting.
+//!
+//! whisper.cpp calls its progress callback far more often than a
+//! webview wants to hear about it, so updates are throttled to
+//! `MIN_INTERVAL` before they reach the caller's closure.
+
+use std::time::{Duration, Instant};
+
+/// Receives transcription progress as a percentage (0–100).
+pub type ProgressCallback = Box<dyn FnMut(u8) + Send + 'static>;
+
+/// At most ~10 updates per second.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Decides which progress values are forwarded. Repeats and values
+/// arriving within `MIN_INTERVAL` of the last one are dropped, except
+/// that 100 always gets through so the UI never stalls at 97%.
+#[derive(Debug, Default)]
+pub struct ProgressThrottle {
+    last: Option<(u8, Instant)>,
+}
+
+impl ProgressThrottle {
+    pub fn should_emit(&mut self, percent: u8, now: Instant) -> bool {
+        let emit = match self.last {
+            None => true,
+            Some((last, _)) if percent <= last => false,
+            Some(_) if percent >= 100 => true,
+            Some((_, at)) => now.duration_since(at) >= MIN_INTERVAL,
+        };
+        if emit {
+            self.last = Some((percent, now));
+        }
+        emit
+    }
+}
+
+/// Wrap `callback` so it only sees throttled, clamped values.
+pub fn throttled(mut callback: ProgressCallback) -> impl FnMut(i32) + 'static {
+    let mut throttle = ProgressThrottle::default();
+    move |raw| {
+        let percent = raw.clamp(0, 100) as u8;
+        if throttle.should_emit(percent, Instant::now()) {
+            callback(percent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttles_to_interval_and_always_finishes() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut throttle = ProgressThrottle::default();
+
+        assert!(throttle.should_emit(0, ms(0)));
+        assert!(!throttle.should_emit(5, ms(50))); // too soon
+        assert!(throttle.should_emit(10, ms(100)));
+        assert!(!throttle.should_emit(10, ms(300))); // no change
+        assert!(!throttle.should_emit(8, ms(400))); // never backwards
+        assert!(throttle.should_emit(40, ms(400)));
+        assert!(throttle.should_emit(100, ms(410))); // completion bypasses the interval
+        assert!(!throttle.should_emit(100, ms(600)));
+    }
+
+    #[test]
+    fn wrapper_clamps_raw_values() {
+        let seen = std::sync::Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut cb = throttled(Box::new(move |p| sink.lock().push(p)));
+        cb(-5);
+        cb(150);
+        assert_eq!(*seen.lock(), vec![0, 100]);
+    }
+}
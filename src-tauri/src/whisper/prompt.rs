@@ -0,0 +1,180 @@
+//! Initial-prompt assembly and dictation context carry-over.
+//!
+//! Whisper decodes better when the initial prompt shows it the topic
+//! and spelling it should expect. Two things feed the prompt:
+//!
+//! - the configured prompt: the user's custom vocabulary, which always
+//!   fits first;
+//! - with context carry-over on, the tail of what was dictated earlier
+//!   in the session (`DictationContext`), prepended in whatever token
+//!   budget the configured prompt leaves.
+//!
+//! whisper.cpp only looks at the last `n_text_ctx / 2` prompt tokens and
+//! silently drops the *front* of anything longer — which is where the
+//! carried-over context sits, but only by accident. `assemble_prompt`
+//! trims the context itself, oldest words first, so the result always
+//! fits.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Words of recent dictation kept for carry-over.
+pub const CONTEXT_MAX_WORDS: usize = 200;
+
+/// Rolling buffer of the words dictated this session.
+#[derive(Debug, Default)]
+pub struct DictationContext {
+    words: VecDeque<String>,
+    last_at: Option<Instant>,
+}
+
+impl DictationContext {
+    /// Add a finished dictation, keeping only the last
+    /// `CONTEXT_MAX_WORDS` words.
+    pub fn push(&mut self, text: &str, now: Instant) {
+        self.words
+            .extend(text.split_whitespace().map(str::to_string));
+        let excess = self.words.len().saturating_sub(CONTEXT_MAX_WORDS);
+        self.words.drain(..excess);
+        self.last_at = Some(now);
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.last_at = None;
+    }
+
+    /// Forget the context if nothing was dictated for `idle` (a zero
+    /// duration never expires). Returns whether it was cleared.
+    pub fn expire_if_idle(&mut self, now: Instant, idle: Duration) -> bool {
+        let expired = !idle.is_zero()
+            && self
+                .last_at
+                .is_some_and(|at| now.duration_since(at) >= idle);
+        if expired {
+            self.clear();
+        }
+        expired
+    }
+
+    pub fn text(&self) -> String {
+        self.words
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Build the initial prompt: as much of the end of `context` as fits in
+/// `budget` tokens alongside `base`, followed by `base`. `count_tokens`
+/// is the model's tokenizer. `base` is never cut; if it alone is over
+/// budget it is returned as-is and whisper keeps its tail. `None` when
+/// there is nothing to prompt with.
+pub fn assemble_prompt(
+    base: &str,
+    context: &str,
+    budget: usize,
+    count_tokens: impl Fn(&str) -> usize,
+) -> Option<String> {
+    // whisper-rs panics on interior NULs
+    let base = base.replace('\0', "");
+    let base = base.trim();
+    let words: Vec<&str> = context.split_whitespace().collect();
+    let join = |keep: usize| {
+        let tail = words[words.len() - keep..].join(" ").replace('\0', "");
+        match (tail.is_empty(), base.is_empty()) {
+            (true, _) => base.to_string(),
+            (false, true) => tail,
+            (false, false) => format!("{tail} {base}"),
+        }
+    };
+
+    // Largest number of trailing context words that still fits. Token
+    // counts grow with the word count, so binary search it.
+    let (mut lo, mut hi) = (0, words.len());
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if count_tokens(&join(mid)) <= budget {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    if lo == 0 && !words.is_empty() {
+        tracing::debug!("No prompt budget left for carried-over context");
+    }
+
+    let prompt = join(lo);
+    (!prompt.is_empty()).then_some(prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One token per word, which is close enough to exercise the budget.
+    fn words(s: &str) -> usize {
+        s.split_whitespace().count()
+    }
+
+    #[test]
+    fn context_fits_before_base() {
+        assert_eq!(
+            assemble_prompt("kubectl, Kubernetes", "we deploy the pods", 10, words).as_deref(),
+            Some("we deploy the pods kubectl, Kubernetes")
+        );
+    }
+
+    #[test]
+    fn context_is_trimmed_from_the_front() {
+        assert_eq!(
+            assemble_prompt("kubectl", "one two three four five", 4, words).as_deref(),
+            Some("three four five kubectl")
+        );
+        // Exactly at the budget
+        assert_eq!(
+            assemble_prompt("", "one two three", 3, words).as_deref(),
+            Some("one two three")
+        );
+    }
+
+    #[test]
+    fn base_over_budget_leaves_no_room_for_context() {
+        assert_eq!(
+            assemble_prompt("a b c d", "one two", 3, words).as_deref(),
+            Some("a b c d")
+        );
+        assert_eq!(
+            assemble_prompt("a b", "one two", 2, words).as_deref(),
+            Some("a b")
+        );
+    }
+
+    #[test]
+    fn empty_inputs() {
+        assert_eq!(assemble_prompt("", "", 224, words), None);
+        assert_eq!(assemble_prompt("  ", "one", 0, words), None);
+        assert_eq!(
+            assemble_prompt("", "one two", 1, words).as_deref(),
+            Some("two")
+        );
+    }
+
+    #[test]
+    fn context_rolls_and_expires() {
+        let t0 = Instant::now();
+        let mut ctx = DictationContext::default();
+        let long = "word ".repeat(CONTEXT_MAX_WORDS);
+        ctx.push(&long, t0);
+        ctx.push("latest words", t0);
+        assert_eq!(ctx.words.len(), CONTEXT_MAX_WORDS);
+        assert!(ctx.text().ends_with("word latest words"));
+
+        let idle = Duration::from_secs(300);
+        assert!(!ctx.expire_if_idle(t0 + Duration::from_secs(299), idle));
+        assert!(!ctx.expire_if_idle(t0 + Duration::from_secs(900), Duration::ZERO));
+        assert!(ctx.expire_if_idle(t0 + idle, idle));
+        assert!(ctx.text().is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+//! Degenerate-output detection.
+//!
+//! Greedy decoding sometimes gets stuck in a loop and returns one word
+//! (or phrase) fifty times over. whisper.cpp's own temperature fallback
+//! catches part of it per window, but not all. `assess` scores a
+//! finished transcript with two signals:
+//!
+//! - the zlib compression ratio, as upstream whisper does (repetitive
+//!   text compresses far better than prose; > 2.4 is suspicious). Like
+//!   upstream, per `WINDOW_MS` of audio, the worst window counting: a
+//!   long transcript compresses well just by coming back to its words;
+//! - the repetition ratio: the share of words sitting in a run of the
+//!   same 1–4 word n-gram repeated back to back at least
+//!   `MIN_REPEATS` times.
+//!
+//! When either trips, the engine decodes once more with
+//! `retry_params` and keeps whichever result scores better.
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+use super::worker::Segment;
+use crate::whisper::decode::{DecodeParams, DecodeStrategy};
+
+/// Upstream whisper's `compression_ratio_threshold`.
+pub const MAX_COMPRESSION_RATIO: f32 = 2.4;
+/// Above this share of looping words the output is degenerate.
+pub const MAX_REPETITION_RATIO: f32 = 0.5;
+/// Shorter transcripts are never judged: "no no no" is a fine answer.
+const MIN_WORDS: usize = 8;
+/// Back-to-back occurrences of an n-gram that make a loop.
+const MIN_REPEATS: usize = 3;
+const MAX_NGRAM: usize = 4;
+/// Audio whose text is compressed together: whisper's window.
+const WINDOW_MS: u64 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quality {
+    pub compression_ratio: f32,
+    pub repetition_ratio: f32,
+}
+
+impl Quality {
+    pub fn is_degenerate(&self) -> bool {
+        self.compression_ratio > MAX_COMPRESSION_RATIO
+            || self.repetition_ratio > MAX_REPETITION_RATIO
+    }
+
+    /// Lower is better; above 1.0 is degenerate.
+    pub fn score(&self) -> f32 {
+        (self.compression_ratio / MAX_COMPRESSION_RATIO)
+            .max(self.repetition_ratio / MAX_REPETITION_RATIO)
+    }
+}
+
+pub fn assess(segments: &[Segment]) -> Quality {
+    let windows = windows(segments);
+    let words: Vec<String> = windows
+        .iter()
+        .flat_map(|text| text.split_whitespace())
+        .map(|w| {
+            w.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.len() < MIN_WORDS {
+        return Quality {
+            compression_ratio: 1.0,
+            repetition_ratio: 0.0,
+        };
+    }
+    Quality {
+        compression_ratio: windows
+            .iter()
+            .map(|text| compression_ratio(text))
+            .fold(1.0, f32::max),
+        repetition_ratio: repetition_ratio(&words),
+    }
+}
+
+/// The segments' text, joined per `WINDOW_MS` from each window's first
+/// segment.
+fn windows(segments: &[Segment]) -> Vec<String> {
+    let mut windows: Vec<(u64, String)> = Vec::new();
+    for segment in segments {
+        let text = segment.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match windows.last_mut() {
+            Some((start, joined)) if segment.start_ms < *start + WINDOW_MS => {
+                joined.push(' ');
+                joined.push_str(text);
+            }
+            _ => windows.push((segment.start_ms, text.to_string())),
+        }
+    }
+    windows.into_iter().map(|(_, text)| text).collect()
+}
+
+/// Text bytes over zlib-compressed bytes.
+fn compression_ratio(text: &str) -> f32 {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let compressed = encoder
+        .write_all(text.as_bytes())
+        .and_then(|()| encoder.finish());
+    match compressed {
+        Ok(compressed) if !compressed.is_empty() => text.len() as f32 / compressed.len() as f32,
+        _ => 1.0,
+    }
+}
+
+/// Share of `words` covered by back-to-back repeats of one n-gram, for
+/// the n that covers the most.
+fn repetition_ratio(words: &[String]) -> f32 {
+    let covered = (1..=MAX_NGRAM)
+        .map(|n| looping_words(words, n))
+        .max()
+        .unwrap_or(0);
+    covered as f32 / words.len() as f32
+}
+
+/// Words in runs of `MIN_REPEATS`+ consecutive identical `n`-grams.
+fn looping_words(words: &[String], n: usize) -> usize {
+    let mut covered = 0;
+    let mut i = 0;
+    while i + n <= words.len() {
+        let gram = &words[i..i + n];
+        let mut repeats = 1;
+        while i + (repeats + 1) * n <= words.len()
+            && words[i + repeats * n..i + (repeats + 1) * n] == *gram
+        {
+            repeats += 1;
+        }
+        if repeats >= MIN_REPEATS {
+            covered += repeats * n;
+            i += repeats * n;
+        } else {
+            i += 1;
+        }
+    }
+    covered
+}
+
+/// Parameters for the single retry: beam search, which rarely loops,
+/// and a little more temperature to shake the decoder out of it.
+pub fn retry_params(decode: DecodeParams) -> DecodeParams {
+    DecodeParams {
+        strategy: DecodeStrategy::BeamSearch,
+        beam_size: decode.beam_size.max(5),
+        temperature: (decode.temperature + 0.2).min(1.0),
+        ..decode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `text` as one segment.
+    fn said(text: &str) -> Vec<Segment> {
+        vec![Segment {
+            start_ms: 0,
+            end_ms: 10_000,
+            text: text.to_string(),
+            confidence: None,
+        }]
+    }
+
+    #[test]
+    fn ordinary_dictation_is_fine() {
+        let quality = assess(&said(
+            "Please send the quarterly report to Martin before Friday, and copy the \
+             finance team so they can check the numbers against last year's budget.",
+        ));
+        assert!(!quality.is_degenerate(), "{quality:?}");
+        assert_eq!(quality.repetition_ratio, 0.0);
+    }
+
+    #[test]
+    fn single_word_loop() {
+        let text = ["thank"; 50].join(" ");
+        let quality = assess(&said(&text));
+        assert!(quality.is_degenerate());
+        assert_eq!(quality.repetition_ratio, 1.0);
+        assert!(quality.compression_ratio > MAX_COMPRESSION_RATIO);
+    }
+
+    #[test]
+    fn phrase_loop_after_real_speech() {
+        let text = format!(
+            "I think we should start with the budget. {}",
+            ["Thank you for watching."; 6].join(" ")
+        );
+        let words: Vec<String> = text
+            .split_whitespace()
+            .map(|w| w.trim_matches('.').to_lowercase())
+            .collect();
+        // 6 × 4 looping words out of 32
+        assert_eq!(looping_words(&words, 4), 24);
+        assert!(assess(&said(&text)).is_degenerate());
+    }
+
+    #[test]
+    fn punctuation_and_case_do_not_hide_a_loop() {
+        let text = "Yes. yes, YES! yes yes. Yes yes yes yes";
+        assert_eq!(assess(&said(text)).repetition_ratio, 1.0);
+    }
+
+    #[test]
+    fn short_or_emphatic_repeats_are_not_loops() {
+        // Too short to judge
+        assert!(!assess(&said("no no no no")).is_degenerate());
+        // Two repeats is emphasis, not a loop
+        let text = "it was very very good and the food was really really nice overall";
+        assert_eq!(assess(&said(text)).repetition_ratio, 0.0);
+    }
+
+    #[test]
+    fn a_long_dictation_is_judged_per_window() {
+        // Ten minutes that keep coming back to the same points
+        let paragraph = "The migration moves the billing service to the new cluster, \
+                         then we check the invoices against last month before we \
+                         switch the traffic over for good.";
+        let segments: Vec<Segment> = (0..20)
+            .map(|i| Segment {
+                start_ms: i * 30_000,
+                end_ms: i * 30_000 + 25_000,
+                text: paragraph.to_string(),
+                confidence: None,
+            })
+            .collect();
+        let whole = vec![paragraph; 20].join(" ");
+        assert!(compression_ratio(&whole) > MAX_COMPRESSION_RATIO);
+        assert!(
+            !assess(&segments).is_degenerate(),
+            "{:?}",
+            assess(&segments)
+        );
+    }
+
+    #[test]
+    fn score_orders_results() {
+        let looping = assess(&said(&["hello"; 40].join(" ")));
+        let fine = assess(&said(
+            "the weather today is sunny with a light breeze from the west",
+        ));
+        assert!(fine.score() < looping.score());
+        assert!(fine.score() <= 1.0 && looping.score() > 1.0);
+    }
+
+    #[test]
+    fn retry_switches_to_beam_search() {
+        let retry = retry_params(DecodeParams::default());
+        assert_eq!(retry.strategy, DecodeStrategy::BeamSearch);
+        assert!(retry.beam_size >= 5);
+        assert!((retry.temperature - 0.2).abs() < f32::EPSILON);
+        let hot = DecodeParams {
+            temperature: 0.9,
+            ..DecodeParams::default()
+        };
+        assert_eq!(retry_params(hot).temperature, 1.0);
+    }
+}
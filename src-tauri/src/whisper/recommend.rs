@@ -0,0 +1,354 @@
+//! Which model to suggest for this machine.
+//!
+//! Walks `TIERS` from the largest model down and takes the first one
+//! the hardware runs at dictation speed: on a GPU that depends on its
+//! memory (Apple Silicon shares the system RAM, so half of it counts),
+//! on CPU alone on RAM and core count. The pick is suggested even when
+//! it isn't on disk yet (`needs_download`), with the best fitting model
+//! that is as the alternative.
+//!
+//! The same needs are the "recommended hardware" of the download
+//! catalog (`requirements`, `whisper::catalog`).
+
+use serde::Serialize;
+
+use super::GpuBackend;
+
+const GB: u64 = 1024 * 1024 * 1024;
+
+/// What `recommend` looks at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hardware {
+    pub ram_bytes: u64,
+    pub cpu_cores: usize,
+    /// `None` without a usable GPU backend.
+    pub gpu: Option<Gpu>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gpu {
+    pub backend: GpuBackend,
+    /// Device-local memory; `None` when unknown or shared with the
+    /// system (Metal).
+    pub vram_bytes: Option<u64>,
+}
+
+/// What a model needs to run at dictation speed, in GB and cores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Requirements {
+    pub min_ram_gb: u64,
+    pub min_vram_gb: u64,
+    /// To run it on CPU alone; `None` when it is too slow there
+    /// whatever the machine.
+    pub cpu: Option<CpuRequirements>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuRequirements {
+    pub ram_gb: u64,
+    pub cores: usize,
+}
+
+struct Tier {
+    id: &'static str,
+    needs: Requirements,
+    /// Suggested by `recommend` when it fits.
+    suggested: bool,
+}
+
+/// Largest first. Medium never runs on CPU: large-v3-turbo decodes as
+/// fast there and transcribes better. large-v3 is never suggested:
+/// turbo transcribes nearly as well, several times faster.
+const TIERS: &[Tier] = &[
+    Tier {
+        id: "large-v3",
+        needs: Requirements {
+            min_ram_gb: 8,
+            min_vram_gb: 4,
+            cpu: None,
+        },
+        suggested: false,
+    },
+    Tier {
+        id: "large-v3-turbo",
+        needs: Requirements {
+            min_ram_gb: 8,
+            min_vram_gb: 3,
+            cpu: Some(CpuRequirements {
+                ram_gb: 16,
+                cores: 8,
+            }),
+        },
+        suggested: true,
+    },
+    Tier {
+        id: "medium",
+        needs: Requirements {
+            min_ram_gb: 8,
+            min_vram_gb: 2,
+            cpu: None,
+        },
+        suggested: true,
+    },
+    Tier {
+        id: "small",
+        needs: Requirements {
+            min_ram_gb: 4,
+            min_vram_gb: 1,
+            cpu: Some(CpuRequirements {
+                ram_gb: 8,
+                cores: 4,
+            }),
+        },
+        suggested: true,
+    },
+    Tier {
+        id: "base",
+        needs: Requirements {
+            min_ram_gb: 2,
+            min_vram_gb: 0,
+            cpu: Some(CpuRequirements {
+                ram_gb: 4,
+                cores: 2,
+            }),
+        },
+        suggested: true,
+    },
+    Tier {
+        id: "tiny",
+        needs: Requirements {
+            min_ram_gb: 0,
+            min_vram_gb: 0,
+            cpu: Some(CpuRequirements {
+                ram_gb: 0,
+                cores: 0,
+            }),
+        },
+        suggested: true,
+    },
+];
+
+/// What model `id` needs; an English-only variant needs what its
+/// multilingual model does. `None` for a model not in the tiers.
+pub fn requirements(id: &str) -> Option<Requirements> {
+    let id = id.strip_suffix(".en").unwrap_or(id);
+    TIERS
+        .iter()
+        .find(|tier| tier.id == id)
+        .map(|tier| tier.needs)
+}
+
+/// `recommend_model` result. Frontend mirror: `ModelRecommendation`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recommendation {
+    pub model: String,
+    /// "8 GB RAM, 4 cores, no GPU → small"
+    pub reason: String,
+    /// `model` isn't among the available models.
+    pub needs_download: bool,
+    /// The best fitting model that is available, when `model` isn't.
+    pub alternative: Option<String>,
+}
+
+/// Pick a model for `hardware` given the ids of the models on disk.
+pub fn recommend(hardware: &Hardware, available: &[String]) -> Recommendation {
+    let fitting: Vec<&Tier> = TIERS
+        .iter()
+        .filter(|tier| tier.suggested && fits(&tier.needs, hardware))
+        .collect();
+    // `tiny` always fits
+    let best = fitting[0];
+    let is_available = |id: &str| available.iter().any(|a| a == id);
+    let needs_download = !is_available(best.id);
+    Recommendation {
+        model: best.id.to_string(),
+        reason: format!("{} → {}", describe(hardware), best.id),
+        needs_download,
+        alternative: needs_download
+            .then(|| fitting[1..].iter().find(|tier| is_available(tier.id)))
+            .flatten()
+            .map(|tier| tier.id.to_string()),
+    }
+}
+
+/// Whether `hardware` meets `needs`.
+pub fn fits(needs: &Requirements, hardware: &Hardware) -> bool {
+    let ram_gb = gigabytes(hardware.ram_bytes);
+    if ram_gb < needs.min_ram_gb {
+        return false;
+    }
+    let on_gpu = hardware
+        .gpu
+        .as_ref()
+        .and_then(|gpu| usable_vram(gpu, hardware.ram_bytes))
+        .is_some_and(|vram| gigabytes(vram) >= needs.min_vram_gb);
+    let on_cpu = needs
+        .cpu
+        .is_some_and(|cpu| ram_gb >= cpu.ram_gb && hardware.cpu_cores >= cpu.cores);
+    on_gpu || on_cpu
+}
+
+/// GPU memory the model can use: the device's own, or half the system
+/// RAM on unified memory. `None` when unknown.
+fn usable_vram(gpu: &Gpu, ram_bytes: u64) -> Option<u64> {
+    match (gpu.backend, gpu.vram_bytes) {
+        (_, Some(vram)) => Some(vram),
+        (GpuBackend::Metal, None) => Some(ram_bytes / 2),
+        _ => None,
+    }
+}
+
+/// Rounded to the nearest GB: 7.8 GB of usable RAM is an 8 GB machine.
+fn gigabytes(bytes: u64) -> u64 {
+    (bytes + GB / 2) / GB
+}
+
+fn describe(hardware: &Hardware) -> String {
+    let ram = gigabytes(hardware.ram_bytes);
+    match &hardware.gpu {
+        None => format!("{ram} GB RAM, {} cores, no GPU", hardware.cpu_cores),
+        Some(Gpu {
+            backend: GpuBackend::Metal,
+            vram_bytes: None,
+        }) => format!("{ram} GB unified memory, Metal"),
+        Some(Gpu {
+            backend,
+            vram_bytes: Some(vram),
+        }) => format!(
+            "{ram} GB RAM, {} GPU with {} GB VRAM",
+            backend.name(),
+            gigabytes(*vram)
+        ),
+        Some(Gpu { backend, .. }) => {
+            format!("{ram} GB RAM, {} GPU of unknown memory", backend.name())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu_only(ram_gb: u64, cores: usize) -> Hardware {
+        Hardware {
+            ram_bytes: ram_gb * GB,
+            cpu_cores: cores,
+            gpu: None,
+        }
+    }
+
+    fn with_gpu(ram_gb: u64, backend: GpuBackend, vram_gb: Option<u64>) -> Hardware {
+        Hardware {
+            ram_bytes: ram_gb * GB,
+            cpu_cores: 8,
+            gpu: Some(Gpu {
+                backend,
+                vram_bytes: vram_gb.map(|gb| gb * GB),
+            }),
+        }
+    }
+
+    fn all_models() -> Vec<String> {
+        TIERS.iter().map(|tier| tier.id.to_string()).collect()
+    }
+
+    #[test]
+    fn hardware_matrix() {
+        for (hardware, expected) in [
+            (cpu_only(2, 2), "tiny"),
+            (cpu_only(4, 2), "base"),
+            (cpu_only(8, 2), "base"),
+            (cpu_only(8, 4), "small"),
+            (cpu_only(16, 4), "small"),
+            (cpu_only(16, 8), "large-v3-turbo"),
+            (cpu_only(64, 6), "small"),
+            (with_gpu(8, GpuBackend::Vulkan, Some(2)), "medium"),
+            (with_gpu(16, GpuBackend::Vulkan, Some(8)), "large-v3-turbo"),
+            (with_gpu(4, GpuBackend::Vulkan, Some(8)), "small"),
+            (with_gpu(8, GpuBackend::Vulkan, Some(1)), "small"),
+            // Unknown VRAM: judged as a CPU machine
+            (with_gpu(8, GpuBackend::Vulkan, None), "small"),
+            (with_gpu(16, GpuBackend::Metal, None), "large-v3-turbo"),
+            (with_gpu(8, GpuBackend::Metal, None), "large-v3-turbo"),
+            (with_gpu(4, GpuBackend::Metal, None), "small"),
+        ] {
+            let recommendation = recommend(&hardware, &all_models());
+            assert_eq!(recommendation.model, expected, "{hardware:?}");
+            assert!(!recommendation.needs_download);
+            assert_eq!(recommendation.alternative, None);
+        }
+    }
+
+    #[test]
+    fn reasons() {
+        for (hardware, expected) in [
+            (cpu_only(8, 4), "8 GB RAM, 4 cores, no GPU → small"),
+            (
+                with_gpu(16, GpuBackend::Vulkan, Some(6)),
+                "16 GB RAM, Vulkan GPU with 6 GB VRAM → large-v3-turbo",
+            ),
+            (
+                with_gpu(16, GpuBackend::Metal, None),
+                "16 GB unified memory, Metal → large-v3-turbo",
+            ),
+            (
+                with_gpu(8, GpuBackend::Vulkan, None),
+                "8 GB RAM, Vulkan GPU of unknown memory → small",
+            ),
+        ] {
+            assert_eq!(recommend(&hardware, &all_models()).reason, expected);
+        }
+    }
+
+    #[test]
+    fn ram_is_rounded() {
+        // What machines sold as 8 GB report once the firmware took its part
+        let hardware = Hardware {
+            ram_bytes: 7_900 * 1024 * 1024,
+            cpu_cores: 4,
+            gpu: None,
+        };
+        assert_eq!(recommend(&hardware, &all_models()).model, "small");
+    }
+
+    #[test]
+    fn missing_pick_needs_a_download() {
+        let hardware = with_gpu(16, GpuBackend::Vulkan, Some(8));
+        let available = vec!["tiny".to_string(), "small".to_string()];
+        let recommendation = recommend(&hardware, &available);
+        assert_eq!(recommendation.model, "large-v3-turbo");
+        assert!(recommendation.needs_download);
+        assert_eq!(recommendation.alternative.as_deref(), Some("small"));
+
+        // Nothing that fits on disk
+        let recommendation = recommend(&hardware, &[]);
+        assert!(recommendation.needs_download);
+        assert_eq!(recommendation.alternative, None);
+    }
+
+    #[test]
+    fn large_v3_is_never_suggested() {
+        let hardware = with_gpu(64, GpuBackend::Vulkan, Some(24));
+        assert!(fits(&requirements("large-v3").unwrap(), &hardware));
+        assert_eq!(recommend(&hardware, &all_models()).model, "large-v3-turbo");
+        assert_eq!(requirements("small.en"), requirements("small"));
+        assert_eq!(requirements("large-v1"), None);
+    }
+
+    #[test]
+    fn payload() {
+        let json = serde_json::to_value(recommend(&cpu_only(8, 4), &["base".to_string()])).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "model": "small",
+                "reason": "8 GB RAM, 4 cores, no GPU → small",
+                "needsDownload": true,
+                "alternative": "base"
+            })
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Streaming partial transcription.
+//!
+//! While recording, `AudioStreamer` accumulates the incoming chunks and
+//! says when the next partial pass is due. Each pass transcribes only the
+//! most recent `PARTIAL_WINDOW_SECS` of audio (a sliding window) so its
+//! cost stays flat however long the recording runs; the full buffer is
+//! still transcribed once at `stop_listen` for the final text.
+//!
+//! Passes run in the background on a detached whisper state. A pass that
+//! is still running when the next one falls due simply causes that one
+//! to be skipped — partials are advisory, so dropping one is cheaper than
+//! queueing work behind a slow decode.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Sample rate the capture pipeline delivers (16 kHz mono).
+const SAMPLE_RATE: usize = 16_000;
+
+/// Audio covered by each partial pass.
+pub const PARTIAL_WINDOW_SECS: usize = 10;
+
+/// Whisper has nothing useful to say about less than a second of audio.
+const MIN_PARTIAL_SAMPLES: usize = SAMPLE_RATE;
+
+pub const DEFAULT_PARTIAL_INTERVAL_MS: u32 = 2000;
+
+/// Accumulates captured audio and schedules partial passes.
+pub struct AudioStreamer {
+    window: VecDeque<i16>,
+    window_samples: usize,
+    interval_samples: usize,
+    /// Samples received since recording started.
+    received: usize,
+    /// Value of `received` when the last pass started.
+    last_pass: usize,
+    in_flight: Arc<AtomicBool>,
+}
+
+/// Held by a running partial pass; dropping it lets the next one start.
+pub struct PartialPass {
+    in_flight: Arc<AtomicBool>,
+}
+
+impl Drop for PartialPass {
+    fn drop(&mut self) {
+        self.in_flight.store(false, Ordering::Release);
+    }
+}
+
+impl AudioStreamer {
+    pub fn new(interval_ms: u32) -> Self {
+        let window_samples = PARTIAL_WINDOW_SECS * SAMPLE_RATE;
+        Self {
+            window: VecDeque::with_capacity(window_samples),
+            window_samples,
+            interval_samples: interval_ms as usize * SAMPLE_RATE / 1000,
+            received: 0,
+            last_pass: 0,
+            in_flight: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Append a captured chunk, dropping audio that has slid out of the
+    /// window.
+    pub fn push(&mut self, samples: &[i16]) {
+        self.window.extend(samples);
+        let excess = self.window.len().saturating_sub(self.window_samples);
+        self.window.drain(..excess);
+        self.received += samples.len();
+    }
+
+    /// Whether enough new audio arrived since the last pass.
+    pub fn is_due(&self) -> bool {
+        self.received >= MIN_PARTIAL_SAMPLES
+            && self.received - self.last_pass >= self.interval_samples
+    }
+
+    /// Start a pass if one is due and none is running, returning the
+    /// window to transcribe and the guard to hold until it finishes.
+    /// When the previous pass is still running this one is skipped (and
+    /// the next attempt waits a full interval).
+    pub fn begin_pass(&mut self) -> Option<(Vec<i16>, PartialPass)> {
+        if !self.is_due() {
+            return None;
+        }
+        self.last_pass = self.received;
+        if self.in_flight.swap(true, Ordering::AcqRel) {
+            tracing::debug!("Previous partial pass still running, skipping");
+            return None;
+        }
+        let guard = PartialPass {
+            in_flight: Arc::clone(&self.in_flight),
+        };
+        Some((self.window.iter().copied().collect(), guard))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(n: f32) -> Vec<i16> {
+        vec![1; (n * SAMPLE_RATE as f32) as usize]
+    }
+
+    #[test]
+    fn passes_follow_the_interval() {
+        let mut streamer = AudioStreamer::new(2000);
+        streamer.push(&secs(0.5));
+        assert!(streamer.begin_pass().is_none(), "under a second of audio");
+        streamer.push(&secs(1.5));
+        let (window, guard) = streamer.begin_pass().expect("2s of audio is due");
+        assert_eq!(window.len(), 2 * SAMPLE_RATE);
+        drop(guard);
+        streamer.push(&secs(1.0));
+        assert!(streamer.begin_pass().is_none(), "only 1s since last pass");
+        streamer.push(&secs(1.0));
+        assert!(streamer.begin_pass().is_some());
+    }
+
+    #[test]
+    fn window_slides() {
+        let mut streamer = AudioStreamer::new(1000);
+        streamer.push(&secs(25.0));
+        streamer.push(&[7; 10]);
+        let (window, _guard) = streamer.begin_pass().unwrap();
+        assert_eq!(window.len(), PARTIAL_WINDOW_SECS * SAMPLE_RATE);
+        assert_eq!(&window[window.len() - 10..], &[7; 10]);
+    }
+
+    #[test]
+    fn overlapping_pass_is_skipped() {
+        let mut streamer = AudioStreamer::new(1000);
+        streamer.push(&secs(1.0));
+        let (_, running) = streamer.begin_pass().unwrap();
+        streamer.push(&secs(1.0));
+        assert!(
+            streamer.begin_pass().is_none(),
+            "previous pass still running"
+        );
+        drop(running);
+        assert!(
+            streamer.begin_pass().is_none(),
+            "skipped pass reset the interval"
+        );
+        streamer.push(&secs(1.0));
+        assert!(streamer.begin_pass().is_some());
+    }
+}
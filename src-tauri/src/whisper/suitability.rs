@@ -0,0 +1,233 @@
+//! Model × language suitability.
+//!
+//! Small Whisper models are trained on the same 99 languages as large
+//! ones but have far less capacity to spare for the low-resource ones:
+//! Hindi on `tiny` is mostly garbage, Spanish on `tiny` is merely
+//! sloppy. `MIN_SIZE_FOR_LANGUAGE` records, per language, the smallest
+//! size class that gives usable dictation (roughly following the
+//! per-language WER tables of the Whisper paper); unlisted languages
+//! are fine on any size. `check` turns a combination into a
+//! `config:warning` payload, and `WarningLimiter` makes sure each
+//! combination is only warned about once per session.
+
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Error returned by `start_listen` when a non-English language is
+/// selected with an English-only (`*.en`) model.
+pub const ENGLISH_ONLY_LANGUAGE_ERROR: &str =
+    "The selected language requires a multilingual model; the loaded model is English-only";
+
+/// Model size classes, as reported by `compat::n_audio_state_to_size_class`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SizeClass {
+    Tiny,
+    Base,
+    Small,
+    Medium,
+    Large,
+}
+
+impl SizeClass {
+    fn parse(size_class: &str) -> Option<Self> {
+        match size_class {
+            "tiny" => Some(Self::Tiny),
+            "base" => Some(Self::Base),
+            "small" => Some(Self::Small),
+            "medium" => Some(Self::Medium),
+            "large" => Some(Self::Large),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Tiny => "tiny",
+            Self::Base => "base",
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        }
+    }
+}
+
+/// Smallest usable size class per language. Non-Latin scripts and
+/// low-resource languages need at least `small`.
+const MIN_SIZE_FOR_LANGUAGE: &[(&str, SizeClass)] = &[
+    // Mid-resource: `tiny` is too rough
+    ("bg", SizeClass::Base),
+    ("cs", SizeClass::Base),
+    ("da", SizeClass::Base),
+    ("fi", SizeClass::Base),
+    ("hr", SizeClass::Base),
+    ("hu", SizeClass::Base),
+    ("id", SizeClass::Base),
+    ("ja", SizeClass::Base),
+    ("ko", SizeClass::Base),
+    ("no", SizeClass::Base),
+    ("pl", SizeClass::Base),
+    ("ro", SizeClass::Base),
+    ("ru", SizeClass::Base),
+    ("sk", SizeClass::Base),
+    ("sv", SizeClass::Base),
+    ("tr", SizeClass::Base),
+    ("uk", SizeClass::Base),
+    ("vi", SizeClass::Base),
+    ("zh", SizeClass::Base),
+    // Non-Latin scripts and low-resource languages
+    ("am", SizeClass::Small),
+    ("ar", SizeClass::Small),
+    ("bn", SizeClass::Small),
+    ("el", SizeClass::Small),
+    ("fa", SizeClass::Small),
+    ("gu", SizeClass::Small),
+    ("he", SizeClass::Small),
+    ("hi", SizeClass::Small),
+    ("hy", SizeClass::Small),
+    ("ka", SizeClass::Small),
+    ("km", SizeClass::Small),
+    ("kn", SizeClass::Small),
+    ("lo", SizeClass::Small),
+    ("ml", SizeClass::Small),
+    ("mr", SizeClass::Small),
+    ("my", SizeClass::Small),
+    ("ne", SizeClass::Small),
+    ("pa", SizeClass::Small),
+    ("si", SizeClass::Small),
+    ("sw", SizeClass::Small),
+    ("ta", SizeClass::Small),
+    ("te", SizeClass::Small),
+    ("th", SizeClass::Small),
+    ("ur", SizeClass::Small),
+    ("yo", SizeClass::Small),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigWarningKind {
+    /// Non-English language on an English-only model: won't work at all.
+    EnglishOnlyModel,
+    /// The model is below the smallest usable size for the language.
+    ModelTooSmall,
+}
+
+/// `config:warning` payload.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigWarning {
+    pub kind: ConfigWarningKind,
+    pub message: String,
+    pub suggestion: String,
+}
+
+/// Whether `language` (a whisper code, `None` = auto-detect) works well
+/// on a model of `size_class`. Auto-detect and unknown size classes
+/// (community fine-tunes) are never warned about.
+pub fn check(
+    size_class: &str,
+    is_multilingual: bool,
+    language: Option<&str>,
+) -> Option<ConfigWarning> {
+    let language = language.filter(|l| *l != "en")?;
+    if !is_multilingual {
+        return Some(ConfigWarning {
+            kind: ConfigWarningKind::EnglishOnlyModel,
+            message: format!(
+                "The loaded model is English-only and cannot transcribe \"{language}\""
+            ),
+            suggestion: "Load a multilingual model (without \".en\" in its name)".to_string(),
+        });
+    }
+    let size = SizeClass::parse(size_class)?;
+    let (_, min) = MIN_SIZE_FOR_LANGUAGE
+        .iter()
+        .find(|(code, _)| *code == language)?;
+    (size < *min).then(|| ConfigWarning {
+        kind: ConfigWarningKind::ModelTooSmall,
+        message: format!(
+            "The {} model gives poor results for \"{language}\"",
+            size.as_str()
+        ),
+        suggestion: format!("Use the {} model or larger", min.as_str()),
+    })
+}
+
+/// Remembers which combinations were already warned about this session.
+#[derive(Debug, Default)]
+pub struct WarningLimiter {
+    seen: HashSet<(String, String)>,
+}
+
+impl WarningLimiter {
+    /// `true` the first time a (model, language) pair is seen.
+    pub fn first_time(&mut self, model: &str, language: &str) -> bool {
+        self.seen.insert((model.to_string(), language.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(size: &str, multilingual: bool, language: Option<&str>) -> Option<ConfigWarningKind> {
+        check(size, multilingual, language).map(|w| w.kind)
+    }
+
+    #[test]
+    fn english_and_auto_detect_are_always_fine() {
+        assert_eq!(kind("tiny", false, Some("en")), None);
+        assert_eq!(kind("tiny", false, None), None);
+        assert_eq!(kind("tiny", true, None), None);
+    }
+
+    #[test]
+    fn english_only_model_with_another_language() {
+        assert_eq!(
+            kind("large", false, Some("fr")),
+            Some(ConfigWarningKind::EnglishOnlyModel)
+        );
+    }
+
+    #[test]
+    fn minimum_sizes() {
+        let too_small = Some(ConfigWarningKind::ModelTooSmall);
+        assert_eq!(kind("tiny", true, Some("hi")), too_small);
+        assert_eq!(kind("base", true, Some("hi")), too_small);
+        assert_eq!(kind("small", true, Some("hi")), None);
+        assert_eq!(kind("tiny", true, Some("ru")), too_small);
+        assert_eq!(kind("base", true, Some("ru")), None);
+        // Unlisted (high-resource) languages are fine on any size
+        assert_eq!(kind("tiny", true, Some("es")), None);
+        // Unknown size classes are not judged
+        assert_eq!(kind("unknown", true, Some("hi")), None);
+
+        let warning = check("tiny", true, Some("th")).unwrap();
+        assert_eq!(warning.suggestion, "Use the small model or larger");
+    }
+
+    #[test]
+    fn table_has_no_duplicates() {
+        let mut codes: Vec<&str> = MIN_SIZE_FOR_LANGUAGE.iter().map(|(c, _)| *c).collect();
+        codes.sort_unstable();
+        let len = codes.len();
+        codes.dedup();
+        assert_eq!(codes.len(), len);
+    }
+
+    #[test]
+    fn warnings_are_limited_per_combination() {
+        let mut limiter = WarningLimiter::default();
+        assert!(limiter.first_time("ggml-tiny.bin", "hi"));
+        assert!(!limiter.first_time("ggml-tiny.bin", "hi"));
+        assert!(limiter.first_time("ggml-tiny.bin", "ar"));
+        assert!(limiter.first_time("ggml-base.bin", "hi"));
+    }
+
+    #[test]
+    fn payload_shape() {
+        let warning = check("tiny", true, Some("hi")).unwrap();
+        let json = serde_json::to_value(&warning).unwrap();
+        assert_eq!(json["kind"], "modelTooSmall");
+        assert!(json["suggestion"].is_string());
+    }
+}
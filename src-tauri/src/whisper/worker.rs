@@ -1,12 +1,29 @@
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use serde::Serialize;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::thread::available_parallelism;
 use thiserror::Error;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use whisper_rs::{
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState,
+};
 
-use crate::whisper::GpuBackend;
+use crate::whisper::chunking::{self, WindowSegments};
+use crate::whisper::confidence::TokenStats;
+use crate::whisper::decode::{
+    resolve_decode_params, AdvancedDecoding, DecodeOverride, DecodeParams, DecodeStrategy,
+};
+use crate::whisper::deterministic::{self, Reproducibility, ResolvedParams};
+use crate::whisper::jobs::{
+    self, CancelToken, JobEngine, JobHandle, JobParams, JobRunner, QueueError,
+};
+use crate::whisper::language::{LanguageDecision, DETECTION_CANDIDATES};
+use crate::whisper::load_failure::{FileFacts, LoadFailure, LoadFailureKind};
+use crate::whisper::memory::{LoadMeter, ModelMemory};
+use crate::whisper::progress::{self, ProgressCallback};
+use crate::whisper::{annotations, compat, native_log, prompt, quality, GpuBackend};
+use std::borrow::Cow;
+use std::collections::HashMap;
 
 /// Calculate optimal thread count: 75% of available CPUs, minimum 1
 /// Leaves headroom for UI responsiveness and system tasks
@@ -17,18 +34,92 @@ fn optimal_thread_count() -> i32 {
     threads.max(1) // At least 1 thread
 }
 
+/// Default for the post-decode no-speech filter. Aligned with
+/// whisper.cpp's own `no_speech_thold` default so if upstream ever
+/// implements the in-engine path the two layers won't disagree.
+/// Field-tunable: bump to 0.7 if legitimate quiet speech gets dropped;
+/// much lower and ordinary speech starts disappearing too.
+pub const DEFAULT_NO_SPEECH_THRESHOLD: f32 = 0.6;
+
+/// Error message used whenever translation is requested on an
+/// English-only (`*.en`) model.
+pub const ENGLISH_ONLY_TRANSLATE_ERROR: &str =
+    "Translation requires a multilingual model; the loaded model is English-only";
+
+/// One second of audio: enough for a full decode pass.
+const WARMUP_SAMPLES: usize = 16_000;
+
 #[derive(Error, Debug)]
 pub enum WhisperError {
     #[error("Model not loaded")]
     NotLoaded,
     #[error("Failed to load model: {0}")]
-    LoadError(String),
+    LoadError(LoadFailure),
     #[error("Model not found: {0}")]
     ModelNotFound(String),
     #[error("Transcription failed: {0}")]
     TranscriptionError(String),
     #[error("Invalid audio data")]
     InvalidAudio,
+    #[error("Transcription cancelled")]
+    Cancelled,
+    #[error("Transcription timed out after {0} s")]
+    Timeout(u64),
+}
+
+/// What the loaded model is, read from its `WhisperContext`. Returned
+/// by `get_model_info`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    pub path: String,
+    /// English-only (`*.en`) models are not.
+    pub is_multilingual: bool,
+    pub n_vocab: i32,
+    /// whisper.cpp's own label ("tiny", "base", ..., "large").
+    pub model_type: String,
+    /// Size bucket from `n_audio_state`, as in `ModelCapabilities`.
+    pub size_class: String,
+    /// Quantisation label from `ftype`, as in `ModelCapabilities`.
+    pub quant_label: String,
+    /// Audio context length (1500 = 30 s windows).
+    pub n_audio_ctx: i32,
+    pub n_text_ctx: i32,
+}
+
+/// Structured error of `get_model_info`.
+#[derive(Error, Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ModelInfoError {
+    #[error("No model is loaded")]
+    NotLoaded,
+}
+
+/// One candidate of `detect_language`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageGuess {
+    /// Whisper language code ("en", "fr", ...).
+    pub code: String,
+    pub probability: f32,
+}
+
+/// Structured error of `detect_language`.
+#[derive(Error, Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum LanguageDetectError {
+    #[error("No model is loaded")]
+    NotLoaded,
+    #[error("The loaded model is English-only and cannot detect languages")]
+    EnglishOnly,
+    #[error("No audio to detect the language from")]
+    NoAudio,
+    #[error("Cannot detect the language while recording or transcribing")]
+    Busy,
+    #[error("Microphone capture failed: {message}")]
+    Capture { message: String },
+    #[error("Language detection failed: {message}")]
+    Failed { message: String },
 }
 
 /// Résultat du chargement du modèle
@@ -43,6 +134,106 @@ pub struct ModelLoadResult {
     pub backend: String,
     /// Fallback CPU utilisé après échec GPU
     pub fallback_used: bool,
+    /// Index du GPU utilisé (None sur CPU)
+    pub gpu_device: Option<u32>,
+    /// Flash attention active sur le modèle chargé
+    pub flash_attention: bool,
+    /// Flash attention demandée mais refusée au chargement : modèle
+    /// rechargé sans
+    pub flash_attention_fallback: bool,
+    /// Durée du préchauffage en ms (None si désactivé ou en échec)
+    pub warmup_ms: Option<u64>,
+    /// Mémoire occupée par le modèle chargé
+    pub memory: ModelMemory,
+}
+
+/// Create a context. With `flash_attention`, a failed load is retried
+/// without it (some backends and quantizations don't support it); the
+/// returned flag says whether flash attention ended up on.
+fn new_context(
+    model_path: &str,
+    use_gpu: bool,
+    gpu_device: u32,
+    flash_attention: bool,
+) -> Result<(WhisperContext, bool), whisper_rs::WhisperError> {
+    let attempt = |flash: bool| {
+        let mut params = WhisperContextParameters::default();
+        params.use_gpu(use_gpu);
+        params.gpu_device(gpu_device as i32);
+        params.flash_attn(flash);
+        WhisperContext::new_with_params(model_path, params)
+    };
+    if flash_attention {
+        match attempt(true) {
+            Ok(ctx) => return Ok((ctx, true)),
+            Err(e) => tracing::warn!(
+                "Loading with flash attention failed: {}. Retrying without it...",
+                e
+            ),
+        }
+    }
+    attempt(false).map(|ctx| (ctx, false))
+}
+
+/// One decoded segment with its position in the recording.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Segment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+    /// 0–1, from the segment's token probabilities (see
+    /// `whisper::confidence`). `None` when it has no text tokens.
+    pub confidence: Option<f32>,
+}
+
+/// Structured output of `WhisperEngine::transcribe_job`: the joined text
+/// (what gets pasted) plus the per-segment timeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionResult {
+    pub text: String,
+    pub segments: Vec<Segment>,
+    /// The decode parameters this transcription actually ran with.
+    pub decode: DecodeParams,
+    /// Segments discarded by the no-speech filter.
+    pub dropped_segments: u32,
+    /// Token-weighted confidence over all kept segments.
+    pub confidence: Option<f32>,
+    /// Text tokens generated, over every window decoded.
+    pub tokens: u32,
+    /// The first decode looked degenerate and was retried once (see
+    /// `whisper::quality`); this is the better of the two.
+    pub retried: bool,
+    /// How the language was settled; the first window's when decoded
+    /// in windows.
+    pub language: Option<LanguageDecision>,
+    /// The input's hash and the parameters it ran with (see
+    /// `whisper::deterministic`). `None` for streaming partials.
+    pub reproducibility: Option<Reproducibility>,
+}
+
+impl TranscriptionResult {
+    /// Build from kept segments, joining their trimmed text with spaces.
+    pub fn from_segments(segments: Vec<Segment>, decode: DecodeParams) -> Self {
+        let text = segments
+            .iter()
+            .map(|s| s.text.trim())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self {
+            text,
+            segments,
+            decode,
+            dropped_segments: 0,
+            confidence: None,
+            tokens: 0,
+            retried: false,
+            language: None,
+            reproducibility: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -51,6 +242,27 @@ pub struct WhisperConfig {
     pub language: Option<String>,
     pub translate: bool,
     pub n_threads: i32,
+    /// User advanced decode settings (all languages). Layer 4 in
+    /// `whisper::decode`.
+    pub decode_advanced: DecodeOverride,
+    /// User per-language decode overrides. Layer 5 in `whisper::decode`.
+    pub decode_overrides: HashMap<String, DecodeOverride>,
+    /// Temperature fallback settings. `advanced.temperature` is folded
+    /// into `decode_advanced` by the command layer.
+    pub advanced: AdvancedDecoding,
+    /// Segments whose no-speech probability exceeds this are dropped.
+    pub no_speech_threshold: f32,
+    /// Suppress non-speech tokens and strip residual annotations such
+    /// as `[Music]` or `(applause)` from the text.
+    pub suppress_non_speech: bool,
+    /// Configured prompt (the custom vocabulary). See `whisper::prompt`.
+    pub initial_prompt: String,
+    /// Carried-over dictation context, prepended to the prompt in the
+    /// remaining token budget. Empty when carry-over is off.
+    pub prompt_context: String,
+    /// Decode reproducibly (see `whisper::deterministic`), overriding
+    /// the threads, decoding, fallback and carry-over above.
+    pub deterministic: bool,
 }
 
 impl Default for WhisperConfig {
@@ -62,27 +274,49 @@ impl Default for WhisperConfig {
             language: None, // Auto-detect
             translate: false,
             n_threads: threads,
+            decode_advanced: DecodeOverride::default(),
+            decode_overrides: HashMap::new(),
+            advanced: AdvancedDecoding::default(),
+            no_speech_threshold: DEFAULT_NO_SPEECH_THRESHOLD,
+            suppress_non_speech: true,
+            initial_prompt: String::new(),
+            prompt_context: String::new(),
+            deterministic: false,
         }
     }
 }
 
 /// Whisper transcription engine using whisper-rs native bindings
 pub struct WhisperEngine {
-    context: Option<WhisperContext>,
+    /// Shared so a streaming partial pass can decode with its own state
+    /// without holding the engine lock (see `WhisperWorker::transcribe_detached`).
+    context: Option<Arc<WhisperContext>>,
+    /// Decoding state reused across transcriptions: allocating one is
+    /// expensive (especially on GPU) and whisper.cpp resets it at the
+    /// start of every run. Created right after a model load, dropped on
+    /// model change and after a failed run, then recreated on demand.
+    state: Option<WhisperState>,
     config: WhisperConfig,
     /// Track if GPU is being used for transcription
     using_gpu: bool,
     /// Track if fallback to CPU was used
     fallback_used: bool,
+    /// GPU to load on (None = device 0). Applies from the next load.
+    gpu_device: Option<u32>,
+    /// Footprint of the loaded model, measured at load.
+    memory: Option<ModelMemory>,
 }
 
 impl WhisperEngine {
     pub fn new() -> Self {
         Self {
             context: None,
+            state: None,
             config: WhisperConfig::default(),
             using_gpu: false,
             fallback_used: false,
+            gpu_device: None,
+            memory: None,
         }
     }
 
@@ -105,17 +339,33 @@ impl WhisperEngine {
         }
     }
 
-    /// Load a model from the given path (legacy method, uses GPU if available)
-    pub fn load_model(&mut self, model_path: PathBuf) -> Result<(), WhisperError> {
-        self.load_model_with_options(model_path, false).map(|_| ())
+    /// Select the GPU used by the next model load
+    pub fn set_gpu_device(&mut self, device: Option<u32>) {
+        self.gpu_device = device;
     }
 
-    /// Load a model with explicit CPU/GPU control
+    /// Load a model with explicit CPU/GPU and flash attention control,
+    /// then warm it up (see `warm_up`) unless `warmup` is off.
     /// Returns ModelLoadResult with details about the loading
     pub fn load_model_with_options(
         &mut self,
         model_path: PathBuf,
         force_cpu: bool,
+        flash_attention: bool,
+        warmup: bool,
+    ) -> Result<ModelLoadResult, WhisperError> {
+        let mut result = self.load_context(model_path, force_cpu, flash_attention)?;
+        if warmup {
+            result.warmup_ms = self.warm_up();
+        }
+        Ok(result)
+    }
+
+    fn load_context(
+        &mut self,
+        model_path: PathBuf,
+        force_cpu: bool,
+        flash_attention: bool,
     ) -> Result<ModelLoadResult, WhisperError> {
         if !model_path.exists() {
             return Err(WhisperError::ModelNotFound(
@@ -128,15 +378,23 @@ impl WhisperEngine {
         let should_use_gpu = gpu_backend != GpuBackend::Cpu && !force_cpu;
 
         tracing::info!(
-            "Loading Whisper model: {} (force_cpu={}, detected_backend={:?})",
+            "Loading Whisper model: {} (force_cpu={}, flash_attention={}, detected_backend={:?})",
             model_path.display(),
             force_cpu,
+            flash_attention,
             gpu_backend
         );
 
-        let model_path_str = model_path
-            .to_str()
-            .ok_or_else(|| WhisperError::LoadError("Invalid model path".to_string()))?;
+        let model_path_str = model_path.to_str().ok_or_else(|| {
+            WhisperError::LoadError(LoadFailure::new(
+                LoadFailureKind::Unknown,
+                "Invalid model path",
+            ))
+        })?;
+        let file_bytes = std::fs::metadata(&model_path).ok().map(|m| m.len());
+        // The old model's decoding state would count against the new
+        // one's footprint; it is made again on demand if this load fails
+        self.state = None;
 
         // First attempt: with GPU if available and not forced CPU
         if should_use_gpu {
@@ -145,19 +403,25 @@ impl WhisperEngine {
                 gpu_backend.name()
             );
 
-            let mut params = WhisperContextParameters::default();
-            params.use_gpu(true);
+            let devices = crate::whisper::gpu_devices();
+            let (device, warning) = crate::whisper::resolve_gpu_device(self.gpu_device, &devices);
+            if let Some(warning) = warning {
+                tracing::warn!("{}", warning);
+            }
 
-            match WhisperContext::new_with_params(model_path_str, params) {
-                Ok(ctx) => {
-                    self.context = Some(ctx);
+            let meter = LoadMeter::start(Some(device));
+            let log = native_log::Capture::start();
+            match new_context(model_path_str, true, device, flash_attention) {
+                Ok((ctx, flash_used)) => {
+                    let memory = self.set_context(ctx, meter, file_bytes, true);
                     self.config.model_path = model_path;
                     self.using_gpu = true;
                     self.fallback_used = false;
 
                     tracing::info!(
-                        "Whisper model loaded successfully with {} GPU acceleration",
-                        gpu_backend.name()
+                        "Whisper model loaded successfully with {} GPU acceleration (device {})",
+                        gpu_backend.name(),
+                        device
                     );
 
                     return Ok(ModelLoadResult {
@@ -165,13 +429,27 @@ impl WhisperEngine {
                         using_gpu: true,
                         backend: gpu_backend.name().to_string(),
                         fallback_used: false,
+                        gpu_device: Some(device),
+                        flash_attention: flash_used,
+                        flash_attention_fallback: flash_attention && !flash_used,
+                        warmup_ms: None,
+                        memory,
                     });
                 }
                 Err(gpu_error) => {
-                    tracing::warn!(
-                        "GPU loading failed: {}. Retrying with CPU fallback...",
-                        gpu_error
-                    );
+                    // Loading the same file on the CPU would fail the
+                    // same way when the file is the problem
+                    let failure = LoadFailure::classify(
+                        gpu_error.to_string(),
+                        log.lines(),
+                        &FileFacts::inspect(&model_path),
+                    )
+                    .context("GPU loading failed");
+                    if !failure.kind.retry_can_help() {
+                        tracing::error!("{} ({:?}), not retrying on CPU", failure, failure.kind);
+                        return Err(WhisperError::LoadError(failure));
+                    }
+                    tracing::warn!("{}. Retrying with CPU fallback...", failure);
 
                     // Fall through to CPU attempt
                 }
@@ -181,13 +459,20 @@ impl WhisperEngine {
         // CPU attempt (either forced or as fallback)
         tracing::info!("Loading model with CPU...");
 
-        let mut cpu_params = WhisperContextParameters::default();
-        cpu_params.use_gpu(false);
-
-        let ctx = WhisperContext::new_with_params(model_path_str, cpu_params)
-            .map_err(|e| WhisperError::LoadError(format!("CPU loading failed: {}", e)))?;
-
-        self.context = Some(ctx);
+        let meter = LoadMeter::start(None);
+        let log = native_log::Capture::start();
+        let (ctx, flash_used) =
+            new_context(model_path_str, false, 0, flash_attention).map_err(|e| {
+                WhisperError::LoadError(
+                    LoadFailure::classify(
+                        e.to_string(),
+                        log.lines(),
+                        &FileFacts::inspect(&model_path),
+                    )
+                    .context("CPU loading failed"),
+                )
+            })?;
+        let memory = self.set_context(ctx, meter, file_bytes, false);
         self.config.model_path = model_path;
         self.using_gpu = false;
         self.fallback_used = should_use_gpu; // True if we tried GPU first and failed
@@ -203,124 +488,646 @@ impl WhisperEngine {
             using_gpu: false,
             backend: "CPU".to_string(),
             fallback_used: self.fallback_used,
+            gpu_device: None,
+            flash_attention: flash_used,
+            flash_attention_fallback: flash_attention && !flash_used,
+            warmup_ms: None,
+            memory,
         })
     }
 
+    /// Run `WARMUP_SAMPLES` of silence through a freshly loaded model:
+    /// whisper.cpp allocates its compute buffers (and the GPU backends
+    /// compile their kernels) on the first `full()`, which otherwise
+    /// makes the first dictation 2–3× slower than the next ones.
+    /// Returns how long it took; a failure is logged and otherwise
+    /// ignored, the model is loaded either way.
+    fn warm_up(&mut self) -> Option<u64> {
+        let start = std::time::Instant::now();
+        let silence = vec![0i16; WARMUP_SAMPLES];
+        match self.transcribe_job(&silence, &JobParams::default(), None, None) {
+            Ok(_) => {
+                let ms = start.elapsed().as_millis() as u64;
+                tracing::info!("Model warmed up in {} ms", ms);
+                Some(ms)
+            }
+            Err(e) => {
+                tracing::warn!("Model warm-up failed: {}", e);
+                None
+            }
+        }
+    }
+
     /// Set the language for transcription (None for auto-detect)
     pub fn set_language(&mut self, language: Option<String>) {
         self.config.language = language;
     }
 
+    /// Set the user's global decode settings (empty override = defaults)
+    pub fn set_decode_advanced(&mut self, advanced: DecodeOverride) {
+        self.config.decode_advanced = advanced;
+    }
+
+    /// Enable or disable non-speech suppression ([Music], (applause), ...)
+    pub fn set_suppress_non_speech(&mut self, enabled: bool) {
+        self.config.suppress_non_speech = enabled;
+    }
+
+    /// Set the no-speech probability above which segments are dropped
+    pub fn set_prompt(&mut self, initial_prompt: String, context: String) {
+        self.config.initial_prompt = initial_prompt;
+        self.config.prompt_context = context;
+    }
+
+    pub fn set_no_speech_threshold(&mut self, threshold: f32) {
+        self.config.no_speech_threshold = threshold;
+    }
+
+    /// Set the temperature fallback settings
+    pub fn set_advanced_decoding(&mut self, advanced: AdvancedDecoding) {
+        self.config.advanced = advanced;
+    }
+
+    /// Replace the user's per-language decode overrides
+    pub fn set_decode_overrides(&mut self, overrides: HashMap<String, DecodeOverride>) {
+        self.config.decode_overrides = overrides;
+    }
+
+    /// Resolve the decode parameters for the current language. No model
+    /// presets exist yet, so layer 2 is always empty.
+    pub fn resolve_decode_params(&self) -> DecodeParams {
+        resolve_decode_params(
+            None,
+            self.config.language.as_deref(),
+            Some(&self.config.decode_advanced),
+            &self.config.decode_overrides,
+        )
+    }
+
+    /// Enable or disable translation to English
+    pub fn set_translate(&mut self, translate: bool) {
+        self.config.translate = translate;
+    }
+
+    /// Enable or disable deterministic mode
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.config.deterministic = deterministic;
+    }
+
+    /// Whether translation to English is enabled
+    pub fn is_translating(&self) -> bool {
+        self.config.translate
+    }
+
+    /// Whether the loaded model is multilingual. `None` if no model is
+    /// loaded. English-only (`*.en`) models can't translate.
+    pub fn is_multilingual(&self) -> Option<bool> {
+        self.context.as_ref().map(|ctx| ctx.is_multilingual())
+    }
+
+    /// Describe the loaded model.
+    pub fn model_info(&self) -> Result<ModelInfo, ModelInfoError> {
+        let ctx = self.context.as_ref().ok_or(ModelInfoError::NotLoaded)?;
+        Ok(ModelInfo {
+            path: self.config.model_path.to_string_lossy().into_owned(),
+            is_multilingual: ctx.is_multilingual(),
+            n_vocab: ctx.n_vocab(),
+            model_type: ctx
+                .model_type_readable_str_lossy()
+                .map(|t| t.into_owned())
+                .unwrap_or_else(|_| "unknown".to_string()),
+            size_class: compat::n_audio_state_to_size_class(ctx.model_n_audio_state()).to_string(),
+            quant_label: compat::ftype_to_label(ctx.model_ftype()).to_string(),
+            n_audio_ctx: ctx.model_n_audio_ctx(),
+            n_text_ctx: ctx.model_n_text_ctx(),
+        })
+    }
+
+    /// Footprint of the loaded model, as measured when it loaded.
+    pub fn model_memory(&self) -> Result<ModelMemory, ModelInfoError> {
+        self.memory.clone().ok_or(ModelInfoError::NotLoaded)
+    }
+
     /// Check if a model is loaded
     pub fn is_loaded(&self) -> bool {
         self.context.is_some()
     }
 
-    /// Transcribe audio samples (i16 PCM, 16kHz mono)
-    pub fn transcribe(&self, samples: &[i16]) -> Result<String, WhisperError> {
-        let ctx = self.context.as_ref().ok_or(WhisperError::NotLoaded)?;
+    /// Drop the loaded model and its state, freeing their memory.
+    pub fn unload_model(&mut self) {
+        self.state = None;
+        self.context = None;
+        self.config.model_path = PathBuf::new();
+        self.using_gpu = false;
+        self.fallback_used = false;
+        self.memory = None;
+    }
 
-        if samples.is_empty() {
-            return Err(WhisperError::InvalidAudio);
+    /// Transcribe audio samples (i16 PCM, 16kHz mono) with per-job
+    /// overrides. `on_progress`, if given, receives throttled progress
+    /// percentages while decoding; decoding aborts once `cancel` is set.
+    pub fn transcribe_job(
+        &mut self,
+        samples: &[i16],
+        params: &JobParams,
+        on_progress: Option<ProgressCallback>,
+        cancel: Option<&CancelToken>,
+    ) -> Result<TranscriptionResult, WhisperError> {
+        let ctx = Arc::clone(self.context.as_ref().ok_or(WhisperError::NotLoaded)?);
+        let deterministic = self.config.deterministic;
+        let mut config = Cow::Borrowed(&self.config);
+        if let Some(language) = &params.language {
+            config.to_mut().language = Some(language.clone());
         }
+        if deterministic {
+            let config = config.to_mut();
+            config.n_threads = deterministic::DETERMINISTIC_THREADS;
+            config.advanced = deterministic::pin_fallback(config.advanced);
+            config.prompt_context.clear();
+            // A fresh state, so its RNG starts over too
+            self.state = None;
+        }
+        let mut decode = resolve_decode_params(
+            None,
+            config.language.as_deref(),
+            Some(&config.decode_advanced),
+            &config.decode_overrides,
+        );
+        if deterministic {
+            decode = deterministic::pin_decode(decode);
+        }
+        let mut state = match self.state.take() {
+            Some(state) => state,
+            None => new_state(&ctx)?,
+        };
+        let mut pass = Pass {
+            ctx: &ctx,
+            state: &mut state,
+            config: &config,
+            cancel,
+        };
+        let mut result = run_windowed(&mut pass, decode, samples, params.chunk_secs, on_progress);
+        let mut state_clean = result.is_ok();
+        if let Ok(first) = &mut result {
+            let first_quality = quality::assess(&first.segments);
+            if first_quality.is_degenerate() && deterministic {
+                tracing::warn!(
+                    "Degenerate transcript (compression {:.2}, repetition {:.2}), not retried in deterministic mode",
+                    first_quality.compression_ratio,
+                    first_quality.repetition_ratio
+                );
+            } else if first_quality.is_degenerate() {
+                let retry = quality::retry_params(first.decode);
+                tracing::warn!(
+                    "Degenerate transcript (compression {:.2}, repetition {:.2}), retrying with {:?}",
+                    first_quality.compression_ratio,
+                    first_quality.repetition_ratio,
+                    retry
+                );
+                first.retried = true;
+                match run_windowed(&mut pass, retry, samples, params.chunk_secs, None) {
+                    Ok(mut second) => {
+                        let second_quality = quality::assess(&second.segments);
+                        let better = second_quality.score() < first_quality.score();
+                        tracing::info!(
+                            "Retry scored {:.2} against {:.2}; keeping the {}",
+                            second_quality.score(),
+                            first_quality.score(),
+                            if better { "retry" } else { "first decode" }
+                        );
+                        if better {
+                            second.retried = true;
+                            *first = second;
+                        }
+                    }
+                    Err(WhisperError::Cancelled) => {
+                        state_clean = false;
+                        result = Err(WhisperError::Cancelled);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Retry failed ({}), keeping the first decode", e);
+                        state_clean = false;
+                    }
+                }
+            }
+        }
+        if let Ok(result) = &mut result {
+            result.reproducibility = Some(Reproducibility::new(
+                samples,
+                ResolvedParams {
+                    model_path: config.model_path.clone(),
+                    using_gpu: self.using_gpu,
+                    deterministic,
+                    language: config.language.clone(),
+                    translate: config.translate,
+                    n_threads: config.n_threads,
+                    decode: result.decode,
+                    fallback: config.advanced,
+                    no_speech_threshold: config.no_speech_threshold,
+                    suppress_non_speech: config.suppress_non_speech,
+                    initial_prompt: config.initial_prompt.clone(),
+                    prompt_context: config.prompt_context.clone(),
+                    chunk_secs: params.chunk_secs,
+                },
+            ));
+        }
+        // A failed (or interrupted) run may leave the state half-written;
+        // only keep it after a clean one.
+        if state_clean {
+            self.state = Some(state);
+        }
+        result
+    }
 
-        // Convert i16 samples to f32 (whisper-rs expects f32)
-        let samples_f32: Vec<f32> = samples
-            .iter()
-            .map(|&s| s as f32 / i16::MAX as f32)
-            .collect();
+    /// A copy without the decoding state, for `WhisperWorker::view`.
+    fn snapshot(&self) -> WhisperEngine {
+        WhisperEngine {
+            context: self.context.clone(),
+            state: None,
+            config: self.config.clone(),
+            using_gpu: self.using_gpu,
+            fallback_used: self.fallback_used,
+            gpu_device: self.gpu_device,
+            memory: self.memory.clone(),
+        }
+    }
 
-        tracing::info!(
-            "Transcribing {} samples ({:.2}s) — language: {}",
-            samples.len(),
-            samples.len() as f32 / 16000.0,
-            self.config.language.as_deref().unwrap_or("auto-detect")
+    /// Copy what a model load changed into `view`, leaving its
+    /// settings alone: they may be newer than the ones queued here.
+    fn publish_model(&self, view: &mut WhisperEngine) {
+        view.context = self.context.clone();
+        view.config.model_path = self.config.model_path.clone();
+        view.using_gpu = self.using_gpu;
+        view.fallback_used = self.fallback_used;
+        view.memory = self.memory.clone();
+    }
+
+    /// Install a freshly loaded model and pre-allocate its decoding
+    /// state, so the first dictation doesn't pay for it. The footprint
+    /// `meter` reports counts that state, and is read before the old
+    /// model is dropped (it was there when the meter started).
+    fn set_context(
+        &mut self,
+        ctx: WhisperContext,
+        meter: LoadMeter,
+        file_bytes: Option<u64>,
+        on_gpu: bool,
+    ) -> ModelMemory {
+        // Free the old model's state before allocating the new one.
+        self.state = None;
+        self.state = match ctx.create_state() {
+            Ok(state) => Some(state),
+            Err(e) => {
+                tracing::warn!("Could not pre-allocate whisper state: {}", e);
+                None
+            }
+        };
+        let memory = meter.finish(file_bytes, on_gpu);
+        self.memory = Some(memory.clone());
+        self.context = Some(Arc::new(ctx));
+        memory
+    }
+}
+
+fn new_state(ctx: &WhisperContext) -> Result<WhisperState, WhisperError> {
+    ctx.create_state()
+        .map_err(|e| WhisperError::TranscriptionError(format!("Failed to create state: {}", e)))
+}
+
+/// Whisper only looks at the first 30 s window for language detection.
+const LANG_DETECT_MAX_SAMPLES: usize = 30 * 16_000;
+
+/// The `n` most likely languages from whisper's per-language
+/// probabilities (indexed by language id), most likely first.
+fn top_languages(
+    probs: &[f32],
+    n: usize,
+    lang_str: impl Fn(i32) -> Option<&'static str>,
+) -> Vec<LanguageGuess> {
+    let mut ranked: Vec<(usize, f32)> = probs.iter().copied().enumerate().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+        .into_iter()
+        .filter_map(|(id, probability)| {
+            lang_str(id as i32).map(|code| LanguageGuess {
+                code: code.to_string(),
+                probability,
+            })
+        })
+        .take(n)
+        .collect()
+}
+
+/// Language probabilities of `samples` on `state`, for
+/// `LanguageDecision::decide`. Empty when the detection fails.
+fn detect_on(state: &mut WhisperState, samples: &[f32], n_threads: usize) -> Vec<LanguageGuess> {
+    let detected = state
+        .pcm_to_mel(
+            &samples[..samples.len().min(LANG_DETECT_MAX_SAMPLES)],
+            n_threads,
+        )
+        .and_then(|()| state.lang_detect(0, n_threads));
+    match detected {
+        Ok((_, probs)) => top_languages(&probs, DETECTION_CANDIDATES, whisper_rs::get_lang_str),
+        Err(e) => {
+            tracing::warn!("Language detection failed, leaving it to whisper: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// `run_full`, in overlapping windows when the recording is longer than
+/// Whisper's 30 s context (see `whisper::chunking`), or in windows of
+/// `chunk_secs` whatever its length when set. Each window gets the
+/// previous window's text as prompt context; progress spans all
+/// windows.
+fn run_windowed(
+    pass: &mut Pass<'_>,
+    decode: DecodeParams,
+    samples: &[i16],
+    chunk_secs: Option<usize>,
+    on_progress: Option<ProgressCallback>,
+) -> Result<TranscriptionResult, WhisperError> {
+    let windows = match chunk_secs {
+        Some(secs) => chunking::windows_of(samples.len(), secs),
+        None => chunking::windows(samples.len()),
+    };
+    if windows.len() == 1 {
+        return run_full(
+            pass.ctx,
+            pass.state,
+            pass.config,
+            decode,
+            samples,
+            on_progress,
+            pass.cancel,
         );
+    }
+    let count = windows.len();
+    tracing::info!(
+        "Long recording ({:.0}s): decoding {} overlapping windows",
+        samples.len() as f32 / 16000.0,
+        count
+    );
+    let on_progress = on_progress.map(|callback| Arc::new(Mutex::new(callback)));
+    let mut config = pass.config.clone();
+    let mut decoded = Vec::with_capacity(count);
+    let mut dropped_segments = 0;
+    let mut tokens = 0;
+    let mut language = None;
+    let mut used_decode = decode;
+    for (i, range) in windows.into_iter().enumerate() {
+        let window_progress = on_progress.clone().map(|callback| -> ProgressCallback {
+            Box::new(move |percent| {
+                let overall = (i * 100 + usize::from(percent)) / count;
+                (callback.lock())(overall as u8);
+            })
+        });
+        let result = run_full(
+            pass.ctx,
+            pass.state,
+            &config,
+            decode,
+            &samples[range.clone()],
+            window_progress,
+            pass.cancel,
+        )?;
+        dropped_segments += result.dropped_segments;
+        tokens += result.tokens;
+        language = language.or(result.language);
+        used_decode = result.decode;
+        config.prompt_context = result.text;
+        decoded.push(WindowSegments {
+            offset_ms: chunking::offset_ms(range.start),
+            segments: result.segments,
+        });
+    }
+
+    let segments = chunking::stitch(decoded);
+    let confidence = chunking::overall_confidence(&segments);
+    let mut result = TranscriptionResult::from_segments(segments, used_decode);
+    result.dropped_segments = dropped_segments;
+    result.confidence = confidence;
+    result.tokens = tokens;
+    result.language = language;
+    Ok(result)
+}
+
+/// What every window of a decode shares: the model, the decoding state
+/// it runs on, the settings, and the token that stops it.
+struct Pass<'a> {
+    ctx: &'a WhisperContext,
+    state: &'a mut WhisperState,
+    config: &'a WhisperConfig,
+    cancel: Option<&'a CancelToken>,
+}
+
+/// Decode `samples` on `state`. Shared by the engine's own pass (cached
+/// state) and detached (streaming partial) passes (their own state).
+fn run_full(
+    ctx: &WhisperContext,
+    state: &mut WhisperState,
+    config: &WhisperConfig,
+    mut decode: DecodeParams,
+    samples: &[i16],
+    on_progress: Option<ProgressCallback>,
+    cancel: Option<&CancelToken>,
+) -> Result<TranscriptionResult, WhisperError> {
+    if samples.is_empty() {
+        return Err(WhisperError::InvalidAudio);
+    }
+
+    // An English-only model asked to translate just hallucinates
+    // English-sounding noise from non-English audio. Refuse instead.
+    if config.translate && !ctx.is_multilingual() {
+        return Err(WhisperError::TranscriptionError(
+            ENGLISH_ONLY_TRANSLATE_ERROR.to_string(),
+        ));
+    }
+
+    // Convert i16 samples to f32 (whisper-rs expects f32)
+    let samples_f32: Vec<f32> = samples
+        .iter()
+        .map(|&s| s as f32 / i16::MAX as f32)
+        .collect();
+
+    tracing::info!(
+        "Transcribing {} samples ({:.2}s) — language: {}",
+        samples.len(),
+        samples.len() as f32 / 16000.0,
+        config.language.as_deref().unwrap_or("auto-detect")
+    );
+
+    // Create transcription parameters from the layered decode
+    // settings (see `whisper::decode` for the precedence order).
+    if !config.suppress_non_speech {
+        decode.suppress_nst = false;
+    }
+    tracing::debug!("Decode parameters: {:?}", decode);
+    let strategy = match decode.strategy {
+        DecodeStrategy::Greedy => SamplingStrategy::Greedy {
+            best_of: decode.best_of as i32,
+        },
+        DecodeStrategy::BeamSearch => SamplingStrategy::BeamSearch {
+            beam_size: decode.beam_size as i32,
+            // -1.0 = whisper.cpp default patience
+            patience: decode.patience,
+        },
+    };
+    // Settle the language up front, so the probabilities behind an
+    // auto-detect are on record (see `whisper::language`). whisper.cpp
+    // runs the same detection on the first window when left on auto.
+    let mut language = LanguageDecision::decide(
+        config.language.as_deref(),
+        ctx.is_multilingual(),
+        samples.len() as u64 * 1000 / 16_000,
+        || detect_on(state, &samples_f32, config.n_threads.max(1) as usize),
+    );
+    let mut params = FullParams::new(strategy);
+    // `None` (detection failed) leaves it to whisper's own auto-detect
+    params.set_language(language.language.as_deref());
 
-        // Create transcription parameters
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_translate(config.translate);
+    params.set_n_threads(config.n_threads);
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+    // The state is reused: never let the previous utterance's tokens
+    // leak in as context. Carry-over goes through the initial prompt.
+    params.set_no_context(true);
 
-        // Set language
-        if let Some(ref lang) = self.config.language {
-            params.set_language(Some(lang));
+    // Anti-hallucination tuning. Whisper is known to insert plausible but
+    // unspoken words on silence or low-energy audio. Deterministic decoding
+    // and the various filters below reduce that significantly. The
+    // temperature fallback (`temperature_inc` + thresholds) is what
+    // breaks repetition loops; its knobs come from the user's
+    // advanced decoding settings, defaulting to whisper.cpp's values.
+    let fallback = config.advanced;
+    params.set_temperature(decode.temperature);
+    params.set_temperature_inc(fallback.temperature_inc);
+    params.set_entropy_thold(fallback.entropy_thold);
+    params.set_logprob_thold(fallback.logprob_thold);
+    // NOTE: `set_no_speech_thold` is documented upstream as "Currently
+    // (as of v1.3.0) not implemented" — it's a no-op at the engine
+    // level. Kept here defensively so we don't silently break if/when
+    // upstream wires it back on. The actual no-speech filtering for us
+    // happens post-decode via `segment.no_speech_probability()` below.
+    params.set_no_speech_thold(config.no_speech_threshold);
+    params.set_suppress_blank(true);
+    // Drop bracketed/parenthesised non-speech tokens like [Music],
+    // [Applause], (typing), (sigh) that whisper inherits from its
+    // subtitle/podcast training data. Useless noise for a dictation
+    // tool — users would otherwise have to delete them by hand.
+    // See https://github.com/openai/whisper/blob/7858aa9c08d98f75575035ecd6481f462d66ca27/whisper/tokenizer.py#L224-L253
+    params.set_suppress_nst(decode.suppress_nst);
+    // whisper.cpp keeps at most n_text_ctx / 2 prompt tokens.
+    let initial_prompt = prompt::assemble_prompt(
+        &config.initial_prompt,
+        &config.prompt_context,
+        ctx.n_text_ctx().max(0) as usize / 2,
+        |text| {
+            ctx.tokenize(text, text.len() + 1)
+                .map_or(usize::MAX, |tokens| tokens.len())
+        },
+    );
+    if let Some(initial_prompt) = &initial_prompt {
+        tracing::debug!("Initial prompt: {} chars", initial_prompt.chars().count());
+        params.set_initial_prompt(initial_prompt);
+    }
+    if let Some(callback) = on_progress {
+        params.set_progress_callback_safe(progress::throttled(callback));
+    }
+    if let Some(cancel) = cancel.cloned() {
+        params.set_abort_callback_safe(move || cancel.is_cancelled());
+    }
+
+    // Run transcription
+    state.full(params, &samples_f32).map_err(|e| {
+        if cancel.is_some_and(CancelToken::is_cancelled) {
+            WhisperError::Cancelled
         } else {
-            params.set_language(None); // Auto-detect
+            WhisperError::TranscriptionError(format!("Transcription failed: {}", e))
         }
+    })?;
 
-        params.set_translate(self.config.translate);
-        params.set_n_threads(self.config.n_threads);
-        params.set_print_special(false);
-        params.set_print_progress(false);
-        params.set_print_realtime(false);
-        params.set_print_timestamps(false);
-
-        // Anti-hallucination tuning. Whisper is known to insert plausible but
-        // unspoken words on silence or low-energy audio. Deterministic decoding
-        // and the various filters below reduce that significantly.
-        params.set_temperature(0.0);
-        params.set_temperature_inc(0.0);
-        // NOTE: `set_no_speech_thold` is documented upstream as "Currently
-        // (as of v1.3.0) not implemented" — it's a no-op at the engine
-        // level. Kept here defensively so we don't silently break if/when
-        // upstream wires it back on. The actual no-speech filtering for us
-        // happens post-decode via `segment.no_speech_probability()` below.
-        params.set_no_speech_thold(0.6);
-        params.set_suppress_blank(true);
-        // Drop bracketed/parenthesised non-speech tokens like [Music],
-        // [Applause], (typing), (sigh) that whisper inherits from its
-        // subtitle/podcast training data. Useless noise for a dictation
-        // tool — users would otherwise have to delete them by hand.
-        // See https://github.com/openai/whisper/blob/7858aa9c08d98f75575035ecd6481f462d66ca27/whisper/tokenizer.py#L224-L253
-        params.set_suppress_nst(true);
-
-        // Create a new state for this transcription
-        let mut state = ctx.create_state().map_err(|e| {
-            WhisperError::TranscriptionError(format!("Failed to create state: {}", e))
-        })?;
+    // Get the transcription result. whisper-rs 0.16 reshuffled the
+    // segment API: `full_n_segments()` now returns i32 directly (no
+    // Result), and `full_get_segment_text(i)` was replaced by
+    // `get_segment(i)` returning `Option<WhisperSegment>`, with text
+    // accessed via `.to_str()`.
+    let num_segments = state.full_n_segments();
+    if language.language.is_none() {
+        language.language =
+            whisper_rs::get_lang_str(state.full_lang_id_from_state()).map(String::from);
+    }
 
-        // Run transcription
-        state.full(params, &samples_f32).map_err(|e| {
-            WhisperError::TranscriptionError(format!("Transcription failed: {}", e))
-        })?;
+    // Threshold for the post-decode no-speech filter, user-tunable via
+    // `set_no_speech_threshold` (see `DEFAULT_NO_SPEECH_THRESHOLD`).
+    let no_speech_threshold = config.no_speech_threshold;
+    let mut dropped_segments = 0u32;
+    // Ids from end-of-text up are special tokens, not text.
+    let token_eot = ctx.token_eot();
+    let mut total_tokens = TokenStats::default();
 
-        // Get the transcription result. whisper-rs 0.16 reshuffled the
-        // segment API: `full_n_segments()` now returns i32 directly (no
-        // Result), and `full_get_segment_text(i)` was replaced by
-        // `get_segment(i)` returning `Option<WhisperSegment>`, with text
-        // accessed via `.to_str()`.
-        let num_segments = state.full_n_segments();
-
-        // Threshold for the post-decode no-speech filter. Aligned with
-        // whisper.cpp's own `no_speech_thold` default (0.6) so if upstream
-        // ever implements the in-engine path the two layers won't disagree.
-        // Field-tunable: bump to 0.7 if we observe legitimate quiet speech
-        // getting dropped; never below 0.5 (too permissive, lets ghosts
-        // through).
-        const NO_SPEECH_THRESHOLD: f32 = 0.6;
-
-        let mut result = String::new();
-        for i in 0..num_segments {
-            if let Some(segment) = state.get_segment(i) {
-                let no_speech_prob = segment.no_speech_probability();
-                if no_speech_prob > NO_SPEECH_THRESHOLD {
-                    tracing::debug!(
-                        "Dropping segment {i} as non-speech (p={:.2})",
-                        no_speech_prob
-                    );
+    let mut segments = Vec::new();
+    for i in 0..num_segments {
+        if let Some(segment) = state.get_segment(i) {
+            let no_speech_prob = segment.no_speech_probability();
+            if no_speech_prob > no_speech_threshold {
+                tracing::debug!(
+                    "Dropping segment {i} as non-speech (p={:.2})",
+                    no_speech_prob
+                );
+                dropped_segments += 1;
+                continue;
+            }
+            if let Ok(text) = segment.to_str() {
+                // Token suppression doesn't catch annotations spelled
+                // out in ordinary tokens; strip those too.
+                let text = if config.suppress_non_speech {
+                    annotations::strip_annotations(text.trim())
+                } else {
+                    text.trim().to_string()
+                };
+                if text.is_empty() {
                     continue;
                 }
-                if let Ok(text) = segment.to_str() {
-                    result.push_str(text);
-                    result.push(' ');
+                let mut tokens = TokenStats::default();
+                for t in 0..segment.n_tokens() {
+                    if let Some(token) = segment.get_token(t) {
+                        if token.token_id() < token_eot {
+                            tokens.push(token.token_probability());
+                        }
+                    }
                 }
+                total_tokens.merge(tokens);
+                // Timestamps come back in centiseconds (whisper.cpp's
+                // t0/t1 units); negative values never happen in
+                // practice but clamp rather than wrap.
+                segments.push(Segment {
+                    start_ms: segment.start_timestamp().max(0) as u64 * 10,
+                    end_ms: segment.end_timestamp().max(0) as u64 * 10,
+                    text,
+                    confidence: tokens.confidence(),
+                });
             }
         }
+    }
 
-        let result = result.trim().to_string();
-        tracing::info!("Transcription complete: \"{}\"", result);
+    let mut result = TranscriptionResult::from_segments(segments, decode);
+    result.dropped_segments = dropped_segments;
+    result.confidence = total_tokens.confidence();
+    result.tokens = total_tokens.count();
+    result.language = Some(language);
+    tracing::info!(
+        "Transcription complete: {} chars ({} segments, {} tokens)",
+        result.text.chars().count(),
+        result.segments.len(),
+        result.tokens
+    );
 
-        Ok(result)
-    }
+    Ok(result)
 }
 
 impl Default for WhisperEngine {
@@ -329,62 +1136,345 @@ impl Default for WhisperEngine {
     }
 }
 
-/// Thread-safe wrapper for WhisperEngine
+impl JobEngine for WhisperEngine {
+    fn run(
+        &mut self,
+        samples: &[i16],
+        params: &JobParams,
+        on_progress: Option<ProgressCallback>,
+        cancel: &CancelToken,
+    ) -> Result<TranscriptionResult, WhisperError> {
+        self.transcribe_job(samples, params, on_progress, Some(cancel))
+    }
+
+    /// The model's weights are only read while decoding; the reused
+    /// decoding state is what a panic can leave mid-run. It is recreated
+    /// on demand.
+    fn recover(&mut self) {
+        self.state = None;
+    }
+}
+
+/// Refuse a draft model whose file is over this share of the available
+/// RAM (the main model is already resident). Same heuristic as the
+/// import pre-flight in `compat`.
+const DRAFT_MAX_RAM_PERCENT: u64 = 60;
+
+/// Whether a draft model of `model_bytes` fits next to the main one.
+/// `available_bytes` of 0 means unknown and lets it through.
+fn draft_fits_in_memory(model_bytes: u64, available_bytes: u64) -> bool {
+    available_bytes == 0
+        || model_bytes.saturating_mul(100) <= available_bytes.saturating_mul(DRAFT_MAX_RAM_PERCENT)
+}
+
+/// Thread-safe wrapper for WhisperEngine.
+///
+/// The engine lives on the job thread (see `whisper::jobs`): loads,
+/// settings and transcriptions are all messages to it, handled in
+/// order. Callers read `view` instead, a state-less copy the setters
+/// update right away and the thread refreshes after each load, so
+/// asking which model is loaded never waits on a transcription.
 pub struct WhisperWorker {
-    engine: Arc<Mutex<WhisperEngine>>,
+    view: Arc<RwLock<WhisperEngine>>,
+    jobs: Arc<JobRunner<WhisperEngine>>,
+    /// Optional small model for an instant draft before the final pass.
+    /// Its own engine (and lock), so both passes run side by side.
+    draft: Arc<Mutex<Option<WhisperEngine>>>,
 }
 
 impl WhisperWorker {
     pub fn new() -> Self {
+        let engine = WhisperEngine::new();
         Self {
-            engine: Arc::new(Mutex::new(WhisperEngine::new())),
+            view: Arc::new(RwLock::new(engine.snapshot())),
+            jobs: Arc::new(JobRunner::spawn(engine)),
+            draft: Arc::new(Mutex::new(None)),
         }
     }
 
-    /// Load a model (thread-safe)
-    pub fn load_model(&self, model_path: PathBuf) -> Result<(), WhisperError> {
-        self.engine.lock().load_model(model_path)
+    /// Load the draft model, replacing any previous one. Refused when the
+    /// file would not comfortably fit in the available memory.
+    pub fn load_draft_model(&self, model_path: PathBuf) -> Result<ModelLoadResult, WhisperError> {
+        let model_bytes = std::fs::metadata(&model_path)
+            .map_err(|_| WhisperError::ModelNotFound(model_path.display().to_string()))?
+            .len();
+        // Free the old draft first so it doesn't count against the new one
+        self.unload_draft_model();
+        let mut sys = sysinfo::System::new();
+        sys.refresh_memory();
+        let available = sys.available_memory();
+        if !draft_fits_in_memory(model_bytes, available) {
+            return Err(WhisperError::LoadError(LoadFailure::new(
+                LoadFailureKind::OutOfMemory,
+                format!(
+                    "Not enough memory for a draft model ({} MB, {} MB available)",
+                    model_bytes / 1_048_576,
+                    available / 1_048_576
+                ),
+            )));
+        }
+
+        let mut engine = WhisperEngine::new();
+        engine.set_gpu_device(self.view.read().gpu_device);
+        let result = engine.load_model_with_options(model_path, false, false, false)?;
+        *self.draft.lock() = Some(engine);
+        Ok(result)
     }
 
-    /// Load a model with explicit CPU/GPU control (thread-safe)
-    pub fn load_model_with_options(
+    pub fn unload_draft_model(&self) {
+        if self.draft.lock().take().is_some() {
+            tracing::info!("Draft model unloaded");
+        }
+    }
+
+    pub fn has_draft_model(&self) -> bool {
+        self.draft.lock().is_some()
+    }
+
+    /// Transcribe with the draft model, using the main engine's settings
+    /// (language, prompt, decoding). `None` when no draft model is loaded.
+    /// Decodes on a copy of the draft engine, so loading or dropping the
+    /// draft model meanwhile doesn't wait for it.
+    pub fn transcribe_draft(
+        &self,
+        samples: &[i16],
+    ) -> Option<Result<TranscriptionResult, WhisperError>> {
+        let config = self.view.read().config.clone();
+        let mut engine = {
+            let mut draft = self.draft.lock();
+            let draft = draft.as_mut()?;
+            let mut engine = draft.snapshot();
+            engine.state = draft.state.take();
+            engine
+        };
+        engine.config = WhisperConfig {
+            model_path: engine.config.model_path.clone(),
+            ..config
+        };
+        let result = engine.transcribe_job(samples, &JobParams::default(), None, None);
+        // The decoding state goes back for the next draft, unless the
+        // draft model changed in the meantime
+        if let (Some(state), Some(draft)) = (engine.state.take(), self.draft.lock().as_mut()) {
+            let same_model = draft
+                .context
+                .as_ref()
+                .zip(engine.context.as_ref())
+                .is_some_and(|(current, used)| Arc::ptr_eq(current, used));
+            if same_model && draft.state.is_none() {
+                draft.state = Some(state);
+            }
+        }
+        Some(result)
+    }
+
+    /// Queue a transcription on the job thread. Await the handle for
+    /// the result; its progress stream and `cancel` work meanwhile.
+    /// Refused with `QueueError::Busy` when the queue is full.
+    pub fn submit(&self, samples: Arc<[i16]>, params: JobParams) -> Result<JobHandle, QueueError> {
+        self.jobs.submit(samples, params)
+    }
+
+    /// Transcriptions queued or running.
+    pub fn queue_length(&self) -> usize {
+        self.jobs.pending()
+    }
+
+    /// Most transcriptions that may be pending at once.
+    pub fn queue_depth(&self) -> usize {
+        self.jobs.max_depth()
+    }
+
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.jobs.set_max_depth(depth);
+    }
+
+    /// Load a model with explicit CPU/GPU and flash attention control,
+    /// on the job thread once the transcriptions queued ahead are done.
+    pub async fn load_model_with_options(
         &self,
         model_path: PathBuf,
         force_cpu: bool,
+        flash_attention: bool,
+        warmup: bool,
     ) -> Result<ModelLoadResult, WhisperError> {
-        self.engine
-            .lock()
-            .load_model_with_options(model_path, force_cpu)
+        let view = Arc::clone(&self.view);
+        self.jobs
+            .call(move |engine| {
+                let result =
+                    engine.load_model_with_options(model_path, force_cpu, flash_attention, warmup);
+                engine.publish_model(&mut *view.write());
+                result
+            })
+            .await
+            .unwrap_or_else(|_| Err(jobs::runner_gone()))
+    }
+
+    /// Unload the model on the job thread, once the transcriptions
+    /// queued ahead are done.
+    pub async fn unload_model(&self) -> Result<(), WhisperError> {
+        let view = Arc::clone(&self.view);
+        self.jobs
+            .call(move |engine| {
+                engine.unload_model();
+                engine.publish_model(&mut *view.write());
+                tracing::info!("Whisper model unloaded");
+            })
+            .await
+            .map_err(|_| jobs::runner_gone())
+    }
+
+    /// Apply a settings change to the view now and to the engine
+    /// after the work already queued.
+    fn configure(&self, change: impl Fn(&mut WhisperEngine) + Send + 'static) {
+        change(&mut *self.view.write());
+        self.jobs.post(change);
+    }
+
+    /// Stop the job thread once the queued work is done (app exit).
+    pub fn shutdown(&self) {
+        self.jobs.shutdown();
+    }
+
+    /// Select the GPU used by the next model load (thread-safe)
+    pub fn set_gpu_device(&self, device: Option<u32>) {
+        self.configure(move |e| e.set_gpu_device(device));
     }
 
     /// Set language (thread-safe)
     pub fn set_language(&self, language: Option<String>) {
-        self.engine.lock().set_language(language);
+        self.configure(move |e| e.set_language(language.clone()));
+    }
+
+    /// Set the global decode settings (thread-safe)
+    pub fn set_decode_advanced(&self, advanced: DecodeOverride) {
+        self.configure(move |e| e.set_decode_advanced(advanced.clone()));
+    }
+
+    /// Enable or disable non-speech suppression (thread-safe)
+    pub fn set_suppress_non_speech(&self, enabled: bool) {
+        self.configure(move |e| e.set_suppress_non_speech(enabled));
+    }
+
+    /// Set the configured prompt and carried-over context (thread-safe)
+    pub fn set_prompt(&self, initial_prompt: String, context: String) {
+        self.configure(move |e| e.set_prompt(initial_prompt.clone(), context.clone()));
+    }
+
+    /// Set the no-speech threshold (thread-safe)
+    pub fn set_no_speech_threshold(&self, threshold: f32) {
+        self.configure(move |e| e.set_no_speech_threshold(threshold));
+    }
+
+    /// Set the temperature fallback settings (thread-safe)
+    pub fn set_advanced_decoding(&self, advanced: AdvancedDecoding) {
+        self.configure(move |e| e.set_advanced_decoding(advanced.clone()));
+    }
+
+    /// Replace the per-language decode overrides (thread-safe)
+    pub fn set_decode_overrides(&self, overrides: HashMap<String, DecodeOverride>) {
+        self.configure(move |e| e.set_decode_overrides(overrides.clone()));
+    }
+
+    /// Enable or disable translation to English (thread-safe)
+    pub fn set_translate(&self, translate: bool) {
+        self.configure(move |e| e.set_translate(translate));
+    }
+
+    /// Enable or disable deterministic mode (thread-safe)
+    pub fn set_deterministic(&self, deterministic: bool) {
+        self.configure(move |e| e.set_deterministic(deterministic));
+    }
+
+    /// Whether translation to English is enabled (thread-safe)
+    pub fn is_translating(&self) -> bool {
+        self.view.read().is_translating()
+    }
+
+    /// Whether the loaded model is multilingual (thread-safe)
+    pub fn is_multilingual(&self) -> Option<bool> {
+        self.view.read().is_multilingual()
+    }
+
+    /// Describe the loaded model (thread-safe)
+    pub fn model_info(&self) -> Result<ModelInfo, ModelInfoError> {
+        self.view.read().model_info()
+    }
+
+    /// Footprint of the loaded model (thread-safe)
+    pub fn model_memory(&self) -> Result<ModelMemory, ModelInfoError> {
+        self.view.read().model_memory()
     }
 
     /// Check if model is loaded (thread-safe)
     pub fn is_loaded(&self) -> bool {
-        self.engine.lock().is_loaded()
+        self.view.read().is_loaded()
     }
 
     /// Check if GPU is being used (thread-safe)
     pub fn is_using_gpu(&self) -> bool {
-        self.engine.lock().is_using_gpu()
+        self.view.read().is_using_gpu()
     }
 
     /// Check if fallback was used (thread-safe)
     pub fn was_fallback_used(&self) -> bool {
-        self.engine.lock().was_fallback_used()
+        self.view.read().was_fallback_used()
     }
 
     /// Get current backend name (thread-safe)
     pub fn get_backend_name(&self) -> String {
-        self.engine.lock().get_backend_name()
+        self.view.read().get_backend_name()
+    }
+
+    /// Transcribe with a second whisper state, on the calling thread,
+    /// with the model and config of the view. Used for streaming
+    /// partials so they never wait for the final pass.
+    pub fn transcribe_detached(
+        &self,
+        samples: &[i16],
+    ) -> Result<TranscriptionResult, WhisperError> {
+        let (ctx, config, decode) = {
+            let engine = self.view.read();
+            let ctx = engine.context.clone().ok_or(WhisperError::NotLoaded)?;
+            (ctx, engine.config.clone(), engine.resolve_decode_params())
+        };
+        let mut state = new_state(&ctx)?;
+        run_full(&ctx, &mut state, &config, decode, samples, None, None)
     }
 
-    /// Transcribe samples (thread-safe)
-    pub fn transcribe(&self, samples: &[i16]) -> Result<String, WhisperError> {
-        self.engine.lock().transcribe(samples)
+    /// Run whisper's language auto-detection on `samples` (only the first
+    /// 30 s count) and return the `top_n` most likely languages. Uses its
+    /// own state, like `transcribe_detached`.
+    pub fn detect_language(
+        &self,
+        samples: &[i16],
+        top_n: usize,
+    ) -> Result<Vec<LanguageGuess>, LanguageDetectError> {
+        if samples.is_empty() {
+            return Err(LanguageDetectError::NoAudio);
+        }
+        let (ctx, n_threads) = {
+            let engine = self.view.read();
+            let ctx = engine
+                .context
+                .clone()
+                .ok_or(LanguageDetectError::NotLoaded)?;
+            (ctx, engine.config.n_threads.max(1) as usize)
+        };
+        if !ctx.is_multilingual() {
+            return Err(LanguageDetectError::EnglishOnly);
+        }
+        let failed = |e: whisper_rs::WhisperError| LanguageDetectError::Failed {
+            message: e.to_string(),
+        };
+
+        let samples_f32: Vec<f32> = samples[..samples.len().min(LANG_DETECT_MAX_SAMPLES)]
+            .iter()
+            .map(|&s| s as f32 / i16::MAX as f32)
+            .collect();
+        let mut state = ctx.create_state().map_err(failed)?;
+        state.pcm_to_mel(&samples_f32, n_threads).map_err(failed)?;
+        let (_, probs) = state.lang_detect(0, n_threads).map_err(failed)?;
+        Ok(top_languages(&probs, top_n, whisper_rs::get_lang_str))
     }
 }
 
@@ -397,7 +1487,9 @@ impl Default for WhisperWorker {
 impl Clone for WhisperWorker {
     fn clone(&self) -> Self {
         Self {
-            engine: Arc::clone(&self.engine),
+            view: Arc::clone(&self.view),
+            jobs: Arc::clone(&self.jobs),
+            draft: Arc::clone(&self.draft),
         }
     }
 }
@@ -406,21 +1498,216 @@ impl Clone for WhisperWorker {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_top_languages() {
+        let names = |id: i32| ["en", "de", "fr"].get(id as usize).copied();
+        let guesses = top_languages(&[0.1, 0.05, 0.8, 0.05], 2, names);
+        assert_eq!(
+            guesses,
+            vec![
+                LanguageGuess {
+                    code: "fr".into(),
+                    probability: 0.8
+                },
+                LanguageGuess {
+                    code: "en".into(),
+                    probability: 0.1
+                },
+            ]
+        );
+        // Ids without a name are skipped, not counted
+        let guesses = top_languages(&[0.1, 0.0, 0.0, 0.9], 1, names);
+        assert_eq!(guesses[0].code, "en");
+    }
+
+    #[test]
+    fn test_draft_memory_check() {
+        const MB: u64 = 1_048_576;
+        assert!(draft_fits_in_memory(75 * MB, 8_000 * MB));
+        assert!(draft_fits_in_memory(600 * MB, 1_000 * MB));
+        assert!(!draft_fits_in_memory(601 * MB, 1_000 * MB));
+        // Unknown available memory doesn't block
+        assert!(draft_fits_in_memory(3_000 * MB, 0));
+    }
+
+    #[test]
+    fn test_draft_without_model() {
+        let worker = WhisperWorker::new();
+        assert!(!worker.has_draft_model());
+        assert!(worker.transcribe_draft(&[0; 16]).is_none());
+        assert!(matches!(
+            worker.load_draft_model(PathBuf::from("/nonexistent/ggml-tiny.bin")),
+            Err(WhisperError::ModelNotFound(_))
+        ));
+    }
+
     #[test]
     fn test_config_default() {
         let config = WhisperConfig::default();
         assert!(config.language.is_none());
         assert!(!config.translate);
+        assert!(config.suppress_non_speech);
         // n_threads is now dynamic (75% of CPUs), just ensure it's at least 1
         assert!(config.n_threads >= 1);
     }
 
     #[test]
     fn test_engine_not_loaded() {
-        let engine = WhisperEngine::new();
+        let mut engine = WhisperEngine::new();
         assert!(!engine.is_loaded());
 
-        let result = engine.transcribe(&[0i16; 1000]);
+        let result = engine.transcribe_job(&[0i16; 1000], &JobParams::default(), None, None);
         assert!(matches!(result, Err(WhisperError::NotLoaded)));
     }
+
+    /// Needs a real model:
+    /// `S2TUI_TEST_MODEL=models/ggml-base.bin cargo test -- --ignored --nocapture state_reuse`.
+    /// Checks that later transcriptions (state reused) are no slower
+    /// than the first (state allocated).
+    #[test]
+    #[ignore = "needs a model file (S2TUI_TEST_MODEL)"]
+    fn test_state_reuse_latency() {
+        let Ok(path) = std::env::var("S2TUI_TEST_MODEL") else {
+            return;
+        };
+        let mut engine = WhisperEngine::new();
+        engine
+            .load_model_with_options(PathBuf::from(path), false, false, false)
+            .unwrap();
+        // One second of low-level noise: enough for a full decode pass.
+        let samples: Vec<i16> = (0..16000)
+            .map(|i| ((i * 7919) % 200) as i16 - 100)
+            .collect();
+        let timed = |engine: &mut WhisperEngine| {
+            let start = std::time::Instant::now();
+            engine
+                .transcribe_job(&samples, &JobParams::default(), None, None)
+                .unwrap();
+            start.elapsed()
+        };
+
+        // Cold path: what every call paid before the state was cached.
+        engine.state = None;
+        let first = timed(&mut engine);
+        assert!(engine.state.is_some(), "state kept after a clean run");
+        let later: Vec<_> = (0..3).map(|_| timed(&mut engine)).collect();
+        // A quarter over the first, for scheduling noise
+        for duration in &later {
+            assert!(
+                *duration <= first + first / 4,
+                "first transcription: {first:?}, subsequent: {later:?}"
+            );
+        }
+
+        // A failed run drops the state; the next one recreates it.
+        assert!(engine
+            .transcribe_job(&[], &JobParams::default(), None, None)
+            .is_err());
+        assert!(engine.state.is_none());
+        timed(&mut engine);
+        assert!(engine.state.is_some());
+    }
+
+    /// Needs the tiny model:
+    /// `S2TUI_TEST_MODEL=models/ggml-tiny.bin cargo test -- --ignored deterministic_runs`.
+    #[test]
+    #[ignore = "needs a model file (S2TUI_TEST_MODEL)"]
+    fn test_deterministic_runs_match() {
+        let Ok(path) = std::env::var("S2TUI_TEST_MODEL") else {
+            return;
+        };
+        let mut engine = WhisperEngine::new();
+        engine
+            .load_model_with_options(PathBuf::from(path), false, false, false)
+            .unwrap();
+        engine.set_deterministic(true);
+        // Three seconds of a vowel-like tone with a slow pitch glide,
+        // over low-level noise: gives the decoder something to chew on.
+        let samples: Vec<i16> = (0..48_000)
+            .map(|i| {
+                let t = i as f32 / 16_000.0;
+                let pitch = 140.0 + 40.0 * t;
+                let voice = (t * pitch * std::f32::consts::TAU).sin()
+                    + 0.5 * (t * pitch * 2.0 * std::f32::consts::TAU).sin();
+                (voice * 6_000.0) as i16 + ((i * 7919) % 200) as i16 - 100
+            })
+            .collect();
+
+        let first = engine
+            .transcribe_job(&samples, &JobParams::default(), None, None)
+            .unwrap();
+        // Carry-over and the decoding settings don't reach the decode
+        engine.set_prompt(String::new(), "Unrelated earlier dictation.".into());
+        engine.set_decode_advanced(DecodeOverride {
+            temperature: Some(0.6),
+            ..DecodeOverride::default()
+        });
+        let second = engine
+            .transcribe_job(&samples, &JobParams::default(), None, None)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&first).unwrap(),
+            serde_json::to_string(&second).unwrap()
+        );
+        let reproducibility = second.reproducibility.unwrap();
+        assert!(reproducibility.params.deterministic);
+        assert_eq!(
+            reproducibility.params.n_threads,
+            deterministic::DETERMINISTIC_THREADS
+        );
+        assert_eq!(
+            reproducibility.samples_sha256,
+            deterministic::samples_sha256(&samples)
+        );
+    }
+
+    #[test]
+    fn test_model_info_not_loaded() {
+        let engine = WhisperEngine::new();
+        let err = engine.model_info().unwrap_err();
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({ "kind": "notLoaded" })
+        );
+    }
+
+    #[test]
+    fn test_result_joins_segment_text() {
+        let result = TranscriptionResult::from_segments(
+            vec![
+                Segment {
+                    start_ms: 0,
+                    end_ms: 1200,
+                    text: " Hello there.".into(),
+                    confidence: None,
+                },
+                Segment {
+                    start_ms: 1200,
+                    end_ms: 1500,
+                    text: "  ".into(),
+                    confidence: None,
+                },
+                Segment {
+                    start_ms: 1500,
+                    end_ms: 3100,
+                    text: "General Kenobi. ".into(),
+                    confidence: None,
+                },
+            ],
+            DecodeParams::default(),
+        );
+        assert_eq!(result.text, "Hello there. General Kenobi.");
+        assert_eq!(result.segments.len(), 3);
+        assert_eq!(result.segments[2].start_ms, 1500);
+    }
+
+    #[test]
+    fn test_translate_toggle_without_model() {
+        let mut engine = WhisperEngine::new();
+        assert!(!engine.is_translating());
+        engine.set_translate(true);
+        assert!(engine.is_translating());
+        // No model → can't tell whether it's multilingual yet.
+        assert!(engine.is_multilingual().is_none());
+    }
 }
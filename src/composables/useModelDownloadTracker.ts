@@ -1,5 +1,5 @@
 import { onScopeDispose } from "vue";
-import { invoke } from "@tauri-apps/api/core";
+import { invoke } from "../utils/invoke";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { useAppStore, type ModelId } from "../stores/appStore";
 
@@ -88,7 +88,9 @@ export function useModelDownloadTracker() {
       }),
     );
     unlistens.push(
-      await listen<{ model: string; message: string }>(
+      // `error` is the structured `DownloadError` ({ kind, ... });
+      // `message` its display text.
+      await listen<{ model: string; message: string; error?: { kind: string } }>(
         "model:download:error",
         (e) => {
           store.upsertModelDownloadItem(e.payload.model, {
@@ -1,7 +1,13 @@
 import { onScopeDispose } from "vue";
-import { invoke } from "@tauri-apps/api/core";
+import { invoke } from "../utils/invoke";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
-import { useAppStore, type ModelInfo, type Language, type ModelCapabilities } from "../stores/appStore";
+import {
+  useAppStore,
+  type ModelInfo,
+  type Language,
+  type ModelCapabilities,
+  type DecodeOverride,
+} from "../stores/appStore";
 import type { PersistedSettings } from "./useStore";
 
 // One sync listener per window. Subscribes to `settings:changed`
@@ -88,6 +94,52 @@ export function useSettingsSync() {
       languageCycleMode: persisted.languageCycleMode ?? "model-first",
       userModels: persisted.userModels ?? [],
       disabledModels: persisted.disabledModels ?? [],
+      translate: persisted.translate ?? false,
+      vocabulary: persisted.vocabulary ?? [],
+      learnVocabularyShortcut: persisted.learnVocabularyShortcut ?? "",
+      decodeOverrides: (persisted.decodeOverrides as Record<string, DecodeOverride>) ?? {},
+      decodingStrategy: persisted.decodingStrategy ?? null,
+      snippets: persisted.snippets ?? [],
+      advancedDecoding: persisted.advancedDecoding ?? { temperature: 0.0, temperatureInc: 0.2, entropyThold: 2.4, logprobThold: -1.0 },
+      noSpeechThreshold: persisted.noSpeechThreshold ?? 0.6,
+      pttDevice: persisted.pttDevice ?? null,
+      suppressNonSpeech: persisted.suppressNonSpeech ?? true,
+      minSpeechMs: persisted.minSpeechMs ?? 300,
+      forceTranscription: persisted.forceTranscription ?? false,
+      privacyMode: persisted.privacyMode ?? false,
+      streamingPartials: persisted.streamingPartials ?? false,
+      partialIntervalMs: persisted.partialIntervalMs ?? 2000,
+      contextCarryOver: persisted.contextCarryOver ?? false,
+      contextIdleResetSecs: persisted.contextIdleResetSecs ?? 300,
+      clipboardDwellMs: persisted.clipboardDwellMs ?? 1000,
+      clipboardAppend: persisted.clipboardAppend ?? false,
+      gpuDevice: persisted.gpuDevice ?? null,
+      flashAttention: persisted.flashAttention ?? false,
+      transcriptionQueueDepth: persisted.transcriptionQueueDepth ?? 3,
+      transcriptionTimeoutSecs: persisted.transcriptionTimeoutSecs ?? 120,
+      fillerMode: persisted.fillerMode ?? "keep",
+      fillerWords: persisted.fillerWords ?? {},
+      quantization: persisted.quantization ?? "q5_0",
+      modelsDir: persisted.modelsDir ?? "",
+      watchedFolders: persisted.watchedFolders ?? [],
+      retainedAudioMb: persisted.retainedAudioMb ?? 256,
+      deterministicMode: persisted.deterministicMode ?? false,
+      customModelPath: persisted.customModelPath ?? null,
+      preferredDevice: persisted.preferredDevice ?? null,
+      gain: persisted.gain ?? 0,
+      agc: persisted.agc ?? false,
+      audioFilters: persisted.audioFilters ?? { highPass: false },
+      debugSaveRecordings: persisted.debugSaveRecordings ?? false,
+      preRollMs: persisted.preRollMs ?? 0,
+      captureSpillMb: persisted.captureSpillMb ?? 0,
+      channelMode: persisted.channelMode ?? "mix",
+      captureSource: persisted.captureSource ?? "microphone",
+      captureApplication: persisted.captureApplication ?? null,
+      speechThreshold: persisted.speechThreshold ?? 0.02,
+      silenceTimeoutMs: persisted.silenceTimeoutMs ?? 1500,
+      vadBackend: persisted.vadBackend ?? "rms",
+      adaptiveThreshold: persisted.adaptiveThreshold ?? false,
+      noiseFactor: persisted.noiseFactor ?? 3,
     });
     store.setHistory(persisted.history ?? []);
     store.setVulkanWarningDismissed(persisted.vulkanWarningDismissed ?? false);
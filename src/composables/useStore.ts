@@ -1,5 +1,5 @@
-import { invoke } from "@tauri-apps/api/core";
-import type { Settings, HistoryEntry, ModelId } from "../stores/appStore";
+import { invoke } from "../utils/invoke";
+import type { Settings, HistoryEntry, ModelId, TextDiff } from "../stores/appStore";
 
 /// All persistence is now backend-driven (cf. CLAUDE.md "Persisted
 /// state"). This module is a thin wrapper around the Tauri commands
@@ -10,9 +10,9 @@ import type { Settings, HistoryEntry, ModelId } from "../stores/appStore";
 /// call.
 ///
 /// The previous `tauri-plugin-store` JS pathway is gone: the Rust
-/// backend reads/writes `settings.json` itself, atomically inside
-/// every setter command, and emits `settings:changed` so all
-/// windows refresh their Pinia cache. This eliminates the per-slice
+/// backend reads/writes `settings.json` itself (setter commands
+/// schedule a write, coalesced to at most one every 500 ms), and
+/// emits `settings:changed` so all windows refresh their Pinia cache. This eliminates the per-slice
 /// "did I plumb the listener?" bug class.
 
 export interface PersistedSettings extends Settings {
@@ -27,6 +27,12 @@ export async function loadSettings(): Promise<PersistedSettings> {
   return await invoke<PersistedSettings>("get_settings");
 }
 
+/** Write pending settings changes to disk now instead of within the
+ *  next 500 ms. */
+export async function flushSettings(): Promise<void> {
+  await invoke("flush_settings");
+}
+
 /** Read just the history slice. Implemented as a projection of
  *  `loadSettings()` to avoid a duplicate command. The full Settings
  *  payload is small (< 5 KB even with 20 history entries) so the
@@ -43,12 +49,16 @@ export async function addHistoryEntry(
   text: string,
   modelId?: string,
   durationMs?: number,
+  retry = false,
+  diff?: TextDiff | null,
 ): Promise<HistoryEntry> {
   return await invoke<HistoryEntry>("add_history_entry", {
     entry: {
       text,
       modelId: modelId as ModelId | undefined,
       durationMs,
+      retry,
+      diff: diff ?? undefined,
     },
   });
 }
@@ -57,3 +67,35 @@ export async function addHistoryEntry(
 export async function clearHistory(): Promise<void> {
   await invoke("clear_history");
 }
+
+/** History entries containing `query` (case-insensitive), newest
+ *  first. Rejects with `{ kind: "locked" }` while the encrypted
+ *  history is locked. */
+export async function getTranscriptHistory(query?: string): Promise<HistoryEntry[]> {
+  return await invoke<HistoryEntry[]>("get_transcript_history", { query });
+}
+
+/** Encrypt the history at rest: with a key derived from `passphrase`,
+ *  or without one, with a random key kept in the OS keychain. */
+export async function enableHistoryEncryption(passphrase?: string): Promise<void> {
+  await invoke("enable_history_encryption", { passphrase });
+}
+
+export async function disableHistoryEncryption(): Promise<void> {
+  await invoke("disable_history_encryption");
+}
+
+/** Returns how many damaged entries were skipped. Rejects with a
+ *  `HistoryVaultError`. */
+export async function unlockHistory(passphrase?: string): Promise<number> {
+  return await invoke<number>("unlock_history", { passphrase });
+}
+
+export async function lockHistory(): Promise<void> {
+  await invoke("lock_history");
+}
+
+/** Discard an encrypted history that can no longer be unlocked. */
+export async function resetHistory(): Promise<void> {
+  await invoke("reset_history");
+}
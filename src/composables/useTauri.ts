@@ -1,4 +1,4 @@
-import { invoke } from "@tauri-apps/api/core";
+import { invoke } from "../utils/invoke";
 import { listen, emit, type UnlistenFn } from "@tauri-apps/api/event";
 import { WebviewWindow } from "@tauri-apps/api/webviewWindow";
 import {
@@ -9,7 +9,54 @@ import {
   type SystemHealth,
   type GpuStatus,
   type ModelCapabilities,
+  type LoadedModelInfo,
+  type MicUsageEntry,
+  type LanguageGuess,
+  type LanguageReport,
+  type ConfigWarning,
+  type RecentError,
+  type RenderState,
+  type ModelLoadProgress,
+  type LoadFailure,
+  type ModelVerification,
+  type Reproducibility,
+  type DeletedModel,
+  type ImportedModel,
+  type PendingDownload,
+  type WatchedFolder,
+  type WatchFormat,
+  type AvailableModel,
+  type ModelMemory,
+  type MemoryUsage,
+  type ModelRecommendation,
+  type CatalogModel,
+  type AudioDevices,
+  type RateCorrection,
+  type DeviceFallback,
+  type Clipping,
+  type SpillFailure,
+  type SilentInput,
+  type NoiseCalibration,
+  type MonitorLevel,
+  type PauseMark,
+  type Utterance,
+  type InputLost,
+  type DeviceChanged,
+  type AudioFilters,
+  type ChannelMode,
+  type VadBackend,
+  type VadBenchmark,
+  type CaptureSource,
+  type CaptureApplication,
+  type PipelineOutput,
+  type PipelineSettings,
+  type TranscriptionQueueError,
+  type TranscriptTruncated,
+  type DecodeCoverage,
+  type StatusChange,
+  type TextDiff,
   LANGUAGE_DISPLAY_NAMES,
+  LOAD_FAILURE_HINTS,
 } from "../stores/appStore";
 import { loadSettings, addHistoryEntry, loadHistory } from "./useStore";
 import { useModelDownloadTracker } from "./useModelDownloadTracker";
@@ -57,7 +104,7 @@ export async function openSettings() {
   });
 }
 
-type ListenMode = "toggle" | "push-to-talk" | "voice-activated";
+type ListenMode = "toggle" | "push-to-talk" | "voice-activated" | "continuous";
 
 // Module-level flags to prevent duplicate initialization and actions
 let listenersInitialized = false;
@@ -66,21 +113,70 @@ const unlistenFns: UnlistenFn[] = [];
 
 interface VadLevelPayload {
   rms: number;
+  isSpeech: boolean;
+  /** Speech threshold on the same 0–1 scale as `rms`. */
+  threshold: number;
+  aboveThreshold: boolean;
+}
+
+interface TranscriptSegment {
+  startMs: number;
+  endMs: number;
+  text: string;
+  /** 0–1 from token probabilities; null when the segment has no text tokens. */
+  confidence: number | null;
 }
 
 interface TranscriptPayload {
   text: string;
   model?: string;
   transcribeDurationMs?: number;
+  /** Per-segment timeline (final transcripts only). */
+  segments?: TranscriptSegment[];
+  /** Input hash and resolved parameters (final transcripts only). */
+  reproducibility?: Reproducibility | null;
+  /** True when the text was translated to English. */
+  translated?: boolean;
+  /** Segments dropped by the no-speech filter. */
+  droppedSegments?: number;
+  /** Token-weighted confidence of the whole transcript (0–1). */
+  confidence?: number | null;
+  /** Id of the transcription job (final transcripts only). */
+  jobId?: number;
+  /** The first decode looked degenerate (looping) and was retried once. */
+  retried?: boolean;
+  /** Speech goes on past the last segment (see `transcript:truncated`). */
+  possiblyTruncated?: boolean;
+  coverage?: DecodeCoverage;
+  /** Share (0–1) of the samples at or near full scale: distorted input
+   *  when past a thousandth. */
+  clippedRatio?: number;
+  /** Recordings only: where it was paused. */
+  pauses?: PauseMark[];
+  /** Continuous recordings only: which utterance this is. */
+  utterance?: Utterance | null;
+  /** `"file"` for `transcribe_file`, with the file's path. */
+  source?: "file";
+  path?: string;
+  /** Re-transcription of the last recording (`retranscribe_last`). */
+  retry?: boolean;
+  /** Retries only: changes from the previous text (null if too long to diff). */
+  diff?: TextDiff | null;
+  /** CPU/GPU min/avg/max over the decode; `gpu` null when unavailable. */
+  utilization?: {
+    cpu: { min: number; avg: number; max: number; samples: number } | null;
+    gpu: { min: number; avg: number; max: number; samples: number } | null;
+  };
 }
 
 export function useTauri() {
   const store = useAppStore();
 
   // Commands - Audio
-  async function startListen(mode: ListenMode = "toggle") {
+  /** Record from `source`, by default `Settings.captureSource`. */
+  async function startListen(mode: ListenMode = "toggle", source?: CaptureSource) {
     try {
-      await invoke("start_listen", { mode });
+      await invoke("start_listen", { mode, source });
     } catch (error) {
       console.error("Failed to start listening:", error);
       store.setStatus("error");
@@ -103,6 +199,35 @@ export function useTauri() {
     }
   }
 
+  /** Stop recording without ending it: the microphone stays open and
+   *  `stopListen` later transcribes what was said around the pause. */
+  async function pauseListen(): Promise<void> {
+    await invoke("pause_listen");
+  }
+
+  async function resumeListen(): Promise<void> {
+    await invoke("resume_listen");
+  }
+
+  /** Abort transcription `jobId`, or every recording's; `stopListen`
+   *  then fails with "Transcription cancelled" and nothing is output. */
+  async function cancelTranscription(jobId?: number): Promise<void> {
+    await invoke("cancel_transcription", { jobId });
+  }
+
+  /** Transcribe the last recording again with the current model and
+   *  settings; `chunked` decodes it in short windows, for a transcript
+   *  that stopped early. The result arrives as `transcript:final`
+   *  (flagged `retry`), preceded by `transcript:diff`. */
+  async function retranscribeLast(options: { chunked?: boolean } = {}): Promise<string> {
+    return await invoke<string>("retranscribe_last", { chunked: options.chunked ?? false });
+  }
+
+  /** Save the last recording as a WAV file; where it was written. */
+  async function saveLastRecording(path: string | null = null): Promise<string> {
+    return await invoke<string>("save_last_recording", { path });
+  }
+
   // Commands - Settings.
   //
   // Each wrapper now does the bare minimum: invoke the backend command,
@@ -199,10 +324,85 @@ export function useTauri() {
     }
   }
 
+  /** Quantization suffix tried first for built-in model files; takes
+   *  effect at the next load. */
+  async function setQuantization(quantization: string): Promise<void> {
+    try {
+      await invoke("set_quantization", { quantization });
+      store.updateSettings({ quantization });
+    } catch (error) {
+      console.error("Failed to set quantization:", error);
+      throw error;
+    }
+  }
+
+  /** Folder searched for models before the app's own (e.g. a shared
+   *  `~/.cache/whisper`); empty clears it. Rejects when it isn't a
+   *  directory. */
+  async function setModelsDir(dir: string): Promise<void> {
+    try {
+      await invoke("set_models_dir", { dir });
+    } catch (error) {
+      console.error("Failed to set models dir:", error);
+      throw error;
+    }
+  }
+
+  /** Memory for recordings kept after transcription, in MB. Recordings
+   *  that no longer fit are dropped (`memory:evicted`). */
+  async function setRetainedAudioMb(mb: number): Promise<void> {
+    try {
+      await invoke("set_retained_audio_mb", { mb });
+      store.updateSettings({ retainedAudioMb: mb });
+    } catch (error) {
+      console.error("Failed to set retained audio budget:", error);
+      throw error;
+    }
+  }
+
+  async function setCaptureSpillMb(mb: number): Promise<void> {
+    try {
+      await invoke("set_capture_spill_mb", { mb });
+      store.updateSettings({ captureSpillMb: mb });
+    } catch (error) {
+      console.error("Failed to set capture spill threshold:", error);
+      throw error;
+    }
+  }
+
   // Commands - Model Management
+  /** Load a small draft model for instant `transcript:draft` feedback
+   *  before the main model's final transcript; `null` unloads it. */
+  async function loadDraftModel(model: ModelId | null): Promise<void> {
+    await invoke("load_draft_model", { model });
+  }
+
+  /** Start a load and wait for its outcome: the command returns a
+   *  load id at once and the result comes as `model:loaded` or
+   *  `model:load-failed`. Only one load runs at a time, so the first
+   *  outcome event after the invoke is this load's. */
+  async function runModelLoad(model: ModelId): Promise<void> {
+    let settle!: (error: string | null) => void;
+    const outcome = new Promise<void>((resolve, reject) => {
+      settle = (error) => (error === null ? resolve() : reject(new Error(error)));
+    });
+    // Listen before invoking: a failing load can finish before the
+    // command's reply arrives.
+    const unlisten = await Promise.all([
+      listen("model:loaded", () => settle(null)),
+      listen<{ error: string }>("model:load-failed", (event) => settle(event.payload.error)),
+    ]);
+    try {
+      await invoke<number>("load_whisper_model", { model });
+      await outcome;
+    } finally {
+      unlisten.forEach((u) => u());
+    }
+  }
+
   async function loadWhisperModel(model: ModelId) {
     try {
-      await invoke("load_whisper_model", { model });
+      await runModelLoad(model);
       // Successful load — clear any prior broken flag for this id.
       // Backend persists + emits settings:changed, frontend cache
       // catches up via the unified resync.
@@ -230,6 +430,110 @@ export function useTauri() {
     }
   }
 
+  /** Capabilities of the loaded model; null when none is loaded. */
+  async function getModelInfo(): Promise<LoadedModelInfo | null> {
+    try {
+      return await invoke<LoadedModelInfo>("get_model_info");
+    } catch (error) {
+      if ((error as { kind?: string })?.kind !== "notLoaded") {
+        console.error("Failed to get model info:", error);
+      }
+      return null;
+    }
+  }
+
+  /** Memory taken by the kept recordings, the model and the history. */
+  async function getMemoryUsage(): Promise<MemoryUsage> {
+    return await invoke<MemoryUsage>("get_memory_usage");
+  }
+
+  /** Memory the loaded model takes; null when none is loaded. */
+  async function getModelMemory(): Promise<ModelMemory | null> {
+    try {
+      return await invoke<ModelMemory>("get_model_memory");
+    } catch (error) {
+      if ((error as { kind?: string })?.kind !== "notLoaded") {
+        console.error("Failed to get model memory:", error);
+      }
+      return null;
+    }
+  }
+
+  /** Microphone sessions that started in [from, to) (Unix ms, either
+   *  bound optional), oldest first. */
+  async function getMicUsageLog(from?: number, to?: number): Promise<MicUsageEntry[]> {
+    return await invoke<MicUsageEntry[]>("get_mic_usage_log", { range: { from, to } });
+  }
+
+  async function clearMicUsageLog(): Promise<void> {
+    await invoke("clear_mic_usage_log");
+  }
+
+  /** Top 3 spoken-language guesses for the last recording, or for a fresh
+   *  3 s capture. Rejects with a `LanguageDetectError`. */
+  async function detectLanguage(fresh = false): Promise<LanguageGuess[]> {
+    return await invoke<LanguageGuess[]>("detect_language", { fresh });
+  }
+
+  /** Why the last transcription came out in its language, and which
+   *  settings to change when it was wrong. Rejects before the first
+   *  transcription. */
+  async function explainLanguageChoice(): Promise<LanguageReport> {
+    return await invoke<LanguageReport>("explain_language_choice");
+  }
+
+  /** Run the text pipeline on `input` (with the current settings, or
+   *  `overrides`) without storing or copying anything. */
+  async function previewTextPipeline(
+    input: string,
+    overrides?: PipelineSettings,
+  ): Promise<PipelineOutput> {
+    return await invoke<PipelineOutput>("preview_text_pipeline", { input, overrides });
+  }
+
+  /** Transcribe a WAV file with the loaded model. The transcript also
+   *  arrives as `transcript:final` with `source: "file"`; progress as
+   *  `file:progress` (`{ path, percent }`). */
+  async function transcribeFile(path: string): Promise<string> {
+    return await invoke<string>("transcribe_file", { path });
+  }
+
+  /** Transcribe the WAV files that appear in `path`, and those already
+   *  there, into `format`; next to them unless `outputDir` is given.
+   *  Results come as `watch:transcribed` (`{ input, output }`),
+   *  `watch:skipped` and `watch:failed`. Rejects with a `WatchError`. */
+  async function watchFolder(
+    path: string,
+    format: WatchFormat,
+    outputDir?: string,
+  ): Promise<WatchedFolder> {
+    return await invoke<WatchedFolder>("watch_folder", { path, format, outputDir });
+  }
+
+  async function unwatchFolder(path: string): Promise<void> {
+    await invoke("unwatch_folder", { path });
+  }
+
+  /** The last recorded backend errors, newest first. */
+  async function getRecentErrors(): Promise<RecentError[]> {
+    return await invoke<RecentError[]>("get_recent_errors");
+  }
+
+  /** Forget the recorded errors and reset the badge count. */
+  async function clearRecentErrors(): Promise<void> {
+    await invoke("clear_recent_errors");
+  }
+
+  /** Everything the overlay draws, in one snapshot. */
+  async function getRenderState(): Promise<RenderState> {
+    return await invoke<RenderState>("get_render_state");
+  }
+
+  /** Transcriptions queued or running. */
+  async function getQueueLength(): Promise<number> {
+    return await invoke<number>("get_queue_length");
+  }
+
   // Commands - Permissions
   async function checkPermissions() {
     try {
@@ -255,10 +559,136 @@ export function useTauri() {
     }
   }
 
+  /** Every input device, the default flagged and first. */
+  async function getAudioDevices(): Promise<AudioDevices> {
+    return await invoke<AudioDevices>("get_audio_devices");
+  }
+
+  /** Record from the device `id` (from `getAudioDevices`), or the
+   *  system default with null. Rejected while recording. */
+  async function setAudioDevice(id: string | null): Promise<void> {
+    await invoke("set_audio_device", { deviceName: id });
+    store.updateSettings({ preferredDevice: id });
+  }
+
+  async function setInputGain(gainDb: number): Promise<void> {
+    await invoke("set_input_gain", { gainDb });
+    store.updateSettings({ gain: gainDb });
+  }
+
+  async function setAgc(enabled: boolean): Promise<void> {
+    await invoke("set_agc", { enabled });
+    store.updateSettings({ agc: enabled });
+  }
+
+  async function setAudioFilters(filters: AudioFilters): Promise<void> {
+    await invoke("set_audio_filters", { filters });
+    store.updateSettings({ audioFilters: filters });
+  }
+
+  /** Record the mix of the input's channels or one of them; refused
+   *  for a channel the device doesn't have. */
+  async function setChannelMode(mode: ChannelMode): Promise<void> {
+    await invoke("set_channel_mode", { mode });
+    store.updateSettings({ channelMode: mode });
+  }
+
+  /** What recordings capture by default; system audio is refused
+   *  where it can't be recorded. */
+  async function setCaptureSource(source: CaptureSource): Promise<void> {
+    await invoke("set_capture_source", { source });
+    store.updateSettings({ captureSource: source });
+  }
+
+  /** The applications playing audio, which system audio can be
+   *  narrowed to; empty where that isn't supported. */
+  async function listCaptureApplications(): Promise<CaptureApplication[]> {
+    return await invoke<CaptureApplication[]>("list_capture_applications");
+  }
+
+  /** Record only `application` as system audio, or all of it with
+   *  null. Rejected while recording. */
+  async function setCaptureApplication(
+    application: CaptureApplication | null,
+  ): Promise<void> {
+    await invoke("set_capture_application", { id: application?.id ?? null });
+    store.updateSettings({ captureApplication: application?.name ?? null });
+  }
+
+  async function setDebugSaveRecordings(enabled: boolean): Promise<void> {
+    await invoke("set_debug_save_recordings", { enabled });
+    store.updateSettings({ debugSaveRecordings: enabled });
+  }
+
+  async function setPreRoll(ms: number): Promise<void> {
+    await invoke("set_pre_roll", { ms });
+    store.updateSettings({ preRollMs: ms });
+  }
+
+  /** Record 3 s of the silent room and fit the VAD threshold to it;
+   *  rejects (with why) when something was heard or it's too loud. */
+  async function calibrateNoiseFloor(): Promise<NoiseCalibration> {
+    const calibration = await invoke<NoiseCalibration>("calibrate_noise_floor");
+    store.updateSettings({ speechThreshold: calibration.threshold });
+    return calibration;
+  }
+
+  /** Meter the input without recording (the settings mic test):
+   *  `onLevel` gets `audio:monitor-level` about 15 times a second until
+   *  the returned function stops it, or the window closes. */
+  async function startLevelMonitor(
+    onLevel: (level: MonitorLevel) => void,
+  ): Promise<() => Promise<void>> {
+    const unlisten = await listen<MonitorLevel>("audio:monitor-level", (event) => {
+      onLevel(event.payload);
+    });
+    try {
+      await invoke("start_level_monitor");
+    } catch (e) {
+      unlisten();
+      throw e;
+    }
+    return async () => {
+      unlisten();
+      await invoke("stop_level_monitor");
+    };
+  }
+
+  /** Tune the VAD; rejects (with why) values out of range, changing
+   *  nothing: threshold 0.001–0.5 RMS, timeout at least 200 ms. */
+  async function setVadConfig(
+    threshold: number,
+    silenceTimeoutMs: number,
+    minSpeechMs: number,
+  ): Promise<void> {
+    await invoke("set_vad_config", { threshold, silenceTimeoutMs, minSpeechMs });
+    store.updateSettings({ speechThreshold: threshold, silenceTimeoutMs, minSpeechMs });
+  }
+
+  /** Let the VAD threshold follow the room's noise, `factor` (1.5–10)
+   *  times over it; rejects a factor out of range. */
+  async function setAdaptiveThreshold(enabled: boolean, factor: number): Promise<void> {
+    await invoke("set_adaptive_threshold", { enabled, factor });
+    store.updateSettings({ adaptiveThreshold: enabled, noiseFactor: factor });
+  }
+
+  /** Detect speech with the RMS threshold or Silero's model. A model
+   *  that can't load is reported as `error:occurred` (`vad-fallback`)
+   *  at the next recording, which uses the threshold. */
+  async function setVadBackend(backend: VadBackend): Promise<void> {
+    await invoke("set_vad_backend", { backend });
+    store.updateSettings({ vadBackend: backend });
+  }
+
+  /** Time each VAD backend on synthetic audio, to choose between them. */
+  async function benchmarkVad(): Promise<VadBenchmark[]> {
+    return await invoke<VadBenchmark[]>("benchmark_vad");
+  }
+
   // Commands - Model detection
-  async function getAvailableModels(): Promise<string[]> {
+  async function getAvailableModels(): Promise<AvailableModel[]> {
     try {
-      return await invoke<string[]>("get_available_models");
+      return await invoke<AvailableModel[]>("get_available_models");
     } catch (error) {
       console.error("Failed to get available models:", error);
       return [];
@@ -375,9 +805,10 @@ export function useTauri() {
   }
 
   // Commands - System Health
-  async function checkSystemHealth(): Promise<SystemHealth> {
+  /** `maxAgeMs` accepts a cached complete result that recent. */
+  async function checkSystemHealth(maxAgeMs?: number): Promise<SystemHealth> {
     try {
-      const health = await invoke<SystemHealth>("check_system_health");
+      const health = await invoke<SystemHealth>("check_system_health", { maxAgeMs });
       store.setSystemHealth(health);
       return health;
     } catch (error) {
@@ -397,20 +828,122 @@ export function useTauri() {
     }
   }
 
+  /** The model that suits this machine's RAM, cores and GPU. */
+  async function recommendModel(): Promise<ModelRecommendation> {
+    return await invoke<ModelRecommendation>("recommend_model");
+  }
+
+  /** The official models to offer for download, marked installed and
+   *  recommended for this machine. */
+  async function getModelCatalog(): Promise<CatalogModel[]> {
+    return await invoke<CatalogModel[]>("get_model_catalog");
+  }
+
+  /**
+   * Stop a `download_model` in flight; it rejects with a `cancelled`
+   * error and keeps what arrived, to resume. `false` when `model`
+   * wasn't downloading.
+   */
+  async function cancelDownload(model: string): Promise<boolean> {
+    return await invoke<boolean>("cancel_download", { model });
+  }
+
+  /** Interrupted downloads; `downloadModel` of one resumes it. */
+  async function getPendingDownloads(): Promise<PendingDownload[]> {
+    return await invoke<PendingDownload[]>("get_pending_downloads");
+  }
+
+  /** Drop what an interrupted download of `model` kept. */
+  async function discardPendingDownload(model: string): Promise<boolean> {
+    return await invoke<boolean>("discard_pending_download", { model });
+  }
+
+  /**
+   * Hash the file of `model` and check it against the known checksum
+   * (built-ins only). Progress comes as `model:verify:progress`; rejects
+   * with an `IntegrityError`.
+   */
+  async function verifyModel(model: string): Promise<ModelVerification> {
+    return await invoke<ModelVerification>("verify_model", { model });
+  }
+
+  /**
+   * Load the model file at `path` whatever its name, as model `custom`;
+   * the next launch loads it again.
+   */
+  async function loadWhisperModelFromPath(
+    path: string,
+    forceCpu = false,
+  ): Promise<ModelLoadResult> {
+    const result = await invoke<ModelLoadResult>("load_whisper_model_from_path", {
+      path,
+      forceCpu,
+    });
+    store.clearModelBroken("custom");
+    store.updateSettings({ model: "custom", customModelPath: path });
+    await refreshModelList();
+    return result;
+  }
+
+  /**
+   * Delete the file of `model`. The loaded model is refused unless
+   * `force`, which unloads it first; bundled models always are. Rejects
+   * with a `DeleteModelError`.
+   */
+  async function deleteModel(model: string, force = false): Promise<DeletedModel> {
+    const deleted = await invoke<DeletedModel>("delete_model", { model, force });
+    await refreshModelList();
+    return deleted;
+  }
+
+  /**
+   * Copy the model file at `sourcePath` into the models directory (or
+   * move it, with `moveSource`). A file already there with the same
+   * contents is returned with `duplicate`. Rejects with an
+   * `ImportModelError`.
+   */
+  async function importModel(sourcePath: string, moveSource = false): Promise<ImportedModel> {
+    const imported = await invoke<ImportedModel>("import_model", { sourcePath, moveSource });
+    await refreshModelList();
+    return imported;
+  }
+
   interface ModelLoadResult {
     success: boolean;
     usingGpu: boolean;
     backend: string;
     fallbackUsed: boolean;
+    gpuDevice: number | null;
+    flashAttention: boolean;
+    /** Flash attention was requested but the model only loaded without it. */
+    flashAttentionFallback: boolean;
+    /** Warm-up pass duration; null when skipped or failed. */
+    warmupMs: number | null;
+    memory: ModelMemory;
   }
 
-  async function loadWhisperModelWithOptions(model: ModelId, forceCpu: boolean): Promise<ModelLoadResult> {
+  /** `flashAttention` overrides (and saves) the flash attention
+   *  preference; omit it to use the saved one. `warmup` (default true)
+   *  runs a silent pass right after loading so the first dictation is
+   *  as fast as the next ones. */
+  async function loadWhisperModelWithOptions(
+    model: ModelId,
+    forceCpu: boolean,
+    flashAttention?: boolean,
+    warmup?: boolean,
+  ): Promise<ModelLoadResult> {
     try {
       const result = await invoke<ModelLoadResult>("load_whisper_model_with_options", {
         model,
         forceCpu,
+        flashAttention,
+        warmup,
       });
 
+      if (result.flashAttentionFallback) {
+        store.showToggleNotification("Flash attention not supported here, loaded without it");
+      }
+
       // Update GPU status in store
       store.setGpuStatus({
         usingGpu: result.usingGpu,
@@ -502,7 +1035,7 @@ export function useTauri() {
       // For now we still call `getAvailableModels` because downstream
       // welcome/init logic uses it to decide whether the welcome
       // dialog should run. Cheap, no harm.
-      let availableModels = await getAvailableModels();
+      let availableModels = (await getAvailableModels()).map((m) => m.id);
 
       // Load history
       const history = await loadHistory();
@@ -572,7 +1105,7 @@ export function useTauri() {
       // Decide whether the model can be loaded right now.
       const tryLoadCurrentModel = async () => {
         // Refresh the available list — a download may have just landed.
-        availableModels = await getAvailableModels();
+        availableModels = (await getAvailableModels()).map((m) => m.id);
         for (const id of availableModels) {
           store.setModelDownloaded(id as ModelId, true);
         }
@@ -610,36 +1143,105 @@ export function useTauri() {
 
     // Audio events
     unlistenFns.push(await listen<VadLevelPayload>("vad:level", (event) => {
-      store.setVuLevel(event.payload.rms);
+      store.setVuLevel(event.payload.rms, event.payload.threshold);
     }));
 
+    // Overlay snapshot: re-pull whenever the backend says it is stale.
+    // Invalidations arriving mid-pull trigger one more pull, not one each.
+    let renderPull: Promise<void> | null = null;
+    let renderStale = false;
+    const pullRenderState = async () => {
+      if (renderPull) {
+        renderStale = true;
+        return;
+      }
+      renderPull = (async () => {
+        do {
+          renderStale = false;
+          try {
+            store.setRenderState(await getRenderState());
+          } catch (error) {
+            console.error("Failed to get render state:", error);
+          }
+        } while (renderStale);
+      })();
+      await renderPull;
+      renderPull = null;
+    };
+    unlistenFns.push(await listen("render:invalidate", pullRenderState));
+    pullRenderState();
+
     // State changes from backend - only handle "listening" state here
     // "processing" is set by stopListen(), "idle" is set by transcript:final handler
-    unlistenFns.push(await listen<string>("state:change", (event) => {
-      const newStatus = event.payload as any;
+    unlistenFns.push(await listen<StatusChange>("state:change", (event) => {
+      const newStatus = event.payload.to;
       // Only update status for listening state - processing/idle are handled elsewhere
       if (newStatus === "listening") {
         store.setStatus(newStatus);
       }
+      // Acknowledge once rendered so the backend can send the next one
+      const seq = event.payload.seq;
+      requestAnimationFrame(() => {
+        invoke("ack_state", { seq }).catch((err) =>
+          console.warn("ack_state failed:", err),
+        );
+      });
     }));
 
     unlistenFns.push(await listen<TranscriptPayload>("transcript:partial", (event) => {
       store.setPartialTranscript(event.payload.text);
     }));
 
+    // Draft-model text, shown until the final transcript replaces it
+    unlistenFns.push(await listen<TranscriptPayload>("transcript:draft", (event) => {
+      store.setPartialTranscript(event.payload.text);
+    }));
+
+    unlistenFns.push(await listen<number>("transcript:progress", (event) => {
+      store.setTranscriptionProgress(event.payload);
+    }));
+
+    // Recording skipped without a decode (too short / no speech heard)
+    // A voice-activated recording stopped itself: it is transcribed
+    // as if stopListen() had been called
+    unlistenFns.push(await listen<{ reason: "speech-ended" | "no-speech" }>("listen:voice-end", () => {
+      store.setStatus("processing");
+    }));
+
+    unlistenFns.push(await listen<{ reason: "too-short" | "no-speech-detected" }>("transcript:empty", (event) => {
+      if (event.payload.reason === "no-speech-detected") {
+        store.showToggleNotification("No speech detected");
+      }
+      // Nothing to wait for (stopListen() also resets it on its error),
+      // unless a new recording started meanwhile
+      if (store.status === "processing") {
+        store.setStatus("idle");
+      }
+    }));
+
     unlistenFns.push(await listen<TranscriptPayload>("transcript:final", async (event) => {
-      const { text, model, transcribeDurationMs } = event.payload;
+      const { text, model, transcribeDurationMs, retry, diff } = event.payload;
       store.setLastTranscript(text);
 
-      // Transcription complete - set status to idle
-      store.setStatus("idle");
+      // Transcription complete - set status to idle. A file transcript
+      // says nothing about the recording state, nor does an utterance
+      // of a continuous recording still under way, nor one that
+      // finished under the next recording.
+      const utterance = event.payload.utterance;
+      if (
+        event.payload.source !== "file" &&
+        (!utterance || utterance.last) &&
+        store.status === "processing"
+      ) {
+        store.setStatus("idle");
+      }
 
       // Add to history (in-memory and persisted)
       if (text.trim()) {
         store.addToHistory(text, model as any, transcribeDurationMs);
 
         // Persist history first, then emit event (so Settings can read the updated file)
-        addHistoryEntry(text, model, transcribeDurationMs)
+        addHistoryEntry(text, model, transcribeDurationMs, retry, diff)
           .then(() => {
             // Emit event only after persistence is complete
             emit("history:updated").catch((error) => {
@@ -649,20 +1251,19 @@ export function useTauri() {
           .catch((error) => {
             console.error("Failed to persist history:", error);
           });
-
-        // Copy to clipboard if autoCopy is enabled
-        if (store.settings.autoCopy) {
-          try {
-            const { writeText } = await import("@tauri-apps/plugin-clipboard-manager");
-            await writeText(text);
-            store.triggerCopyNotification();
-          } catch (error) {
-            console.error("Failed to copy to clipboard:", error);
-          }
-        }
+        // Auto-copy happens in the backend (output coordinator), which
+        // reports through `output:copied` below.
       }
     }));
 
+    unlistenFns.push(await listen<{ text: string }>("output:copied", () => {
+      store.triggerCopyNotification();
+    }));
+
+    unlistenFns.push(await listen<{ text: string }>("output:superseded", () => {
+      store.showToggleNotification("Previous transcript replaced on clipboard");
+    }));
+
     // Permission events
     unlistenFns.push(await listen<string>("permission:required", (event) => {
       if (event.payload === "microphone") {
@@ -681,9 +1282,112 @@ export function useTauri() {
 
     // Model loaded event
     unlistenFns.push(await listen<string>("model:loaded", (event) => {
+      store.setModelLoadProgress(null);
       store.updateSettings({ model: event.payload as ModelId });
     }));
 
+    // Estimated model load progress (whisper.cpp reports none)
+    unlistenFns.push(await listen<ModelLoadProgress>("model:loading", (event) => {
+      store.setModelLoadProgress(event.payload);
+    }));
+    unlistenFns.push(await listen<{ model: string; failure?: LoadFailure }>("model:load-failed", (event) => {
+      store.setModelLoadProgress(null);
+      const failure = event.payload.failure;
+      if (failure && LOAD_FAILURE_HINTS[failure.kind]) {
+        console.warn(`[model] ${event.payload.model} failed to load (${failure.kind}):`, failure.message, failure.log);
+        store.showToggleNotification(LOAD_FAILURE_HINTS[failure.kind]);
+      }
+    }));
+
+    // Warm-up pass done: the next transcription runs at full speed
+    unlistenFns.push(await listen<{ model: string; durationMs: number }>("model:warmed", (event) => {
+      console.info(`[model] ${event.payload.model} warmed up in ${event.payload.durationMs} ms`);
+    }));
+
+    // Poor model × language combination (once per combination per session)
+    unlistenFns.push(await listen<ConfigWarning>("config:warning", (event) => {
+      console.warn("Config warning:", event.payload);
+      store.showToggleNotification(`${event.payload.message}. ${event.payload.suggestion}`);
+    }));
+
+    // The microphone misreports its sample rate; the backend corrected it.
+    unlistenFns.push(await listen<RateCorrection>("capture:rate-corrected", (event) => {
+      console.warn("Input sample rate corrected:", event.payload);
+    }));
+
+    // The chosen microphone is unplugged; recording from the default.
+    unlistenFns.push(await listen<DeviceFallback>("audio:device-fallback", (event) => {
+      console.warn("Input device missing:", event.payload);
+      store.showToggleNotification(`Microphone not found, using ${event.payload.used}`);
+    }));
+
+    // The input gain is too high for this microphone.
+    unlistenFns.push(await listen<Clipping>("audio:clipping", (event) => {
+      console.warn("Input clipping:", event.payload);
+      const { percent, gainDb } = event.payload;
+      store.showToggleNotification(`Input is clipping (${percent.toFixed(1)}% of samples), lower the gain (now ${gainDb} dB)`);
+    }));
+
+    // The system default input changed mid-recording; the recording followed it.
+    unlistenFns.push(await listen<DeviceChanged>("audio:device-changed", (event) => {
+      console.info("Input device changed:", event.payload.name);
+      store.showToggleNotification(`Recording from ${event.payload.name}`);
+    }));
+
+    // The microphone went away mid-recording.
+    unlistenFns.push(await listen<InputLost>("audio:error", (event) => {
+      console.error("Input device lost:", event.payload);
+      const messages: Record<InputLost["action"], string> = {
+        reopen: "Microphone lost, reconnecting",
+        transcribe: "Microphone lost, transcribing what was recorded",
+        fail: "Microphone lost, recording stopped",
+      };
+      store.showToggleNotification(messages[event.payload.action]);
+    }));
+
+    // A long capture couldn't be spilled to disk, or read back from it.
+    unlistenFns.push(await listen<SpillFailure>("audio:spill-failed", (event) => {
+      console.warn("Capture spill failed:", event.payload.message);
+      store.showToggleNotification(event.payload.message);
+    }));
+
+    // Nothing but digital silence from the input: likely muted.
+    unlistenFns.push(await listen<SilentInput>("audio:silent-input", (event) => {
+      const { device, muted } = event.payload;
+      console.warn("Silent input:", event.payload);
+      store.showToggleNotification(
+        muted === false
+          ? `No sound from ${device}, check its volume`
+          : `Your microphone (${device}) appears muted`,
+      );
+    }));
+
+    // Every backend error, also kept for `getRecentErrors`.
+    unlistenFns.push(await listen<RecentError>("error:occurred", (event) => {
+      console.error(`Backend error (${event.payload.code}):`, event.payload.message);
+      store.showToggleNotification(event.payload.message);
+    }));
+
+    // A transcription was refused: too many already queued.
+    unlistenFns.push(await listen<TranscriptionQueueError>("transcript:busy", (event) => {
+      console.warn("Transcription refused:", event.payload);
+      store.showToggleNotification(
+        `Still transcribing (${event.payload.pending} pending), recording discarded`,
+      );
+    }));
+
+    // The transcript stops before the speech does: say so, and point at
+    // the chunked retry.
+    unlistenFns.push(await listen<TranscriptTruncated>("transcript:truncated", (event) => {
+      console.warn("Transcript may be truncated:", event.payload);
+      const missing = Math.round(event.payload.coverage.uncoveredSpeechMs / 1000);
+      store.showToggleNotification(
+        event.payload.canRetryChunked
+          ? `Transcript may be cut short (~${missing}s of speech missing), re-transcribe in chunks to recover it`
+          : `Transcript may still be cut short (~${missing}s of speech missing)`,
+      );
+    }));
+
     // Model-download lifecycle. Both the seed (initial `list_required_models`
     // call) and the 3 listeners are encapsulated in `useModelDownloadTracker`,
     // which the Settings window also calls — see SettingsPage.vue.
@@ -960,6 +1664,26 @@ export function useTauri() {
       }
     }));
 
+    // HID push-to-talk pedal: held = recording, released = transcribe.
+    // The next press may come while the last one is transcribed.
+    unlistenFns.push(await listen("ptt:pressed", async () => {
+      if (isActionInProgress || (store.status !== "idle" && store.status !== "processing")) {
+        return;
+      }
+      isActionInProgress = true;
+      try {
+        await startListen("push-to-talk");
+      } finally {
+        isActionInProgress = false;
+      }
+    }));
+
+    unlistenFns.push(await listen("ptt:released", async () => {
+      if (store.status === "listening") {
+        await stopListen();
+      }
+    }));
+
     // Check permissions on init
     checkPermissions();
 
@@ -971,6 +1695,11 @@ export function useTauri() {
     // Audio
     startListen,
     stopListen,
+    pauseListen,
+    resumeListen,
+    cancelTranscription,
+    retranscribeLast,
+    saveLastRecording,
     // Settings
     setModel,
     setLanguage,
@@ -980,10 +1709,30 @@ export function useTauri() {
     setFavoriteLanguages,
     setModelLanguages,
     setLanguageCycleMode,
+    setQuantization,
+    setModelsDir,
+    setRetainedAudioMb,
+    setCaptureSpillMb,
     // Models
     loadWhisperModel,
+    loadDraftModel,
     loadWhisperModelWithOptions,
     isModelLoaded,
+    getModelInfo,
+    getModelMemory,
+    getMemoryUsage,
+    getMicUsageLog,
+    clearMicUsageLog,
+    detectLanguage,
+    explainLanguageChoice,
+    getQueueLength,
+    getRenderState,
+    getRecentErrors,
+    clearRecentErrors,
+    transcribeFile,
+    watchFolder,
+    unwatchFolder,
+    previewTextPipeline,
     getAvailableModels,
     refreshModelList,
     validateCustomModel,
@@ -993,9 +1742,35 @@ export function useTauri() {
     // System Health
     checkSystemHealth,
     getGpuStatus,
+    recommendModel,
+    getModelCatalog,
+    cancelDownload,
+    getPendingDownloads,
+    discardPendingDownload,
+    verifyModel,
+    deleteModel,
+    importModel,
+    loadWhisperModelFromPath,
     // Permissions
     checkPermissions,
     requestMicrophonePermission,
+    getAudioDevices,
+    setAudioDevice,
+    setInputGain,
+    setAgc,
+    setAudioFilters,
+    setChannelMode,
+    setCaptureSource,
+    listCaptureApplications,
+    setCaptureApplication,
+    setDebugSaveRecordings,
+    setPreRoll,
+    calibrateNoiseFloor,
+    setVadConfig,
+    setAdaptiveThreshold,
+    setVadBackend,
+    benchmarkVad,
+    startLevelMonitor,
     // Init
     initListeners,
     initApp,
@@ -5,7 +5,23 @@ import {
   LANGUAGE_DISPLAY_NAMES as LANGUAGE_DISPLAY_NAMES_REGISTRY,
 } from "../utils/languages";
 
-export type AppStatus = "idle" | "listening" | "processing" | "error";
+export type AppStatus =
+  | "idle"
+  | "listening"
+  | "paused"
+  | "processing"
+  | "cancelling"
+  | "error"
+  | "degraded";
+
+/** `state:change` payload. `seq` goes back through `ack_state` once
+ *  the change is on screen; the backend holds the next change until
+ *  then (or 100 ms). */
+export interface StatusChange {
+  from: AppStatus;
+  to: AppStatus;
+  seq: number;
+}
 // `ModelId` was a closed union in v0.1.7 (only the two built-ins).
 // Custom user-imported models use uuid-v4 ids so the type widens to
 // `string`. The two built-in literals "small" / "large-v3-turbo" are
@@ -19,6 +35,14 @@ export type Language = string;
 export type GpuBackendType = "cpu" | "vulkan" | "metal" | "cuda" | "hipblas";
 
 // System health check types
+export type LinuxEnvironment =
+  | "standard"
+  | "ostree"
+  | "nixOs"
+  | "steamOs"
+  | "flatpak"
+  | "container";
+
 export interface SystemHealth {
   vulkanAvailable: boolean;
   vulkanVersion: string | null;
@@ -27,9 +51,367 @@ export interface SystemHealth {
     platform: string;
     version: string | null;
     distribution: string | null;
+    environment: LinuxEnvironment;
   };
   installGuide: VulkanInstallGuide | null;
   canRunWithoutVulkan: boolean;
+  /** ostree, NixOS or SteamOS: the system image is read-only. */
+  systemIsImmutable: boolean;
+  /** Probes that timed out; their fields hold conservative defaults. */
+  probeTimeouts: string[];
+}
+
+/** Loaded-model capabilities from `get_model_info`. */
+/** One microphone session from `get_mic_usage_log`. Times are Unix ms. */
+export interface MicUsageEntry {
+  startedAt: number;
+  /** null when the app exited with the microphone open. */
+  endedAt: number | null;
+  durationMs: number | null;
+  trigger: string;
+  transcribed: boolean;
+  bytesCaptured: number;
+}
+
+/** `config:warning` payload: the model is a poor fit for the language. */
+export interface ConfigWarning {
+  kind: "englishOnlyModel" | "modelTooSmall";
+  message: string;
+  suggestion: string;
+}
+
+/** `transcript:busy` payload: a transcription refused because
+ *  `pending` jobs were already queued or running. */
+export interface TranscriptionQueueError {
+  kind: "busy";
+  pending: number;
+  maxDepth: number;
+}
+
+/** How far a decode got against the detected speech. Mirrors the
+ *  Rust `whisper::coverage::Coverage`. */
+export interface DecodeCoverage {
+  tokens: number;
+  segments: number;
+  audioMs: number;
+  /** End of the last segment. */
+  coveredMs: number;
+  /** From `coveredMs` to the end of the last speech after it. */
+  gapMs: number;
+  uncoveredSpeechMs: number;
+  possiblyTruncated: boolean;
+}
+
+/** `transcript:truncated` payload: the transcript likely stops before
+ *  the speech does. */
+export interface TranscriptTruncated {
+  jobId: number;
+  coverage: DecodeCoverage;
+  /** `retranscribeLast({ chunked: true })` may recover the rest. */
+  canRetryChunked: boolean;
+}
+
+/** `capture:rate-corrected` payload: the input device delivers
+ *  `measured` Hz although it reports `reported` Hz. */
+export interface RateCorrection {
+  reported: number;
+  measured: number;
+}
+
+/** A recorded backend error (`error:occurred` payload). */
+export interface RecentError {
+  /** Kebab-case identifier, e.g. "transcription-timeout". */
+  code: string;
+  message: string;
+  /** Unix time, ms. */
+  timestamp: number;
+  context: string | null;
+}
+
+/** Something in the backend waiting on the user. */
+export type PendingConfirmation = {
+  /** A pedal was pressed and awaits `bind_ptt_device`. */
+  kind: "pttBinding";
+  vendorId: number;
+  productId: number;
+};
+
+/** `model:loading` payload: estimated, capped below 100 until the
+ *  load really finishes. */
+export interface ModelLoadProgress {
+  loadId: number;
+  model: string;
+  elapsedMs: number;
+  percent: number;
+}
+
+/** What a failed model load comes down to (`LoadFailureKind`). */
+export type LoadFailureKind =
+  | "model-missing"
+  | "truncated"
+  | "model-corrupt"
+  | "unsupported-version"
+  | "out-of-memory"
+  | "disk-full"
+  | "gpu-init"
+  | "unknown";
+
+/** `failure` of `model:load-failed`. */
+export interface LoadFailure {
+  kind: LoadFailureKind;
+  message: string;
+  /** whisper.cpp's warnings and errors during the load. */
+  log: string[];
+}
+
+/** What to try after a failed load, by kind. */
+export const LOAD_FAILURE_HINTS: Record<LoadFailureKind, string> = {
+  "model-missing": "Download the model again.",
+  truncated: "The file is incomplete: download the model again.",
+  "model-corrupt": "The file is damaged: download the model again.",
+  "unsupported-version": "The model is newer than this app: update S2Tui.",
+  "out-of-memory": "Not enough memory: pick a smaller model.",
+  "disk-full": "Free some disk space.",
+  "gpu-init": "The GPU didn't start: load the model on the CPU.",
+  unknown: "",
+};
+
+/** `verify_model` rejection: the model file can't be used as it is. */
+export type IntegrityError =
+  | { kind: "unreadable"; message: string }
+  | { kind: "incomplete"; sizeBytes: number; expectedBytes: number | null }
+  | { kind: "corrupt"; reason: string }
+  | { kind: "unsupported"; reason: string }
+  | { kind: "checksum"; expected: string; actual: string };
+
+/** `verify_model` result. */
+export interface ModelVerification {
+  model: string;
+  path: string;
+  sizeBytes: number;
+  sha256: string;
+  /** The checksum it matched; null when none is known for the file. */
+  expectedSha256: string | null;
+}
+
+/** `delete_model` rejection. */
+export type DeleteModelError =
+  | { kind: "unknownModel"; message: string }
+  | { kind: "notFound"; path: string }
+  | { kind: "bundled"; path: string }
+  | { kind: "loaded"; model: string }
+  | { kind: "midRecording" }
+  | { kind: "loading" }
+  | { kind: "disk"; message: string };
+
+/** `delete_model` result and `model:deleted` payload. */
+export interface DeletedModel {
+  model: string;
+  path: string;
+  freedBytes: number;
+  /** The model was loaded and got unloaded first (`force`). */
+  unloaded: boolean;
+}
+
+/** `get_pending_downloads` entry: an interrupted download
+ *  `download_model` resumes. */
+export interface PendingDownload {
+  model: string;
+  filename: string;
+  bytesDownloaded: number;
+  totalBytes: number | null;
+}
+
+/** `import_model` rejection. */
+export type ImportModelError =
+  | { kind: "notFound"; path: string }
+  | { kind: "invalid"; error: IntegrityError }
+  | { kind: "badName"; path: string }
+  | { kind: "disk"; message: string };
+
+/** `import_model` result and `model:imported` payload. */
+export interface ImportedModel {
+  model: AvailableModel;
+  /** The same file was already there: nothing was copied. */
+  duplicate: boolean;
+}
+
+/** `get_render_state` snapshot: everything the overlay draws. Pulled
+ *  again on every `render:invalidate`. */
+export interface RenderState {
+  status: AppStatus;
+  /** Smoothed input level, 0–1. */
+  level: number;
+  /** Recording time so far, pauses excluded; null when not recording. */
+  elapsedMs: number | null;
+  model: string;
+  language: string;
+  listenMode: "toggle" | "push-to-talk" | "voice-activated" | "continuous" | null;
+  /** End of the latest partial transcript of this recording. */
+  partialTail: string | null;
+  /** Transcriptions queued or running. */
+  queueDepth: number;
+  pendingConfirmation: PendingConfirmation | null;
+}
+
+/** A `ggml-*.bin` file in the models directory (`get_available_models`).
+ *  Display as `large-v3-turbo (q5_0)` when `quantization` is set. */
+export interface AvailableModel {
+  id: string;
+  quantization: string | null;
+  filename: string;
+  /** The id ends in `.en`. */
+  englishOnly: boolean;
+  /** Absolute path of the file. */
+  path: string;
+  /** Which models folder it is in; the first one wins for an id found
+   *  in several. `custom` is `customModelPath`, id `custom`. */
+  source: "user" | "appdata" | "bundled" | "custom";
+  sizeBytes: number;
+  /** Last modified, Unix ms; null where the platform doesn't tell. */
+  modified: number | null;
+}
+
+/** `get_model_memory` result and `ModelLoadResult.memory`, in MB;
+ *  null where the platform can't measure it. */
+export interface ModelMemory {
+  fileMb: number | null;
+  /** Growth of the process RSS over the load. */
+  ramMb: number | null;
+  /** null when the model runs on CPU. */
+  vramMb: number | null;
+  vramSource: "measured" | "estimated" | null;
+}
+
+/** A feature keeping a recording in memory. */
+export type RetentionTag = "retranscribe";
+
+/** `memory:evicted` payload: a kept recording dropped to stay within
+ *  `retainedAudioMb`. */
+export interface AudioEviction {
+  id: number;
+  bytes: number;
+  tags: RetentionTag[];
+  budgetBytes: number;
+}
+
+/** `get_memory_usage` result. */
+export interface MemoryUsage {
+  retainedAudio: {
+    usedBytes: number;
+    budgetBytes: number;
+    /** Oldest first. */
+    recordings: { id: number; bytes: number; durationMs: number; tags: RetentionTag[] }[];
+  };
+  /** null without a loaded model. */
+  model: ModelMemory | null;
+  /** Estimated as the history's size in JSON. */
+  historyBytes: number;
+}
+
+/** `recommend_model` result. */
+export interface ModelRecommendation {
+  model: string;
+  /** e.g. "8 GB RAM, 4 cores, no GPU → small" */
+  reason: string;
+  /** `model` isn't on disk yet. */
+  needsDownload: boolean;
+  /** The best fitting model that is on disk, when `model` isn't. */
+  alternative: string | null;
+}
+
+/** What a model needs to run at dictation speed. */
+export interface ModelRequirements {
+  minRamGb: number;
+  minVramGb: number;
+  /** To run on CPU alone; null when it is too slow there. */
+  cpu: { ramGb: number; cores: number } | null;
+}
+
+/** A quantized variant of a `CatalogModel`. */
+export interface CatalogVariant {
+  /** The id `download_model` takes, e.g. "small-q5_1". */
+  id: string;
+  quantization: string;
+  sizeBytes: number;
+  installed: boolean;
+}
+
+/** `get_model_catalog` entry: an official model the app can download. */
+export interface CatalogModel {
+  id: string;
+  displayName: string;
+  /** Approximate download size. */
+  sizeBytes: number;
+  englishOnly: boolean;
+  variants: CatalogVariant[];
+  requirements: ModelRequirements;
+  /** This machine meets `requirements`. */
+  fits: boolean;
+  /** The model `recommend_model` picks for this machine. */
+  recommended: boolean;
+  installed: boolean;
+}
+
+export interface LanguageGuess {
+  code: string;
+  probability: number;
+}
+
+/** How the language of a transcription was settled. Carried as
+ *  `language` by `transcript:final`. */
+export interface LanguageDecision {
+  rule: "englishOnlyModel" | "setting" | "detected";
+  /** The language setting; null on auto-detect. */
+  requested: string | null;
+  /** What the decode ran in; null when unknown. */
+  language: string | null;
+  /** Detection's most likely languages; empty unless `detected`. */
+  candidates: LanguageGuess[];
+  audioMs: number;
+}
+
+/** `explain_language_choice` result. */
+export interface LanguageReport {
+  model: string;
+  decision: LanguageDecision;
+  /** The model's `modelLanguages`; auto-detect doesn't go by it. */
+  modelLanguages: string[];
+  /** The runner-up language was almost as likely. */
+  closeCall: boolean;
+  summary: string;
+  /** `setting` is the settings key to change; null when the advice is
+   *  about the recording itself. */
+  fixes: { setting: string | null; advice: string }[];
+}
+
+/** `detect_language` error, tagged by `kind`. */
+export type LanguageDetectError =
+  | { kind: "notLoaded" }
+  | { kind: "englishOnly" }
+  | { kind: "noAudio" }
+  | { kind: "busy" }
+  | { kind: "capture"; message: string }
+  | { kind: "failed"; message: string };
+
+/** Error of the encrypted-history commands, tagged by `kind`. */
+export type HistoryVaultError =
+  | { kind: "locked" }
+  | { kind: "passphraseRequired" }
+  | { kind: "wrongKey" }
+  | { kind: "keyUnavailable"; message: string }
+  | { kind: "crypto"; message: string }
+  | { kind: "storage"; message: string };
+
+export interface LoadedModelInfo {
+  path: string;
+  isMultilingual: boolean;
+  nVocab: number;
+  modelType: string;
+  sizeClass: string;
+  quantLabel: string;
+  nAudioCtx: number;
+  nTextCtx: number;
 }
 
 export interface VulkanInstallGuide {
@@ -108,6 +490,123 @@ export interface UserModel {
   capabilities: ModelCapabilities;
 }
 
+/** Partial decode parameters; unset fields inherit from lower layers
+ *  (engine defaults → built-in language defaults → user settings). */
+export interface DecodeOverride {
+  strategy?: "greedy" | "beam-search";
+  bestOf?: number;
+  beamSize?: number;
+  patience?: number;
+  temperature?: number;
+  suppressNst?: boolean;
+}
+
+/** Temperature fallback knobs; defaults are whisper.cpp's. */
+export interface AdvancedDecoding {
+  temperature: number;
+  temperatureInc: number;
+  entropyThold: number;
+  logprobThold: number;
+}
+
+/** A transcription's input hash and the parameters it ran with, so
+ *  two runs compare (see deterministic mode). */
+export interface Reproducibility {
+  samplesSha256: string;
+  samples: number;
+  params: {
+    modelPath: string;
+    usingGpu: boolean;
+    deterministic: boolean;
+    /** Requested language; null auto-detects. */
+    language: string | null;
+    translate: boolean;
+    nThreads: number;
+    decode: {
+      strategy: "greedy" | "beam-search";
+      bestOf: number;
+      beamSize: number;
+      patience: number;
+      temperature: number;
+      suppressNst: boolean;
+    };
+    fallback: AdvancedDecoding;
+    noSpeechThreshold: number;
+    suppressNonSpeech: boolean;
+    initialPrompt: string;
+    promptContext: string;
+    chunkSecs: number | null;
+  };
+}
+
+/** Global decoding strategy; `null` = defaults. */
+export type DecodingOptions =
+  | { strategy: "greedy"; bestOf: number }
+  | { strategy: "beam-search"; beamSize: number; patience: number };
+
+/** HID device (foot pedal) bound as push-to-talk. */
+export interface HidBinding {
+  vendorId: number;
+  productId: number;
+  usagePage: number;
+  usage: number;
+}
+
+/** What a watched folder's transcripts are written as. */
+export type WatchFormat = "txt" | "srt" | "vtt" | "json";
+
+/** A folder whose new audio files are transcribed (`watch_folder`). */
+export interface WatchedFolder {
+  path: string;
+  format: WatchFormat;
+  /** Where transcripts go; null for next to the audio. */
+  outputDir: string | null;
+}
+
+/** `watch_folder` / `unwatch_folder` rejection. */
+export type WatchError =
+  | { kind: "notAFolder"; path: string }
+  | { kind: "alreadyWatched"; path: string }
+  | { kind: "notWatched"; path: string }
+  | { kind: "watcher"; message: string }
+  | { kind: "persistFailed"; reason: string };
+
+export interface Snippet {
+  id: string;
+  name: string;
+  /** Spoken phrase, e.g. "insert signature". */
+  trigger: string;
+  /** Replacement text; supports {date}, {time} and {clipboard}. */
+  body: string;
+}
+
+/** Filler words ("um", "euh"): kept, removed or wrapped in brackets. */
+export type FillerMode = "keep" | "remove" | "mark";
+
+/** Text pipeline settings, as `preview_text_pipeline` overrides. */
+export interface PipelineSettings {
+  stripAnnotations: boolean;
+  fillerMode?: FillerMode;
+  /** Whisper code for the filler lexicon; null = auto-detect. */
+  language?: string | null;
+  extraFillers?: string[];
+  snippets: Snippet[];
+}
+
+export interface PipelineStageReport {
+  stage: "annotations" | "fillers" | "snippets";
+  /** Text after this stage. */
+  output: string;
+  /** Null when the stage changed nothing. */
+  diff: TextDiff | null;
+}
+
+/** `preview_text_pipeline` result. */
+export interface PipelineOutput {
+  text: string;
+  stages: PipelineStageReport[];
+}
+
 export interface Settings {
   language: Language;
   model: ModelId;
@@ -129,6 +628,98 @@ export interface Settings {
   /** Model ids (built-in or custom) the user has marked as disabled.
    *  Disabled models are skipped by the cycle shortcuts. */
   disabledModels: string[];
+  /** Translate dictation to English (multilingual models only). */
+  translate: boolean;
+  /** Custom vocabulary learned from corrected transcripts. */
+  vocabulary: { term: string; heard: string[] }[];
+  /** Shortcut running learn_from_clipboard. Empty = unbound. */
+  learnVocabularyShortcut: string;
+  /** Per-language decode overrides keyed by language code. */
+  decodeOverrides: Record<string, DecodeOverride>;
+  /** Global decoding strategy. Null = engine defaults + per-language tuning. */
+  decodingStrategy: DecodingOptions | null;
+  /** Voice-triggered snippets (trigger phrase → body). */
+  snippets: Snippet[];
+  /** Temperature and fallback thresholds. */
+  advancedDecoding: AdvancedDecoding;
+  /** Segments with a higher no-speech probability are dropped (0.0 – 1.0). */
+  noSpeechThreshold: number;
+  /** HID device bound as push-to-talk, if any. */
+  pttDevice: HidBinding | null;
+  /** Strip non-speech annotations such as [Music] or (applause). */
+  suppressNonSpeech: boolean;
+  /** VAD-detected speech (ms) required before transcribing; 0 disables. */
+  minSpeechMs: number;
+  /** Transcribe even when the VAD detected too little speech. */
+  forceTranscription: boolean;
+  /** Never write transcripts to disk (disables session crash recovery). */
+  privacyMode: boolean;
+  /** Emit interim transcript:partial events while recording. */
+  streamingPartials: boolean;
+  /** How often a streaming partial pass runs (ms of recorded audio). */
+  partialIntervalMs: number;
+  /** Prepend recent dictation to the prompt of the next one. */
+  contextCarryOver: boolean;
+  /** Idle gap (s) after which carried-over context is dropped; 0 = never. */
+  contextIdleResetSecs: number;
+  /** Minimum time between two clipboard writes (ms). */
+  clipboardDwellMs: number;
+  /** Join transcripts arriving within the dwell time into one clipboard write. */
+  clipboardAppend: boolean;
+  /** GPU index models load on; null uses device 0. */
+  gpuDevice: number | null;
+  /** Load models with flash attention. */
+  flashAttention: boolean;
+  /** Transcriptions that may be queued or running at once. */
+  transcriptionQueueDepth: number;
+  /** A transcription running longer than this (seconds) is aborted. */
+  transcriptionTimeoutSecs: number;
+  /** Keep, remove or mark filler words; voice-activated sessions always keep them. */
+  fillerMode: FillerMode;
+  /** Extra filler words by language code, on top of the built-in lexicons. */
+  fillerWords: Record<string, string[]>;
+  /** Quantization suffix tried first for built-in model files (`ggml-{model}-{q}.bin`); empty for plain names only. */
+  quantization: string;
+  /** Folder searched for models before the app's own; empty for none. */
+  modelsDir: string;
+  /** Folders whose new audio files are transcribed (`watch_folder`). */
+  watchedFolders: WatchedFolder[];
+  /** Memory for recordings kept after transcription, in MB; 0 keeps none. */
+  retainedAudioMb: number;
+  /** Reproducible decodes for debugging (fixed threads, greedy, no fallback). */
+  deterministicMode: boolean;
+  /** File last loaded by `load_whisper_model_from_path` (model id `custom`). */
+  customModelPath: string | null;
+  /** Input device to record from (an `AudioDevice` id); null for the system default. */
+  preferredDevice: string | null;
+  /** Input gain in dB, -20 to +30. */
+  gain: number;
+  /** Automatic gain control on the input. */
+  agc: boolean;
+  /** Filters on the input. */
+  audioFilters: AudioFilters;
+  /** Save every recording as a WAV file, for bug reports. */
+  debugSaveRecordings: boolean;
+  /** Audio kept from before each recording, in ms (0 for none, else 500 to 3000). */
+  preRollMs: number;
+  /** MB of audio kept in memory before a capture spills to disk (0 keeps it all in RAM, else 1 to 1024). */
+  captureSpillMb: number;
+  /** Which input channels are recorded: their mix, or one of them. */
+  channelMode: ChannelMode;
+  /** What recordings capture unless told otherwise. */
+  captureSource: CaptureSource;
+  /** The application system audio is recorded from alone (a `CaptureApplication` name); null for all of it. */
+  captureApplication: string | null;
+  /** RMS (0–1) over which the VAD hears speech; set by calibrateNoiseFloor or setVadConfig. */
+  speechThreshold: number;
+  /** Silence (ms) after which the VAD says speech ended. */
+  silenceTimeoutMs: number;
+  /** What detects speech: the RMS threshold, or Silero's model. */
+  vadBackend: VadBackend;
+  /** VAD threshold from the room's noise: its floor times `noiseFactor`. */
+  adaptiveThreshold: boolean;
+  /** The adaptive threshold over the noise floor (1.5–10). */
+  noiseFactor: number;
 }
 
 // Re-exports kept for backward compat with components that already import
@@ -140,19 +731,183 @@ export interface Permissions {
   microphone: boolean;
 }
 
+/** An input device, as `get_audio_devices` lists it. */
+export interface AudioDevice {
+  /** What `set_audio_device` takes: host and name, numbered when a
+   *  name repeats ("ALSA/USB Mic#2"). */
+  id: string;
+  name: string;
+  /** The audio host it is reached through: "ALSA", "WASAPI", … */
+  host: string;
+  /** The system default input, which recording uses. */
+  isDefault: boolean;
+  defaultSampleRate: number | null;
+  channels: number | null;
+}
+
+/** `get_audio_devices` result. */
+export interface AudioDevices {
+  /** The default first. */
+  devices: AudioDevice[];
+  /** Hosts and devices that couldn't be listed, and why. */
+  warnings: string[];
+}
+
+/** `audio:device-fallback` payload: the preferred device was missing,
+ *  the default was recorded from. */
+export interface DeviceFallback {
+  preferred: string;
+  used: string;
+}
+
+/** Which input filters are on. */
+export interface AudioFilters {
+  /** High-pass at 100 Hz, against rumble and hum. */
+  highPass: boolean;
+}
+
+/** Which input channels are recorded: their mix, or one of them
+ *  (`channel` counts from 0), checked against `AudioDevice.channels`. */
+export type ChannelMode = "mix" | "left" | "right" | { channel: number };
+
+/** What a recording captures: the microphone, or what the computer
+ *  plays (Windows, Linux, or macOS with a loopback driver). */
+export type CaptureSource = "microphone" | "system-audio";
+
+/** An application playing audio, as `list_capture_applications` lists
+ *  it (Linux only; the list is empty elsewhere). */
+export interface CaptureApplication {
+  /** What `set_capture_application` takes; a new one each time the
+   *  application reopens its stream. */
+  id: number;
+  name: string;
+}
+
+/** `audio:clipping` payload: too many of the input's samples are at or
+ *  near full scale (a hot mic, or too much gain). */
+export interface Clipping {
+  /** Share of the last half second's samples, in percent. */
+  percent: number;
+  gainDb: number;
+}
+
+/** `audio:device-changed` payload: the recording follows the system
+ *  default input to this device. */
+export interface DeviceChanged {
+  name: string;
+}
+
+/** `audio:error` payload: the input device went away mid-recording.
+ *  `action` is what the recording does about it: reopen the stream
+ *  (once), stop and transcribe what was captured, or stop and fail. */
+export interface InputLost {
+  message: string;
+  action: "reopen" | "transcribe" | "fail";
+}
+
+/** `audio:spill-failed` payload: the capture stays in memory. */
+export interface SpillFailure {
+  message: string;
+}
+
+/** A pause in a recording (`transcript:final`): segment times count
+ *  recorded audio only, so one after `atMs` happened `pausedMs` later
+ *  into the recording. */
+export interface PauseMark {
+  atMs: number;
+  pausedMs: number;
+}
+
+/** A continuous recording's utterance (`transcript:final`). Times are
+ *  wall-clock, in ms since the Unix epoch. */
+export interface Utterance {
+  /** 1 for the recording's first. */
+  index: number;
+  startedAtMs: number;
+  endedAtMs: number;
+  /** The recording's last: it ended with it. */
+  last: boolean;
+}
+
+/** `audio:monitor-level` payload, sent while the level monitor runs. */
+export interface MonitorLevel {
+  /** RMS (0–1) since the last one. */
+  rms: number;
+  /** The same on the level meter's 0–1 scale. */
+  level: number;
+}
+
+/** What decides that audio is speech: its RMS against the threshold,
+ *  or the Silero VAD model. */
+export type VadBackend = "rms" | "silero";
+
+/** `benchmark_vad` result: what a VAD backend costs. */
+export interface VadBenchmark {
+  backend: VadBackend;
+  /** Loading the model (Silero). */
+  loadMs: number;
+  /** Mean time per 10 ms chunk, in µs. */
+  chunkUs: number;
+  /** That as a share of one core in real time (%). */
+  cpuPercent: number;
+  /** Why Silero fell back: the timings are then the threshold's. */
+  error: string | null;
+}
+
+/** `calibrate_noise_floor` result, both in RMS (0–1). */
+export interface NoiseCalibration {
+  /** The room's level while silent. */
+  floor: number;
+  /** The VAD threshold now set. */
+  threshold: number;
+}
+
+/** `audio:silent-input` payload: the recording has been digital silence
+ *  for seconds, as from a muted microphone. */
+export interface SilentInput {
+  device: string;
+  /** The default source's mute flag (Linux); null where unknown. */
+  muted: boolean | null;
+}
+
+/** A run of inserted or deleted words. `start` indexes the new text's
+ *  words for insertions, the previous text's for deletions. */
+export interface DiffSpan {
+  start: number;
+  len: number;
+  text: string;
+}
+
+/** `transcript:diff` payload. Mirrors the Rust `text::TextDiff`. */
+export interface TextDiff {
+  insertions: DiffSpan[];
+  deletions: DiffSpan[];
+  unchangedRatio: number;
+}
+
 export interface HistoryEntry {
   id: string;
   text: string;
   timestamp: number;
   modelId?: ModelId;
   durationMs?: number;
+  /** Produced by `retranscribe_last` rather than a new recording. */
+  retry?: boolean;
+  diff?: TextDiff;
 }
 
 export const useAppStore = defineStore("app", () => {
   // State
   const status = ref<AppStatus>("idle");
   const vuLevel = ref(0);
+  /** Speech threshold on the `vuLevel` scale. */
+  const vuThreshold = ref(0);
   const partialTranscript = ref("");
+  const renderState = ref<RenderState | null>(null);
+  /** Decode progress (0–100) while processing; null when idle. */
+  const transcriptionProgress = ref<number | null>(null);
+  /** Model load in progress; null when none. */
+  const modelLoadProgress = ref<ModelLoadProgress | null>(null);
   const lastTranscript = ref("");
   const showCopyNotification = ref(false);
 
@@ -178,6 +933,52 @@ export const useAppStore = defineStore("app", () => {
     languageCycleMode: "model-first",
     userModels: [],
     disabledModels: [],
+    translate: false,
+    vocabulary: [],
+    learnVocabularyShortcut: "",
+    decodeOverrides: {},
+    decodingStrategy: null,
+    snippets: [],
+    advancedDecoding: { temperature: 0.0, temperatureInc: 0.2, entropyThold: 2.4, logprobThold: -1.0 },
+    noSpeechThreshold: 0.6,
+    pttDevice: null,
+    suppressNonSpeech: true,
+    minSpeechMs: 300,
+    forceTranscription: false,
+    privacyMode: false,
+    streamingPartials: false,
+    partialIntervalMs: 2000,
+    contextCarryOver: false,
+    contextIdleResetSecs: 300,
+    clipboardDwellMs: 1000,
+    clipboardAppend: false,
+    gpuDevice: null,
+    flashAttention: false,
+    transcriptionQueueDepth: 3,
+    transcriptionTimeoutSecs: 120,
+    fillerMode: "keep",
+    fillerWords: {},
+    quantization: "q5_0",
+    modelsDir: "",
+    watchedFolders: [],
+    retainedAudioMb: 256,
+    deterministicMode: false,
+    customModelPath: null,
+    preferredDevice: null,
+    gain: 0,
+    agc: false,
+    audioFilters: { highPass: false },
+    debugSaveRecordings: false,
+    preRollMs: 0,
+    captureSpillMb: 0,
+    channelMode: "mix",
+    captureSource: "microphone",
+    captureApplication: null,
+    speechThreshold: 0.02,
+    silenceTimeoutMs: 1500,
+    vadBackend: "rms",
+    adaptiveThreshold: false,
+    noiseFactor: 3,
   });
 
   // Toast shown above the mic button after a language/model toggle.
@@ -294,17 +1095,33 @@ export const useAppStore = defineStore("app", () => {
     status.value = newStatus;
   }
 
-  function setVuLevel(level: number) {
+  function setVuLevel(level: number, threshold?: number) {
     vuLevel.value = Math.max(0, Math.min(1, level));
+    if (threshold !== undefined) {
+      vuThreshold.value = Math.max(0, Math.min(1, threshold));
+    }
   }
 
   function setPartialTranscript(text: string) {
     partialTranscript.value = text;
   }
 
+  function setRenderState(snapshot: RenderState) {
+    renderState.value = snapshot;
+  }
+
   function setLastTranscript(text: string) {
     lastTranscript.value = text;
     partialTranscript.value = "";
+    transcriptionProgress.value = null;
+  }
+
+  function setTranscriptionProgress(percent: number | null) {
+    transcriptionProgress.value = percent;
+  }
+
+  function setModelLoadProgress(progress: ModelLoadProgress | null) {
+    modelLoadProgress.value = progress;
   }
 
   // Error toast actions
@@ -535,7 +1352,11 @@ export const useAppStore = defineStore("app", () => {
     // State
     status,
     vuLevel,
+    vuThreshold,
     partialTranscript,
+    renderState,
+    transcriptionProgress,
+    modelLoadProgress,
     lastTranscript,
     showCopyNotification,
     toggleNotification,
@@ -561,6 +1382,9 @@ export const useAppStore = defineStore("app", () => {
     setStatus,
     setVuLevel,
     setPartialTranscript,
+    setRenderState,
+    setTranscriptionProgress,
+    setModelLoadProgress,
     setLastTranscript,
     showError,
     clearError,
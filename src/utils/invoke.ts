@@ -0,0 +1,46 @@
+// Every command call goes through this `invoke` rather than Tauri's.
+// Tauri's invoke handler sees which command is called but not what it
+// returns, so a failed command is reported to the backend's recent
+// errors from here (`report_command_error`), then rethrown as usual.
+// The backend skips errors the command already reported itself.
+
+import {
+  invoke as tauriInvoke,
+  type InvokeArgs,
+  type InvokeOptions,
+} from "@tauri-apps/api/core";
+
+const REPORT_COMMAND = "report_command_error";
+
+/** The message of a command's rejection: a plain string for most
+ *  commands, an object with a `message` for the structured errors. */
+function messageOf(error: unknown): string {
+  if (typeof error === "string") return error;
+  if (
+    error !== null &&
+    typeof error === "object" &&
+    "message" in error &&
+    typeof error.message === "string"
+  ) {
+    return error.message;
+  }
+  return JSON.stringify(error) ?? String(error);
+}
+
+export async function invoke<T>(
+  cmd: string,
+  args?: InvokeArgs,
+  options?: InvokeOptions,
+): Promise<T> {
+  try {
+    return await tauriInvoke<T>(cmd, args, options);
+  } catch (error) {
+    if (cmd !== REPORT_COMMAND) {
+      tauriInvoke(REPORT_COMMAND, {
+        command: cmd,
+        message: messageOf(error),
+      }).catch(() => {});
+    }
+    throw error;
+  }
+}